@@ -2,6 +2,8 @@
 
 #[cfg(test)]
 mod test;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 use std::cmp::max;
 use std::collections::BTreeMap;
@@ -9,6 +11,8 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::Cursor;
 use std::ops::RangeBounds;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -21,14 +25,17 @@ use openraft::storage::InitialState;
 use openraft::storage::Snapshot;
 use openraft::AppData;
 use openraft::AppDataResponse;
+use openraft::ClientSessionTable;
 use openraft::EffectiveMembership;
 use openraft::ErrorSubject;
 use openraft::ErrorVerb;
 use openraft::LogId;
 use openraft::NodeId;
+use openraft::RaftNodeId;
 use openraft::RaftStorage;
 use openraft::RaftStorageDebug;
 use openraft::SnapshotMeta;
+use openraft::SnapshotView;
 use openraft::StateMachineChanges;
 use openraft::StorageError;
 use openraft::StorageIOError;
@@ -52,14 +59,37 @@ pub struct ClientRequest {
     pub status: String,
 }
 
-impl AppData for ClientRequest {}
+impl AppData for ClientRequest {
+    fn encode_entries<NID: RaftNodeId>(entries: &[Entry<Self, NID>]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(serde_json::to_vec(entries)?))
+    }
+
+    fn decode_entries<NID: RaftNodeId>(data: &[u8]) -> anyhow::Result<Vec<Entry<Self, NID>>> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    fn size_hint(&self) -> u64 {
+        (self.client.len() + std::mem::size_of_val(&self.serial) + self.status.len()) as u64
+    }
+}
 
 /// The application data response type which the `MemStore` works with.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ClientResponse(Option<String>);
+///
+/// It is the status of the client before the request is applied, `None` if there is none.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientResponse(pub Option<String>);
 
 impl AppDataResponse for ClientResponse {}
 
+/// The number of the most recent requests with an id of every client the `MemStore` remembers the responses to.
+pub const CLIENT_DEDUP_WINDOW: u64 = 2;
+
+/// The number of log entries after which the `MemStore` forgets a client that sends no request with an id.
+pub const CLIENT_SESSION_EXPIRE_AFTER: u64 = 1000;
+
+/// The format version of the snapshots the `MemStore` builds. It installs snapshots of this or an earlier version.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
 /// The application snapshot type which the `MemStore` works with.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MemStoreSnapshot {
@@ -70,54 +100,84 @@ pub struct MemStoreSnapshot {
 }
 
 /// The state machine of the `MemStore`.
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-pub struct MemStoreStateMachine {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct MemStoreStateMachine<NID: RaftNodeId = NodeId> {
     pub last_applied_log: LogId,
 
-    pub last_membership: Option<EffectiveMembership>,
+    pub last_membership: Option<EffectiveMembership<NID>>,
 
     /// A mapping of client IDs to their state info.
     pub client_serial_responses: HashMap<String, (u64, Option<String>)>,
     /// The current status of a client by ID.
     pub client_status: HashMap<String, String>,
+
+    /// The responses to the recent requests with an id, to apply a retried request only once.
+    #[serde(default = "new_client_sessions")]
+    pub client_sessions: ClientSessionTable<ClientResponse>,
+}
+
+fn new_client_sessions() -> ClientSessionTable<ClientResponse> {
+    ClientSessionTable::new(CLIENT_DEDUP_WINDOW, CLIENT_SESSION_EXPIRE_AFTER)
+}
+
+impl<NID: RaftNodeId> Default for MemStoreStateMachine<NID> {
+    fn default() -> Self {
+        Self {
+            last_applied_log: LogId::default(),
+            last_membership: None,
+            client_serial_responses: HashMap::new(),
+            client_status: HashMap::new(),
+            client_sessions: new_client_sessions(),
+        }
+    }
 }
 
 /// An in-memory storage system implementing the `RaftStorage` trait.
-pub struct MemStore {
+pub struct MemStore<NID: RaftNodeId = NodeId> {
     /// The ID of the Raft node for which this memory storage instances is configured.
-    id: NodeId,
+    id: NID,
     /// The Raft log.
-    log: RwLock<BTreeMap<u64, Entry<ClientRequest>>>,
+    log: RwLock<BTreeMap<u64, Entry<ClientRequest, NID>>>,
+    /// The total encoded size of the entries in `log`, updated along with it.
+    log_size: AtomicU64,
     /// The Raft state machine.
-    sm: RwLock<MemStoreStateMachine>,
+    ///
+    /// It is shared with the views taken to build snapshots from, and copied when it is changed while shared.
+    sm: RwLock<Arc<MemStoreStateMachine<NID>>>,
     /// The current hard state.
-    hs: RwLock<Option<HardState>>,
+    hs: RwLock<Option<HardState<NID>>>,
 
     snapshot_idx: Arc<Mutex<u64>>,
     /// The current snapshot.
-    current_snapshot: RwLock<Option<MemStoreSnapshot>>,
+    current_snapshot: Arc<RwLock<Option<MemStoreSnapshot>>>,
 }
 
-impl MemStore {
+impl<NID: RaftNodeId> MemStore<NID> {
     /// Create a new `MemStore` instance.
     /// TODO(xp): creating a store should not require an id.
-    pub async fn new(id: NodeId) -> Self {
+    pub async fn new(id: NID) -> Self {
         let log = RwLock::new(BTreeMap::new());
-        let sm = RwLock::new(MemStoreStateMachine::default());
+        let sm = RwLock::new(Arc::new(MemStoreStateMachine::default()));
         let hs = RwLock::new(None);
-        let current_snapshot = RwLock::new(None);
+        let current_snapshot = Arc::new(RwLock::new(None));
 
+        let log_size;
         {
             let mut l = log.write().await;
-            l.insert(0, Entry {
+            let entry = Entry {
                 log_id: LogId::default(),
                 payload: EntryPayload::Blank,
-            });
+                request_id: None,
+            };
+            log_size = entry_size(&entry);
+            l.insert(0, entry);
         }
 
         Self {
             id,
             log,
+            log_size: AtomicU64::new(log_size),
             sm,
             hs,
             snapshot_idx: Arc::new(Mutex::new(0)),
@@ -128,39 +188,46 @@ impl MemStore {
     /// Create a new `MemStore` instance with some existing state (for testing).
     #[cfg(test)]
     pub fn new_with_state(
-        id: NodeId,
-        log: BTreeMap<u64, Entry<ClientRequest>>,
-        sm: MemStoreStateMachine,
-        hs: Option<HardState>,
+        id: NID,
+        log: BTreeMap<u64, Entry<ClientRequest, NID>>,
+        sm: MemStoreStateMachine<NID>,
+        hs: Option<HardState<NID>>,
         current_snapshot: Option<MemStoreSnapshot>,
     ) -> Self {
+        let log_size = log.values().map(entry_size).sum();
         let log = RwLock::new(log);
-        let sm = RwLock::new(sm);
+        let sm = RwLock::new(Arc::new(sm));
         let hs = RwLock::new(hs);
-        let current_snapshot = RwLock::new(current_snapshot);
+        let current_snapshot = Arc::new(RwLock::new(current_snapshot));
         Self {
             id,
             log,
+            log_size: AtomicU64::new(log_size),
             sm,
             hs,
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
         }
     }
+
+    /// Replace the state machine, e.g., to simulate a corrupted state machine in tests.
+    pub async fn set_state_machine(&self, sm: MemStoreStateMachine<NID>) {
+        *self.sm.write().await = Arc::new(sm);
+    }
 }
 
 #[async_trait]
-impl RaftStorageDebug<MemStoreStateMachine> for MemStore {
+impl<NID: RaftNodeId> RaftStorageDebug<MemStoreStateMachine<NID>> for MemStore<NID> {
     /// Get a handle to the state machine for testing purposes.
-    async fn get_state_machine(&self) -> MemStoreStateMachine {
-        self.sm.write().await.clone()
+    async fn get_state_machine(&self) -> MemStoreStateMachine<NID> {
+        self.sm.read().await.as_ref().clone()
     }
 }
 
-impl MemStore {
-    fn find_first_membership_log<'a, T, D>(mut it: T) -> Option<EffectiveMembership>
+impl<NID: RaftNodeId> MemStore<NID> {
+    fn find_first_membership_log<'a, T, D>(mut it: T) -> Option<EffectiveMembership<NID>>
     where
-        T: 'a + Iterator<Item = &'a Entry<D>>,
+        T: 'a + Iterator<Item = &'a Entry<D, NID>>,
         D: AppData,
     {
         it.find_map(|entry| match &entry.payload {
@@ -174,7 +241,10 @@ impl MemStore {
 
     /// Go backwards through the log to find the most recent membership config <= `upto_index`.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub async fn get_membership_from_log(&self, upto_index: Option<u64>) -> Result<EffectiveMembership, StorageError> {
+    pub async fn get_membership_from_log(
+        &self,
+        upto_index: Option<u64>,
+    ) -> Result<EffectiveMembership<NID>, StorageError<NID>> {
         let membership_in_log = {
             let log = self.log.read().await;
 
@@ -211,17 +281,93 @@ impl MemStore {
     }
 }
 
+/// The encoded size of a log entry, as counted by `log_storage_size()`.
+///
+/// It is computed once when an entry is appended and once when it is deleted, not every time the size is queried.
+fn entry_size<NID: RaftNodeId>(entry: &Entry<ClientRequest, NID>) -> u64 {
+    // Encoding an entry of `ClientRequest` never fails.
+    serde_json::to_vec(entry).map(|data| data.len() as u64).unwrap_or_default()
+}
+
+/// Save the serialized state machine at `last_applied_log` as the current snapshot.
+async fn save_snapshot(
+    snapshot_idx: &Mutex<u64>,
+    current_snapshot: &RwLock<Option<MemStoreSnapshot>>,
+    data: Vec<u8>,
+    last_applied_log: LogId,
+) -> Snapshot<Cursor<Vec<u8>>> {
+    let snapshot_size = data.len();
+
+    let snapshot_idx = {
+        let mut l = snapshot_idx.lock().unwrap();
+        *l += 1;
+        *l
+    };
+
+    let meta;
+    {
+        let mut current_snapshot = current_snapshot.write().await;
+
+        let snapshot_id = format!("{}-{}-{}", last_applied_log.term, last_applied_log.index, snapshot_idx);
+
+        meta = SnapshotMeta {
+            last_log_id: last_applied_log,
+            snapshot_id,
+            size: data.len() as u64,
+            checksum: None,
+            format_version: SNAPSHOT_FORMAT_VERSION,
+        };
+
+        let snapshot = MemStoreSnapshot {
+            meta: meta.clone(),
+            data: data.clone(),
+        };
+
+        *current_snapshot = Some(snapshot);
+    } // Release log & snapshot write locks.
+
+    tracing::info!({ snapshot_size = snapshot_size }, "log compaction complete");
+    Snapshot {
+        meta,
+        snapshot: Box::new(Cursor::new(data)),
+    }
+}
+
+/// A view of the state machine of a `MemStore` to build a snapshot from, while the state machine is changed.
+struct MemStoreSnapshotView<NID: RaftNodeId> {
+    sm: Arc<MemStoreStateMachine<NID>>,
+    snapshot_idx: Arc<Mutex<u64>>,
+    current_snapshot: Arc<RwLock<Option<MemStoreSnapshot>>>,
+}
+
+#[async_trait]
+impl<NID: RaftNodeId> SnapshotView<Cursor<Vec<u8>>, NID> for MemStoreSnapshotView<NID> {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn build_snapshot(self: Box<Self>) -> Result<Snapshot<Cursor<Vec<u8>>>, StorageError<NID>> {
+        let data = serde_json::to_vec(&*self.sm)
+            .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, e.into()))?;
+
+        Ok(save_snapshot(
+            &self.snapshot_idx,
+            &self.current_snapshot,
+            data,
+            self.sm.last_applied_log,
+        )
+        .await)
+    }
+}
+
 #[async_trait]
-impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
+impl<NID: RaftNodeId> RaftStorage<ClientRequest, ClientResponse, NID> for MemStore<NID> {
     type SnapshotData = Cursor<Vec<u8>>;
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_membership_config(&self) -> Result<EffectiveMembership, StorageError> {
+    async fn get_membership_config(&self) -> Result<EffectiveMembership<NID>, StorageError<NID>> {
         self.get_membership_from_log(None).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
+    async fn get_initial_state(&self) -> Result<InitialState<NID>, StorageError<NID>> {
         let membership = self.get_membership_config().await?;
         let mut hs = self.hs.write().await;
         match &mut *hs {
@@ -253,7 +399,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError> {
+    async fn save_hard_state(&self, hs: &HardState<NID>) -> Result<(), StorageError<NID>> {
         tracing::debug!(?hs, "save_hard_state");
         let mut h = self.hs.write().await;
 
@@ -261,7 +407,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(())
     }
 
-    async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError> {
+    async fn read_hard_state(&self) -> Result<Option<HardState<NID>>, StorageError<NID>> {
         Ok(self.hs.read().await.clone())
     }
 
@@ -269,7 +415,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+    ) -> Result<Vec<Entry<ClientRequest, NID>>, StorageError<NID>> {
         let res = {
             let log = self.log.read().await;
             log.range(range.clone()).map(|(_, val)| val.clone()).collect::<Vec<_>>()
@@ -281,7 +427,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+    ) -> Result<Vec<Entry<ClientRequest, NID>>, StorageError<NID>> {
         let res = {
             let log = self.log.read().await;
             log.range(range.clone()).map(|(_, val)| val.clone()).collect::<Vec<_>>()
@@ -291,18 +437,18 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest>>, StorageError> {
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest, NID>>, StorageError<NID>> {
         let log = self.log.read().await;
         Ok(log.get(&log_index).cloned())
     }
 
-    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
+    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError<NID>> {
         let log = self.log.read().await;
         let first = log.iter().next().map(|(_, ent)| ent.log_id);
         Ok(first)
     }
 
-    async fn first_known_log_id(&self) -> Result<LogId, StorageError> {
+    async fn first_known_log_id(&self) -> Result<LogId, StorageError<NID>> {
         let first = self.first_id_in_log().await?;
         let (last_applied, _) = self.last_applied_state().await?;
 
@@ -313,13 +459,13 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(last_applied)
     }
 
-    async fn last_id_in_log(&self) -> Result<LogId, StorageError> {
+    async fn last_id_in_log(&self) -> Result<LogId, StorageError<NID>> {
         let log = self.log.read().await;
         let last = log.iter().last().map(|(_, ent)| ent.log_id).unwrap_or_default();
         Ok(last)
     }
 
-    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
+    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership<NID>>), StorageError<NID>> {
         let sm = self.sm.read().await;
         Ok((sm.last_applied_log, sm.last_membership.clone()))
     }
@@ -328,7 +474,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     async fn delete_logs_from<R: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: R,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         {
             tracing::debug!("delete_logs_from: {:?}", range);
 
@@ -336,7 +482,9 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
             let keys = log.range(range).map(|(k, _v)| *k).collect::<Vec<_>>();
             for key in keys {
-                log.remove(&key);
+                if let Some(entry) = log.remove(&key) {
+                    self.log_size.fetch_sub(entry_size(&entry), Ordering::Relaxed);
+                }
             }
         }
 
@@ -344,10 +492,13 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
-    async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<(), StorageError> {
+    async fn append_to_log(&self, entries: &[&Entry<ClientRequest, NID>]) -> Result<(), StorageError<NID>> {
         let mut log = self.log.write().await;
         for entry in entries {
-            log.insert(entry.log_id.index, (*entry).clone());
+            self.log_size.fetch_add(entry_size(entry), Ordering::Relaxed);
+            if let Some(replaced) = log.insert(entry.log_id.index, (*entry).clone()) {
+                self.log_size.fetch_sub(entry_size(&replaced), Ordering::Relaxed);
+            }
         }
         Ok(())
     }
@@ -355,9 +506,10 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn apply_to_state_machine(
         &self,
-        entries: &[&Entry<ClientRequest>],
-    ) -> Result<Vec<ClientResponse>, StorageError> {
+        entries: &[&Entry<ClientRequest, NID>],
+    ) -> Result<Vec<ClientResponse>, StorageError<NID>> {
         let mut sm = self.sm.write().await;
+        let sm = Arc::make_mut(&mut sm);
         let mut res = Vec::with_capacity(entries.len());
 
         for entry in entries {
@@ -368,6 +520,10 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             match entry.payload {
                 EntryPayload::Blank => res.push(ClientResponse(None)),
                 EntryPayload::Normal(ref data) => {
+                    if let Some(r) = sm.client_sessions.get(entry) {
+                        res.push(r);
+                        continue;
+                    }
                     if let Some((serial, r)) = sm.client_serial_responses.get(&data.client) {
                         if serial == &data.serial {
                             res.push(ClientResponse(r.clone()));
@@ -376,6 +532,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                     }
                     let previous = sm.client_status.insert(data.client.clone(), data.status.clone());
                     sm.client_serial_responses.insert(data.client.clone(), (data.serial, previous.clone()));
+                    sm.client_sessions.insert(entry, ClientResponse(previous.clone()));
                     res.push(ClientResponse(previous));
                 }
                 EntryPayload::Membership(ref mem) => {
@@ -391,54 +548,45 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError<NID>> {
         let (data, last_applied_log);
 
         {
             // Serialize the data of the state machine.
             let sm = self.sm.read().await;
-            data = serde_json::to_vec(&*sm)
+            data = serde_json::to_vec(&**sm)
                 .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, e.into()))?;
 
             last_applied_log = sm.last_applied_log;
         }
 
-        let snapshot_size = data.len();
-
-        let snapshot_idx = {
-            let mut l = self.snapshot_idx.lock().unwrap();
-            *l += 1;
-            *l
-        };
-
-        let meta;
-        {
-            let mut current_snapshot = self.current_snapshot.write().await;
-
-            let snapshot_id = format!("{}-{}-{}", last_applied_log.term, last_applied_log.index, snapshot_idx);
-
-            meta = SnapshotMeta {
-                last_log_id: last_applied_log,
-                snapshot_id,
-            };
+        Ok(save_snapshot(&self.snapshot_idx, &self.current_snapshot, data, last_applied_log).await)
+    }
 
-            let snapshot = MemStoreSnapshot {
-                meta: meta.clone(),
-                data: data.clone(),
-            };
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn snapshot_view(
+        &self,
+        _last_applied: LogId,
+    ) -> Result<Option<Box<dyn SnapshotView<Self::SnapshotData, NID>>>, StorageError<NID>> {
+        // It is cheap to share the state machine, it is copied by the next apply if the view is still in use.
+        Ok(Some(Box::new(MemStoreSnapshotView {
+            sm: self.sm.read().await.clone(),
+            snapshot_idx: self.snapshot_idx.clone(),
+            current_snapshot: self.current_snapshot.clone(),
+        })))
+    }
 
-            *current_snapshot = Some(snapshot);
-        } // Release log & snapshot write locks.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn log_storage_size(&self) -> Result<u64, StorageError<NID>> {
+        Ok(self.log_size.load(Ordering::Relaxed))
+    }
 
-        tracing::info!({ snapshot_size = snapshot_size }, "log compaction complete");
-        Ok(Snapshot {
-            meta,
-            snapshot: Box::new(Cursor::new(data)),
-        })
+    fn is_snapshot_format_supported(&self, format_version: u32) -> bool {
+        format_version <= SNAPSHOT_FORMAT_VERSION
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>> {
         Ok(Box::new(Cursor::new(Vec::new())))
     }
 
@@ -447,7 +595,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         &self,
         meta: &SnapshotMeta,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError> {
+    ) -> Result<StateMachineChanges, StorageError<NID>> {
         tracing::info!(
             { snapshot_size = snapshot.get_ref().len() },
             "decoding snapshot for installation"
@@ -467,7 +615,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
         // Update the state machine.
         {
-            let new_sm: MemStoreStateMachine = serde_json::from_slice(&new_snapshot.data).map_err(|e| {
+            let new_sm: MemStoreStateMachine<NID> = serde_json::from_slice(&new_snapshot.data).map_err(|e| {
                 StorageIOError::new(
                     ErrorSubject::Snapshot(new_snapshot.meta.clone()),
                     ErrorVerb::Read,
@@ -475,7 +623,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 )
             })?;
             let mut sm = self.sm.write().await;
-            *sm = new_sm;
+            *sm = Arc::new(new_sm);
         }
 
         // Update current snapshot.
@@ -488,7 +636,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError<NID>> {
         match &*self.current_snapshot.read().await {
             Some(snapshot) => {
                 // TODO(xp): try not to clone the entire data.
@@ -502,4 +650,19 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             None => Ok(None),
         }
     }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn reset_state_machine(&self) -> Result<Option<LogId>, StorageError<NID>> {
+        let new_sm: MemStoreStateMachine<NID> = match &*self.current_snapshot.read().await {
+            Some(snapshot) => serde_json::from_slice(&snapshot.data).map_err(|e| {
+                StorageIOError::new(ErrorSubject::Snapshot(snapshot.meta.clone()), ErrorVerb::Read, e.into())
+            })?,
+            // Without a snapshot, every log is re-applied to an empty state machine.
+            None => MemStoreStateMachine::default(),
+        };
+
+        let mut sm = self.sm.write().await;
+        *sm = Arc::new(new_sm);
+        Ok(Some(sm.last_applied_log))
+    }
 }
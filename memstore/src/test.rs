@@ -59,6 +59,42 @@ pub fn test_mem_store_defensive() -> anyhow::Result<()> {
     Suite::test_store_defensive(&DefensiveBuilder {})
 }
 
+#[test]
+pub fn test_mem_store_log_storage_size() -> anyhow::Result<()> {
+    run_fut(async {
+        let store = MemStore::new(NODE_ID).await;
+        let initial = store.log_storage_size().await?;
+
+        let entries = (1..=3)
+            .map(|i| Entry {
+                log_id: LogId { term: 1, index: i },
+                payload: EntryPayload::Normal(ClientRequest {
+                    client: "foo".to_string(),
+                    serial: i,
+                    status: format!("status-{}", i),
+                }),
+                request_id: None,
+            })
+            .collect::<Vec<_>>();
+        let sizes = entries.iter().map(entry_size).collect::<Vec<_>>();
+
+        store.append_to_log(&entries.iter().collect::<Vec<_>>()).await?;
+        assert_eq!(initial + sizes.iter().sum::<u64>(), store.log_storage_size().await?);
+
+        tracing::info!("--- overriding an entry replaces its size");
+        store.append_to_log(&[&entries[2]]).await?;
+        assert_eq!(initial + sizes.iter().sum::<u64>(), store.log_storage_size().await?);
+
+        store.delete_logs_from(..=1).await?;
+        assert_eq!(sizes[1] + sizes[2], store.log_storage_size().await?);
+
+        store.delete_logs_from(2..).await?;
+        assert_eq!(0, store.log_storage_size().await?);
+
+        Ok(())
+    })
+}
+
 /// Block until a future is finished.
 /// The future will be running in a clean tokio runtime, to prevent an unfinished task affecting the test.
 pub fn run_fut<F>(f: F) -> anyhow::Result<()>
@@ -129,10 +165,12 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -148,6 +186,7 @@ where
                 .append_to_log(&[&Entry {
                     log_id: (1, 1).into(),
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    request_id: None,
                 }])
                 .await?;
 
@@ -162,6 +201,7 @@ where
                 .append_to_log(&[&Entry {
                     log_id: LogId { term: 1, index: 3 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    request_id: None,
                 }])
                 .await?;
 
@@ -214,6 +254,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (3, 2).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -221,6 +262,7 @@ where
             .apply_to_state_machine(&[&Entry {
                 log_id: LogId { term: 3, index: 1 },
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -262,10 +304,12 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -284,6 +328,7 @@ where
                 .append_to_log(&[&Entry {
                     log_id: (1, 1).into(),
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    request_id: None,
                 }])
                 .await?;
 
@@ -301,6 +346,7 @@ where
                 .append_to_log(&[&Entry {
                     log_id: LogId { term: 1, index: 3 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                    request_id: None,
                 }])
                 .await?;
 
@@ -323,6 +369,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (2, 1).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -331,10 +378,12 @@ where
                 &Entry {
                     log_id: LogId { term: 1, index: 1 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: LogId { term: 1, index: 2 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -357,6 +406,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (1, 2).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -364,6 +414,7 @@ where
             .apply_to_state_machine(&[&Entry {
                 log_id: LogId { term: 3, index: 1 },
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -473,10 +524,12 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -491,6 +544,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 1 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 }])
                 .await?;
             let log_id = store.first_known_log_id().await?;
@@ -500,6 +554,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 2 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 }])
                 .await?;
             let log_id = store.first_known_log_id().await?;
@@ -509,6 +564,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 3 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 }])
                 .await?;
             let log_id = store.first_known_log_id().await?;
@@ -531,10 +587,12 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -572,10 +630,12 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -590,6 +650,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 3 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 }])
                 .await?;
             let log_id = store.last_id_in_log().await?;
@@ -620,6 +681,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 3 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2})),
+                    request_id: None,
                 }])
                 .await?;
 
@@ -640,6 +702,7 @@ where
                 .apply_to_state_machine(&[&Entry {
                     log_id: LogId { term: 1, index: 5 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 }])
                 .await?;
 
@@ -722,6 +785,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (2, 10).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -744,6 +808,7 @@ where
                 serial: 0,
                 status: "lit".into(),
             }),
+            request_id: None,
         };
 
         store.apply_to_state_machine(&[&entry]).await?;
@@ -798,6 +863,7 @@ where
         .map(|(id, req)| Entry {
             log_id: *id,
             payload: EntryPayload::Normal(req.clone()),
+            request_id: None,
         })
         .collect::<Vec<_>>();
 
@@ -852,6 +918,7 @@ where
             sto.append_to_log(&[&Entry {
                 log_id: (1, i).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
         }
@@ -909,14 +976,17 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 3 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -925,10 +995,12 @@ where
                     &Entry {
                         log_id: LogId { term: 2, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 2, index: 2 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -959,14 +1031,17 @@ where
                     &Entry {
                         log_id: LogId { term: 1, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 2 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 1, index: 3 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -976,10 +1051,12 @@ where
                     &Entry {
                         log_id: LogId { term: 2, index: 1 },
                         payload: EntryPayload::Blank,
+                        request_id: None,
                     },
                     &Entry {
                         log_id: LogId { term: 2, index: 2 },
                         payload: EntryPayload::Membership(Membership::new_single(btreeset! {3,4,5})),
+                        request_id: None,
                     },
                 ])
                 .await?;
@@ -1226,10 +1303,12 @@ where
                 &Entry {
                     log_id: (1, 1).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: (1, 3).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await;
@@ -1259,10 +1338,12 @@ where
                 &Entry {
                     log_id: (1, 1).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: (1, 2).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1271,6 +1352,7 @@ where
             .apply_to_state_machine(&[&Entry {
                 log_id: LogId { term: 1, index: 1 },
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await?;
 
@@ -1278,6 +1360,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (3, 4).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await;
 
@@ -1309,10 +1392,12 @@ where
                 &Entry {
                     log_id: (1, 1).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: (1, 2).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1322,10 +1407,12 @@ where
                 &Entry {
                     log_id: LogId { term: 1, index: 1 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: LogId { term: 1, index: 2 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1336,6 +1423,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (1, 4).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await;
 
@@ -1362,10 +1450,12 @@ where
                 &Entry {
                     log_id: (2, 1).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: (2, 2).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1374,6 +1464,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (1, 3).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await;
 
@@ -1401,10 +1492,12 @@ where
                 &Entry {
                     log_id: (2, 1).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: (2, 2).into(),
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1414,10 +1507,12 @@ where
                 &Entry {
                     log_id: LogId { term: 2, index: 1 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
                 &Entry {
                     log_id: LogId { term: 2, index: 2 },
                     payload: EntryPayload::Blank,
+                    request_id: None,
                 },
             ])
             .await?;
@@ -1428,6 +1523,7 @@ where
             .append_to_log(&[&Entry {
                 log_id: (1, 3).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             }])
             .await;
 
@@ -1467,6 +1563,7 @@ where
                 serial: 0,
                 status: "lit".into(),
             }),
+            request_id: None,
         };
 
         store.apply_to_state_machine(&[&entry]).await?;
@@ -1496,6 +1593,7 @@ where
                     serial: 0,
                     status: "lit".into(),
                 }),
+                request_id: None,
             };
             let res = store.apply_to_state_machine(&[&entry]).await;
 
@@ -1519,6 +1617,7 @@ where
         let entry = Entry {
             log_id: LogId { term: 3, index: 1 },
             payload: EntryPayload::Blank,
+            request_id: None,
         };
 
         store.apply_to_state_machine(&[&entry]).await?;
@@ -1528,6 +1627,7 @@ where
             let entry = Entry {
                 log_id: LogId { term: 2, index: 2 },
                 payload: EntryPayload::Blank,
+                request_id: None,
             };
             let res = store.apply_to_state_machine(&[&entry]).await;
             assert!(res.is_err());
@@ -0,0 +1,192 @@
+//! An in-process cluster of `MemStore` nodes, to spin up a cluster in one call in tests and benchmarks.
+//!
+//! It is enabled by the `testing` feature.
+//!
+//! ```ignore
+//! let cluster = Cluster::new(Arc::new(Config::default().validate()?), btreeset! {0, 1, 2}).await?;
+//!
+//! cluster.client_write(ClientRequest { client: "foo".to_string(), serial: 1, status: "bar".to_string() }).await?;
+//! ```
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use openraft::async_trait::async_trait;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::Config;
+use openraft::NodeId;
+use openraft::Raft;
+use openraft::RaftNetwork;
+use openraft::RaftStorage;
+use openraft::State;
+use tokio::sync::RwLock;
+
+use crate::ClientRequest;
+use crate::ClientResponse;
+use crate::MemStore;
+
+/// A Raft node of an in-process cluster.
+pub type MemRaft = RoutedRaft<MemStore>;
+
+/// A Raft node with store `S`, connected to the others by a `Router`.
+pub type RoutedRaft<S> = Raft<ClientRequest, ClientResponse, Router<S>, S>;
+
+/// A `RaftNetwork` delivering every RPC to the target `Raft` in the same process.
+///
+/// The nodes use a `MemStore` by default, or any other store of `ClientRequest`.
+pub struct Router<S = MemStore>
+where S: RaftStorage<ClientRequest, ClientResponse>
+{
+    nodes: RwLock<BTreeMap<NodeId, RoutedRaft<S>>>,
+}
+
+impl<S> Default for Router<S>
+where S: RaftStorage<ClientRequest, ClientResponse>
+{
+    fn default() -> Self {
+        Self {
+            nodes: Default::default(),
+        }
+    }
+}
+
+impl<S> Router<S>
+where S: RaftStorage<ClientRequest, ClientResponse>
+{
+    /// Add a node to send RPCs to, replacing the node of the same id.
+    pub async fn add_node(&self, id: NodeId, raft: RoutedRaft<S>) {
+        self.nodes.write().await.insert(id, raft);
+    }
+
+    /// Remove a node, the RPCs to it fail from now on.
+    pub async fn remove_node(&self, id: NodeId) -> Option<RoutedRaft<S>> {
+        self.nodes.write().await.remove(&id)
+    }
+
+    /// Get the node of `id`.
+    pub async fn get_node(&self, id: NodeId) -> Result<RoutedRaft<S>> {
+        let nodes = self.nodes.read().await;
+        nodes.get(&id).cloned().ok_or_else(|| anyhow!("node {} is not found", id))
+    }
+}
+
+#[async_trait]
+impl<S> RaftNetwork<ClientRequest> for Router<S>
+where S: RaftStorage<ClientRequest, ClientResponse>
+{
+    async fn send_append_entries(
+        &self,
+        target: NodeId,
+        rpc: AppendEntriesRequest<ClientRequest>,
+    ) -> Result<AppendEntriesResponse> {
+        Ok(self.get_node(target).await?.append_entries(rpc).await?)
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: NodeId,
+        rpc: InstallSnapshotRequest,
+    ) -> Result<InstallSnapshotResponse> {
+        Ok(self.get_node(target).await?.install_snapshot(rpc).await?)
+    }
+
+    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse> {
+        Ok(self.get_node(target).await?.vote(rpc).await?)
+    }
+
+    async fn send_timeout_now(&self, target: NodeId, rpc: TimeoutNowRequest) -> Result<TimeoutNowResponse> {
+        Ok(self.get_node(target).await?.timeout_now(rpc).await?)
+    }
+}
+
+/// A cluster of `MemStore` nodes connected by a `Router`.
+pub struct Cluster {
+    pub config: Arc<Config>,
+    pub router: Arc<Router>,
+    stores: BTreeMap<NodeId, Arc<MemStore>>,
+}
+
+impl Cluster {
+    /// Bring up a node for every id in `voters`, initialize them as a cluster and wait until a leader is elected.
+    pub async fn new(config: Arc<Config>, voters: BTreeSet<NodeId>) -> Result<Self> {
+        let first = *voters.iter().next().ok_or_else(|| anyhow!("a cluster needs at least one voter"))?;
+
+        let router = Arc::new(Router::default());
+        let mut stores = BTreeMap::new();
+
+        for id in voters.iter().copied() {
+            let sto = Arc::new(MemStore::new(id).await);
+            let raft = Raft::new(id, config.clone(), router.clone(), sto.clone());
+            router.add_node(id, raft).await;
+            stores.insert(id, sto);
+        }
+
+        let cluster = Self { config, router, stores };
+
+        let raft = cluster.router.get_node(first).await?;
+        raft.initialize(voters).await?;
+        raft.wait(Some(cluster.election_wait()))
+            .metrics(|x| x.current_leader.is_some(), "a leader is elected")
+            .await?;
+
+        Ok(cluster)
+    }
+
+    /// Get the Raft node of `id`.
+    pub async fn raft(&self, id: NodeId) -> Result<MemRaft> {
+        self.router.get_node(id).await
+    }
+
+    /// Get the store of node `id`.
+    pub fn store(&self, id: NodeId) -> Option<Arc<MemStore>> {
+        self.stores.get(&id).cloned()
+    }
+
+    /// The node in the leader state, if there is one.
+    pub async fn leader(&self) -> Option<NodeId> {
+        let nodes = self.router.nodes.read().await;
+        nodes.values().find_map(|raft| {
+            let m = raft.metrics().borrow().clone();
+            if m.state == State::Leader {
+                Some(m.id)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Write `req` through the leader, and wait until it is applied.
+    pub async fn client_write(&self, req: ClientRequest) -> Result<ClientWriteResponse<ClientResponse>> {
+        let leader = self.leader().await.ok_or_else(|| anyhow!("there is no leader"))?;
+        let raft = self.raft(leader).await?;
+
+        Ok(raft.client_write(ClientWriteRequest::new(req)).await?)
+    }
+
+    /// Shut down every node.
+    pub async fn shutdown(&self) -> Result<()> {
+        let nodes = std::mem::take(&mut *self.router.nodes.write().await);
+        for raft in nodes.into_values() {
+            raft.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    /// The time to wait for an election: a few election timeouts, in case of a split vote.
+    fn election_wait(&self) -> Duration {
+        Duration::from_millis(self.config.election_timeout_max * 10)
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt::Debug;
+
+use borsh::BorshDeserialize;
+use borsh::BorshSerialize;
+use maplit::btreeset;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::CompressedEntries;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::raft::InstallSnapshotRequest;
+use crate::raft::InstallSnapshotResponse;
+use crate::raft::Membership;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::storage::HardState;
+use crate::AppData;
+use crate::Compression;
+use crate::LogId;
+use crate::NodeCapabilities;
+use crate::SnapshotMeta;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+struct Data(String);
+
+impl AppData for Data {}
+
+/// Encode and decode `v` with borsh, and assert the decoded value is the same, in terms of its debug output.
+fn assert_round_trip<T: BorshSerialize + BorshDeserialize + Debug>(v: &T) -> anyhow::Result<()> {
+    let bytes = v.try_to_vec()?;
+    let got = T::try_from_slice(&bytes)?;
+
+    assert_eq!(format!("{:?}", v), format!("{:?}", got));
+    assert_eq!(bytes, got.try_to_vec()?, "re-encoding gives the same bytes");
+    Ok(())
+}
+
+fn entries() -> Vec<Entry<Data>> {
+    vec![
+        Entry {
+            log_id: LogId::new(1, 1),
+            payload: EntryPayload::Blank,
+            request_id: None,
+        },
+        Entry {
+            log_id: LogId::new(1, 2),
+            payload: EntryPayload::Normal(Data("foo".to_string())),
+            request_id: None,
+        },
+        Entry {
+            log_id: LogId::new(2, 3),
+            payload: EntryPayload::Membership(Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}])),
+            request_id: None,
+        },
+    ]
+}
+
+#[test]
+fn test_borsh_log_types() -> anyhow::Result<()> {
+    assert_round_trip(&LogId::new(3, 5))?;
+    assert_round_trip(&LogId::default())?;
+
+    for ent in entries() {
+        assert_round_trip(&ent)?;
+        assert_round_trip(&ent.payload)?;
+    }
+
+    assert_round_trip(&Membership::<u64>::new_single(btreeset! {1,2,3}))?;
+    assert_round_trip(&Membership::<u64>::new_multi(vec![btreeset! {1}, btreeset! {2,3}]))?;
+
+    assert_round_trip(&HardState::<u64> {
+        current_term: 5,
+        voted_for: Some(2),
+    })?;
+    assert_round_trip(&HardState::<u64>::default())?;
+
+    assert_round_trip(&SnapshotMeta {
+        last_log_id: LogId::new(2, 10),
+        snapshot_id: "2-10-1".to_string(),
+        size: 1024,
+        checksum: Some(0x1234_5678),
+        format_version: 2,
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn test_borsh_append_entries() -> anyhow::Result<()> {
+    assert_round_trip(&AppendEntriesRequest::<Data> {
+        term: 2,
+        leader_id: 1,
+        prev_log_id: LogId::new(1, 1),
+        entries: entries(),
+        leader_commit: LogId::new(1, 1),
+        compressed_entries: None,
+    })?;
+
+    assert_round_trip(&AppendEntriesRequest::<Data> {
+        term: 2,
+        leader_id: 1,
+        prev_log_id: LogId::new(2, 3),
+        entries: vec![],
+        leader_commit: LogId::new(2, 3),
+        compressed_entries: Some(CompressedEntries {
+            codec: Compression::Lz4,
+            data: vec![1, 2, 3],
+        }),
+    })?;
+
+    assert_round_trip(&AppendEntriesResponse {
+        term: 2,
+        matched: Some(LogId::new(2, 3)),
+        conflict: None,
+    })?;
+
+    assert_round_trip(&AppendEntriesResponse {
+        term: 2,
+        matched: None,
+        conflict: Some(LogId::new(1, 2)),
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn test_borsh_vote() -> anyhow::Result<()> {
+    assert_round_trip(&VoteRequest::<u64>::new(3, 2, LogId::new(2, 5)))?;
+    assert_round_trip(&VoteRequest::<u64> {
+        leadership_transfer: true,
+        pre_vote: true,
+        ..VoteRequest::new(3, 2, LogId::new(2, 5))
+    })?;
+
+    assert_round_trip(&VoteResponse {
+        term: 3,
+        vote_granted: true,
+        last_log_id: LogId::new(2, 5),
+    })?;
+
+    assert_round_trip(&TimeoutNowRequest::<u64> { term: 3, leader_id: 1 })?;
+    assert_round_trip(&TimeoutNowResponse { term: 3 })?;
+
+    assert_round_trip(&NodeCapabilities::current())?;
+    assert_round_trip(&NodeCapabilities::legacy())?;
+
+    Ok(())
+}
+
+#[test]
+fn test_borsh_install_snapshot() -> anyhow::Result<()> {
+    assert_round_trip(&InstallSnapshotRequest::<u64> {
+        term: 3,
+        leader_id: 1,
+        meta: SnapshotMeta {
+            last_log_id: LogId::new(2, 10),
+            snapshot_id: "2-10-1".to_string(),
+            size: 6,
+            checksum: None,
+            format_version: 0,
+        },
+        offset: 3,
+        data: vec![4, 5, 6],
+        done: true,
+    })?;
+
+    assert_round_trip(&InstallSnapshotResponse { term: 3 })?;
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! The source of time of a Raft node.
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+/// A clock providing the current time and timers to a Raft node.
+///
+/// Raft reads it for election timeouts, heartbeats, leadership transfer deadlines and snapshot policies.
+/// The default is `TokioClock`. A test may pass its own clock to `Raft::with_clock()` and advance it manually, to drive
+/// elections and heartbeats deterministically, without sleeping.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Returns a future that completes once the clock reaches `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()>;
+}
+
+/// The real clock, backed by `tokio::time`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep_until(deadline))
+    }
+}
+
+/// A timer that ticks every `period` of a `Clock`.
+///
+/// Like `tokio::time::Interval`, the first tick completes immediately.
+pub(crate) struct Ticker {
+    clock: Arc<dyn Clock>,
+    period: Duration,
+    next: Instant,
+}
+
+impl Ticker {
+    pub(crate) fn new(clock: Arc<dyn Clock>, period: Duration) -> Self {
+        let next = clock.now();
+        Self { clock, period, next }
+    }
+
+    /// Tick every `period` from now on, starting with an immediate tick.
+    pub(crate) fn reset(&mut self, period: Duration) {
+        self.period = period;
+        self.next = self.clock.now();
+    }
+
+    /// Completes at the next tick.
+    ///
+    /// It is cancel safe: if the returned future is dropped before completion, no tick is lost.
+    pub(crate) async fn tick(&mut self) {
+        self.clock.sleep_until(self.next).await;
+
+        // Ticks missed while no one was waiting are skipped.
+        let now = self.clock.now();
+        self.next += self.period;
+        if self.next <= now {
+            self.next = now + self.period;
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Compress the entries replicated with AppendEntries, see `Config::replication_compression`.
+//!
+//! A codec is available only with the cargo feature of the same name, `lz4_flex` or `zstd`.
+
+use crate::config::Compression;
+use crate::raft::CompressedEntries;
+use crate::raft::Entry;
+use crate::AppData;
+use crate::RaftNodeId;
+
+/// Encode `entries` with `AppData::encode_entries()` and compress them with `codec`.
+///
+/// It returns `None` if there is nothing to compress, if the application does not encode entries, if the codec is
+/// not enabled, if the encoded entries are larger than `max_bytes`, or if compressing does not make them smaller. In
+/// any of these cases they should be sent uncompressed.
+pub(crate) fn compress<D: AppData, NID: RaftNodeId>(
+    codec: Compression,
+    entries: &[Entry<D, NID>],
+    max_bytes: u64,
+) -> anyhow::Result<Option<CompressedEntries>> {
+    if codec == Compression::None || entries.is_empty() || !codec.is_enabled() {
+        return Ok(None);
+    }
+
+    let raw = match D::encode_entries(entries)? {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+
+    // The follower refuses to decompress more than `max_payload_bytes`.
+    if raw.len() as u64 > max_bytes {
+        return Ok(None);
+    }
+
+    let data = match compress_with(codec, &raw)? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+
+    if data.len() >= raw.len() {
+        return Ok(None);
+    }
+
+    Ok(Some(CompressedEntries { codec, data }))
+}
+
+/// Compress `raw` with `codec`, or return `None` if the codec is not enabled.
+#[cfg_attr(not(any(feature = "lz4_flex", feature = "zstd")), allow(unused_variables))]
+fn compress_with(codec: Compression, raw: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+    match codec {
+        #[cfg(feature = "lz4_flex")]
+        Compression::Lz4 => Ok(Some(lz4_flex::compress_prepend_size(raw))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Some(zstd::stream::encode_all(raw, zstd::DEFAULT_COMPRESSION_LEVEL)?)),
+        #[allow(unreachable_patterns)]
+        _ => Ok(None),
+    }
+}
+
+/// Decompress and decode the entries compressed by `compress()`.
+///
+/// It fails if they are larger than `max_bytes` once decompressed, without decompressing more than that.
+pub(crate) fn decompress<D: AppData, NID: RaftNodeId>(
+    compressed: &CompressedEntries,
+    max_bytes: u64,
+) -> anyhow::Result<Vec<Entry<D, NID>>> {
+    let raw = match compressed.codec {
+        Compression::None => compressed.data.clone(),
+        #[cfg(feature = "lz4_flex")]
+        Compression::Lz4 => decompress_lz4(&compressed.data, max_bytes)?,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => decompress_zstd(&compressed.data, max_bytes)?,
+        #[allow(unreachable_patterns)]
+        codec => return Err(anyhow::anyhow!("codec {:?} is not enabled", codec)),
+    };
+
+    if raw.len() as u64 > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+
+    D::decode_entries(&raw)
+}
+
+#[cfg(feature = "lz4_flex")]
+fn decompress_lz4(data: &[u8], max_bytes: u64) -> anyhow::Result<Vec<u8>> {
+    // `compress_prepend_size()` prepends the decompressed size as a little endian u32.
+    if data.len() < 4 {
+        return Err(anyhow::anyhow!("lz4 data is too short"));
+    }
+    let (size, data) = data.split_at(4);
+    let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as u64;
+
+    if size > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+
+    Ok(lz4_flex::decompress(data, size as usize)?)
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], max_bytes: u64) -> anyhow::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut raw = vec![];
+    // Read one more byte to tell if it is larger than `max_bytes`.
+    zstd::stream::read::Decoder::new(data)?.take(max_bytes + 1).read_to_end(&mut raw)?;
+
+    Ok(raw)
+}
+
+fn too_large(max_bytes: u64) -> anyhow::Error {
+    anyhow::anyhow!("decompressed entries are larger than max_payload_bytes {}", max_bytes)
+}
@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::compression::compress;
+use crate::compression::decompress;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::AppData;
+use crate::Compression;
+use crate::LogId;
+use crate::RaftNodeId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Data(String);
+
+impl AppData for Data {
+    fn encode_entries<NID: RaftNodeId>(entries: &[Entry<Self, NID>]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(serde_json::to_vec(entries)?))
+    }
+
+    fn decode_entries<NID: RaftNodeId>(data: &[u8]) -> anyhow::Result<Vec<Entry<Self, NID>>> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// Data of an application that does not encode entries.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Opaque(String);
+
+impl AppData for Opaque {}
+
+const MAX_BYTES: u64 = 1024 * 1024;
+
+fn entries(n: u64) -> Vec<Entry<Data>> {
+    (1..=n)
+        .map(|i| Entry {
+            log_id: LogId::new(1, i),
+            payload: EntryPayload::Normal(Data(format!("the same value of entry-{}", i % 3))),
+            request_id: None,
+        })
+        .collect()
+}
+
+fn enabled_codecs() -> Vec<Compression> {
+    [Compression::Lz4, Compression::Zstd].into_iter().filter(|c| c.is_enabled()).collect()
+}
+
+#[test]
+fn test_compression_round_trip() -> anyhow::Result<()> {
+    let ents = entries(100);
+    let raw_size = serde_json::to_vec(&ents)?.len();
+
+    for codec in enabled_codecs() {
+        let compressed = compress(codec, &ents, MAX_BYTES)?.expect("repeated values are compressible");
+        assert_eq!(codec, compressed.codec);
+        assert!(compressed.data.len() < raw_size, "{:?} makes it smaller", codec);
+
+        let got: Vec<Entry<Data>> = decompress(&compressed, MAX_BYTES)?;
+        assert_eq!(ents, got, "{:?} round trip", codec);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_compression_skipped() -> anyhow::Result<()> {
+    assert!(
+        compress(Compression::None, &entries(100), MAX_BYTES)?.is_none(),
+        "compression disabled"
+    );
+
+    for codec in [Compression::Lz4, Compression::Zstd] {
+        assert!(
+            compress::<Data, u64>(codec, &[], MAX_BYTES)?.is_none(),
+            "nothing to compress"
+        );
+
+        let opaque = vec![Entry {
+            log_id: LogId::new(1, 1),
+            payload: EntryPayload::Normal(Opaque("foo".repeat(100))),
+            request_id: None,
+        }];
+        assert!(
+            compress::<Opaque, u64>(codec, &opaque, MAX_BYTES)?.is_none(),
+            "the application does not encode entries"
+        );
+
+        let ents = entries(100);
+        let raw_size = serde_json::to_vec(&ents)?.len() as u64;
+        assert!(compress(codec, &ents, raw_size - 1)?.is_none(), "larger than max bytes");
+
+        if !codec.is_enabled() {
+            assert!(
+                compress(codec, &ents, MAX_BYTES)?.is_none(),
+                "{:?} is not enabled",
+                codec
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decompress_corrupted() -> anyhow::Result<()> {
+    for codec in enabled_codecs() {
+        let mut compressed = compress(codec, &entries(100), MAX_BYTES)?.expect("repeated values are compressible");
+        let len = compressed.data.len();
+        compressed.data.truncate(len / 2);
+
+        let res = decompress::<Data, u64>(&compressed, MAX_BYTES);
+        assert!(res.is_err(), "{:?} rejects truncated data", codec);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decompress_too_large() -> anyhow::Result<()> {
+    let ents = entries(100);
+    let raw_size = serde_json::to_vec(&ents)?.len() as u64;
+
+    for codec in enabled_codecs() {
+        let compressed = compress(codec, &ents, MAX_BYTES)?.expect("repeated values are compressible");
+
+        let res = decompress::<Data, u64>(&compressed, raw_size - 1);
+        assert!(
+            res.is_err(),
+            "{:?} rejects data larger than max bytes once decompressed",
+            codec
+        );
+
+        let got = decompress::<Data, u64>(&compressed, raw_size)?;
+        assert_eq!(ents, got);
+    }
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 //! Raft runtime configuration.
 
+use std::time::Duration;
+
 use rand::thread_rng;
 use rand::Rng;
 use serde::Deserialize;
@@ -8,6 +10,49 @@ use structopt::StructOpt;
 
 use crate::error::ConfigError;
 
+/// The minimal number of heartbeats a leader should be able to send within `election_timeout_min`.
+///
+/// With fewer heartbeats per election window, a single delayed or lost heartbeat is enough to make a follower start
+/// an election.
+pub const MIN_HEARTBEATS_PER_ELECTION_TIMEOUT: u64 = 3;
+
+/// The lowest throughput in bytes per second assumed for sending a snapshot chunk.
+///
+/// `install_snapshot_timeout` must be long enough to send a chunk of `snapshot_max_chunk_size` bytes at this rate,
+/// otherwise every InstallSnapshot RPC is likely to time out.
+pub const MIN_SNAPSHOT_THROUGHPUT: u64 = 32 * 1024 * 1024;
+
+/// The number of `heartbeat_interval` after which a peer whose capabilities can not be queried, with
+/// `RaftNetwork::capabilities()`, is asked again.
+///
+/// Until then, the result of the failed query is used, thus an unreachable or legacy peer does not delay every RPC
+/// to it with a query.
+pub const CAPABILITIES_RETRY_HEARTBEATS: u64 = 20;
+
+/// The number of election rounds in a row a voter rejects for the candidate's log is stale, before the candidate
+/// backs off from elections, with `Config::backoff_on_stale_log`.
+pub const STALE_LOG_ELECTIONS: u64 = 2;
+
+/// The number of `Config::learner_catch_up_window` in a row in which the lag of a learner being added grows, before
+/// the add is aborted.
+///
+/// A single window is not enough: the lag is noisy, e.g., when the leader receives a burst of writes.
+pub const LEARNER_CATCH_UP_GROWING_WINDOWS: u64 = 3;
+
+/// The number of `election_timeout_max` a node backs off from elections for its log is stale, with
+/// `Config::backoff_on_stale_log`, if its log does not advance.
+///
+/// The voters that rejected it may be gone, e.g., the only node with a greater log crashed, then the rest of the
+/// cluster still has to elect a leader.
+pub const STALE_LOG_BACKOFF_ELECTION_TIMEOUTS: u64 = 10;
+
+/// The number of `install_snapshot_timeout` a new voter is given to catch up by default, when changing membership
+/// with `blocking_catch_up`.
+///
+/// A new voter usually catches up by installing a snapshot, thus the time is scaled with the budget of a snapshot
+/// chunk, see `Config::membership_catch_up_timeout`.
+pub const MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS: u64 = 25;
+
 /// Log compaction and snapshot policy.
 ///
 /// This governs when periodic snapshots will be taken, and also governs the conditions which
@@ -19,6 +64,197 @@ pub enum SnapshotPolicy {
     /// A snapshot will be generated once the log has grown the specified number of logs since
     /// the last snapshot.
     LogsSinceLast(u64),
+
+    /// A snapshot will be generated once the specified duration has elapsed since the last snapshot, no matter how
+    /// many logs have been applied.
+    ///
+    /// The condition is checked every time logs are applied to the state machine, and when the duration elapses on an
+    /// idle node. A snapshot is only built if there are applied logs not included in the last snapshot.
+    ///
+    /// A time based snapshot may be built before the log has grown much.
+    /// It does not affect purging logs: applied logs are still purged according to `max_applied_log_to_keep`, and
+    /// since this policy provides no log count threshold, `max_applied_log_to_keep` is used in place of it to decide
+    /// whether an existing snapshot is fresh enough to send to a lagging follower.
+    EveryDuration(Duration),
+
+    /// A snapshot will be generated once either the log has grown `logs` logs since the last snapshot, or `duration`
+    /// has elapsed since the last snapshot, whichever comes first.
+    ///
+    /// A zero `logs` or a zero `duration` disables the corresponding condition, but not both.
+    /// Both conditions are reset at the same time when a snapshot is built or installed.
+    LogsOrDuration { logs: u64, duration: Duration },
+
+    /// A snapshot will be generated once the log has grown the specified number of bytes since the last snapshot.
+    ///
+    /// The size of the log is queried with `RaftStorage::log_storage_size()` every time logs are applied to the state
+    /// machine. Like `EveryDuration`, it provides no log count threshold, and `max_applied_log_to_keep` is used in
+    /// place of it to decide whether an existing snapshot is fresh enough to send to a lagging follower.
+    SizeSinceLast(u64),
+}
+
+impl SnapshotPolicy {
+    /// Returns the log count threshold of this policy, if there is one.
+    pub(crate) fn logs_threshold(&self) -> Option<u64> {
+        match self {
+            SnapshotPolicy::LogsSinceLast(n) => Some(*n),
+            SnapshotPolicy::EveryDuration(_) => None,
+            SnapshotPolicy::SizeSinceLast(_) => None,
+            SnapshotPolicy::LogsOrDuration { logs, .. } => {
+                if *logs > 0 {
+                    Some(*logs)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// The distribution a randomized election timeout is sampled from.
+///
+/// Every election timeout is sampled within `[election_timeout_min, election_timeout_max]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ElectionTimeoutDistribution {
+    /// Every value in the range is equally likely.
+    Uniform,
+
+    /// The offset from `election_timeout_min` follows an exponential distribution, scaled to the width of the range
+    /// and clamped to `election_timeout_max`.
+    ///
+    /// `lambda` is the rate in units of the range width: the greater it is, the more the timeouts skew toward
+    /// `election_timeout_min`, which lets the first node timing out win an election before the others wake up.
+    Exponential { lambda: f64 },
+}
+
+impl ElectionTimeoutDistribution {
+    /// Sample an election timeout in milliseconds within `[min, max]`.
+    fn sample<G: Rng>(&self, rng: &mut G, min: u64, max: u64) -> u64 {
+        match self {
+            ElectionTimeoutDistribution::Uniform => rng.gen_range(min..max),
+            ElectionTimeoutDistribution::Exponential { lambda } => {
+                // `1 - u` is in `(0, 1]`, thus `ln()` is always finite.
+                let u: f64 = rng.gen();
+                let ratio = -(1.0 - u).ln() / *lambda;
+
+                let offset = (ratio * (max - min) as f64) as u64;
+                min + offset.min(max - min)
+            }
+        }
+    }
+}
+
+/// The policy of making writes to `RaftStorage` durable.
+///
+/// Raft relies on the log and the hard state (term and vote) being on disk before it acknowledges them to other nodes.
+/// Relaxing it trades durability for throughput: a node that crashes may lose writes it has acknowledged, and may
+/// vote twice in a term or forget logs it replicated, which breaks the safety of Raft if a quorum crashes at the same
+/// time.
+///
+/// The policy is passed to `RaftStorage` as hints: Raft calls `RaftStorage::flush()` to sync appended logs and
+/// `RaftStorage::flush_hard_state()` to sync a saved hard state, only when the policy requires it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Durability {
+    /// Sync both the logs and the hard state before relying on them.
+    FullSync,
+
+    /// Sync the logs before relying on them, but let the storage batch hard state writes.
+    LogSyncOnly,
+
+    /// Never request a sync, the storage writes in its own pace.
+    ///
+    /// It is not crash safe, and a warning is logged when a Raft node starts with it.
+    Async,
+}
+
+impl Durability {
+    /// Whether appended logs should be synced before Raft relies on them.
+    pub(crate) fn sync_log(&self) -> bool {
+        matches!(self, Durability::FullSync | Durability::LogSyncOnly)
+    }
+
+    /// Whether a saved hard state should be synced before Raft relies on it.
+    pub(crate) fn sync_hard_state(&self) -> bool {
+        matches!(self, Durability::FullSync)
+    }
+}
+
+/// The policy of purging logs from `RaftStorage`.
+///
+/// Logs are purged to bound the storage size, while `max_applied_log_to_keep` logs are kept before the purge boundary
+/// for replicating to lagging followers without sending a snapshot.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogPurgePolicy {
+    /// Purge logs as soon as they are applied to the state machine.
+    ///
+    /// It relies on the state machine being persisted along with the applied logs. If the state machine is only
+    /// persisted with snapshots, a crash loses the purged logs that are not yet in a snapshot. This is the default.
+    AfterApplied,
+
+    /// Purge only the logs included in the last snapshot built, once the snapshot is persisted by
+    /// `RaftStorage::do_log_compaction()`.
+    ///
+    /// Logs are never purged before a snapshot covers them, no matter how many logs are applied.
+    AfterSnapshot,
+}
+
+/// The checksum a leader attaches to a snapshot it sends, for the receiver to detect corrupted data.
+///
+/// A receiver verifies the checksum whenever the leader attaches one, no matter what its own config is.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotChecksum {
+    /// Send snapshots without a checksum.
+    None,
+
+    /// Attach a CRC32 of the snapshot data.
+    Crc32,
+}
+
+/// The codec a leader compresses the entries it replicates with, see `Config::replication_compression`.
+///
+/// A follower decompresses the entries with whatever codec the leader used, no matter what its own config is. A
+/// codec is available only with the cargo feature of the same name, `lz4_flex` or `zstd`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub enum Compression {
+    /// Send entries uncompressed.
+    None,
+
+    /// Compress with LZ4, fast with a moderate ratio.
+    Lz4,
+
+    /// Compress with Zstandard, slower than LZ4 with a better ratio.
+    Zstd,
+}
+
+impl Compression {
+    /// Whether the cargo feature providing the codec is enabled.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Lz4 => cfg!(feature = "lz4_flex"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+}
+
+/// How a leader makes sure it is still the leader before serving a linearizable read, see
+/// `Raft::ensure_linearizable()`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadStrategy {
+    /// Exchange a round of heartbeats with a quorum for every read.
+    ReadIndex,
+
+    /// Serve a read at once while the leader holds a lease, and fall back to `ReadIndex` once the lease expires.
+    ///
+    /// A voter that accepted an AppendEntries from the leader does not vote for another node within
+    /// `election_timeout_min`. Thus the leader can not be deposed within `election_timeout_min` since it sent an
+    /// AppendEntries a quorum accepted. Every heartbeat accepted by a quorum extends the lease.
+    ///
+    /// It saves the round trips of `ReadIndex`, but it is safe only if the clocks of the nodes run at about the same
+    /// rate: a node whose clock runs fast may vote for a new leader while the old leader still believes it holds the
+    /// lease. Thus the lease is shortened by `max_clock_drift`. A lease is given up when the leader starts
+    /// transferring its leadership.
+    LeaseRead,
 }
 
 /// Parse number with unit such as 5.3 KB
@@ -28,22 +264,136 @@ fn parse_bytes_with_unit(src: &str) -> anyhow::Result<u64> {
     Ok(res.get_bytes() as u64)
 }
 
+/// Parse duration with unit such as 150ms, 1.5s, 1m or 2h
+fn parse_duration(src: &str) -> anyhow::Result<Duration> {
+    let src = src.trim();
+    let unit_start = src.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(src.len());
+    let (num, unit) = src.split_at(unit_start);
+
+    let num = num.parse::<f64>().map_err(|e| anyhow::anyhow!("invalid duration '{}': {}", src, e))?;
+
+    let secs_per_unit = match unit.trim() {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        u => {
+            return Err(anyhow::anyhow!(
+                "invalid duration '{}': unknown unit '{}', expect one of ms, s, m, h",
+                src,
+                u
+            ))
+        }
+    };
+
+    Ok(Duration::from_secs_f64(num * secs_per_unit))
+}
+
+/// Parse duration with unit such as 150ms, 1.5s or 1m into milliseconds.
+///
+/// A bare number without unit is interpreted as milliseconds.
+fn parse_duration_ms(src: &str) -> anyhow::Result<u64> {
+    if let Ok(ms) = src.trim().parse::<u64>() {
+        return Ok(ms);
+    }
+
+    let d = parse_duration(src)?;
+    Ok(d.as_millis() as u64)
+}
+
 fn parse_snapshot_policy(src: &str) -> anyhow::Result<SnapshotPolicy> {
+    let usage = || {
+        anyhow::anyhow!(
+            "snapshot policy should be in form of 'since_last:<num>', 'every_duration:<duration>', \
+             'logs_or_duration:<num>:<duration>' or 'size_since_last:<size>'"
+        )
+    };
+
+    let elts = src.split(':').collect::<Vec<_>>();
+
+    match elts.as_slice() {
+        ["since_last", n_logs] => {
+            let n_logs = n_logs.parse::<u64>()?;
+            Ok(SnapshotPolicy::LogsSinceLast(n_logs))
+        }
+        ["every_duration", d] => {
+            let d = parse_duration(d)?;
+            Ok(SnapshotPolicy::EveryDuration(d))
+        }
+        ["logs_or_duration", n_logs, d] => {
+            let logs = n_logs.parse::<u64>()?;
+            let duration = parse_duration(d)?;
+            Ok(SnapshotPolicy::LogsOrDuration { logs, duration })
+        }
+        ["size_since_last", size] => {
+            let size = parse_bytes_with_unit(size)?;
+            Ok(SnapshotPolicy::SizeSinceLast(size))
+        }
+        _ => Err(usage()),
+    }
+}
+
+fn parse_election_timeout_distribution(src: &str) -> anyhow::Result<ElectionTimeoutDistribution> {
     let elts = src.split(':').collect::<Vec<_>>();
-    if elts.len() != 2 {
-        return Err(anyhow::anyhow!(
-            "snapshot policy should be in form of 'since_last:<num>'"
-        ));
+
+    match elts.as_slice() {
+        ["uniform"] => Ok(ElectionTimeoutDistribution::Uniform),
+        ["exponential", lambda] => {
+            let lambda = lambda.parse::<f64>()?;
+            Ok(ElectionTimeoutDistribution::Exponential { lambda })
+        }
+        _ => Err(anyhow::anyhow!(
+            "election timeout distribution should be in form of 'uniform' or 'exponential:<lambda>'"
+        )),
+    }
+}
+
+fn parse_durability(src: &str) -> anyhow::Result<Durability> {
+    match src {
+        "full_sync" => Ok(Durability::FullSync),
+        "log_sync_only" => Ok(Durability::LogSyncOnly),
+        "async" => Ok(Durability::Async),
+        _ => Err(anyhow::anyhow!(
+            "durability should be one of 'full_sync', 'log_sync_only' or 'async'"
+        )),
+    }
+}
+
+fn parse_snapshot_checksum(src: &str) -> anyhow::Result<SnapshotChecksum> {
+    match src {
+        "none" => Ok(SnapshotChecksum::None),
+        "crc32" => Ok(SnapshotChecksum::Crc32),
+        _ => Err(anyhow::anyhow!("snapshot checksum should be one of 'none' or 'crc32'")),
+    }
+}
+
+fn parse_compression(src: &str) -> anyhow::Result<Compression> {
+    match src {
+        "none" => Ok(Compression::None),
+        "lz4" => Ok(Compression::Lz4),
+        "zstd" => Ok(Compression::Zstd),
+        _ => Err(anyhow::anyhow!("compression should be one of 'none', 'lz4' or 'zstd'")),
     }
+}
 
-    if elts[0] != "since_last" {
-        return Err(anyhow::anyhow!(
-            "snapshot policy should be in form of 'since_last:<num>'"
-        ));
+fn parse_read_strategy(src: &str) -> anyhow::Result<ReadStrategy> {
+    match src {
+        "read_index" => Ok(ReadStrategy::ReadIndex),
+        "lease_read" => Ok(ReadStrategy::LeaseRead),
+        _ => Err(anyhow::anyhow!(
+            "read strategy should be one of 'read_index' or 'lease_read'"
+        )),
     }
+}
 
-    let n_logs = elts[1].parse::<u64>()?;
-    Ok(SnapshotPolicy::LogsSinceLast(n_logs))
+fn parse_log_purge_policy(src: &str) -> anyhow::Result<LogPurgePolicy> {
+    match src {
+        "after_applied" => Ok(LogPurgePolicy::AfterApplied),
+        "after_snapshot" => Ok(LogPurgePolicy::AfterSnapshot),
+        _ => Err(anyhow::anyhow!(
+            "log purge policy should be one of 'after_applied' or 'after_snapshot'"
+        )),
+    }
 }
 
 /// The runtime configuration for a Raft node.
@@ -70,26 +420,128 @@ fn parse_snapshot_policy(src: &str) -> anyhow::Result<SnapshotPolicy> {
 /// What does all of this mean? Simply keep your election timeout settings high enough that the
 /// performance of your network will not cause election timeouts, but don't keep it so high that
 /// a real leader crash would cause prolonged downtime. See the Raft spec §5.6 for more details.
-#[derive(Clone, Debug, Serialize, Deserialize, StructOpt)]
+///
+/// The timeouts and intervals in milliseconds can be given with a unit suffix on the command line, such as `150ms`,
+/// `1.5s` or `1m`. A bare number is interpreted as milliseconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, StructOpt)]
 pub struct Config {
     /// The application specific name of this Raft cluster
     #[structopt(long, env = "RAFT_CLUSTER_NAME", default_value = "foo")]
     pub cluster_name: String,
 
     /// The minimum election timeout in milliseconds
-    #[structopt(long, env = "RAFT_ELECTION_TIMEOUT_MIN", default_value = "150")]
+    #[structopt(long, env = "RAFT_ELECTION_TIMEOUT_MIN", default_value = "150", parse(try_from_str=parse_duration_ms))]
     pub election_timeout_min: u64,
 
     /// The maximum election timeout in milliseconds
-    #[structopt(long, env = "RAFT_ELECTION_TIMEOUT_MAX", default_value = "300")]
+    #[structopt(long, env = "RAFT_ELECTION_TIMEOUT_MAX", default_value = "300", parse(try_from_str=parse_duration_ms))]
     pub election_timeout_max: u64,
 
+    /// The distribution election timeouts are sampled from, within the configured min & max.
+    #[structopt(
+        long,
+        env = "RAFT_ELECTION_TIMEOUT_DISTRIBUTION",
+        default_value = "uniform",
+        parse(try_from_str=parse_election_timeout_distribution)
+    )]
+    pub election_timeout_distribution: ElectionTimeoutDistribution,
+
+    /// The extra delay in milliseconds a follower waits before starting an election, if it has heard from a leader
+    /// since the last election timeout, 0 to disable
+    ///
+    /// After a brief network blip, the leader that was heard from is given a chance to reassert itself before the
+    /// followers elect another one, thus the leadership does not flap. The extra delay is random between
+    /// `leader_stickiness / 2` and `leader_stickiness`, added to the election timeout. A follower that has not heard
+    /// from a leader, e.g., one that just started, or whose last election failed, does not wait.
+    #[structopt(long, env = "RAFT_LEADER_STICKINESS", default_value = "0", parse(try_from_str=parse_duration_ms))]
+    pub leader_stickiness: u64,
+
+    /// Whether a candidate runs a pre-vote round before starting an election
+    ///
+    /// In a pre-vote round a candidate asks every voter whether it would grant a vote, without increasing its term.
+    /// It starts an election only if a quorum would. Thus a node rejoining after a partition does not disrupt the
+    /// cluster with a higher term.
+    #[structopt(long, env = "RAFT_ENABLE_PRE_VOTE", default_value = "true", parse(try_from_str))]
+    pub enable_pre_vote: bool,
+
+    /// The number of consecutive failed election rounds after which a node stops starting elections, 0 for no limit
+    ///
+    /// A node that can not win, e.g., in a badly partitioned cluster, would otherwise keep starting elections and
+    /// inflate its term. A paused node is in `State::Paused`: it still votes and accepts logs, and resumes when it
+    /// hears from a leader, or when it is restarted.
+    #[structopt(long, env = "RAFT_MAX_CONSECUTIVE_FAILED_ELECTIONS", default_value = "0")]
+    pub max_consecutive_failed_elections: u64,
+
+    /// Whether a node stops starting elections when voters keep rejecting it for its log is not up to date
+    ///
+    /// Such a node loses every election until it catches up. With it enabled, after `STALE_LOG_ELECTIONS` rounds in a
+    /// row in which a voter with a greater last log id rejects it, the node enters `State::Paused`, and resumes
+    /// elections after its log advances, e.g., by replication from a leader, or after
+    /// `STALE_LOG_BACKOFF_ELECTION_TIMEOUTS` election timeouts.
+    #[structopt(
+        long,
+        env = "RAFT_BACKOFF_ON_STALE_LOG",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub backoff_on_stale_log: bool,
+
+    /// The timeout in milliseconds of a vote request, 0 to wait for a response until the election times out
+    ///
+    /// A voter that does not respond within it is counted as rejecting, while the candidate keeps waiting for the
+    /// other voters until the election times out. It must be less than `election_timeout_min`.
+    #[structopt(long, env = "RAFT_VOTE_REQUEST_TIMEOUT", default_value = "0", parse(try_from_str=parse_duration_ms))]
+    pub vote_request_timeout: u64,
+
+    /// The gap by which a term received from another node has to exceed the local term to be logged as suspicious, 0
+    /// to never log
+    ///
+    /// A sudden jump of the term by far more than a few elections hints at a misbehaving node or a clock issue. The
+    /// term is still adopted as Raft requires, and the greatest term seen is in `RaftMetrics::max_term_seen`.
+    #[structopt(long, env = "RAFT_SUSPICIOUS_TERM_GAP", default_value = "0")]
+    pub suspicious_term_gap: u64,
+
+    /// Whether a follower rejects an AppendEntries whose `leader_commit` is less than one the same leader sent before
+    ///
+    /// The commit index of a leader never goes backward within its term, a regression means a buggy leader or
+    /// reordered RPCs. Either way the committed log of the follower never goes backward: with it disabled, the
+    /// regression is logged and the AppendEntries is handled as usual; with it enabled, it is rejected with
+    /// `RaftError::CommitIndexRegression`.
+    #[structopt(
+        long,
+        env = "RAFT_REJECT_COMMIT_REGRESSION",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub reject_commit_regression: bool,
+
     /// The heartbeat interval in milliseconds at which leaders will send heartbeats to followers
-    #[structopt(long, env = "RAFT_HEARTBEAT_INTERVAL", default_value = "50")]
+    ///
+    /// It must be small enough for a leader to send several heartbeats within an election timeout, i.e.,
+    /// `election_timeout_min >= heartbeat_interval * MIN_HEARTBEATS_PER_ELECTION_TIMEOUT`. This is the
+    /// `broadcastTime ≪ electionTimeout` part of the Raft inequality: a follower should not start an election just
+    /// because one heartbeat is delayed or lost.
+    #[structopt(long, env = "RAFT_HEARTBEAT_INTERVAL", default_value = "50", parse(try_from_str=parse_duration_ms))]
     pub heartbeat_interval: u64,
 
+    /// The timeout in milliseconds of an AppendEntries or TimeoutNow RPC, 0 to use `heartbeat_interval`
+    ///
+    /// An AppendEntries that does not respond within it fails, and the replication stream retries it. A TimeoutNow
+    /// that does not respond within it fails the leadership transfer.
+    #[structopt(long, env = "RAFT_APPEND_ENTRIES_TIMEOUT", default_value = "0", parse(try_from_str=parse_duration_ms))]
+    pub append_entries_timeout: u64,
+
+    /// The timeout in milliseconds of a RequestVote RPC, 0 for no limit other than `vote_request_timeout`
+    ///
+    /// Votes are expected to respond fast: a RequestVote that does not respond within it is given up and counted as a
+    /// rejection, while the candidate keeps waiting for the other voters. It must be less than `election_timeout_min`.
+    #[structopt(long, env = "RAFT_VOTE_TIMEOUT", default_value = "0", parse(try_from_str=parse_duration_ms))]
+    pub vote_timeout: u64,
+
     /// The timeout for sending a snapshot segment, in millisecond
-    #[structopt(long, env = "RAFT_INSTALL_SNAPSHOT_TIMEOUT", default_value = "200")]
+    ///
+    /// It must be long enough to send a chunk of `snapshot_max_chunk_size` bytes at `MIN_SNAPSHOT_THROUGHPUT`.
+    #[structopt(long, env = "RAFT_INSTALL_SNAPSHOT_TIMEOUT", default_value = "200", parse(try_from_str=parse_duration_ms))]
     pub install_snapshot_timeout: u64,
 
     /// The maximum number of entries per payload allowed to be transmitted during replication
@@ -99,6 +551,27 @@ pub struct Config {
     #[structopt(long, env = "RAFT_MAX_PAYLOAD_ENTRIES", default_value = "300")]
     pub max_payload_entries: u64,
 
+    /// The maximum size in bytes of the entries per payload allowed to be transmitted during replication
+    ///
+    /// The size of an entry is counted with `AppData::size_hint()`. A payload is sent once either
+    /// `max_payload_entries` or this limit is reached, whichever comes first. A single entry larger than this limit is
+    /// still sent alone.
+    #[structopt(long, env = "RAFT_MAX_PAYLOAD_BYTES", default_value = "16MiB", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_payload_bytes: u64,
+
+    /// The maximum size in bytes of the payload of a single client write, 0 for no limit
+    ///
+    /// A larger client write is rejected with `ClientWriteError::PayloadTooLarge` before it is appended to the log.
+    /// The size of the payload is counted with `AppData::size_hint()`. It should not be larger than
+    /// `snapshot_max_chunk_size`, otherwise a warning is logged.
+    #[structopt(
+        long,
+        env = "RAFT_MAX_LOG_ENTRY_SIZE",
+        default_value = "0",
+        parse(try_from_str=parse_bytes_with_unit)
+    )]
+    pub max_log_entry_size: u64,
+
     /// The distance behind in log replication a follower must fall before it is considered lagging
     ///
     /// Once a replication stream transition into line-rate state, the target node will be considered safe to join a
@@ -106,6 +579,64 @@ pub struct Config {
     #[structopt(long, env = "RAFT_REPLICATION_LAG_THRESHOLD", default_value = "1000")]
     pub replication_lag_threshold: u64,
 
+    /// The number of consecutive failed AppendEntries RPCs to a target beyond which the leader reports it unreachable
+    ///
+    /// See `PeerHealth::Unreachable` in `LeaderMetrics::peer_health`. A successful RPC resets the count.
+    #[structopt(long, env = "RAFT_UNREACHABLE_RPC_FAILURES", default_value = "3")]
+    pub unreachable_rpc_failures: u64,
+
+    /// The window in milliseconds over which the lag of a learner being added with `blocking` is sampled
+    ///
+    /// If the lag of the learner grows in `LEARNER_CATCH_UP_GROWING_WINDOWS` windows in a row, the learner is falling
+    /// further behind rather than catching up, and the add is aborted with `AddLearnerError::LearnerCannotCatchUp`.
+    /// The lag is not sampled while a snapshot is being sent to the learner. 0 disables it.
+    #[structopt(long, env = "RAFT_LEARNER_CATCH_UP_WINDOW", default_value = "1000", parse(try_from_str=parse_duration_ms))]
+    pub learner_catch_up_window: u64,
+
+    /// The time in milliseconds a learner being added with `blocking` is given to catch up, 0 for no limit
+    ///
+    /// The add is aborted with `AddLearnerError::LearnerCannotCatchUp` if the learner is still lagging by then.
+    #[structopt(
+        long,
+        env = "RAFT_LEARNER_CATCH_UP_TIMEOUT",
+        default_value = "0",
+        parse(try_from_str=parse_duration_ms)
+    )]
+    pub learner_catch_up_timeout: u64,
+
+    /// The time in milliseconds a new voter is given to catch up when changing membership with
+    /// `blocking_catch_up`, 0 to use `install_snapshot_timeout * MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS`
+    ///
+    /// The change is rejected with `ChangeMembershipError::CatchUpTimeout` if a new voter is still lagging by then.
+    #[structopt(
+        long,
+        env = "RAFT_MEMBERSHIP_CATCH_UP_TIMEOUT",
+        default_value = "0",
+        parse(try_from_str=parse_duration_ms)
+    )]
+    pub membership_catch_up_timeout: u64,
+
+    /// The codec to compress the entries of an AppendEntries sent to a follower with
+    ///
+    /// One of `none`, `lz4` or `zstd`. The entries of an AppendEntries are encoded with `AppData::encode_entries()`
+    /// and compressed as a whole, and only if it makes them smaller and they are not larger than `max_payload_bytes`.
+    /// They are sent uncompressed to a follower that does not support it, see `NodeCapabilities::compression`, or
+    /// that fails to decompress them. The cargo feature of the codec must be enabled.
+    #[structopt(
+        long,
+        env = "RAFT_REPLICATION_COMPRESSION",
+        default_value = "none",
+        parse(try_from_str=parse_compression)
+    )]
+    pub replication_compression: Compression,
+
+    /// The number of the most recent log entries to keep in memory, 0 to disable it
+    ///
+    /// The replication streams read the entries to send from the cache if they are there, instead of from
+    /// `RaftStorage`. Thus replicating the same recent entries to every follower reads the storage only once.
+    #[structopt(long, env = "RAFT_LOG_CACHE_SIZE", default_value = "0")]
+    pub log_cache_size: u64,
+
     /// The snapshot policy to use for a Raft node.
     #[structopt(
         long,
@@ -119,28 +650,256 @@ pub struct Config {
     #[structopt(long, env = "RAFT_SNAPSHOT_MAX_CHUNK_SIZE", default_value = "3MiB", parse(try_from_str=parse_bytes_with_unit))]
     pub snapshot_max_chunk_size: u64,
 
+    /// The checksum to attach to a snapshot sent to a follower
+    ///
+    /// One of `none` or `crc32`. A follower rejects a snapshot that does not match its checksum, and the leader
+    /// sends it again.
+    #[structopt(long, env = "RAFT_SNAPSHOT_CHECKSUM", default_value = "none", parse(try_from_str=parse_snapshot_checksum))]
+    pub snapshot_checksum: SnapshotChecksum,
+
+    /// The number of snapshot chunks a leader sends before it waits for the target to acknowledge them
+    ///
+    /// With 1, the leader waits for the response to every chunk before sending the next one. With a larger value, up
+    /// to this many chunks are in flight in a sliding window, which saves round trips over a high-latency link. The
+    /// first chunk is sent alone, and the last one only when every chunk before it is acknowledged. A target keeps up
+    /// to this many chunks arriving ahead of the chunks before them, e.g., reordered by the transport, until the gap
+    /// is filled. It must be > 0.
+    #[structopt(long, env = "RAFT_SNAPSHOT_ACK_EVERY_N_CHUNKS", default_value = "1")]
+    pub snapshot_ack_every_n_chunks: u64,
+
     /// The maximum number of applied logs to keep before purging
     #[structopt(long, env = "RAFT_MAX_APPLIED_LOG_TO_KEEP", default_value = "1000")]
     pub max_applied_log_to_keep: u64,
+
+    /// When logs are purged from storage
+    ///
+    /// One of `after_applied` or `after_snapshot`. See `LogPurgePolicy`.
+    #[structopt(long, env = "RAFT_LOG_PURGE_POLICY", default_value = "after_applied", parse(try_from_str=parse_log_purge_policy))]
+    pub log_purge_policy: LogPurgePolicy,
+
+    /// Whether to purge the blank logs at the head of the log after building a snapshot
+    ///
+    /// A leader appends a blank log on election, thus frequent leader changes fill the log with them. With it
+    /// enabled, the run of blank logs at the head of the log that is included in a new snapshot is purged, as long as
+    /// `max_applied_log_to_keep` logs are left, counting the logs after the snapshot. The logs must stay consecutive,
+    /// thus a blank log after a non-blank one is not purged.
+    #[structopt(
+        long,
+        env = "RAFT_COMPACT_NOOP_ON_SNAPSHOT",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub compact_noop_on_snapshot: bool,
+
+    /// Whether to build a snapshot of the last applied log during a graceful shutdown
+    ///
+    /// A restarted node then installs the snapshot instead of re-applying the logs, and the logs included in it can be
+    /// purged. It makes a shutdown slower for a faster startup. It is skipped if the last snapshot already includes
+    /// the last applied log.
+    #[structopt(
+        long,
+        env = "RAFT_SNAPSHOT_ON_SHUTDOWN",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    pub snapshot_on_shutdown: bool,
+
+    /// The maximum number of committed entries a leader hands to the state machine before they are applied
+    ///
+    /// With `1`, the leader applies every committed entry before it does anything else. A greater value lets the
+    /// leader keep committing while the entries are applied in the background, in log order; entries that pile up are
+    /// applied in one batch. A client write is always responded to after its own entry is applied.
+    #[structopt(long, env = "RAFT_MAX_IN_FLIGHT_APPLIES", default_value = "1")]
+    pub max_in_flight_applies: u64,
+
+    /// The maximum number of entries passed to `RaftStorage::apply_to_state_machine()` in one call
+    ///
+    /// Committed entries are applied in batches of at most this many entries, in log order, so that a state machine
+    /// can amortize the cost of a transaction over a batch, while a batch is never unbounded.
+    #[structopt(long, env = "RAFT_MAX_APPLY_BATCH_SIZE", default_value = "1000")]
+    pub max_apply_batch_size: u64,
+
+    /// The maximum number of entries a leader keeps in its log but not yet committed
+    ///
+    /// When the last log index is this many entries ahead of the commit index, e.g., because a quorum of followers
+    /// falls behind, the leader rejects client writes with `ClientWriteError::Overloaded` instead of appending them,
+    /// until the followers catch up. It bounds the memory an uncommitted log takes and tells clients to throttle.
+    #[structopt(long, env = "RAFT_MAX_UNCOMMITTED_ENTRIES", default_value = "10000")]
+    pub max_uncommitted_entries: u64,
+
+    /// How a leader confirms its leadership before serving a linearizable read
+    ///
+    /// One of `read_index` or `lease_read`. See `ReadStrategy`.
+    #[structopt(long, env = "RAFT_READ_STRATEGY", default_value = "read_index", parse(try_from_str=parse_read_strategy))]
+    pub read_strategy: ReadStrategy,
+
+    /// The maximum clock drift in milliseconds between the nodes over an election timeout
+    ///
+    /// A leader lease lasts for `election_timeout_min` minus this margin, thus a follower whose clock runs somewhat
+    /// fast does not vote for a new leader while the old leader still believes it holds the lease. It must be less
+    /// than `election_timeout_min`.
+    #[structopt(long, env = "RAFT_MAX_CLOCK_DRIFT", default_value = "15")]
+    pub max_clock_drift: u64,
+
+    /// The maximum number of snapshots a leader sends at the same time
+    ///
+    /// A target that needs a snapshot waits until another snapshot sending finishes, while it still receives
+    /// heartbeats. It bounds the disk and network load a leader puts on to bring lagging nodes up to date.
+    #[structopt(long, env = "RAFT_MAX_CONCURRENT_SNAPSHOT_SENDS", default_value = "2")]
+    pub max_concurrent_snapshot_sends: u64,
+
+    /// The policy of syncing the logs and the hard state written to storage
+    ///
+    /// One of `full_sync`, `log_sync_only` or `async`. See `Durability` for the risk of relaxing it.
+    #[structopt(long, env = "RAFT_DURABILITY", default_value = "full_sync", parse(try_from_str=parse_durability))]
+    pub durability: Durability,
+
+    /// The time in milliseconds within which a leader coalesces the syncs of logs appended one after another into one
+    /// `RaftStorage::flush()`, 0 to sync every append
+    ///
+    /// A leader counts its own log toward a quorum only once it is synced, thus a burst of writes is synced once,
+    /// at the cost of delaying their commit by at most this window. Followers still sync every AppendEntries before
+    /// responding. It takes no effect if `durability` does not sync logs. It must be less than `heartbeat_interval`.
+    #[structopt(long, env = "RAFT_FSYNC_COALESCE_WINDOW", default_value = "0", parse(try_from_str=parse_duration_ms))]
+    pub fsync_coalesce_window: u64,
+
+    /// The number of times to retry a storage operation that fails with a transient error, before shutting down
+    ///
+    /// It applies to appending logs and reading logs on the critical path of Raft.
+    #[structopt(long, env = "RAFT_STORAGE_RETRY_ATTEMPTS", default_value = "3")]
+    pub storage_retry_attempts: u64,
+
+    /// The delay in milliseconds before the first retry of a storage operation that fails with a transient error
+    ///
+    /// The delay doubles for every following retry, thus a busy store is given time to recover.
+    #[structopt(long, env = "RAFT_STORAGE_RETRY_BACKOFF", default_value = "10", parse(try_from_str=parse_duration_ms))]
+    pub storage_retry_backoff: u64,
+
+    /// The base delay in milliseconds of the backoff before retrying a failed replication RPC
+    ///
+    /// When a target is unreachable, the leader waits for a random delay between 0 and
+    /// `replication_retry_base * 2^n` before the n-th retry, capped at `replication_retry_max`, instead of retrying
+    /// every heartbeat. The backoff is reset once an RPC to the target succeeds.
+    #[structopt(long, env = "RAFT_REPLICATION_RETRY_BASE", default_value = "50", parse(try_from_str=parse_duration_ms))]
+    pub replication_retry_base: u64,
+
+    /// The maximum delay in milliseconds of the backoff before retrying a failed replication RPC
+    #[structopt(long, env = "RAFT_REPLICATION_RETRY_MAX", default_value = "500", parse(try_from_str=parse_duration_ms))]
+    pub replication_retry_max: u64,
 }
 
 impl Default for Config {
     fn default() -> Self {
-        <Self as StructOpt>::from_iter(&Vec::<&'static str>::new())
+        ConfigBuilder::default().build().unwrap()
     }
 }
 
 impl Config {
+    /// Create a builder to construct a `Config` programmatically, without CLI parsing.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
     /// Generate a new random election timeout within the configured min & max.
     pub fn new_rand_election_timeout(&self) -> u64 {
-        thread_rng().gen_range(self.election_timeout_min..self.election_timeout_max)
+        self.election_timeout_distribution.sample(
+            &mut thread_rng(),
+            self.election_timeout_min,
+            self.election_timeout_max,
+        )
+    }
+
+    /// Generate a random extra delay in milliseconds before starting an election, after hearing from a leader, see
+    /// `Config::leader_stickiness`.
+    pub(crate) fn new_rand_leader_stickiness(&self) -> u64 {
+        if self.leader_stickiness == 0 {
+            return 0;
+        }
+        thread_rng().gen_range(self.leader_stickiness / 2..=self.leader_stickiness)
+    }
+
+    /// The delay before the `attempt`-th retry of a storage operation that fails with a transient error, starting
+    /// from 0.
+    pub(crate) fn storage_retry_delay(&self, attempt: u32) -> Duration {
+        let exp = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+        Duration::from_millis(self.storage_retry_backoff.saturating_mul(exp))
+    }
+
+    /// The upper bound in milliseconds of the delay before the `attempt`-th retry of a failed replication RPC, starting
+    /// from 0.
+    pub(crate) fn replication_retry_ceiling(&self, attempt: u32) -> u64 {
+        let exp = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+        self.replication_retry_base.saturating_mul(exp).min(self.replication_retry_max)
+    }
+
+    /// Generate a random delay before the `attempt`-th retry of a failed replication RPC, starting from 0.
+    ///
+    /// It is a capped exponential backoff with full jitter, thus the retries from several leaders or streams do not
+    /// reconnect at the same time after a network blip.
+    pub(crate) fn new_rand_replication_retry_delay(&self, attempt: u32) -> Duration {
+        let ceiling = self.replication_retry_ceiling(attempt);
+        Duration::from_millis(thread_rng().gen_range(0..=ceiling))
     }
 
+    /// Build a `Config` from command line arguments, the first one is the program name.
     pub fn build(args: &[&str]) -> Result<Config, ConfigError> {
         let config = <Self as StructOpt>::from_iter(args);
         config.validate()
     }
 
+    /// The time a new voter is given to catch up with the leader, when changing membership with
+    /// `blocking_catch_up`.
+    pub(crate) fn membership_catch_up_timeout(&self) -> Duration {
+        match self.membership_catch_up_timeout {
+            0 => Duration::from_millis(
+                self.install_snapshot_timeout.saturating_mul(MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS),
+            ),
+            ms => Duration::from_millis(ms),
+        }
+    }
+
+    /// The time a candidate waits for the response to a vote request, the shorter one of `vote_timeout` and
+    /// `vote_request_timeout`, `None` to wait until the election times out.
+    pub(crate) fn vote_request_ttl(&self) -> Option<Duration> {
+        [self.vote_timeout, self.vote_request_timeout]
+            .iter()
+            .filter(|ms| **ms > 0)
+            .min()
+            .map(|ms| Duration::from_millis(*ms))
+    }
+
+    /// The time a learner being added with `blocking` is given to catch up, `None` for no limit.
+    pub(crate) fn learner_catch_up_timeout(&self) -> Option<Duration> {
+        match self.learner_catch_up_timeout {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// The time to wait for the response of an AppendEntries or TimeoutNow RPC.
+    pub(crate) fn append_entries_ttl(&self) -> Duration {
+        match self.append_entries_timeout {
+            0 => Duration::from_millis(self.heartbeat_interval),
+            ms => Duration::from_millis(ms),
+        }
+    }
+
+    /// The number of applied logs to keep when logs are purged right after being applied.
+    ///
+    /// With `LogPurgePolicy::AfterSnapshot` no log is purged on apply, which is expressed as keeping all of them.
+    pub(crate) fn max_logs_to_keep_on_apply(&self) -> u64 {
+        match self.log_purge_policy {
+            LogPurgePolicy::AfterApplied => self.max_applied_log_to_keep,
+            LogPurgePolicy::AfterSnapshot => u64::MAX,
+        }
+    }
+
+    /// The minimal `install_snapshot_timeout` in milliseconds to send a chunk at `MIN_SNAPSHOT_THROUGHPUT`.
+    fn min_install_snapshot_timeout(&self) -> u64 {
+        let ms = self.snapshot_max_chunk_size as u128 * 1000 / MIN_SNAPSHOT_THROUGHPUT as u128;
+        ms as u64
+    }
+
     /// Validate the state of this config.
     pub fn validate(self) -> Result<Config, ConfigError> {
         if self.election_timeout_min >= self.election_timeout_max {
@@ -151,34 +910,557 @@ impl Config {
             return Err(ConfigError::ElectionTimeoutLessThanHeartBeatInterval);
         }
 
-        if self.max_payload_entries == 0 {
-            return Err(ConfigError::MaxPayloadEntriesTooSmall);
+        if self.election_timeout_min < self.heartbeat_interval.saturating_mul(MIN_HEARTBEATS_PER_ELECTION_TIMEOUT) {
+            return Err(ConfigError::ElectionTimeoutTooCloseToHeartbeat {
+                min_heartbeats: MIN_HEARTBEATS_PER_ELECTION_TIMEOUT,
+            });
         }
 
-        Ok(self)
-    }
-}
+        if self.vote_request_timeout >= self.election_timeout_min {
+            return Err(ConfigError::VoteRequestTimeoutTooLarge);
+        }
 
-//////////////////////////////////////////////////////////////////////////////////////////////////
-// Unit Tests ////////////////////////////////////////////////////////////////////////////////////
+        if self.vote_timeout >= self.election_timeout_min {
+            return Err(ConfigError::VoteTimeoutTooLarge);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        if let ElectionTimeoutDistribution::Exponential { lambda } = &self.election_timeout_distribution {
+            if !(lambda.is_finite() && *lambda > 0.0) {
+                return Err(ConfigError::InvalidElectionTimeoutDistribution);
+            }
+        }
 
-    #[test]
-    fn test_config_defaults() {
-        let cfg = Config::default();
+        if self.max_payload_entries == 0 {
+            return Err(ConfigError::MaxPayloadEntriesTooSmall);
+        }
 
-        assert!(cfg.election_timeout_min >= 150);
-        assert!(cfg.election_timeout_max <= 300);
+        if self.max_payload_bytes == 0 {
+            return Err(ConfigError::MaxPayloadBytesTooSmall);
+        }
 
+        if self.max_log_entry_size > self.snapshot_max_chunk_size {
+            tracing::warn!(
+                max_log_entry_size = self.max_log_entry_size,
+                snapshot_max_chunk_size = self.snapshot_max_chunk_size,
+                "max_log_entry_size is larger than snapshot_max_chunk_size"
+            );
+        }
+
+        if !self.replication_compression.is_enabled() {
+            return Err(ConfigError::CompressionNotEnabled {
+                codec: self.replication_compression,
+            });
+        }
+
+        if self.max_in_flight_applies == 0 {
+            return Err(ConfigError::MaxInFlightAppliesTooSmall);
+        }
+
+        if self.fsync_coalesce_window >= self.heartbeat_interval {
+            return Err(ConfigError::FsyncCoalesceWindowTooLarge);
+        }
+
+        if self.max_clock_drift >= self.election_timeout_min {
+            return Err(ConfigError::ClockDriftTooLarge);
+        }
+
+        if self.max_apply_batch_size == 0 {
+            return Err(ConfigError::MaxApplyBatchSizeTooSmall);
+        }
+
+        if self.max_uncommitted_entries == 0 {
+            return Err(ConfigError::MaxUncommittedEntriesTooSmall);
+        }
+
+        if self.max_concurrent_snapshot_sends == 0 {
+            return Err(ConfigError::MaxConcurrentSnapshotSendsTooSmall);
+        }
+
+        if self.snapshot_ack_every_n_chunks == 0 {
+            return Err(ConfigError::SnapshotAckEveryNChunksTooSmall);
+        }
+
+        let min_snapshot_timeout = self.min_install_snapshot_timeout();
+        if self.install_snapshot_timeout < min_snapshot_timeout {
+            return Err(ConfigError::SnapshotTimeoutLikelyTooSmall {
+                install_snapshot_timeout: self.install_snapshot_timeout,
+                snapshot_max_chunk_size: self.snapshot_max_chunk_size,
+                min: min_snapshot_timeout,
+            });
+        }
+
+        match &self.snapshot_policy {
+            SnapshotPolicy::LogsOrDuration { logs, duration } if *logs == 0 && duration.is_zero() => {
+                return Err(ConfigError::InvalidSnapshotPolicy);
+            }
+            SnapshotPolicy::SizeSinceLast(0) => return Err(ConfigError::InvalidSnapshotPolicy),
+            _ => {}
+        }
+
+        if self.replication_retry_base == 0 || self.replication_retry_base > self.replication_retry_max {
+            return Err(ConfigError::InvalidReplicationRetryBackoff);
+        }
+
+        Ok(self)
+    }
+}
+
+/// A builder to construct a `Config` programmatically.
+///
+/// It starts with the same default values as the command line arguments of `Config`.
+/// `build()` runs the same validation as `Config::validate()`.
+///
+/// ```
+/// # use openraft::Config;
+/// # use openraft::SnapshotPolicy;
+/// let config = Config::builder()
+///     .cluster_name("bar")
+///     .heartbeat_interval(100)
+///     .election_timeout_min(500)
+///     .election_timeout_max(1000)
+///     .snapshot_policy(SnapshotPolicy::LogsSinceLast(10_000))
+///     .build()
+///     .unwrap();
+/// # assert_eq!(100, config.heartbeat_interval);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        // The same values as the `default_value` of every field of `Config`, see
+        // `test_builder_defaults_equal_cli_defaults`.
+        Self {
+            config: Config {
+                cluster_name: "foo".to_string(),
+                election_timeout_min: 150,
+                election_timeout_max: 300,
+                election_timeout_distribution: ElectionTimeoutDistribution::Uniform,
+                leader_stickiness: 0,
+                enable_pre_vote: true,
+                max_consecutive_failed_elections: 0,
+                backoff_on_stale_log: false,
+                vote_request_timeout: 0,
+                suspicious_term_gap: 0,
+                reject_commit_regression: false,
+                heartbeat_interval: 50,
+                append_entries_timeout: 0,
+                vote_timeout: 0,
+                install_snapshot_timeout: 200,
+                max_payload_entries: 300,
+                max_payload_bytes: 16 * 1024 * 1024,
+                max_log_entry_size: 0,
+                replication_lag_threshold: 1000,
+                unreachable_rpc_failures: 3,
+                learner_catch_up_window: 1000,
+                learner_catch_up_timeout: 0,
+                membership_catch_up_timeout: 0,
+                replication_compression: Compression::None,
+                log_cache_size: 0,
+                snapshot_policy: SnapshotPolicy::LogsSinceLast(5000),
+                snapshot_max_chunk_size: 3 * 1024 * 1024,
+                snapshot_checksum: SnapshotChecksum::None,
+                snapshot_ack_every_n_chunks: 1,
+                max_applied_log_to_keep: 1000,
+                log_purge_policy: LogPurgePolicy::AfterApplied,
+                compact_noop_on_snapshot: false,
+                snapshot_on_shutdown: false,
+                max_in_flight_applies: 1,
+                max_apply_batch_size: 1000,
+                max_uncommitted_entries: 10000,
+                read_strategy: ReadStrategy::ReadIndex,
+                max_clock_drift: 15,
+                max_concurrent_snapshot_sends: 2,
+                durability: Durability::FullSync,
+                fsync_coalesce_window: 0,
+                storage_retry_attempts: 3,
+                storage_retry_backoff: 10,
+                replication_retry_base: 50,
+                replication_retry_max: 500,
+            },
+        }
+    }
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `Config::cluster_name`.
+    pub fn cluster_name(mut self, cluster_name: impl ToString) -> Self {
+        self.config.cluster_name = cluster_name.to_string();
+        self
+    }
+
+    /// Set `Config::election_timeout_min`, in milliseconds.
+    pub fn election_timeout_min(mut self, election_timeout_min: u64) -> Self {
+        self.config.election_timeout_min = election_timeout_min;
+        self
+    }
+
+    /// Set `Config::election_timeout_max`, in milliseconds.
+    pub fn election_timeout_max(mut self, election_timeout_max: u64) -> Self {
+        self.config.election_timeout_max = election_timeout_max;
+        self
+    }
+
+    /// Set `Config::election_timeout_distribution`.
+    pub fn election_timeout_distribution(mut self, election_timeout_distribution: ElectionTimeoutDistribution) -> Self {
+        self.config.election_timeout_distribution = election_timeout_distribution;
+        self
+    }
+
+    /// Set `Config::leader_stickiness`, in milliseconds.
+    pub fn leader_stickiness(mut self, leader_stickiness: u64) -> Self {
+        self.config.leader_stickiness = leader_stickiness;
+        self
+    }
+
+    /// Set `Config::enable_pre_vote`.
+    pub fn enable_pre_vote(mut self, enable_pre_vote: bool) -> Self {
+        self.config.enable_pre_vote = enable_pre_vote;
+        self
+    }
+
+    /// Set `Config::max_consecutive_failed_elections`.
+    pub fn max_consecutive_failed_elections(mut self, max_consecutive_failed_elections: u64) -> Self {
+        self.config.max_consecutive_failed_elections = max_consecutive_failed_elections;
+        self
+    }
+
+    /// Set `Config::backoff_on_stale_log`.
+    pub fn backoff_on_stale_log(mut self, backoff_on_stale_log: bool) -> Self {
+        self.config.backoff_on_stale_log = backoff_on_stale_log;
+        self
+    }
+
+    /// Set `Config::vote_request_timeout`.
+    pub fn vote_request_timeout(mut self, vote_request_timeout: u64) -> Self {
+        self.config.vote_request_timeout = vote_request_timeout;
+        self
+    }
+
+    /// Set `Config::suspicious_term_gap`.
+    pub fn suspicious_term_gap(mut self, suspicious_term_gap: u64) -> Self {
+        self.config.suspicious_term_gap = suspicious_term_gap;
+        self
+    }
+
+    /// Set `Config::reject_commit_regression`.
+    pub fn reject_commit_regression(mut self, reject_commit_regression: bool) -> Self {
+        self.config.reject_commit_regression = reject_commit_regression;
+        self
+    }
+
+    /// Set `Config::heartbeat_interval`, in milliseconds.
+    pub fn heartbeat_interval(mut self, heartbeat_interval: u64) -> Self {
+        self.config.heartbeat_interval = heartbeat_interval;
+        self
+    }
+
+    /// Set `Config::append_entries_timeout`, in milliseconds.
+    pub fn append_entries_timeout(mut self, append_entries_timeout: u64) -> Self {
+        self.config.append_entries_timeout = append_entries_timeout;
+        self
+    }
+
+    /// Set `Config::vote_timeout`, in milliseconds.
+    pub fn vote_timeout(mut self, vote_timeout: u64) -> Self {
+        self.config.vote_timeout = vote_timeout;
+        self
+    }
+
+    /// Set `Config::install_snapshot_timeout`, in milliseconds.
+    pub fn install_snapshot_timeout(mut self, install_snapshot_timeout: u64) -> Self {
+        self.config.install_snapshot_timeout = install_snapshot_timeout;
+        self
+    }
+
+    /// Set `Config::max_payload_entries`.
+    pub fn max_payload_entries(mut self, max_payload_entries: u64) -> Self {
+        self.config.max_payload_entries = max_payload_entries;
+        self
+    }
+
+    /// Set `Config::max_payload_bytes`, in bytes.
+    pub fn max_payload_bytes(mut self, max_payload_bytes: u64) -> Self {
+        self.config.max_payload_bytes = max_payload_bytes;
+        self
+    }
+
+    /// Set `Config::max_log_entry_size`, in bytes.
+    pub fn max_log_entry_size(mut self, max_log_entry_size: u64) -> Self {
+        self.config.max_log_entry_size = max_log_entry_size;
+        self
+    }
+
+    /// Set `Config::replication_lag_threshold`.
+    pub fn replication_lag_threshold(mut self, replication_lag_threshold: u64) -> Self {
+        self.config.replication_lag_threshold = replication_lag_threshold;
+        self
+    }
+
+    /// Set `Config::unreachable_rpc_failures`.
+    pub fn unreachable_rpc_failures(mut self, unreachable_rpc_failures: u64) -> Self {
+        self.config.unreachable_rpc_failures = unreachable_rpc_failures;
+        self
+    }
+
+    /// Set `Config::learner_catch_up_window`.
+    pub fn learner_catch_up_window(mut self, learner_catch_up_window: u64) -> Self {
+        self.config.learner_catch_up_window = learner_catch_up_window;
+        self
+    }
+
+    /// Set `Config::learner_catch_up_timeout`.
+    pub fn learner_catch_up_timeout(mut self, learner_catch_up_timeout: u64) -> Self {
+        self.config.learner_catch_up_timeout = learner_catch_up_timeout;
+        self
+    }
+
+    /// Set `Config::membership_catch_up_timeout`.
+    pub fn membership_catch_up_timeout(mut self, membership_catch_up_timeout: u64) -> Self {
+        self.config.membership_catch_up_timeout = membership_catch_up_timeout;
+        self
+    }
+
+    /// Set `Config::replication_compression`.
+    pub fn replication_compression(mut self, replication_compression: Compression) -> Self {
+        self.config.replication_compression = replication_compression;
+        self
+    }
+
+    /// Set `Config::log_cache_size`.
+    pub fn log_cache_size(mut self, log_cache_size: u64) -> Self {
+        self.config.log_cache_size = log_cache_size;
+        self
+    }
+
+    /// Set `Config::snapshot_policy`.
+    pub fn snapshot_policy(mut self, snapshot_policy: SnapshotPolicy) -> Self {
+        self.config.snapshot_policy = snapshot_policy;
+        self
+    }
+
+    /// Set `Config::snapshot_max_chunk_size`, in bytes.
+    pub fn snapshot_max_chunk_size(mut self, snapshot_max_chunk_size: u64) -> Self {
+        self.config.snapshot_max_chunk_size = snapshot_max_chunk_size;
+        self
+    }
+
+    /// Set `Config::snapshot_checksum`.
+    pub fn snapshot_checksum(mut self, snapshot_checksum: SnapshotChecksum) -> Self {
+        self.config.snapshot_checksum = snapshot_checksum;
+        self
+    }
+
+    /// Set `Config::snapshot_ack_every_n_chunks`.
+    pub fn snapshot_ack_every_n_chunks(mut self, snapshot_ack_every_n_chunks: u64) -> Self {
+        self.config.snapshot_ack_every_n_chunks = snapshot_ack_every_n_chunks;
+        self
+    }
+
+    /// Set `Config::max_applied_log_to_keep`.
+    pub fn max_applied_log_to_keep(mut self, max_applied_log_to_keep: u64) -> Self {
+        self.config.max_applied_log_to_keep = max_applied_log_to_keep;
+        self
+    }
+
+    /// Set `Config::log_purge_policy`.
+    pub fn log_purge_policy(mut self, log_purge_policy: LogPurgePolicy) -> Self {
+        self.config.log_purge_policy = log_purge_policy;
+        self
+    }
+
+    /// Set `Config::compact_noop_on_snapshot`.
+    pub fn compact_noop_on_snapshot(mut self, compact_noop_on_snapshot: bool) -> Self {
+        self.config.compact_noop_on_snapshot = compact_noop_on_snapshot;
+        self
+    }
+
+    /// Set `Config::snapshot_on_shutdown`.
+    pub fn snapshot_on_shutdown(mut self, snapshot_on_shutdown: bool) -> Self {
+        self.config.snapshot_on_shutdown = snapshot_on_shutdown;
+        self
+    }
+
+    /// Set `Config::max_in_flight_applies`.
+    pub fn max_in_flight_applies(mut self, max_in_flight_applies: u64) -> Self {
+        self.config.max_in_flight_applies = max_in_flight_applies;
+        self
+    }
+
+    /// Set `Config::max_apply_batch_size`.
+    pub fn max_apply_batch_size(mut self, max_apply_batch_size: u64) -> Self {
+        self.config.max_apply_batch_size = max_apply_batch_size;
+        self
+    }
+
+    /// Set `Config::max_uncommitted_entries`.
+    pub fn max_uncommitted_entries(mut self, max_uncommitted_entries: u64) -> Self {
+        self.config.max_uncommitted_entries = max_uncommitted_entries;
+        self
+    }
+
+    /// Set `Config::read_strategy`.
+    pub fn read_strategy(mut self, read_strategy: ReadStrategy) -> Self {
+        self.config.read_strategy = read_strategy;
+        self
+    }
+
+    /// Set `Config::max_clock_drift`, in milliseconds.
+    pub fn max_clock_drift(mut self, max_clock_drift: u64) -> Self {
+        self.config.max_clock_drift = max_clock_drift;
+        self
+    }
+
+    /// Set `Config::max_concurrent_snapshot_sends`.
+    pub fn max_concurrent_snapshot_sends(mut self, max_concurrent_snapshot_sends: u64) -> Self {
+        self.config.max_concurrent_snapshot_sends = max_concurrent_snapshot_sends;
+        self
+    }
+
+    /// Set `Config::durability`.
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.config.durability = durability;
+        self
+    }
+
+    /// Set `Config::fsync_coalesce_window`, in milliseconds.
+    pub fn fsync_coalesce_window(mut self, fsync_coalesce_window: u64) -> Self {
+        self.config.fsync_coalesce_window = fsync_coalesce_window;
+        self
+    }
+
+    /// Set `Config::storage_retry_attempts`.
+    pub fn storage_retry_attempts(mut self, storage_retry_attempts: u64) -> Self {
+        self.config.storage_retry_attempts = storage_retry_attempts;
+        self
+    }
+
+    /// Set `Config::storage_retry_backoff`.
+    pub fn storage_retry_backoff(mut self, storage_retry_backoff: u64) -> Self {
+        self.config.storage_retry_backoff = storage_retry_backoff;
+        self
+    }
+
+    /// Set `Config::replication_retry_base`, in milliseconds.
+    pub fn replication_retry_base(mut self, replication_retry_base: u64) -> Self {
+        self.config.replication_retry_base = replication_retry_base;
+        self
+    }
+
+    /// Set `Config::replication_retry_max`, in milliseconds.
+    pub fn replication_retry_max(mut self, replication_retry_max: u64) -> Self {
+        self.config.replication_retry_max = replication_retry_max;
+        self
+    }
+
+    /// Validate and build the `Config`.
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()
+    }
+}
+
+/// A partial update to the `Config` of a running Raft node.
+///
+/// A `None` field leaves the current value unchanged.
+/// The updated config is validated as a whole before it is applied, see `Raft::update_config()`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigDelta {
+    /// New value of `Config::heartbeat_interval`, in milliseconds.
+    pub heartbeat_interval: Option<u64>,
+
+    /// New value of `Config::election_timeout_min`, in milliseconds.
+    pub election_timeout_min: Option<u64>,
+
+    /// New value of `Config::election_timeout_max`, in milliseconds.
+    pub election_timeout_max: Option<u64>,
+
+    /// New value of `Config::max_payload_entries`.
+    pub max_payload_entries: Option<u64>,
+}
+
+impl ConfigDelta {
+    /// Build a new `Config` by applying this delta to `config`, and validate it.
+    pub fn apply_to(&self, config: &Config) -> Result<Config, ConfigError> {
+        let mut c = config.clone();
+
+        if let Some(v) = self.heartbeat_interval {
+            c.heartbeat_interval = v;
+        }
+        if let Some(v) = self.election_timeout_min {
+            c.election_timeout_min = v;
+        }
+        if let Some(v) = self.election_timeout_max {
+            c.election_timeout_max = v;
+        }
+        if let Some(v) = self.max_payload_entries {
+            c.max_payload_entries = v;
+        }
+
+        c.validate()
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+// Unit Tests ////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        let cfg = Config::default();
+
+        assert!(cfg.election_timeout_min >= 150);
+        assert!(cfg.election_timeout_max <= 300);
+
+        assert_eq!(ElectionTimeoutDistribution::Uniform, cfg.election_timeout_distribution);
+        assert_eq!(0, cfg.leader_stickiness);
+        assert!(cfg.enable_pre_vote);
+        assert_eq!(0, cfg.max_consecutive_failed_elections);
+        assert!(!cfg.backoff_on_stale_log);
+        assert_eq!(0, cfg.vote_request_timeout);
+        assert_eq!(0, cfg.suspicious_term_gap);
+        assert!(!cfg.reject_commit_regression);
         assert_eq!(50, cfg.heartbeat_interval);
+        assert_eq!(0, cfg.append_entries_timeout);
+        assert_eq!(0, cfg.vote_timeout);
         assert_eq!(300, cfg.max_payload_entries);
+        assert_eq!(16 * 1024 * 1024, cfg.max_payload_bytes);
+        assert_eq!(0, cfg.max_log_entry_size);
         assert_eq!(1000, cfg.replication_lag_threshold);
+        assert_eq!(3, cfg.unreachable_rpc_failures);
+        assert_eq!(1000, cfg.learner_catch_up_window);
+        assert_eq!(0, cfg.learner_catch_up_timeout);
+        assert_eq!(0, cfg.membership_catch_up_timeout);
+        assert_eq!(Compression::None, cfg.replication_compression);
+        assert_eq!(0, cfg.log_cache_size);
 
         assert_eq!(3 * 1024 * 1024, cfg.snapshot_max_chunk_size);
         assert_eq!(SnapshotPolicy::LogsSinceLast(5000), cfg.snapshot_policy);
+        assert_eq!(SnapshotChecksum::None, cfg.snapshot_checksum);
+        assert_eq!(1, cfg.snapshot_ack_every_n_chunks);
+        assert_eq!(1, cfg.max_in_flight_applies);
+        assert_eq!(1000, cfg.max_apply_batch_size);
+        assert_eq!(10000, cfg.max_uncommitted_entries);
+        assert_eq!(ReadStrategy::ReadIndex, cfg.read_strategy);
+        assert_eq!(15, cfg.max_clock_drift);
+        assert_eq!(2, cfg.max_concurrent_snapshot_sends);
+        assert_eq!(LogPurgePolicy::AfterApplied, cfg.log_purge_policy);
+        assert!(!cfg.compact_noop_on_snapshot);
+        assert!(!cfg.snapshot_on_shutdown);
+        assert_eq!(Durability::FullSync, cfg.durability);
+        assert_eq!(0, cfg.fsync_coalesce_window);
+        assert_eq!(3, cfg.storage_retry_attempts);
+        assert_eq!(10, cfg.storage_retry_backoff);
+        assert_eq!(50, cfg.replication_retry_base);
+        assert_eq!(500, cfg.replication_retry_max);
     }
 
     #[test]
@@ -194,32 +1476,827 @@ mod tests {
         assert_eq!(err, ConfigError::InvalidElectionTimeoutMinMax);
     }
 
+    #[test]
+    fn test_election_timeout_too_close_to_heartbeat() -> anyhow::Result<()> {
+        // Exactly 3 times
+        let config = Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        };
+        config.validate()?;
+
+        // Just below 3 times
+        let config = Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 149,
+            election_timeout_max: 300,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::ElectionTimeoutTooCloseToHeartbeat {
+            min_heartbeats: 3
+        });
+
+        // Not greater than heartbeat interval is still reported as is.
+        let config = Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 50,
+            election_timeout_max: 300,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::ElectionTimeoutLessThanHeartBeatInterval);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_timeout_likely_too_small() -> anyhow::Result<()> {
+        // 3 MiB in 200 ms
+        let config = Config {
+            install_snapshot_timeout: 200,
+            snapshot_max_chunk_size: 3 * 1024 * 1024,
+            ..Default::default()
+        };
+        config.validate()?;
+
+        // 64 MiB in 10 ms
+        let config = Config {
+            install_snapshot_timeout: 10,
+            snapshot_max_chunk_size: 64 * 1024 * 1024,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::SnapshotTimeoutLikelyTooSmall {
+            install_snapshot_timeout: 10,
+            snapshot_max_chunk_size: 64 * 1024 * 1024,
+            min: 2000,
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_payload_bytes_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            max_payload_bytes: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::MaxPayloadBytesTooSmall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replication_compression_not_enabled() -> anyhow::Result<()> {
+        for codec in [Compression::Lz4, Compression::Zstd] {
+            let res = Config {
+                replication_compression: codec,
+                ..Default::default()
+            }
+            .validate();
+
+            if codec.is_enabled() {
+                assert_eq!(codec, res?.replication_compression);
+            } else {
+                assert_eq!(Err(ConfigError::CompressionNotEnabled { codec }), res);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_in_flight_applies_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            max_in_flight_applies: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::MaxInFlightAppliesTooSmall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_apply_batch_size_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            max_apply_batch_size: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::MaxApplyBatchSizeTooSmall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_uncommitted_entries_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            max_uncommitted_entries: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::MaxUncommittedEntriesTooSmall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vote_request_timeout() -> anyhow::Result<()> {
+        let config = Config {
+            election_timeout_min: 150,
+            vote_request_timeout: 149,
+            ..Default::default()
+        };
+        config.validate()?;
+
+        let config = Config {
+            election_timeout_min: 150,
+            vote_request_timeout: 150,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::VoteRequestTimeoutTooLarge);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vote_timeout() -> anyhow::Result<()> {
+        let config = Config {
+            election_timeout_min: 150,
+            vote_timeout: 150,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::VoteTimeoutTooLarge);
+
+        let config = Config {
+            election_timeout_min: 150,
+            vote_timeout: 0,
+            ..Default::default()
+        };
+        assert_eq!(None, config.validate()?.vote_request_ttl());
+
+        let config = Config {
+            election_timeout_min: 150,
+            vote_timeout: 40,
+            vote_request_timeout: 0,
+            ..Default::default()
+        };
+        assert_eq!(Some(Duration::from_millis(40)), config.validate()?.vote_request_ttl());
+
+        let config = Config {
+            election_timeout_min: 150,
+            vote_timeout: 40,
+            vote_request_timeout: 30,
+            ..Default::default()
+        };
+        assert_eq!(Some(Duration::from_millis(30)), config.validate()?.vote_request_ttl());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fsync_coalesce_window_too_large() -> anyhow::Result<()> {
+        let config = Config {
+            heartbeat_interval: 50,
+            fsync_coalesce_window: 49,
+            ..Default::default()
+        };
+        config.validate()?;
+
+        let config = Config {
+            heartbeat_interval: 50,
+            fsync_coalesce_window: 50,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::FsyncCoalesceWindowTooLarge);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_clock_drift_too_large() -> anyhow::Result<()> {
+        let config = Config {
+            election_timeout_min: 150,
+            max_clock_drift: 149,
+            ..Default::default()
+        };
+        config.validate()?;
+
+        let config = Config {
+            election_timeout_min: 150,
+            max_clock_drift: 150,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::ClockDriftTooLarge);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_entries_ttl() -> anyhow::Result<()> {
+        let config = Config {
+            heartbeat_interval: 50,
+            append_entries_timeout: 0,
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(50), config.append_entries_ttl());
+
+        let config = Config {
+            heartbeat_interval: 50,
+            append_entries_timeout: 120,
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(120), config.append_entries_ttl());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_membership_catch_up_timeout() -> anyhow::Result<()> {
+        let config = Config {
+            install_snapshot_timeout: 200,
+            membership_catch_up_timeout: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Duration::from_millis(200 * MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS),
+            config.membership_catch_up_timeout()
+        );
+
+        let config = Config {
+            install_snapshot_timeout: 200,
+            membership_catch_up_timeout: 120,
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(120), config.membership_catch_up_timeout());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replication_retry_backoff() -> anyhow::Result<()> {
+        let config = Config::builder().replication_retry_base(50).replication_retry_max(500).build()?;
+
+        let ceilings = (0..6).map(|i| config.replication_retry_ceiling(i)).collect::<Vec<_>>();
+        assert_eq!(vec![50, 100, 200, 400, 500, 500], ceilings, "grows upto the cap");
+        assert_eq!(500, config.replication_retry_ceiling(100), "no overflow");
+
+        for attempt in 0..6 {
+            let delay = config.new_rand_replication_retry_delay(attempt);
+            assert!(delay <= Duration::from_millis(config.replication_retry_ceiling(attempt)));
+        }
+
+        for (base, max) in [(0, 500), (600, 500)] {
+            let config = Config {
+                replication_retry_base: base,
+                replication_retry_max: max,
+                ..Default::default()
+            };
+            let err = config.validate().unwrap_err();
+            assert_eq!(err, ConfigError::InvalidReplicationRetryBackoff);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_concurrent_snapshot_sends_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            max_concurrent_snapshot_sends: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::MaxConcurrentSnapshotSendsTooSmall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_ack_every_n_chunks_too_small() -> anyhow::Result<()> {
+        let config = Config {
+            snapshot_ack_every_n_chunks: 0,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::SnapshotAckEveryNChunksTooSmall);
+
+        Ok(())
+    }
+
     #[test]
     fn test_build() -> anyhow::Result<()> {
         let config = Config::build(&[
             "foo",
             "--cluster-name=bar",
-            "--election-timeout-min=10",
+            "--election-timeout-min=15",
             "--election-timeout-max=20",
+            "--election-timeout-distribution=exponential:2.5",
+            "--leader-stickiness=219",
+            "--enable-pre-vote=false",
+            "--max-consecutive-failed-elections=7",
+            "--backoff-on-stale-log=true",
+            "--vote-request-timeout=12",
+            "--suspicious-term-gap=13",
+            "--reject-commit-regression=true",
             "--heartbeat-interval=5",
+            "--append-entries-timeout=214",
+            "--vote-timeout=11",
             "--install-snapshot-timeout=200",
             "--max-payload-entries=201",
+            "--max-payload-bytes=1KiB",
+            "--max-log-entry-size=218",
             "--replication-lag-threshold=202",
+            "--unreachable-rpc-failures=216",
+            "--learner-catch-up-window=217",
+            "--learner-catch-up-timeout=221",
+            "--membership-catch-up-timeout=222",
+            "--log-cache-size=213",
             "--snapshot-policy=since_last:203",
             "--snapshot-max-chunk-size=204",
+            "--snapshot-checksum=crc32",
+            "--snapshot-ack-every-n-chunks=220",
             "--max-applied-log-to-keep=205",
+            "--log-purge-policy=after_snapshot",
+            "--compact-noop-on-snapshot=true",
+            "--snapshot-on-shutdown=true",
+            "--max-in-flight-applies=206",
+            "--max-apply-batch-size=212",
+            "--max-uncommitted-entries=215",
+            "--read-strategy=lease_read",
+            "--max-clock-drift=3",
+            "--max-concurrent-snapshot-sends=207",
+            "--durability=log_sync_only",
+            "--fsync-coalesce-window=4",
+            "--storage-retry-attempts=208",
+            "--storage-retry-backoff=223",
+            "--replication-retry-base=209",
+            "--replication-retry-max=210",
         ])?;
 
         assert_eq!("bar", config.cluster_name);
-        assert_eq!(10, config.election_timeout_min);
+        assert_eq!(15, config.election_timeout_min);
         assert_eq!(20, config.election_timeout_max);
+        assert_eq!(
+            ElectionTimeoutDistribution::Exponential { lambda: 2.5 },
+            config.election_timeout_distribution
+        );
+        assert_eq!(219, config.leader_stickiness);
+        assert!(!config.enable_pre_vote);
+        assert_eq!(7, config.max_consecutive_failed_elections);
+        assert!(config.backoff_on_stale_log);
+        assert_eq!(12, config.vote_request_timeout);
+        assert_eq!(13, config.suspicious_term_gap);
+        assert!(config.reject_commit_regression);
         assert_eq!(5, config.heartbeat_interval);
+        assert_eq!(214, config.append_entries_timeout);
+        assert_eq!(11, config.vote_timeout);
         assert_eq!(200, config.install_snapshot_timeout);
         assert_eq!(201, config.max_payload_entries);
+        assert_eq!(1024, config.max_payload_bytes);
+        assert_eq!(218, config.max_log_entry_size);
         assert_eq!(202, config.replication_lag_threshold);
+        assert_eq!(216, config.unreachable_rpc_failures);
+        assert_eq!(217, config.learner_catch_up_window);
+        assert_eq!(221, config.learner_catch_up_timeout);
+        assert_eq!(222, config.membership_catch_up_timeout);
+        assert_eq!(213, config.log_cache_size);
         assert_eq!(SnapshotPolicy::LogsSinceLast(203), config.snapshot_policy);
         assert_eq!(204, config.snapshot_max_chunk_size);
+        assert_eq!(SnapshotChecksum::Crc32, config.snapshot_checksum);
+        assert_eq!(220, config.snapshot_ack_every_n_chunks);
         assert_eq!(205, config.max_applied_log_to_keep);
+        assert_eq!(LogPurgePolicy::AfterSnapshot, config.log_purge_policy);
+        assert!(config.compact_noop_on_snapshot);
+        assert!(config.snapshot_on_shutdown);
+        assert_eq!(206, config.max_in_flight_applies);
+        assert_eq!(212, config.max_apply_batch_size);
+        assert_eq!(215, config.max_uncommitted_entries);
+        assert_eq!(ReadStrategy::LeaseRead, config.read_strategy);
+        assert_eq!(3, config.max_clock_drift);
+        assert_eq!(207, config.max_concurrent_snapshot_sends);
+        assert_eq!(Durability::LogSyncOnly, config.durability);
+        assert_eq!(4, config.fsync_coalesce_window);
+        assert_eq!(208, config.storage_retry_attempts);
+        assert_eq!(223, config.storage_retry_backoff);
+        assert_eq!(209, config.replication_retry_base);
+        assert_eq!(210, config.replication_retry_max);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_snapshot_policy_every_duration() -> anyhow::Result<()> {
+        let config = Config::build(&["foo", "--snapshot-policy=every_duration:30s"])?;
+        assert_eq!(
+            SnapshotPolicy::EveryDuration(Duration::from_secs(30)),
+            config.snapshot_policy
+        );
+
+        let config = Config::build(&["foo", "--snapshot-policy=every_duration:1.5m"])?;
+        assert_eq!(
+            SnapshotPolicy::EveryDuration(Duration::from_secs(90)),
+            config.snapshot_policy
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_snapshot_policy_logs_or_duration() -> anyhow::Result<()> {
+        let config = Config::build(&["foo", "--snapshot-policy=logs_or_duration:5000:30s"])?;
+        assert_eq!(
+            SnapshotPolicy::LogsOrDuration {
+                logs: 5000,
+                duration: Duration::from_secs(30)
+            },
+            config.snapshot_policy
+        );
+
+        // Either condition can be disabled.
+        Config::build(&["foo", "--snapshot-policy=logs_or_duration:0:30s"])?;
+        Config::build(&["foo", "--snapshot-policy=logs_or_duration:5000:0s"])?;
+
+        let res = Config::build(&["foo", "--snapshot-policy=logs_or_duration:0:0s"]);
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::InvalidSnapshotPolicy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_snapshot_policy_size_since_last() -> anyhow::Result<()> {
+        let config = Config::build(&["foo", "--snapshot-policy=size_since_last:64MiB"])?;
+        assert_eq!(SnapshotPolicy::SizeSinceLast(64 * 1024 * 1024), config.snapshot_policy);
+
+        let res = Config::build(&["foo", "--snapshot-policy=size_since_last:0"]);
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::InvalidSnapshotPolicy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration_ms() -> anyhow::Result<()> {
+        assert_eq!(150, parse_duration_ms("150")?);
+        assert_eq!(150, parse_duration_ms("150ms")?);
+        assert_eq!(1500, parse_duration_ms("1.5s")?);
+        assert_eq!(2000, parse_duration_ms("2s")?);
+        assert_eq!(60_000, parse_duration_ms("1m")?);
+        assert_eq!(3_600_000, parse_duration_ms("1h")?);
+
+        assert!(parse_duration_ms("").is_err());
+        assert!(parse_duration_ms("1.5").is_err());
+        assert!(parse_duration_ms("10d").is_err());
+        assert!(parse_duration_ms("s").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_duration_with_unit() -> anyhow::Result<()> {
+        let config = Config::build(&[
+            "foo",
+            "--election-timeout-min=1s",
+            "--election-timeout-max=1.5s",
+            "--heartbeat-interval=100ms",
+            "--install-snapshot-timeout=1m",
+        ])?;
+
+        assert_eq!(1000, config.election_timeout_min);
+        assert_eq!(1500, config.election_timeout_max);
+        assert_eq!(100, config.heartbeat_interval);
+        assert_eq!(60_000, config.install_snapshot_timeout);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_snapshot_policy() -> anyhow::Result<()> {
+        assert_eq!(
+            SnapshotPolicy::LogsSinceLast(10),
+            parse_snapshot_policy("since_last:10")?
+        );
+        assert_eq!(
+            SnapshotPolicy::EveryDuration(Duration::from_millis(200)),
+            parse_snapshot_policy("every_duration:200ms")?
+        );
+
+        assert_eq!(
+            SnapshotPolicy::LogsOrDuration {
+                logs: 5000,
+                duration: Duration::from_secs(30)
+            },
+            parse_snapshot_policy("logs_or_duration:5000:30s")?
+        );
+
+        assert_eq!(
+            SnapshotPolicy::SizeSinceLast(1024),
+            parse_snapshot_policy("size_since_last:1KiB")?
+        );
+
+        assert!(parse_snapshot_policy("every_duration:30").is_err());
+        assert!(parse_snapshot_policy("logs_or_duration:5000").is_err());
+        assert!(parse_snapshot_policy("logs_or_duration:30s:5000").is_err());
+        assert!(parse_snapshot_policy("since_last:10:30s").is_err());
+        assert!(parse_snapshot_policy("every_duration:30d").is_err());
+        assert!(parse_snapshot_policy("since_last").is_err());
+        assert!(parse_snapshot_policy("foo:30").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_election_timeout_distribution() -> anyhow::Result<()> {
+        assert_eq!(
+            ElectionTimeoutDistribution::Uniform,
+            parse_election_timeout_distribution("uniform")?
+        );
+        assert_eq!(
+            ElectionTimeoutDistribution::Exponential { lambda: 3.0 },
+            parse_election_timeout_distribution("exponential:3")?
+        );
+
+        assert!(parse_election_timeout_distribution("exponential").is_err());
+        assert!(parse_election_timeout_distribution("exponential:x").is_err());
+        assert!(parse_election_timeout_distribution("uniform:3").is_err());
+
+        for lambda in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let config = Config {
+                election_timeout_distribution: ElectionTimeoutDistribution::Exponential { lambda },
+                ..Default::default()
+            };
+            let err = config.validate().unwrap_err();
+            assert_eq!(err, ConfigError::InvalidElectionTimeoutDistribution);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_durability() -> anyhow::Result<()> {
+        assert_eq!(Durability::FullSync, parse_durability("full_sync")?);
+        assert_eq!(Durability::LogSyncOnly, parse_durability("log_sync_only")?);
+        assert_eq!(Durability::Async, parse_durability("async")?);
+
+        assert!(parse_durability("sync").is_err());
+        assert!(parse_durability("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compression() -> anyhow::Result<()> {
+        assert_eq!(Compression::None, parse_compression("none")?);
+        assert_eq!(Compression::Lz4, parse_compression("lz4")?);
+        assert_eq!(Compression::Zstd, parse_compression("zstd")?);
+
+        assert!(parse_compression("gzip").is_err());
+        assert!(parse_compression("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_snapshot_checksum() -> anyhow::Result<()> {
+        assert_eq!(SnapshotChecksum::None, parse_snapshot_checksum("none")?);
+        assert_eq!(SnapshotChecksum::Crc32, parse_snapshot_checksum("crc32")?);
+
+        assert!(parse_snapshot_checksum("blake3").is_err());
+        assert!(parse_snapshot_checksum("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_read_strategy() -> anyhow::Result<()> {
+        assert_eq!(ReadStrategy::ReadIndex, parse_read_strategy("read_index")?);
+        assert_eq!(ReadStrategy::LeaseRead, parse_read_strategy("lease_read")?);
+
+        assert!(parse_read_strategy("lease").is_err());
+        assert!(parse_read_strategy("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_log_purge_policy() -> anyhow::Result<()> {
+        assert_eq!(LogPurgePolicy::AfterApplied, parse_log_purge_policy("after_applied")?);
+        assert_eq!(LogPurgePolicy::AfterSnapshot, parse_log_purge_policy("after_snapshot")?);
+
+        assert!(parse_log_purge_policy("snapshot").is_err());
+        assert!(parse_log_purge_policy("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_logs_to_keep_on_apply() -> anyhow::Result<()> {
+        let cfg = Config::builder().max_applied_log_to_keep(100).build()?;
+        assert_eq!(100, cfg.max_logs_to_keep_on_apply());
+
+        let cfg = Config::builder()
+            .max_applied_log_to_keep(100)
+            .log_purge_policy(LogPurgePolicy::AfterSnapshot)
+            .build()?;
+        assert_eq!(u64::MAX, cfg.max_logs_to_keep_on_apply());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_election_timeout_distribution() -> anyhow::Result<()> {
+        let n = 10_000;
+
+        let mean_of = |distribution: ElectionTimeoutDistribution| -> anyhow::Result<f64> {
+            let config = Config {
+                election_timeout_distribution: distribution,
+                ..Default::default()
+            }
+            .validate()?;
+
+            let mut sum = 0;
+            for _ in 0..n {
+                let t = config.new_rand_election_timeout();
+                assert!(t >= config.election_timeout_min);
+                assert!(t <= config.election_timeout_max);
+                sum += t;
+            }
+            Ok(sum as f64 / n as f64)
+        };
+
+        let uniform = mean_of(ElectionTimeoutDistribution::Uniform)?;
+        let exponential = mean_of(ElectionTimeoutDistribution::Exponential { lambda: 4.0 })?;
+
+        // Default range is [150, 300]: the uniform mean is about 225,
+        // and the exponential mean is about 150 + 150 / 4.
+        assert!((215.0..235.0).contains(&uniform), "uniform mean: {}", uniform);
+        assert!(exponential < 200.0, "exponential mean: {}", exponential);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_defaults_equal_cli_defaults() -> anyhow::Result<()> {
+        let from_cli = Config::build(&["foo"])?;
+        let from_builder = Config::builder().build()?;
+
+        assert_eq!(from_cli, from_builder);
+        assert_eq!(from_cli, Config::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_equals_cli() -> anyhow::Result<()> {
+        let from_cli = Config::build(&[
+            "foo",
+            "--cluster-name=bar",
+            "--election-timeout-min=15",
+            "--election-timeout-max=20",
+            "--election-timeout-distribution=exponential:2.5",
+            "--leader-stickiness=219",
+            "--enable-pre-vote=false",
+            "--max-consecutive-failed-elections=7",
+            "--backoff-on-stale-log=true",
+            "--vote-request-timeout=12",
+            "--suspicious-term-gap=13",
+            "--reject-commit-regression=true",
+            "--heartbeat-interval=5",
+            "--append-entries-timeout=214",
+            "--vote-timeout=11",
+            "--install-snapshot-timeout=200",
+            "--max-payload-entries=201",
+            "--max-payload-bytes=1KiB",
+            "--max-log-entry-size=218",
+            "--replication-lag-threshold=202",
+            "--unreachable-rpc-failures=216",
+            "--learner-catch-up-window=217",
+            "--learner-catch-up-timeout=221",
+            "--membership-catch-up-timeout=222",
+            "--log-cache-size=213",
+            "--snapshot-policy=since_last:203",
+            "--snapshot-max-chunk-size=204",
+            "--snapshot-checksum=crc32",
+            "--snapshot-ack-every-n-chunks=220",
+            "--max-applied-log-to-keep=205",
+            "--log-purge-policy=after_snapshot",
+            "--compact-noop-on-snapshot=true",
+            "--snapshot-on-shutdown=true",
+            "--max-in-flight-applies=206",
+            "--max-apply-batch-size=212",
+            "--max-uncommitted-entries=215",
+            "--read-strategy=lease_read",
+            "--max-clock-drift=3",
+            "--max-concurrent-snapshot-sends=207",
+            "--durability=log_sync_only",
+            "--fsync-coalesce-window=4",
+            "--storage-retry-attempts=208",
+            "--storage-retry-backoff=223",
+            "--replication-retry-base=209",
+            "--replication-retry-max=210",
+        ])?;
+
+        let from_builder = ConfigBuilder::new()
+            .cluster_name("bar")
+            .election_timeout_min(15)
+            .election_timeout_max(20)
+            .election_timeout_distribution(ElectionTimeoutDistribution::Exponential { lambda: 2.5 })
+            .leader_stickiness(219)
+            .enable_pre_vote(false)
+            .max_consecutive_failed_elections(7)
+            .backoff_on_stale_log(true)
+            .vote_request_timeout(12)
+            .suspicious_term_gap(13)
+            .reject_commit_regression(true)
+            .heartbeat_interval(5)
+            .append_entries_timeout(214)
+            .vote_timeout(11)
+            .install_snapshot_timeout(200)
+            .max_payload_entries(201)
+            .max_payload_bytes(1024)
+            .max_log_entry_size(218)
+            .replication_lag_threshold(202)
+            .unreachable_rpc_failures(216)
+            .learner_catch_up_window(217)
+            .learner_catch_up_timeout(221)
+            .membership_catch_up_timeout(222)
+            .log_cache_size(213)
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(203))
+            .snapshot_max_chunk_size(204)
+            .snapshot_checksum(SnapshotChecksum::Crc32)
+            .snapshot_ack_every_n_chunks(220)
+            .max_applied_log_to_keep(205)
+            .log_purge_policy(LogPurgePolicy::AfterSnapshot)
+            .compact_noop_on_snapshot(true)
+            .snapshot_on_shutdown(true)
+            .max_in_flight_applies(206)
+            .max_apply_batch_size(212)
+            .max_uncommitted_entries(215)
+            .read_strategy(ReadStrategy::LeaseRead)
+            .max_clock_drift(3)
+            .max_concurrent_snapshot_sends(207)
+            .durability(Durability::LogSyncOnly)
+            .fsync_coalesce_window(4)
+            .storage_retry_attempts(208)
+            .storage_retry_backoff(223)
+            .replication_retry_base(209)
+            .replication_retry_max(210)
+            .build()?;
+
+        assert_eq!(from_cli, from_builder);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validates() -> anyhow::Result<()> {
+        let res = Config::builder().election_timeout_min(1000).election_timeout_max(700).build();
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::InvalidElectionTimeoutMinMax);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_delta() -> anyhow::Result<()> {
+        let config = Config::default();
+
+        let updated = ConfigDelta::default().apply_to(&config)?;
+        assert_eq!(config, updated, "empty delta changes nothing");
+
+        let updated = ConfigDelta {
+            heartbeat_interval: Some(100),
+            election_timeout_min: Some(300),
+            election_timeout_max: Some(600),
+            max_payload_entries: Some(10),
+        }
+        .apply_to(&config)?;
+
+        assert_eq!(100, updated.heartbeat_interval);
+        assert_eq!(300, updated.election_timeout_min);
+        assert_eq!(600, updated.election_timeout_max);
+        assert_eq!(10, updated.max_payload_entries);
+        assert_eq!(config.snapshot_policy, updated.snapshot_policy);
+
+        // The heartbeat interval is not small enough for the current election timeout.
+        let res = ConfigDelta {
+            heartbeat_interval: Some(100),
+            ..Default::default()
+        }
+        .apply_to(&config);
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::ElectionTimeoutTooCloseToHeartbeat {
+            min_heartbeats: 3
+        });
 
         Ok(())
     }
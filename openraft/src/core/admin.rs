@@ -1,38 +1,132 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::sync::Arc;
 
+use tokio::sync::oneshot;
+use tokio::time::Duration;
+use tokio::time::Instant;
+use tracing::Instrument;
+
+use crate::config::LEARNER_CATCH_UP_GROWING_WINDOWS;
 use crate::core::client::ClientRequestEntry;
 use crate::core::EffectiveMembership;
 use crate::core::LeaderState;
 use crate::core::LearnerState;
+use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::AddLearnerError;
 use crate::error::ChangeMembershipError;
 use crate::error::ClientWriteError;
 use crate::error::InitializeError;
+use crate::error::RaftResult;
+use crate::metrics::PeerHealth;
 use crate::raft::AddLearnerResponse;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::Membership;
 use crate::raft::RaftRespTx;
+use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
-use crate::NodeId;
 use crate::RaftError;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
+use crate::Update;
+
+/// A membership change waiting for the new voters to catch up, before proposing the joint config.
+pub(super) struct MembershipCatchUp<R: AppDataResponse, NID: RaftNodeId> {
+    /// The proposed voters and their vote weights.
+    pub members: BTreeMap<NID, u64>,
+
+    /// The proposed voters that become witnesses.
+    pub witnesses: BTreeSet<NID>,
+
+    /// When to reject the change if a new voter is still lagging.
+    pub deadline: Instant,
+
+    pub tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+}
+
+/// A config overridden locally by `Raft::force_remove_node()` on a follower or candidate, waiting for this node to be
+/// elected with it.
+pub(super) struct ForcedMembership<R: AppDataResponse, NID: RaftNodeId> {
+    /// The voter removed.
+    pub target: NID,
+
+    /// The config without `target`.
+    pub membership: Membership<NID>,
+
+    pub tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+}
+
+/// The lag of a learner being added with `blocking`, sampled to tell whether it will ever catch up.
+pub(super) struct LearnerCatchUp {
+    /// When to abort the add if the learner is still lagging, see `Config::learner_catch_up_timeout`.
+    pub deadline: Option<Instant>,
+
+    /// When the lag is sampled last time.
+    pub sampled_at: Instant,
+
+    /// The number of logs the learner is behind the leader at `sampled_at`.
+    pub sampled_lag: u64,
+
+    /// The number of sampling windows in a row in which the lag grows.
+    pub growing_windows: u64,
+
+    /// Whether a snapshot is being sent to the learner, during which the lag is not sampled.
+    pub snapshotting: bool,
+}
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LearnerState<'a, D, R, N, S> {
+impl LearnerCatchUp {
+    pub fn new(now: Instant, lag: u64, timeout: Option<Duration>) -> Self {
+        Self {
+            deadline: timeout.map(|t| now + t),
+            sampled_at: now,
+            sampled_lag: lag,
+            growing_windows: 0,
+            snapshotting: false,
+        }
+    }
+
+    /// Start over sampling the lag, e.g., after the learner is resumed or a snapshot is sent to it.
+    pub fn restart(&mut self, now: Instant, lag: u64) {
+        self.sampled_at = now;
+        self.sampled_lag = lag;
+        self.growing_windows = 0;
+    }
+
+    /// When to check the learner next: at the end of the current sampling window, or at the deadline.
+    pub fn next_check(&self, window: u64) -> Option<Instant> {
+        if window == 0 || self.snapshotting {
+            return self.deadline;
+        }
+
+        let window_end = self.sampled_at + Duration::from_millis(window);
+        Some(self.deadline.map(|d| d.min(window_end)).unwrap_or(window_end))
+    }
+}
+
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LearnerState<'a, D, R, N, S, NID>
+{
     /// Handle the admin `init_with_config` command.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) async fn handle_init_with_config(
-        &mut self,
-        mut members: BTreeSet<NodeId>,
-    ) -> Result<(), InitializeError> {
-        if self.core.last_log_id.index != 0 || self.core.current_term != 0 {
-            tracing::error!({self.core.last_log_id.index, self.core.current_term}, "rejecting init_with_config request as last_log_index or current_term is 0");
+    pub(super) async fn handle_init_with_config(&mut self, mut members: BTreeSet<NID>) -> Result<(), InitializeError> {
+        if self.core.last_log_id.index != 0 {
+            tracing::error!(%self.core.last_log_id, "rejecting init_with_config request as the log is not empty");
+            return Err(InitializeError::AlreadyInitialized {
+                last_log_id: self.core.last_log_id,
+            });
+        }
+
+        if self.core.current_term != 0 {
+            tracing::error!(
+                { self.core.current_term },
+                "rejecting init_with_config request as current_term is not 0"
+            );
             return Err(InitializeError::NotAllowed);
         }
 
@@ -43,10 +137,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         // Build a new membership config from given init data & assign it as the new cluster
         // membership config in memory only.
-        self.core.effective_membership = EffectiveMembership {
+        self.core.set_effective_membership(EffectiveMembership {
             log_id: LogId { term: 1, index: 1 },
             membership: Membership::new_single(members),
-        };
+        });
 
         // Become a candidate and start campaigning for leadership. If this node is the only node
         // in the cluster, then become leader without holding an election. If members len == 1, we
@@ -64,14 +158,16 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Add a new node to the cluster as a learner, bringing it up-to-speed, and then responding
     /// on the given channel.
     #[tracing::instrument(level = "debug", skip(self, tx))]
     pub(super) fn add_learner(
         &mut self,
-        target: NodeId,
-        tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
+        target: NID,
+        tx: RaftRespTx<AddLearnerResponse, AddLearnerError<NID>>,
         blocking: bool,
     ) {
         // Ensure the node doesn't already exist in the current
@@ -91,7 +187,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
 
         if blocking {
-            let state = self.spawn_replication_stream(target, Some(tx));
+            let now = self.core.clock.now();
+            let mut state = self.spawn_replication_stream(target, Some(tx));
+            state.catch_up = Some(LearnerCatchUp::new(
+                now,
+                self.core.last_log_id.index,
+                self.core.config.learner_catch_up_timeout(),
+            ));
             self.nodes.insert(target, state);
         } else {
             let state = self.spawn_replication_stream(target, None);
@@ -104,53 +206,177 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, tx))]
-    pub(super) async fn change_membership(
-        &mut self,
-        members: BTreeSet<NodeId>,
-        blocking: bool,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
-    ) {
+    /// When the next learner being added with `blocking` has to be checked for whether it is catching up.
+    ///
+    /// A paused learner is not checked until it is resumed.
+    pub(super) fn next_learner_catch_up_check(&self) -> Option<Instant> {
+        let window = self.core.config.learner_catch_up_window;
+        self.nodes
+            .values()
+            .filter(|node| !node.paused)
+            .filter_map(|node| node.catch_up.as_ref().and_then(|c| c.next_check(window)))
+            .min()
+    }
+
+    /// Abort adding a learner that reaches the deadline before catching up, or whose lag grows over
+    /// `LEARNER_CATCH_UP_GROWING_WINDOWS` sampling windows in a row, instead of letting `add_learner` block forever.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn handle_learner_catch_up_check(&mut self) {
+        let now = self.core.clock.now();
+        let window = self.core.config.learner_catch_up_window;
+        let last_index = self.core.last_log_id.index;
+
+        let mut to_abort = vec![];
+
+        for (id, node) in self.nodes.iter_mut() {
+            if node.paused {
+                continue;
+            }
+
+            let catch_up = match &mut node.catch_up {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let lag = last_index.saturating_sub(node.matched.index);
+
+            if catch_up.deadline.map(|d| now >= d).unwrap_or(false) {
+                tracing::info!(node_id = %id, lag, "learner did not catch up before the deadline");
+                to_abort.push(*id);
+                continue;
+            }
+
+            // `matched` does not move until a snapshot is installed.
+            if window == 0 || catch_up.snapshotting || now < catch_up.sampled_at + Duration::from_millis(window) {
+                continue;
+            }
+
+            if lag > catch_up.sampled_lag {
+                catch_up.growing_windows += 1;
+            } else {
+                catch_up.growing_windows = 0;
+            }
+
+            if catch_up.growing_windows >= LEARNER_CATCH_UP_GROWING_WINDOWS {
+                tracing::info!(
+                    node_id = %id,
+                    lag,
+                    prev_lag = catch_up.sampled_lag,
+                    growing_windows = catch_up.growing_windows,
+                    "learner is falling behind"
+                );
+                to_abort.push(*id);
+                continue;
+            }
+
+            catch_up.sampled_at = now;
+            catch_up.sampled_lag = lag;
+        }
+
+        for target in to_abort {
+            self.abort_adding_learner(target);
+        }
+    }
+
+    /// Stop replicating to a learner being added, and respond to the caller of `add_learner` with an error.
+    fn abort_adding_learner(&mut self, target: NID) {
+        let mut node = match self.nodes.remove(&target) {
+            Some(node) => node,
+            None => return,
+        };
+
+        let _ = node.repl_stream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+        self.leader_metrics.replication.remove(&target);
+
+        // Publish the removal before responding, thus the caller sees it in the metrics.
+        self.leader_report_metrics();
+
+        if let Some(tx) = node.tx.take() {
+            let _ = tx.send(Err(AddLearnerError::LearnerCannotCatchUp {
+                node_id: target,
+                matched: node.matched,
+                distance: self.core.last_log_id.index.saturating_sub(node.matched.index),
+            }));
+        }
+    }
+
+    /// Check a proposed membership change against the current membership, and build the config to propose for it.
+    fn build_membership_change(
+        &self,
+        members: &BTreeMap<NID, u64>,
+        witnesses: &BTreeSet<NID>,
+    ) -> Result<Membership<NID>, ChangeMembershipError<NID>> {
         // Ensure cluster will have at least one node.
         if members.is_empty() {
-            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                ChangeMembershipError::EmptyMembership,
-            )));
-            return;
+            return Err(ChangeMembershipError::EmptyMembership);
         }
 
-        // The last membership config is not committed yet.
-        // Can not process the next one.
-        if self.core.committed < self.core.effective_membership.log_id {
-            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                ChangeMembershipError::InProgress {
-                    membership_log_id: self.core.effective_membership.log_id,
-                },
-            )));
-            return;
-        }
+        check_weights(members)?;
+
+        let voters = members.keys().cloned().collect::<BTreeSet<_>>();
 
-        let new_config;
+        // The last membership config is not committed yet, or another change is waiting for its learners to catch
+        // up. Can not process the next one.
+        if self.core.committed < self.core.effective_membership.log_id || self.membership_catch_up.is_some() {
+            return Err(ChangeMembershipError::InProgress {
+                membership_log_id: self.core.effective_membership.log_id,
+            });
+        }
 
         let curr = &self.core.effective_membership.membership;
 
-        if let Some(next_membership) = curr.get_ith_config(1) {
-            // When it is in joint state, it is only allowed to change to the `members_after_consensus`
-            if &members != next_membership {
-                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                    ChangeMembershipError::Incompatible {
-                        curr: curr.clone(),
-                        to: members,
-                    },
-                )));
-                return;
-            } else {
-                new_config = Membership::new_single(next_membership.clone());
+        // A witness has to be a new voter: the log of a full voter has application data a witness does not keep.
+        for id in witnesses.iter() {
+            if *id == self.core.id || !voters.contains(id) || (curr.contains(id) && !curr.is_witness(id)) {
+                return Err(ChangeMembershipError::InvalidWitness { node_id: *id });
             }
+        }
+
+        // A node replicated to as a witness has no application data in its log, it can not become a full voter.
+        for id in voters.iter() {
+            let witness = witnesses.contains(id) || curr.is_witness(id);
+            if !witness && self.nodes.get(id).map(|node| node.witness).unwrap_or(false) {
+                return Err(ChangeMembershipError::InvalidWitness { node_id: *id });
+            }
+        }
+
+        if let Some(next_membership) = curr.get_ith_weights(1) {
+            // When it is in joint state, it is only allowed to change to the `members_after_consensus`, with the same
+            // vote weights and witnesses.
+            if *members != next_membership || !witnesses.is_subset(curr.witnesses()) {
+                return Err(ChangeMembershipError::Incompatible {
+                    curr: curr.clone(),
+                    to: voters,
+                });
+            }
+            Ok(curr.to_final_config())
         } else {
-            // currently it is uniform config, enter joint state
-            new_config = Membership::new_multi(vec![curr.get_ith_config(0).unwrap().clone(), members.clone()]);
+            // currently it is uniform config, enter joint state. The current witnesses stay witnesses.
+            let all_witnesses = curr.witnesses().union(witnesses).cloned().collect();
+            Ok(
+                Membership::new_weighted(vec![curr.get_ith_weights(0).unwrap(), members.clone()])
+                    .with_witnesses(all_witnesses),
+            )
         }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn change_membership(
+        &mut self,
+        members: BTreeMap<NID, u64>,
+        witnesses: BTreeSet<NID>,
+        blocking_catch_up: bool,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    ) {
+        let new_config = match self.build_membership_change(&members, &witnesses) {
+            Ok(new_config) => new_config,
+            Err(err) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(err)));
+                return;
+            }
+        };
+
+        let voters = members.keys().cloned().collect::<BTreeSet<_>>();
 
         tracing::debug!(?new_config, "new_config");
 
@@ -165,7 +391,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         // TODO(xp): 111 test adding a node that is not learner.
         // TODO(xp): 111 test adding a node that is lagging.
-        for new_node in members.difference(self.core.effective_membership.membership.get_ith_config(0).unwrap()) {
+        for new_node in voters.difference(self.core.effective_membership.membership.get_ith_config(0).unwrap()) {
             match self.nodes.get(new_node) {
                 // Node is ready to join.
                 Some(node) => {
@@ -173,17 +399,28 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                         continue;
                     }
 
-                    if !blocking {
-                        // Node has repl stream, but is not yet ready to join.
-                        let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                            ChangeMembershipError::LearnerIsLagging {
-                                node_id: *new_node,
-                                matched: node.matched,
-                                distance: self.core.last_log_id.index.saturating_sub(node.matched.index),
-                            },
-                        )));
+                    if blocking_catch_up {
+                        // Keep it as a learner until it catches up, thus it does not stall the quorum.
+                        tracing::info!(node_id = %new_node, matched = %node.matched, "wait for learner to catch up");
+
+                        self.membership_catch_up = Some(MembershipCatchUp {
+                            members,
+                            witnesses,
+                            deadline: self.core.clock.now() + self.core.config.membership_catch_up_timeout(),
+                            tx,
+                        });
                         return;
                     }
+
+                    // Node has repl stream, but is not yet ready to join.
+                    let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                        ChangeMembershipError::LearnerIsLagging {
+                            node_id: *new_node,
+                            matched: node.matched,
+                            distance: self.core.last_log_id.index.saturating_sub(node.matched.index),
+                        },
+                    )));
+                    return;
                 }
 
                 // Node does not yet have a repl stream, spawn one.
@@ -204,20 +441,251 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, resp_tx), fields(id=self.core.id))]
+    /// Check if a membership change would be accepted and could be committed right now, without proposing it.
+    ///
+    /// Besides the checks `change_membership()` does, every new voter has to be caught up, and the voters the leader
+    /// can reach have to form a quorum of the proposed config.
+    pub(super) fn can_change_membership(&self, members: &BTreeMap<NID, u64>) -> Result<(), ChangeMembershipError<NID>> {
+        let new_config = self.build_membership_change(members, &BTreeSet::new())?;
+
+        let curr_voters = self.core.effective_membership.membership.get_ith_config(0).unwrap();
+
+        for new_node in members.keys().filter(|id| !curr_voters.contains(id)) {
+            match self.nodes.get(new_node) {
+                Some(node) if node.is_line_rate(&self.core.last_log_id, &self.core.config) => {}
+                Some(node) => {
+                    return Err(ChangeMembershipError::LearnerIsLagging {
+                        node_id: *new_node,
+                        matched: node.matched,
+                        distance: self.core.last_log_id.index.saturating_sub(node.matched.index),
+                    });
+                }
+                None => return Err(ChangeMembershipError::LearnerNotFound { node_id: *new_node }),
+            }
+        }
+
+        let unreachable_rpc_failures = self.core.config.unreachable_rpc_failures;
+
+        let reachable = new_config
+            .voters()
+            .iter()
+            .filter(|id| {
+                **id == self.core.id
+                    || self.leader_metrics.replication.get(id).map_or(false, |metrics| {
+                        PeerHealth::of(metrics, unreachable_rpc_failures) != PeerHealth::Unreachable
+                    })
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        if !new_config.is_majority(&reachable) {
+            return Err(ChangeMembershipError::QuorumUnreachable { reachable });
+        }
+
+        Ok(())
+    }
+
+    /// Return the first new voter of the waiting membership change that has not caught up yet.
+    fn lagging_new_voter(&self, members: &BTreeMap<NID, u64>) -> Option<(NID, LogId)> {
+        let voters = self.core.effective_membership.membership.get_ith_config(0)?;
+
+        members.keys().filter(|id| !voters.contains(id)).find_map(|id| match self.nodes.get(id) {
+            Some(node) if node.is_line_rate(&self.core.last_log_id, &self.core.config) => None,
+            Some(node) => Some((*id, node.matched)),
+            None => Some((*id, LogId::new(0, 0))),
+        })
+    }
+
+    /// Propose the waiting membership change if every new voter has caught up.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn try_resume_membership_change(&mut self) {
+        match &self.membership_catch_up {
+            Some(pending) if self.lagging_new_voter(&pending.members).is_none() => {}
+            _ => return,
+        }
+
+        let pending = self.membership_catch_up.take().unwrap();
+
+        tracing::info!(members = ?pending.members, "new voters caught up, propose membership change");
+
+        self.change_membership(pending.members, pending.witnesses, true, pending.tx).await;
+    }
+
+    /// Reject the waiting membership change when a new voter does not catch up before the deadline.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn handle_membership_catch_up_timeout(&mut self) {
+        let lagging = match &self.membership_catch_up {
+            Some(pending) => self.lagging_new_voter(&pending.members),
+            None => return,
+        };
+
+        let (node_id, matched) = match lagging {
+            Some(lagging) => lagging,
+            None => {
+                // Caught up just in time.
+                self.try_resume_membership_change().await;
+                return;
+            }
+        };
+
+        let pending = self.membership_catch_up.take().unwrap();
+
+        tracing::info!(%node_id, %matched, "learner did not catch up, reject membership change");
+
+        let _ = pending.tx.send(Err(ClientWriteError::ChangeMembershipError(
+            ChangeMembershipError::CatchUpTimeout {
+                node_id,
+                matched,
+                distance: self.core.last_log_id.index.saturating_sub(matched.index),
+                timeout: self.core.config.membership_catch_up_timeout(),
+            },
+        )));
+    }
+
+    /// Abort the membership change in progress, see `Raft::abort_membership_change()` for the abortable states.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn abort_membership_change(&mut self, tx: RaftRespTx<LogId, ClientWriteError<NID>>) {
+        // Nothing is proposed yet, just drop it.
+        if let Some(pending) = self.membership_catch_up.take() {
+            tracing::info!(members = ?pending.members, "abort membership change waiting for catch up");
+
+            let _ = pending.tx.send(Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::Aborted,
+            )));
+            let _ = tx.send(Ok(self.core.effective_membership.log_id));
+            return;
+        }
+
+        let membership_log_id = self.core.effective_membership.log_id;
+        let committed = self.core.committed >= membership_log_id;
+
+        let prev_voters = match self.core.effective_membership.membership.get_ith_config(1) {
+            Some(_) if !committed => self.core.effective_membership.membership.get_ith_weights(0).unwrap(),
+            Some(_) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::JointCommitted { membership_log_id },
+                )));
+                return;
+            }
+            None if !committed => {
+                // The uniform config after a committed joint config.
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::JointCommitted { membership_log_id },
+                )));
+                return;
+            }
+            None => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::NotInProgress,
+                )));
+                return;
+            }
+        };
+
+        tracing::info!(%membership_log_id, ?prev_voters, "abort joint config, propose the previous voters");
+
+        // The joint config will be committed along with the config rolling back, do not tell the caller it succeeded.
+        if let Some(req) = self.awaiting_committed.iter_mut().find(|req| req.entry.log_id == membership_log_id) {
+            if let Some(change_tx) = req.tx.take() {
+                let _ = change_tx.send(Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::Aborted,
+                )));
+            }
+        }
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let witnesses = self.core.effective_membership.membership.witnesses().clone();
+        let prev_config = Membership::new_weighted(vec![prev_voters]).with_witnesses(witnesses);
+        let res = self.append_membership_log(prev_config, Some(resp_tx)).await;
+        if let Err(e) = res {
+            tracing::error!("append membership log to abort change error: {:?}", e);
+        }
+
+        tokio::spawn(
+            async move {
+                let res = match resp_rx.await {
+                    Ok(res) => res.map(|resp| resp.log_id),
+                    Err(_) => Err(ClientWriteError::RaftError(RaftError::ShuttingDown)),
+                };
+                let _ = tx.send(res);
+            }
+            .instrument(tracing::debug_span!("abort_membership_change")),
+        );
+    }
+
+    /// Remove a voter at once without joint consensus, see `Raft::force_remove_node()`.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn force_remove_node(
+        &mut self,
+        target: NID,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    ) {
+        match self.core.force_removed_config(target) {
+            Ok(new_config) => self.append_forced_membership(target, new_config, tx).await,
+            Err(err) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(err)));
+            }
+        }
+    }
+
+    /// Append a config built by `force_removed_config()` and commit it with the quorum of itself.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn append_forced_membership(
+        &mut self,
+        target: NID,
+        new_config: Membership<NID>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    ) {
+        tracing::warn!(%target, ?new_config, "force remove node without joint consensus");
+
+        if let Some(pending) = self.membership_catch_up.take() {
+            let _ = pending.tx.send(Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::Aborted,
+            )));
+        }
+
+        // The node is gone and never acknowledges the config removing it, stop replicating to it at once.
+        if self.nodes.remove(&target).is_some() {
+            tracing::info!("removed replication to: {}", target);
+            self.leader_metrics.replication.remove(&target);
+        }
+
+        let res = self.append_membership_log(new_config, Some(tx)).await;
+        if let Err(e) = res {
+            tracing::error!("append membership log to force remove node error: {:?}", e);
+            return;
+        }
+
+        // The logs replicated to a quorum of the new config are committed at once.
+        self.try_commit().await;
+        self.leader_report_metrics();
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, resp_tx), fields(id=%self.core.id))]
     pub async fn append_membership_log(
         &mut self,
-        mem: Membership,
-        resp_tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
+        mem: Membership<NID>,
+        resp_tx: Option<RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>>,
     ) -> Result<(), RaftError> {
-        let payload = ClientWriteRequest::<D>::new_config(mem.clone());
-        let res = self.append_payload_to_log(payload.entry).await;
+        // The voters a uniform config removes are no longer replicated to once it is committed. The learners are not
+        // in any config, and are replicated to as before.
+        let removed = if mem.is_in_joint_consensus() {
+            BTreeSet::new()
+        } else {
+            let prev = self.core.effective_membership.membership.all_nodes();
+            prev.difference(mem.all_nodes()).cloned().collect::<BTreeSet<_>>()
+        };
+
+        let payload = ClientWriteRequest::<D, NID>::new_config(mem.clone());
+        let res = self.append_payload_to_log(payload).await;
 
         // Caveat: membership must be updated before commit check is done with the new config.
-        self.core.effective_membership = EffectiveMembership {
+        self.core.set_effective_membership(EffectiveMembership {
             log_id: self.core.last_log_id,
             membership: mem,
-        };
+        });
+
+        self.update_witness_replication();
 
         self.leader_report_metrics();
 
@@ -235,6 +703,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
         };
 
+        for id in removed {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                tracing::info!("set remove_after_commit for {} = {}", id, entry.log_id.index);
+                node.remove_since = Some(entry.log_id.index);
+            }
+        }
+
         let cr_entry = ClientRequestEntry {
             entry: Arc::new(entry),
             tx: resp_tx,
@@ -250,8 +725,6 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// This is ony called by leader.
     #[tracing::instrument(level = "debug", skip(self))]
     pub(super) fn handle_uniform_consensus_committed(&mut self, log_id: &LogId) {
-        let index = log_id.index;
-
         // Step down if needed.
         if !self.core.effective_membership.membership.contains(&self.core.id) {
             tracing::debug!("raft node is stepping down");
@@ -262,24 +735,9 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return;
         }
 
-        let membership = &self.core.effective_membership.membership;
-
-        let all = membership.all_nodes();
-        for (id, state) in self.nodes.iter_mut() {
-            if all.contains(id) {
-                continue;
-            }
-
-            tracing::info!(
-                "set remove_after_commit for {} = {}, membership: {:?}",
-                id,
-                index,
-                self.core.effective_membership
-            );
-
-            state.remove_since = Some(index)
-        }
+        tracing::debug!(%log_id, membership = ?self.core.effective_membership, "uniform config committed");
 
+        // The voters removed by the config are marked with `remove_since` when it is appended, the learners stay.
         let targets = self.nodes.keys().cloned().collect::<Vec<_>>();
         for target in targets {
             self.try_remove_replication(target);
@@ -288,19 +746,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         self.leader_report_metrics();
     }
 
-    /// Remove a replication if the membership that does not include it has committed.
+    /// Remove a replication if the membership that does not include it has committed, and is replicated to it.
     ///
     /// Return true if removed.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub fn try_remove_replication(&mut self, target: u64) -> bool {
-        tracing::debug!(target, "try_remove_replication");
+    pub fn try_remove_replication(&mut self, target: NID) -> bool {
+        tracing::debug!(%target, "try_remove_replication");
 
         {
             let n = self.nodes.get(&target);
 
             if let Some(n) = n {
                 if let Some(since) = n.remove_since {
-                    if n.matched.index < since {
+                    if n.matched.index < since || self.core.committed.index < since {
                         return false;
                     }
                 } else {
@@ -318,3 +776,107 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         true
     }
 }
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
+    /// Build the config with `target` removed from every config of the current one, see `Raft::force_remove_node()`.
+    pub(super) fn force_removed_config(&self, target: NID) -> Result<Membership<NID>, ChangeMembershipError<NID>> {
+        let curr = &self.effective_membership.membership;
+
+        if target == self.id || !curr.voters().contains(&target) || !curr.voters().contains(&self.id) {
+            return Err(ChangeMembershipError::InvalidForceRemove { node_id: target });
+        }
+
+        // Remove it from every config, a config of only this node is dropped.
+        let configs = (0..curr.get_configs().len())
+            .filter_map(|i| curr.get_ith_weights(i))
+            .map(|mut weights| {
+                weights.remove(&target);
+                weights
+            })
+            .filter(|weights| !weights.is_empty())
+            .collect::<Vec<_>>();
+
+        for weights in configs.iter() {
+            check_weights(weights)?;
+        }
+
+        let mut witnesses = curr.witnesses().clone();
+        witnesses.remove(&target);
+        Ok(Membership::new_weighted(configs).with_witnesses(witnesses))
+    }
+
+    /// Override the membership of this follower or candidate locally with `target` removed, and start an election
+    /// with it, see `Raft::force_remove_node()`.
+    ///
+    /// The config is appended to the log once this node is elected. Until then it is kept only in memory.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn force_remove_node_locally(
+        &mut self,
+        target: NID,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    ) {
+        let new_config = match self.force_removed_config(target) {
+            Ok(x) => x,
+            Err(err) => {
+                let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(err)));
+                return;
+            }
+        };
+
+        tracing::warn!(%target, ?new_config, "override membership locally to force remove node, then elect itself");
+
+        if let Some(prev) = self.forced_membership.take() {
+            let _ = prev.tx.send(Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::Aborted,
+            )));
+        }
+
+        self.set_effective_membership(EffectiveMembership {
+            log_id: self.effective_membership.log_id,
+            membership: new_config.clone(),
+        });
+        self.forced_membership = Some(ForcedMembership {
+            target,
+            membership: new_config,
+            tx,
+        });
+
+        self.set_target_state(State::Candidate);
+        self.report_metrics(Update::Ignore);
+    }
+
+    /// Fail a pending `Raft::force_remove_node()` once this node is not going to be the leader, and restore the
+    /// membership from the storage.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn abandon_forced_membership(&mut self) -> RaftResult<()> {
+        let forced = match self.forced_membership.take() {
+            None => return Ok(()),
+            Some(x) => x,
+        };
+
+        tracing::warn!(target = %forced.target, "abandon the locally overridden membership, not elected");
+        let _ = forced.tx.send(Err(ClientWriteError::not_leader(self.current_leader)));
+
+        let membership = self.storage.get_membership_config().await.map_err(|err| self.map_storage_error(err))?;
+        self.set_effective_membership(membership);
+        Ok(())
+    }
+}
+
+/// Check the vote weights of a config: no set of voters has more than half of a total weight of 0, thus nothing
+/// could ever be committed, and a total weight beyond `u64` is rejected as well.
+fn check_weights<NID: RaftNodeId>(weights: &BTreeMap<NID, u64>) -> Result<(), ChangeMembershipError<NID>> {
+    let total = weights.values().try_fold(0u64, |acc, w| acc.checked_add(*w));
+
+    match total {
+        Some(0) => Err(ChangeMembershipError::NoWeightedMajority {
+            weights: weights.clone(),
+        }),
+        Some(_) => Ok(()),
+        None => Err(ChangeMembershipError::WeightOverflow {
+            weights: weights.clone(),
+        }),
+    }
+}
@@ -1,4 +1,5 @@
 use crate::core::apply_to_state_machine;
+use crate::core::retry_transient;
 use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
@@ -14,17 +15,20 @@ use crate::LogId;
 use crate::MessageSummary;
 use crate::RaftError;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
     /// An RPC invoked by the leader to replicate log entries (§5.3); also used as heartbeat (§5.2).
     ///
     /// See `receiver implementation: AppendEntries RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level = "debug", skip(self, msg))]
     pub(super) async fn handle_append_entries_request(
         &mut self,
-        msg: AppendEntriesRequest<D>,
+        msg: AppendEntriesRequest<D, NID>,
     ) -> RaftResult<AppendEntriesResponse> {
         tracing::debug!(%self.last_log_id, %self.last_applied, msg=%msg.summary(), "handle_append_entries_request");
 
@@ -41,6 +45,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
 
         self.update_next_election_timeout(true);
+        self.resume_elections();
 
         // Caveat: Because we can not just delete `log[prev_log_id.index..]`, (which results in loss of committed
         // entry), the commit index must be update only after append-entries
@@ -63,23 +68,18 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         tracing::debug!("start to check and update to latest term/leader");
         {
-            let mut report_metrics = false;
-
             if msg.term > self.current_term {
                 self.update_current_term(msg.term, None);
                 self.save_hard_state().await?;
-                report_metrics = true;
             }
 
             // Update current leader if needed.
             if self.current_leader.as_ref() != Some(&msg.leader_id) {
                 self.update_current_leader(UpdateCurrentLeader::OtherNode(msg.leader_id));
-                report_metrics = true;
             }
 
-            if report_metrics {
-                self.report_metrics(Update::Ignore);
-            }
+            // `millis_since_last_heartbeat` is reset by every AppendEntries, it is published only if it changes.
+            self.report_metrics(Update::Ignore);
         }
 
         // Transition to follower state if needed.
@@ -87,6 +87,30 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.set_target_state(State::Follower);
         }
 
+        self.check_leader_commit(msg.term, msg.leader_commit)?;
+
+        // Fast path for a heartbeat to an up to date node, which is the common case of an idle cluster.
+        // `prev_log_id` is the last log of this node, thus the logs are consistent without reading the storage, and
+        // there is nothing to append, commit or apply. Except saving a greater term, a heartbeat then costs no storage
+        // access, while the full path below reads the entry at `prev_log_id` if it is not committed.
+        if msg_entries.is_empty()
+            && msg.prev_log_id == self.last_log_id
+            && valid_committed == self.committed
+            && self.has_completed_initial_replication_to_sm
+        {
+            tracing::debug!(%msg.prev_log_id, "heartbeat to an up to date node");
+
+            if valid_committed == msg.leader_commit {
+                self.last_leader_sync = Some(self.clock.now());
+            }
+
+            return Ok(AppendEntriesResponse {
+                term: self.current_term,
+                matched: Some(msg.prev_log_id),
+                conflict: None,
+            });
+        }
+
         tracing::debug!("begin log consistency check");
 
         // There are 5 cases a prev_log_id could have:
@@ -94,7 +118,39 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         //              +----------------+------------------------+
         //              ` 0              ` last_applied           ` last_log_id
 
-        return self.append_apply_log_entries(&msg.prev_log_id, msg_entries, valid_committed).await;
+        let resp = self.append_apply_log_entries(&msg.prev_log_id, msg_entries, valid_committed).await?;
+
+        // The state machine is in sync with the leader only if every entry the leader has committed is applied.
+        if resp.success() && valid_committed == msg.leader_commit {
+            self.last_leader_sync = Some(self.clock.now());
+        }
+
+        Ok(resp)
+    }
+
+    /// Check the leader of `term` does not send a `leader_commit` less than one it sent before.
+    ///
+    /// A new leader may know of fewer committed logs than its predecessor, thus only the commit indexes sent by the
+    /// same leader are compared. It returns an error only if `Config::reject_commit_regression` is enabled.
+    fn check_leader_commit(&mut self, term: u64, leader_commit: LogId) -> RaftResult<()> {
+        match self.leader_commit_seen {
+            Some((seen_term, seen)) if seen_term == term && leader_commit < seen => {
+                tracing::warn!(term, %leader_commit, %seen, "leader commit index regressed");
+
+                if self.config.reject_commit_regression {
+                    return Err(RaftError::CommitIndexRegression {
+                        term,
+                        leader_commit,
+                        seen,
+                    });
+                }
+            }
+            _ => {
+                self.leader_commit_seen = Some((term, leader_commit));
+            }
+        }
+
+        Ok(())
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -106,6 +162,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         //           RaftStorage should only provides the least basic APIs.
 
         self.storage.delete_logs_from(start..).await.map_err(|err| self.map_storage_error(err))?;
+        self.log_cache.truncate(start);
 
         self.last_log_id = self.get_log_id(start - 1).await?;
 
@@ -124,7 +181,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(self.last_applied);
         }
 
-        let entries = self.storage.get_log_entries(index..=index).await.map_err(|err| self.map_storage_error(err))?;
+        let entries = retry_transient(&*self.clock, &self.config, || {
+            self.storage.get_log_entries(index..=index)
+        })
+        .await
+        .map_err(|err| self.map_storage_error(err))?;
 
         let entry = entries
             .first()
@@ -173,7 +234,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If log 5 is committed by R1, and log 3 is not removed, R5 in future could become a new leader and overrides log
     /// 5 on R3.
     #[tracing::instrument(level="trace", skip(self, msg_entries), fields(msg_entries=%msg_entries.summary()))]
-    async fn delete_inconsistent_log<'s, 'e>(&'s mut self, msg_entries: &'e [Entry<D>]) -> RaftResult<()> {
+    async fn delete_inconsistent_log<'s, 'e>(&'s mut self, msg_entries: &'e [Entry<D, NID>]) -> RaftResult<()> {
         // all msg_entries are inconsistent logs
 
         tracing::debug!(msg_entries=%msg_entries.summary(), "try to delete_inconsistent_log");
@@ -215,7 +276,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(LogId { term: 0, index: 0 });
         }
 
-        let entries = self.storage.get_log_entries(start..=start).await.map_err(|err| self.map_storage_error(err))?;
+        let entries = retry_transient(&*self.clock, &self.config, || {
+            self.storage.get_log_entries(start..=start)
+        })
+        .await
+        .map_err(|err| self.map_storage_error(err))?;
 
         let log_id = entries.first().unwrap().log_id;
 
@@ -228,7 +293,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     async fn append_apply_log_entries(
         &mut self,
         prev_log_id: &LogId,
-        entries: &[Entry<D>],
+        entries: &[Entry<D, NID>],
         committed: LogId,
     ) -> RaftResult<AppendEntriesResponse> {
         let matching = self.does_log_id_match(prev_log_id).await?;
@@ -246,6 +311,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 self.delete_logs(prev_log_id.index).await?;
             }
 
+            self.conflicts_reported += 1;
+            self.report_metrics(Update::Ignore);
+
             return Ok(AppendEntriesResponse {
                 term: self.current_term,
                 matched: None,
@@ -280,7 +348,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // commit index must not > last_log_id.index
         // This is guaranteed by caller.
-        self.committed = committed;
+        // It must not go backward either, e.g., when the leader resends logs before the committed one.
+        if committed > self.committed {
+            self.committed = committed;
+
+            // Report the committed log before applying it, which may take a while.
+            self.report_metrics(Update::Ignore);
+        }
 
         self.replicate_to_state_machine_if_needed().await?;
 
@@ -299,9 +373,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// The entries in request that are matches local ones does not need to be append again.
     /// Filter them out.
     pub async fn skip_matching_entries<'s, 'e>(
-        &'s self,
-        entries: &'e [Entry<D>],
-    ) -> RaftResult<(usize, &'e [Entry<D>])> {
+        &'s mut self,
+        entries: &'e [Entry<D, NID>],
+    ) -> RaftResult<(usize, &'e [Entry<D, NID>])> {
         let l = entries.len();
 
         for i in 0..l {
@@ -313,7 +387,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
 
             // TODO(xp): this is a naive impl. Batch loading entries from storage.
-            let log = self.storage.try_get_log_entry(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
+            let log = self.storage.try_get_log_entry(index).await.map_err(|err| self.map_storage_error(err))?;
 
             if let Some(local) = log {
                 if local.log_id == log_id {
@@ -331,7 +405,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// This way to check if the entries in append-entries request is consecutive with local logs.
     /// Raft only accept consecutive logs to be appended.
-    pub async fn does_log_id_match(&self, remote_log_id: &LogId) -> RaftResult<bool> {
+    pub async fn does_log_id_match(&mut self, remote_log_id: &LogId) -> RaftResult<bool> {
         let index = remote_log_id.index;
 
         // Committed entries are always safe and are consistent to a valid leader.
@@ -339,7 +413,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(true);
         }
 
-        let log = self.storage.try_get_log_entry(index).await.map_err(|err| RaftError::RaftStorage(err.into()))?;
+        let log = self.storage.try_get_log_entry(index).await.map_err(|err| self.map_storage_error(err))?;
         tracing::debug!(
             "check log id matching: local: {:?} remote: {}",
             log.as_ref().map(|x| x.log_id),
@@ -360,7 +434,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Configuration changes are also detected and applied here. See `configuration changes`
     /// in the raft-essentials.md in this repo.
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn append_log_entries(&mut self, entries: &[Entry<D>]) -> RaftResult<()> {
+    async fn append_log_entries(&mut self, entries: &[Entry<D, NID>]) -> RaftResult<()> {
         if entries.is_empty() {
             return Ok(());
         }
@@ -388,7 +462,18 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // Replicate entries to log (same as append, but in follower mode).
         let entry_refs = entries.iter().collect::<Vec<_>>();
-        self.storage.append_to_log(&entry_refs).await.map_err(|err| self.map_storage_error(err))?;
+        retry_transient(&*self.clock, &self.config, || self.storage.append_to_log(&entry_refs))
+            .await
+            .map_err(|err| self.map_storage_error(err))?;
+        self.log_cache.append(&entry_refs);
+
+        // Make the entries durable once for the whole batch, before responding to the leader.
+        if self.config.durability.sync_log() {
+            retry_transient(&*self.clock, &self.config, || self.storage.flush())
+                .await
+                .map_err(|err| self.map_storage_error(err))?;
+        }
+
         if let Some(entry) = entries.last() {
             self.last_log_id = entry.log_id;
         }
@@ -400,7 +485,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Very importantly, this routine must not block the main control loop main task, else it
     /// may cause the Raft leader to timeout the requests to this node.
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn replicate_to_state_machine_if_needed(&mut self) -> Result<(), RaftError> {
+    pub(super) async fn replicate_to_state_machine_if_needed(&mut self) -> Result<(), RaftError> {
         tracing::debug!("replicate_to_sm_if_needed: last_applied: {}", self.last_applied,);
 
         // Perform initial replication to state machine if needed.
@@ -436,13 +521,21 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         let entries_refs: Vec<_> = entries.iter().collect();
 
-        apply_to_state_machine(self.storage.clone(), &entries_refs, self.config.max_applied_log_to_keep)
-            .await
-            .map_err(|e| self.map_storage_error(e))?;
+        apply_to_state_machine(
+            self.storage.clone(),
+            &self.sm_applied,
+            &entries_refs,
+            self.config.max_logs_to_keep_on_apply(),
+            self.config.max_apply_batch_size,
+            &self.tx_events,
+        )
+        .await
+        .map_err(|e| self.map_storage_error(e))?;
 
         self.last_applied = last_log_id;
 
         self.report_metrics(Update::Ignore);
+        self.refresh_log_storage_size().await;
         self.trigger_log_compaction_if_needed(false);
 
         Ok(())
@@ -468,18 +561,28 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // Fetch the series of entries which must be applied to the state machine, then apply them.
 
-        let entries = storage.get_log_entries(start..stop).await.map_err(|e| self.map_storage_error(e))?;
+        let entries = retry_transient(&*self.clock, &self.config, || storage.get_log_entries(start..stop))
+            .await
+            .map_err(|e| self.map_storage_error(e))?;
 
         let new_last_applied = entries.last().unwrap();
 
         let data_entries: Vec<_> = entries.iter().collect();
 
-        apply_to_state_machine(storage, &data_entries, self.config.max_applied_log_to_keep)
-            .await
-            .map_err(|e| self.map_storage_error(e))?;
+        apply_to_state_machine(
+            storage,
+            &self.sm_applied,
+            &data_entries,
+            self.config.max_logs_to_keep_on_apply(),
+            self.config.max_apply_batch_size,
+            &self.tx_events,
+        )
+        .await
+        .map_err(|e| self.map_storage_error(e))?;
 
         self.last_applied = new_last_applied.log_id;
         self.report_metrics(Update::Ignore);
+        self.refresh_log_storage_size().await;
         self.trigger_log_compaction_if_needed(false);
 
         Ok(())
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use tracing::Instrument;
+
+use crate::core::apply_to_state_machine;
+use crate::core::client::ClientRequestEntry;
+use crate::error::RaftError;
+use crate::error::RaftResult;
+use crate::event::EventTx;
+use crate::raft::Entry;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::LogId;
+use crate::RaftNodeId;
+use crate::RaftStorage;
+use crate::StorageError;
+
+/// The outcome of applying a batch of committed entries, reported by the apply worker to the leader.
+pub(super) struct Applied<D: AppData, R: AppDataResponse, NID: RaftNodeId> {
+    /// The last log id that has been handed to the state machine.
+    pub last_applied: LogId,
+
+    /// The applied requests along with their results, to respond to the clients.
+    pub results: Vec<(ClientRequestEntry<D, R, NID>, RaftResult<R>)>,
+
+    /// The error returned by the storage, if the batch failed.
+    pub error: Option<StorageError<NID>>,
+}
+
+/// Sends a request to apply, along with the last log id submitted before it.
+type ApplyTx<D, R, NID> = mpsc::UnboundedSender<(LogId, ClientRequestEntry<D, R, NID>)>;
+
+/// A task applying the entries committed by a leader to the state machine, in log order.
+///
+/// The leader submits every committed entry and goes on committing, while the worker applies them. Entries submitted
+/// while the worker is busy are applied in one batch. The leader responds to the clients once it sees a batch applied.
+///
+/// An inline worker spawns no task: the leader applies every entry itself with `apply_now()`.
+pub(super) struct ApplyWorker<D: AppData, R: AppDataResponse, NID: RaftNodeId> {
+    /// Submits requests to the worker, it is `None` once closed.
+    tx_apply: Option<ApplyTx<D, R, NID>>,
+
+    /// The stream of batches that have been applied.
+    pub rx_applied: mpsc::UnboundedReceiver<Applied<D, R, NID>>,
+
+    /// The last log id submitted to the worker.
+    pub submitted: LogId,
+
+    /// The last log id the state machine is at, locked while applying.
+    sm_applied: Arc<Mutex<LogId>>,
+
+    /// Reports the logs purged after applying.
+    tx_events: EventTx<NID>,
+}
+
+impl<D: AppData, R: AppDataResponse, NID: RaftNodeId> ApplyWorker<D, R, NID> {
+    /// Create a worker that applies entries after `last_applied`, and spawn its task unless it is `inline`.
+    pub fn new<S: RaftStorage<D, R, NID>>(
+        storage: Arc<S>,
+        last_applied: LogId,
+        sm_applied: Arc<Mutex<LogId>>,
+        max_keep: u64,
+        max_batch: u64,
+        inline: bool,
+        tx_events: EventTx<NID>,
+    ) -> Self {
+        let (tx_apply, rx_apply) = mpsc::unbounded_channel();
+        let (tx_applied, rx_applied) = mpsc::unbounded_channel();
+
+        if inline {
+            // Nothing is ever submitted, and `rx_applied` is closed at once.
+            return Self {
+                tx_apply: None,
+                rx_applied,
+                submitted: last_applied,
+                sm_applied,
+                tx_events,
+            };
+        }
+
+        tokio::spawn(
+            apply_loop(
+                storage,
+                sm_applied.clone(),
+                max_keep,
+                max_batch,
+                tx_events.clone(),
+                rx_apply,
+                tx_applied,
+            )
+            .instrument(tracing::debug_span!("spawn-apply-worker")),
+        );
+
+        Self {
+            tx_apply: Some(tx_apply),
+            rx_applied,
+            submitted: last_applied,
+            sm_applied,
+            tx_events,
+        }
+    }
+
+    /// Submit a committed entry to apply.
+    pub fn submit(&mut self, req: ClientRequestEntry<D, R, NID>) {
+        let prev = self.submitted;
+        self.submitted = req.entry.log_id;
+
+        if let Some(tx) = &self.tx_apply {
+            let _ = tx.send((prev, req));
+        }
+    }
+
+    /// Apply a committed entry without handing it to the worker.
+    ///
+    /// It must be called only when every submitted entry is applied.
+    pub async fn apply_now<S: RaftStorage<D, R, NID>>(
+        &mut self,
+        storage: Arc<S>,
+        req: ClientRequestEntry<D, R, NID>,
+        max_keep: u64,
+        max_batch: u64,
+    ) -> Applied<D, R, NID> {
+        let prev = self.submitted;
+        self.submitted = req.entry.log_id;
+
+        apply_reqs(
+            storage,
+            &self.sm_applied,
+            &prev,
+            vec![req],
+            max_keep,
+            max_batch,
+            &self.tx_events,
+        )
+        .await
+    }
+
+    /// Stop accepting entries. The worker quits after applying all submitted entries.
+    pub fn close(&mut self) {
+        self.tx_apply = None;
+    }
+}
+
+async fn apply_loop<D, R, S, NID>(
+    storage: Arc<S>,
+    sm_applied: Arc<Mutex<LogId>>,
+    max_keep: u64,
+    max_batch: u64,
+    tx_events: EventTx<NID>,
+    mut rx_apply: mpsc::UnboundedReceiver<(LogId, ClientRequestEntry<D, R, NID>)>,
+    tx_applied: mpsc::UnboundedSender<Applied<D, R, NID>>,
+) where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+{
+    while let Some((last_applied, req)) = rx_apply.recv().await {
+        let mut reqs = vec![req];
+        while let Ok((_, req)) = rx_apply.try_recv() {
+            reqs.push(req);
+        }
+
+        let applied = apply_reqs(
+            storage.clone(),
+            &sm_applied,
+            &last_applied,
+            reqs,
+            max_keep,
+            max_batch,
+            &tx_events,
+        )
+        .await;
+        let is_io = matches!(applied.error, Some(StorageError::IO { .. }));
+
+        let _ = tx_applied.send(applied);
+
+        if is_io {
+            // The leader is shutting down, nothing more can be applied.
+            return;
+        }
+    }
+}
+
+/// Apply `reqs` that follow `last_applied`, and build the result to report to the leader.
+async fn apply_reqs<D, R, S, NID>(
+    storage: Arc<S>,
+    sm_applied: &Mutex<LogId>,
+    last_applied: &LogId,
+    reqs: Vec<ClientRequestEntry<D, R, NID>>,
+    max_keep: u64,
+    max_batch: u64,
+    tx_events: &EventTx<NID>,
+) -> Applied<D, R, NID>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+{
+    let res = apply_batch(storage, sm_applied, last_applied, &reqs, max_keep, max_batch, tx_events).await;
+
+    // Like a synchronous apply, a failed batch is still regarded as applied.
+    let last_applied = reqs.last().unwrap().entry.log_id;
+
+    let (results, error) = match res {
+        Ok(resps) => (reqs.into_iter().zip(resps.into_iter().map(Ok)).collect(), None),
+        Err(err) => {
+            let results = reqs
+                .into_iter()
+                .map(|req| {
+                    let raft_err = RaftError::RaftStorage(anyhow!("{}", err));
+                    (req, Err(raft_err))
+                })
+                .collect();
+            (results, Some(err))
+        }
+    };
+
+    Applied {
+        last_applied,
+        results,
+        error,
+    }
+}
+
+/// Apply the entries of `reqs`, and any entries not yet applied before each of them.
+///
+/// It returns the responses of `reqs`.
+#[tracing::instrument(level = "debug", skip(storage, sm_applied, reqs, tx_events), fields(n_reqs=reqs.len()))]
+async fn apply_batch<D, R, S, NID>(
+    storage: Arc<S>,
+    sm_applied: &Mutex<LogId>,
+    last_applied: &LogId,
+    reqs: &[ClientRequestEntry<D, R, NID>],
+    max_keep: u64,
+    max_batch: u64,
+    tx_events: &EventTx<NID>,
+) -> Result<Vec<R>, StorageError<NID>>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+{
+    // Entries that are committed before this node becomes leader are applied along with the first request, so that
+    // every request is responded with the result of its own entry.
+    let mut missing = vec![];
+    let mut next_index = last_applied.index + 1;
+    for req in reqs.iter() {
+        let index = req.entry.log_id.index;
+        if index > next_index {
+            missing.push(storage.get_log_entries(next_index..index).await?);
+        } else {
+            missing.push(vec![]);
+        }
+        next_index = index + 1;
+    }
+
+    let mut entries: Vec<&Entry<D, NID>> = vec![];
+    let mut is_req = vec![];
+    for (req, ms) in reqs.iter().zip(missing.iter()) {
+        for ent in ms.iter() {
+            entries.push(ent);
+            is_req.push(false);
+        }
+        entries.push(&req.entry);
+        is_req.push(true);
+    }
+
+    let resps = apply_to_state_machine(storage, sm_applied, &entries, max_keep, max_batch, tx_events).await?;
+
+    let resps = resps
+        .into_iter()
+        .zip(is_req.into_iter())
+        .filter(|(_, is_req)| *is_req)
+        .map(|(resp, _)| resp)
+        .collect();
+
+    Ok(resps)
+}
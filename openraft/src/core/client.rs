@@ -1,24 +1,31 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use anyhow::anyhow;
 use futures::future::TryFutureExt;
 use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
+use maplit::btreeset;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing::Instrument;
 
-use crate::core::apply_to_state_machine;
+use crate::config::ReadStrategy;
+use crate::core::apply_worker::Applied;
+use crate::core::retry_transient;
 use crate::core::LeaderState;
 use crate::core::State;
+use crate::core::UpdateCurrentLeader;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
+use crate::error::ForwardToLeader;
 use crate::error::RaftError;
 use crate::error::RaftResult;
-use crate::quorum;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
+use crate::raft::ClientWriteWithTx;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
 use crate::raft::RaftRespTx;
@@ -28,28 +35,31 @@ use crate::AppDataResponse;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::StorageError;
 
 /// A wrapper around a ClientRequest which has been transformed into an Entry, along with its response channel.
-pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse> {
+pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse, NID: RaftNodeId> {
     /// The Arc'd entry of the ClientRequest.
     ///
     /// This value is Arc'd so that it may be sent across thread boundaries for replication
     /// without having to clone the data payload itself.
-    pub entry: Arc<Entry<D>>,
+    pub entry: Arc<Entry<D, NID>>,
 
     /// The response channel for the request.
-    pub tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
+    pub tx: Option<RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>>,
 }
 
-impl<D: AppData, R: AppDataResponse> MessageSummary for ClientRequestEntry<D, R> {
+impl<D: AppData, R: AppDataResponse, NID: RaftNodeId> MessageSummary for ClientRequestEntry<D, R, NID> {
     fn summary(&self) -> String {
         format!("entry:{}", self.entry.summary())
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Commit the initial entry which new leaders are obligated to create when first coming to power, per §8.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(super) async fn commit_initial_leader_entry(&mut self) -> RaftResult<()> {
@@ -57,14 +67,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // config, else a blank payload.
         let last_index = self.core.last_log_id.index;
 
-        let req: ClientWriteRequest<D> = if last_index == 0 {
+        let req: ClientWriteRequest<D, NID> = if last_index == 0 {
             ClientWriteRequest::new_config(self.core.effective_membership.membership.clone())
         } else {
             ClientWriteRequest::new_blank_payload()
         };
 
         // Commit the initial payload to the cluster.
-        let entry = self.append_payload_to_log(req.entry).await?;
+        let entry = self.append_payload_to_log(req).await?;
         self.core.last_log_id.term = self.core.current_term; // This only ever needs to be updated once per term.
 
         self.leader_report_metrics();
@@ -91,43 +101,40 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// request (its information may be stale if a more recent leader has been elected). Raft
     /// handles this by having the leader exchange heartbeat messages with a majority of the
     /// cluster before responding to read-only requests.
+    ///
+    /// On success it responds with the read index: the log id the state machine has to apply upto before serving a
+    /// linearizable read. It is the commit index when this request is received. If the leader has not yet committed
+    /// an entry in its term, its commit index may be stale, and the last log id is used instead, which includes the
+    /// leader's initial entry.
+    ///
+    /// With `ReadStrategy::LeaseRead`, it responds at once without exchanging heartbeats if the leader holds a lease.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) async fn handle_client_read_request(&mut self, tx: RaftRespTx<(), ClientReadError>) {
-        // Setup sentinel values to track when we've received majority confirmation of leadership.
-        let mut c0_confirmed = 0usize;
-
-        let mems = &self.core.effective_membership.membership;
-
-        // Will never be zero, as we don't allow it when proposing config changes.
-        let len_members = mems.get_ith_config(0).unwrap().len();
-
-        let c0_needed = quorum::majority_of(len_members);
-
-        let mut c1_confirmed = 0usize;
-        let mut c1_needed = 0usize;
-
-        let second = mems.get_ith_config(1);
-
-        if let Some(joint_members) = second {
-            let len = joint_members.len(); // Will never be zero, as we don't allow it when proposing config changes.
-            c1_needed = quorum::majority_of(len);
+    pub(super) async fn handle_client_read_request(&mut self, tx: RaftRespTx<LogId, ClientReadError<NID>>) {
+        let read_log_id = if self.core.committed.term == self.core.current_term {
+            self.core.committed
+        } else {
+            self.core.last_log_id
+        };
 
-            if joint_members.contains(&self.core.id) {
-                c1_confirmed += 1;
-            }
+        if self.has_read_lease() {
+            tracing::debug!(%read_log_id, "serve read with the leader lease");
+            let _ = tx.send(Ok(read_log_id));
+            return;
         }
 
-        // Increment confirmations for self, including post-joint-consensus config if applicable.
-        c0_confirmed += 1;
+        // Track the voters that confirmed the leadership, until they constitute a quorum by vote weight.
+        // This node confirms itself, even if it is not a voter of the second config.
+        let mut confirmed = btreeset! {self.core.id};
 
         // If we already have all needed confirmations — which would be the case for single node
         // clusters — then respond.
-        if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-            let _ = tx.send(Ok(()));
+        if self.core.effective_membership.membership.is_majority(&confirmed) {
+            let _ = tx.send(Ok(read_log_id));
             return;
         }
 
         // Spawn parallel requests, all with the standard timeout for heartbeats.
+        let sent_at = self.core.clock.now();
         let mut pending = FuturesUnordered::new();
         let all_members = self.core.effective_membership.membership.all_nodes();
         for (id, node) in self.nodes.iter() {
@@ -140,10 +147,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 prev_log_id: node.matched,
                 entries: vec![],
                 leader_commit: self.core.committed,
+                compressed_entries: None,
             };
             let target = *id;
             let network = self.core.network.clone();
-            let ttl = Duration::from_millis(self.core.config.heartbeat_interval);
+            let ttl = self.core.config.append_entries_ttl();
             let task = tokio::spawn(
                 async move {
                     match timeout(ttl, network.send_append_entries(target, rpc)).await {
@@ -154,7 +162,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 }
                 .instrument(tracing::debug_span!("spawn")),
             )
-            .map_err(move |err| (*id, err));
+            .map_err(move |err| (target, err));
             pending.push(task);
         }
 
@@ -164,11 +172,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             let (target, data) = match res {
                 Ok(Ok(res)) => res,
                 Ok(Err((target, err))) => {
-                    tracing::error!(target, error=%err, "timeout while confirming leadership for read request");
+                    tracing::error!(%target, error=%err, "timeout while confirming leadership for read request");
                     continue;
                 }
                 Err((target, err)) => {
-                    tracing::error!(target, "{}", err);
+                    tracing::error!(target = %target, "{}", err);
                     continue;
                 }
             };
@@ -176,24 +184,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             // If we receive a response with a greater term, then revert to follower and abort this request.
             if data.term != self.core.current_term {
                 self.core.update_current_term(data.term, None);
+                if let Err(err) = self.core.save_hard_state().await {
+                    let _ = tx.send(Err(ClientReadError::RaftError(err)));
+                    return;
+                }
+                self.core.update_current_leader(UpdateCurrentLeader::Unknown);
                 self.core.set_target_state(State::Follower);
+
+                let _ = tx.send(Err(ClientReadError::ForwardToLeader(ForwardToLeader {
+                    leader_id: self.core.current_leader,
+                })));
+                return;
             }
 
             // If the term is the same, then it means we are still the leader.
-            if self.core.effective_membership.membership.get_ith_config(0).unwrap().contains(&target) {
-                c0_confirmed += 1;
-            }
+            self.handle_acked(target, sent_at, self.core.clock.now());
 
-            let second = self.core.effective_membership.membership.get_ith_config(1);
+            confirmed.insert(target);
 
-            if let Some(joint) = second {
-                if joint.contains(&target) {
-                    c1_confirmed += 1;
-                }
-            }
-
-            if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-                let _ = tx.send(Ok(()));
+            if self.core.effective_membership.membership.is_majority(&confirmed) {
+                let _ = tx.send(Ok(read_log_id));
                 return;
             }
         }
@@ -205,20 +215,137 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         )))));
     }
 
+    /// Whether this leader holds a lease to serve a linearizable read without confirming its leadership.
+    ///
+    /// See `ReadStrategy::LeaseRead`.
+    fn has_read_lease(&self) -> bool {
+        if self.core.config.read_strategy != ReadStrategy::LeaseRead {
+            return false;
+        }
+
+        match self.lease_expiry() {
+            Some(expiry) => self.core.clock.now() < expiry,
+            None => false,
+        }
+    }
+
+    /// How long this leader still holds the lease, zero if it expired.
+    pub(super) fn lease_remaining(&self) -> Duration {
+        match self.lease_expiry() {
+            Some(expiry) => expiry.saturating_duration_since(self.core.clock.now()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// When the lease of this leader expires, or `None` if it holds no lease.
+    ///
+    /// The lease starts when the latest AppendEntries accepted by a quorum was sent, and lasts for
+    /// `election_timeout_min` less `max_clock_drift`: no other node can be elected before it expires, even if its
+    /// clock runs somewhat fast. A leader transferring its leadership holds no lease, since the target may be elected
+    /// at once.
+    fn lease_expiry(&self) -> Option<Instant> {
+        let lease_start = self.lease_start()?;
+        let config = &self.core.config;
+        let lease = config.election_timeout_min.saturating_sub(config.max_clock_drift);
+        Some(lease_start + Duration::from_millis(lease))
+    }
+
+    /// When the latest AppendEntries accepted by a quorum was sent, i.e., the last time this node is known to be the
+    /// leader. `None` if there is no such time or if it is transferring its leadership.
+    fn lease_start(&self) -> Option<Instant> {
+        if self.leadership_transfer.is_some() {
+            return None;
+        }
+
+        let now = self.core.clock.now();
+
+        let mut acks = BTreeMap::new();
+        for id in self.core.effective_membership.membership.all_nodes().iter() {
+            let ack = if *id == self.core.id {
+                Some(now)
+            } else {
+                self.nodes.get(id).and_then(|x| x.last_ack)
+            };
+
+            if let Some(t) = ack {
+                acks.insert(*id, t);
+            }
+        }
+
+        let lease_start = self.core.effective_membership.membership.greatest_majority_value(&acks)?;
+
+        Some(*lease_start)
+    }
+
+    /// Respond to a local read if this node is known to be the leader within `max_staleness`.
+    ///
+    /// A leader deposed without knowing it still believes it is the leader, thus it serves a local read only if it
+    /// holds a lease, or a quorum has confirmed its leadership within `max_staleness`. Otherwise it responds with
+    /// `Stale`.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn handle_read_local_request(
+        &self,
+        max_staleness: Duration,
+        tx: RaftRespTx<LogId, ClientReadError<NID>>,
+    ) {
+        let fresh = self.has_read_lease()
+            || match self.lease_start() {
+                Some(t) => self.core.clock.now().saturating_duration_since(t) <= max_staleness,
+                None => false,
+            };
+
+        let res = if fresh {
+            Ok(self.core.last_applied)
+        } else {
+            Err(ClientReadError::Stale {
+                leader_id: self.core.current_leader,
+                max_staleness,
+            })
+        };
+        let _ = tx.send(res);
+    }
+
     /// Handle client write requests.
     #[tracing::instrument(level = "trace", skip(self, tx), fields(rpc=%rpc.summary()))]
     pub(super) async fn handle_client_write_request(
         &mut self,
-        rpc: ClientWriteRequest<D>,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        rpc: ClientWriteRequest<D, NID>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
     ) {
-        let entry = match self.append_payload_to_log(rpc.entry).await {
+        let request_id = rpc.request_id.clone();
+
+        let tx = match &request_id {
+            None => tx,
+            Some(id) => match self.client_sessions.check(id, tx) {
+                Some(tx) => tx,
+                None => return,
+            },
+        };
+
+        let rejected = match self.payload_too_large(&rpc.entry) {
+            Some(err) => Some(err),
+            None if self.uncommitted_room() == 0 => Some(self.overloaded()),
+            None => None,
+        };
+
+        if let Some(err) = rejected {
+            if let Some(id) = &request_id {
+                self.client_sessions.abort(id, &err.to_string());
+            }
+            let _ = tx.send(Err(err));
+            return;
+        }
+
+        let entry = match self.append_payload_to_log(rpc).await {
             Ok(entry) => ClientRequestEntry {
                 entry: Arc::new(entry),
                 tx: Some(tx),
             },
 
             Err(err) => {
+                if let Some(id) = &request_id {
+                    self.client_sessions.abort(id, &err.to_string());
+                }
                 let _ = tx.send(Err(ClientWriteError::RaftError(err)));
                 return;
             }
@@ -229,22 +356,183 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         self.replicate_client_request(entry).await;
     }
 
-    /// Transform the given payload into an entry, assign an index and term, and append the entry to the log.
-    #[tracing::instrument(level = "debug", skip(self, payload))]
-    pub(super) async fn append_payload_to_log(&mut self, payload: EntryPayload<D>) -> RaftResult<Entry<D>> {
-        let entry = Entry {
-            log_id: LogId {
-                index: self.core.last_log_id.index + 1,
-                term: self.core.current_term,
-            },
-            payload,
+    /// Handle a batch of client write requests.
+    ///
+    /// All of the entries are appended to the log with one storage write, then replicated together. Every request
+    /// is responded through its own `tx`, once its entry is applied.
+    #[tracing::instrument(level = "trace", skip(self, reqs), fields(n=reqs.len()))]
+    pub(super) async fn handle_client_write_batch(&mut self, reqs: Vec<ClientWriteWithTx<D, R, NID>>) {
+        // Duplicates of in flight requests are not appended.
+        let mut payloads = Vec::with_capacity(reqs.len());
+        let mut txs = Vec::with_capacity(reqs.len());
+
+        // Requests beyond the room for uncommitted entries are rejected, thus apart from the ones rejected for their
+        // size, the appended ones are a prefix.
+        let room = self.uncommitted_room();
+
+        for (rpc, tx) in reqs {
+            let request_id = rpc.request_id.clone();
+
+            let tx = match &request_id {
+                None => tx,
+                Some(id) => match self.client_sessions.check(id, tx) {
+                    Some(tx) => tx,
+                    None => continue,
+                },
+            };
+
+            let rejected = match self.payload_too_large(&rpc.entry) {
+                Some(err) => Some(err),
+                None if payloads.len() as u64 >= room => Some(self.overloaded()),
+                None => None,
+            };
+
+            if let Some(err) = rejected {
+                if let Some(id) = &request_id {
+                    self.client_sessions.abort(id, &err.to_string());
+                }
+                let _ = tx.send(Err(err));
+                continue;
+            }
+
+            payloads.push(rpc);
+            txs.push((tx, request_id));
+        }
+
+        if payloads.is_empty() {
+            return;
+        }
+
+        let entries = match self.append_payloads_to_log(payloads).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                // None of the entries is appended, every request fails with the same error.
+                let msg = err.to_string();
+                for (tx, request_id) in txs {
+                    if let Some(id) = &request_id {
+                        self.client_sessions.abort(id, &msg);
+                    }
+                    let _ = tx.send(Err(ClientWriteError::RaftError(RaftError::RaftStorage(anyhow!(
+                        msg.clone()
+                    )))));
+                }
+                return;
+            }
         };
-        self.core.storage.append_to_log(&[&entry]).await.map_err(|err| self.core.map_storage_error(err))?;
 
-        tracing::debug!("append log: {}", entry.summary());
-        self.core.last_log_id.index = entry.log_id.index;
+        self.leader_report_metrics();
+
+        for (entry, (tx, _)) in entries.into_iter().zip(txs) {
+            let cr_entry = ClientRequestEntry {
+                entry: Arc::new(entry),
+                tx: Some(tx),
+            };
+            self.replicate_client_request(cr_entry).await;
+        }
+    }
+
+    /// The number of entries that can be appended before the uncommitted entries reach
+    /// `Config::max_uncommitted_entries`.
+    fn uncommitted_room(&self) -> u64 {
+        let uncommitted = self.core.last_log_id.index.saturating_sub(self.core.committed.index);
+        self.core.config.max_uncommitted_entries.saturating_sub(uncommitted)
+    }
+
+    /// Build the error to reject a write when there is no room for more uncommitted entries.
+    fn overloaded(&self) -> ClientWriteError<NID> {
+        ClientWriteError::Overloaded {
+            uncommitted: self.core.last_log_id.index.saturating_sub(self.core.committed.index),
+            max: self.core.config.max_uncommitted_entries,
+        }
+    }
+
+    /// Build the error to reject a write whose payload is larger than `Config::max_log_entry_size`, if it is.
+    fn payload_too_large(&self, payload: &EntryPayload<D, NID>) -> Option<ClientWriteError<NID>> {
+        let max = self.core.config.max_log_entry_size;
+        if max == 0 {
+            return None;
+        }
+
+        let size = payload.size_hint();
+        if size > max {
+            Some(ClientWriteError::PayloadTooLarge { size, max })
+        } else {
+            None
+        }
+    }
+
+    /// Transform the given request into an entry, assign an index and term, and append the entry to the log.
+    #[tracing::instrument(level = "debug", skip(self, req))]
+    pub(super) async fn append_payload_to_log(&mut self, req: ClientWriteRequest<D, NID>) -> RaftResult<Entry<D, NID>> {
+        let mut entries = self.append_payloads_to_log(vec![req]).await?;
+        Ok(entries.pop().unwrap())
+    }
 
-        Ok(entry)
+    /// Transform the given requests into entries with consecutive indexes, and append them to the log with one
+    /// storage write.
+    #[tracing::instrument(level = "debug", skip(self, reqs), fields(n=reqs.len()))]
+    pub(super) async fn append_payloads_to_log(
+        &mut self,
+        reqs: Vec<ClientWriteRequest<D, NID>>,
+    ) -> RaftResult<Vec<Entry<D, NID>>> {
+        let first_index = self.core.last_log_id.index + 1;
+
+        let entries = reqs
+            .into_iter()
+            .enumerate()
+            .map(|(i, req)| Entry {
+                log_id: LogId {
+                    index: first_index + i as u64,
+                    term: self.core.current_term,
+                },
+                payload: req.entry,
+                request_id: req.request_id,
+            })
+            .collect::<Vec<_>>();
+
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+        retry_transient(&*self.core.clock, &self.core.config, || {
+            self.core.storage.append_to_log(&entry_refs)
+        })
+        .await
+        .map_err(|err| self.core.map_storage_error(err))?;
+        self.core.log_cache.append(&entry_refs);
+        if self.core.config.durability.sync_log() {
+            let window = self.core.config.fsync_coalesce_window;
+            if window == 0 {
+                retry_transient(&*self.core.clock, &self.core.config, || self.core.storage.flush())
+                    .await
+                    .map_err(|err| self.core.map_storage_error(err))?;
+            } else if self.flush_deadline.is_none() {
+                // Sync once along with the logs appended within the window.
+                self.flush_deadline = Some(self.core.clock.now() + Duration::from_millis(window));
+            }
+        }
+
+        for entry in entries.iter() {
+            tracing::debug!("append log: {}", entry.summary());
+        }
+        if let Some(last) = entries.last() {
+            self.core.last_log_id.index = last.log_id.index;
+            if self.flush_deadline.is_none() {
+                self.flushed = last.log_id;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Sync the logs appended within `Config::fsync_coalesce_window`, after which they count toward a quorum.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn flush_coalesced(&mut self) -> RaftResult<()> {
+        self.flush_deadline = None;
+
+        retry_transient(&*self.core.clock, &self.core.config, || self.core.storage.flush())
+            .await
+            .map_err(|err| self.core.map_storage_error(err))?;
+
+        self.flushed = self.core.last_log_id;
+        Ok(())
     }
 
     /// Begin the process of replicating the given client request.
@@ -253,7 +541,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// merely beings the process. Once the request is committed to the cluster, its response will
     /// be generated asynchronously.
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
-    pub(super) async fn replicate_client_request(&mut self, req: ClientRequestEntry<D, R>) {
+    pub(super) async fn replicate_client_request(&mut self, req: ClientRequestEntry<D, R, NID>) {
         // Replicate the request if there are other cluster members. The client response will be
         // returned elsewhere after the entry has been committed to the cluster.
         let entry_arc = req.entry.clone();
@@ -267,10 +555,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         tracing::debug!(?nodes, ?all_members, "replicate_client_request");
 
         // Except the leader itself, there are other nodes that need to replicate log to.
-        let await_quorum = all_members.len() > 1;
+        // Or the entry is not yet synced, see `Config::fsync_coalesce_window`.
+        // Or a previous entry is not committed yet, e.g., when the other voters are just force removed.
+        let await_quorum =
+            all_members.len() > 1 || entry_arc.log_id > self.flushed || !self.awaiting_committed.is_empty();
 
         if await_quorum {
             self.awaiting_committed.push(req);
+
+            // The only voter commits by itself, along with the previous entries waiting for a quorum.
+            if all_members.len() == 1 {
+                self.try_commit().await;
+            }
         } else {
             // Else, there are no voting nodes for replication, so the payload is now committed.
             self.core.committed = entry_arc.log_id;
@@ -292,58 +588,85 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Handle the post-commit logic for a client request.
+    ///
+    /// The entry is handed to the apply worker. It waits for entries to be applied only if there are
+    /// `Config::max_in_flight_applies` entries submitted but not yet applied.
     #[tracing::instrument(level = "debug", skip(self, req))]
-    pub(super) async fn client_request_post_commit(&mut self, req: ClientRequestEntry<D, R>) {
-        let entry = &req.entry;
-
-        let apply_res = self.apply_entry_to_state_machine(entry).await;
+    pub(super) async fn client_request_post_commit(&mut self, req: ClientRequestEntry<D, R, NID>) {
+        self.handle_special_log(&req.entry);
+
+        if self.core.config.max_in_flight_applies == 1 {
+            // The worker is inline and nothing is in flight, apply it at once.
+            let applied = self
+                .apply_worker
+                .apply_now(
+                    self.core.storage.clone(),
+                    req,
+                    self.core.config.max_logs_to_keep_on_apply(),
+                    self.core.config.max_apply_batch_size,
+                )
+                .await;
+            self.handle_applied(applied).await;
+            return;
+        }
 
-        self.send_response(entry, apply_res, req.tx).await;
+        self.apply_worker.submit(req);
 
-        // Trigger log compaction if needed.
-        self.core.trigger_log_compaction_if_needed(false);
+        while self.apply_worker.submitted.index - self.core.last_applied.index >= self.core.config.max_in_flight_applies
+        {
+            match self.apply_worker.rx_applied.recv().await {
+                Some(applied) => self.handle_applied(applied).await,
+                None => {
+                    // The worker quits only after a fatal storage error.
+                    break;
+                }
+            }
+        }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, entry, resp, tx), fields(entry=%entry.summary()))]
-    pub(super) async fn send_response(
-        &mut self,
-        entry: &Entry<D>,
-        resp: RaftResult<R>,
-        tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
-    ) {
-        let tx = match tx {
-            None => return,
-            Some(x) => x,
-        };
+    /// Handle a batch of entries that have been applied by the apply worker.
+    #[tracing::instrument(level = "debug", skip(self, applied), fields(last_applied=%applied.last_applied))]
+    pub(super) async fn handle_applied(&mut self, applied: Applied<D, R, NID>) {
+        self.core.last_applied = applied.last_applied;
 
-        let res = match resp {
-            Ok(data) => {
-                let membership = if let EntryPayload::Membership(ref c) = entry.payload {
-                    Some(c.clone())
-                } else {
-                    None
-                };
-
-                Ok(ClientWriteResponse {
-                    log_id: entry.log_id,
-                    data,
-                    membership,
-                })
+        match applied.error {
+            Some(err @ StorageError::IO { .. }) => {
+                self.core.map_storage_error(err);
             }
-            Err(raft_err) => {
-                tracing::error!(err=?raft_err, entry=%entry.summary(), "apply client entry");
-                Err(ClientWriteError::RaftError(raft_err))
+            Some(err) => {
+                tracing::error!(error=?err, "apply client entries");
             }
-        };
+            None => {}
+        }
+
+        self.leader_report_metrics();
+
+        for (req, res) in applied.results {
+            let res = build_response(&req.entry, res);
+
+            if let Some(id) = &req.entry.request_id {
+                self.client_sessions.applied(id, &res);
+            }
+
+            send_response(res, req.tx);
+        }
+
+        // Trigger log compaction if needed.
+        self.core.refresh_log_storage_size().await;
+        self.core.trigger_log_compaction_if_needed(false);
+    }
+
+    /// Wait for every submitted entry to be applied, before this node leaves leader state.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn drain_applies(&mut self) {
+        self.apply_worker.close();
 
-        let send_res = tx.send(res);
-        tracing::debug!(
-            "send client response through tx, send_res is error: {}",
-            send_res.is_err()
-        );
+        while let Some(applied) = self.apply_worker.rx_applied.recv().await {
+            self.handle_applied(applied).await;
+        }
     }
 
-    pub fn handle_special_log(&mut self, entry: &Entry<D>) {
+    pub fn handle_special_log(&mut self, entry: &Entry<D, NID>) {
         match &entry.payload {
             EntryPayload::Membership(ref m) => {
                 if m.is_in_joint_consensus() {
@@ -356,71 +679,47 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             EntryPayload::Normal(_) => {}
         }
     }
+}
 
-    /// Apply the given log entry to the state machine.
-    #[tracing::instrument(level = "debug", skip(self, entry))]
-    pub(super) async fn apply_entry_to_state_machine(&mut self, entry: &Entry<D>) -> RaftResult<R> {
-        self.handle_special_log(entry);
-
-        // First, we just ensure that we apply any outstanding up to, but not including, the index
-        // of the given entry. We need to be able to return the data response from applying this
-        // entry to the state machine.
-        //
-        // Note that this would only ever happen if a node had unapplied logs from before becoming leader.
-
-        let log_id = &entry.log_id;
-        let index = log_id.index;
-
-        let expected_next_index = self.core.last_applied.index + 1;
-        if index != expected_next_index {
-            let entries = self
-                .core
-                .storage
-                .get_log_entries(expected_next_index..index)
-                .await
-                .map_err(|err| self.core.map_storage_error(err))?;
-
-            if let Some(entry) = entries.last() {
-                self.core.last_applied = entry.log_id;
-            }
-
-            let data_entries: Vec<_> = entries.iter().collect();
-            if !data_entries.is_empty() {
-                apply_to_state_machine(
-                    self.core.storage.clone(),
-                    &data_entries,
-                    self.core.config.max_applied_log_to_keep,
-                )
-                .await
-                .map_err(|err| self.core.map_storage_error(err))?;
-            }
-        }
-
-        // Apply this entry to the state machine and return its data response.
-        let apply_res = apply_to_state_machine(
-            self.core.storage.clone(),
-            &[entry],
-            self.core.config.max_applied_log_to_keep,
-        )
-        .await;
-
-        let res = apply_res.map_err(|err| {
-            if let StorageError::IO { .. } = err {
-                // If this is an instance of the storage impl's shutdown error, then trigger shutdown.
-                self.core.map_storage_error(err)
+/// Build the response to a client from the result of applying `entry`.
+fn build_response<D: AppData, R: AppDataResponse, NID: RaftNodeId>(
+    entry: &Entry<D, NID>,
+    resp: RaftResult<R>,
+) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+    match resp {
+        Ok(data) => {
+            let membership = if let EntryPayload::Membership(ref c) = entry.payload {
+                Some(c.clone())
             } else {
-                // TODO(xp): remove this
-                // Else, we propagate normally.
-                RaftError::RaftStorage(err.into())
-            }
-        });
-
-        self.core.last_applied = *log_id;
-        self.leader_report_metrics();
-        let res = res?;
-
-        // TODO(xp) merge this function to replication_to_state_machine?
+                None
+            };
 
-        Ok(res.into_iter().next().unwrap())
+            Ok(ClientWriteResponse {
+                log_id: entry.log_id,
+                data,
+                membership,
+            })
+        }
+        Err(raft_err) => {
+            tracing::error!(err=?raft_err, entry=%entry.summary(), "apply client entry");
+            Err(ClientWriteError::RaftError(raft_err))
+        }
     }
 }
+
+/// Send the response to the client, if it is waiting for one.
+fn send_response<R: AppDataResponse, NID: RaftNodeId>(
+    res: Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    tx: Option<RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>>,
+) {
+    let tx = match tx {
+        None => return,
+        Some(x) => x,
+    };
+
+    let send_res = tx.send(res);
+    tracing::debug!(
+        "send client response through tx, send_res is error: {}",
+        send_res.is_err()
+    );
+}
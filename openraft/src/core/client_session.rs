@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use crate::error::ClientWriteError;
+use crate::error::RaftError;
+use crate::raft::ClientRequestId;
+use crate::raft::ClientWriteResponse;
+use crate::raft::RaftRespTx;
+use crate::AppDataResponse;
+use crate::RaftNodeId;
+
+type ResponseTx<R, NID> = RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>;
+
+/// Tracks the write requests with an id a leader has appended but not yet applied, so that a duplicate sent while
+/// the first one is in flight waits for its response instead of being appended again.
+///
+/// A duplicate of an applied request is appended, and the state machine responds to it with the remembered response,
+/// see `ClientSessionTable`. Thus a leader keeps nothing once a request is applied.
+pub(super) struct ClientSessions<R: AppDataResponse, NID: RaftNodeId> {
+    /// The requests in flight, with the duplicates waiting for the response to it.
+    in_flight: HashMap<ClientRequestId, Vec<ResponseTx<R, NID>>>,
+}
+
+impl<R: AppDataResponse, NID: RaftNodeId> ClientSessions<R, NID> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// Check a request before appending it to the log.
+    ///
+    /// If the request is in flight, it waits for the response to the first one and it returns `None`.
+    /// Otherwise it returns `tx` back to append the request, and the request is tracked as in flight.
+    pub fn check(&mut self, id: &ClientRequestId, tx: ResponseTx<R, NID>) -> Option<ResponseTx<R, NID>> {
+        if let Some(waiting) = self.in_flight.get_mut(id) {
+            tracing::debug!(%id, "duplicate request is in flight, wait for it");
+            waiting.push(tx);
+            return None;
+        }
+
+        self.in_flight.insert(id.clone(), vec![]);
+        Some(tx)
+    }
+
+    /// Respond to the duplicates waiting for an applied request.
+    pub fn applied(&mut self, id: &ClientRequestId, res: &Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>>) {
+        let waiting = self.in_flight.remove(id).unwrap_or_default();
+
+        match res {
+            Ok(resp) => {
+                for tx in waiting {
+                    let _ = tx.send(Ok(resp.clone()));
+                }
+            }
+            Err(err) => {
+                Self::fail(waiting, &err.to_string());
+            }
+        }
+    }
+
+    /// Forget a request that is not appended, and fail the duplicates waiting for it.
+    pub fn abort(&mut self, id: &ClientRequestId, msg: &str) {
+        let waiting = self.in_flight.remove(id).unwrap_or_default();
+        Self::fail(waiting, msg);
+    }
+
+    fn fail(waiting: Vec<ResponseTx<R, NID>>, msg: &str) {
+        for tx in waiting {
+            let _ = tx.send(Err(ClientWriteError::RaftError(RaftError::RaftStorage(anyhow!(
+                msg.to_string()
+            )))));
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use std::io::SeekFrom;
+use std::collections::BTreeMap;
+use std::io;
 
-use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
+use tracing_futures::Instrument;
 
 use crate::core::delete_applied_logs;
 use crate::core::RaftCore;
@@ -9,18 +11,27 @@ use crate::core::SnapshotState;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::RaftResult;
+use crate::event::RaftEvent;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::snapshot_stream::snapshot_channel;
+use crate::snapshot_stream::SnapshotChunk;
+use crate::snapshot_stream::SnapshotSender;
+use crate::snapshot_stream::SNAPSHOT_STREAM_BUFFER;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::MessageSummary;
 use crate::RaftError;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
+use crate::SnapshotMeta;
 use crate::SnapshotSegmentId;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
     /// Invoked by leader to send chunks of a snapshot to a follower (§7).
     ///
     /// Leaders always send chunks in order. It is important to note that, according to the Raft spec,
@@ -29,7 +40,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
     pub(super) async fn handle_install_snapshot_request(
         &mut self,
-        req: InstallSnapshotRequest,
+        req: InstallSnapshotRequest<NID>,
     ) -> RaftResult<InstallSnapshotResponse> {
         // If message's term is less than most recent term, then we do not honor the request.
         if req.term < self.current_term {
@@ -40,6 +51,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // Update election timeout.
         self.update_next_election_timeout(true);
+        self.resume_elections();
 
         // Update current term if needed.
         let mut report_metrics = false;
@@ -64,11 +76,25 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.report_metrics(Update::Ignore);
         }
 
+        let res = self.receive_snapshot_chunk(req).await;
+
+        // Report the progress, or that the installation is finished or aborted.
+        self.report_metrics(Update::Ignore);
+
+        res
+    }
+
+    async fn receive_snapshot_chunk(
+        &mut self,
+        req: InstallSnapshotRequest<NID>,
+    ) -> RaftResult<InstallSnapshotResponse> {
         // Compare current snapshot state with received RPC and handle as needed.
         // - Init a new state if it is empty or building a snapshot locally.
         // - Mismatched id with offset=0 indicates a new stream has been sent, the old one should be dropped and start
         //   to receive the new snapshot,
         // - Mismatched id with offset greater than 0 is an out of order message that should be rejected.
+        // - Matched id with offset greater than the received data is a chunk arriving too early, it is kept until the
+        //   chunks before it arrive, unless too many chunks are already kept.
         match self.snapshot_state.take() {
             None => {
                 return self.begin_installing_snapshot(req).await;
@@ -77,9 +103,54 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 handle.abort(); // Abort the current compaction in favor of installation from leader.
                 return self.begin_installing_snapshot(req).await;
             }
-            Some(SnapshotState::Streaming { snapshot, id, offset }) => {
+            Some(SnapshotState::Streaming {
+                offset,
+                size,
+                id,
+                sender,
+                writer,
+                mut ahead,
+            }) => {
+                if req.meta.snapshot_id == id && req.offset > offset {
+                    // A chunk arrives ahead of the chunks before it, e.g., when the leader sends several chunks at
+                    // once and the transport reorders them. Keep it until the chunks before it arrive. The last
+                    // chunk is sent only when every chunk before it is acknowledged, thus it never arrives ahead.
+                    let window = self.config.snapshot_ack_every_n_chunks as usize;
+                    let keep = !req.done && (ahead.len() < window || ahead.contains_key(&req.offset));
+
+                    let res = if keep {
+                        ahead.insert(req.offset, SnapshotChunk {
+                            offset: req.offset,
+                            data: req.data,
+                            done: false,
+                        });
+                        Ok(InstallSnapshotResponse {
+                            term: self.current_term,
+                        })
+                    } else {
+                        // The leader resends from the first chunk not acknowledged.
+                        Err(RaftError::SnapshotMismatch {
+                            expect: SnapshotSegmentId { id: id.clone(), offset },
+                            got: SnapshotSegmentId {
+                                id: req.meta.snapshot_id.clone(),
+                                offset: req.offset,
+                            },
+                        })
+                    };
+
+                    self.snapshot_state = Some(SnapshotState::Streaming {
+                        offset,
+                        size,
+                        id,
+                        sender,
+                        writer,
+                        ahead,
+                    });
+                    return res;
+                }
+
                 if req.meta.snapshot_id == id {
-                    return self.continue_installing_snapshot(req, offset, snapshot).await;
+                    return self.continue_installing_snapshot(req, offset, sender, writer, ahead).await;
                 }
 
                 if req.offset == 0 {
@@ -98,7 +169,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
-    async fn begin_installing_snapshot(&mut self, req: InstallSnapshotRequest) -> RaftResult<InstallSnapshotResponse> {
+    async fn begin_installing_snapshot(
+        &mut self,
+        req: InstallSnapshotRequest<NID>,
+    ) -> RaftResult<InstallSnapshotResponse> {
         let id = req.meta.snapshot_id.clone();
 
         if req.offset > 0 {
@@ -111,72 +185,141 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             });
         }
 
-        // Create a new snapshot and begin writing its contents.
-        let mut snapshot = self.storage.begin_receiving_snapshot().await.map_err(|err| self.map_storage_error(err))?;
-        snapshot.as_mut().write_all(&req.data).await?;
-
-        // If this was a small snapshot, and it is already done, then finish up.
-        if req.done {
-            self.finalize_snapshot_installation(req, snapshot).await?;
-            return Ok(InstallSnapshotResponse {
-                term: self.current_term,
+        let format_version = req.meta.format_version;
+        if !self.storage.is_snapshot_format_supported(format_version) {
+            tracing::warn!(snapshot_id = %id, format_version, "reject snapshot of unsupported format version");
+            return Err(RaftError::SnapshotFormatUnsupported {
+                snapshot_id: id,
+                format_version,
             });
         }
 
-        // Else, retain snapshot components for later segments & respond.
-        self.snapshot_state = Some(SnapshotState::Streaming {
-            offset: req.data.len() as u64,
-            id,
-            snapshot,
-        });
-        Ok(InstallSnapshotResponse {
-            term: self.current_term,
-        })
+        // Create a new snapshot and write its contents in another task, as the chunks arrive.
+        let mut snapshot = self.storage.begin_receiving_snapshot().await.map_err(|err| self.map_storage_error(err))?;
+
+        let (sender, mut receiver) = snapshot_channel(SNAPSHOT_STREAM_BUFFER);
+        let writer = tokio::spawn(
+            async move {
+                let res = receiver.write_to(snapshot.as_mut()).await;
+                (res, snapshot)
+            }
+            .instrument(tracing::debug_span!("write_snapshot")),
+        );
+
+        self.continue_installing_snapshot(req, 0, sender, writer, BTreeMap::new()).await
     }
 
-    #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
+    /// Send a received chunk, and the chunks kept in `ahead` that follow it without a gap, to the snapshot writer.
+    ///
+    /// If the writer can not keep up, it waits for the writer to consume a buffered chunk before responding to the
+    /// leader. Thus the leader does not send the next chunk until then.
+    #[tracing::instrument(level = "debug", skip(self, req, sender, writer, ahead), fields(req=%req.summary()))]
     async fn continue_installing_snapshot(
         &mut self,
-        req: InstallSnapshotRequest,
-        mut offset: u64,
-        mut snapshot: Box<S::SnapshotData>,
+        req: InstallSnapshotRequest<NID>,
+        offset: u64,
+        sender: SnapshotSender,
+        writer: JoinHandle<(io::Result<u32>, Box<S::SnapshotData>)>,
+        mut ahead: BTreeMap<u64, SnapshotChunk>,
     ) -> RaftResult<InstallSnapshotResponse> {
-        let id = req.meta.snapshot_id.clone();
+        let InstallSnapshotRequest {
+            meta,
+            offset: req_offset,
+            data,
+            done,
+            ..
+        } = req;
+
+        if req_offset != offset {
+            tracing::debug!(
+                offset,
+                req_offset,
+                "chunk is not at the expected offset, the writer skips the data already written"
+            );
+        }
 
-        // Always seek to the target offset if not an exact match.
-        if req.offset != offset {
-            if let Err(err) = snapshot.as_mut().seek(SeekFrom::Start(req.offset)).await {
-                self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
-                return Err(err.into());
+        let mut next_offset = std::cmp::max(offset, req_offset + data.len() as u64);
+
+        let mut chunks = vec![SnapshotChunk {
+            offset: req_offset,
+            data,
+            done,
+        }];
+
+        if !done {
+            while let Some(chunk) = ahead.remove(&next_offset) {
+                next_offset += chunk.data.len() as u64;
+                chunks.push(chunk);
             }
-            offset = req.offset;
+            // The chunks before the next offset are written.
+            ahead = ahead.split_off(&next_offset);
         }
 
-        // Write the next segment & update offset.
-        if let Err(err) = snapshot.as_mut().write_all(&req.data).await {
-            self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
-            return Err(err.into());
+        for chunk in chunks {
+            if sender.send(chunk).await.is_err() {
+                // The writer quits before the last chunk, because of an error.
+                drop(sender);
+                let err = match Self::wait_snapshot_writer(writer).await {
+                    Err(err) => err,
+                    Ok(_) => io::Error::new(io::ErrorKind::Other, "snapshot writer quits before the last chunk"),
+                };
+                return Err(err.into());
+            }
         }
-        offset += req.data.len() as u64;
 
-        // If the snapshot stream is done, then finalize.
-        if req.done {
-            self.finalize_snapshot_installation(req, snapshot).await?;
+        // If the snapshot stream is done, verify the received data then finalize.
+        if done {
+            drop(sender);
+            let (snapshot, checksum) = Self::wait_snapshot_writer(writer).await?;
+
+            if let Some(expect) = meta.checksum {
+                if checksum != expect {
+                    tracing::warn!(
+                        snapshot_id = %meta.snapshot_id,
+                        expect,
+                        got = checksum,
+                        "received snapshot is corrupted, discard it"
+                    );
+                    return Err(RaftError::SnapshotChecksumMismatch {
+                        snapshot_id: meta.snapshot_id,
+                        expect,
+                        got: checksum,
+                    });
+                }
+            }
+
+            self.finalize_snapshot_installation(&meta, snapshot).await?;
         } else {
-            self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
+            self.snapshot_state = Some(SnapshotState::Streaming {
+                offset: next_offset,
+                size: meta.size,
+                id: meta.snapshot_id,
+                sender,
+                writer,
+                ahead,
+            });
         }
         Ok(InstallSnapshotResponse {
             term: self.current_term,
         })
     }
 
+    /// Wait for the snapshot writer to finish, and return the written snapshot and the CRC32 of the written data.
+    async fn wait_snapshot_writer(
+        writer: JoinHandle<(io::Result<u32>, Box<S::SnapshotData>)>,
+    ) -> io::Result<(Box<S::SnapshotData>, u32)> {
+        let (res, snapshot) = writer.await.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let checksum = res?;
+        Ok((snapshot, checksum))
+    }
+
     /// Finalize the installation of a new snapshot.
     ///
     /// Any errors which come up from this routine will cause the Raft node to go into shutdown.
-    #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
+    #[tracing::instrument(level = "debug", skip(self, snapshot), fields(meta=?meta))]
     async fn finalize_snapshot_installation(
         &mut self,
-        req: InstallSnapshotRequest,
+        meta: &SnapshotMeta,
         mut snapshot: Box<S::SnapshotData>,
     ) -> RaftResult<()> {
         snapshot.as_mut().shutdown().await.map_err(|err| self.map_fatal_storage_error(err.into()))?;
@@ -197,11 +340,22 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // TODO(xp): do not install if self.last_applied >= snapshot.meta.last_applied
 
-        let changes = self
-            .storage
-            .finalize_snapshot_installation(&req.meta, snapshot)
-            .await
-            .map_err(|e| self.map_storage_error(e))?;
+        let changes = {
+            let sm_applied = self.sm_applied.clone();
+            let mut sm_applied = sm_applied.lock().await;
+            let changes = self
+                .storage
+                .finalize_snapshot_installation(meta, snapshot)
+                .await
+                .map_err(|e| self.map_storage_error(e))?;
+            if let Some(last_applied) = changes.last_applied {
+                *sm_applied = last_applied;
+            }
+            changes
+        };
+
+        // The storage may have replaced any of the logs with the snapshot.
+        self.log_cache.clear();
 
         tracing::debug!("update after apply or install-snapshot: {:?}", changes);
 
@@ -211,9 +365,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         if let Some(last_applied) = changes.last_applied {
             // Applied logs are not needed.
-            delete_applied_logs(self.storage.clone(), &last_applied, self.config.max_applied_log_to_keep)
-                .await
-                .map_err(|e| self.map_storage_error(e))?;
+            delete_applied_logs(
+                self.storage.clone(),
+                &last_applied,
+                self.config.max_applied_log_to_keep,
+                &self.tx_events,
+            )
+            .await
+            .map_err(|e| self.map_storage_error(e))?;
 
             // snapshot is installed
             self.last_applied = last_applied;
@@ -232,7 +391,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.update_membership(membership)?;
 
             self.snapshot_last_log_id = self.last_applied;
+            self.snapshot_last_time = self.clock.now();
             self.report_metrics(Update::Ignore);
+
+            let _ = self.tx_events.send(RaftEvent::SnapshotInstalled(last_applied));
         } else {
             // snapshot not installed
         }
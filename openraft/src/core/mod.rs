@@ -2,7 +2,9 @@
 
 mod admin;
 mod append_entries;
+mod apply_worker;
 mod client;
+mod client_session;
 mod install_snapshot;
 pub(crate) mod replication;
 #[cfg(test)]
@@ -11,9 +13,11 @@ mod vote;
 
 #[cfg(test)]
 mod startup_test;
+mod transfer_leadership;
 
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::future::Future;
 use std::sync::Arc;
 
 use futures::future::AbortHandle;
@@ -27,26 +31,48 @@ use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
+use tokio::sync::Mutex;
+use tokio::sync::Semaphore;
 use tokio::task::JoinHandle;
-use tokio::time::sleep_until;
 use tokio::time::Duration;
 use tokio::time::Instant;
 use tracing::trace_span;
 use tracing::Instrument;
 use tracing::Span;
 
+use crate::clock::Clock;
+use crate::clock::Ticker;
 use crate::config::Config;
+use crate::config::ConfigDelta;
+use crate::config::Durability;
+use crate::config::LogPurgePolicy;
 use crate::config::SnapshotPolicy;
+use crate::core::admin::ForcedMembership;
+use crate::core::admin::LearnerCatchUp;
+use crate::core::admin::MembershipCatchUp;
+use crate::core::apply_worker::ApplyWorker;
 use crate::core::client::ClientRequestEntry;
+use crate::core::client_session::ClientSessions;
+use crate::core::transfer_leadership::LeadershipTransfer;
 use crate::error::AddLearnerError;
+use crate::error::AppliedError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::ForwardToLeader;
 use crate::error::InitializeError;
+use crate::error::LogReadError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::RebuildStateMachineError;
+use crate::error::TriggerSnapshotError;
+use crate::error::UpdateConfigError;
+use crate::event::EventTx;
+use crate::event::RaftEvent;
+use crate::log_cache::LogCache;
 use crate::metrics::LeaderMetrics;
+use crate::metrics::PeerHealth;
 use crate::metrics::RaftMetrics;
+use crate::network::NodeCapabilities;
 use crate::raft::AddLearnerResponse;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
@@ -55,18 +81,27 @@ use crate::raft::EntryPayload;
 use crate::raft::Membership;
 use crate::raft::RaftMsg;
 use crate::raft::RaftRespTx;
-use crate::replication::RaftEvent;
+use crate::raft::VoteRequest;
 use crate::replication::ReplicaEvent;
 use crate::replication::ReplicationStream;
+use crate::snapshot_stream::SnapshotChunk;
+use crate::snapshot_stream::SnapshotSender;
 use crate::storage::HardState;
+use crate::storage::Snapshot;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::ErrorSubject;
+use crate::ErrorVerb;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::NodeId;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
+use crate::ReplicationStatus;
+use crate::SnapshotMeta;
 use crate::StorageError;
+use crate::StorageIOError;
 use crate::Update;
 
 /// The currently active membership config.
@@ -77,29 +112,31 @@ use crate::Update;
 ///
 /// An active config is just the last seen config in raft spec.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct EffectiveMembership {
+#[serde(bound = "")]
+pub struct EffectiveMembership<NID: RaftNodeId = NodeId> {
     /// The id of the log that applies this membership config
     pub log_id: LogId,
 
-    pub membership: Membership,
+    pub membership: Membership<NID>,
 }
 
-impl MessageSummary for EffectiveMembership {
+impl<NID: RaftNodeId> MessageSummary for EffectiveMembership<NID> {
     fn summary(&self) -> String {
         format!("{{log_id:{} membership:{}}}", self.log_id, self.membership.summary())
     }
 }
 
 /// The core type implementing the Raft protocol.
-pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+{
     /// This node's ID.
-    id: NodeId,
+    id: NID,
 
     /// This node's runtime config.
     config: Arc<Config>,
 
     /// The cluster's current membership configuration.
-    effective_membership: EffectiveMembership,
+    effective_membership: EffectiveMembership<NID>,
 
     /// The `RaftNetwork` implementation.
     network: Arc<N>,
@@ -107,6 +144,12 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The `RaftStorage` implementation.
     storage: Arc<S>,
 
+    /// The most recent log entries, see `Config::log_cache_size`.
+    log_cache: Arc<LogCache<D, NID>>,
+
+    /// The source of time of this node.
+    clock: Arc<dyn Clock>,
+
     /// The target state of the system.
     target_state: State,
 
@@ -120,6 +163,12 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The log id of the highest log entry which has been applied to the local state machine.
     last_applied: LogId,
 
+    /// The last log id the state machine is at, locked while the state machine changes, so that a view of the state
+    /// machine is taken at it, see `RaftStorage::snapshot_view()`.
+    ///
+    /// Unlike `last_applied`, it is updated by the apply worker of a leader as soon as a batch is applied.
+    sm_applied: Arc<Mutex<LogId>>,
+
     /// The current term.
     ///
     /// Is initialized to 0 on first boot, and increases monotonically. This is normally based on
@@ -128,13 +177,13 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     current_term: u64,
 
     /// The ID of the current leader of the Raft cluster.
-    current_leader: Option<NodeId>,
+    current_leader: Option<NID>,
 
     /// The ID of the candidate which received this node's vote for the current term.
     ///
     /// Each server will vote for at most one candidate in a given term, on a
     /// first-come-first-served basis. See §5.4.1 for additional restriction on votes.
-    voted_for: Option<NodeId>,
+    voted_for: Option<NID>,
 
     /// The last entry to be appended to the log.
     last_log_id: LogId,
@@ -147,6 +196,19 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// This is primarily used in making a determination on when a compaction job needs to be triggered.
     snapshot_last_log_id: LogId,
 
+    /// The number of bytes the log takes in the storage, refreshed before deciding whether to build a snapshot with
+    /// `SnapshotPolicy::SizeSinceLast`.
+    log_storage_size: u64,
+
+    /// The value of `log_storage_size` when the last snapshot is started, or the lowest value since then, as the log
+    /// is purged.
+    snapshot_log_storage_size: u64,
+
+    /// The time when the last snapshot was built or installed, or when this node started if there is none yet.
+    ///
+    /// This is used by time based snapshot policies to decide when a compaction job needs to be triggered.
+    snapshot_last_time: Instant,
+
     /// A bool indicating if this system has performed its initial replication of
     /// outstanding entries to the state machine.
     has_completed_initial_replication_to_sm: bool,
@@ -154,31 +216,103 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The last time a heartbeat was received.
     last_heartbeat: Option<Instant>,
 
+    /// The last time an AppendEntries from the leader brought the state machine up to the leader's commit index.
+    last_leader_sync: Option<Instant>,
+
     /// The duration until the next election timeout.
     next_election_timeout: Option<Instant>,
 
+    /// Set when a TimeoutNow is received from the leader, the next election is a leadership transfer.
+    leadership_transfer: bool,
+
+    /// The config overridden locally by `Raft::force_remove_node()` on a follower or candidate, to append once this
+    /// node is elected with it.
+    forced_membership: Option<ForcedMembership<R, NID>>,
+
+    /// The number of election rounds in a row that did not make this node the leader.
+    failed_elections: u64,
+
+    /// The number of election rounds in a row in which a voter rejected this node for its log is stale.
+    stale_log_elections: u64,
+
+    /// The last log id of this node when it backs off from elections for its log is stale, see
+    /// `Config::backoff_on_stale_log`. Elections resume once the log advances beyond it.
+    stale_log_id: Option<LogId>,
+
+    /// When a node backing off for its stale log resumes elections even if its log does not advance.
+    stale_log_deadline: Option<Instant>,
+
+    /// The greatest term received from other nodes, see `Config::suspicious_term_gap`.
+    max_term_seen: u64,
+
+    /// The number of AppendEntries this node rejected because `prev_log_id` does not match its log.
+    conflicts_reported: u64,
+
+    /// The term of the leader and the greatest `leader_commit` it has sent, see `Config::reject_commit_regression`.
+    leader_commit_seen: Option<(u64, LogId)>,
+
+    /// The optional RPCs every peer supports, queried with `RaftNetwork::capabilities()` on first use.
+    capabilities: BTreeMap<NID, NodeCapabilities>,
+
+    /// When to ask a peer for its capabilities again, after failing to, see `CAPABILITIES_RETRY_HEARTBEATS`.
+    capabilities_retry_at: BTreeMap<NID, Instant>,
+
     tx_compaction: mpsc::Sender<SnapshotUpdate>,
     rx_compaction: mpsc::Receiver<SnapshotUpdate>,
 
-    rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
+    rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R, NID>, Span)>,
+
+    tx_metrics: watch::Sender<RaftMetrics<NID>>,
 
-    tx_metrics: watch::Sender<RaftMetrics>,
+    /// The term in which this node is the leader, or `None` if it is not a leader.
+    tx_leadership: watch::Sender<Option<u64>>,
 
-    rx_shutdown: oneshot::Receiver<()>,
+    /// The config in use, updated along with `config`.
+    tx_config: watch::Sender<Arc<Config>>,
+
+    /// Broadcasts the lifecycle events, see `Raft::events()`.
+    tx_events: EventTx<NID>,
+
+    /// Receives a request to shutdown, `true` for a graceful one.
+    rx_shutdown: oneshot::Receiver<bool>,
+
+    /// Set when a graceful shutdown is requested: committed entries are applied before this node shuts down.
+    graceful_shutdown: bool,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+/// The channels RaftCore communicates with the `Raft` handle through.
+pub(crate) struct CoreChannels<D: AppData, R: AppDataResponse, NID: RaftNodeId> {
+    pub(crate) rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R, NID>, Span)>,
+    pub(crate) tx_metrics: watch::Sender<RaftMetrics<NID>>,
+    pub(crate) tx_leadership: watch::Sender<Option<u64>>,
+    pub(crate) tx_config: watch::Sender<Arc<Config>>,
+    pub(crate) tx_events: EventTx<NID>,
+    pub(crate) rx_shutdown: oneshot::Receiver<bool>,
+}
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
     pub(crate) fn spawn(
-        id: NodeId,
+        id: NID,
         config: Arc<Config>,
         network: Arc<N>,
         storage: Arc<S>,
-        rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
-        tx_metrics: watch::Sender<RaftMetrics>,
-        rx_shutdown: oneshot::Receiver<()>,
+        clock: Arc<dyn Clock>,
+        channels: CoreChannels<D, R, NID>,
     ) -> JoinHandle<RaftResult<()>> {
+        let CoreChannels {
+            rx_api,
+            tx_metrics,
+            tx_leadership,
+            tx_config,
+            tx_events,
+            rx_shutdown,
+        } = channels;
         let membership = Membership::new_initial(id); // This is updated from storage in the main loop.
         let (tx_compaction, rx_compaction) = mpsc::channel(1);
+        let now = clock.now();
+        let log_cache = Arc::new(LogCache::new(config.log_cache_size));
         let this = Self {
             id,
             config,
@@ -188,38 +322,68 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             },
             network,
             storage,
+            log_cache,
+            clock,
             target_state: State::Follower,
             committed: LogId::new(0, 0),
             last_applied: LogId::new(0, 0),
+            sm_applied: Arc::new(Mutex::new(LogId::new(0, 0))),
             current_term: 0,
             current_leader: None,
             voted_for: None,
             last_log_id: LogId::new(0, 0),
             snapshot_state: None,
             snapshot_last_log_id: LogId::new(0, 0),
+            log_storage_size: 0,
+            snapshot_log_storage_size: 0,
+            snapshot_last_time: now,
             has_completed_initial_replication_to_sm: false,
             last_heartbeat: None,
+            last_leader_sync: None,
             next_election_timeout: None,
+            leadership_transfer: false,
+            forced_membership: None,
+            failed_elections: 0,
+            stale_log_elections: 0,
+            stale_log_id: None,
+            stale_log_deadline: None,
+            max_term_seen: 0,
+            conflicts_reported: 0,
+            leader_commit_seen: None,
+            capabilities: BTreeMap::new(),
+            capabilities_retry_at: BTreeMap::new(),
             tx_compaction,
             rx_compaction,
             rx_api,
             tx_metrics,
+            tx_leadership,
+            tx_config,
+            tx_events,
             rx_shutdown,
+            graceful_shutdown: false,
         };
         tokio::spawn(this.main().instrument(trace_span!("spawn").or_current()))
     }
 
     /// The main loop of the Raft protocol.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.id, cluster=%self.config.cluster_name))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.id, cluster=%self.config.cluster_name))]
     async fn main(mut self) -> RaftResult<()> {
         tracing::debug!("raft node is initializing");
 
+        if self.config.durability == Durability::Async {
+            tracing::warn!(
+                id = %self.id,
+                "durability is Async: logs and hard state are not synced to disk, a crash may lose acknowledged writes"
+            );
+        }
+
         let state = self.storage.get_initial_state().await.map_err(|err| self.map_storage_error(err))?;
         self.last_log_id = state.last_log_id;
         self.current_term = state.hard_state.current_term;
         self.voted_for = state.hard_state.voted_for;
         self.effective_membership = state.last_membership.clone();
         self.last_applied = state.last_applied;
+        *self.sm_applied.lock().await = state.last_applied;
 
         // NOTE: this is repeated here for clarity. It is unsafe to initialize the node's commit
         // index to any other value. The commit index must be determined by a leader after
@@ -263,7 +427,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             // to ensure that restarted nodes don't disrupt a stable cluster by timing out and driving up their
             // term before network communication is established.
             let inst =
-                Instant::now() + Duration::from_millis(thread_rng().gen_range(1..3) * self.config.heartbeat_interval);
+                self.clock.now() + Duration::from_millis(thread_rng().gen_range(1..3) * self.config.heartbeat_interval);
             self.next_election_timeout = Some(inst);
         }
 
@@ -273,13 +437,48 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // on cluster state. The Raft core will delegate control to the different state
         // controllers and simply awaits the delegated loop to return, which will only take place
         // if some error has been encountered, or if a state change is required.
+        let mut was_follower = false;
+        let mut was_paused = false;
         loop {
+            // A change between `Follower` and `Paused` is not a new follower.
+            let is_follower = matches!(self.target_state, State::Follower | State::Paused);
+            if is_follower && !was_follower {
+                let _ = self.tx_events.send(RaftEvent::BecameFollower(self.current_term, self.current_leader));
+            }
+            was_follower = is_follower;
+
+            let is_paused = self.target_state.is_paused();
+            if is_paused && !was_paused {
+                let _ = self.tx_events.send(RaftEvent::ElectionsPaused(self.current_term));
+            }
+            was_paused = is_paused;
+
             match &self.target_state {
-                State::Leader => LeaderState::new(&mut self).run().await?,
+                State::Leader => {
+                    let res = LeaderState::new(&mut self).run().await;
+
+                    // Whatever makes the leader state quit, notify the leadership loss before this node serves in
+                    // another state.
+                    tracing::info!(term = self.current_term, "leadership lost");
+                    let _ = self.tx_leadership.send(None);
+
+                    res?
+                }
                 State::Candidate => CandidateState::new(&mut self).run().await?,
-                State::Follower => FollowerState::new(&mut self).run().await?,
-                State::Learner => LearnerState::new(&mut self).run().await?,
+                // A paused node is a follower that does not time out.
+                State::Follower | State::Paused => {
+                    self.abandon_forced_membership().await?;
+                    FollowerState::new(&mut self).run().await?
+                }
+                // The target state is never uninitialized: such a node is a learner, reported differently.
+                State::Learner | State::Uninitialized => {
+                    self.abandon_forced_membership().await?;
+                    LearnerState::new(&mut self).run().await?
+                }
                 State::Shutdown => {
+                    if self.graceful_shutdown {
+                        self.drain_for_shutdown().await?;
+                    }
                     tracing::info!("node has shutdown");
                     return Ok(());
                 }
@@ -289,46 +488,103 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Report a metrics payload on the current state of the Raft node.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics>>) {
+    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics<NID>>>) {
         let leader_metrics = match leader_metrics {
-            Update::Update(v) => v.cloned(),
+            Update::Update(v) => v.cloned().map(Arc::new),
             Update::Ignore => self.tx_metrics.borrow().leader_metrics.clone(),
         };
 
+        let (snapshot_building, installing_snapshot_progress) = match &self.snapshot_state {
+            Some(SnapshotState::Snapshotting { .. }) => (true, None),
+            Some(SnapshotState::Streaming { offset, size, .. }) => (false, Some((*offset, *size))),
+            None => (false, None),
+        };
+
+        // Rounded down to a heartbeat interval, thus it does not change while heartbeats arrive in time.
+        let heartbeat_interval = std::cmp::max(self.config.heartbeat_interval, 1);
+        let millis_since_last_heartbeat =
+            if self.target_state.is_follower() || self.target_state.is_paused() || self.target_state.is_learner() {
+                self.last_heartbeat.map(|t| {
+                    let millis = self.clock.now().saturating_duration_since(t).as_millis() as u64;
+                    millis / heartbeat_interval * heartbeat_interval
+                })
+            } else {
+                None
+            };
+
         let m = RaftMetrics {
             id: self.id,
-            state: self.target_state,
+            state: if self.is_uninitialized() {
+                State::Uninitialized
+            } else {
+                self.target_state
+            },
             current_term: self.current_term,
+            max_term_seen: std::cmp::max(self.max_term_seen, self.current_term),
             last_log_index: self.last_log_id.index,
             last_applied: self.last_applied.index,
+            last_committed: non_zero(self.committed),
             current_leader: self.current_leader,
+            leader_ready: self.target_state == State::Leader && self.committed.term == self.current_term,
             membership_config: self.effective_membership.clone(),
             snapshot: self.snapshot_last_log_id,
+            snapshot_building,
+            installing_snapshot_progress,
+            millis_since_last_heartbeat,
+            conflicts_reported: self.conflicts_reported,
             leader_metrics,
         };
 
+        // Do not wake up the subscribers if nothing changes.
+        if *self.tx_metrics.borrow() == m {
+            return;
+        }
+
         tracing::debug!("report_metrics: {}", m.summary());
         let res = self.tx_metrics.send(m);
 
         if let Err(err) = res {
-            tracing::error!(error=%err, id=self.id, "error reporting metrics");
+            tracing::error!(error=%err, id=%self.id, "error reporting metrics");
+        }
+    }
+
+    /// The Raft node's current hard state.
+    ///
+    /// A term or vote is updated in memory only right before it is saved with `save_hard_state()`, in the same
+    /// message handling. Thus it is always the same as what is persisted when another message is handled.
+    fn hard_state(&self) -> HardState<NID> {
+        HardState {
+            current_term: self.current_term,
+            voted_for: self.voted_for,
         }
     }
 
     /// Save the Raft node's current hard state to disk.
     #[tracing::instrument(level = "trace", skip(self))]
     async fn save_hard_state(&mut self) -> RaftResult<()> {
-        let hs = HardState {
-            current_term: self.current_term,
-            voted_for: self.voted_for,
-        };
-        self.storage.save_hard_state(&hs).await.map_err(|err| self.map_storage_error(err))
+        let hs = self.hard_state();
+        self.storage.save_hard_state(&hs).await.map_err(|err| self.map_storage_error(err))?;
+
+        if self.config.durability.sync_hard_state() {
+            self.storage.flush_hard_state().await.map_err(|err| self.map_storage_error(err))?;
+        }
+        Ok(())
+    }
+
+    /// A learner with an empty log and term 0 has neither been initialized nor heard from a leader.
+    fn is_uninitialized(&self) -> bool {
+        self.target_state.is_learner() && self.last_log_id.index == 0 && self.current_term == 0
     }
 
     /// Update core's target state, ensuring all invariants are upheld.
-    #[tracing::instrument(level = "trace", skip(self), fields(id=self.id))]
+    #[tracing::instrument(level = "trace", skip(self), fields(id=%self.id))]
     fn set_target_state(&mut self, target_state: State) {
-        tracing::debug!(id = self.id, ?target_state, "set_target_state");
+        tracing::debug!(id = %self.id, ?target_state, "set_target_state");
+
+        // Only hearing from a leader resumes elections, see `resume_elections()`.
+        if target_state == State::Follower && self.target_state == State::Paused {
+            return;
+        }
 
         if target_state == State::Follower && !self.effective_membership.membership.contains(&self.id) {
             self.target_state = State::Learner;
@@ -337,6 +593,67 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Count an election round that did not make this node the leader, and pause elections if there are too many in
+    /// a row.
+    fn count_failed_election(&mut self) {
+        if !self.target_state.is_candidate() {
+            return;
+        }
+
+        self.failed_elections += 1;
+
+        let max = self.config.max_consecutive_failed_elections;
+        if max > 0 && self.failed_elections >= max {
+            tracing::warn!(
+                id = %self.id,
+                failed_elections = self.failed_elections,
+                term = self.current_term,
+                "too many failed elections in a row, pause elections until a leader is heard from"
+            );
+            self.set_target_state(State::Paused);
+        }
+    }
+
+    /// Reset the count of failed elections as a leader is heard from, and resume elections if they are paused.
+    ///
+    /// A node that backs off for its log is stale resumes only after its log advances.
+    fn resume_elections(&mut self) {
+        if let Some(stale_log_id) = self.stale_log_id {
+            if self.last_log_id <= stale_log_id {
+                return;
+            }
+            tracing::info!(id = %self.id, %stale_log_id, last_log_id = %self.last_log_id, "log advanced");
+            self.stale_log_id = None;
+            self.stale_log_deadline = None;
+        }
+
+        self.failed_elections = 0;
+        self.stale_log_elections = 0;
+
+        if self.target_state.is_paused() {
+            tracing::info!(id = %self.id, "a leader is heard from, resume elections");
+            self.target_state = State::Follower;
+        }
+    }
+
+    /// Resume elections when a node has backed off for its stale log for too long, see
+    /// `STALE_LOG_BACKOFF_ELECTION_TIMEOUTS`.
+    fn end_stale_log_backoff(&mut self) {
+        tracing::info!(
+            id = %self.id,
+            last_log_id = %self.last_log_id,
+            "log did not advance while backing off for it is stale, resume elections"
+        );
+
+        self.stale_log_id = None;
+        self.stale_log_deadline = None;
+        self.stale_log_elections = 0;
+
+        if self.target_state.is_paused() {
+            self.target_state = State::Follower;
+        }
+    }
+
     /// Get the next election timeout, generating a new value if not set.
     #[tracing::instrument(level = "trace", skip(self))]
     fn get_next_election_timeout(&mut self) -> Instant {
@@ -345,7 +662,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             None => {
                 let t = Duration::from_millis(self.config.new_rand_election_timeout());
                 tracing::debug!("create election timeout after: {:?}", t);
-                let inst = Instant::now() + t;
+                let inst = self.clock.now() + t;
                 self.next_election_timeout = Some(inst);
                 inst
             }
@@ -354,12 +671,18 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Set a value for the next election timeout.
     ///
-    /// If `heartbeat=true`, then also update the value of `last_heartbeat`.
+    /// If `heartbeat=true`, then also update the value of `last_heartbeat`, and wait an extra delay for the leader
+    /// heard from to reassert itself, see `Config::leader_stickiness`.
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_next_election_timeout(&mut self, heartbeat: bool) {
-        let now = Instant::now();
+        let now = self.clock.now();
 
-        let t = Duration::from_millis(self.config.new_rand_election_timeout());
+        let mut millis = self.config.new_rand_election_timeout();
+        if heartbeat {
+            millis += self.config.new_rand_leader_stickiness();
+        }
+
+        let t = Duration::from_millis(millis);
         tracing::debug!("update election timeout after: {:?}", t);
 
         self.next_election_timeout = Some(now + t);
@@ -370,7 +693,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Update the value of the `current_leader` property.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_current_leader(&mut self, update: UpdateCurrentLeader) {
+    fn update_current_leader(&mut self, update: UpdateCurrentLeader<NID>) {
         match update {
             UpdateCurrentLeader::ThisNode => {
                 self.current_leader = Some(self.id);
@@ -386,13 +709,36 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Encapsulate the process of updating the current term, as updating the `voted_for` state must also be updated.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_current_term(&mut self, new_term: u64, voted_for: Option<NodeId>) {
+    fn update_current_term(&mut self, new_term: u64, voted_for: Option<NID>) {
+        self.observe_term(new_term);
+
         if new_term > self.current_term {
             self.current_term = new_term;
             self.voted_for = voted_for;
         }
     }
 
+    /// Record a term received from another node, and warn if it is suspiciously greater than the local term.
+    ///
+    /// A term is warned about only once, when it is seen for the first time.
+    fn observe_term(&mut self, term: u64) {
+        if term <= self.max_term_seen {
+            return;
+        }
+        self.max_term_seen = term;
+
+        let gap = self.config.suspicious_term_gap;
+        if gap > 0 && term > self.current_term.saturating_add(gap) {
+            tracing::warn!(
+                id = %self.id,
+                current_term = self.current_term,
+                term,
+                suspicious_term_gap = gap,
+                "received a term suspiciously greater than the local term, a misbehaving node or a clock issue?"
+            );
+        }
+    }
+
     /// Trigger the shutdown sequence due to a non-recoverable error from the storage layer.
     ///
     /// This method assumes that a storage error observed here is non-recoverable. As such, the
@@ -400,20 +746,83 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// interface.
     #[tracing::instrument(level = "trace", skip(self))]
     fn map_fatal_storage_error(&mut self, err: anyhow::Error) -> RaftError {
-        tracing::error!({error=?err, id=self.id}, "fatal storage error, shutting down");
+        tracing::error!({error=?err, id=%self.id}, "fatal storage error, shutting down");
         self.set_target_state(State::Shutdown);
         RaftError::RaftStorage(err)
     }
 
-    fn map_storage_error(&mut self, err: StorageError) -> RaftError {
-        tracing::error!({error=?err, id=self.id}, "fatal storage error, shutting down");
+    /// Stop serving on a request from the application.
+    fn handle_shutdown(&mut self, graceful: bool) {
+        tracing::info!(graceful, "recv shutdown request");
+        self.graceful_shutdown = graceful;
+        self.set_target_state(State::Shutdown);
+    }
+
+    /// Apply every committed entry and save the hard state, so that nothing is left to redo on restart.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn drain_for_shutdown(&mut self) -> RaftResult<()> {
+        self.replicate_to_state_machine_if_needed().await?;
+        self.save_hard_state().await?;
+
+        if self.config.snapshot_on_shutdown {
+            self.snapshot_for_shutdown().await;
+        }
+        Ok(())
+    }
+
+    /// Build a snapshot of the last applied log, unless the last snapshot already includes it.
+    ///
+    /// A snapshot being built is waited for first, so that two are never built at the same time. A failure is only
+    /// logged: the node restarts by re-applying the logs, as if there is no snapshot on shutdown.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn snapshot_for_shutdown(&mut self) {
+        // A snapshot being received from the leader is abandoned on shutdown.
+        if let Some(SnapshotState::Streaming { .. }) = &self.snapshot_state {
+            self.snapshot_state = None;
+        }
+
+        while let Some(SnapshotState::Snapshotting { .. }) = &self.snapshot_state {
+            match self.rx_compaction.recv().await {
+                Some(update) => self.update_snapshot_state(update),
+                None => return,
+            }
+        }
+
+        if self.snapshot_last_log_id >= self.last_applied {
+            tracing::info!(snapshot_last_log_id = %self.snapshot_last_log_id, "snapshot is up to date, skip it");
+            return;
+        }
+
+        let mut rx = match self.trigger_log_compaction_if_needed(true) {
+            Some(rx) => rx,
+            None => return,
+        };
+
+        match rx.recv().await {
+            Ok(meta) => {
+                tracing::info!(last_log_id = %meta.last_log_id, "snapshot is built on shutdown");
+                self.snapshot_state = None;
+                self.snapshot_last_log_id = meta.last_log_id;
+            }
+            Err(_) => {
+                tracing::warn!("failed to build snapshot on shutdown");
+            }
+        }
+    }
+
+    fn map_storage_error(&mut self, err: StorageError<NID>) -> RaftError {
+        if err.is_transient() {
+            tracing::error!({error=?err, id=%self.id, retries=self.config.storage_retry_attempts}, "storage error persists after retries, shutting down");
+        } else {
+            tracing::error!({error=?err, id=%self.id}, "fatal storage error, shutting down");
+        }
         self.set_target_state(State::Shutdown);
         RaftError::RaftStorage(err.into())
     }
 
     /// Update the node's current membership config & save hard state.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_membership(&mut self, cfg: EffectiveMembership) -> RaftResult<()> {
+    fn update_membership(&mut self, cfg: EffectiveMembership<NID>) -> RaftResult<()> {
         // If the given config does not contain this node's ID, it means one of the following:
         //
         // - the node is currently a learner and is replicating an old config to which it has
@@ -421,7 +830,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // - the node has been removed from the cluster. The parent application can observe the
         // transition to the learner state as a signal for when it is safe to shutdown a node
         // being removed.
-        self.effective_membership = cfg;
+        self.set_effective_membership(cfg);
         if self.effective_membership.membership.contains(&self.id) {
             if self.target_state == State::Learner {
                 // The node is a Learner and the new config has it configured as a normal member.
@@ -434,58 +843,171 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         Ok(())
     }
 
+    /// Replace the effective membership config, and broadcast a `MembershipChanged` event if it changed.
+    fn set_effective_membership(&mut self, cfg: EffectiveMembership<NID>) {
+        if self.effective_membership != cfg {
+            let _ = self.tx_events.send(RaftEvent::MembershipChanged(cfg.clone()));
+        }
+        self.effective_membership = cfg;
+    }
+
+    /// Apply a config delta to the current config if the result is valid.
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn update_config(&mut self, delta: &ConfigDelta) -> Result<(), UpdateConfigError> {
+        let config = delta.apply_to(&self.config)?;
+        tracing::info!(id = %self.id, ?config, "update config");
+
+        self.config = Arc::new(config);
+        let _ = self.tx_config.send(self.config.clone());
+        Ok(())
+    }
+
     /// Update the system's snapshot state based on the given data.
     #[tracing::instrument(level = "trace", skip(self))]
     fn update_snapshot_state(&mut self, update: SnapshotUpdate) {
         if let SnapshotUpdate::SnapshotComplete(log_id) = update {
             self.snapshot_last_log_id = log_id;
-            self.report_metrics(Update::Ignore);
+            self.snapshot_last_time = self.clock.now();
         }
         // If snapshot state is anything other than streaming, then drop it.
         if let Some(state @ SnapshotState::Streaming { .. }) = self.snapshot_state.take() {
             self.snapshot_state = Some(state);
         }
+        self.report_metrics(Update::Ignore);
+    }
+
+    /// Query the size of the log from the storage, if the snapshot policy depends on it.
+    ///
+    /// A failure is only logged, and the last known size is kept.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(self) async fn refresh_log_storage_size(&mut self) {
+        if !matches!(self.config.snapshot_policy, SnapshotPolicy::SizeSinceLast(_)) || self.snapshot_state.is_some() {
+            return;
+        }
+
+        match self.storage.log_storage_size().await {
+            Ok(size) => {
+                self.log_storage_size = size;
+                // The log is purged since the last snapshot, count the bytes from the lowest point.
+                self.snapshot_log_storage_size = self.snapshot_log_storage_size.min(size);
+            }
+            Err(err) => {
+                tracing::warn!(error=%err, "failed to get log storage size");
+            }
+        }
+    }
+
+    /// When a time based snapshot policy is due, if there are applied logs not included in the last snapshot.
+    ///
+    /// A node checks the policy at this time even if it applies nothing, thus an idle node still builds a snapshot.
+    pub(self) fn next_snapshot_deadline(&self) -> Option<Instant> {
+        if self.snapshot_state.is_some() || self.last_applied.index <= self.snapshot_last_log_id.index {
+            return None;
+        }
+
+        match &self.config.snapshot_policy {
+            SnapshotPolicy::EveryDuration(interval) => Some(self.snapshot_last_time + *interval),
+            SnapshotPolicy::LogsOrDuration { duration, .. } if !duration.is_zero() => {
+                Some(self.snapshot_last_time + *duration)
+            }
+            _ => None,
+        }
     }
 
     /// Trigger a log compaction (snapshot) job if needed.
     /// If force is True, it will skip the threshold check and start creating snapshot as demanded.
+    ///
+    /// It returns a receiver of the meta of the snapshot if a job is started. The receiver is closed without a
+    /// value if the job fails.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub(self) fn trigger_log_compaction_if_needed(&mut self, force: bool) {
+    pub(self) fn trigger_log_compaction_if_needed(&mut self, force: bool) -> Option<broadcast::Receiver<SnapshotMeta>> {
         if self.snapshot_state.is_some() {
-            return;
+            return None;
         }
-        let SnapshotPolicy::LogsSinceLast(threshold) = &self.config.snapshot_policy;
         // Check to ensure we have actual entries for compaction.
         if self.last_applied.index == 0 || self.last_applied.index < self.snapshot_last_log_id.index {
-            return;
+            return None;
         }
 
         if !force {
             // If we are below the threshold, then there is nothing to do.
-            if self.last_applied.index < self.snapshot_last_log_id.index + *threshold {
-                return;
+            match &self.config.snapshot_policy {
+                SnapshotPolicy::LogsSinceLast(threshold) => {
+                    if self.last_applied.index < self.snapshot_last_log_id.index + *threshold {
+                        return None;
+                    }
+                }
+                SnapshotPolicy::EveryDuration(interval) => {
+                    // No new applied logs since last snapshot, a new snapshot would be the same.
+                    if self.last_applied.index == self.snapshot_last_log_id.index {
+                        return None;
+                    }
+                    if self.clock.now().saturating_duration_since(self.snapshot_last_time) < *interval {
+                        return None;
+                    }
+                }
+                SnapshotPolicy::LogsOrDuration { logs, duration } => {
+                    let logs_reached = *logs > 0 && self.last_applied.index >= self.snapshot_last_log_id.index + *logs;
+                    let duration_reached = !duration.is_zero()
+                        && self.last_applied.index > self.snapshot_last_log_id.index
+                        && self.clock.now().saturating_duration_since(self.snapshot_last_time) >= *duration;
+
+                    if !logs_reached && !duration_reached {
+                        return None;
+                    }
+                }
+                SnapshotPolicy::SizeSinceLast(threshold) => {
+                    if self.log_storage_size.saturating_sub(self.snapshot_log_storage_size) < *threshold {
+                        return None;
+                    }
+                }
             }
         }
 
         // At this point, we are clear to begin a new compaction process.
         let storage = self.storage.clone();
         let (handle, reg) = AbortHandle::new_pair();
-        let (chan_tx, _) = broadcast::channel(1);
+        let (chan_tx, chan_rx) = broadcast::channel(1);
         let tx_compaction = self.tx_compaction.clone();
+        let purge_after_snapshot = self.config.log_purge_policy == LogPurgePolicy::AfterSnapshot;
+        let max_keep = self.config.max_applied_log_to_keep;
+        let compact_noop = self.config.compact_noop_on_snapshot;
+        let tx_events = self.tx_events.clone();
+        let sm_applied = self.sm_applied.clone();
+        self.snapshot_log_storage_size = self.log_storage_size;
         self.snapshot_state = Some(SnapshotState::Snapshotting {
             handle,
             sender: chan_tx.clone(),
         });
+        self.report_metrics(Update::Ignore);
 
         tokio::spawn(
             async move {
-                let f = storage.do_log_compaction();
+                let f = build_snapshot(storage.clone(), sm_applied);
                 let res = Abortable::new(f, reg).await;
                 match res {
                     Ok(res) => match res {
                         Ok(snapshot) => {
+                            if purge_after_snapshot {
+                                // The snapshot is persisted, the logs it includes are no longer needed.
+                                let last_log_id = snapshot.meta.last_log_id;
+                                let res =
+                                    delete_applied_logs(storage.clone(), &last_log_id, max_keep, &tx_events).await;
+                                if let Err(err) = res {
+                                    tracing::error!({error=%err}, "error while purging logs included in snapshot");
+                                }
+                            }
+                            if compact_noop {
+                                let last_log_id = snapshot.meta.last_log_id;
+                                let res =
+                                    delete_leading_blank_logs(storage.clone(), &last_log_id, max_keep, &tx_events)
+                                        .await;
+                                if let Err(err) = res {
+                                    tracing::error!({error=%err}, "error while purging blank logs included in snapshot");
+                                }
+                            }
                             let _ = tx_compaction.try_send(SnapshotUpdate::SnapshotComplete(snapshot.meta.last_log_id));
-                            let _ = chan_tx.send(snapshot.meta.last_log_id.index); // This will always succeed.
+                            let _ = chan_tx.send(snapshot.meta); // This will always succeed.
                         }
                         Err(err) => {
                             tracing::error!({error=%err}, "error while generating snapshot");
@@ -499,18 +1021,107 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
             .instrument(tracing::debug_span!("beginning new log compaction process")),
         );
+
+        Some(chan_rx)
+    }
+
+    /// Build a snapshot right away, regardless of the snapshot policy, and respond with its meta once it is built.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    fn handle_trigger_snapshot(&mut self, tx: RaftRespTx<SnapshotMeta, TriggerSnapshotError>) {
+        if self.snapshot_state.is_some() {
+            let _ = tx.send(Err(TriggerSnapshotError::InProgress));
+            return;
+        }
+
+        let mut rx = match self.trigger_log_compaction_if_needed(true) {
+            Some(rx) => rx,
+            None => {
+                let _ = tx.send(Err(TriggerSnapshotError::NothingToSnapshot));
+                return;
+            }
+        };
+
+        tokio::spawn(
+            async move {
+                let res = rx.recv().await.map_err(|_| TriggerSnapshotError::Failed);
+                let _ = tx.send(res);
+            }
+            .instrument(tracing::debug_span!("wait for triggered snapshot")),
+        );
+    }
+
+    /// Reset the state machine to the current snapshot and re-apply the logs that were applied after it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn rebuild_state_machine(&mut self) -> Result<LogId, RebuildStateMachineError> {
+        if self.snapshot_state.is_some() {
+            return Err(RebuildStateMachineError::SnapshotInProgress);
+        }
+
+        let last_applied = self.last_applied;
+
+        // Make sure every log to re-apply is present before resetting anything.
+        let since = self.snapshot_last_log_id.index + 1;
+        if last_applied.index >= since {
+            let first = self.storage.first_id_in_log().await.map_err(|e| self.map_storage_error(e))?;
+            if first.map(|x| x.index > since).unwrap_or(true) {
+                return Err(RebuildStateMachineError::LogsPurged { since });
+            }
+        }
+
+        let reset_to = {
+            let sm_applied = self.sm_applied.clone();
+            let mut sm_applied = sm_applied.lock().await;
+            let reset_to = self.storage.reset_state_machine().await.map_err(|e| self.map_storage_error(e))?;
+            let reset_to = reset_to.ok_or(RebuildStateMachineError::NoSnapshot)?;
+            *sm_applied = reset_to;
+            reset_to
+        };
+        tracing::info!(%reset_to, %last_applied, "state machine is reset, re-apply logs");
+
+        if last_applied.index > reset_to.index {
+            let entries = self
+                .storage
+                .get_log_entries(reset_to.index + 1..=last_applied.index)
+                .await
+                .map_err(|e| self.map_storage_error(e))?;
+
+            let entries_refs: Vec<_> = entries.iter().collect();
+
+            apply_to_state_machine(
+                self.storage.clone(),
+                &self.sm_applied,
+                &entries_refs,
+                self.config.max_logs_to_keep_on_apply(),
+                self.config.max_apply_batch_size,
+                &self.tx_events,
+            )
+            .await
+            .map_err(|e| self.map_storage_error(e))?;
+        }
+
+        self.last_applied = std::cmp::max(last_applied, reset_to);
+        self.report_metrics(Update::Ignore);
+
+        Ok(self.last_applied)
     }
 
     /// Reject an init config request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
     fn reject_init_with_config(&self, tx: oneshot::Sender<Result<(), InitializeError>>) {
-        let _ = tx.send(Err(InitializeError::NotAllowed));
+        let err = if self.last_log_id.index != 0 {
+            InitializeError::AlreadyInitialized {
+                last_log_id: self.last_log_id,
+            }
+        } else {
+            InitializeError::NotAllowed
+        };
+        let _ = tx.send(Err(err));
     }
 
     /// Reject a proposed config change request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
     fn reject_config_change_not_leader<T, E>(&self, tx: RaftRespTx<T, E>)
-    where E: From<ForwardToLeader> {
+    where E: From<ForwardToLeader<NID>> {
         let err = ForwardToLeader {
             leader_id: self.current_leader,
         };
@@ -522,14 +1133,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "trace", skip(self, req, tx))]
     fn forward_client_write_request(
         &self,
-        req: ClientWriteRequest<D>,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        req: ClientWriteRequest<D, NID>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
     ) {
         match req.entry {
+            EntryPayload::Normal(_entry) if self.is_uninitialized() => {
+                let _ = tx.send(Err(ClientWriteError::Uninitialized));
+            }
             EntryPayload::Normal(_entry) => {
-                let _ = tx.send(Err(ClientWriteError::ForwardToLeader(ForwardToLeader {
-                    leader_id: self.current_leader,
-                })));
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.current_leader)));
             }
             _ => {
                 // This is unreachable, and well controlled by the type system, but let's log an
@@ -541,44 +1153,218 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Forward the given client read request to the leader.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    fn forward_client_read_request(&self, tx: RaftRespTx<(), ClientReadError>) {
+    fn forward_client_read_request(&self, tx: RaftRespTx<LogId, ClientReadError<NID>>) {
         let _ = tx.send(Err(ClientReadError::ForwardToLeader(ForwardToLeader {
             leader_id: self.current_leader,
         })));
     }
+
+    /// Respond to a local read if the state machine has been in sync with the leader within `max_staleness`.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    fn handle_read_local_request(&self, max_staleness: Duration, tx: RaftRespTx<LogId, ClientReadError<NID>>) {
+        let fresh = match self.last_leader_sync {
+            Some(t) => self.clock.now().saturating_duration_since(t) <= max_staleness,
+            None => false,
+        };
+
+        let res = if fresh {
+            Ok(self.last_applied)
+        } else {
+            Err(ClientReadError::Stale {
+                leader_id: self.current_leader,
+                max_staleness,
+            })
+        };
+        let _ = tx.send(res);
+    }
+
+    /// Check if the entry of `log_id` has been applied to the state machine of this node.
+    ///
+    /// A log id that is not yet in the log is regarded as not applied, since it may be replicated later.
+    /// An entry that has been purged after being applied is regarded as applied.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn check_applied(&mut self, log_id: LogId) -> Result<bool, AppliedError> {
+        if log_id.index > self.last_log_id.index && log_id.index > self.last_applied.index {
+            return Ok(false);
+        }
+
+        let found = if log_id.index == self.last_applied.index {
+            Some(self.last_applied)
+        } else if log_id.index == self.snapshot_last_log_id.index {
+            Some(self.snapshot_last_log_id)
+        } else {
+            let ent = self.storage.try_get_log_entry(log_id.index).await.map_err(|err| self.map_storage_error(err))?;
+            ent.map(|x| x.log_id)
+        };
+
+        match found {
+            Some(found) if found != log_id => Err(AppliedError::NotInLog { log_id, found }),
+            _ => Ok(log_id.index <= self.last_applied.index),
+        }
+    }
+
+    /// Read the logs in `[start, end)`.
+    ///
+    /// Only applied logs are read, unless `read_unapplied` is set, in which case any log in the log store is read,
+    /// including those not yet committed.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_log_entries(
+        &mut self,
+        start: u64,
+        end: u64,
+        read_unapplied: bool,
+    ) -> Result<Vec<Entry<D, NID>>, LogReadError> {
+        if start > end {
+            return Err(LogReadError::InvalidRange { start, end });
+        }
+
+        if read_unapplied {
+            if end > self.last_log_id.index + 1 {
+                return Err(LogReadError::BeyondLastLog {
+                    start,
+                    end,
+                    last_log_id: self.last_log_id,
+                });
+            }
+        } else if end > self.last_applied.index + 1 {
+            return Err(LogReadError::NotApplied {
+                start,
+                end,
+                last_applied: self.last_applied,
+            });
+        }
+
+        if start == end {
+            return Ok(vec![]);
+        }
+
+        let first = self.storage.first_id_in_log().await.map_err(|err| self.map_storage_error(err))?;
+        let first = first.map(|x| x.index).unwrap_or(self.last_log_id.index + 1);
+        if start < first {
+            return Err(LogReadError::Purged { start, first });
+        }
+
+        let entries = self.storage.get_log_entries(start..end).await.map_err(|err| self.map_storage_error(err))?;
+        Ok(entries)
+    }
 }
 
-#[tracing::instrument(level = "trace", skip(sto), fields(entries=%entries.summary()))]
-async fn apply_to_state_machine<D, R, S>(
+/// Build a snapshot of the state machine.
+///
+/// If the storage takes a view of the state machine, see `RaftStorage::snapshot_view()`, applying is paused while it
+/// is being taken, and the snapshot is built from it at `sm_applied`. Otherwise it is built with
+/// `RaftStorage::do_log_compaction()` while logs are applied.
+async fn build_snapshot<D, R, S, NID>(
     sto: Arc<S>,
-    entries: &[&Entry<D>],
+    sm_applied: Arc<Mutex<LogId>>,
+) -> Result<Snapshot<S::SnapshotData>, StorageError<NID>>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+{
+    let (last_applied, view) = {
+        let sm_applied = sm_applied.lock().await;
+        (*sm_applied, sto.snapshot_view(*sm_applied).await?)
+    };
+
+    let view = match view {
+        None => return sto.do_log_compaction().await,
+        Some(x) => x,
+    };
+
+    let snapshot = view.build_snapshot().await?;
+
+    if snapshot.meta.last_log_id != last_applied {
+        let err = anyhow::anyhow!(
+            "snapshot is built at {}, but the view is taken at {}",
+            snapshot.meta.last_log_id,
+            last_applied
+        );
+        return Err(StorageIOError::new(ErrorSubject::Snapshot(snapshot.meta.clone()), ErrorVerb::Write, err).into());
+    }
+
+    Ok(snapshot)
+}
+
+/// Apply `entries` in batches of at most `max_batch` entries, in log order, then purge the applied logs.
+///
+/// `sm_applied` is locked while applying, and is set to the last applied log id.
+#[tracing::instrument(level = "trace", skip(sto, sm_applied, tx_events), fields(entries=%entries.summary()))]
+async fn apply_to_state_machine<D, R, S, NID>(
+    sto: Arc<S>,
+    sm_applied: &Mutex<LogId>,
+    entries: &[&Entry<D, NID>],
     max_keep: u64,
-) -> Result<Vec<R>, StorageError>
+    max_batch: u64,
+    tx_events: &EventTx<NID>,
+) -> Result<Vec<R>, StorageError<NID>>
 where
     D: AppData,
     R: AppDataResponse,
-    S: RaftStorage<D, R>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
 {
-    tracing::debug!(entries=%entries.summary(), max_keep, "apply_to_state_machine");
+    tracing::debug!(entries=%entries.summary(), max_keep, max_batch, "apply_to_state_machine");
 
     let last = entries.last().map(|x| x.log_id);
 
     if let Some(last_applied) = last {
-        // TODO(xp): apply_to_state_machine should return the last applied
-        let res = sto.apply_to_state_machine(entries).await?;
-        delete_applied_logs(sto, &last_applied, max_keep).await?;
+        let mut res = Vec::with_capacity(entries.len());
+
+        {
+            let mut sm_applied = sm_applied.lock().await;
+
+            // TODO(xp): apply_to_state_machine should return the last applied
+            for batch in entries.chunks(max_batch as usize) {
+                res.extend(sto.apply_to_state_machine(batch).await?);
+            }
+            *sm_applied = last_applied;
+        }
+
+        delete_applied_logs(sto, &last_applied, max_keep, tx_events).await?;
         Ok(res)
     } else {
         Ok(vec![])
     }
 }
 
-#[tracing::instrument(level = "trace", skip(sto))]
-async fn delete_applied_logs<D, R, S>(sto: Arc<S>, last_applied: &LogId, max_keep: u64) -> Result<(), StorageError>
+/// Run a storage operation, and run it again if it fails with a transient error, at most
+/// `Config::storage_retry_attempts` times, after a backoff of `Config::storage_retry_backoff` doubled every retry.
+async fn retry_transient<T, NID, F, Fut>(clock: &dyn Clock, config: &Config, mut op: F) -> Result<T, StorageError<NID>>
+where
+    NID: RaftNodeId,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, StorageError<NID>>>,
+{
+    let retries = config.storage_retry_attempts;
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(err) if err.is_transient() && attempt < retries => {
+                let delay = config.storage_retry_delay(attempt as u32);
+                attempt += 1;
+                tracing::warn!(error=%err, attempt, retries, ?delay, "transient storage error, retry");
+                clock.sleep_until(clock.now() + delay).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(sto, tx_events))]
+async fn delete_applied_logs<D, R, S, NID>(
+    sto: Arc<S>,
+    last_applied: &LogId,
+    max_keep: u64,
+    tx_events: &EventTx<NID>,
+) -> Result<(), StorageError<NID>>
 where
     D: AppData,
     R: AppDataResponse,
-    S: RaftStorage<D, R>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
 {
     // TODO(xp): periodically batch delete
     let x = last_applied.index + 1;
@@ -586,18 +1372,88 @@ where
 
     tracing::debug!(%last_applied, max_keep, delete_lt = x, "delete_applied_logs");
 
-    if x > 0 {
-        sto.delete_logs_from(..x).await
+    if x == 0 {
+        return Ok(());
+    }
+
+    // The last log to purge is not in the log if it is already purged.
+    let last_purged = if x - 1 == last_applied.index {
+        Some(*last_applied)
     } else {
-        Ok(())
+        sto.try_get_log_entry(x - 1).await?.map(|ent| ent.log_id)
+    };
+
+    sto.delete_logs_from(..x).await?;
+
+    if let Some(log_id) = last_purged {
+        let _ = tx_events.send(RaftEvent::LogPurged(log_id));
+    }
+    Ok(())
+}
+
+/// The number of logs read at a time when scanning for blank logs to purge.
+const BLANK_LOG_SCAN_BATCH: u64 = 64;
+
+/// Delete the run of blank logs at the head of the log, upto `upto`, which are all included in a snapshot.
+///
+/// Logs are purged from the head and must stay consecutive, thus only the leading run can be purged; a blank log
+/// after a non-blank one is kept. At least `max_keep` logs, counting the ones after `upto`, are left in the log for
+/// lagging followers.
+#[tracing::instrument(level = "trace", skip(sto, tx_events))]
+async fn delete_leading_blank_logs<D, R, S, NID>(
+    sto: Arc<S>,
+    upto: &LogId,
+    max_keep: u64,
+    tx_events: &EventTx<NID>,
+) -> Result<(), StorageError<NID>>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+{
+    let first = match sto.first_id_in_log().await? {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let last = sto.last_id_in_log().await?;
+
+    // Purge no log at or after `end`.
+    let end = std::cmp::min(upto.index + 1, (last.index + 1).saturating_sub(max_keep));
+
+    let mut delete_lt = first.index;
+    let mut last_purged = None;
+
+    while delete_lt < end {
+        let batch_end = std::cmp::min(delete_lt + BLANK_LOG_SCAN_BATCH, end);
+        let entries = sto.try_get_log_entries(delete_lt..batch_end).await?;
+
+        let blanks = entries.iter().take_while(|ent| matches!(ent.payload, EntryPayload::Blank));
+        for ent in blanks {
+            last_purged = Some(ent.log_id);
+            delete_lt += 1;
+        }
+
+        if delete_lt < batch_end {
+            break;
+        }
     }
+
+    tracing::debug!(%first, %upto, max_keep, delete_lt, "delete_leading_blank_logs");
+
+    if let Some(log_id) = last_purged {
+        sto.delete_logs_from(..delete_lt).await?;
+        let _ = tx_events.send(RaftEvent::LogPurged(log_id));
+    }
+    Ok(())
 }
 
 /// An enum describing the way the current leader property is to be updated.
 #[derive(Debug)]
-pub(self) enum UpdateCurrentLeader {
+pub(self) enum UpdateCurrentLeader<NID: RaftNodeId> {
     Unknown,
-    OtherNode(NodeId),
+    OtherNode(NID),
     ThisNode,
 }
 
@@ -608,16 +1464,22 @@ pub(self) enum SnapshotState<S> {
         /// A handle to abort the compaction process early if needed.
         handle: AbortHandle,
         /// A sender for notifiying any other tasks of the completion of this compaction.
-        sender: broadcast::Sender<u64>,
+        sender: broadcast::Sender<SnapshotMeta>,
     },
     /// The Raft node is streaming in a snapshot from the leader.
     Streaming {
-        /// The offset of the last byte written to the snapshot.
+        /// The offset of the last byte received for the snapshot.
         offset: u64,
+        /// The total size of the snapshot.
+        size: u64,
         /// The ID of the snapshot being written.
         id: String,
-        /// A handle to the snapshot writer.
-        snapshot: Box<S>,
+        /// Sends the received chunks to the snapshot writer.
+        sender: SnapshotSender,
+        /// The chunks that arrive ahead of the chunks before them, by offset, to write once the gap is filled.
+        ahead: BTreeMap<u64, SnapshotChunk>,
+        /// The task writing chunks to the snapshot, it returns the snapshot when the last chunk is written.
+        writer: JoinHandle<(std::io::Result<u32>, Box<S>)>,
     },
 }
 
@@ -633,8 +1495,17 @@ pub(self) enum SnapshotUpdate {
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// All possible states of a Raft node.
+///
+/// More states may be added, thus a `match` on it needs a wildcard arm.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum State {
+    /// The node has an empty log and term 0: `Raft::initialize()` is not called on it, and it has not heard from a
+    /// leader.
+    ///
+    /// It is reported in the metrics only, the node behaves like a learner. It rejects client writes with
+    /// `ClientWriteError::Uninitialized`.
+    Uninitialized,
     /// The node is completely passive; replicating entries, but neither voting nor timing out.
     Learner,
     /// The node is replicating logs from the leader.
@@ -643,11 +1514,22 @@ pub enum State {
     Candidate,
     /// The node is the Raft cluster leader.
     Leader,
+    /// The node failed `Config::max_consecutive_failed_elections` elections in a row, or backs off for its log is
+    /// stale with `Config::backoff_on_stale_log`, and stops starting elections.
+    ///
+    /// Otherwise it behaves like a follower. It becomes a follower again when it hears from a leader, or, if it backs
+    /// off for its stale log, when its log advances.
+    Paused,
     /// The Raft node is shutting down.
     Shutdown,
 }
 
 impl State {
+    /// Check if currently in uninitialized state.
+    pub fn is_uninitialized(&self) -> bool {
+        matches!(self, Self::Uninitialized)
+    }
+
     /// Check if currently in learner state.
     pub fn is_learner(&self) -> bool {
         matches!(self, Self::Learner)
@@ -667,34 +1549,85 @@ impl State {
     pub fn is_leader(&self) -> bool {
         matches!(self, Self::Leader)
     }
+
+    /// Check if currently in paused state.
+    pub fn is_paused(&self) -> bool {
+        matches!(self, Self::Paused)
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to the Raft leader.
-struct LeaderState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    pub(super) core: &'a mut RaftCore<D, R, N, S>,
+struct LeaderState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+> {
+    pub(super) core: &'a mut RaftCore<D, R, N, S, NID>,
 
     /// A mapping of node IDs the replication state of the target node.
-    pub(super) nodes: BTreeMap<NodeId, ReplicationState<D>>,
+    pub(super) nodes: BTreeMap<NID, ReplicationState<D, NID>>,
 
     /// The metrics about a leader
-    pub leader_metrics: LeaderMetrics,
+    pub leader_metrics: LeaderMetrics<NID>,
 
     /// The stream of events coming from replication streams.
-    pub(super) replication_rx: mpsc::UnboundedReceiver<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_rx: mpsc::UnboundedReceiver<(ReplicaEvent<NID>, Span)>,
 
     /// The cloneable sender channel for replication stream events.
-    pub(super) replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_tx: mpsc::UnboundedSender<(ReplicaEvent<NID>, Span)>,
 
     /// A buffer of client requests which have been appended locally and are awaiting to be committed to the cluster.
-    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R>>,
+    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R, NID>>,
+
+    /// The leadership transfer in progress, if any. Writes are rejected until it ends.
+    pub(super) leadership_transfer: Option<LeadershipTransfer<NID>>,
+
+    /// The membership change waiting for its new voters to catch up, if any.
+    pub(super) membership_catch_up: Option<MembershipCatchUp<R, NID>>,
+
+    /// The worker applying committed entries to the state machine.
+    pub(super) apply_worker: ApplyWorker<D, R, NID>,
+
+    /// The permits to send a snapshot, shared by all replication streams.
+    pub(super) snapshot_sends: Arc<Semaphore>,
+
+    /// The write requests with an id this leader has appended but not yet applied.
+    pub(super) client_sessions: ClientSessions<R, NID>,
+
+    /// An acknowledgement of an AppendEntries sent no later than it does not count for the read lease, since a
+    /// TimeoutNow sent then lets the target be elected without waiting for its election timeout.
+    pub(super) lease_not_before: Option<Instant>,
+
+    /// The last log of this node synced to storage. Only logs upto it count toward a quorum, see
+    /// `Config::fsync_coalesce_window`.
+    pub(super) flushed: LogId,
+
+    /// When to sync the logs appended since the last sync, if there are any.
+    pub(super) flush_deadline: Option<Instant>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Create a new instance.
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         let (replication_tx, replication_rx) = mpsc::unbounded_channel();
+        let apply_worker = ApplyWorker::new(
+            core.storage.clone(),
+            core.last_applied,
+            core.sm_applied.clone(),
+            core.config.max_logs_to_keep_on_apply(),
+            core.config.max_apply_batch_size,
+            core.config.max_in_flight_applies == 1,
+            core.tx_events.clone(),
+        );
+        let snapshot_sends = Arc::new(Semaphore::new(core.config.max_concurrent_snapshot_sends as usize));
+        let flushed = core.last_log_id;
         Self {
             core,
             nodes: BTreeMap::new(),
@@ -702,11 +1635,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             replication_tx,
             replication_rx,
             awaiting_committed: Vec::new(),
+            leadership_transfer: None,
+            membership_catch_up: None,
+            apply_worker,
+            snapshot_sends,
+            client_sessions: ClientSessions::new(),
+            lease_not_before: None,
+            flushed,
+            flush_deadline: None,
         }
     }
 
     /// Transition to the Raft leader state.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="leader"))]
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="leader"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         // Spawn replication streams.
         let targets = self
@@ -724,24 +1665,55 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
 
         // Setup state as leader.
+        self.core.failed_elections = 0;
+        self.core.stale_log_elections = 0;
         self.core.last_heartbeat = None;
         self.core.next_election_timeout = None;
         self.core.update_current_leader(UpdateCurrentLeader::ThisNode);
+        // Signal the leadership before reporting it in the metrics, thus a metrics watcher that sees this node as the
+        // leader also sees it in `Raft::leadership()`.
+        let _ = self.core.tx_leadership.send(Some(self.core.current_term));
         self.leader_report_metrics();
+        let _ = self.core.tx_events.send(RaftEvent::BecameLeader(self.core.current_term));
+
+        let res = match self.commit_initial_leader_entry().await {
+            Ok(()) => {
+                // Elected with a locally overridden config, make it the config of the cluster.
+                if let Some(forced) = self.core.forced_membership.take() {
+                    self.append_forced_membership(forced.target, forced.membership, forced.tx).await;
+                }
+                self.leader_loop().await
+            }
+            Err(err) => Err(err),
+        };
 
-        self.commit_initial_leader_entry().await?;
+        // A follower must not acknowledge logs that are not synced.
+        if self.flush_deadline.is_some() {
+            let _ = self.flush_coalesced().await;
+        }
+
+        // The next state must not apply again the entries the leader has submitted.
+        self.drain_applies().await;
 
-        self.leader_loop().await
+        res
     }
 
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id))]
-    pub(self) async fn leader_loop(mut self) -> RaftResult<()> {
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id))]
+    pub(self) async fn leader_loop(&mut self) -> RaftResult<()> {
         loop {
             if !self.core.target_state.is_leader() {
                 tracing::info!("id={} state becomes: {:?}", self.core.id, self.core.target_state);
 
                 for node in self.nodes.values() {
-                    let _ = node.repl_stream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+                    let _ = node
+                        .repl_stream
+                        .repl_tx
+                        .send((crate::replication::RaftEvent::Terminate, tracing::debug_span!("CH")));
+                }
+
+                // The membership change has not been proposed, the client should retry with the next leader.
+                if let Some(pending) = self.membership_catch_up.take() {
+                    let _ = pending.tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
                 }
                 return Ok(());
             }
@@ -749,6 +1721,12 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             let span = tracing::debug_span!("CHrx:LeaderState");
             let _ent = span.enter();
 
+            let transfer_deadline = self.leadership_transfer.as_ref().map(|t| t.deadline);
+            let catch_up_deadline = self.membership_catch_up.as_ref().map(|c| c.deadline);
+            let learner_check = self.next_learner_catch_up_check();
+            let flush_deadline = self.flush_deadline;
+            let snapshot_deadline = self.core.next_snapshot_deadline();
+
             tokio::select! {
                 Some((msg,span)) = self.core.rx_api.recv() => {
                     self.handle_msg(msg).instrument(span).await;
@@ -762,16 +1740,34 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     let _ent = span.enter();
                     self.handle_replica_event(event).await;
                 }
-                Ok(_) = &mut self.core.rx_shutdown => {
-                    tracing::info!("leader recv from rx_shudown");
-                    self.core.set_target_state(State::Shutdown);
+                Some(applied) = self.apply_worker.rx_applied.recv() => {
+                    self.handle_applied(applied).await;
+                }
+                _ = self.core.clock.sleep_until(transfer_deadline.unwrap_or_else(|| self.core.clock.now())), if transfer_deadline.is_some() => {
+                    self.handle_leadership_transfer_timeout();
+                }
+                _ = self.core.clock.sleep_until(catch_up_deadline.unwrap_or_else(|| self.core.clock.now())), if catch_up_deadline.is_some() => {
+                    self.handle_membership_catch_up_timeout().await;
                 }
+                _ = self.core.clock.sleep_until(learner_check.unwrap_or_else(|| self.core.clock.now())), if learner_check.is_some() => {
+                    self.handle_learner_catch_up_check();
+                }
+                _ = self.core.clock.sleep_until(flush_deadline.unwrap_or_else(|| self.core.clock.now())), if flush_deadline.is_some() => {
+                    if self.flush_coalesced().await.is_ok() {
+                        self.try_commit().await;
+                        self.leader_report_metrics();
+                    }
+                }
+                _ = self.core.clock.sleep_until(snapshot_deadline.unwrap_or_else(|| self.core.clock.now())), if snapshot_deadline.is_some() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
+                Ok(graceful) = &mut self.core.rx_shutdown => self.core.handle_shutdown(graceful),
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "leader", id=self.core.id))]
-    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R>) {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "leader", id=%self.core.id))]
+    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID>) {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -784,11 +1780,44 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.handle_client_read_request(tx).await;
             }
+            RaftMsg::ReadLocal { max_staleness, tx } => {
+                self.handle_read_local_request(max_staleness, tx);
+            }
+            RaftMsg::CheckApplied { log_id, tx } => {
+                let _ = tx.send(self.core.check_applied(log_id).await);
+            }
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                tx,
+            } => {
+                let _ = tx.send(self.core.get_log_entries(start, end, read_unapplied).await);
+            }
+            RaftMsg::GetHardState { tx } => {
+                let _ = tx.send(Ok(self.core.hard_state()));
+            }
             RaftMsg::ClientWriteRequest { rpc, tx } => {
-                self.handle_client_write_request(rpc, tx).await;
+                if self.leadership_transfer.is_some() {
+                    self.reject_write_in_leadership_transfer(tx);
+                } else {
+                    self.handle_client_write_request(rpc, tx).await;
+                }
+            }
+            RaftMsg::ClientWriteBatch { reqs } => {
+                if self.leadership_transfer.is_some() {
+                    for (_rpc, tx) in reqs {
+                        self.reject_write_in_leadership_transfer(tx);
+                    }
+                } else {
+                    self.handle_client_write_batch(reqs).await;
+                }
             }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
@@ -796,8 +1825,54 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::AddLearner { id, tx, blocking } => {
                 self.add_learner(id, tx, blocking);
             }
-            RaftMsg::ChangeMembership { members, blocking, tx } => {
-                self.change_membership(members, blocking, tx).await;
+            RaftMsg::ChangeMembership {
+                members,
+                witnesses,
+                blocking_catch_up,
+                tx,
+            } => {
+                if self.leadership_transfer.is_some() {
+                    self.reject_write_in_leadership_transfer(tx);
+                } else {
+                    self.change_membership(members, witnesses, blocking_catch_up, tx).await;
+                }
+            }
+            RaftMsg::AbortMembershipChange { tx } => {
+                self.abort_membership_change(tx).await;
+            }
+            RaftMsg::ForceRemoveNode { id, tx } => {
+                if self.leadership_transfer.is_some() {
+                    self.reject_write_in_leadership_transfer(tx);
+                } else {
+                    self.force_remove_node(id, tx).await;
+                }
+            }
+            RaftMsg::CanChangeMembership { members, tx } => {
+                let _ = tx.send(self.can_change_membership(&members).map_err(ClientWriteError::ChangeMembershipError));
+            }
+            RaftMsg::UpdateConfig { delta, tx } => {
+                let _ = tx.send(self.update_config(&delta));
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::RebuildStateMachine { tx } => {
+                let _ = tx.send(Err(RebuildStateMachineError::NotAllowed(State::Leader)));
+            }
+            RaftMsg::TransferLeadership { target, tx } => {
+                self.transfer_leadership(target, tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.step_down(tx);
+            }
+            RaftMsg::LeaderLeaseRemaining { tx } => {
+                let _ = tx.send(Ok(Some(self.lease_remaining())));
+            }
+            RaftMsg::LastContact { target, tx } => {
+                let _ = tx.send(Ok(self.nodes.get(&target).and_then(|x| x.last_contact)));
+            }
+            RaftMsg::PauseReplication { target, paused, tx } => {
+                let _ = tx.send(self.pause_replication(target, paused));
             }
         }
     }
@@ -805,21 +1880,86 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Report metrics with leader specific states.
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn leader_report_metrics(&mut self) {
+        let last_log_id = self.core.last_log_id;
+
+        // The lag changes when either the leader appends logs or a target catches up.
+        for metrics in self.leader_metrics.replication.values_mut() {
+            metrics.lag = last_log_id.index.saturating_sub(metrics.matched.index);
+
+            if !matches!(
+                metrics.state,
+                ReplicationStatus::Snapshotting | ReplicationStatus::SnapshotQueued
+            ) {
+                let upto_date = is_matched_upto_date(&metrics.matched, &last_log_id, &self.core.config);
+                metrics.state = if upto_date && !metrics.paused {
+                    ReplicationStatus::LineRate
+                } else {
+                    ReplicationStatus::Lagging
+                };
+            }
+        }
+
+        let membership = &self.core.effective_membership.membership;
+        let config = &self.core.config;
+        let ready_learners: BTreeSet<NID> = self
+            .leader_metrics
+            .replication
+            .iter()
+            .filter(|(id, metrics)| {
+                !membership.contains(id)
+                    && !metrics.paused
+                    && is_matched_upto_date(&metrics.matched, &last_log_id, config)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        let newly_ready = ready_learners.difference(&self.leader_metrics.ready_learners).copied().collect::<Vec<_>>();
+        self.leader_metrics.ready_learners = ready_learners;
+
+        self.leader_metrics.peer_health = self
+            .leader_metrics
+            .replication
+            .iter()
+            .map(|(id, metrics)| (*id, PeerHealth::of(metrics, config.unreachable_rpc_failures)))
+            .collect();
+
         self.core.report_metrics(Update::Update(Some(&self.leader_metrics)));
+
+        // Sent after the metrics are published, so that a learner is either in the metrics or in a later event, for a
+        // `MetricsEvents` created in between.
+        for id in newly_ready {
+            let _ = self.core.tx_events.send(RaftEvent::LearnerReady(id));
+        }
     }
 }
 
 /// A struct tracking the state of a replication stream from the perspective of the Raft actor.
-struct ReplicationState<D: AppData> {
+struct ReplicationState<D: AppData, NID: RaftNodeId> {
     pub matched: LogId,
     pub remove_since: Option<u64>,
-    pub repl_stream: ReplicationStream<D>,
+    pub repl_stream: ReplicationStream<D, NID>,
+
+    /// When the last AppendEntries the target accepted in this term was sent, see `ReadStrategy::LeaseRead`.
+    pub last_ack: Option<Instant>,
+
+    /// When the last response from the target accepting this node as the leader was received, see
+    /// `Raft::last_contact()`.
+    pub last_contact: Option<Instant>,
 
     /// The response channel to use for when this node has successfully synced with the cluster.
-    pub tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError>>,
+    pub tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError<NID>>>,
+
+    /// The lag sampled while a learner is being added with `blocking`, until it catches up.
+    pub catch_up: Option<LearnerCatchUp>,
+
+    /// Whether replication to the target is paused, see `Raft::pause_replication()`.
+    pub paused: bool,
+
+    /// Whether the target is replicated to as a witness, without application data. It never becomes a full voter.
+    pub witness: bool,
 }
 
-impl<D: AppData> MessageSummary for ReplicationState<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for ReplicationState<D, NID> {
     fn summary(&self) -> String {
         format!(
             "matched: {}, remove_after_commit: {:?}",
@@ -828,14 +1968,16 @@ impl<D: AppData> MessageSummary for ReplicationState<D> {
     }
 }
 
-impl<D> ReplicationState<D>
+impl<D, NID: RaftNodeId> ReplicationState<D, NID>
 where D: AppData
 {
     // TODO(xp): make this a method of Config?
 
     /// Return true if the distance behind last_log_id is smaller than the threshold to join.
+    ///
+    /// A paused target is always lagging.
     pub fn is_line_rate(&self, last_log_id: &LogId, config: &Config) -> bool {
-        is_matched_upto_date(&self.matched, last_log_id, config)
+        !self.paused && is_matched_upto_date(&self.matched, last_log_id, config)
     }
 }
 
@@ -845,26 +1987,48 @@ pub fn is_matched_upto_date(matched: &LogId, last_log_id: &LogId, config: &Confi
     distance <= config.replication_lag_threshold
 }
 
+/// The log id to report in metrics: the placeholder log id at index 0 means there is no such log.
+fn non_zero(log_id: LogId) -> Option<LogId> {
+    if log_id.index == 0 {
+        None
+    } else {
+        Some(log_id)
+    }
+}
+
 /// Volatile state specific to a Raft node in candidate state.
-struct CandidateState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+struct CandidateState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
 
     /// Ids of the nodes that has granted our vote request.
-    granted: BTreeSet<NodeId>,
+    granted: BTreeSet<NID>,
+
+    /// Whether a voter rejected this node in the current election round for its log is stale.
+    stale_log: bool,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    CandidateState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         let id = core.id;
         Self {
             core,
             // vote for itself.
             granted: btreeset! {id},
+            stale_log: false,
         }
     }
 
     /// Run the candidate loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="candidate"))]
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="candidate"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         // Each iteration of the outer loop represents a new term.
         loop {
@@ -872,6 +2036,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 return Ok(());
             }
 
+            // Only the first election after a TimeoutNow is a leadership transfer.
+            let leadership_transfer = std::mem::take(&mut self.core.leadership_transfer);
+
+            self.stale_log = false;
+
+            // The leader asks for a leadership transfer, there is no need to ask whether this node could be elected.
+            if self.core.config.enable_pre_vote && !leadership_transfer && !self.run_pre_vote().await? {
+                self.count_stale_log_election();
+                self.core.count_failed_election();
+                continue;
+            }
+
             // Setup new term.
             self.core.update_next_election_timeout(false); // Generates a new rand value within range.
             self.core.current_term += 1;
@@ -880,34 +2056,54 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.core.save_hard_state().await?;
             self.core.report_metrics(Update::Update(None));
 
+            // Votes granted in a previous term do not count in this one.
+            self.granted = btreeset! {self.core.id};
+
+            // A single voter cluster elects this node with its own vote, without any RPC round.
+            if self.core.effective_membership.membership.is_majority(&self.granted) {
+                tracing::debug!("the vote of this node is a quorum, become leader");
+                self.core.set_target_state(State::Leader);
+                return Ok(());
+            }
+
             // Send RPCs to all members in parallel.
-            let mut pending_votes = self.spawn_parallel_vote_requests();
+            let rpc = VoteRequest {
+                leadership_transfer,
+                ..VoteRequest::new(self.core.current_term, self.core.id, self.core.last_log_id)
+            };
+            let mut pending_votes = self.spawn_parallel_vote_requests(rpc, self.peers());
 
             // Inner processing loop for this Raft state.
             loop {
                 if !self.core.target_state.is_candidate() {
                     return Ok(());
                 }
-                let timeout_fut = sleep_until(self.core.get_next_election_timeout());
+                let deadline = self.core.get_next_election_timeout();
+                let timeout_fut = self.core.clock.sleep_until(deadline);
 
                 let span = tracing::debug_span!("CHrx:CandidateState");
                 let _ent = span.enter();
 
                 tokio::select! {
-                    _ = timeout_fut => break, // This election has timed-out. Break to outer loop, which starts a new term.
+                    _ = timeout_fut => {
+                        // This election has timed-out. Break to outer loop, which starts a new term.
+                        self.count_stale_log_election();
+                        self.core.count_failed_election();
+                        break;
+                    },
                     Some((res, peer)) = pending_votes.recv() => self.handle_vote_response(res, peer).await?,
                     Some((msg,span)) = self.core.rx_api.recv() => {
                         self.handle_msg(msg).instrument(span).await;
                     },
                     Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                    Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                    Ok(graceful) = &mut self.core.rx_shutdown => self.core.handle_shutdown(graceful),
                 }
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "candidate", id=self.core.id))]
-    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R>) {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "candidate", id=%self.core.id))]
+    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID>) {
         tracing::debug!("recv from rx_api: {}", msg.summary());
         match msg {
             RaftMsg::AppendEntries { rpc, tx } => {
@@ -919,12 +2115,37 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
+            RaftMsg::ReadLocal { max_staleness, tx } => {
+                self.core.handle_read_local_request(max_staleness, tx);
+            }
+            RaftMsg::CheckApplied { log_id, tx } => {
+                let _ = tx.send(self.core.check_applied(log_id).await);
+            }
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                tx,
+            } => {
+                let _ = tx.send(self.core.get_log_entries(start, end, read_unapplied).await);
+            }
+            RaftMsg::GetHardState { tx } => {
+                let _ = tx.send(Ok(self.core.hard_state()));
+            }
             RaftMsg::ClientWriteRequest { rpc, tx } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
+            RaftMsg::ClientWriteBatch { reqs } => {
+                for (rpc, tx) in reqs {
+                    self.core.forward_client_write_request(rpc, tx);
+                }
+            }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
@@ -932,6 +2153,44 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::AbortMembershipChange { tx } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::ForceRemoveNode { id, tx } => {
+                self.core.force_remove_node_locally(id, tx).await;
+
+                // The vote of this node in the current term may be a quorum of the overridden config.
+                if self.core.effective_membership.membership.is_majority(&self.granted) {
+                    self.core.set_target_state(State::Leader);
+                }
+            }
+            RaftMsg::CanChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::UpdateConfig { delta, tx } => {
+                let _ = tx.send(self.core.update_config(&delta));
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::RebuildStateMachine { tx } => {
+                let _ = tx.send(Err(RebuildStateMachineError::NotAllowed(State::Candidate)));
+            }
+            RaftMsg::TransferLeadership { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::LeaderLeaseRemaining { tx } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::LastContact { tx, .. } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
         }
@@ -941,43 +2200,82 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in follower state.
-pub struct FollowerState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+pub struct FollowerState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> FollowerState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    FollowerState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         Self { core }
     }
 
     /// Run the follower loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="follower"))]
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="follower"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         self.core.report_metrics(Update::Update(None));
+
+        // Refresh `millis_since_last_heartbeat` even if no message is received.
+        let mut metrics_tick = Ticker::new(
+            self.core.clock.clone(),
+            Duration::from_millis(self.core.config.heartbeat_interval),
+        );
+
+        // Either `Follower` or `Paused`. Return on a change between them too, to report it in metrics.
+        let state = self.core.target_state;
+
         loop {
-            if !self.core.target_state.is_follower() {
+            // A node backing off for its stale log resumes as soon as its log advances, e.g., by the last
+            // AppendEntries.
+            if self.core.stale_log_id.is_some() {
+                self.core.resume_elections();
+            }
+
+            if self.core.target_state != state {
                 return Ok(());
             }
 
-            let election_timeout = sleep_until(self.core.get_next_election_timeout()); // Value is updated as heartbeats are received.
+            let deadline = self.core.get_next_election_timeout(); // Value is updated as heartbeats are received.
+            let election_timeout = self.core.clock.sleep_until(deadline);
+
+            let stale_log_deadline = self.core.stale_log_deadline;
+            let stale_log_timeout = self.core.clock.sleep_until(stale_log_deadline.unwrap_or(deadline));
+
+            // A witness is never elected, thus never starts an election.
+            let electable = self.core.effective_membership.membership.is_electable(&self.core.id);
+
+            let snapshot_deadline = self.core.next_snapshot_deadline();
 
             tokio::select! {
-                // If an election timeout is hit, then we need to transition to candidate.
-                _ = election_timeout => {
+                // If an election timeout is hit, then we need to transition to candidate, unless elections are paused.
+                _ = election_timeout, if electable && !state.is_paused() => {
                     tracing::debug!("timeout to recv a event, change to CandidateState");
                     self.core.set_target_state(State::Candidate)
                 },
+                _ = stale_log_timeout, if stale_log_deadline.is_some() => self.core.end_stale_log_backoff(),
                 Some((msg,span)) = self.core.rx_api.recv() => {
                     self.handle_msg(msg).instrument(span).await;
                 },
+                _ = metrics_tick.tick() => self.core.report_metrics(Update::Ignore),
+                _ = self.core.clock.sleep_until(snapshot_deadline.unwrap_or_else(|| self.core.clock.now())), if snapshot_deadline.is_some() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                Ok(graceful) = &mut self.core.rx_shutdown => self.core.handle_shutdown(graceful),
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "follower", id=self.core.id))]
-    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R>) {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "follower", id=%self.core.id))]
+    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID>) {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -990,12 +2288,37 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
+            RaftMsg::ReadLocal { max_staleness, tx } => {
+                self.core.handle_read_local_request(max_staleness, tx);
+            }
+            RaftMsg::CheckApplied { log_id, tx } => {
+                let _ = tx.send(self.core.check_applied(log_id).await);
+            }
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                tx,
+            } => {
+                let _ = tx.send(self.core.get_log_entries(start, end, read_unapplied).await);
+            }
+            RaftMsg::GetHardState { tx } => {
+                let _ = tx.send(Ok(self.core.hard_state()));
+            }
             RaftMsg::ClientWriteRequest { rpc, tx } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
+            RaftMsg::ClientWriteBatch { reqs } => {
+                for (rpc, tx) in reqs {
+                    self.core.forward_client_write_request(rpc, tx);
+                }
+            }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
@@ -1003,6 +2326,39 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::AbortMembershipChange { tx } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::ForceRemoveNode { id, tx } => {
+                self.core.force_remove_node_locally(id, tx).await;
+            }
+            RaftMsg::CanChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::UpdateConfig { delta, tx } => {
+                let _ = tx.send(self.core.update_config(&delta));
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::RebuildStateMachine { tx } => {
+                let _ = tx.send(self.core.rebuild_state_machine().await);
+            }
+            RaftMsg::TransferLeadership { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::LeaderLeaseRemaining { tx } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::LastContact { tx, .. } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
         }
@@ -1012,19 +2368,35 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in learner state.
-pub struct LearnerState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+pub struct LearnerState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LearnerState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LearnerState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         Self { core }
     }
 
     /// Run the learner loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="non-voter"))]
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="non-voter"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         self.core.report_metrics(Update::Update(None));
+
+        // Refresh `millis_since_last_heartbeat` even if no message is received.
+        let mut metrics_tick = Ticker::new(
+            self.core.clock.clone(),
+            Duration::from_millis(self.core.config.heartbeat_interval),
+        );
+
         loop {
             if !self.core.target_state.is_learner() {
                 return Ok(());
@@ -1033,18 +2405,24 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             let span = tracing::debug_span!("CHrx:LearnerState");
             let _ent = span.enter();
 
+            let snapshot_deadline = self.core.next_snapshot_deadline();
+
             tokio::select! {
                 Some((msg,span)) = self.core.rx_api.recv() => {
                     self.handle_msg(msg).instrument(span).await;
                 },
+                _ = metrics_tick.tick() => self.core.report_metrics(Update::Ignore),
+                _ = self.core.clock.sleep_until(snapshot_deadline.unwrap_or_else(|| self.core.clock.now())), if snapshot_deadline.is_some() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
-                Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+                Ok(graceful) = &mut self.core.rx_shutdown => self.core.handle_shutdown(graceful),
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "learner", id=self.core.id))]
-    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R>) {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "learner", id=%self.core.id))]
+    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID>) {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -1057,12 +2435,37 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.forward_client_read_request(tx);
             }
+            RaftMsg::ReadLocal { max_staleness, tx } => {
+                self.core.handle_read_local_request(max_staleness, tx);
+            }
+            RaftMsg::CheckApplied { log_id, tx } => {
+                let _ = tx.send(self.core.check_applied(log_id).await);
+            }
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                tx,
+            } => {
+                let _ = tx.send(self.core.get_log_entries(start, end, read_unapplied).await);
+            }
+            RaftMsg::GetHardState { tx } => {
+                let _ = tx.send(Ok(self.core.hard_state()));
+            }
             RaftMsg::ClientWriteRequest { rpc, tx } => {
                 self.core.forward_client_write_request(rpc, tx);
             }
+            RaftMsg::ClientWriteBatch { reqs } => {
+                for (rpc, tx) in reqs {
+                    self.core.forward_client_write_request(rpc, tx);
+                }
+            }
             RaftMsg::Initialize { members, tx } => {
                 let _ = tx.send(self.handle_init_with_config(members).await);
             }
@@ -1070,6 +2473,39 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 self.core.reject_config_change_not_leader(tx);
             }
             RaftMsg::ChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::AbortMembershipChange { tx } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::ForceRemoveNode { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::CanChangeMembership { tx, .. } => {
+                let _ = tx.send(Err(ClientWriteError::not_leader(self.core.current_leader)));
+            }
+            RaftMsg::UpdateConfig { delta, tx } => {
+                let _ = tx.send(self.core.update_config(&delta));
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::RebuildStateMachine { tx } => {
+                let _ = tx.send(self.core.rebuild_state_machine().await);
+            }
+            RaftMsg::TransferLeadership { tx, .. } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::StepDown { tx } => {
+                self.core.reject_config_change_not_leader(tx);
+            }
+            RaftMsg::LeaderLeaseRemaining { tx } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::LastContact { tx, .. } => {
+                let _ = tx.send(Ok(None));
+            }
+            RaftMsg::PauseReplication { tx, .. } => {
                 self.core.reject_config_change_not_leader(tx);
             }
         }
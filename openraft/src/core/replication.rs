@@ -1,39 +1,47 @@
 use std::collections::BTreeMap;
 
 use tokio::sync::oneshot;
+use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing_futures::Instrument;
 
-use crate::config::SnapshotPolicy;
+use crate::config::ConfigDelta;
 use crate::core::LeaderState;
 use crate::core::ReplicationState;
 use crate::core::SnapshotState;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::AddLearnerError;
+use crate::error::PauseReplicationError;
 use crate::error::RaftResult;
+use crate::error::UpdateConfigError;
 use crate::raft::AddLearnerResponse;
 use crate::raft::RaftRespTx;
 use crate::replication::RaftEvent;
 use crate::replication::ReplicaEvent;
 use crate::replication::ReplicationStream;
 use crate::storage::Snapshot;
+use crate::storage::SnapshotReader;
 use crate::summary::MessageSummary;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
-use crate::NodeId;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
-use crate::ReplicationMetrics;
+use crate::ReplicationStatus;
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Spawn a new replication stream returning its replication state handle.
     #[tracing::instrument(level = "debug", skip(self, caller_tx))]
     pub(super) fn spawn_replication_stream(
         &self,
-        target: NodeId,
-        caller_tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError>>,
-    ) -> ReplicationState<D> {
+        target: NID,
+        caller_tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError<NID>>>,
+    ) -> ReplicationState<D, NID> {
+        let witness = self.core.effective_membership.membership.is_witness(&target);
         let repl_stream = ReplicationStream::new(
             self.core.id,
             target,
@@ -43,23 +51,165 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.core.committed,
             self.core.network.clone(),
             self.core.storage.clone(),
+            self.core.log_cache.clone(),
             self.replication_tx.clone(),
+            self.snapshot_sends.clone(),
+            self.core.clock.clone(),
+            witness,
         );
         ReplicationState {
             matched: LogId { term: 0, index: 0 },
             repl_stream,
             remove_since: None,
+            last_ack: None,
+            last_contact: None,
             tx: caller_tx,
+            catch_up: None,
+            paused: false,
+            witness,
+        }
+    }
+
+    /// Pause or resume replication to `target`.
+    ///
+    /// A learner being added with `blocking` starts over sampling its lag once resumed, since it can not catch up
+    /// while paused.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn pause_replication(&mut self, target: NID, paused: bool) -> Result<(), PauseReplicationError<NID>> {
+        let now = self.core.clock.now();
+        let last_index = self.core.last_log_id.index;
+        let catch_up_timeout = self.core.config.learner_catch_up_timeout();
+
+        let node = match self.nodes.get_mut(&target) {
+            Some(node) => node,
+            None => return Err(PauseReplicationError::NotReplicated { node_id: target }),
+        };
+
+        if node.paused == paused {
+            return Ok(());
+        }
+
+        tracing::info!(%target, paused, "pause replication");
+
+        node.paused = paused;
+        let _ = node.repl_stream.repl_tx.send((RaftEvent::SetPaused { paused }, tracing::debug_span!("CH")));
+
+        if !paused {
+            if let Some(catch_up) = &mut node.catch_up {
+                catch_up.deadline = catch_up_timeout.map(|t| now + t);
+                catch_up.restart(now, last_index.saturating_sub(node.matched.index));
+            }
+        }
+
+        let metrics = self.leader_metrics.replication.entry(target).or_default();
+        metrics.paused = paused;
+        if !paused {
+            metrics.snapshot_format_rejected = None;
+        }
+        self.leader_report_metrics();
+
+        Ok(())
+    }
+
+    /// The target rejected the format of the snapshot sent to it, and its replication stream paused itself.
+    ///
+    /// It is reported in the metrics, the application resumes the replication once the target supports the format.
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn handle_snapshot_format_rejected(&mut self, target: NID, format_version: u32) {
+        tracing::error!(%target, format_version, "target does not support the snapshot format, pause replication");
+
+        if self.pause_replication(target, true).is_err() {
+            // The replication stream may have been removed.
+            return;
+        }
+
+        self.leader_metrics.replication.entry(target).or_default().snapshot_format_rejected = Some(format_version);
+        self.leader_report_metrics();
+    }
+
+    /// Update the config of the core and of all replication streams.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn update_config(&mut self, delta: &ConfigDelta) -> Result<(), UpdateConfigError> {
+        self.core.update_config(delta)?;
+
+        for node in self.nodes.values() {
+            let _ = node.repl_stream.repl_tx.send((
+                RaftEvent::UpdateConfig {
+                    config: self.core.config.clone(),
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Stop replicating application data to the targets that become witnesses in the effective membership.
+    ///
+    /// A target stays a witness to the replication stream even if it is no longer one in the membership, e.g., when
+    /// the change is aborted: its log has no application data any more.
+    pub(super) fn update_witness_replication(&mut self) {
+        let membership = &self.core.effective_membership.membership;
+
+        for (id, node) in self.nodes.iter_mut() {
+            if node.witness || !membership.is_witness(id) {
+                continue;
+            }
+
+            tracing::info!(target = %id, "replicate to witness without application data");
+
+            node.witness = true;
+            let _ = node.repl_stream.repl_tx.send((RaftEvent::TargetIsWitness, tracing::debug_span!("CH")));
         }
     }
 
     /// Handle a replication event coming from one of the replication streams.
     #[tracing::instrument(level = "trace", skip(self, event), fields(event=%event.summary()))]
-    pub(super) async fn handle_replica_event(&mut self, event: ReplicaEvent<S::SnapshotData>) {
+    pub(super) async fn handle_replica_event(&mut self, event: ReplicaEvent<NID>) {
         let res = match event {
             ReplicaEvent::RevertToFollower { target, term } => self.handle_revert_to_follower(target, term).await,
             ReplicaEvent::UpdateMatched { target, matched } => self.handle_update_matched(target, matched).await,
+            ReplicaEvent::UpdateProgress {
+                target,
+                snapshotting,
+                snapshot_queued,
+                last_rpc_latency,
+                consecutive_failures,
+            } => {
+                self.handle_update_progress(
+                    target,
+                    snapshotting,
+                    snapshot_queued,
+                    last_rpc_latency,
+                    consecutive_failures,
+                );
+                Ok(())
+            }
+            ReplicaEvent::Acked {
+                target,
+                sent_at,
+                received_at,
+            } => {
+                self.handle_acked(target, sent_at, received_at);
+                Ok(())
+            }
+            ReplicaEvent::UpdateRejections {
+                target,
+                log_conflicts,
+                term_rejections,
+            } => {
+                self.handle_update_rejections(target, log_conflicts, term_rejections);
+                Ok(())
+            }
             ReplicaEvent::NeedsSnapshot { target, tx } => self.handle_needs_snapshot(target, tx).await,
+            ReplicaEvent::TimeoutNowFailed { target, error, tx } => {
+                self.handle_timeout_now_failed(target, error, tx);
+                Ok(())
+            }
+            ReplicaEvent::SnapshotFormatRejected { target, format_version } => {
+                self.handle_snapshot_format_rejected(target, format_version);
+                Ok(())
+            }
             ReplicaEvent::Shutdown => {
                 self.core.set_target_state(State::Shutdown);
                 return;
@@ -73,7 +223,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     /// Handle events from replication streams for when this node needs to revert to follower state.
     #[tracing::instrument(level = "trace", skip(self, term))]
-    async fn handle_revert_to_follower(&mut self, _: NodeId, term: u64) -> RaftResult<()> {
+    async fn handle_revert_to_follower(&mut self, _: NID, term: u64) -> RaftResult<()> {
         if term > self.core.current_term {
             self.core.update_current_term(term, None);
             self.core.save_hard_state().await?;
@@ -84,7 +234,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn handle_update_matched(&mut self, target: NodeId, matched: LogId) -> RaftResult<()> {
+    async fn handle_update_matched(&mut self, target: NID, matched: LogId) -> RaftResult<()> {
         // Update target's match index & check if it is awaiting removal.
 
         if let Some(state) = self.nodes.get_mut(&target) {
@@ -100,6 +250,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
                 // When adding a learner, it blocks until the replication becomes line-rate.
                 if let Some(tx) = state.tx.take() {
+                    state.catch_up = None;
+
                     // TODO(xp): define a specific response type for learner matched event.
                     let x = AddLearnerResponse { matched: state.matched };
                     let _ = tx.send(Ok(x));
@@ -109,6 +261,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return Ok(());
         }
 
+        self.try_send_timeout_now();
+
         // Drop replication stream if needed.
         if self.try_remove_replication(target) {
             // nothing to do
@@ -116,11 +270,24 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.update_leader_metrics(target, matched);
         }
 
+        self.try_resume_membership_change().await;
+
         if matched <= self.core.committed {
             self.leader_report_metrics();
             return Ok(());
         }
 
+        self.try_commit().await;
+
+        // TODO(xp): does this update too frequently?
+        self.leader_report_metrics();
+        Ok(())
+    }
+
+    /// Advance the commit index to the greatest log replicated to a quorum, and hand the requests it commits to the
+    /// state machine.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) async fn try_commit(&mut self) {
         let commit_index = self.calc_commit_log_id();
 
         // Determine if we have a new commit index, accounting for joint consensus.
@@ -156,36 +323,124 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 }
             }
         }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn update_leader_metrics(&mut self, target: NID, matched: LogId) {
+        tracing::debug!(%target, %matched, "update_leader_metrics");
+        self.leader_metrics.replication.entry(target).or_default().matched = matched;
+    }
+
+    /// Update the replication metrics with the progress reported by a replication stream.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn handle_update_progress(
+        &mut self,
+        target: NID,
+        snapshotting: bool,
+        snapshot_queued: bool,
+        last_rpc_latency: Option<Duration>,
+        consecutive_failures: u64,
+    ) {
+        let now = self.core.clock.now();
+        let last_index = self.core.last_log_id.index;
+
+        // The replication stream may have been removed.
+        let node = match self.nodes.get_mut(&target) {
+            Some(node) => node,
+            None => return,
+        };
+
+        // A learner being added is sampled again from when it has received the snapshot.
+        if let Some(catch_up) = &mut node.catch_up {
+            let snapshotting = snapshotting || snapshot_queued;
+            if catch_up.snapshotting && !snapshotting {
+                catch_up.restart(now, last_index.saturating_sub(node.matched.index));
+            }
+            catch_up.snapshotting = snapshotting;
+        }
+
+        let metrics = self.leader_metrics.replication.entry(target).or_default();
+        metrics.last_rpc_latency_ms = last_rpc_latency.map(|x| x.as_millis() as u64);
+        metrics.consecutive_failures = consecutive_failures;
+        metrics.state = if snapshot_queued {
+            ReplicationStatus::SnapshotQueued
+        } else if snapshotting {
+            ReplicationStatus::Snapshotting
+        } else {
+            ReplicationStatus::LineRate
+        };
 
-        // TODO(xp): does this update too frequently?
         self.leader_report_metrics();
-        Ok(())
     }
 
+    /// Record that `target` accepted this node as the leader with an AppendEntries sent at `sent_at`, which extends
+    /// the read lease, and that the response was received at `received_at`.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_leader_metrics(&mut self, target: NodeId, matched: LogId) {
-        tracing::debug!(%target, %matched, "update_leader_metrics");
-        self.leader_metrics.replication.insert(target, ReplicationMetrics { matched });
+    pub(super) fn handle_acked(&mut self, target: NID, sent_at: Instant, received_at: Instant) {
+        // The target is in contact even if the lease is given up.
+        if let Some(state) = self.nodes.get_mut(&target) {
+            if state.last_contact.map(|t| t < received_at).unwrap_or(true) {
+                state.last_contact = Some(received_at);
+            }
+        }
+
+        if let Some(not_before) = self.lease_not_before {
+            if sent_at <= not_before {
+                return;
+            }
+        }
+
+        if let Some(state) = self.nodes.get_mut(&target) {
+            if state.last_ack.map(|t| t < sent_at).unwrap_or(true) {
+                state.last_ack = Some(sent_at);
+            }
+        }
     }
 
+    /// Update the replication metrics with the number of AppendEntries rejected by a target.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn calc_commit_log_id(&self) -> LogId {
-        let repl_indexes = self.get_match_log_indexes();
+    fn handle_update_rejections(&mut self, target: NID, log_conflicts: u64, term_rejections: u64) {
+        // The replication stream may have been removed.
+        if !self.nodes.contains_key(&target) {
+            return;
+        }
 
-        let committed = self.core.effective_membership.membership.greatest_majority_value(&repl_indexes);
+        let metrics = self.leader_metrics.replication.entry(target).or_default();
+        metrics.log_conflicts = log_conflicts;
+        metrics.term_rejections = term_rejections;
 
-        *committed.unwrap_or(&self.core.committed)
+        self.leader_report_metrics();
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn calc_commit_log_id(&mut self) -> LogId {
+        let membership = &self.core.effective_membership.membership;
+
+        let repl_indexes = self.get_match_log_indexes(true);
+        let committed = *membership.greatest_majority_value(&repl_indexes).unwrap_or(&self.core.committed);
+
+        // E.g., right after election, a quorum may have matched logs of previous terms, but not yet the blank log of
+        // the current term.
+        let blocked = committed <= self.core.committed && {
+            let all_indexes = self.get_match_log_indexes(false);
+            membership.greatest_majority_value(&all_indexes).map(|x| *x > self.core.committed).unwrap_or(false)
+        };
+        self.leader_metrics.commit_blocked_by_term = blocked;
+
+        committed
     }
 
     /// Collect indexes of the greatest matching log on every replica(include the leader itself)
-    fn get_match_log_indexes(&self) -> BTreeMap<NodeId, LogId> {
+    ///
+    /// With `current_term_only`, a replica whose matched log is not of the current term is left out.
+    fn get_match_log_indexes(&self, current_term_only: bool) -> BTreeMap<NID, LogId> {
         let node_ids = self.core.effective_membership.membership.all_nodes();
 
         let mut res = BTreeMap::new();
 
         for id in node_ids.iter() {
             let matched = if *id == self.core.id {
-                self.core.last_log_id
+                self.flushed
             } else {
                 let repl_state = self.nodes.get(id);
                 repl_state.map(|x| x.matched).unwrap_or_default()
@@ -194,7 +449,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             // Mismatching term can not prevent other replica with higher term log from being chosen as leader,
             // and that new leader may overrides any lower term logs.
             // Thus it is not considered as committed.
-            if matched.term == self.core.current_term {
+            if !current_term_only || matched.term == self.core.current_term {
                 res.insert(*id, matched);
             }
         }
@@ -204,19 +459,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     /// A replication streams requesting for snapshot info.
     #[tracing::instrument(level = "debug", skip(self, tx))]
-    async fn handle_needs_snapshot(
-        &mut self,
-        _: NodeId,
-        tx: oneshot::Sender<Snapshot<S::SnapshotData>>,
-    ) -> RaftResult<()> {
-        // Ensure snapshotting is configured, else do nothing.
-        let threshold = match &self.core.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => *threshold,
-        };
+    async fn handle_needs_snapshot(&mut self, _: NID, tx: oneshot::Sender<Snapshot<SnapshotReader>>) -> RaftResult<()> {
+        // Without a log count threshold, use the number of applied logs to keep to decide if a snapshot is too old.
+        let threshold = self
+            .core
+            .config
+            .snapshot_policy
+            .logs_threshold()
+            .unwrap_or(self.core.config.max_applied_log_to_keep);
 
         // Check for existence of current snapshot.
         let current_snapshot_opt =
-            self.core.storage.get_current_snapshot().await.map_err(|err| self.core.map_storage_error(err))?;
+            self.core.storage.get_snapshot_reader().await.map_err(|err| self.core.map_storage_error(err))?;
 
         if let Some(snapshot) = current_snapshot_opt {
             // If snapshot exists, ensure its distance from the leader's last log index is <= half
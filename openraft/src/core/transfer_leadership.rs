@@ -0,0 +1,297 @@
+use anyhow::anyhow;
+use tokio::sync::mpsc::error::SendError;
+use tokio::time::timeout;
+use tokio::time::Duration;
+use tokio::time::Instant;
+use tracing_futures::Instrument;
+
+use crate::core::LeaderState;
+use crate::core::RaftCore;
+use crate::core::State;
+use crate::core::UpdateCurrentLeader;
+use crate::error::ClientWriteError;
+use crate::error::RaftError;
+use crate::error::RaftResult;
+use crate::error::TransferLeadershipError;
+use crate::raft::RaftRespTx;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
+use crate::replication::ReplicaEvent;
+use crate::summary::MessageSummary;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::RaftNetwork;
+use crate::RaftNodeId;
+use crate::RaftStorage;
+
+/// A leadership transfer in progress on the leader.
+pub(super) struct LeadershipTransfer<NID: RaftNodeId> {
+    /// The node to transfer leadership to.
+    pub target: NID,
+
+    /// When to abandon the transfer and resume accepting writes.
+    pub deadline: Instant,
+
+    /// Responds with the target when a TimeoutNow is sent to it, it is `None` since then.
+    pub tx: Option<RaftRespTx<NID, TransferLeadershipError<NID>>>,
+}
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
+    /// An RPC invoked by the leader to make this node start an election at once, to transfer leadership to it.
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(msg=%msg.summary()))]
+    pub(super) async fn handle_timeout_now_request(
+        &mut self,
+        msg: TimeoutNowRequest<NID>,
+    ) -> RaftResult<TimeoutNowResponse> {
+        if msg.term < self.current_term {
+            tracing::debug!({self.current_term, rpc_term=msg.term}, "TimeoutNow RPC term is less than current term");
+            return Ok(TimeoutNowResponse {
+                term: self.current_term,
+            });
+        }
+
+        if msg.term > self.current_term {
+            self.update_current_term(msg.term, None);
+            self.save_hard_state().await?;
+        }
+
+        // A learner or a witness never becomes a candidate.
+        if !self.effective_membership.membership.is_electable(&self.id) {
+            tracing::debug!("ignore TimeoutNow RPC: this node is not a voter, or is a witness");
+            return Ok(TimeoutNowResponse {
+                term: self.current_term,
+            });
+        }
+
+        tracing::info!(leader = %msg.leader_id, "received TimeoutNow, start an election at once");
+
+        self.leadership_transfer = true;
+        self.set_target_state(State::Candidate);
+
+        Ok(TimeoutNowResponse {
+            term: self.current_term,
+        })
+    }
+}
+
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
+    /// Start transferring leadership to `target`, or to the most up to date voter if it is `None`.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn transfer_leadership(
+        &mut self,
+        target: Option<NID>,
+        tx: RaftRespTx<NID, TransferLeadershipError<NID>>,
+    ) {
+        if let Some(transfer) = &self.leadership_transfer {
+            let _ = tx.send(Err(TransferLeadershipError::InProgress {
+                target: transfer.target,
+            }));
+            return;
+        }
+
+        let membership = &self.core.effective_membership.membership;
+
+        let target = match target {
+            Some(node_id) => {
+                if node_id == self.core.id || !membership.is_electable(&node_id) {
+                    let _ = tx.send(Err(TransferLeadershipError::InvalidTarget { node_id }));
+                    return;
+                }
+                node_id
+            }
+            None => {
+                let most_up_to_date = self
+                    .nodes
+                    .iter()
+                    .filter(|(id, _)| membership.is_electable(id))
+                    .max_by_key(|(_, state)| state.matched);
+
+                match most_up_to_date {
+                    Some((node_id, _)) => *node_id,
+                    None => {
+                        let _ = tx.send(Err(TransferLeadershipError::NoTarget));
+                        return;
+                    }
+                }
+            }
+        };
+
+        tracing::info!(%target, "start leadership transfer, stop accepting writes");
+
+        self.leadership_transfer = Some(LeadershipTransfer {
+            target,
+            deadline: self.core.clock.now() + Duration::from_millis(self.core.config.election_timeout_max),
+            tx: Some(tx),
+        });
+
+        self.try_send_timeout_now();
+    }
+
+    /// Send a TimeoutNow to the target of the leadership transfer, if it has every log of the leader.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn try_send_timeout_now(&mut self) {
+        let transfer = match &mut self.leadership_transfer {
+            Some(transfer) => transfer,
+            None => return,
+        };
+
+        let target = transfer.target;
+
+        match self.nodes.get(&target) {
+            Some(state) if state.matched >= self.core.last_log_id => {}
+            _ => return,
+        }
+
+        let tx = match transfer.tx.take() {
+            Some(tx) => tx,
+            // Already sent.
+            None => return,
+        };
+
+        self.spawn_send_timeout_now(target, Some(tx));
+    }
+
+    /// Send a TimeoutNow to `target` in another task, and respond through `tx` once it is sent, if there is one.
+    ///
+    /// The target may be elected at once, thus the read lease held so far is given up.
+    fn spawn_send_timeout_now(&mut self, target: NID, tx: Option<RaftRespTx<NID, TransferLeadershipError<NID>>>) {
+        self.lease_not_before = Some(self.core.clock.now());
+        for state in self.nodes.values_mut() {
+            state.last_ack = None;
+        }
+
+        let rpc = TimeoutNowRequest {
+            term: self.core.current_term,
+            leader_id: self.core.id,
+        };
+
+        let ttl = self.core.config.append_entries_ttl();
+        let network = self.core.network.clone();
+        let replication_tx = self.replication_tx.clone();
+        tokio::spawn(
+            async move {
+                let res = match timeout(ttl, network.send_timeout_now(target, rpc)).await {
+                    Ok(res) => res,
+                    Err(_timeout) => Err(anyhow!("timeout after {:?} sending TimeoutNow", ttl)),
+                };
+
+                let err = match res {
+                    Ok(_) => {
+                        if let Some(tx) = tx {
+                            let _ = tx.send(Ok(target));
+                        }
+                        return;
+                    }
+                    Err(err) => err,
+                };
+
+                tracing::error!({error=%err, %target}, "while sending TimeoutNow");
+
+                // Let the leader end the transfer before responding, or respond here if it is no longer the leader.
+                let event = ReplicaEvent::TimeoutNowFailed {
+                    target,
+                    error: RaftError::RaftNetwork(err).into(),
+                    tx,
+                };
+                if let Err(SendError((
+                    ReplicaEvent::TimeoutNowFailed {
+                        error, tx: Some(tx), ..
+                    },
+                    _,
+                ))) = replication_tx.send((event, tracing::debug_span!("CH")))
+                {
+                    let _ = tx.send(Err(error));
+                }
+            }
+            .instrument(tracing::debug_span!("send_timeout_now", target = %target)),
+        );
+    }
+
+    /// Revert to a follower at once, and send a TimeoutNow to a voter that has every log of the leader, if any.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn step_down(&mut self, tx: RaftRespTx<Option<NID>, TransferLeadershipError<NID>>) {
+        if let Some(transfer) = &self.leadership_transfer {
+            let _ = tx.send(Err(TransferLeadershipError::InProgress {
+                target: transfer.target,
+            }));
+            return;
+        }
+
+        let membership = &self.core.effective_membership.membership;
+        let last_log_id = self.core.last_log_id;
+
+        let target = self
+            .nodes
+            .iter()
+            .filter(|(id, state)| membership.is_electable(id) && state.matched >= last_log_id)
+            .map(|(id, _)| *id)
+            .next();
+
+        match target {
+            Some(target) => {
+                tracing::info!(%target, "step down, send TimeoutNow to an up to date voter");
+                self.spawn_send_timeout_now(target, None);
+            }
+            None => {
+                tracing::info!("step down, no voter is up to date, leave it to the next election");
+            }
+        }
+
+        self.core.update_current_leader(UpdateCurrentLeader::Unknown);
+        self.core.set_target_state(State::Follower);
+
+        let _ = tx.send(Ok(target));
+    }
+
+    /// End the leadership transfer to `target` because the TimeoutNow to it could not be sent, and resume accepting
+    /// writes at once instead of at the deadline.
+    pub(super) fn handle_timeout_now_failed(
+        &mut self,
+        target: NID,
+        error: TransferLeadershipError<NID>,
+        tx: Option<RaftRespTx<NID, TransferLeadershipError<NID>>>,
+    ) {
+        if let Some(transfer) = &self.leadership_transfer {
+            if transfer.target == target {
+                tracing::info!(%target, "leadership transfer failed, resume accepting writes");
+                self.leadership_transfer = None;
+            }
+        }
+
+        if let Some(tx) = tx {
+            let _ = tx.send(Err(error));
+        }
+    }
+
+    /// Abandon the leadership transfer when its deadline is reached, and resume accepting writes.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn handle_leadership_transfer_timeout(&mut self) {
+        let transfer = match self.leadership_transfer.take() {
+            Some(transfer) => transfer,
+            None => return,
+        };
+
+        tracing::info!(
+            target = %transfer.target,
+            "leadership transfer ends, resume accepting writes"
+        );
+
+        if let Some(tx) = transfer.tx {
+            let _ = tx.send(Err(TransferLeadershipError::Timeout {
+                target: transfer.target,
+                timeout: Duration::from_millis(self.core.config.election_timeout_max),
+            }));
+        }
+    }
+
+    /// Reject a request that proposes logs, because this leader is going to step down.
+    ///
+    /// The next leader is not elected yet, the client should retry later.
+    pub(super) fn reject_write_in_leadership_transfer<T>(&self, tx: RaftRespTx<T, ClientWriteError<NID>>) {
+        let _ = tx.send(Err(ClientWriteError::LeaderUnknown));
+    }
+}
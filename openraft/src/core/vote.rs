@@ -1,32 +1,105 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use futures::future::join_all;
+use futures::FutureExt;
+use futures::StreamExt;
+use maplit::btreeset;
 use tokio::sync::mpsc;
-use tokio::time::Instant;
+use tokio::time::timeout;
+use tokio::time::Duration;
 use tracing_futures::Instrument;
 
+use crate::config::CAPABILITIES_RETRY_HEARTBEATS;
+use crate::config::STALE_LOG_BACKOFF_ELECTION_TIMEOUTS;
+use crate::config::STALE_LOG_ELECTIONS;
 use crate::core::CandidateState;
 use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::RaftResult;
+use crate::network::NodeCapabilities;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::summary::MessageSummary;
 use crate::AppData;
 use crate::AppDataResponse;
-use crate::NodeId;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
+use crate::Update;
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    RaftCore<D, R, N, S, NID>
+{
+    /// Get the optional RPCs every target supports, from the cache or by asking `RaftNetwork::capabilities()` of all
+    /// the uncached targets at once.
+    ///
+    /// If a target can not be asked, it is assumed to support every optional RPC. It is not asked again until
+    /// `CAPABILITIES_RETRY_HEARTBEATS` heartbeats later, thus an unreachable peer does not delay every election.
+    #[tracing::instrument(level = "debug", skip(self, targets))]
+    pub(super) async fn capabilities_of(
+        &mut self,
+        targets: impl IntoIterator<Item = NID>,
+    ) -> BTreeMap<NID, NodeCapabilities> {
+        let now = self.clock.now();
+
+        let mut res = BTreeMap::new();
+        let mut to_ask = vec![];
+        for target in targets {
+            if let Some(caps) = self.capabilities.get(&target) {
+                res.insert(target, *caps);
+                continue;
+            }
+            match self.capabilities_retry_at.get(&target) {
+                Some(retry_at) if now < *retry_at => {
+                    res.insert(target, NodeCapabilities::current());
+                }
+                _ => to_ask.push(target),
+            }
+        }
+
+        let ttl = Duration::from_millis(self.config.heartbeat_interval);
+        let network = &self.network;
+        let asked = join_all(
+            to_ask
+                .into_iter()
+                .map(|target| timeout(ttl, network.capabilities(target)).map(move |x| (target, x))),
+        )
+        .await;
+
+        let retry_after = Duration::from_millis(self.config.heartbeat_interval * CAPABILITIES_RETRY_HEARTBEATS);
+        for (target, r) in asked {
+            let err = match r {
+                Ok(Ok(caps)) => {
+                    tracing::debug!(%target, ?caps, "got capabilities");
+                    self.capabilities.insert(target, caps);
+                    self.capabilities_retry_at.remove(&target);
+                    res.insert(target, caps);
+                    continue;
+                }
+                Ok(Err(err)) => err.to_string(),
+                Err(_timeout) => "timeout".to_string(),
+            };
+
+            tracing::warn!(%target, error=%err, "failed to get capabilities, assume current");
+            self.capabilities_retry_at.insert(target, self.clock.now() + retry_after);
+            res.insert(target, NodeCapabilities::current());
+        }
+
+        res
+    }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
     /// An RPC invoked by candidates to gather votes (§5.2).
     ///
     /// See `receiver implementation: RequestVote RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level = "debug", skip(self, msg), fields(msg=%msg.summary()))]
-    pub(super) async fn handle_vote_request(&mut self, msg: VoteRequest) -> RaftResult<VoteResponse> {
-        tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "start handle_vote_request");
+    pub(super) async fn handle_vote_request(&mut self, msg: VoteRequest<NID>) -> RaftResult<VoteResponse> {
+        tracing::debug!({candidate=%msg.candidate_id, self.current_term, rpc_term=msg.term}, "start handle_vote_request");
 
         // If candidate's current term is less than this nodes current term, reject.
         if msg.term < self.current_term {
-            tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "RequestVote RPC term is less than current term");
+            tracing::debug!({candidate=%msg.candidate_id, self.current_term, rpc_term=msg.term}, "RequestVote RPC term is less than current term");
             return Ok(VoteResponse {
                 term: self.current_term,
                 vote_granted: false,
@@ -34,13 +107,27 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             });
         }
 
-        // Do not respond to the request if we've received a heartbeat within the election timeout minimum.
-        if let Some(inst) = &self.last_heartbeat {
-            let now = Instant::now();
+        // A witness is never elected.
+        if self.effective_membership.membership.is_witness(&msg.candidate_id) {
+            tracing::debug!({ candidate = %msg.candidate_id }, "rejecting vote request from a witness");
+            return Ok(VoteResponse {
+                term: self.current_term,
+                vote_granted: false,
+                last_log_id: self.last_log_id,
+            });
+        }
+
+        // Record the term even if it is not adopted, e.g., by a pre-vote.
+        self.observe_term(msg.term);
+
+        // Do not respond to the request if we've received a heartbeat within the election timeout minimum,
+        // unless the current leader asked the candidate to start this election.
+        if let Some(inst) = self.last_heartbeat.as_ref().filter(|_| !msg.leadership_transfer) {
+            let now = self.clock.now();
             let delta = now.duration_since(*inst);
             if self.config.election_timeout_min >= (delta.as_millis() as u64) {
                 tracing::debug!(
-                    { candidate = msg.candidate_id },
+                    { candidate = %msg.candidate_id },
                     "rejecting vote request received within election timeout minimum"
                 );
                 return Ok(VoteResponse {
@@ -51,6 +138,20 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
         }
 
+        // A pre-vote is granted if a vote would be granted, without changing any state: the candidate campaigns for a
+        // term greater than this node's, with an up-to-date log, and no leader is heard from.
+        // A leader never grants a pre-vote: a leader exists.
+        if msg.pre_vote {
+            let vote_granted =
+                !self.target_state.is_leader() && msg.term > self.current_term && msg.last_log_id >= self.last_log_id;
+            tracing::debug!({candidate=%msg.candidate_id, msg.term, vote_granted}, "handle pre-vote");
+            return Ok(VoteResponse {
+                term: self.current_term,
+                vote_granted,
+                last_log_id: self.last_log_id,
+            });
+        }
+
         // Per spec, if we observe a term greater than our own outside of the election timeout
         // minimum, then we must update term & immediately become follower. We still need to
         // do vote checking after this.
@@ -65,7 +166,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // If candidate's log is not at least as up-to-date as this node, then reject.
         if msg.last_log_id < self.last_log_id {
             tracing::debug!(
-                { candidate = msg.candidate_id },
+                { candidate = %msg.candidate_id },
                 "rejecting vote request as candidate's log is not up-to-date"
             );
             return Ok(VoteResponse {
@@ -75,9 +176,6 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             });
         }
 
-        // TODO: add hook for PreVote optimization here. If the RPC is a PreVote, then at this
-        // point we can respond to the candidate telling them that we would vote for them.
-
         // Candidate's log is up-to-date so handle voting conditions.
         match &self.voted_for {
             // This node has already voted for the candidate.
@@ -98,7 +196,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 self.set_target_state(State::Follower);
                 self.update_next_election_timeout(false);
                 self.save_hard_state().await?;
-                tracing::debug!({candidate=msg.candidate_id, msg.term}, "voted for candidate");
+                tracing::debug!({candidate=%msg.candidate_id, msg.term}, "voted for candidate");
                 Ok(VoteResponse {
                     term: self.current_term,
                     vote_granted: true,
@@ -109,10 +207,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
-    /// Handle response from a vote request sent to a peer.
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    CandidateState<'a, D, R, N, S, NID>
+{
+    /// Handle response from a vote request sent to a peer, `None` if it does not respond within
+    /// `Config::vote_timeout` or `Config::vote_request_timeout`.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) async fn handle_vote_response(&mut self, res: VoteResponse, target: NodeId) -> RaftResult<()> {
+    pub(super) async fn handle_vote_response(&mut self, res: Option<VoteResponse>, target: NID) -> RaftResult<()> {
+        // Counted as a rejection: the election goes on until a quorum grants or it times out.
+        let res = match res {
+            Some(res) => res,
+            None => return Ok(()),
+        };
+
         // If peer's term is greater than current term, revert to follower state.
 
         if res.term > self.core.current_term {
@@ -126,6 +233,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             //           When reverted to follower, it waits for heartbeat for 2 second before starting a new round of
             //           election.
             if self.core.last_log_id < res.last_log_id {
+                self.observe_stale_log(&res);
+                self.count_stale_log_election();
                 self.core.set_target_state(State::Follower);
                 tracing::debug!("reverting to follower state due to greater term observed in RequestVote RPC response");
             } else {
@@ -149,36 +258,194 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 self.core.set_target_state(State::Leader);
                 return Ok(());
             }
+        } else {
+            self.observe_stale_log(&res);
         }
 
         // Otherwise, we just return and let the candidate loop wait for more votes to come in.
         Ok(())
     }
 
-    /// Spawn parallel vote requests to all cluster members.
-    #[tracing::instrument(level = "trace", skip(self))]
-    pub(super) fn spawn_parallel_vote_requests(&self) -> mpsc::Receiver<(VoteResponse, NodeId)> {
-        let all_nodes = self.core.effective_membership.membership.all_nodes().clone();
-        let (tx, rx) = mpsc::channel(all_nodes.len());
+    /// Remember that a voter rejected this node in this round for its log is stale.
+    fn observe_stale_log(&mut self, res: &VoteResponse) {
+        if !res.vote_granted && res.last_log_id > self.core.last_log_id {
+            tracing::debug!(self_last_log_id=%self.core.last_log_id, res_last_log_id=%res.last_log_id, "log is stale");
+            self.stale_log = true;
+        }
+    }
+
+    /// Count an election round that is lost, and back off from elections if voters rejected this node for its stale
+    /// log in too many rounds in a row, see `Config::backoff_on_stale_log`.
+    pub(super) fn count_stale_log_election(&mut self) {
+        if !self.core.config.backoff_on_stale_log || !self.core.target_state.is_candidate() {
+            return;
+        }
+
+        if !std::mem::take(&mut self.stale_log) {
+            self.core.stale_log_elections = 0;
+            return;
+        }
+
+        self.core.stale_log_elections += 1;
 
-        for member in all_nodes.into_iter().filter(|member| member != &self.core.id) {
-            let rpc = VoteRequest::new(self.core.current_term, self.core.id, self.core.last_log_id);
+        if self.core.stale_log_elections >= STALE_LOG_ELECTIONS {
+            tracing::warn!(
+                id = %self.core.id,
+                stale_log_elections = self.core.stale_log_elections,
+                last_log_id = %self.core.last_log_id,
+                "log is stale, pause elections until it advances"
+            );
+            let backoff = self.core.config.election_timeout_max * STALE_LOG_BACKOFF_ELECTION_TIMEOUTS;
+            self.core.stale_log_id = Some(self.core.last_log_id);
+            self.core.stale_log_deadline = Some(self.core.clock.now() + Duration::from_millis(backoff));
+            self.core.set_target_state(State::Paused);
+        }
+    }
+
+    /// Run a pre-vote round: ask every voter whether it would grant a vote for the next term, without changing the
+    /// term of any node.
+    ///
+    /// It returns true if a quorum would grant, or false if the round times out or this node is no longer a candidate.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn run_pre_vote(&mut self) -> RaftResult<bool> {
+        self.core.update_next_election_timeout(false);
+        self.core.report_metrics(Update::Update(None));
+
+        let mut granted = btreeset! {self.core.id};
+
+        // A peer that does not support pre-vote is counted as granting, as if there were no pre-vote phase.
+        let mut targets = BTreeSet::new();
+        let peers = self.peers();
+        for (peer, caps) in self.core.capabilities_of(peers).await {
+            if caps.pre_vote {
+                targets.insert(peer);
+            } else {
+                tracing::debug!(%peer, "peer does not support pre-vote, skip it");
+                granted.insert(peer);
+            }
+        }
+
+        if self.core.effective_membership.membership.is_majority(&granted) {
+            return Ok(true);
+        }
+
+        let rpc = VoteRequest {
+            pre_vote: true,
+            ..VoteRequest::new(self.core.current_term + 1, self.core.id, self.core.last_log_id)
+        };
+        let mut pending_votes = self.spawn_parallel_vote_requests(rpc, targets);
+
+        loop {
+            if !self.core.target_state.is_candidate() {
+                return Ok(false);
+            }
+            let deadline = self.core.get_next_election_timeout();
+            let timeout_fut = self.core.clock.sleep_until(deadline);
+
+            tokio::select! {
+                _ = timeout_fut => {
+                    tracing::debug!("pre-vote timeout, try again");
+                    return Ok(false);
+                }
+                Some((res, peer)) = pending_votes.recv() => {
+                    // A voter that does not respond in time rejects.
+                    let res = match res {
+                        Some(res) => res,
+                        None => continue,
+                    };
 
-            let (network, tx_inner) = (self.core.network.clone(), tx.clone());
-            let _ = tokio::spawn(
-                async move {
-                    let res = network.send_vote(member, rpc).await;
+                    self.observe_stale_log(&res);
+
+                    if res.term > self.core.current_term {
+                        self.core.update_current_term(res.term, None);
+                        self.core.save_hard_state().await?;
+                        self.core.update_current_leader(UpdateCurrentLeader::Unknown);
+                        self.count_stale_log_election();
+                        self.core.set_target_state(State::Follower);
+                        tracing::debug!("reverting to follower state due to greater term observed in pre-vote response");
+                        return Ok(false);
+                    }
 
-                    match res {
-                        Ok(vote_resp) => {
-                            let _ = tx_inner.send((vote_resp, member)).await;
+                    if res.vote_granted {
+                        granted.insert(peer);
+
+                        if self.core.effective_membership.membership.is_majority(&granted) {
+                            tracing::debug!("pre-vote is granted by a quorum, start an election");
+                            return Ok(true);
                         }
-                        Err(err) => tracing::error!({error=%err, target=member}, "while requesting vote"),
                     }
                 }
-                .instrument(tracing::debug_span!("send_vote_req", target = member)),
-            );
+                Some((msg,span)) = self.core.rx_api.recv() => {
+                    self.handle_msg(msg).instrument(span).await;
+                },
+                Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
+                Ok(graceful) = &mut self.core.rx_shutdown => self.core.handle_shutdown(graceful),
+            }
         }
+    }
+
+    /// Every cluster member except this node.
+    pub(super) fn peers(&self) -> BTreeSet<NID> {
+        let all_nodes = self.core.effective_membership.membership.all_nodes();
+        all_nodes.iter().filter(|member| *member != &self.core.id).copied().collect()
+    }
+
+    /// Spawn parallel vote requests to the given cluster members, with `RaftNetwork::broadcast_vote()`.
+    ///
+    /// Every request is sent at once, thus each is given up at the same deadline, the shorter one of
+    /// `Config::vote_timeout` and `Config::vote_request_timeout`.
+    /// The receiver gets `None` for a member that does not respond within it, which is counted as a rejection.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn spawn_parallel_vote_requests(
+        &self,
+        rpc: VoteRequest<NID>,
+        targets: BTreeSet<NID>,
+    ) -> mpsc::Receiver<(Option<VoteResponse>, NID)> {
+        let (tx, rx) = mpsc::channel(targets.len().max(1));
+
+        let clock = self.core.clock.clone();
+        let ttl = self.core.config.vote_request_ttl();
+        let mut expired = match ttl {
+            Some(ttl) => clock.sleep_until(clock.now() + ttl),
+            None => futures::future::pending().boxed(),
+        };
+
+        let network = self.core.network.clone();
+        let _ = tokio::spawn(
+            async move {
+                let mut outstanding = targets.clone();
+                let mut responses = network.broadcast_vote(targets, rpc);
+
+                loop {
+                    tokio::select! {
+                        next = responses.next() => {
+                            let (target, res) = match next {
+                                Some(x) => x,
+                                None => return,
+                            };
+                            outstanding.remove(&target);
+
+                            match res {
+                                Ok(vote_resp) => {
+                                    let _ = tx.send((Some(vote_resp), target)).await;
+                                }
+                                Err(err) => tracing::error!({error=%err, target=%target}, "while requesting vote"),
+                            }
+                        }
+                        _ = &mut expired => {
+                            // Dropping the stream abandons the requests not responded yet.
+                            for target in outstanding {
+                                tracing::warn!(?ttl, %target, "timeout requesting vote, count it as rejected");
+                                let _ = tx.send((None, target)).await;
+                            }
+                            return;
+                        }
+                    }
+                }
+            }
+            .instrument(tracing::debug_span!("send_vote_req")),
+        );
+
         rx
     }
 }
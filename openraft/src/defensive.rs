@@ -11,6 +11,8 @@ use crate::AppDataResponse;
 use crate::DefensiveError;
 use crate::ErrorSubject;
 use crate::LogId;
+use crate::NodeId;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::StorageError;
 use crate::Violation;
@@ -18,11 +20,12 @@ use crate::Wrapper;
 
 /// Defines methods of defensive checks for RaftStorage.
 #[async_trait]
-pub trait DefensiveCheck<D, R, T>
+pub trait DefensiveCheck<D, R, T, NID = NodeId>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
     Self: Wrapper<T>,
 {
     /// Enable or disable defensive check when calling storage APIs.
@@ -32,7 +35,7 @@ where
 
     /// Ensure that logs that have greater index than last_applied should have greater log_id.
     /// Invariant must hold: `log.log_id.index > last_applied.index` implies `log.log_id > last_applied`.
-    async fn defensive_no_dirty_log(&self) -> Result<(), StorageError> {
+    async fn defensive_no_dirty_log(&self) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -55,7 +58,7 @@ where
 
     /// Ensure that current_term must increment for every update, and for every term there could be only one value for
     /// voted_for.
-    async fn defensive_incremental_hard_state(&self, hs: &HardState) -> Result<(), StorageError> {
+    async fn defensive_incremental_hard_state(&self, hs: &HardState<NID>) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -88,7 +91,7 @@ where
     }
 
     /// The log entries fed into a store must be consecutive otherwise it is a bug.
-    async fn defensive_consecutive_input(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_consecutive_input(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -117,7 +120,7 @@ where
     /// Trying to feed in emtpy entries slice is an inappropriate action.
     ///
     /// The impl has to avoid this otherwise it may be a bug.
-    async fn defensive_nonempty_input(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_nonempty_input(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -130,7 +133,10 @@ where
     }
 
     /// The entries to append has to be last_log_id.index + 1
-    async fn defensive_append_log_index_is_last_plus_one(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_append_log_index_is_last_plus_one(
+        &self,
+        entries: &[&Entry<D, NID>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -152,7 +158,7 @@ where
     }
 
     /// The entries to append has to be greater than any known log ids
-    async fn defensive_append_log_id_gt_last(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_append_log_id_gt_last(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -175,7 +181,7 @@ where
 
     /// Find the last known log id from log or state machine
     /// If no log id found, the default one `0,0` is returned.
-    async fn last_log_id(&self) -> Result<LogId, StorageError> {
+    async fn last_log_id(&self) -> Result<LogId, StorageError<NID>> {
         let log_last_id = self.inner().last_id_in_log().await?;
         let (sm_last_id, _) = self.inner().last_applied_state().await?;
 
@@ -183,7 +189,10 @@ where
     }
 
     /// The entries to apply to state machien has to be last_applied_log_id.index + 1
-    async fn defensive_apply_index_is_last_applied_plus_one(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_apply_index_is_last_applied_plus_one(
+        &self,
+        entries: &[&Entry<D, NID>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -208,7 +217,7 @@ where
     async fn defensive_nonempty_range<RNG: RangeBounds<u64> + Clone + Debug + Send>(
         &self,
         range: RNG,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -240,7 +249,7 @@ where
     async fn defensive_half_open_range<RNG: RangeBounds<u64> + Clone + Debug + Send>(
         &self,
         range: RNG,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -264,8 +273,8 @@ where
     async fn defensive_range_hits_logs<RNG: RangeBounds<u64> + Debug + Send>(
         &self,
         range: RNG,
-        logs: &[Entry<D>],
-    ) -> Result<(), StorageError> {
+        logs: &[Entry<D, NID>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -318,7 +327,7 @@ where
     }
 
     /// The log id of the entries to apply has to be greater than the last known one.
-    async fn defensive_apply_log_id_gt_last(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_apply_log_id_gt_last(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
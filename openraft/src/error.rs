@@ -1,13 +1,18 @@
 //! Error types exposed by this crate.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::time::Duration;
 
+use crate::config::Compression;
 use crate::raft::Membership;
+use crate::raft_types::SnapshotId;
 use crate::raft_types::SnapshotSegmentId;
 use crate::LogId;
 use crate::NodeId;
+use crate::RaftNodeId;
+use crate::State;
 use crate::StorageError;
 
 /// A result type where the error variant is always a `RaftError`.
@@ -24,6 +29,34 @@ pub enum RaftError {
         got: SnapshotSegmentId,
     },
 
+    /// The received snapshot data does not match the checksum attached by the leader, the snapshot is discarded.
+    #[error("snapshot {snapshot_id} is corrupted, expect checksum: {expect:08x}, got: {got:08x}")]
+    SnapshotChecksumMismatch {
+        snapshot_id: SnapshotId,
+        expect: u32,
+        got: u32,
+    },
+
+    /// The state machine does not support the format version of the snapshot, the snapshot is not installed.
+    #[error("snapshot {snapshot_id} is of unsupported format version: {format_version}")]
+    SnapshotFormatUnsupported {
+        snapshot_id: SnapshotId,
+        format_version: u32,
+    },
+
+    /// The leader of the term sent a commit index less than one it sent before, see
+    /// `Config::reject_commit_regression`.
+    #[error("leader of term {term} regressed its commit index to {leader_commit}, less than {seen} it sent before")]
+    CommitIndexRegression {
+        term: u64,
+        leader_commit: LogId,
+        seen: LogId,
+    },
+
+    /// The compressed entries of an AppendEntries can not be decompressed, see `Config::replication_compression`.
+    #[error("failed to decompress the entries of AppendEntries: {0}")]
+    EntriesDecompression(anyhow::Error),
+
     /// An error which has come from the `RaftStorage` layer.
     #[error("{0}")]
     RaftStorage(anyhow::Error),
@@ -41,7 +74,7 @@ pub enum RaftError {
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 #[allow(clippy::large_enum_variant)]
-pub enum ReplicationError {
+pub enum ReplicationError<NID: RaftNodeId = NodeId> {
     #[error("seen a higher term: {higher} GT mine: {mine}")]
     HigherTerm { higher: u64, mine: u64 },
 
@@ -57,7 +90,7 @@ pub enum ReplicationError {
     // TODO(xp): two sub type: StorageError / TransportError
     // TODO(xp): a sub error for just send_append_entries()
     #[error("{0}")]
-    StorageError(#[from] StorageError),
+    StorageError(#[from] StorageError<NID>),
 
     #[error(transparent)]
     IO {
@@ -67,11 +100,10 @@ pub enum ReplicationError {
     },
 
     #[error("timeout after {timeout:?} to replicate {id}->{target}")]
-    Timeout {
-        id: NodeId,
-        target: NodeId,
-        timeout: Duration,
-    },
+    Timeout { id: NID, target: NID, timeout: Duration },
+
+    #[error("target does not support snapshot format version: {format_version}")]
+    SnapshotFormatUnsupported { format_version: u32 },
 
     #[error(transparent)]
     Network {
@@ -88,8 +120,8 @@ pub struct LackEntry {
 
 #[derive(Debug, thiserror::Error)]
 #[error("has to forward request to: {leader_id:?}")]
-pub struct ForwardToLeader {
-    pub leader_id: Option<NodeId>,
+pub struct ForwardToLeader<NID: RaftNodeId = NodeId> {
+    pub leader_id: Option<NID>,
 }
 
 impl From<tokio::io::Error> for RaftError {
@@ -100,26 +132,108 @@ impl From<tokio::io::Error> for RaftError {
 
 /// An error related to a client read request.
 #[derive(Debug, thiserror::Error)]
-pub enum ClientReadError {
+pub enum ClientReadError<NID: RaftNodeId = NodeId> {
+    #[error(transparent)]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID>),
+
+    /// The state machine of this node may lag behind the leader by more than the bound of a local read.
+    ///
+    /// The read should be sent to the leader instead.
+    #[error("state machine may be staler than {max_staleness:?}, read from the leader: {leader_id:?}")]
+    Stale {
+        leader_id: Option<NID>,
+        max_staleness: Duration,
+    },
+}
+
+/// An error related to checking whether a log is applied.
+#[derive(Debug, thiserror::Error)]
+pub enum AppliedError {
     #[error(transparent)]
     RaftError(#[from] RaftError),
 
+    /// The log id is not in the log of this node: the entry at its index has been replaced by another leader.
+    ///
+    /// The entry will never be applied.
+    #[error("log {log_id} is not in the log, found: {found}")]
+    NotInLog { log_id: LogId, found: LogId },
+
+    #[error("timeout after {timeout:?} waiting for log {log_id} to be applied")]
+    Timeout { log_id: LogId, timeout: Duration },
+}
+
+/// An error related to reading a range of logs with `Raft::get_log_entries()`.
+#[derive(Debug, thiserror::Error)]
+pub enum LogReadError {
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    RaftError(#[from] RaftError),
+
+    #[error("invalid log range [{start}, {end}): start must be <= end")]
+    InvalidRange { start: u64, end: u64 },
+
+    /// Some of the logs in the range have been purged, e.g., after being included in a snapshot.
+    #[error("logs before {first} are purged, can not read from {start}")]
+    Purged { start: u64, first: u64 },
+
+    /// Some of the logs in the range are not yet applied, thus they may not be committed and may be replaced.
+    #[error("log range [{start}, {end}) goes beyond the last applied log {last_applied}")]
+    NotApplied { start: u64, end: u64, last_applied: LogId },
+
+    /// Some of the logs in the range are not in the log yet.
+    #[error("log range [{start}, {end}) goes beyond the last log {last_log_id}")]
+    BeyondLastLog { start: u64, end: u64, last_log_id: LogId },
 }
 
 /// An error related to a client write request.
 #[derive(thiserror::Error, Debug, derive_more::TryInto)]
-pub enum ClientWriteError {
+pub enum ClientWriteError<NID: RaftNodeId = NodeId> {
     #[error("{0}")]
     RaftError(#[from] RaftError),
 
+    /// This node is not the leader, the request should be sent to the leader instead.
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    ForwardToLeader(#[from] ForwardToLeader<NID>),
+
+    /// This node is not the leader and does not know which node is, e.g., an election is in progress.
+    ///
+    /// The request should be retried later.
+    #[error("the leader is unknown, retry later")]
+    LeaderUnknown,
+
+    /// This node has an empty log and term 0, it is neither initialized nor a member of a cluster.
+    ///
+    /// Call `Raft::initialize()` on it, or add it to a cluster, before writing to it.
+    #[error("the node is not initialized")]
+    Uninitialized,
 
     /// When writing a change-membership entry.
     #[error(transparent)]
-    ChangeMembershipError(#[from] ChangeMembershipError),
+    ChangeMembershipError(#[from] ChangeMembershipError<NID>),
+
+    /// The leader has too many uncommitted entries, see `Config::max_uncommitted_entries`.
+    ///
+    /// The request is not appended, it should be retried after a while.
+    #[error("the leader has {uncommitted} uncommitted entries, more than {max}, retry later")]
+    Overloaded { uncommitted: u64, max: u64 },
+
+    /// The payload of the request is larger than `Config::max_log_entry_size`.
+    ///
+    /// The request is not appended, and retrying it will fail the same way.
+    #[error("the payload is {size} bytes, larger than the max log entry size {max}")]
+    PayloadTooLarge { size: u64, max: u64 },
+}
+
+impl<NID: RaftNodeId> ClientWriteError<NID> {
+    /// Build the error to reject a write on a node that is not the leader, with the leader it knows, if any.
+    pub(crate) fn not_leader(leader_id: Option<NID>) -> Self {
+        match leader_id {
+            Some(_) => ClientWriteError::ForwardToLeader(ForwardToLeader { leader_id }),
+            None => ClientWriteError::LeaderUnknown,
+        }
+    }
 }
 
 /// Error variants related to configuration.
@@ -131,14 +245,88 @@ pub enum ConfigError {
     #[error("given values for election timeout min & max are invalid: max must be greater than min")]
     InvalidElectionTimeoutMinMax,
 
+    /// The lambda of an exponential election timeout distribution must be a positive finite number.
+    #[error("the lambda of an exponential election timeout distribution must be > 0")]
+    InvalidElectionTimeoutDistribution,
+
     /// The given value for max_payload_entries is too small, must be > 0.
     #[error("the given value for max_payload_entries is too small, must be > 0")]
     MaxPayloadEntriesTooSmall,
 
+    /// The given value for max_payload_bytes is too small, must be > 0.
+    #[error("the given value for max_payload_bytes is too small, must be > 0")]
+    MaxPayloadBytesTooSmall,
+
+    /// The given value for max_in_flight_applies is too small, must be > 0.
+    #[error("the given value for max_in_flight_applies is too small, must be > 0")]
+    MaxInFlightAppliesTooSmall,
+
+    /// The given value for max_apply_batch_size is too small, must be > 0.
+    #[error("the given value for max_apply_batch_size is too small, must be > 0")]
+    MaxApplyBatchSizeTooSmall,
+
+    /// The given value for max_uncommitted_entries is too small, must be > 0.
+    #[error("the given value for max_uncommitted_entries is too small, must be > 0")]
+    MaxUncommittedEntriesTooSmall,
+
+    /// The given value for max_concurrent_snapshot_sends is too small, must be > 0.
+    #[error("the given value for max_concurrent_snapshot_sends is too small, must be > 0")]
+    MaxConcurrentSnapshotSendsTooSmall,
+
+    /// The given value for snapshot_ack_every_n_chunks is too small, must be > 0.
+    #[error("the given value for snapshot_ack_every_n_chunks is too small, must be > 0")]
+    SnapshotAckEveryNChunksTooSmall,
+
     /// election_timeout_min smaller than heartbeat_interval would cause endless election.
     /// A recommended election_timeout_min value is about 3 times heartbeat_interval.
     #[error("election_timeout_min value must be > heartbeat_interval")]
     ElectionTimeoutLessThanHeartBeatInterval,
+
+    /// election_timeout_min must leave room for several heartbeats, otherwise a single delayed heartbeat would
+    /// trigger an election.
+    #[error("election_timeout_min value must be >= {min_heartbeats} * heartbeat_interval")]
+    ElectionTimeoutTooCloseToHeartbeat { min_heartbeats: u64 },
+
+    /// A vote request must time out before the election does, otherwise the timeout takes no effect.
+    #[error("vote_request_timeout must be < election_timeout_min")]
+    VoteRequestTimeoutTooLarge,
+
+    /// A RequestVote RPC must time out before the election does, otherwise the timeout takes no effect.
+    #[error("vote_timeout must be < election_timeout_min")]
+    VoteTimeoutTooLarge,
+
+    /// The codec of replication_compression is provided by a cargo feature that is not enabled.
+    #[error("the cargo feature of replication_compression {codec:?} is not enabled")]
+    CompressionNotEnabled { codec: Compression },
+
+    /// Coalescing syncs for a heartbeat interval or longer delays commits more than a heartbeat round does.
+    #[error("fsync_coalesce_window must be < heartbeat_interval")]
+    FsyncCoalesceWindowTooLarge,
+
+    /// A clock drift margin as long as the election timeout leaves no leader lease at all.
+    #[error("max_clock_drift must be < election_timeout_min")]
+    ClockDriftTooLarge,
+
+    /// install_snapshot_timeout is too small to send a snapshot chunk of snapshot_max_chunk_size bytes, every
+    /// InstallSnapshot RPC would likely time out.
+    #[error("install_snapshot_timeout {install_snapshot_timeout} ms is likely too small to send a snapshot chunk of {snapshot_max_chunk_size} bytes, must be >= {min} ms")]
+    SnapshotTimeoutLikelyTooSmall {
+        install_snapshot_timeout: u64,
+        snapshot_max_chunk_size: u64,
+        min: u64,
+    },
+
+    /// A `LogsOrDuration` snapshot policy with both a zero log count and a zero duration, or a `SizeSinceLast` policy
+    /// with a zero size, would never be satisfied in a meaningful way.
+    #[error(
+        "snapshot policy logs_or_duration must have a non-zero log count or a non-zero duration, size_since_last \
+             must have a non-zero size"
+    )]
+    InvalidSnapshotPolicy,
+
+    /// The backoff of replication retries must start from a non-zero delay that is no more than the maximum delay.
+    #[error("replication_retry_base must be > 0 and <= replication_retry_max")]
+    InvalidReplicationRetryBackoff,
 }
 
 /// The set of errors which may take place when initializing a pristine Raft node.
@@ -152,44 +340,184 @@ pub enum InitializeError {
     /// The requested action is not allowed due to the Raft node's current state.
     #[error("the requested action is not allowed due to the Raft node's current state")]
     NotAllowed,
+
+    /// The log of the Raft node is not empty: it is already a member of an initialized cluster.
+    #[error("the raft node is already initialized, last log: {last_log_id}")]
+    AlreadyInitialized { last_log_id: LogId },
 }
 
 /// The set of errors which may take place when requesting to propose a config change.
 #[derive(Debug, thiserror::Error)]
-pub enum ChangeMembershipError {
+pub enum ChangeMembershipError<NID: RaftNodeId = NodeId> {
     #[error("the cluster is already undergoing a configuration change at log {membership_log_id}")]
     InProgress { membership_log_id: LogId },
 
     #[error("new membership can not be empty")]
     EmptyMembership,
 
+    /// The total vote weight of the new membership is 0, no set of voters has more than half of it.
+    #[error("the total vote weight of new membership {weights:?} can not form a majority")]
+    NoWeightedMajority { weights: BTreeMap<NID, u64> },
+
+    /// The total vote weight of the new membership does not fit in a `u64`.
+    #[error("the total vote weight of new membership {weights:?} overflows u64")]
+    WeightOverflow { weights: BTreeMap<NID, u64> },
+
     // TODO(xp): 111 test it
     #[error("to add a member {node_id} first need to add it as learner")]
-    LearnerNotFound { node_id: NodeId },
+    LearnerNotFound { node_id: NID },
 
     // TODO(xp): 111 test it
     #[error("replication to learner {node_id} is lagging {distance}, matched: {matched}, can not add as member")]
     LearnerIsLagging {
-        node_id: NodeId,
+        node_id: NID,
         matched: LogId,
         distance: u64,
     },
 
+    /// A new voter did not catch up with the leader in time, when changing membership with `blocking_catch_up`.
+    #[error("learner {node_id} did not catch up in {timeout:?}, lagging {distance}, matched: {matched}")]
+    CatchUpTimeout {
+        node_id: NID,
+        matched: LogId,
+        distance: u64,
+        timeout: Duration,
+    },
+
     // TODO(xp): test it in unittest
     // TODO(xp): rename this error to some elaborated name.
     // TODO(xp): 111 test it
     #[error("now allowed to change from {curr:?} to {to:?}")]
-    Incompatible { curr: Membership, to: BTreeSet<NodeId> },
+    Incompatible { curr: Membership<NID>, to: BTreeSet<NID> },
+
+    /// A witness must be a new voter other than the leader, and a node replicated to as a witness can not become a
+    /// full voter.
+    #[error("invalid witness {node_id}: a witness must be a new voter other than the leader, and stays a witness")]
+    InvalidWitness { node_id: NID },
+
+    /// The membership change is aborted by `Raft::abort_membership_change()`.
+    #[error("the membership change is aborted")]
+    Aborted,
+
+    /// There is no membership change to abort.
+    #[error("there is no membership change in progress to abort")]
+    NotInProgress,
+
+    /// The joint config of the membership change is committed, it can only go on to the new config.
+    #[error("can not abort the membership change, the joint config at log {membership_log_id} is committed")]
+    JointCommitted { membership_log_id: LogId },
+
+    /// `Raft::force_remove_node()` is called without asserting the node is gone for good.
+    #[error("force removing {node_id} is not confirmed, the caller must assert the node is gone for good")]
+    ForceRemoveNotConfirmed { node_id: NID },
+
+    /// The node to force remove is the leader itself, or is not a voter.
+    #[error("can not force remove {node_id}: it is the leader, or it is not a voter")]
+    InvalidForceRemove { node_id: NID },
+
+    /// The voters the leader can reach do not form a quorum of the proposed membership, committing it would stall.
+    #[error("reachable voters {reachable:?} can not form a quorum of the new membership")]
+    QuorumUnreachable { reachable: BTreeSet<NID> },
+}
+
+/// The set of errors which may take place when updating the config of a running Raft node.
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateConfigError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    /// The updated config is invalid, the current config is kept.
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+}
+
+/// The set of errors which may take place when transferring leadership.
+#[derive(Debug, thiserror::Error)]
+pub enum TransferLeadershipError<NID: RaftNodeId = NodeId> {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID>),
+
+    #[error("leadership transfer to {target} is already in progress")]
+    InProgress { target: NID },
+
+    #[error("node {node_id} is not a voter other than the leader, or is a witness, can not transfer leadership to it")]
+    InvalidTarget { node_id: NID },
+
+    #[error("there is no voter to transfer leadership to")]
+    NoTarget,
+
+    #[error("timeout after {timeout:?} to catch up {target} for leadership transfer")]
+    Timeout { target: NID, timeout: Duration },
+}
+
+/// The set of errors which may take place when pausing or resuming replication to a node.
+#[derive(Debug, thiserror::Error)]
+pub enum PauseReplicationError<NID: RaftNodeId = NodeId> {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID>),
+
+    #[error("node {node_id} is not replicated to by the leader")]
+    NotReplicated { node_id: NID },
+}
+
+/// The set of errors which may take place when triggering a snapshot.
+#[derive(Debug, thiserror::Error)]
+pub enum TriggerSnapshotError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error("a snapshot is already being built or installed")]
+    InProgress,
+
+    #[error("no log is applied, there is nothing to snapshot")]
+    NothingToSnapshot,
+
+    #[error("failed to build the snapshot")]
+    Failed,
 }
 
+/// The set of errors which may take place when rebuilding the state machine.
 #[derive(Debug, thiserror::Error)]
-pub enum AddLearnerError {
+pub enum RebuildStateMachineError {
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+
+    #[error("can not rebuild the state machine in state {0:?}, only a follower or a learner can")]
+    NotAllowed(State),
+
+    #[error("a snapshot is being built or installed")]
+    SnapshotInProgress,
+
+    #[error("the logs since index {since} to re-apply are purged")]
+    LogsPurged { since: u64 },
+
+    #[error("there is no snapshot to reset the state machine to")]
+    NoSnapshot,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AddLearnerError<NID: RaftNodeId = NodeId> {
     #[error("{0}")]
     RaftError(#[from] RaftError),
 
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    ForwardToLeader(#[from] ForwardToLeader<NID>),
 
     #[error("node {0} is already a learner")]
-    Exists(NodeId),
+    Exists(NID),
+
+    /// The learner added with `blocking` falls further behind instead of catching up, or does not catch up before the
+    /// deadline. The leader stops replicating to it.
+    #[error("learner {node_id} can not catch up, lagging {distance}, matched: {matched}")]
+    LearnerCannotCatchUp {
+        node_id: NID,
+        matched: LogId,
+        distance: u64,
+    },
 }
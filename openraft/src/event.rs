@@ -0,0 +1,45 @@
+//! Discrete lifecycle events of a Raft node, see `Raft::events()`.
+
+use tokio::sync::broadcast;
+
+use crate::core::EffectiveMembership;
+use crate::LogId;
+use crate::NodeId;
+use crate::RaftNodeId;
+
+/// The number of events a subscriber can fall behind before it misses some of them.
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// The sending end of the `RaftEvent` channel, shared by RaftCore and the tasks it spawns.
+pub(crate) type EventTx<NID> = broadcast::Sender<RaftEvent<NID>>;
+
+/// A lifecycle event of a Raft node.
+///
+/// Unlike `RaftMetrics`, which only keeps the latest state, every event is delivered to every subscriber in the
+/// order it happened, unless the subscriber falls more than `1024` events behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaftEvent<NID: RaftNodeId = NodeId> {
+    /// This node became the leader of the term.
+    BecameLeader(u64),
+
+    /// This node became a follower in the term, with the leader if it is known.
+    BecameFollower(u64, Option<NID>),
+
+    /// This node stopped starting elections in the term and became `State::Paused`, after
+    /// `Config::max_consecutive_failed_elections` failed elections in a row, or to back off for its stale log. It
+    /// resumes when it hears from a leader.
+    ElectionsPaused(u64),
+
+    /// The effective membership config of this node changed.
+    MembershipChanged(EffectiveMembership<NID>),
+
+    /// A snapshot sent by the leader is installed, which includes logs upto the log id.
+    SnapshotInstalled(LogId),
+
+    /// The logs upto the log id, inclusive, are purged from the log.
+    LogPurged(LogId),
+
+    /// The replication to a learner has come within `Config::replication_lag_threshold` logs of this leader's last
+    /// log: the learner is ready to be promoted with `Raft::promote_learner()`.
+    LearnerReady(NID),
+}
@@ -1,17 +1,46 @@
 #![doc = include_str!("../README.md")]
 #![feature(backtrace)]
 
+// The optional dependencies are renamed to be enabled by the features of their own names, e.g. `borsh`.
+#[cfg(feature = "borsh")]
+extern crate dep_borsh as borsh;
+#[cfg(feature = "lz4_flex")]
+extern crate dep_lz4_flex as lz4_flex;
+#[cfg(feature = "zstd")]
+extern crate dep_zstd as zstd;
+
+#[cfg(all(test, feature = "borsh"))]
+mod borsh_test;
+mod clock;
+mod compression;
+#[cfg(test)]
+mod compression_test;
 pub mod config;
 mod core;
 pub mod error;
+pub mod event;
+mod log_cache;
+#[cfg(test)]
+mod log_cache_test;
 pub mod metrics;
 #[cfg(test)]
+mod metrics_changes_test;
+#[cfg(feature = "prometheus")]
+mod metrics_prometheus;
+#[cfg(all(test, feature = "prometheus"))]
+mod metrics_prometheus_test;
+#[cfg(test)]
 mod metrics_wait_test;
 pub mod network;
-mod quorum;
 pub mod raft;
 mod raft_types;
 mod replication;
+mod session_table;
+#[cfg(test)]
+mod session_table_test;
+pub mod snapshot_stream;
+#[cfg(test)]
+mod snapshot_stream_test;
 pub mod storage;
 mod storage_error;
 mod summary;
@@ -22,13 +51,27 @@ mod membership_test;
 mod store_ext;
 mod store_wrapper;
 
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+
 pub use async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 pub use store_ext::StoreExt;
 pub use store_wrapper::Wrapper;
 
+pub use crate::clock::Clock;
+pub use crate::clock::TokioClock;
+pub use crate::config::Compression;
 pub use crate::config::Config;
+pub use crate::config::ConfigBuilder;
+pub use crate::config::ConfigDelta;
+pub use crate::config::Durability;
+pub use crate::config::ElectionTimeoutDistribution;
+pub use crate::config::LogPurgePolicy;
+pub use crate::config::ReadStrategy;
+pub use crate::config::SnapshotChecksum;
 pub use crate::config::SnapshotPolicy;
 pub use crate::core::EffectiveMembership;
 pub use crate::core::State;
@@ -37,10 +80,20 @@ pub use crate::error::ChangeMembershipError;
 pub use crate::error::ClientWriteError;
 pub use crate::error::ConfigError;
 pub use crate::error::InitializeError;
+pub use crate::error::PauseReplicationError;
 pub use crate::error::RaftError;
+pub use crate::error::RebuildStateMachineError;
 pub use crate::error::ReplicationError;
+pub use crate::error::TransferLeadershipError;
+pub use crate::error::TriggerSnapshotError;
+pub use crate::error::UpdateConfigError;
+pub use crate::event::RaftEvent;
+pub use crate::metrics::MembershipMetrics;
+pub use crate::metrics::PeerHealth;
 pub use crate::metrics::RaftMetrics;
+pub use crate::network::NodeCapabilities;
 pub use crate::network::RaftNetwork;
+use crate::raft::Entry;
 pub use crate::raft::Raft;
 pub use crate::raft_types::LogId;
 pub use crate::raft_types::SnapshotId;
@@ -48,10 +101,14 @@ pub use crate::raft_types::SnapshotSegmentId;
 pub use crate::raft_types::StateMachineChanges;
 pub use crate::raft_types::Update;
 pub use crate::replication::ReplicationMetrics;
+pub use crate::replication::ReplicationStatus;
+pub use crate::session_table::ClientSessionTable;
 pub use crate::storage::RaftStorage;
 pub use crate::storage::RaftStorageDebug;
 pub use crate::storage::SnapshotMeta;
+pub use crate::storage::SnapshotView;
 pub use crate::storage_error::DefensiveError;
+pub use crate::storage_error::ErrorClass;
 pub use crate::storage_error::ErrorSubject;
 pub use crate::storage_error::ErrorVerb;
 pub use crate::storage_error::StorageError;
@@ -59,9 +116,21 @@ pub use crate::storage_error::StorageIOError;
 pub use crate::storage_error::Violation;
 pub use crate::summary::MessageSummary;
 
-/// A Raft node's ID.
+/// The default type of a Raft node's ID.
 pub type NodeId = u64;
 
+/// A trait defining the type of a Raft node's ID.
+///
+/// Applications that already have their own id scheme, such as UUIDs or structured ids, may use it as the id of the
+/// Raft nodes instead of the default `NodeId`. It is implemented for every type that meets its bounds.
+pub trait RaftNodeId:
+    Copy + Debug + Display + Ord + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+impl<T> RaftNodeId for T where T: Copy + Debug + Display + Ord + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{}
+
 /// A trait defining application specific data.
 ///
 /// The intention of this trait is that applications which are using this crate will be able to
@@ -70,7 +139,29 @@ pub type NodeId = u64;
 /// models as-is to Raft, Raft will present it to the application's `RaftStorage` impl when ready,
 /// and the application may then deal with the data directly in the storage engine without having
 /// to do a preliminary deserialization.
-pub trait AppData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {}
+pub trait AppData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {
+    /// Encode a batch of log entries to compress them as a whole, see `Config::replication_compression`.
+    ///
+    /// It should use the encoding the application sends entries over the network in. By default it returns
+    /// `Ok(None)`, and entries are always replicated uncompressed.
+    fn encode_entries<NID: RaftNodeId>(_entries: &[Entry<Self, NID>]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Decode the log entries encoded with `encode_entries()`.
+    fn decode_entries<NID: RaftNodeId>(_data: &[u8]) -> anyhow::Result<Vec<Entry<Self, NID>>> {
+        Err(anyhow::anyhow!("decoding log entries is not supported"))
+    }
+
+    /// The size in bytes of this data when it is sent over the network, to bound a replication payload with
+    /// `Config::max_payload_bytes`, and a client write with `Config::max_log_entry_size`.
+    ///
+    /// It is called for every replicated entry, thus it should be cheap. By default it is the in-memory size of the
+    /// value, not counting the heap data it owns: an application with variable sized data should count it.
+    fn size_hint(&self) -> u64 {
+        std::mem::size_of_val(self) as u64
+    }
+}
 
 /// A trait defining application specific response data.
 ///
@@ -0,0 +1,93 @@
+//! A cache of the most recent log entries, shared by a Raft node and its replication streams.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::raft::Entry;
+use crate::AppData;
+use crate::RaftNodeId;
+
+/// Keeps the last `capacity` entries appended to the log, so that the replication streams read the hot tail of the
+/// log from memory instead of from `RaftStorage`.
+///
+/// The cached entries are always consecutive and always the same as those in the log: RaftCore adds every entry it
+/// appends, and removes the entries it deletes from the log. With a capacity of 0 nothing is cached.
+pub(crate) struct LogCache<D: AppData, NID: RaftNodeId> {
+    capacity: usize,
+    entries: Mutex<VecDeque<Entry<D, NID>>>,
+}
+
+impl<D: AppData, NID: RaftNodeId> LogCache<D, NID> {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            capacity: capacity as usize,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Add entries that have just been appended to the log, evicting the oldest ones beyond the capacity.
+    ///
+    /// An entry replaces the cached entry at the same index and all after it. If the entries do not follow the cached
+    /// ones, the cache restarts from them.
+    pub fn append(&self, entries: &[&Entry<D, NID>]) {
+        if self.capacity == 0 || entries.is_empty() {
+            return;
+        }
+
+        let mut cached = self.entries.lock().unwrap();
+
+        let first = entries[0].log_id.index;
+        Self::truncate_locked(&mut cached, first);
+
+        let follows = cached.back().map(|x| x.log_id.index + 1 == first).unwrap_or(true);
+        if !follows {
+            cached.clear();
+        }
+
+        for ent in entries.iter() {
+            cached.push_back((*ent).clone());
+        }
+
+        while cached.len() > self.capacity {
+            cached.pop_front();
+        }
+    }
+
+    /// Remove the cached entries at index `since` and after, which are deleted from the log.
+    pub fn truncate(&self, since: u64) {
+        let mut cached = self.entries.lock().unwrap();
+        Self::truncate_locked(&mut cached, since);
+    }
+
+    /// Remove every cached entry, e.g., when a snapshot replaces the log.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Get the entries in `[start, end)`, only if all of them are cached.
+    pub fn get(&self, start: u64, end: u64) -> Option<Vec<Entry<D, NID>>> {
+        let cached = self.entries.lock().unwrap();
+
+        let first = cached.front()?.log_id.index;
+        let last = cached.back()?.log_id.index;
+
+        if start < first || end > last + 1 || start > end {
+            return None;
+        }
+
+        let from = (start - first) as usize;
+        let to = (end - first) as usize;
+        Some(cached.range(from..to).cloned().collect())
+    }
+
+    /// Get the entry at `index`, if it is cached.
+    pub fn get_one(&self, index: u64) -> Option<Entry<D, NID>> {
+        self.get(index, index + 1).and_then(|mut x| x.pop())
+    }
+
+    fn truncate_locked(cached: &mut VecDeque<Entry<D, NID>>, since: u64) {
+        while cached.back().map(|x| x.log_id.index >= since).unwrap_or(false) {
+            cached.pop_back();
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::log_cache::LogCache;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::AppData;
+use crate::LogId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Data(String);
+
+impl AppData for Data {}
+
+fn ent(term: u64, index: u64) -> Entry<Data> {
+    Entry {
+        log_id: LogId { term, index },
+        payload: EntryPayload::Blank,
+        request_id: None,
+    }
+}
+
+fn indexes(entries: Option<Vec<Entry<Data>>>) -> Option<Vec<u64>> {
+    entries.map(|x| x.iter().map(|e| e.log_id.index).collect())
+}
+
+#[test]
+fn test_log_cache_evicts_oldest() -> anyhow::Result<()> {
+    let cache = LogCache::<Data, u64>::new(3);
+
+    let ents = (1..=5).map(|i| ent(1, i)).collect::<Vec<_>>();
+    cache.append(&ents.iter().collect::<Vec<_>>());
+
+    assert_eq!(Some(vec![3, 4, 5]), indexes(cache.get(3, 6)));
+    assert_eq!(Some(vec![4]), indexes(cache.get(4, 5)));
+    assert_eq!(Some(vec![]), indexes(cache.get(6, 6)));
+    assert_eq!(None, indexes(cache.get(2, 4)), "evicted");
+    assert_eq!(None, indexes(cache.get(4, 7)), "beyond the last");
+    assert_eq!(None, cache.get_one(2));
+    assert_eq!(Some(ent(1, 5)), cache.get_one(5));
+
+    Ok(())
+}
+
+#[test]
+fn test_log_cache_truncate() -> anyhow::Result<()> {
+    let cache = LogCache::<Data, u64>::new(10);
+
+    let ents = (1..=5).map(|i| ent(1, i)).collect::<Vec<_>>();
+    cache.append(&ents.iter().collect::<Vec<_>>());
+
+    cache.truncate(4);
+    assert_eq!(None, cache.get_one(4));
+    assert_eq!(Some(vec![1, 2, 3]), indexes(cache.get(1, 4)));
+
+    // An overlapping append replaces the entries since its first one.
+    cache.append(&[&ent(2, 3), &ent(2, 4)]);
+    assert_eq!(Some(ent(2, 3)), cache.get_one(3));
+    assert_eq!(Some(vec![1, 2, 3, 4]), indexes(cache.get(1, 5)));
+
+    // A non-consecutive append restarts the cache.
+    cache.append(&[&ent(2, 10)]);
+    assert_eq!(None, cache.get_one(4));
+    assert_eq!(Some(ent(2, 10)), cache.get_one(10));
+
+    cache.clear();
+    assert_eq!(None, cache.get_one(10));
+
+    Ok(())
+}
+
+#[test]
+fn test_log_cache_disabled() -> anyhow::Result<()> {
+    let cache = LogCache::<Data, u64>::new(0);
+
+    cache.append(&[&ent(1, 1)]);
+    assert_eq!(None, cache.get_one(1));
+
+    Ok(())
+}
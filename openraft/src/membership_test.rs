@@ -29,6 +29,9 @@ fn test_membership() -> anyhow::Result<()> {
     assert_eq!(&btreeset! {1,2,3}, m123.all_nodes());
     assert_eq!(&btreeset! {1,2,3,4,5}, m123_345.all_nodes());
 
+    assert_eq!(&btreeset! {1,2,3}, m123.voters());
+    assert_eq!(&btreeset! {1,2,3,4,5}, m123_345.voters());
+
     assert!(!m1.contains(&0));
     assert!(m1.contains(&1));
     assert!(m123_345.contains(&4));
@@ -132,3 +135,128 @@ fn test_membership_greatest_majority_value() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_membership_weighted() -> anyhow::Result<()> {
+    // Weights of 1 are not stored.
+    assert_eq!(
+        Membership::new_multi(vec![btreeset! {1,2,3}]),
+        Membership::<NodeId>::new_weighted(vec![btreemap! {1=>1,2=>1,3=>1}])
+    );
+
+    let m = Membership::<NodeId>::new_weighted(vec![btreemap! {1=>1,2=>1,3=>1}, btreemap! {1=>2,2=>1,3=>1}]);
+    assert!(m.is_weighted());
+    assert_eq!(&btreeset! {1,2,3}, m.all_nodes());
+    assert_eq!(1, m.weight(0, &1));
+    assert_eq!(2, m.weight(1, &1));
+    assert_eq!(Some(btreemap! {1=>2,2=>1,3=>1}), m.get_ith_weights(1));
+    assert_eq!(None, m.get_ith_weights(2));
+
+    let got = m.to_final_config();
+    assert_eq!(Membership::new_weighted(vec![btreemap! {1=>2,2=>1,3=>1}]), got);
+
+    // The final config of all weights of 1 is not weighted.
+    let m = Membership::<NodeId>::new_weighted(vec![btreemap! {1=>2,2=>1,3=>1}, btreemap! {1=>1,2=>1,3=>1}]);
+    assert!(!m.to_final_config().is_weighted());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_weighted_majority() -> anyhow::Result<()> {
+    {
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>2,1=>1,2=>1}]);
+        assert!(!m.is_majority(&btreeset! {0}));
+        assert!(!m.is_majority(&btreeset! {1,2}));
+        assert!(m.is_majority(&btreeset! {0,1}));
+        assert!(m.is_majority(&btreeset! {0,2}));
+    }
+
+    {
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>3,1=>1,2=>1}]);
+        assert!(m.is_majority(&btreeset! {0}));
+        assert!(!m.is_majority(&btreeset! {1,2}));
+    }
+
+    {
+        // A voter of weight 0 never helps to form a quorum.
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>1,1=>1,2=>0}]);
+        assert!(!m.is_majority(&btreeset! {0,2}));
+        assert!(m.is_majority(&btreeset! {0,1}));
+    }
+
+    {
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>1,1=>1,2=>1}, btreemap! {0=>2,1=>1,2=>1}]);
+        assert!(
+            !m.is_majority(&btreeset! {1,2}),
+            "a majority of the first config but not the second"
+        );
+        assert!(m.is_majority(&btreeset! {0,1}));
+    }
+
+    {
+        // Weights summing up to near u64::MAX do not overflow.
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>u64::MAX/2,1=>u64::MAX/2,2=>1}]);
+        assert!(!m.is_majority(&btreeset! {0}));
+        assert!(m.is_majority(&btreeset! {0,2}));
+
+        let values = btreemap! {0=>5,1=>3,2=>7};
+        assert_eq!(Some(&5), m.greatest_majority_value(&values));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_weighted_greatest_majority_value() -> anyhow::Result<()> {
+    {
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>2,1=>1,2=>1}]);
+        assert_eq!(None, m.greatest_majority_value(&btreemap! {0=>10}));
+        assert_eq!(None, m.greatest_majority_value(&btreemap! {1=>10,2=>20}));
+        assert_eq!(Some(&10), m.greatest_majority_value(&btreemap! {0=>10,1=>20}));
+        assert_eq!(Some(&20), m.greatest_majority_value(&btreemap! {0=>30,1=>10,2=>20}));
+        assert_eq!(Some(&20), m.greatest_majority_value(&btreemap! {0=>20,1=>30,2=>30}));
+    }
+
+    {
+        let m = Membership::<NodeId>::new_weighted(vec![btreemap! {0=>3,1=>1,2=>1}]);
+        assert_eq!(Some(&10), m.greatest_majority_value(&btreemap! {0=>10}));
+        assert_eq!(Some(&10), m.greatest_majority_value(&btreemap! {0=>10,1=>20,2=>20}));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_witnesses() -> anyhow::Result<()> {
+    // Non-voters are not witnesses.
+    let m = Membership::<NodeId>::new_multi(vec![btreeset! {1,2}, btreeset! {1,2,3}]).with_witnesses(btreeset! {3,4});
+    assert_eq!(&btreeset! {3}, m.witnesses());
+    assert!(m.is_witness(&3));
+    assert!(!m.is_witness(&4));
+
+    assert!(m.is_electable(&1));
+    assert!(!m.is_electable(&3), "a witness is not electable");
+    assert!(!m.is_electable(&4), "a non-voter is not electable");
+
+    // A witness counts toward a quorum.
+    let m123 = Membership::<NodeId>::new_single(btreeset! {1,2,3}).with_witnesses(btreeset! {3});
+    assert!(m123.is_majority(&btreeset! {1,3}));
+    assert!(!m123.is_majority(&btreeset! {3}));
+
+    // The final config keeps the witnesses in it.
+    assert_eq!(&btreeset! {3}, m.to_final_config().witnesses());
+
+    let m = Membership::<NodeId>::new_multi(vec![btreeset! {1,2,3}, btreeset! {1,2}]).with_witnesses(btreeset! {3});
+    assert!(m.to_final_config().witnesses().is_empty());
+
+    // Witnesses are stored in the membership config, but not when there is none.
+    let m = Membership::<NodeId>::new_single(btreeset! {1,2,3}).with_witnesses(btreeset! {3});
+    let got: Membership<NodeId> = serde_json::from_str(&serde_json::to_string(&m)?)?;
+    assert_eq!(m, got);
+
+    let m = Membership::<NodeId>::new_single(btreeset! {1,2,3});
+    assert!(!serde_json::to_string(&m)?.contains("witnesses"));
+
+    Ok(())
+}
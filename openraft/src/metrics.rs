@@ -7,75 +7,213 @@
 //! Metrics are observed on a running Raft node via the `Raft::metrics()` method, which will
 //! return a stream of metrics.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
 
+use futures::Stream;
 use serde::Deserialize;
 use serde::Serialize;
 use thiserror::Error;
+use tokio::sync::broadcast;
 use tokio::sync::watch;
 use tokio::time::Duration;
 use tokio::time::Instant;
 
 use crate::core::EffectiveMembership;
 use crate::core::State;
+use crate::event::RaftEvent;
 use crate::raft::Membership;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::NodeId;
 use crate::RaftError;
+use crate::RaftNodeId;
 use crate::ReplicationMetrics;
+use crate::ReplicationStatus;
 
 /// A set of metrics describing the current state of a Raft node.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RaftMetrics {
+#[serde(bound = "")]
+pub struct RaftMetrics<NID: RaftNodeId = NodeId> {
     /// The ID of the Raft node.
-    pub id: NodeId,
+    pub id: NID,
     /// The state of the Raft node.
     pub state: State,
     /// The current term of the Raft node.
     pub current_term: u64,
+    /// The greatest term this Raft node has seen, including the terms received from other nodes.
+    pub max_term_seen: u64,
     /// The last log index to be appended to this Raft node's log.
     pub last_log_index: u64,
     /// The last log index to be applied to this Raft node's state machine.
     pub last_applied: u64,
+
+    /// The id of the last log this Raft node knows to be committed, i.e., accepted by a quorum. `None` if it knows
+    /// of no committed log.
+    ///
+    /// It may fall behind the leader's on a follower, and may run ahead of `last_applied` while the state machine is
+    /// catching up.
+    pub last_committed: Option<LogId>,
+
     /// The current cluster leader.
-    pub current_leader: Option<NodeId>,
+    pub current_leader: Option<NID>,
+
+    /// Whether this node is a leader that has committed the initial entry of its term.
+    ///
+    /// A newly elected leader does not know which of its logs are committed until the entry it appends on
+    /// election is committed. Until then it can not serve reads or report an up to date commit index.
+    pub leader_ready: bool,
+
     /// The current membership config of the cluster.
-    pub membership_config: EffectiveMembership,
+    pub membership_config: EffectiveMembership<NID>,
 
     /// The id of the last log included in snapshot.
     /// If there is no snapshot, it is (0,0).
     pub snapshot: LogId,
 
+    /// Whether this node is building a snapshot.
+    pub snapshot_building: bool,
+
+    /// The bytes received and the total bytes of the snapshot this node is receiving from the leader, if any. The
+    /// total is 0 if the leader does not know the size, see `SnapshotMeta::size`.
+    pub installing_snapshot_progress: Option<(u64, u64)>,
+
+    /// Milliseconds since this node last received an AppendEntries from a leader of the current or a greater term.
+    ///
+    /// It is `Some()` only on a follower or learner that has heard from a leader. Unlike `current_leader`, it tells
+    /// whether the leader is still alive: a health check may find a silently dead leader with it before the
+    /// election timeout fires. It is rounded down to a multiple of `Config::heartbeat_interval`, and refreshed every
+    /// `Config::heartbeat_interval`.
+    pub millis_since_last_heartbeat: Option<u64>,
+
+    /// The number of AppendEntries this node rejected because the leader's `prev_log_id` does not match its log.
+    ///
+    /// Every conflict makes the leader retry with an earlier log. A count that keeps growing tells that this node's
+    /// log keeps diverging from the leader's.
+    pub conflicts_reported: u64,
+
     /// The metrics about the leader. It is Some() only when this node is leader.
-    pub leader_metrics: Option<LeaderMetrics>,
+    ///
+    /// It is shared between clones, so that cloning a `RaftMetrics` does not copy the replication metrics of every
+    /// target.
+    pub leader_metrics: Option<Arc<LeaderMetrics<NID>>>,
 }
 
-impl MessageSummary for RaftMetrics {
+impl<NID: RaftNodeId> MessageSummary for RaftMetrics<NID> {
     fn summary(&self) -> String {
-        format!("Metrics{{id:{},{:?}, term:{}, last_log:{}, last_applied:{}, leader:{:?}, membership:{}, snapshot:{}, replication:{}",
+        format!("Metrics{{id:{},{:?}, term:{}, max_term_seen:{}, last_log:{}, last_applied:{}, last_committed:{:?}, leader:{:?}, leader_ready:{}, membership:{}, snapshot:{}, snapshot_building:{}, installing_snapshot:{:?}, since_last_heartbeat:{:?}, conflicts_reported:{}, replication:{}",
             self.id,
             self.state,
             self.current_term,
+            self.max_term_seen,
             self.last_log_index,
             self.last_applied,
+            self.last_committed,
             self.current_leader,
+            self.leader_ready,
             self.membership_config.summary(),
             self.snapshot,
+            self.snapshot_building,
+            self.installing_snapshot_progress,
+            self.millis_since_last_heartbeat,
+            self.conflicts_reported,
             self.leader_metrics.as_ref().map(|x| x.summary()).unwrap_or_default(),
         )
     }
 }
 
 /// The metrics about the leader. It is Some() only when this node is leader.
-#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
-pub struct LeaderMetrics {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LeaderMetrics<NID: RaftNodeId = NodeId> {
     /// Replication metrics of all known replication target: voters and learners
-    pub replication: HashMap<NodeId, ReplicationMetrics>,
+    pub replication: HashMap<NID, ReplicationMetrics>,
+
+    /// The learners no more than `Config::replication_lag_threshold` logs behind the leader, i.e., ready to be
+    /// promoted to voters.
+    pub ready_learners: BTreeSet<NID>,
+
+    /// The health of every replication target, rolled up from its replication metrics.
+    pub peer_health: BTreeMap<NID, PeerHealth>,
+
+    /// Whether a quorum has replicated logs beyond the committed one, which can not be committed because none of
+    /// them is of the current term.
+    ///
+    /// A log of a previous term replicated to a quorum may still be overridden by a later leader, thus a leader
+    /// commits it only indirectly, by committing a log of its own term after it. E.g., right after an election
+    /// nothing is committed until the initial blank log of the leader is replicated to a quorum.
+    pub commit_blocked_by_term: bool,
 }
 
-impl MessageSummary for LeaderMetrics {
+impl<NID: RaftNodeId> Default for LeaderMetrics<NID> {
+    fn default() -> Self {
+        Self {
+            replication: HashMap::new(),
+            ready_learners: BTreeSet::new(),
+            peer_health: BTreeMap::new(),
+            commit_blocked_by_term: false,
+        }
+    }
+}
+
+/// The voters and the learners of the cluster, as a Raft node knows them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MembershipMetrics<NID: RaftNodeId = NodeId> {
+    voters: BTreeSet<NID>,
+    learners: BTreeSet<NID>,
+}
+
+impl<NID: RaftNodeId> MembershipMetrics<NID> {
+    /// Returns the voters, i.e., the nodes in any of the configs of the membership.
+    pub fn voters(&self) -> &BTreeSet<NID> {
+        &self.voters
+    }
+
+    /// Returns the learners the leader replicates logs to, which do not vote.
+    pub fn learners(&self) -> &BTreeSet<NID> {
+        &self.learners
+    }
+}
+
+/// The health of a replication target as seen by the leader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerHealth {
+    /// The target accepts RPCs and is no more than `Config::replication_lag_threshold` logs behind the leader.
+    Healthy,
+
+    /// The target accepts RPCs but is more than `Config::replication_lag_threshold` logs behind the leader.
+    Lagging,
+
+    /// More than `Config::unreachable_rpc_failures` RPCs to the target failed in a row.
+    Unreachable,
+
+    /// The leader is sending, or waiting to send, a snapshot to the target.
+    Snapshotting,
+}
+
+impl PeerHealth {
+    /// Classify a target by its replication metrics.
+    ///
+    /// Being unreachable takes precedence: a target that can not be reached is neither catching up nor receiving a
+    /// snapshot.
+    pub(crate) fn of(metrics: &ReplicationMetrics, unreachable_rpc_failures: u64) -> Self {
+        if metrics.consecutive_failures > unreachable_rpc_failures {
+            return PeerHealth::Unreachable;
+        }
+
+        match metrics.state {
+            ReplicationStatus::LineRate => PeerHealth::Healthy,
+            ReplicationStatus::Lagging => PeerHealth::Lagging,
+            ReplicationStatus::SnapshotQueued | ReplicationStatus::Snapshotting => PeerHealth::Snapshotting,
+        }
+    }
+}
+
+impl<NID: RaftNodeId> MessageSummary for LeaderMetrics<NID> {
     fn summary(&self) -> String {
         let mut res = vec!["LeaderMetrics{".to_string()];
         for (i, (k, v)) in self.replication.iter().enumerate() {
@@ -84,27 +222,55 @@ impl MessageSummary for LeaderMetrics {
             }
             res.push(format!("{}:{}", k, v.summary()));
         }
+        res.push(format!(", ready_learners:{:?}", self.ready_learners));
+        res.push(format!(", peer_health:{:?}", self.peer_health));
+        res.push(format!(", commit_blocked_by_term:{}", self.commit_blocked_by_term));
 
         res.push("}".to_string());
         res.join("")
     }
 }
 
-impl RaftMetrics {
-    pub(crate) fn new_initial(id: NodeId) -> Self {
+impl<NID: RaftNodeId> RaftMetrics<NID> {
+    /// Returns the voters and the learners of the current membership.
+    ///
+    /// Only a leader knows its learners, the replication targets not in the membership config: on any other node the
+    /// learners are empty.
+    pub fn membership(&self) -> MembershipMetrics<NID> {
+        let membership = &self.membership_config.membership;
+
+        let learners = match &self.leader_metrics {
+            Some(lm) => lm.replication.keys().filter(|id| !membership.contains(id)).copied().collect(),
+            None => BTreeSet::new(),
+        };
+
+        MembershipMetrics {
+            voters: membership.voters().clone(),
+            learners,
+        }
+    }
+
+    pub(crate) fn new_initial(id: NID) -> Self {
         let membership_config = Membership::new_initial(id);
         Self {
             id,
             state: State::Follower,
             current_term: 0,
+            max_term_seen: 0,
             last_log_index: 0,
             last_applied: 0,
+            last_committed: None,
             current_leader: None,
+            leader_ready: false,
             membership_config: EffectiveMembership {
                 log_id: LogId::default(),
                 membership: membership_config,
             },
             snapshot: LogId { term: 0, index: 0 },
+            snapshot_building: false,
+            installing_snapshot_progress: None,
+            millis_since_last_heartbeat: None,
+            conflicts_reported: 0,
             leader_metrics: None,
         }
     }
@@ -121,16 +287,16 @@ pub enum WaitError {
 }
 
 /// Wait is a wrapper of RaftMetrics channel that impls several utils to wait for metrics to satisfy some condition.
-pub struct Wait {
+pub struct Wait<NID: RaftNodeId = NodeId> {
     pub timeout: Duration,
-    pub rx: watch::Receiver<RaftMetrics>,
+    pub rx: watch::Receiver<RaftMetrics<NID>>,
 }
 
-impl Wait {
+impl<NID: RaftNodeId> Wait<NID> {
     /// Wait for metrics to satisfy some condition or timeout.
     #[tracing::instrument(level = "trace", skip(self, func), fields(msg=%msg.to_string()))]
-    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics, WaitError>
-    where T: Fn(&RaftMetrics) -> bool + Send {
+    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError>
+    where T: Fn(&RaftMetrics<NID>) -> bool + Send {
         let timeout_at = Instant::now() + self.timeout;
 
         let mut rx = self.rx.clone();
@@ -194,7 +360,7 @@ impl Wait {
 
     /// Wait for `current_leader` to become `Some(leader_id)` until timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn current_leader(&self, leader_id: NodeId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn current_leader(&self, leader_id: NID, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.current_leader == Some(leader_id),
             &format!("{} .current_leader -> {}", msg.to_string(), leader_id),
@@ -204,7 +370,7 @@ impl Wait {
 
     /// Wait until applied upto `want_log`(inclusive) logs or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn log(&self, want_log: u64, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn log(&self, want_log: u64, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.last_log_index == want_log,
             &format!("{} .last_log_index -> {}", msg.to_string(), want_log),
@@ -218,9 +384,22 @@ impl Wait {
         .await
     }
 
+    /// Wait until applied at least upto `want_applied`(inclusive) logs or timeout.
+    ///
+    /// Unlike `log()`, it does not require the last log index to be exactly `want_applied`, and it is satisfied by
+    /// any greater applied index.
+    #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
+    pub async fn applied_index(&self, want_applied: u64, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
+        self.metrics(
+            |x| x.last_applied >= want_applied,
+            &format!("{} .last_applied >= {}", msg.to_string(), want_applied),
+        )
+        .await
+    }
+
     /// Wait for `state` to become `want_state` or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.state == want_state,
             &format!("{} .state -> {:?}", msg.to_string(), want_state),
@@ -230,7 +409,11 @@ impl Wait {
 
     /// Wait for `membership_config.members` to become expected node set or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn members(&self, want_members: BTreeSet<NodeId>, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn members(
+        &self,
+        want_members: BTreeSet<NID>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.membership_config.membership.get_ith_config(0).cloned().unwrap() == want_members,
             &format!("{} .membership_config.members -> {:?}", msg.to_string(), want_members),
@@ -242,9 +425,9 @@ impl Wait {
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
     pub async fn next_members(
         &self,
-        want_members: Option<BTreeSet<NodeId>>,
+        want_members: Option<BTreeSet<NID>>,
         msg: impl ToString,
-    ) -> Result<RaftMetrics, WaitError> {
+    ) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.membership_config.membership.get_ith_config(1) == want_members.as_ref(),
             &format!("{} .membership_config.next -> {:?}", msg.to_string(), want_members),
@@ -254,7 +437,7 @@ impl Wait {
 
     /// Wait for `snapshot` to become `want_snapshot` or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn snapshot(&self, want_snapshot: LogId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn snapshot(&self, want_snapshot: LogId, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.snapshot == want_snapshot,
             &format!("{} .snapshot -> {:?}", msg.to_string(), want_snapshot),
@@ -262,3 +445,154 @@ impl Wait {
         .await
     }
 }
+
+/// The type of predicate used by the convenience constructors of `MetricsChanges`.
+pub type MetricsChangedFn<NID = NodeId> = fn(&RaftMetrics<NID>, &RaftMetrics<NID>) -> bool;
+
+/// MetricsChanges is a wrapper of RaftMetrics channel that only yields the metrics when they changed in a way the
+/// caller is interested in.
+///
+/// `changed(prev, latest)` is evaluated on every two successive metrics observed.
+/// Just like a `watch` channel, multiple updates that happen before the next poll are coalesced:
+/// only the latest one is observed.
+///
+/// ```ignore
+/// let mut leader_changes = MetricsChanges::on_leader_change(raft.metrics());
+///
+/// while let Some(m) = leader_changes.next().await {
+///     println!("leader changed to {:?}", m.current_leader);
+/// }
+/// ```
+pub struct MetricsChanges<F, NID = NodeId>
+where
+    F: Fn(&RaftMetrics<NID>, &RaftMetrics<NID>) -> bool,
+    NID: RaftNodeId,
+{
+    rx: watch::Receiver<RaftMetrics<NID>>,
+    prev: RaftMetrics<NID>,
+    changed: F,
+}
+
+impl<F, NID> MetricsChanges<F, NID>
+where
+    F: Fn(&RaftMetrics<NID>, &RaftMetrics<NID>) -> bool,
+    NID: RaftNodeId,
+{
+    pub fn new(rx: watch::Receiver<RaftMetrics<NID>>, changed: F) -> Self {
+        let prev = rx.borrow().clone();
+        Self { rx, prev, changed }
+    }
+
+    /// Wait for the next metrics for which `changed(prev, latest)` returns true.
+    ///
+    /// It returns `None` if the Raft node is shut down.
+    pub async fn next(&mut self) -> Option<RaftMetrics<NID>> {
+        loop {
+            self.rx.changed().await.ok()?;
+
+            let latest = self.rx.borrow().clone();
+            let prev = std::mem::replace(&mut self.prev, latest.clone());
+
+            if (self.changed)(&prev, &latest) {
+                tracing::debug!("id={} metrics changed: {}", latest.id, latest.summary());
+                return Some(latest);
+            }
+        }
+    }
+
+    /// Convert it into a `Stream` of metrics for which `changed(prev, latest)` returns true.
+    pub fn into_stream(self) -> impl Stream<Item = RaftMetrics<NID>> {
+        futures::stream::unfold(self, |mut changes| async move {
+            let m = changes.next().await?;
+            Some((m, changes))
+        })
+    }
+}
+
+impl<NID: RaftNodeId> MetricsChanges<MetricsChangedFn<NID>, NID> {
+    /// Yields the metrics when `current_leader` changes.
+    pub fn on_leader_change(rx: watch::Receiver<RaftMetrics<NID>>) -> Self {
+        Self::new(rx, |prev, latest| prev.current_leader != latest.current_leader)
+    }
+
+    /// Yields the metrics when `membership_config` changes.
+    pub fn on_membership_change(rx: watch::Receiver<RaftMetrics<NID>>) -> Self {
+        Self::new(rx, |prev, latest| prev.membership_config != latest.membership_config)
+    }
+
+    /// Yields the metrics when `state` changes.
+    pub fn on_state_change(rx: watch::Receiver<RaftMetrics<NID>>) -> Self {
+        Self::new(rx, |prev, latest| prev.state != latest.state)
+    }
+}
+
+/// MetricsEvents yields the lifecycle events that are also reflected in the metrics, `RaftEvent::LearnerReady` and
+/// `RaftEvent::ElectionsPaused`, from the channel of `Raft::events()`.
+///
+/// The learners already ready when it is created, according to the metrics, are yielded first.
+/// A learner that falls behind and catches up again is yielded again.
+///
+/// ```ignore
+/// let mut events = raft.metrics_events();
+///
+/// while let Some(ev) = events.next().await {
+///     if let RaftEvent::LearnerReady(id) = ev {
+///         raft.promote_learner(id).await?;
+///     }
+/// }
+/// ```
+pub struct MetricsEvents<NID: RaftNodeId = NodeId> {
+    rx_events: broadcast::Receiver<RaftEvent<NID>>,
+
+    /// The learners yielded from the metrics when it is created, whose event may be received again.
+    initially_ready: BTreeSet<NID>,
+
+    pending: VecDeque<RaftEvent<NID>>,
+}
+
+impl<NID: RaftNodeId> MetricsEvents<NID> {
+    /// Create it from a receiver of the lifecycle events subscribed before `latest` is read.
+    pub(crate) fn new(latest: &RaftMetrics<NID>, rx_events: broadcast::Receiver<RaftEvent<NID>>) -> Self {
+        let initially_ready = latest.leader_metrics.as_ref().map(|x| x.ready_learners.clone()).unwrap_or_default();
+        let pending = initially_ready.iter().map(|id| RaftEvent::LearnerReady(*id)).collect();
+
+        Self {
+            rx_events,
+            initially_ready,
+            pending,
+        }
+    }
+
+    /// Wait for the next event.
+    ///
+    /// It returns `None` if the Raft node is shut down.
+    pub async fn next(&mut self) -> Option<RaftEvent<NID>> {
+        loop {
+            if let Some(ev) = self.pending.pop_front() {
+                return Some(ev);
+            }
+
+            match self.rx_events.recv().await {
+                Ok(RaftEvent::LearnerReady(id)) => {
+                    if !self.initially_ready.remove(&id) {
+                        return Some(RaftEvent::LearnerReady(id));
+                    }
+                }
+                Ok(RaftEvent::ElectionsPaused(term)) => return Some(RaftEvent::ElectionsPaused(term)),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(n, "metrics events lagged, some events are missed");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Convert it into a `Stream` of events.
+    pub fn into_stream(self) -> impl Stream<Item = RaftEvent<NID>> {
+        futures::stream::unfold(self, |mut events| async move {
+            let ev = events.next().await?;
+            Some((ev, events))
+        })
+    }
+}
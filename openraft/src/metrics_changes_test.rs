@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use maplit::btreeset;
+use tokio::sync::watch;
+use tokio::time::timeout;
+
+use crate::core::EffectiveMembership;
+use crate::metrics::MetricsChanges;
+use crate::raft::Membership;
+use crate::LogId;
+use crate::RaftMetrics;
+use crate::State;
+
+/// Only the updates satisfying the predicate are yielded.
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_metrics_changes_filter() -> anyhow::Result<()> {
+    let (init, rx, tx) = init_metrics_changes_test();
+    let mut changes = MetricsChanges::on_leader_change(rx);
+
+    let mut m = init;
+
+    // Not a leader change
+    m.last_log_index = 1;
+    tx.send(m.clone())?;
+    assert!(try_next(&mut changes).await.is_none(), "no leader change");
+
+    m.current_leader = Some(1);
+    tx.send(m.clone())?;
+    let got = try_next(&mut changes).await.unwrap();
+    assert_eq!(Some(1), got.current_leader);
+
+    // Not a leader change
+    m.last_applied = 1;
+    tx.send(m.clone())?;
+    assert!(try_next(&mut changes).await.is_none(), "no leader change");
+
+    m.current_leader = None;
+    tx.send(m.clone())?;
+    let got = try_next(&mut changes).await.unwrap();
+    assert_eq!(None, got.current_leader);
+    assert_eq!(1, got.last_applied);
+
+    Ok(())
+}
+
+/// Multiple updates before polling are coalesced into the latest one.
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_metrics_changes_coalesce() -> anyhow::Result<()> {
+    let (init, rx, tx) = init_metrics_changes_test();
+    let mut changes = MetricsChanges::on_state_change(rx);
+
+    let mut m = init;
+    for (i, state) in [State::Follower, State::Candidate, State::Leader].into_iter().enumerate() {
+        m.state = state;
+        m.current_term = i as u64 + 1;
+        tx.send(m.clone())?;
+    }
+
+    let got = try_next(&mut changes).await.unwrap();
+    assert_eq!(State::Leader, got.state);
+    assert_eq!(3, got.current_term);
+
+    assert!(try_next(&mut changes).await.is_none(), "coalesced into one");
+
+    // Changed and changed back before polling: the latest compared with the previous observed is not a change.
+    m.state = State::Follower;
+    tx.send(m.clone())?;
+    m.state = State::Leader;
+    tx.send(m.clone())?;
+    assert!(try_next(&mut changes).await.is_none(), "changed back");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_metrics_changes_membership_stream() -> anyhow::Result<()> {
+    let (init, rx, tx) = init_metrics_changes_test();
+    let changes = MetricsChanges::on_membership_change(rx);
+
+    let h = tokio::spawn(async move {
+        let mut m = init;
+        for i in 1..=3 {
+            m.membership_config = EffectiveMembership {
+                log_id: LogId { term: 1, index: i },
+                membership: Membership::new_single(btreeset! {1,2,i+2}),
+            };
+            tx.send(m.clone()).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            m.last_applied = i;
+            tx.send(m.clone()).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Dropping tx ends the stream.
+    });
+
+    let got = changes.into_stream().map(|m| m.membership_config.log_id.index).collect::<Vec<_>>().await;
+    h.await?;
+
+    assert_eq!(vec![1, 2, 3], got);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_metrics_changes_shutdown() -> anyhow::Result<()> {
+    let (_init, rx, tx) = init_metrics_changes_test();
+    let mut changes = MetricsChanges::new(rx, |_, _| true);
+
+    drop(tx);
+    assert!(changes.next().await.is_none());
+
+    Ok(())
+}
+
+/// Returns the next change, or None if there is no change in a short while.
+async fn try_next<F>(changes: &mut MetricsChanges<F>) -> Option<RaftMetrics>
+where F: Fn(&RaftMetrics, &RaftMetrics) -> bool {
+    timeout(Duration::from_millis(50), changes.next()).await.ok().flatten()
+}
+
+/// Build a initial state for testing of MetricsChanges:
+/// Returns init metrics, the rx to build a MetricsChanges, and the tx to send an updated metrics.
+fn init_metrics_changes_test() -> (RaftMetrics, watch::Receiver<RaftMetrics>, watch::Sender<RaftMetrics>) {
+    let init = RaftMetrics {
+        id: 0,
+        state: State::Learner,
+        current_term: 0,
+        max_term_seen: 0,
+        last_log_index: 0,
+        last_applied: 0,
+        last_committed: None,
+        current_leader: None,
+        leader_ready: false,
+        membership_config: EffectiveMembership {
+            log_id: LogId::default(),
+            membership: Membership::new_single(btreeset! {}),
+        },
+
+        snapshot: LogId { term: 0, index: 0 },
+        snapshot_building: false,
+        installing_snapshot_progress: None,
+        millis_since_last_heartbeat: None,
+        conflicts_reported: 0,
+        leader_metrics: None,
+    };
+    let (tx, rx) = watch::channel(init.clone());
+
+    (init, rx, tx)
+}
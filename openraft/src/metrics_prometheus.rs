@@ -0,0 +1,267 @@
+//! Render `RaftMetrics` in the Prometheus text exposition format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::core::State;
+use crate::metrics::RaftMetrics;
+use crate::RaftNodeId;
+use crate::ReplicationMetrics;
+
+/// Every state a node may be in, in the order they are rendered as the `state` label of `openraft_state`.
+const STATES: [(State, &str); 7] = [
+    (State::Uninitialized, "uninitialized"),
+    (State::Learner, "learner"),
+    (State::Follower, "follower"),
+    (State::Candidate, "candidate"),
+    (State::Leader, "leader"),
+    (State::Paused, "paused"),
+    (State::Shutdown, "shutdown"),
+];
+
+impl<NID: RaftNodeId> RaftMetrics<NID> {
+    /// Render the metrics in the Prometheus text exposition format.
+    ///
+    /// Every sample has the label `node`, set to `node_label`. The node-wide metrics are:
+    ///
+    /// - `openraft_current_term`, `openraft_max_term_seen`: gauges of the term.
+    /// - `openraft_state{state}`: 1 for the state the node is in, 0 for the others, with `state` being one of
+    ///   `uninitialized`, `learner`, `follower`, `candidate`, `leader`, `paused` or `shutdown`.
+    /// - `openraft_has_leader`, `openraft_leader_ready`, `openraft_snapshot_building`: 1 or 0.
+    /// - `openraft_last_log_index`, `openraft_last_committed_index`, `openraft_last_applied_index`,
+    ///   `openraft_snapshot_index`, `openraft_membership_log_index`: gauges of log indexes, 0 if there is none.
+    /// - `openraft_millis_since_last_heartbeat`: a gauge, only if the node has heard from a leader.
+    /// - `openraft_conflicts_reported_total`: a counter.
+    ///
+    /// On a leader, there are also the replication metrics of every target, with the label `target` set to its node
+    /// id:
+    ///
+    /// - `openraft_replication_matched_index`, `openraft_replication_lag`: gauges of the replication progress.
+    /// - `openraft_replication_last_rpc_latency_ms`: a gauge, only if an RPC to the target has succeeded.
+    /// - `openraft_replication_paused`: 1 or 0.
+    /// - `openraft_replication_consecutive_failures`: a gauge.
+    /// - `openraft_replication_log_conflicts_total`, `openraft_replication_term_rejections_total`: counters.
+    /// - `openraft_commit_blocked_by_term`: 1 or 0, without `target`.
+    pub fn to_prometheus(&self, node_label: &str) -> String {
+        let mut e = Exposition::new(node_label);
+
+        e.family("openraft_current_term", "gauge", "The current term of the node.");
+        e.sample("openraft_current_term", &[], self.current_term);
+
+        e.family(
+            "openraft_max_term_seen",
+            "gauge",
+            "The greatest term received from other nodes.",
+        );
+        e.sample("openraft_max_term_seen", &[], self.max_term_seen);
+
+        e.family("openraft_state", "gauge", "Whether the node is in the state.");
+        for (state, name) in STATES.iter() {
+            e.sample("openraft_state", &[("state", name)], (self.state == *state) as u64);
+        }
+
+        e.family(
+            "openraft_has_leader",
+            "gauge",
+            "Whether the node knows the current leader.",
+        );
+        e.sample("openraft_has_leader", &[], self.current_leader.is_some() as u64);
+
+        e.family(
+            "openraft_leader_ready",
+            "gauge",
+            "Whether the node is a leader that committed a log of its term.",
+        );
+        e.sample("openraft_leader_ready", &[], self.leader_ready as u64);
+
+        e.family(
+            "openraft_last_log_index",
+            "gauge",
+            "The index of the last log appended.",
+        );
+        e.sample("openraft_last_log_index", &[], self.last_log_index);
+
+        e.family(
+            "openraft_last_committed_index",
+            "gauge",
+            "The index of the last log known to be committed.",
+        );
+        e.sample(
+            "openraft_last_committed_index",
+            &[],
+            self.last_committed.map(|x| x.index).unwrap_or_default(),
+        );
+
+        e.family(
+            "openraft_last_applied_index",
+            "gauge",
+            "The index of the last log applied to the state machine.",
+        );
+        e.sample("openraft_last_applied_index", &[], self.last_applied);
+
+        e.family(
+            "openraft_membership_log_index",
+            "gauge",
+            "The index of the log of the membership config.",
+        );
+        e.sample(
+            "openraft_membership_log_index",
+            &[],
+            self.membership_config.log_id.index,
+        );
+
+        e.family(
+            "openraft_snapshot_index",
+            "gauge",
+            "The index of the last log included in the snapshot.",
+        );
+        e.sample("openraft_snapshot_index", &[], self.snapshot.index);
+
+        e.family(
+            "openraft_snapshot_building",
+            "gauge",
+            "Whether a snapshot is being built.",
+        );
+        e.sample("openraft_snapshot_building", &[], self.snapshot_building as u64);
+
+        if let Some(ms) = self.millis_since_last_heartbeat {
+            e.family(
+                "openraft_millis_since_last_heartbeat",
+                "gauge",
+                "Milliseconds since the last heartbeat.",
+            );
+            e.sample("openraft_millis_since_last_heartbeat", &[], ms);
+        }
+
+        e.family(
+            "openraft_conflicts_reported_total",
+            "counter",
+            "AppendEntries rejected for a log mismatch.",
+        );
+        e.sample("openraft_conflicts_reported_total", &[], self.conflicts_reported);
+
+        if let Some(leader_metrics) = &self.leader_metrics {
+            let targets: BTreeMap<NID, &ReplicationMetrics> =
+                leader_metrics.replication.iter().map(|(id, m)| (*id, m)).collect();
+
+            e.per_target(
+                &targets,
+                "openraft_replication_matched_index",
+                "gauge",
+                "The last log replicated.",
+                |m| Some(m.matched.index),
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_lag",
+                "gauge",
+                "Logs behind the leader.",
+                |m| Some(m.lag),
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_last_rpc_latency_ms",
+                "gauge",
+                "Last RPC latency.",
+                |m| m.last_rpc_latency_ms,
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_paused",
+                "gauge",
+                "Whether it is paused.",
+                |m| Some(m.paused as u64),
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_consecutive_failures",
+                "gauge",
+                "RPCs failed in a row.",
+                |m| Some(m.consecutive_failures),
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_log_conflicts_total",
+                "counter",
+                "Log mismatches.",
+                |m| Some(m.log_conflicts),
+            );
+            e.per_target(
+                &targets,
+                "openraft_replication_term_rejections_total",
+                "counter",
+                "Stale terms.",
+                |m| Some(m.term_rejections),
+            );
+
+            e.family(
+                "openraft_commit_blocked_by_term",
+                "gauge",
+                "Whether commit waits for a log of the term.",
+            );
+            e.sample(
+                "openraft_commit_blocked_by_term",
+                &[],
+                leader_metrics.commit_blocked_by_term as u64,
+            );
+        }
+
+        e.out
+    }
+}
+
+/// Builds the exposition text, adding the `node` label to every sample.
+struct Exposition {
+    node: String,
+    out: String,
+}
+
+impl Exposition {
+    fn new(node_label: &str) -> Self {
+        Self {
+            node: escape(node_label),
+            out: String::new(),
+        }
+    }
+
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: u64) {
+        let _ = write!(self.out, "{}{{node=\"{}\"", name, self.node);
+        for (k, v) in labels {
+            let _ = write!(self.out, ",{}=\"{}\"", k, escape(v));
+        }
+        let _ = writeln!(self.out, "}} {}", value);
+    }
+
+    /// Render a replication metric of every target that has a value for it, ordered by the target.
+    fn per_target<NID, F>(
+        &mut self,
+        targets: &BTreeMap<NID, &ReplicationMetrics>,
+        name: &str,
+        kind: &str,
+        help: &str,
+        get: F,
+    ) where
+        NID: RaftNodeId,
+        F: Fn(&ReplicationMetrics) -> Option<u64>,
+    {
+        let samples = targets.iter().filter_map(|(t, m)| get(m).map(|v| (t.to_string(), v))).collect::<Vec<_>>();
+        if samples.is_empty() {
+            return;
+        }
+
+        self.family(name, kind, help);
+        for (target, value) in samples {
+            self.sample(name, &[("target", &target)], value);
+        }
+    }
+}
+
+/// Escape a label value: backslash, double-quote and line feed.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use maplit::hashmap;
+
+use crate::metrics::LeaderMetrics;
+use crate::LogId;
+use crate::RaftMetrics;
+use crate::ReplicationMetrics;
+use crate::State;
+
+#[test]
+fn test_to_prometheus() -> anyhow::Result<()> {
+    let mut m = RaftMetrics::<u64>::new_initial(1);
+    m.state = State::Leader;
+    m.current_term = 5;
+    m.last_log_index = 10;
+    m.last_committed = Some(LogId { term: 5, index: 9 });
+    m.leader_metrics = Some(Arc::new(LeaderMetrics {
+        replication: hashmap! {
+            2 => ReplicationMetrics {
+                matched: LogId { term: 5, index: 7 },
+                lag: 3,
+                last_rpc_latency_ms: Some(4),
+                ..Default::default()
+            },
+            10 => ReplicationMetrics {
+                matched: LogId { term: 5, index: 10 },
+                paused: true,
+                ..Default::default()
+            },
+        },
+        ..Default::default()
+    }));
+
+    let text = m.to_prometheus("n1");
+
+    for name in [
+        "openraft_current_term",
+        "openraft_max_term_seen",
+        "openraft_state",
+        "openraft_has_leader",
+        "openraft_leader_ready",
+        "openraft_last_log_index",
+        "openraft_last_committed_index",
+        "openraft_last_applied_index",
+        "openraft_snapshot_index",
+        "openraft_membership_log_index",
+        "openraft_snapshot_building",
+        "openraft_conflicts_reported_total",
+        "openraft_replication_matched_index",
+        "openraft_replication_lag",
+        "openraft_replication_last_rpc_latency_ms",
+        "openraft_replication_paused",
+        "openraft_replication_consecutive_failures",
+        "openraft_replication_log_conflicts_total",
+        "openraft_replication_term_rejections_total",
+        "openraft_commit_blocked_by_term",
+    ] {
+        assert!(
+            text.contains(&format!("# TYPE {} ", name)),
+            "{} is rendered: {}",
+            name,
+            text
+        );
+    }
+
+    let lines = text.lines().collect::<Vec<_>>();
+
+    assert!(lines.contains(&"openraft_current_term{node=\"n1\"} 5"));
+    assert!(lines.contains(&"openraft_last_committed_index{node=\"n1\"} 9"));
+    assert!(lines.contains(&"openraft_state{node=\"n1\",state=\"leader\"} 1"));
+    assert!(lines.contains(&"openraft_state{node=\"n1\",state=\"follower\"} 0"));
+
+    assert!(lines.contains(&"openraft_replication_lag{node=\"n1\",target=\"2\"} 3"));
+    assert!(lines.contains(&"openraft_replication_matched_index{node=\"n1\",target=\"10\"} 10"));
+    assert!(lines.contains(&"openraft_replication_paused{node=\"n1\",target=\"10\"} 1"));
+
+    // Only a target with a successful RPC has a latency.
+    assert!(lines.contains(&"openraft_replication_last_rpc_latency_ms{node=\"n1\",target=\"2\"} 4"));
+    assert!(!text.contains("openraft_replication_last_rpc_latency_ms{node=\"n1\",target=\"10\"}"));
+
+    // No heartbeat is received by a leader.
+    assert!(!text.contains("openraft_millis_since_last_heartbeat"));
+
+    // Targets are ordered by node id, not by the rendered label.
+    let p2 = text.find("openraft_replication_lag{node=\"n1\",target=\"2\"}").unwrap();
+    let p10 = text.find("openraft_replication_lag{node=\"n1\",target=\"10\"}").unwrap();
+    assert!(p2 < p10);
+
+    Ok(())
+}
+
+#[test]
+fn test_to_prometheus_escape_label() -> anyhow::Result<()> {
+    let m = RaftMetrics::<u64>::new_initial(1);
+
+    let text = m.to_prometheus("a\"b\\c");
+    assert!(text.contains("openraft_current_term{node=\"a\\\"b\\\\c\"} 0"));
+
+    // A node that is not a leader has no replication metrics.
+    assert!(!text.contains("openraft_replication_"));
+
+    Ok(())
+}
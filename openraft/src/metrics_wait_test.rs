@@ -149,6 +149,53 @@ async fn test_wait() -> anyhow::Result<()> {
         }
     }
 
+    {
+        // wait for applied index, a greater one also satisfies
+        let (init, w, tx) = init_wait_test();
+
+        let h = tokio::spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            let mut update = init.clone();
+            update.last_log_index = 5;
+            update.last_applied = 5;
+            let rst = tx.send(update);
+            assert!(rst.is_ok());
+        });
+        let got = w.applied_index(3, "applied_index").await?;
+        h.await?;
+        assert_eq!(5, got.last_applied);
+    }
+
+    {
+        // already satisfied, returns at once
+        let (_init, w, _tx) = init_wait_test();
+
+        let now = tokio::time::Instant::now();
+        let got = w.applied_index(0, "applied_index already satisfied").await?;
+        assert_eq!(0, got.last_applied);
+        assert!(now.elapsed() < Duration::from_millis(50));
+
+        let got = w.state(State::Learner, "state already satisfied").await?;
+        assert_eq!(State::Learner, got.state);
+        assert!(now.elapsed() < Duration::from_millis(50));
+    }
+
+    {
+        // timeout waiting for applied index
+        let (_init, w, _tx) = init_wait_test();
+
+        let got = w.applied_index(1, "applied_index timeout").await;
+
+        match got.unwrap_err() {
+            WaitError::Timeout(t, _) => {
+                assert_eq!(Duration::from_millis(100), t);
+            }
+            _ => {
+                panic!("expect WaitError::Timeout");
+            }
+        }
+    }
+
     {
         // timeout
         let (_init, w, _tx) = init_wait_test();
@@ -179,15 +226,22 @@ fn init_wait_test() -> (RaftMetrics, Wait, watch::Sender<RaftMetrics>) {
         id: 0,
         state: State::Learner,
         current_term: 0,
+        max_term_seen: 0,
         last_log_index: 0,
         last_applied: 0,
+        last_committed: None,
         current_leader: None,
+        leader_ready: false,
         membership_config: EffectiveMembership {
             log_id: LogId::default(),
             membership: Membership::new_single(btreeset! {}),
         },
 
         snapshot: LogId { term: 0, index: 0 },
+        snapshot_building: false,
+        installing_snapshot_progress: None,
+        millis_since_last_heartbeat: None,
+        conflicts_reported: 0,
         leader_metrics: None,
     };
     let (tx, rx) = watch::channel(init.clone());
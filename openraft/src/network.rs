@@ -1,35 +1,135 @@
 //! The Raft network interface.
 
+use std::collections::BTreeSet;
+
+use anyhow::anyhow;
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::AppData;
 use crate::NodeId;
+use crate::RaftNodeId;
+
+/// The optional RPCs a Raft node supports.
+///
+/// In a cluster of nodes running different versions, e.g., during a rolling upgrade, a node only uses an optional RPC
+/// with a peer that supports it, and falls back to the legacy behavior otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct NodeCapabilities {
+    /// Whether the node handles a pre-vote `VoteRequest`, i.e., with `pre_vote` set.
+    ///
+    /// A candidate does not send a pre-vote to a node without it, and counts the node as granting the pre-vote, as if
+    /// there were no pre-vote phase.
+    pub pre_vote: bool,
+
+    /// Whether the node decompresses the entries of an AppendEntries, i.e., with `compressed_entries` set.
+    ///
+    /// A leader sends entries uncompressed to a node without it, no matter what `Config::replication_compression` is.
+    #[serde(default)]
+    pub compression: bool,
+}
+
+impl NodeCapabilities {
+    /// The capabilities of a node running this version, which supports every optional RPC.
+    pub fn current() -> Self {
+        Self {
+            pre_vote: true,
+            compression: true,
+        }
+    }
+
+    /// The capabilities of a legacy node, which supports none of the optional RPCs.
+    pub fn legacy() -> Self {
+        Self {
+            pre_vote: false,
+            compression: false,
+        }
+    }
+}
 
 /// A trait defining the interface for a Raft network between cluster members.
 ///
 /// See the [network chapter of the guide](https://datafuselabs.github.io/openraft/network.html)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftNetwork<D>: Send + Sync + 'static
-where D: AppData
+pub trait RaftNetwork<D, NID = NodeId>: Send + Sync + 'static
+where
+    D: AppData,
+    NID: RaftNodeId,
 {
     /// Send an AppendEntries RPC to the target Raft node (§5).
-    async fn send_append_entries(&self, target: NodeId, rpc: AppendEntriesRequest<D>) -> Result<AppendEntriesResponse>;
+    ///
+    /// If the target fails to decompress the entries with `RaftError::EntriesDecompression`, return that error, so
+    /// that the leader sends the entries to it uncompressed.
+    async fn send_append_entries(
+        &self,
+        target: NID,
+        rpc: AppendEntriesRequest<D, NID>,
+    ) -> Result<AppendEntriesResponse>;
 
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
+    ///
+    /// If the target rejects a corrupted snapshot with `RaftError::SnapshotChecksumMismatch`, return that error, so
+    /// that the leader sends the snapshot again from the beginning.
     async fn send_install_snapshot(
         &self,
-        target: NodeId,
-        rpc: InstallSnapshotRequest,
+        target: NID,
+        rpc: InstallSnapshotRequest<NID>,
     ) -> Result<InstallSnapshotResponse>;
 
     /// Send a RequestVote RPC to the target Raft node (§5).
-    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse>;
+    async fn send_vote(&self, target: NID, rpc: VoteRequest<NID>) -> Result<VoteResponse>;
+
+    /// Send a RequestVote RPC to every target, and return the responses in the order they arrive.
+    ///
+    /// A candidate calls it to request votes, or pre-votes, from all the other voters at once. A transport may override
+    /// it to send the requests over multiplexed connections, or in one batch.
+    ///
+    /// By default it calls `send_vote()` for every target concurrently.
+    fn broadcast_vote(
+        &self,
+        targets: BTreeSet<NID>,
+        rpc: VoteRequest<NID>,
+    ) -> BoxStream<'_, (NID, Result<VoteResponse>)> {
+        targets
+            .into_iter()
+            .map(|target| {
+                let rpc = rpc.clone();
+                async move { (target, self.send_vote(target, rpc).await) }
+            })
+            .collect::<FuturesUnordered<_>>()
+            .boxed()
+    }
+
+    /// Send a TimeoutNow RPC to the target Raft node, to transfer leadership to it.
+    ///
+    /// By default it fails, thus `Raft::transfer_leadership()` fails and the leader resumes accepting writes, and
+    /// `Raft::step_down()` leaves the next leader to an election.
+    async fn send_timeout_now(&self, target: NID, _rpc: TimeoutNowRequest<NID>) -> Result<TimeoutNowResponse> {
+        Err(anyhow!("TimeoutNow to node {} is not supported", target))
+    }
+
+    /// Get the optional RPCs the target Raft node supports.
+    ///
+    /// Raft caches the result of every target until it restarts, thus it is called about once for a target. If it
+    /// fails, Raft assumes the target supports every optional RPC, and asks again next time.
+    ///
+    /// By default it returns `NodeCapabilities::current()`, for a cluster in which every node runs this version.
+    async fn capabilities(&self, _target: NID) -> Result<NodeCapabilities> {
+        Ok(NodeCapabilities::current())
+    }
 }
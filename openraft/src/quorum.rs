@@ -1,3 +0,0 @@
-pub fn majority_of(n: usize) -> usize {
-    n / 2 + 1
-}
@@ -3,44 +3,73 @@
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::mem;
 use std::sync::Arc;
 use std::time::Duration;
 
 use maplit::btreeset;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tracing::Span;
 
+use crate::clock::Clock;
+use crate::clock::TokioClock;
+use crate::compression;
+use crate::config::Compression;
 use crate::config::Config;
+use crate::config::ConfigDelta;
+use crate::core::CoreChannels;
 use crate::core::RaftCore;
+use crate::core::State;
 use crate::error::AddLearnerError;
+use crate::error::AppliedError;
+use crate::error::ChangeMembershipError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::InitializeError;
+use crate::error::LogReadError;
+use crate::error::PauseReplicationError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::RebuildStateMachineError;
+use crate::error::TransferLeadershipError;
+use crate::error::TriggerSnapshotError;
+use crate::error::UpdateConfigError;
+use crate::event::EventTx;
+use crate::event::RaftEvent;
+use crate::event::EVENT_CHANNEL_CAPACITY;
+use crate::metrics::MembershipMetrics;
+use crate::metrics::MetricsChanges;
+use crate::metrics::MetricsEvents;
 use crate::metrics::RaftMetrics;
 use crate::metrics::Wait;
-use crate::quorum;
+use crate::storage::HardState;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::NodeId;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::SnapshotMeta;
 
-struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    tx_api: mpsc::UnboundedSender<(RaftMsg<D, R>, Span)>,
-    rx_metrics: watch::Receiver<RaftMetrics>,
+struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId> {
+    tx_api: mpsc::UnboundedSender<(RaftMsg<D, R, NID>, Span)>,
+    rx_metrics: watch::Receiver<RaftMetrics<NID>>,
+    rx_leadership: watch::Receiver<Option<u64>>,
+    rx_config: watch::Receiver<Arc<Config>>,
+    /// Subscribes to the lifecycle events. It is dropped on shutdown, to close the channel.
+    tx_events: std::sync::Mutex<Option<EventTx<NID>>>,
     raft_handle: Mutex<Option<JoinHandle<RaftResult<()>>>>,
-    tx_shutdown: Mutex<Option<oneshot::Sender<()>>>,
+    tx_shutdown: Mutex<Option<oneshot::Sender<bool>>>,
     marker_n: std::marker::PhantomData<N>,
     marker_s: std::marker::PhantomData<S>,
 }
@@ -66,11 +95,19 @@ struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStora
 /// is shutting down (potentially for data safety reasons due to a storage error), and the `shutdown`
 /// method should be called on this type to await the shutdown of the node. If the parent
 /// application needs to shutdown the Raft node for any reason, calling `shutdown` will do the trick.
-pub struct Raft<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    inner: Arc<RaftInner<D, R, N, S>>,
+pub struct Raft<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId = NodeId,
+> {
+    inner: Arc<RaftInner<D, R, N, S, NID>>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Raft<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    Raft<D, R, N, S, NID>
+{
     /// Create and spawn a new Raft task.
     ///
     /// ### `id`
@@ -90,14 +127,37 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// An implementation of the `RaftStorage` trait which will be used by Raft for data storage.
     /// See the docs on the `RaftStorage` trait for more details.
     #[tracing::instrument(level="debug", skip(config, network, storage), fields(cluster=%config.cluster_name))]
-    pub fn new(id: NodeId, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+    pub fn new(id: NID, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+        Self::with_clock(id, config, network, storage, Arc::new(TokioClock))
+    }
+
+    /// Create and spawn a new Raft task that reads time from `clock`.
+    ///
+    /// It is the same as `Raft::new()`, except that election timeouts, heartbeats and other timers are driven by
+    /// `clock` instead of the real time. It is mainly used by tests to simulate time deterministically.
+    #[tracing::instrument(level="debug", skip(config, network, storage, clock), fields(cluster=%config.cluster_name))]
+    pub fn with_clock(id: NID, config: Arc<Config>, network: Arc<N>, storage: Arc<S>, clock: Arc<dyn Clock>) -> Self {
         let (tx_api, rx_api) = mpsc::unbounded_channel();
         let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id));
+        let (tx_leadership, rx_leadership) = watch::channel(None);
+        let (tx_config, rx_config) = watch::channel(config.clone());
+        let (tx_events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         let (tx_shutdown, rx_shutdown) = oneshot::channel();
-        let raft_handle = RaftCore::spawn(id, config, network, storage, rx_api, tx_metrics, rx_shutdown);
+        let channels = CoreChannels {
+            rx_api,
+            tx_metrics,
+            tx_leadership,
+            tx_config,
+            tx_events: tx_events.clone(),
+            rx_shutdown,
+        };
+        let raft_handle = RaftCore::spawn(id, config, network, storage, clock, channels);
         let inner = RaftInner {
             tx_api,
             rx_metrics,
+            rx_leadership,
+            rx_config,
+            tx_events: std::sync::Mutex::new(Some(tx_events)),
             raft_handle: Mutex::new(Some(raft_handle)),
             tx_shutdown: Mutex::new(Some(tx_shutdown)),
             marker_n: std::marker::PhantomData,
@@ -110,8 +170,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// These RPCs are sent by the cluster leader to replicate log entries (§5.3), and are also
     /// used as heartbeats (§5.2).
+    ///
+    /// Compressed entries are decompressed first, it fails with `RaftError::EntriesDecompression` if they can not be,
+    /// or if they are larger than `Config::max_payload_bytes` once decompressed.
     #[tracing::instrument(level = "trace", skip(self, rpc), fields(rpc=%rpc.summary()))]
-    pub async fn append_entries(&self, rpc: AppendEntriesRequest<D>) -> Result<AppendEntriesResponse, RaftError> {
+    pub async fn append_entries(
+        &self,
+        mut rpc: AppendEntriesRequest<D, NID>,
+    ) -> Result<AppendEntriesResponse, RaftError> {
+        if let Some(compressed) = rpc.compressed_entries.take() {
+            let max_bytes = self.inner.rx_config.borrow().max_payload_bytes;
+            rpc.entries = compression::decompress(&compressed, max_bytes).map_err(RaftError::EntriesDecompression)?;
+        }
+
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::AppendEntries { rpc, tx }, rx).await
     }
@@ -120,17 +191,30 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// These RPCs are sent by cluster peers which are in candidate state attempting to gather votes (§5.2).
     #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
-    pub async fn vote(&self, rpc: VoteRequest) -> Result<VoteResponse, RaftError> {
+    pub async fn vote(&self, rpc: VoteRequest<NID>) -> Result<VoteResponse, RaftError> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::RequestVote { rpc, tx }, rx).await
     }
 
+    /// Submit a TimeoutNow RPC to this Raft node.
+    ///
+    /// These RPCs are sent by the leader to an up to date follower during a leadership transfer, to make it start an
+    /// election at once. See `Raft::transfer_leadership()`.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub async fn timeout_now(&self, rpc: TimeoutNowRequest<NID>) -> Result<TimeoutNowResponse, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TimeoutNow { rpc, tx }, rx).await
+    }
+
     /// Submit an InstallSnapshot RPC to this Raft node.
     ///
     /// These RPCs are sent by the cluster leader in order to bring a new node or a slow node up-to-speed
     /// with the leader (§7).
     #[tracing::instrument(level = "debug", skip(self, rpc), fields(snapshot_id=%rpc.meta.last_log_id))]
-    pub async fn install_snapshot(&self, rpc: InstallSnapshotRequest) -> Result<InstallSnapshotResponse, RaftError> {
+    pub async fn install_snapshot(
+        &self,
+        rpc: InstallSnapshotRequest<NID>,
+    ) -> Result<InstallSnapshotResponse, RaftError> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::InstallSnapshot { rpc, tx }, rx).await
     }
@@ -141,18 +225,180 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// up-to-date; however, the `client_read` method must still be used to guard against stale
     /// reads. This method is perfect for making decisions on where to route client requests.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn current_leader(&self) -> Option<NodeId> {
+    pub async fn current_leader(&self) -> Option<NID> {
         self.metrics().borrow().current_leader
     }
 
+    /// Get the current term of this Raft node.
+    ///
+    /// It is the term persisted in the hard state, read from the copy Raft keeps in memory, not from the storage.
+    /// E.g., it helps to diagnose a suspected split brain, or to fence off a stale leader in an external system.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn current_term(&self) -> Result<u64, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        let hs = self.call_core(RaftMsg::GetHardState { tx }, rx).await?;
+        Ok(hs.current_term)
+    }
+
+    /// Get the node this Raft node voted for in the current term, along with the term.
+    ///
+    /// Like [`Raft::current_term`], it is the vote persisted in the hard state, read from the copy in memory.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn current_vote(&self) -> Result<HardState<NID>, RaftError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::GetHardState { tx }, rx).await
+    }
+
     /// Check to ensure this node is still the cluster leader, in order to guard against stale reads (§8).
     ///
     /// The actual read operation itself is up to the application, this method just ensures that
     /// the read will not be stale.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn client_read(&self) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self) -> Result<(), ClientReadError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::ClientReadRequest { tx }, rx).await?;
+        Ok(())
+    }
+
+    /// Ensure a read on the state machine after this call returns is linearizable, without writing any log.
+    ///
+    /// This implements the read-index protocol, described in §6.4 of the Raft dissertation:
+    /// - The leader records its commit index as the read index.
+    /// - The leader confirms it is still the leader by exchanging heartbeats with a quorum.
+    /// - It waits until the local state machine has applied upto the read index.
+    ///
+    /// With `Config::read_strategy` set to `ReadStrategy::LeaseRead`, the leader skips the heartbeats while it holds a
+    /// lease, and falls back to the heartbeats once the lease expires.
+    ///
+    /// When it returns the read index, the state machine reflects every write committed before this call.
+    /// It fails with a `ForwardToLeader` error if this node is not the leader, carrying the leader id if known.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn ensure_linearizable(&self) -> Result<LogId, ClientReadError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        let read_log_id = self.call_core(RaftMsg::ClientReadRequest { tx }, rx).await?;
+
+        tracing::debug!(%read_log_id, "wait for state machine to apply upto read index");
+
+        let mut rx_metrics = self.inner.rx_metrics.clone();
+        loop {
+            if rx_metrics.borrow().last_applied >= read_log_id.index {
+                return Ok(read_log_id);
+            }
+
+            if rx_metrics.changed().await.is_err() {
+                return Err(ClientReadError::RaftError(RaftError::ShuttingDown));
+            }
+        }
+    }
+
+    /// How long this node, as the leader, can still serve reads without confirming its leadership.
+    ///
+    /// The lease starts when the latest AppendEntries accepted by a quorum was sent, and lasts for
+    /// `Config::election_timeout_min` less `Config::max_clock_drift`: no other node can be elected before it expires,
+    /// given the clocks of the nodes drift apart by no more than that. Every successful heartbeat round extends it. An
+    /// application serving reads on its own should confirm the leadership, e.g., by `ensure_linearizable()`, once
+    /// it drops to zero.
+    ///
+    /// It returns `Duration::ZERO` if the lease expired or the leader is transferring its leadership, and `None` if
+    /// this node is not the leader.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn leader_lease_remaining(&self) -> Option<Duration> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::ClientReadRequest { tx }, rx).await
+        self.call_core(RaftMsg::LeaderLeaseRemaining { tx }, rx).await.unwrap_or_default()
+    }
+
+    /// When this node, as the leader, last received a response from `target` accepting it as the leader, e.g., to
+    /// feed an external failure detector.
+    ///
+    /// A response counts even if the logs of the target do not match, since the target is alive and reachable. Unlike
+    /// `PeerHealth`, which only tells if a target is unreachable after some failed RPCs in a row, it tells how long
+    /// ago exactly the target was last heard from. The time is of the `Clock` of this node.
+    ///
+    /// It returns `None` if this node is not the leader, if the leader does not replicate to `target`, including the
+    /// leader itself, or if no response has been received from it yet.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn last_contact(&self, target: NID) -> Option<Instant> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::LastContact { target, tx }, rx).await.unwrap_or_default()
+    }
+
+    /// Read from the local state machine, if it is no staler than `max_staleness`, without contacting the leader.
+    ///
+    /// On a follower or learner, it returns the last applied log id only if an AppendEntries from the leader has
+    /// brought the state machine up to the leader's commit index within `max_staleness`. The state machine then
+    /// reflects every write committed earlier than `max_staleness` ago. On the leader it succeeds only if the leader
+    /// holds a lease or a quorum has confirmed its leadership within `max_staleness`, thus a deposed leader does not
+    /// serve a stale read.
+    ///
+    /// Otherwise it fails with a `Stale` error carrying the leader id if known, the read should be sent to the leader.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn read_local(&self, max_staleness: Duration) -> Result<LogId, ClientReadError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::ReadLocal { max_staleness, tx }, rx).await
+    }
+
+    /// Check if the entry of `log_id` has been applied to the local state machine.
+    ///
+    /// It returns `false` if the entry is not yet applied, e.g., not yet committed or not yet replicated to this node.
+    /// It fails with a `NotInLog` error if the entry at the index of `log_id` is of another term: the entry has been
+    /// truncated by a new leader and will never be applied.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn is_applied(&self, log_id: LogId) -> Result<bool, AppliedError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::CheckApplied { log_id, tx }, rx).await
+    }
+
+    /// Read the logs in the range `[start, end)` from the local log store, e.g., for a debugging tool or to replicate
+    /// the log to an external system.
+    ///
+    /// By default only the applied logs can be read: an unapplied log may not be committed and may be replaced by a
+    /// new leader. Set `read_unapplied` to read any log in the log store.
+    ///
+    /// It fails with a `Purged` error if some of the logs are purged, and with a `NotApplied` or `BeyondLastLog`
+    /// error if the range goes beyond the logs that can be read.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_log_entries(
+        &self,
+        start: u64,
+        end: u64,
+        read_unapplied: bool,
+    ) -> Result<Vec<Entry<D, NID>>, LogReadError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Wait until the entry of `log_id` is applied to the local state machine, or fail after `timeout`.
+    ///
+    /// It fails with a `NotInLog` error as soon as the entry is found truncated, see [`Raft::is_applied`].
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn wait_applied(&self, log_id: LogId, timeout: Duration) -> Result<(), AppliedError> {
+        let mut rx_metrics = self.inner.rx_metrics.clone();
+
+        let wait = async {
+            loop {
+                if self.is_applied(log_id).await? {
+                    return Ok(());
+                }
+
+                if rx_metrics.changed().await.is_err() {
+                    return Err(AppliedError::RaftError(RaftError::ShuttingDown));
+                }
+            }
+        };
+
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(res) => res,
+            Err(_) => Err(AppliedError::Timeout { log_id, timeout }),
+        }
     }
 
     /// Submit a mutating client request to Raft to update the state of the system (§5.1).
@@ -161,6 +407,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// application state machine. The result of applying the request to the state machine will
     /// be returned as the response from this method.
     ///
+    /// If this node is not the leader, it fails with `ClientWriteError::ForwardToLeader` carrying the id of the
+    /// leader to redirect to, or with `ClientWriteError::LeaderUnknown` if no leader is known yet, in which case the
+    /// client should retry later. A node that is not initialized and has not joined a cluster fails with
+    /// `ClientWriteError::Uninitialized`.
+    ///
+    /// If the leader already has `Config::max_uncommitted_entries` uncommitted entries, it fails at once with
+    /// `ClientWriteError::Overloaded` without appending the request. The client should slow down and retry later.
+    ///
+    /// If `Config::max_log_entry_size` is set and the serialized payload is larger, it fails at once with
+    /// `ClientWriteError::PayloadTooLarge` without appending the request.
+    ///
     /// Our goal for Raft is to implement linearizable semantics. If the leader crashes after committing
     /// a log entry but before responding to the client, the client may retry the command with a new
     /// leader, causing it to be executed a second time. As such, clients should assign unique serial
@@ -173,24 +430,83 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// These are application specific requirements, and must be implemented by the application which is
     /// being built on top of Raft.
     #[tracing::instrument(level = "debug", skip(self, rpc))]
-    pub async fn client_write(&self, rpc: ClientWriteRequest<D>) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+    pub async fn client_write(
+        &self,
+        rpc: ClientWriteRequest<D, NID>,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::ClientWriteRequest { rpc, tx }, rx).await
     }
 
+    /// Submit a batch of mutating client requests to Raft.
+    ///
+    /// It is the same as calling `client_write()` for every request in order, except that the entries are appended
+    /// to the log with one storage write and replicated together, instead of each waiting for its own round.
+    ///
+    /// It returns one result for every request, in the same order. Entries are committed in log order, thus the
+    /// requests that succeed are always a prefix of the batch, apart from the ones rejected for their size:
+    /// - If this node is not the leader, every request fails with the same error as `client_write()`.
+    /// - The requests beyond `Config::max_uncommitted_entries` fail with `ClientWriteError::Overloaded`.
+    /// - A request larger than `Config::max_log_entry_size` fails with `ClientWriteError::PayloadTooLarge`, without
+    ///   affecting the others.
+    /// - If the leadership is lost after the batch is appended, the entries that are not committed by then fail with
+    ///   `RaftError::ShuttingDown`. They may still be committed by the next leader, thus a client has to retry them
+    ///   with the same serial numbers to be idempotent.
+    #[tracing::instrument(level = "debug", skip(self, rpcs), fields(n=rpcs.len()))]
+    pub async fn client_write_batch(
+        &self,
+        rpcs: Vec<ClientWriteRequest<D, NID>>,
+    ) -> Vec<Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>>> {
+        if rpcs.is_empty() {
+            return vec![];
+        }
+
+        let mut reqs = Vec::with_capacity(rpcs.len());
+        let mut rxs = Vec::with_capacity(rpcs.len());
+        for rpc in rpcs {
+            let (tx, rx) = oneshot::channel();
+            reqs.push((rpc, tx));
+            rxs.push(rx);
+        }
+
+        let mes = RaftMsg::ClientWriteBatch { reqs };
+        let sum = mes.summary();
+
+        let send_res = self.inner.tx_api.send((mes, tracing::Span::current()));
+        if let Err(send_err) = send_res {
+            tracing::error!(%send_err, mes=%sum, "error send tx to RaftCore");
+            return rxs.iter().map(|_| Err(RaftError::ShuttingDown.into())).collect();
+        }
+
+        let mut results = Vec::with_capacity(rxs.len());
+        for rx in rxs {
+            let res = match rx.await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::error!(%e, mes=%sum, "error recv rx from RaftCore");
+                    Err(RaftError::ShuttingDown.into())
+                }
+            };
+            results.push(res);
+        }
+
+        results
+    }
+
     /// Initialize a pristine Raft node with the given config.
     ///
     /// This command should be called on pristine nodes — where the log index is 0 and the node is
     /// in Learner state — as if either of those constraints are false, it indicates that the
-    /// cluster is already formed and in motion. If `InitializeError::NotAllowed` is returned
-    /// from this function, it is safe to ignore, as it simply indicates that the cluster is
-    /// already up and running, which is ultimately the goal of this function.
+    /// cluster is already formed and in motion. If `InitializeError::AlreadyInitialized` is returned
+    /// from this function, the log is not empty, and if `InitializeError::NotAllowed` is returned, the
+    /// node is already in an election. Both are safe to ignore, as they simply indicate that the cluster
+    /// is already up and running, which is ultimately the goal of this function.
     ///
-    /// This command will work for single-node or multi-node cluster formation. This command
-    /// should be called with all discovered nodes which need to be part of cluster, and as such
-    /// it is recommended that applications be configured with an initial cluster formation delay
-    /// which will allow time for the initial members of the cluster to be discovered (by the
-    /// parent application) for this call.
+    /// This command will work for single-node or multi-node cluster formation. `members` is the full
+    /// initial voter set: a pre-agreed cluster of several nodes is brought up by one call on any of them,
+    /// without initializing a single node and then adding the others one by one. It is recommended that
+    /// applications be configured with an initial cluster formation delay which will allow time for the
+    /// initial members of the cluster to be discovered (by the parent application) for this call.
     ///
     /// If successful, this routine will set the given config as the active config, only in memory,
     /// and will start an election.
@@ -207,11 +523,57 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// free, and Raft guarantees that the first node to become the cluster leader will propagate
     /// only its own config.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn initialize(&self, members: BTreeSet<NodeId>) -> Result<(), InitializeError> {
+    pub async fn initialize(&self, members: BTreeSet<NID>) -> Result<(), InitializeError> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::Initialize { members, tx }, rx).await
     }
 
+    /// Update the config of this Raft node at runtime, without restarting it.
+    ///
+    /// The `delta` is applied to the current config and the result is validated as a whole.
+    /// If it is invalid, the current config is kept and a `ConfigError` is returned.
+    ///
+    /// The new config takes effect without dropping the current leadership:
+    /// - A leader sends heartbeats and payloads to every replication target with the new values at once.
+    /// - The election timer picks up the new values the next time it is reset, e.g., on receiving a heartbeat.
+    ///
+    /// The config is local to this node. To widen the heartbeat interval of a cluster, first widen the election
+    /// timeouts on every node, then widen the heartbeat interval.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn update_config(&self, delta: ConfigDelta) -> Result<(), UpdateConfigError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::UpdateConfig { delta, tx }, rx).await
+    }
+
+    /// Build a snapshot right away, regardless of `Config::snapshot_policy`.
+    ///
+    /// It returns the meta of the snapshot once it is built, e.g., to make sure the logs are compacted before a
+    /// maintenance window. It includes every log applied to the state machine when it is called.
+    ///
+    /// It returns `TriggerSnapshotError::InProgress` if a snapshot is already being built or installed, and
+    /// `TriggerSnapshotError::NothingToSnapshot` if no log is applied yet.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn trigger_snapshot(&self) -> Result<SnapshotMeta, TriggerSnapshotError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TriggerSnapshot { tx }, rx).await
+    }
+
+    /// Rebuild the state machine by resetting it to the current snapshot and re-applying the logs after it.
+    ///
+    /// It is meant for recovery, e.g., to regenerate the data of a state machine after a bug in it is fixed. It
+    /// re-applies every log that was applied before it is called, and returns the last of them. See
+    /// `RaftStorage::reset_state_machine()` for how the state machine is reset.
+    ///
+    /// It is only allowed on a follower or a learner, which applies logs only from within the Raft core and serves
+    /// no client write: a leader or a candidate rejects it with `RebuildStateMachineError::NotAllowed`. It returns
+    /// `RebuildStateMachineError::LogsPurged` without resetting anything if the logs after the snapshot are not all
+    /// in the log any more, and `RebuildStateMachineError::NoSnapshot` if the storage has nothing to reset to.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rebuild_state_machine(&self) -> Result<LogId, RebuildStateMachineError> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::RebuildStateMachine { tx }, rx).await
+    }
+
     /// Synchronize a new Raft node, optionally, blocking until up-to-speed (§6).
     ///
     /// - Add a node as learner into the cluster.
@@ -220,12 +582,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If blocking is true, this function blocks until the leader believes the logs on the new node is up to date,
     /// i.e., ready to join the cluster, as a voter, by calling `change_membership`.
     /// When finished, it returns the last log id on the new node, in a `RaftResponse::LogId`.
+    /// If the new node keeps falling further behind over several `Config::learner_catch_up_window`, or does not catch
+    /// up in `Config::learner_catch_up_timeout`, the leader stops replicating to it and returns
+    /// `AddLearnerError::LearnerCannotCatchUp`.
     ///
     /// If blocking is false, this function returns at once as successfully setting up the replication.
     ///
     /// If the node to add is already a voter or learner, it returns `RaftResponse::NoChange` at once.
-    #[tracing::instrument(level = "debug", skip(self, id), fields(target=id))]
-    pub async fn add_learner(&self, id: NodeId, blocking: bool) -> Result<AddLearnerResponse, AddLearnerError> {
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn add_learner(&self, id: NID, blocking: bool) -> Result<AddLearnerResponse, AddLearnerError<NID>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::AddLearner { id, blocking, tx }, rx).await
     }
@@ -239,21 +604,51 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// - It proposes a **joint** config.
     /// - When the **joint** config is committed, it proposes a uniform config.
     ///
-    /// If blocking is true, it blocks until every learner becomes up to date.
-    /// Otherwise it returns error `ChangeMembershipError::LearnerIsLagging` if there is a lagging learner.
+    /// If `blocking_catch_up` is true, a new voter is kept as a learner until it catches up with the leader, i.e.,
+    /// lagging no more than `Config::replication_lag_threshold` logs, and only then the **joint** config is proposed.
+    /// Thus a lagging new voter does not stall the quorum. If a new voter does not catch up within
+    /// `Config::membership_catch_up_timeout`, which is derived from `Config::install_snapshot_timeout` by default, it
+    /// returns `ChangeMembershipError::CatchUpTimeout` and the membership is not changed.
+    ///
+    /// Otherwise it returns error `ChangeMembershipError::LearnerIsLagging` at once if there is a lagging learner.
+    ///
+    /// Only one change can be in progress at a time: if the last membership config log is not committed yet, it
+    /// returns error `ChangeMembershipError::InProgress`.
     ///
     /// If it lost leadership or crashed before committing the second **uniform** config log, the cluster is left in the
     /// **joint** config.
+    ///
+    /// Every member has a vote weight of 1, see `change_membership_weighted()` to assign vote weights.
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn change_membership(
         &self,
-        members: BTreeSet<NodeId>,
-        blocking: bool,
-    ) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+        members: BTreeSet<NID>,
+        blocking_catch_up: bool,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+        let members = members.into_iter().map(|id| (id, 1)).collect();
+        self.change_membership_weighted(members, blocking_catch_up).await
+    }
+
+    /// Propose a cluster configuration change in which every member has a vote weight.
+    ///
+    /// It is the same as `change_membership()`, except that a quorum is a set of voters having more than half of the
+    /// total vote weight, instead of more than half of the voters, in both electing a leader and committing logs.
+    /// E.g., with weights `{1: 2, 2: 1, 3: 1}`, node 1 with any other node is a quorum, while nodes 2 and 3 are not.
+    ///
+    /// The weights are stored in the membership config log, thus every node counts the quorum the same way. It
+    /// returns `ChangeMembershipError::NoWeightedMajority` if the total weight is 0, or
+    /// `ChangeMembershipError::WeightOverflow` if it does not fit in a `u64`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn change_membership_weighted(
+        &self,
+        members: BTreeMap<NID, u64>,
+        blocking_catch_up: bool,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
         tracing::info!(?members, "change_membership: add every member as learner");
 
-        for id in members.iter() {
-            let res = self.add_learner(*id, blocking).await;
+        // Do not block here: RaftCore waits for the learners to catch up, until a deadline.
+        for id in members.keys() {
+            let res = self.add_learner(*id, false).await;
             let res_err = match res {
                 Ok(_) => {
                     continue;
@@ -269,16 +664,256 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 }
                 // TODO(xp): test add learner on non-leader
                 AddLearnerError::ForwardToLeader(forward_err) => {
-                    return Err(ClientWriteError::ForwardToLeader(forward_err))
+                    return Err(ClientWriteError::not_leader(forward_err.leader_id))
                 }
                 AddLearnerError::Exists(node_id) => {
                     tracing::info!(%node_id, "add learner: already exists");
                     continue;
                 }
+                AddLearnerError::LearnerCannotCatchUp { .. } => {
+                    unreachable!("a learner added without blocking is never aborted")
+                }
+            }
+        }
+
+        self.commit_membership(members, btreeset! {}, blocking_catch_up).await
+    }
+
+    /// Check if `change_membership(members, false)` would succeed right now, without proposing anything.
+    ///
+    /// It is meant for an orchestration tool to validate a change before acting. Besides the checks
+    /// `change_membership()` does, it returns:
+    /// - `ChangeMembershipError::LearnerNotFound` if a new voter is not replicated to, since no learner is added by
+    ///   this call, and `ChangeMembershipError::LearnerIsLagging` if a new voter is not caught up.
+    /// - `ChangeMembershipError::QuorumUnreachable` if the voters the leader can reach, i.e., not
+    ///   `PeerHealth::Unreachable`, do not form a quorum of the proposed **joint** config.
+    ///
+    /// The result only reflects the current replication state: it may change by the time the change is proposed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn can_change_membership(&self, members: BTreeSet<NID>) -> Result<(), ClientWriteError<NID>> {
+        let members = members.into_iter().map(|id| (id, 1)).collect();
+
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::CanChangeMembership { members, tx }, rx).await
+    }
+
+    /// Abort the membership change in progress, and keep the membership before it.
+    ///
+    /// E.g., when a change is stuck because a new voter can not be reached, an operator aborts it instead of waiting
+    /// forever. The pending `change_membership()` call then returns `ChangeMembershipError::Aborted`.
+    ///
+    /// Whether a change can be aborted depends on how far it has gone:
+    /// - Waiting for new voters to catch up, with `blocking_catch_up`: nothing is proposed yet, the change is dropped.
+    ///   It returns the log id of the current membership config.
+    /// - The **joint** config is proposed but not committed: a uniform config of the previous voters is proposed. It
+    ///   returns the log id of this config, when it is committed. Since a quorum of the joint config is also a quorum
+    ///   of the previous voters, it is committed without the unreachable new voters.
+    /// - The **joint** config is committed: the change is past the point of no return, it can only go on to the new
+    ///   config. It returns `ChangeMembershipError::JointCommitted`.
+    ///
+    /// It returns `ChangeMembershipError::NotInProgress` if there is no change to abort.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn abort_membership_change(&self) -> Result<LogId, ClientWriteError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::AbortMembershipChange { tx }, rx).await
+    }
+
+    /// Remove a voter that is gone for good at once, without joint consensus and without waiting for the current
+    /// membership config to be committed.
+    ///
+    /// **This is dangerous.** It is meant only for an operator recovering a cluster that can not commit anything,
+    /// e.g., a cluster of 2 voters one of which is permanently dead, where a normal `change_membership()` is stuck
+    /// because the quorum of the joint config can never be formed.
+    ///
+    /// The leader appends a config with `id` removed from every config at once, and counts the quorum with it right
+    /// away, thus the logs replicated to the rest of the voters are committed. Since this bypasses joint consensus,
+    /// the cluster may elect two leaders, or lose committed logs, if the removed node is in fact alive, or comes back
+    /// with its storage: the caller must make sure it is shut down and its storage is destroyed, and must pass
+    /// `node_is_gone` as `true` to assert so. Otherwise it returns `ChangeMembershipError::ForceRemoveNotConfirmed`.
+    ///
+    /// `id` must be a voter other than this node, and this node must be a voter, otherwise it returns
+    /// `ChangeMembershipError::InvalidForceRemove`. It returns when the config without `id` is committed.
+    /// - On the leader, a membership change waiting for its learners to catch up is aborted.
+    /// - On a follower or a candidate, e.g., when the node gone is the leader, the membership is overridden locally,
+    ///   and this node starts an election with it at once. The config is appended to the log once this node is elected.
+    ///   If another node is elected instead, it returns `ClientWriteError::ForwardToLeader`, and the membership is
+    ///   restored from the log.
+    ///
+    /// A learner returns `ClientWriteError::ForwardToLeader`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn force_remove_node(
+        &self,
+        id: NID,
+        node_is_gone: bool,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+        if !node_is_gone {
+            return Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::ForceRemoveNotConfirmed { node_id: id },
+            ));
+        }
+
+        tracing::warn!("force_remove_node: {}", id);
+
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::ForceRemoveNode { id, tx }, rx).await
+    }
+
+    /// Promote a learner to a voter.
+    ///
+    /// The learner must have been added with `add_learner()`, and its replication must be up to date, i.e., lagging
+    /// no more than `Config::replication_lag_threshold` logs behind the leader. Otherwise it returns
+    /// `ChangeMembershipError::LearnerNotFound` or `ChangeMembershipError::LearnerIsLagging` at once, and the
+    /// membership is not changed.
+    ///
+    /// Like `change_membership`, the voter set is changed through a **joint** config, and it returns when the final
+    /// **uniform** config is committed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn promote_learner(&self, id: NID) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+        let metrics = self.metrics().borrow().clone();
+
+        if metrics.current_leader != Some(metrics.id) {
+            return Err(ClientWriteError::not_leader(metrics.current_leader));
+        }
+
+        // Keep the vote weights of the current voters.
+        let mut members = metrics.membership_config.membership.get_ith_weights(0).unwrap_or_default();
+        members.entry(id).or_insert(1);
+
+        tracing::info!(?members, "promote_learner: {}", id);
+
+        // Do not add it as learner: a node not being replicated to, or lagging, is rejected by RaftCore.
+        self.commit_membership(members, btreeset! {}, false).await
+    }
+
+    /// Add a node to the voters as a witness, e.g., as a cheap tiebreaker in the third zone of a cluster.
+    ///
+    /// A witness votes and counts toward a quorum like any other voter with a weight of 1, but the leader replicates
+    /// only the log ids and the membership configs to it, with the application data stripped. Thus it applies no
+    /// data to its state machine, and it is never elected: it does not start an election, and no voter grants it a
+    /// vote.
+    ///
+    /// Like `change_membership()`, it adds the node as a learner and changes the voter set through a **joint**
+    /// config. A node that is already a full voter can not become a witness, it returns
+    /// `ChangeMembershipError::InvalidWitness`. A witness stays a witness as long as it is a voter, and its storage
+    /// must be wiped before it joins again as a full voter, since its log has no application data.
+    ///
+    /// Note that an entry committed with the vote of a witness is stored only on the other voters of the quorum.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn add_witness(
+        &self,
+        id: NID,
+        blocking_catch_up: bool,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+        let metrics = self.metrics().borrow().clone();
+
+        if metrics.current_leader != Some(metrics.id) {
+            return Err(ClientWriteError::not_leader(metrics.current_leader));
+        }
+
+        match self.add_learner(id, false).await {
+            Ok(_) | Err(AddLearnerError::Exists(_)) => {}
+            Err(AddLearnerError::RaftError(raft_err)) => return Err(ClientWriteError::RaftError(raft_err)),
+            Err(AddLearnerError::ForwardToLeader(forward_err)) => {
+                return Err(ClientWriteError::not_leader(forward_err.leader_id))
+            }
+            Err(AddLearnerError::LearnerCannotCatchUp { .. }) => {
+                unreachable!("a learner added without blocking is never aborted")
             }
         }
 
-        tracing::info!("change_membership: start to commit joint config");
+        // Keep the vote weights of the current voters.
+        let mut members = metrics.membership_config.membership.get_ith_weights(0).unwrap_or_default();
+        members.entry(id).or_insert(1);
+
+        tracing::info!(?members, "add_witness: {}", id);
+
+        self.commit_membership(members, btreeset! {id}, blocking_catch_up).await
+    }
+
+    /// Transfer leadership to another voter, e.g., before restarting the leader.
+    ///
+    /// If `target` is `None`, the voter with the most up to date replication is chosen.
+    ///
+    /// Once the transfer starts, the leader stops accepting client writes and membership changes, and keeps
+    /// replicating until the target has every log of the leader. Then it sends a TimeoutNow to the target, which
+    /// starts an election at once, and returns the target.
+    /// Other voters grant the target a vote even if they have just heard from the current leader.
+    ///
+    /// If the target can not be caught up within `Config::election_timeout_max`, the transfer is abandoned and the
+    /// leader resumes accepting writes, and it returns `TransferLeadershipError::Timeout`.
+    ///
+    /// Returning successfully does not guarantee the target is elected, watch the metrics to see the new leader.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn transfer_leadership(&self, target: Option<NID>) -> Result<NID, TransferLeadershipError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TransferLeadership { target, tx }, rx).await
+    }
+
+    /// Relinquish leadership at once, e.g., to take the leader offline for maintenance, while this node stays in the
+    /// cluster.
+    ///
+    /// Unlike `transfer_leadership()`, it does not wait for any voter to catch up. If a voter already has every log of
+    /// the leader, a TimeoutNow is sent to it to start an election at once, and it returns that voter. Otherwise it
+    /// returns `None`, and the next leader is elected when an election timeout fires, which may elect this node again.
+    ///
+    /// Either way this node reverts to a follower in the current term: it stops accepting writes, and keeps
+    /// replicating logs from the next leader and serving local reads.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn step_down(&self) -> Result<Option<NID>, TransferLeadershipError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::StepDown { tx }, rx).await
+    }
+
+    /// Stop sending AppendEntries to `target`, e.g., to diagnose or throttle a misbehaving node, without removing it
+    /// from the membership.
+    ///
+    /// While paused, the target receives neither logs nor heartbeats, and it is reported as lagging in the metrics.
+    /// It is not removed, and a learner being added with `blocking` is not aborted for not catching up meanwhile.
+    /// A snapshot being sent to it is sent to the end first. A new leader replicates to every node again.
+    ///
+    /// Pausing a voter may elect another leader, once it does not hear from this one for an election timeout.
+    /// It fails with `PauseReplicationError::NotReplicated` if the leader does not replicate to `target`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn pause_replication(&self, target: NID) -> Result<(), PauseReplicationError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::PauseReplication {
+                target,
+                paused: true,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Start sending AppendEntries to `target` again, after it is paused with `pause_replication()`, or after it
+    /// rejected the format of a snapshot, see `ReplicationMetrics::snapshot_format_rejected`.
+    ///
+    /// Resuming a target that is not paused does nothing.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn resume_replication(&self, target: NID) -> Result<(), PauseReplicationError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(
+            RaftMsg::PauseReplication {
+                target,
+                paused: false,
+                tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Change the voter set to `members`, with their vote weights and the new `witnesses` among them, through a joint
+    /// config, without adding any learner.
+    async fn commit_membership(
+        &self,
+        members: BTreeMap<NID, u64>,
+        witnesses: BTreeSet<NID>,
+        blocking_catch_up: bool,
+    ) -> Result<ClientWriteResponse<R, NID>, ClientWriteError<NID>> {
+        tracing::info!("commit_membership: start to commit joint config");
 
         let (tx, rx) = oneshot::channel();
         // res is error if membership can not be changed.
@@ -287,7 +922,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             .call_core(
                 RaftMsg::ChangeMembership {
                     members: members.clone(),
-                    blocking,
+                    witnesses: witnesses.clone(),
+                    blocking_catch_up,
                     tx,
                 },
                 rx,
@@ -307,7 +943,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         tracing::debug!("the second step is to change to uniform config: {:?}", members);
 
         let (tx, rx) = oneshot::channel();
-        let res = self.call_core(RaftMsg::ChangeMembership { members, blocking, tx }, rx).await?;
+        let res = self
+            .call_core(
+                RaftMsg::ChangeMembership {
+                    members,
+                    witnesses,
+                    blocking_catch_up,
+                    tx,
+                },
+                rx,
+            )
+            .await?;
 
         tracing::info!("res of second change_membership: {}", res.summary());
 
@@ -316,7 +962,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Invoke RaftCore by sending a RaftMsg and blocks waiting for response.
     #[tracing::instrument(level = "debug", skip(self, mes, rx))]
-    pub(crate) async fn call_core<T, E>(&self, mes: RaftMsg<D, R>, rx: RaftRespRx<T, E>) -> Result<T, E>
+    pub(crate) async fn call_core<T, E>(&self, mes: RaftMsg<D, R, NID>, rx: RaftRespRx<T, E>) -> Result<T, E>
     where E: From<RaftError> {
         let span = tracing::Span::current();
 
@@ -340,11 +986,131 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         res
     }
 
+    /// Wait until this node steps down from being the leader.
+    ///
+    /// It fires for whatever makes the leader step down, e.g., a higher term is seen, leadership is transferred to
+    /// another node, or this node shuts down. The step-down is notified before this node starts to serve in another
+    /// state, e.g., as a follower. Thus an application can release the resources it keeps only on the leader.
+    ///
+    /// It returns the term in which this node was the leader, or `None` at once if this node is not a leader.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn leadership_lost(&self) -> Option<u64> {
+        let mut rx = self.inner.rx_leadership.clone();
+
+        let term = (*rx.borrow())?;
+
+        loop {
+            if *rx.borrow() != Some(term) {
+                return Some(term);
+            }
+
+            // RaftCore quits.
+            if rx.changed().await.is_err() {
+                return Some(term);
+            }
+        }
+    }
+
+    /// Subscribe to the lifecycle events of this node, such as becoming the leader or installing a snapshot.
+    ///
+    /// Every subscriber receives every event that happens after it subscribes, in order. Polling the metrics may miss
+    /// an event, e.g., a node that became the leader and then a follower between two polls. A subscriber that falls
+    /// too far behind receives a `RecvError::Lagged` and misses the oldest events.
+    ///
+    /// The channel is closed when this node shuts down.
+    pub fn events(&self) -> broadcast::Receiver<RaftEvent<NID>> {
+        match self.inner.tx_events.lock().unwrap().as_ref() {
+            Some(tx) => tx.subscribe(),
+            None => {
+                // Already shut down, return a closed channel.
+                let (_tx, rx) = broadcast::channel(1);
+                rx
+            }
+        }
+    }
+
+    /// The config this Raft node is using.
+    ///
+    /// It is the validated config passed to `Raft::new()`, with every update by `Raft::update_config()` applied.
+    /// An update is reflected as soon as `update_config()` returns.
+    pub fn config(&self) -> Arc<Config> {
+        self.inner.rx_config.borrow().clone()
+    }
+
     /// Get a handle to the metrics channel.
-    pub fn metrics(&self) -> watch::Receiver<RaftMetrics> {
+    pub fn metrics(&self) -> watch::Receiver<RaftMetrics<NID>> {
         self.inner.rx_metrics.clone()
     }
 
+    /// Get the latest metrics of this Raft node, for a one-shot query such as a health check.
+    ///
+    /// It is the last value sent to the metrics channel, and does not wait for a new one. Use `Raft::metrics()` or
+    /// `Raft::wait()` to watch the changes instead.
+    pub fn current_metrics(&self) -> RaftMetrics<NID> {
+        self.inner.rx_metrics.borrow().clone()
+    }
+
+    /// Get the voters and the learners of the latest membership this Raft node knows of.
+    ///
+    /// The learners are known only to the leader: on any other node `MembershipMetrics::learners()` is empty.
+    pub fn membership(&self) -> MembershipMetrics<NID> {
+        self.inner.rx_metrics.borrow().membership()
+    }
+
+    /// Get the id of the log that established the latest membership this Raft node knows of, `None` if there is no
+    /// membership config in the log yet.
+    ///
+    /// E.g., a tool coordinating an external system with membership changes compares it with the id of a log to tell
+    /// whether the log is ordered before or after the config. The config takes effect as soon as it is appended, thus
+    /// the log may not be committed yet.
+    pub fn membership_log_id(&self) -> Option<LogId> {
+        let log_id = self.inner.rx_metrics.borrow().membership_config.log_id;
+        if log_id.index == 0 {
+            None
+        } else {
+            Some(log_id)
+        }
+    }
+
+    /// Get the number of logs the replication target `target` is behind the last log of this leader.
+    ///
+    /// It is the lag in the latest metrics, see `ReplicationMetrics::lag`. It returns `None` if this node is not the
+    /// leader, or `target` is neither a voter nor a learner this leader replicates to.
+    pub fn replication_lag(&self, target: NID) -> Option<u64> {
+        let metrics = self.inner.rx_metrics.borrow();
+        if metrics.state != State::Leader {
+            return None;
+        }
+        metrics.leader_metrics.as_ref()?.replication.get(&target).map(|x| x.lag)
+    }
+
+    /// Get a handle to receive only the metrics that changed in a way the caller is interested in.
+    ///
+    /// `changed(prev, latest)` is evaluated on every two successive metrics.
+    /// See `MetricsChanges::on_leader_change()` and the like for the common cases.
+    ///
+    /// ```ignore
+    /// let mut changes = r.metrics_changes(|prev, latest| prev.last_applied / 100 != latest.last_applied / 100);
+    ///
+    /// // wait for every 100 logs to be applied
+    /// while let Some(m) = changes.next().await {
+    ///     println!("applied upto {}", m.last_applied);
+    /// }
+    /// ```
+    pub fn metrics_changes<F>(&self, changed: F) -> MetricsChanges<F, NID>
+    where F: Fn(&RaftMetrics<NID>, &RaftMetrics<NID>) -> bool {
+        MetricsChanges::new(self.inner.rx_metrics.clone(), changed)
+    }
+
+    /// Get a handle to receive the events derived from the metrics, such as a learner becoming ready to promote.
+    ///
+    /// See `MetricsEvents`.
+    pub fn metrics_events(&self) -> MetricsEvents<NID> {
+        let rx_events = self.events();
+        let latest = self.inner.rx_metrics.borrow().clone();
+        MetricsEvents::new(&latest, rx_events)
+    }
+
     /// Get a handle to wait for the metrics to satisfy some condition.
     ///
     /// ```ignore
@@ -356,13 +1122,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// // wait for raft log-3 to be received and applied:
     /// r.wait(Some(timeout)).log(3).await?;
     ///
+    /// // wait for raft log-3 or a greater one to be applied:
+    /// r.wait(Some(timeout)).applied_index(3).await?;
+    ///
     /// // wait for ever for raft node's current leader to become 3:
     /// r.wait(None).current_leader(2).await?;
     ///
     /// // wait for raft state to become a follower
     /// r.wait(None).state(State::Follower).await?;
     /// ```
-    pub fn wait(&self, timeout: Option<Duration>) -> Wait {
+    pub fn wait(&self, timeout: Option<Duration>) -> Wait<NID> {
         let timeout = match timeout {
             Some(t) => t,
             None => Duration::from_millis(500),
@@ -374,18 +1143,51 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     /// Shutdown this Raft node.
+    ///
+    /// Entries that are committed but not yet applied are left to be applied when the node restarts.
     pub async fn shutdown(&self) -> anyhow::Result<()> {
+        self.do_shutdown(false, None).await
+    }
+
+    /// Shutdown this Raft node gracefully, e.g., before a rolling restart.
+    ///
+    /// It stops serving any new request, applies every committed entry to the state machine and saves the hard
+    /// state before it returns. Thus a restarted node does not need to apply them again.
+    /// With `Config::snapshot_on_shutdown`, it also builds a snapshot of the last applied log.
+    ///
+    /// If it does not finish within `timeout`, the node is aborted and an error is returned.
+    pub async fn shutdown_graceful(&self, timeout: Duration) -> anyhow::Result<()> {
+        self.do_shutdown(true, Some(timeout)).await
+    }
+
+    async fn do_shutdown(&self, graceful: bool, timeout: Option<Duration>) -> anyhow::Result<()> {
         if let Some(tx) = self.inner.tx_shutdown.lock().await.take() {
-            let _ = tx.send(());
+            let _ = tx.send(graceful);
         }
-        if let Some(handle) = self.inner.raft_handle.lock().await.take() {
-            let _ = handle.await?;
+        self.inner.tx_events.lock().unwrap().take();
+        if let Some(mut handle) = self.inner.raft_handle.lock().await.take() {
+            match timeout {
+                None => {
+                    let _ = handle.await?;
+                }
+                Some(t) => match tokio::time::timeout(t, &mut handle).await {
+                    Ok(res) => {
+                        let _ = res?;
+                    }
+                    Err(_) => {
+                        handle.abort();
+                        return Err(anyhow::anyhow!("graceful shutdown did not finish in {:?}, aborted", t));
+                    }
+                },
+            }
         }
         Ok(())
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Clone for Raft<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId> Clone
+    for Raft<D, R, N, S, NID>
+{
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -396,59 +1198,146 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Cl
 pub(crate) type RaftRespTx<T, E> = oneshot::Sender<Result<T, E>>;
 pub(crate) type RaftRespRx<T, E> = oneshot::Receiver<Result<T, E>>;
 
+/// A client write request along with the channel to respond to it.
+pub(crate) type ClientWriteWithTx<D, R, NID> = (
+    ClientWriteRequest<D, NID>,
+    RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AddLearnerResponse {
     pub matched: LogId,
 }
 
 /// A message coming from the Raft API.
-pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
+pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse, NID: RaftNodeId> {
     AppendEntries {
-        rpc: AppendEntriesRequest<D>,
+        rpc: AppendEntriesRequest<D, NID>,
         tx: RaftRespTx<AppendEntriesResponse, RaftError>,
     },
     RequestVote {
-        rpc: VoteRequest,
+        rpc: VoteRequest<NID>,
         tx: RaftRespTx<VoteResponse, RaftError>,
     },
     InstallSnapshot {
-        rpc: InstallSnapshotRequest,
+        rpc: InstallSnapshotRequest<NID>,
         tx: RaftRespTx<InstallSnapshotResponse, RaftError>,
     },
+    TimeoutNow {
+        rpc: TimeoutNowRequest<NID>,
+        tx: RaftRespTx<TimeoutNowResponse, RaftError>,
+    },
     ClientWriteRequest {
-        rpc: ClientWriteRequest<D>,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        rpc: ClientWriteRequest<D, NID>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
     },
+    /// A batch of client write requests, each with its own response channel.
+    ClientWriteBatch { reqs: Vec<ClientWriteWithTx<D, R, NID>> },
     ClientReadRequest {
-        tx: RaftRespTx<(), ClientReadError>,
+        /// Responds with the read index, see `LeaderState::handle_client_read_request()`.
+        tx: RaftRespTx<LogId, ClientReadError<NID>>,
+    },
+    ReadLocal {
+        max_staleness: Duration,
+        /// Responds with the last applied log id.
+        tx: RaftRespTx<LogId, ClientReadError<NID>>,
+    },
+    CheckApplied {
+        log_id: LogId,
+        tx: RaftRespTx<bool, AppliedError>,
+    },
+    GetLogEntries {
+        start: u64,
+        end: u64,
+        read_unapplied: bool,
+        tx: RaftRespTx<Vec<Entry<D, NID>>, LogReadError>,
+    },
+    GetHardState {
+        /// Responds with the term and vote in memory, which are always the same as the persisted ones.
+        tx: RaftRespTx<HardState<NID>, RaftError>,
     },
     Initialize {
-        members: BTreeSet<NodeId>,
+        members: BTreeSet<NID>,
         tx: RaftRespTx<(), InitializeError>,
     },
     // TODO(xp): make tx a field of a struct
     /// Request raft core to setup a new replication to a learner.
     AddLearner {
-        id: NodeId,
+        id: NID,
 
         /// If block until the newly added learner becomes line-rate.
         blocking: bool,
 
         /// Send the log id when the replication becomes line-rate.
-        tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
+        tx: RaftRespTx<AddLearnerResponse, AddLearnerError<NID>>,
     },
     ChangeMembership {
-        members: BTreeSet<NodeId>,
-        /// with blocking==false, respond to client a ChangeMembershipError::LearnerIsLagging error at once if a
-        /// non-member is lagging.
+        /// The proposed voters and their vote weights.
+        members: BTreeMap<NID, u64>,
+        /// The proposed voters that become witnesses. The current witnesses stay witnesses if they are proposed.
+        witnesses: BTreeSet<NID>,
+        /// with blocking_catch_up==false, respond to client a ChangeMembershipError::LearnerIsLagging error at once
+        /// if a non-member is lagging.
         ///
-        /// Otherwise, wait for commit of the member change log.
-        blocking: bool,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        /// Otherwise, wait for every non-member to catch up before proposing the member change log, until a
+        /// deadline.
+        blocking_catch_up: bool,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    },
+    AbortMembershipChange {
+        /// Responds with the log id of the membership config in effect after aborting, once it is committed.
+        tx: RaftRespTx<LogId, ClientWriteError<NID>>,
+    },
+    ForceRemoveNode {
+        /// The voter to remove without joint consensus.
+        id: NID,
+        tx: RaftRespTx<ClientWriteResponse<R, NID>, ClientWriteError<NID>>,
+    },
+    CanChangeMembership {
+        /// The proposed voters and their vote weights, to validate without proposing.
+        members: BTreeMap<NID, u64>,
+        tx: RaftRespTx<(), ClientWriteError<NID>>,
+    },
+    UpdateConfig {
+        delta: ConfigDelta,
+        tx: RaftRespTx<(), UpdateConfigError>,
+    },
+    TriggerSnapshot {
+        /// Responds with the meta of the snapshot once it is built.
+        tx: RaftRespTx<SnapshotMeta, TriggerSnapshotError>,
+    },
+    RebuildStateMachine {
+        /// Responds with the last log id re-applied once the state machine is rebuilt.
+        tx: RaftRespTx<LogId, RebuildStateMachineError>,
+    },
+    TransferLeadership {
+        /// The node to transfer leadership to, or `None` to let the leader choose the most up to date voter.
+        target: Option<NID>,
+        /// Responds with the chosen target once a TimeoutNow is sent to it.
+        tx: RaftRespTx<NID, TransferLeadershipError<NID>>,
+    },
+    StepDown {
+        /// Responds with the voter a TimeoutNow is sent to, if any, once the leader becomes a follower.
+        tx: RaftRespTx<Option<NID>, TransferLeadershipError<NID>>,
+    },
+    LeaderLeaseRemaining {
+        /// Responds with the remaining lease if this node is the leader.
+        tx: RaftRespTx<Option<Duration>, RaftError>,
+    },
+    LastContact {
+        target: NID,
+        /// Responds with when the last response from `target` was received, if this node is the leader.
+        tx: RaftRespTx<Option<Instant>, RaftError>,
+    },
+    PauseReplication {
+        target: NID,
+        /// Whether to pause or to resume replication to `target`.
+        paused: bool,
+        tx: RaftRespTx<(), PauseReplicationError<NID>>,
     },
 }
 
-impl<D, R> MessageSummary for RaftMsg<D, R>
+impl<D, R, NID: RaftNodeId> MessageSummary for RaftMsg<D, R, NID>
 where
     D: AppData,
     R: AppDataResponse,
@@ -464,18 +1353,61 @@ where
             RaftMsg::InstallSnapshot { rpc, .. } => {
                 format!("InstallSnapshot: {}", rpc.summary())
             }
+            RaftMsg::TimeoutNow { rpc, .. } => {
+                format!("TimeoutNow: {}", rpc.summary())
+            }
             RaftMsg::ClientWriteRequest { rpc, .. } => {
                 format!("ClientWriteRequest: {}", rpc.summary())
             }
+            RaftMsg::ClientWriteBatch { reqs } => {
+                format!("ClientWriteBatch: n: {}", reqs.len())
+            }
             RaftMsg::ClientReadRequest { .. } => "ClientReadRequest".to_string(),
+            RaftMsg::ReadLocal { max_staleness, .. } => format!("ReadLocal: max_staleness: {:?}", max_staleness),
+            RaftMsg::CheckApplied { log_id, .. } => format!("CheckApplied: {}", log_id),
+            RaftMsg::GetLogEntries {
+                start,
+                end,
+                read_unapplied,
+                ..
+            } => format!(
+                "GetLogEntries: [{}, {}), read_unapplied: {}",
+                start, end, read_unapplied
+            ),
+            RaftMsg::GetHardState { .. } => "GetHardState".to_string(),
             RaftMsg::Initialize { members, .. } => {
                 format!("Initialize: {:?}", members)
             }
             RaftMsg::AddLearner { id, blocking, .. } => {
                 format!("AddLearner: id: {}, blocking: {}", id, blocking)
             }
-            RaftMsg::ChangeMembership { members, blocking, .. } => {
-                format!("ChangeMembership: members: {:?}, blocking: {}", members, blocking)
+            RaftMsg::ChangeMembership {
+                members,
+                witnesses,
+                blocking_catch_up,
+                ..
+            } => {
+                format!(
+                    "ChangeMembership: members: {:?}, witnesses: {:?}, blocking_catch_up: {}",
+                    members, witnesses, blocking_catch_up
+                )
+            }
+            RaftMsg::AbortMembershipChange { .. } => "AbortMembershipChange".to_string(),
+            RaftMsg::ForceRemoveNode { id, .. } => format!("ForceRemoveNode: {}", id),
+            RaftMsg::CanChangeMembership { members, .. } => format!("CanChangeMembership: {:?}", members),
+            RaftMsg::UpdateConfig { delta, .. } => {
+                format!("UpdateConfig: {:?}", delta)
+            }
+            RaftMsg::TriggerSnapshot { .. } => "TriggerSnapshot".to_string(),
+            RaftMsg::RebuildStateMachine { .. } => "RebuildStateMachine".to_string(),
+            RaftMsg::TransferLeadership { target, .. } => {
+                format!("TransferLeadership: {:?}", target)
+            }
+            RaftMsg::StepDown { .. } => "StepDown".to_string(),
+            RaftMsg::LeaderLeaseRemaining { .. } => "LeaderLeaseRemaining".to_string(),
+            RaftMsg::LastContact { target, .. } => format!("LastContact: {}", target),
+            RaftMsg::PauseReplication { target, paused, .. } => {
+                format!("PauseReplication: target: {}, paused: {}", target, paused)
             }
         }
     }
@@ -485,12 +1417,14 @@ where
 
 /// An RPC sent by a cluster leader to replicate log entries (§5.3), and as a heartbeat (§5.2).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppendEntriesRequest<D: AppData> {
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct AppendEntriesRequest<D: AppData, NID: RaftNodeId = NodeId> {
     /// The leader's current term.
     pub term: u64,
 
     /// The leader's ID. Useful in redirecting clients.
-    pub leader_id: u64,
+    pub leader_id: NID,
 
     pub prev_log_id: LogId,
 
@@ -499,27 +1433,46 @@ pub struct AppendEntriesRequest<D: AppData> {
     /// This may be empty when the leader is sending heartbeats. Entries
     /// are batched for efficiency.
     #[serde(bound = "D: AppData")]
-    pub entries: Vec<Entry<D>>,
+    pub entries: Vec<Entry<D, NID>>,
 
     /// The leader's committed log id.
     pub leader_commit: LogId,
+
+    /// The new log entries compressed as a whole, in place of `entries`, see `Config::replication_compression`.
+    ///
+    /// `Raft::append_entries()` decompresses them into `entries` before handling the RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compressed_entries: Option<CompressedEntries>,
+}
+
+/// The log entries of an AppendEntries, encoded with `AppData::encode_entries()` and then compressed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct CompressedEntries {
+    /// The codec the entries are compressed with.
+    pub codec: Compression,
+
+    /// The compressed entries.
+    pub data: Vec<u8>,
 }
 
-impl<D: AppData> MessageSummary for AppendEntriesRequest<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for AppendEntriesRequest<D, NID> {
     fn summary(&self) -> String {
         format!(
-            "leader={}-{}, prev_log_id={}, leader_commit={}, entries={}",
+            "leader={}-{}, prev_log_id={}, leader_commit={}, entries={}, compressed={:?}",
             self.term,
             self.leader_id,
             self.prev_log_id,
             self.leader_commit,
-            self.entries.as_slice().summary()
+            self.entries.as_slice().summary(),
+            self.compressed_entries.as_ref().map(|x| (x.codec, x.data.len()))
         )
     }
 }
 
 /// The response to an `AppendEntriesRequest`.
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct AppendEntriesResponse {
     /// The responding node's current term, for leader to update itself.
     pub term: u64,
@@ -544,21 +1497,36 @@ impl AppendEntriesResponse {
 
 /// A Raft log entry.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Entry<D: AppData> {
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct Entry<D: AppData, NID: RaftNodeId = NodeId> {
     pub log_id: LogId,
 
     /// This entry's payload.
     #[serde(bound = "D: AppData")]
-    pub payload: EntryPayload<D>,
+    pub payload: EntryPayload<D, NID>,
+
+    /// The id of the client request this entry is appended for, if the client attached one.
+    ///
+    /// A state machine uses it to apply a retried request only once, see `ClientSessionTable`.
+    #[serde(default)]
+    pub request_id: Option<ClientRequestId>,
+}
+
+impl<D: AppData, NID: RaftNodeId> Entry<D, NID> {
+    /// The approximate size in bytes of the entry when it is sent, see `AppData::size_hint()`.
+    pub(crate) fn size_hint(&self) -> u64 {
+        mem::size_of::<LogId>() as u64 + self.payload.size_hint()
+    }
 }
 
-impl<D: AppData> MessageSummary for Entry<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for Entry<D, NID> {
     fn summary(&self) -> String {
         format!("{}:{}", self.log_id, self.payload.summary())
     }
 }
 
-impl<D: AppData> MessageSummary for Option<Entry<D>> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for Option<Entry<D, NID>> {
     fn summary(&self) -> String {
         match self {
             None => "None".to_string(),
@@ -567,14 +1535,14 @@ impl<D: AppData> MessageSummary for Option<Entry<D>> {
     }
 }
 
-impl<D: AppData> MessageSummary for &[Entry<D>] {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for &[Entry<D, NID>] {
     fn summary(&self) -> String {
         let entry_refs: Vec<_> = self.iter().collect();
         entry_refs.as_slice().summary()
     }
 }
 
-impl<D: AppData> MessageSummary for &[&Entry<D>] {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for &[&Entry<D, NID>] {
     fn summary(&self) -> String {
         let mut res = Vec::with_capacity(self.len());
         if self.len() <= 5 {
@@ -595,7 +1563,9 @@ impl<D: AppData> MessageSummary for &[&Entry<D>] {
 
 /// Log entry payload variants.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum EntryPayload<D: AppData> {
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub enum EntryPayload<D: AppData, NID: RaftNodeId = NodeId> {
     /// An empty payload committed by a new cluster leader.
     Blank,
 
@@ -603,10 +1573,10 @@ pub enum EntryPayload<D: AppData> {
     Normal(D),
 
     /// A change-membership log entry.
-    Membership(Membership),
+    Membership(Membership<NID>),
 }
 
-impl<D: AppData> MessageSummary for EntryPayload<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for EntryPayload<D, NID> {
     fn summary(&self) -> String {
         match self {
             EntryPayload::Blank => "blank".to_string(),
@@ -618,6 +1588,17 @@ impl<D: AppData> MessageSummary for EntryPayload<D> {
     }
 }
 
+impl<D: AppData, NID: RaftNodeId> EntryPayload<D, NID> {
+    /// The approximate size in bytes of the payload when it is sent, `AppData::size_hint()` for application data.
+    pub(crate) fn size_hint(&self) -> u64 {
+        match self {
+            EntryPayload::Blank => 0,
+            EntryPayload::Normal(data) => data.size_hint(),
+            EntryPayload::Membership(m) => mem::size_of_val(m) as u64,
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// The membership configuration of the cluster.
@@ -677,15 +1658,31 @@ impl<D: AppData> MessageSummary for EntryPayload<D> {
 /// - and stores the last committed membership and the newly proposed membership in on log entry(because raft does not
 ///   store committed index), which is the joint membership entry.
 #[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Membership {
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct Membership<NID: RaftNodeId = NodeId> {
     /// Multi configs.
-    configs: Vec<BTreeSet<NodeId>>,
+    configs: Vec<BTreeSet<NID>>,
+
+    /// The vote weight of the voters of every config, in the same order as `configs`.
+    ///
+    /// A voter not in it has a weight of 1, thus it is empty if every voter has a weight of 1. A quorum of a config is
+    /// the voters with more than half of the total weight of the config.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    weights: Vec<BTreeMap<NID, u64>>,
+
+    /// The voters that only vote, see `Raft::add_witness()`.
+    ///
+    /// A witness counts toward a quorum like any other voter, but the leader replicates only the log ids and the
+    /// membership configs to it, and it is never elected.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    witnesses: BTreeSet<NID>,
 
     /// Cache of all node ids.
-    all_nodes: BTreeSet<NodeId>,
+    all_nodes: BTreeSet<NID>,
 }
 
-impl MessageSummary for Membership {
+impl<NID: RaftNodeId> MessageSummary for Membership<NID> {
     fn summary(&self) -> String {
         let mut res = vec!["[".to_string()];
         for (i, c) in self.configs.iter().enumerate() {
@@ -695,51 +1692,142 @@ impl MessageSummary for Membership {
             res.push(format!("{:?}", c));
         }
         res.push("]".to_string());
+        if !self.weights.is_empty() {
+            res.push(format!(",weights:{:?}", self.weights));
+        }
+        if !self.witnesses.is_empty() {
+            res.push(format!(",witnesses:{:?}", self.witnesses));
+        }
         res.join("")
     }
 }
 
-impl Membership {
-    pub fn new_single(members: BTreeSet<NodeId>) -> Self {
+impl<NID: RaftNodeId> Membership<NID> {
+    pub fn new_single(members: BTreeSet<NID>) -> Self {
         let configs = vec![members];
         let all_nodes = Self::build_all_nodes(&configs);
-        Membership { configs, all_nodes }
+        Membership {
+            configs,
+            weights: vec![],
+            witnesses: BTreeSet::new(),
+            all_nodes,
+        }
     }
 
-    pub fn new_multi(configs: Vec<BTreeSet<NodeId>>) -> Self {
+    pub fn new_multi(configs: Vec<BTreeSet<NID>>) -> Self {
         let all_nodes = Self::build_all_nodes(&configs);
-        Membership { configs, all_nodes }
+        Membership {
+            configs,
+            weights: vec![],
+            witnesses: BTreeSet::new(),
+            all_nodes,
+        }
     }
 
-    pub fn all_nodes(&self) -> &BTreeSet<NodeId> {
+    /// Create a membership of configs in which every voter has a vote weight.
+    ///
+    /// Weights of 1 are not stored, thus a membership in which every voter has a weight of 1 is the same as the one
+    /// built by `new_multi()`.
+    pub fn new_weighted(configs: Vec<BTreeMap<NID, u64>>) -> Self {
+        let mut weights = Vec::with_capacity(configs.len());
+        for config in configs.iter() {
+            let w = config.iter().filter(|(_, w)| **w != 1).map(|(id, w)| (*id, *w)).collect::<BTreeMap<_, _>>();
+            weights.push(w);
+        }
+
+        if weights.iter().all(|w| w.is_empty()) {
+            weights = vec![];
+        }
+
+        let configs = configs.into_iter().map(|c| c.into_keys().collect()).collect::<Vec<_>>();
+        let all_nodes = Self::build_all_nodes(&configs);
+        Membership {
+            configs,
+            weights,
+            witnesses: BTreeSet::new(),
+            all_nodes,
+        }
+    }
+
+    pub fn all_nodes(&self) -> &BTreeSet<NID> {
+        &self.all_nodes
+    }
+
+    /// Returns the voters, i.e., the nodes in any of the configs.
+    ///
+    /// In a joint config it includes the voters of both the old and the new config, since both have to grant a vote.
+    pub fn voters(&self) -> &BTreeSet<NID> {
         &self.all_nodes
     }
 
-    pub fn replace(&mut self, new_configs: Vec<BTreeSet<NodeId>>) {
+    /// Returns the voters that are witnesses.
+    pub fn witnesses(&self) -> &BTreeSet<NID> {
+        &self.witnesses
+    }
+
+    /// Returns true if the given node is a witness: it votes, but stores no application data and is never elected.
+    pub fn is_witness(&self, id: &NID) -> bool {
+        self.witnesses.contains(id)
+    }
+
+    /// Returns true if the given node is a voter that can be elected, i.e., not a witness.
+    pub fn is_electable(&self, id: &NID) -> bool {
+        self.contains(id) && !self.is_witness(id)
+    }
+
+    /// Mark the given voters as witnesses. The ones that are not voters of this membership are ignored.
+    #[must_use]
+    pub fn with_witnesses(mut self, witnesses: BTreeSet<NID>) -> Self {
+        self.witnesses = witnesses.into_iter().filter(|id| self.all_nodes.contains(id)).collect();
+        self
+    }
+
+    pub fn replace(&mut self, new_configs: Vec<BTreeSet<NID>>) {
         self.configs = new_configs;
+        self.weights = vec![];
         self.all_nodes = Self::build_all_nodes(&self.configs);
+        self.witnesses.retain(|id| self.all_nodes.contains(id));
     }
 
-    pub fn push(&mut self, new_config: BTreeSet<NodeId>) {
+    pub fn push(&mut self, new_config: BTreeSet<NID>) {
         self.configs.push(new_config);
+        if !self.weights.is_empty() {
+            self.weights.push(BTreeMap::new());
+        }
         self.all_nodes = Self::build_all_nodes(&self.configs);
     }
 
-    pub fn get_configs(&self) -> &Vec<BTreeSet<NodeId>> {
+    pub fn get_configs(&self) -> &Vec<BTreeSet<NID>> {
         &self.configs
     }
 
-    pub fn get_ith_config(&self, i: usize) -> Option<&BTreeSet<NodeId>> {
+    pub fn get_ith_config(&self, i: usize) -> Option<&BTreeSet<NID>> {
         self.configs.get(i)
     }
 
+    /// Returns the voters of the i-th config along with their vote weights.
+    pub fn get_ith_weights(&self, i: usize) -> Option<BTreeMap<NID, u64>> {
+        let config = self.configs.get(i)?;
+        Some(config.iter().map(|id| (*id, self.weight(i, id))).collect())
+    }
+
+    /// Returns the vote weight of a voter in the i-th config.
+    pub fn weight(&self, i: usize, id: &NID) -> u64 {
+        self.weights.get(i).and_then(|w| w.get(id)).copied().unwrap_or(1)
+    }
+
+    /// Returns true if any voter has a vote weight other than 1.
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
     // TODO(xp): remove this
-    pub fn ith_config(&self, i: usize) -> Vec<NodeId> {
+    pub fn ith_config(&self, i: usize) -> Vec<NID> {
         self.configs[i].iter().cloned().collect()
     }
 
     /// Check if the given NodeId exists in this membership config.
-    pub fn contains(&self, x: &NodeId) -> bool {
+    pub fn contains(&self, x: &NID) -> bool {
         for c in self.configs.iter() {
             if c.contains(x) {
                 return true;
@@ -755,7 +1843,7 @@ impl Membership {
 
     // TODO(xp): rename this
     /// Create a new initial config containing only the given node ID.
-    pub fn new_initial(id: NodeId) -> Self {
+    pub fn new_initial(id: NID) -> Self {
         Membership::new_single(btreeset! {id})
     }
 
@@ -763,16 +1851,16 @@ impl Membership {
     pub fn to_final_config(&self) -> Self {
         assert!(!self.configs.is_empty());
 
-        let last = self.configs.last().cloned().unwrap();
-        Membership::new_single(last)
+        let last = self.get_ith_weights(self.configs.len() - 1).unwrap();
+        Membership::new_weighted(vec![last]).with_witnesses(self.witnesses.clone())
     }
 
     /// Return true if the given set of ids constitutes a majority.
     ///
     /// I.e. the id set includes a majority of every config.
-    pub fn is_majority(&self, granted: &BTreeSet<NodeId>) -> bool {
-        for config in self.configs.iter() {
-            if !Self::is_majority_of_single_config(granted, config) {
+    pub fn is_majority(&self, granted: &BTreeSet<NID>) -> bool {
+        for i in 0..self.configs.len() {
+            if !self.is_majority_of_single_config(granted, i) {
                 return false;
             }
         }
@@ -786,29 +1874,37 @@ impl Membership {
     /// `10` constitutes a majoirty in the first config {1,2,3}.
     /// `20` constitutes a majority in the second config {4,5,6}.
     /// Thus the minimal value `10` is the greatest joint majority for this membership config.
-    pub fn greatest_majority_value<'v, V>(&self, values: &'v BTreeMap<NodeId, V>) -> Option<&'v V>
+    ///
+    /// With vote weights, a value constitutes a majority if the voters having a value no less than it have more than
+    /// half of the total weight of a config.
+    pub fn greatest_majority_value<'v, V>(&self, values: &'v BTreeMap<NID, V>) -> Option<&'v V>
     where V: Ord {
         let mut res = vec![];
-        for config in self.configs.iter() {
+        for (i, config) in self.configs.iter().enumerate() {
             let mut vs = Vec::with_capacity(config.len());
 
             for id in config.iter() {
                 let v = values.get(id);
                 if let Some(v) = v {
-                    vs.push(v)
+                    vs.push((v, self.weight(i, id) as u128))
                 }
             }
 
-            let majority = quorum::majority_of(config.len());
+            // Collect values from the greatest until they have a majority of the weight.
+            vs.sort_unstable_by(|a, b| b.0.cmp(a.0));
 
-            if vs.len() < majority {
-                res.push(None);
-                continue;
-            }
+            let total = self.total_weight(i);
+            let mut acc = 0u128;
+            let mut majority_greatest = None;
 
-            vs.sort_unstable();
+            for (v, w) in vs {
+                acc += w;
+                if acc * 2 > total {
+                    majority_greatest = Some(v);
+                    break;
+                }
+            }
 
-            let majority_greatest = Some(vs[vs.len() - majority]);
             res.push(majority_greatest);
         }
 
@@ -816,15 +1912,20 @@ impl Membership {
         min_greatest.unwrap_or(None)
     }
 
-    fn is_majority_of_single_config(granted: &BTreeSet<NodeId>, single_config: &BTreeSet<NodeId>) -> bool {
-        let d = granted.intersection(single_config);
-        let n_granted = d.fold(0, |a, _x| a + 1);
+    /// Returns the total vote weight of the i-th config.
+    ///
+    /// It is summed in `u128`, thus neither the sum of large weights nor twice a sum overflows.
+    pub(crate) fn total_weight(&self, i: usize) -> u128 {
+        self.configs[i].iter().map(|id| self.weight(i, id) as u128).sum()
+    }
+
+    fn is_majority_of_single_config(&self, granted: &BTreeSet<NID>, i: usize) -> bool {
+        let n_granted = granted.intersection(&self.configs[i]).map(|id| self.weight(i, id) as u128).sum::<u128>();
 
-        let majority = quorum::majority_of(single_config.len());
-        n_granted >= majority
+        n_granted * 2 > self.total_weight(i)
     }
 
-    fn build_all_nodes(configs: &[BTreeSet<NodeId>]) -> BTreeSet<NodeId> {
+    fn build_all_nodes(configs: &[BTreeSet<NID>]) -> BTreeSet<NID> {
         let mut nodes = BTreeSet::new();
         for config in configs.iter() {
             nodes.extend(config)
@@ -836,34 +1937,54 @@ impl Membership {
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// An RPC sent by candidates to gather votes (§5.2).
-#[derive(Debug, Serialize, Deserialize)]
-pub struct VoteRequest {
-    /// The candidate's current term.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct VoteRequest<NID: RaftNodeId = NodeId> {
+    /// The candidate's current term, or the term it is going to campaign for in a pre-vote.
     pub term: u64,
 
-    pub candidate_id: u64,
+    pub candidate_id: NID,
 
     pub last_log_id: LogId,
+
+    /// Set if the candidate is elected because of a TimeoutNow from the current leader.
+    ///
+    /// A voter does not reject such a request even if it has heard from the current leader recently.
+    #[serde(default)]
+    pub leadership_transfer: bool,
+
+    /// Set if it only asks whether the voter would grant a vote for `term`, see `Config::enable_pre_vote`.
+    ///
+    /// A pre-vote does not change the term or the vote of the voter.
+    #[serde(default)]
+    pub pre_vote: bool,
 }
 
-impl MessageSummary for VoteRequest {
+impl<NID: RaftNodeId> MessageSummary for VoteRequest<NID> {
     fn summary(&self) -> String {
-        format!("{}-{}, last_log:{}", self.term, self.candidate_id, self.last_log_id)
+        format!(
+            "{}-{}, last_log:{}, pre_vote:{}",
+            self.term, self.candidate_id, self.last_log_id, self.pre_vote
+        )
     }
 }
 
-impl VoteRequest {
-    pub fn new(term: u64, candidate_id: u64, last_log_id: LogId) -> Self {
+impl<NID: RaftNodeId> VoteRequest<NID> {
+    pub fn new(term: u64, candidate_id: NID, last_log_id: LogId) -> Self {
         Self {
             term,
             candidate_id,
             last_log_id,
+            leadership_transfer: false,
+            pre_vote: false,
         }
     }
 }
 
 /// The response to a `VoteRequest`.
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct VoteResponse {
     /// The current term of the responding node, for the candidate to update itself.
     pub term: u64,
@@ -877,13 +1998,44 @@ pub struct VoteResponse {
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// An RPC sent by the leader to an up to date follower to make it start an election at once.
+///
+/// It is the last step of a leadership transfer, see `Raft::transfer_leadership()`.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct TimeoutNowRequest<NID: RaftNodeId = NodeId> {
+    /// The leader's current term.
+    pub term: u64,
+
+    pub leader_id: NID,
+}
+
+impl<NID: RaftNodeId> MessageSummary for TimeoutNowRequest<NID> {
+    fn summary(&self) -> String {
+        format!("{}-{}", self.term, self.leader_id)
+    }
+}
+
+/// The response to a `TimeoutNowRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct TimeoutNowResponse {
+    /// The current term of the responding node.
+    pub term: u64,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+
 /// An RPC sent by the Raft leader to send chunks of a snapshot to a follower (§7).
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InstallSnapshotRequest {
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct InstallSnapshotRequest<NID: RaftNodeId = NodeId> {
     /// The leader's current term.
     pub term: u64,
     /// The leader's ID. Useful in redirecting clients.
-    pub leader_id: u64,
+    pub leader_id: NID,
 
     /// Metadata of a snapshot: snapshot_id, last_log_ed membership etc.
     pub meta: SnapshotMeta,
@@ -897,7 +2049,7 @@ pub struct InstallSnapshotRequest {
     pub done: bool,
 }
 
-impl MessageSummary for InstallSnapshotRequest {
+impl<NID: RaftNodeId> MessageSummary for InstallSnapshotRequest<NID> {
     fn summary(&self) -> String {
         format!(
             "term={}, leader_id={}, meta={:?}, offset={}, len={}, done={}",
@@ -913,6 +2065,7 @@ impl MessageSummary for InstallSnapshotRequest {
 
 /// The response to an `InstallSnapshotRequest`.
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct InstallSnapshotResponse {
     /// The receiving node's current term, for leader to update itself.
     pub term: u64,
@@ -925,31 +2078,54 @@ pub struct InstallSnapshotResponse {
 /// The entry of this payload will be appended to the Raft log and then applied to the Raft state
 /// machine according to the Raft protocol.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ClientWriteRequest<D: AppData> {
+#[serde(bound = "")]
+pub struct ClientWriteRequest<D: AppData, NID: RaftNodeId = NodeId> {
     /// The application specific contents of this client request.
     #[serde(bound = "D: AppData")]
-    pub(crate) entry: EntryPayload<D>,
+    pub(crate) entry: EntryPayload<D, NID>,
+
+    /// The id a client attaches to have a retried request applied only once.
+    #[serde(default)]
+    pub(crate) request_id: Option<ClientRequestId>,
 }
 
-impl<D: AppData> MessageSummary for ClientWriteRequest<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for ClientWriteRequest<D, NID> {
     fn summary(&self) -> String {
-        self.entry.summary()
+        match &self.request_id {
+            None => self.entry.summary(),
+            Some(id) => format!("{}, {}", id, self.entry.summary()),
+        }
     }
 }
 
-impl<D: AppData> ClientWriteRequest<D> {
+impl<D: AppData, NID: RaftNodeId> ClientWriteRequest<D, NID> {
     /// Create a new client payload instance with a normal entry type.
     pub fn new(entry: D) -> Self {
         Self::new_base(EntryPayload::Normal(entry))
     }
 
     /// Create a new instance.
-    pub(crate) fn new_base(entry: EntryPayload<D>) -> Self {
-        Self { entry }
+    pub(crate) fn new_base(entry: EntryPayload<D, NID>) -> Self {
+        Self {
+            entry,
+            request_id: None,
+        }
+    }
+
+    /// Attach the id of this request, so that a retry with the same id is applied only once.
+    ///
+    /// The id is written into the log entry. A retry sent while the first request is in flight waits for its
+    /// response. A retry of an applied request is recognized by the state machine if it keeps a `ClientSessionTable`.
+    pub fn with_request_id(mut self, client_id: impl ToString, sequence: u64) -> Self {
+        self.request_id = Some(ClientRequestId {
+            client_id: client_id.to_string(),
+            sequence,
+        });
+        self
     }
 
     /// Generate a new payload holding a config change.
-    pub(crate) fn new_config(membership: Membership) -> Self {
+    pub(crate) fn new_config(membership: Membership<NID>) -> Self {
         Self::new_base(EntryPayload::Membership(membership))
     }
 
@@ -961,9 +2137,27 @@ impl<D: AppData> ClientWriteRequest<D> {
     }
 }
 
+/// Identifies a client write request, see `ClientWriteRequest::with_request_id()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct ClientRequestId {
+    /// The client sending the request. Every client numbers its requests on its own.
+    pub client_id: String,
+
+    /// The sequence number of the request, unique among the requests of the client.
+    pub sequence: u64,
+}
+
+impl std::fmt::Display for ClientRequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.client_id, self.sequence)
+    }
+}
+
 /// The response to a `ClientRequest`.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ClientWriteResponse<R: AppDataResponse> {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ClientWriteResponse<R: AppDataResponse, NID: RaftNodeId = NodeId> {
     pub log_id: LogId,
 
     /// Application specific response data.
@@ -971,10 +2165,10 @@ pub struct ClientWriteResponse<R: AppDataResponse> {
     pub data: R,
 
     /// If the log entry is a change-membership entry.
-    pub membership: Option<Membership>,
+    pub membership: Option<Membership<NID>>,
 }
 
-impl<R: AppDataResponse> MessageSummary for ClientWriteResponse<R> {
+impl<R: AppDataResponse, NID: RaftNodeId> MessageSummary for ClientWriteResponse<R, NID> {
     fn summary(&self) -> String {
         format!("log_id: {}, membership: {:?}", self.log_id, self.membership)
     }
@@ -7,6 +7,7 @@ use serde::Serialize;
 /// The identity of a raft log.
 /// A term and an index identifies an log globally.
 #[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct LogId {
     pub term: u64,
     pub index: u64,
@@ -1,71 +1,161 @@
 //! Replication stream.
 
-use std::io::SeekFrom;
+#[cfg(test)]
+mod payload_size_test;
+
+use std::collections::VecDeque;
+use std::io;
 use std::sync::Arc;
 
 use futures::future::FutureExt;
+use futures::stream::FuturesOrdered;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
-use tokio::io::AsyncRead;
-use tokio::io::AsyncReadExt;
-use tokio::io::AsyncSeek;
-use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
-use tokio::time::interval;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tokio::time::Duration;
-use tokio::time::Interval;
+use tokio::time::Instant;
 use tracing::Instrument;
 use tracing::Span;
 
+use crate::clock::Clock;
+use crate::clock::Ticker;
+use crate::compression;
+use crate::config::Compression;
 use crate::config::Config;
-use crate::config::SnapshotPolicy;
+use crate::config::SnapshotChecksum;
+use crate::config::CAPABILITIES_RETRY_HEARTBEATS;
 use crate::error::LackEntry;
+use crate::error::TransferLeadershipError;
+use crate::log_cache::LogCache;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::Entry;
+use crate::raft::EntryPayload;
 use crate::raft::InstallSnapshotRequest;
+use crate::raft::RaftRespTx;
+use crate::snapshot_stream::snapshot_channel;
+use crate::snapshot_stream::SnapshotChunk;
+use crate::snapshot_stream::SnapshotReceiver;
+use crate::snapshot_stream::SNAPSHOT_STREAM_BUFFER;
 use crate::storage::Snapshot;
+use crate::storage::SnapshotReader;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::MessageSummary;
-use crate::NodeId;
+use crate::RaftError;
 use crate::RaftNetwork;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::ReplicationError;
 
+/// The metrics about replicating to a target node, reported by the leader.
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ReplicationMetrics {
+    /// The last log known to be replicated to the target.
     pub matched: LogId,
+
+    /// The number of logs the target is behind the last log of the leader.
+    pub lag: u64,
+
+    /// The round trip time in milliseconds of the last successful RPC to the target.
+    /// It is `None` if no RPC to the target has succeeded yet.
+    pub last_rpc_latency_ms: Option<u64>,
+
+    /// How the leader replicates to the target.
+    pub state: ReplicationStatus,
+
+    /// The number of AppendEntries the target rejected because `prev_log_id` does not match its log.
+    pub log_conflicts: u64,
+
+    /// The number of AppendEntries the target rejected because it has a greater term.
+    pub term_rejections: u64,
+
+    /// The number of AppendEntries RPCs to the target that failed in a row, e.g., on network errors or timeouts.
+    pub consecutive_failures: u64,
+
+    /// Whether replication to the target is paused with `Raft::pause_replication()`.
+    pub paused: bool,
+
+    /// The format version of the snapshot the target rejected, see `SnapshotMeta::format_version`.
+    ///
+    /// Replication to the target is paused once it rejects a snapshot format, until it is resumed with
+    /// `Raft::resume_replication()`, e.g., after the target is upgraded.
+    pub snapshot_format_rejected: Option<u32>,
 }
 
 impl MessageSummary for ReplicationMetrics {
     fn summary(&self) -> String {
-        format!("{}", self.matched)
+        format!(
+            "{}, lag:{}, latency:{:?}ms, {:?}, conflicts:{}, term_rejections:{}, failures:{}, paused:{}, \
+             snapshot_format_rejected:{:?}",
+            self.matched,
+            self.lag,
+            self.last_rpc_latency_ms,
+            self.state,
+            self.log_conflicts,
+            self.term_rejections,
+            self.consecutive_failures,
+            self.paused,
+            self.snapshot_format_rejected
+        )
+    }
+}
+
+/// How a leader replicates to a target node.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReplicationStatus {
+    /// The target is no more than `Config::replication_lag_threshold` logs behind the leader.
+    LineRate,
+
+    /// The target is more than `Config::replication_lag_threshold` logs behind the leader.
+    Lagging,
+
+    /// The target needs a snapshot, and waits for the leader to finish sending others.
+    ///
+    /// See `Config::max_concurrent_snapshot_sends`.
+    SnapshotQueued,
+
+    /// The leader is sending a snapshot to the target.
+    Snapshotting,
+}
+
+impl Default for ReplicationStatus {
+    fn default() -> Self {
+        ReplicationStatus::LineRate
     }
 }
 
 /// The public handle to a spawned replication stream.
-pub(crate) struct ReplicationStream<D: AppData> {
+pub(crate) struct ReplicationStream<D: AppData, NID: RaftNodeId> {
     /// The spawn handle the `ReplicationCore` task.
     // pub handle: JoinHandle<()>,
     /// The channel used for communicating with the replication task.
-    pub repl_tx: mpsc::UnboundedSender<(RaftEvent<D>, Span)>,
+    pub repl_tx: mpsc::UnboundedSender<(RaftEvent<D, NID>, Span)>,
 }
 
-impl<D: AppData> ReplicationStream<D> {
+impl<D: AppData, NID: RaftNodeId> ReplicationStream<D, NID> {
     /// Create a new replication stream for the target peer.
-    pub(crate) fn new<R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>>(
-        id: NodeId,
-        target: NodeId,
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new<R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>>(
+        id: NID,
+        target: NID,
         term: u64,
         config: Arc<Config>,
         last_log: LogId,
         committed: LogId,
         network: Arc<N>,
         storage: Arc<S>,
-        replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+        log_cache: Arc<LogCache<D, NID>>,
+        replication_tx: mpsc::UnboundedSender<(ReplicaEvent<NID>, Span)>,
+        snapshot_sends: Arc<Semaphore>,
+        clock: Arc<dyn Clock>,
+        witness: bool,
     ) -> Self {
         ReplicationCore::spawn(
             id,
@@ -76,7 +166,11 @@ impl<D: AppData> ReplicationStream<D> {
             committed,
             network,
             storage,
+            log_cache,
             replication_tx,
+            snapshot_sends,
+            clock,
+            witness,
         )
     }
 }
@@ -86,21 +180,27 @@ impl<D: AppData> ReplicationStream<D> {
 /// NOTE: we do not stack replication requests to targets because this could result in
 /// out-of-order delivery. We always buffer until we receive a success response, then send the
 /// next payload from the buffer.
-struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+struct ReplicationCore<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
+> {
     //////////////////////////////////////////////////////////////////////////
     // Static Fields /////////////////////////////////////////////////////////
     /// The ID of this Raft node.
-    id: NodeId,
+    id: NID,
     /// The ID of the target Raft node which replication events are to be sent to.
-    target: NodeId,
+    target: NID,
     /// The current term, which will never change during the lifetime of this task.
     term: u64,
 
     /// A channel for sending events to the Raft node.
-    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<NID>, Span)>,
 
     /// A channel for receiving events from the Raft node.
-    repl_rx: mpsc::UnboundedReceiver<(RaftEvent<D>, Span)>,
+    repl_rx: mpsc::UnboundedReceiver<(RaftEvent<D, NID>, Span)>,
 
     /// The `RaftNetwork` interface.
     network: Arc<N>,
@@ -108,6 +208,9 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     /// The `RaftStorage` interface.
     storage: Arc<S>,
 
+    /// The recent log entries, to read before `storage`.
+    log_cache: Arc<LogCache<D, NID>>,
+
     /// The Raft's runtime config.
     config: Arc<Config>,
 
@@ -138,26 +241,74 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     max_possible_matched_index: u64,
 
     /// The heartbeat interval for ensuring that heartbeats are always delivered in a timely fashion.
-    heartbeat: Interval,
+    heartbeat: Ticker,
+
+    /// The source of time to wait for a retry backoff.
+    clock: Arc<dyn Clock>,
+
+    /// The number of consecutive failed AppendEntries RPCs to the target, reset to 0 by a successful one.
+    retry_attempt: u32,
 
     /// The timeout for sending snapshot segment.
     install_snapshot_timeout: Duration,
+
+    /// The round trip time of the last successful RPC to the target.
+    last_rpc_latency: Option<Duration>,
+
+    /// The number of AppendEntries the target rejected because of a log conflict.
+    log_conflicts: u64,
+
+    /// The number of AppendEntries the target rejected because of a greater term.
+    term_rejections: u64,
+
+    /// The permits to send a snapshot, shared with the other replication streams of the leader.
+    snapshot_sends: Arc<Semaphore>,
+
+    /// The permit held while sending a snapshot. In `Snapshotting` state without it, the stream is queued.
+    snapshot_permit: Option<OwnedSemaphorePermit>,
+
+    /// Whether the target decompresses entries, asked with `RaftNetwork::capabilities()` on the first entries to
+    /// compress. It becomes `false` once the target fails to decompress them.
+    supports_compression: Option<bool>,
+
+    /// When to ask the target for its capabilities again, after failing to, see `CAPABILITIES_RETRY_HEARTBEATS`.
+    capabilities_retry_at: Option<Instant>,
+
+    /// Whether nothing is sent to the target, not even heartbeats, until it is resumed.
+    paused: bool,
+
+    /// Whether the target is a witness, to which the entries are sent without application data.
+    witness: bool,
+
+    /// The progress last reported to the Raft node: if sending a snapshot, if waiting to send one, the RPC latency in
+    /// milliseconds, and the number of consecutive failed RPCs.
+    reported_progress: Option<(bool, bool, Option<u64>, u32)>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    ReplicationCore<D, R, N, S, NID>
+{
     /// Spawn a new replication task for the target node.
-    #[tracing::instrument(level = "trace", skip(config, network, storage, raft_core_tx))]
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(
+        level = "trace",
+        skip(config, network, storage, log_cache, raft_core_tx, snapshot_sends, clock)
+    )]
     pub(self) fn spawn(
-        id: NodeId,
-        target: NodeId,
+        id: NID,
+        target: NID,
         term: u64,
         config: Arc<Config>,
         last_log: LogId,
         committed: LogId,
         network: Arc<N>,
         storage: Arc<S>,
-        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
-    ) -> ReplicationStream<D> {
+        log_cache: Arc<LogCache<D, NID>>,
+        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<NID>, Span)>,
+        snapshot_sends: Arc<Semaphore>,
+        clock: Arc<dyn Clock>,
+        witness: bool,
+    ) -> ReplicationStream<D, NID> {
         // other component to ReplicationStream
         let (repl_tx, repl_rx) = mpsc::unbounded_channel();
         let heartbeat_timeout = Duration::from_millis(config.heartbeat_interval);
@@ -169,6 +320,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             term,
             network,
             storage,
+            log_cache,
             config,
             marker_r: std::marker::PhantomData,
             target_repl_state: TargetReplState::LineRate,
@@ -178,8 +330,20 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             max_possible_matched_index: last_log.index,
             raft_core_tx,
             repl_rx,
-            heartbeat: interval(heartbeat_timeout),
+            heartbeat: Ticker::new(clock.clone(), heartbeat_timeout),
+            clock,
+            retry_attempt: 0,
             install_snapshot_timeout,
+            last_rpc_latency: None,
+            log_conflicts: 0,
+            term_rejections: 0,
+            snapshot_sends,
+            snapshot_permit: None,
+            supports_compression: None,
+            capabilities_retry_at: None,
+            paused: false,
+            witness,
+            reported_progress: None,
         };
 
         let _handle = tokio::spawn(this.main().instrument(tracing::trace_span!("spawn").or_current()));
@@ -190,7 +354,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         }
     }
 
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.id, target=self.target, cluster=%self.config.cluster_name))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.id, target=%self.target, cluster=%self.config.cluster_name))]
     async fn main(mut self) {
         loop {
             // If it returns Ok(), always go back to LineRate state.
@@ -208,6 +372,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 Err(err) => err,
             };
 
+            // Let a queued stream send its snapshot, this one queues again if it retries.
+            let permit = self.snapshot_permit.take();
+            if permit.is_some() {
+                self.report_progress();
+            }
+            drop(permit);
+
             tracing::warn!(error=%err, "error replication to target={}", self.target);
 
             match err {
@@ -246,6 +417,18 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 ReplicationError::Network { .. } => {
                     // nothing to do
                 }
+                ReplicationError::SnapshotFormatUnsupported { format_version } => {
+                    // Sending it again is rejected again, wait for the application to resume the replication.
+                    self.paused = true;
+                    self.set_target_repl_state(TargetReplState::LineRate);
+                    let _ = self.raft_core_tx.send((
+                        ReplicaEvent::SnapshotFormatRejected {
+                            target: self.target,
+                            format_version,
+                        },
+                        tracing::debug_span!("CH"),
+                    ));
+                }
             };
         }
     }
@@ -255,7 +438,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     /// This request will timeout if no response is received within the
     /// configured heartbeat interval.
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn send_append_entries(&mut self) -> Result<(), ReplicationError> {
+    async fn send_append_entries(&mut self) -> Result<(), ReplicationError<NID>> {
         // find the mid position aligning to 8
         let diff = self.max_possible_matched_index - self.matched.index;
         let mut prev_index = self.matched.index + diff / 16 * 8;
@@ -288,7 +471,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             let prev_log_id = if prev_index == first_log_id.index {
                 first_log_id
             } else {
-                let first = self.storage.try_get_log_entry(prev_index).await?;
+                let first = match self.log_cache.get_one(prev_index) {
+                    Some(ent) => Some(ent),
+                    None => self.storage.try_get_log_entry(prev_index).await?,
+                };
                 match first {
                     Some(f) => f.log_id,
                     None => {
@@ -301,7 +487,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             let logs = if start == end {
                 vec![]
             } else {
-                let logs = self.storage.try_get_log_entries(start..end).await?;
+                let logs = match self.log_cache.get(start, end) {
+                    Some(logs) => logs,
+                    None => self.storage.try_get_log_entries(start..end).await?,
+                };
                 if !logs.is_empty() && logs[0].log_id.index > prev_log_id.index + 1 {
                     // There is still chance the first log is removed.
                     // log entry is just deleted after fetching first_log_id.
@@ -309,6 +498,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     continue;
                 }
 
+                let mut logs = logs;
+                if self.witness {
+                    strip_payloads(&mut logs);
+                }
+                logs.truncate(entries_within_size(&logs, self.config.max_payload_bytes));
                 logs
             };
 
@@ -316,29 +510,46 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         };
 
         // Build the heartbeat frame to be sent to the follower.
-        let payload = AppendEntriesRequest {
+        let mut payload = AppendEntriesRequest {
             term: self.term,
             leader_id: self.id,
             prev_log_id,
             leader_commit: self.committed,
             entries: logs,
+            compressed_entries: None,
         };
 
+        self.compress_entries(&mut payload).await;
+
+        let the_timeout = self.config.append_entries_ttl();
+
         // Send the payload.
         tracing::debug!(
             payload=%payload.summary(),
             "start sending append_entries, timeout: {:?}",
-            self.config.heartbeat_interval
+            the_timeout
         );
 
-        let the_timeout = Duration::from_millis(self.config.heartbeat_interval);
+        let start = Instant::now();
+        let sent_at = self.clock.now();
         let res = timeout(the_timeout, self.network.send_append_entries(self.target, payload)).await;
 
         let append_resp = match res {
             Ok(append_res) => match append_res {
-                Ok(res) => res,
+                Ok(res) => {
+                    self.update_rpc_latency(start.elapsed());
+                    res
+                }
                 Err(err) => {
                     tracing::warn!(error=%err, "error sending AppendEntries RPC to target");
+
+                    // E.g., the target accepts a smaller `max_payload_bytes`. Otherwise the same entries would be
+                    // compressed and rejected again forever.
+                    if let Some(RaftError::EntriesDecompression(_)) = err.downcast_ref::<RaftError>() {
+                        tracing::warn!(target=%self.target, "target failed to decompress entries, send uncompressed");
+                        self.supports_compression = Some(false);
+                    }
+
                     return Err(ReplicationError::Network { source: err });
                 }
             },
@@ -354,6 +565,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
         tracing::debug!("append_entries resp: {:?}", append_resp);
 
+        // The target accepts this node as the leader of the term, no matter whether the logs match.
+        if append_resp.term == self.term {
+            self.report_acked(sent_at);
+        }
+
         // Handle success conditions.
         if append_resp.success() {
             let matched = append_resp.matched.unwrap();
@@ -368,6 +584,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         if append_resp.term > self.term {
             tracing::debug!({ append_resp.term }, "append entries failed, reverting to follower");
 
+            self.term_rejections += 1;
+            self.report_rejections();
+
             return Err(ReplicationError::HigherTerm {
                 higher: append_resp.term,
                 mine: self.term,
@@ -385,6 +604,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
         assert_eq!(conflict, prev_log_id, "if conflict, it is always the prev_log_id");
 
+        self.log_conflicts += 1;
+        self.report_rejections();
+
         // Continue to find the matching log id on follower.
         self.max_possible_matched_index = conflict.index - 1;
         Ok(())
@@ -392,7 +614,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
     /// max_possible_matched_index is the least index for `prev_log_id` to form a consecutive log sequence
     #[tracing::instrument(level = "trace", skip(self), fields(max_possible_matched_index=self.max_possible_matched_index))]
-    fn check_consecutive(&self, first_log_index: u64) -> Result<(), ReplicationError> {
+    fn check_consecutive(&self, first_log_index: u64) -> Result<(), ReplicationError<NID>> {
         tracing::debug!(first_log_index, self.max_possible_matched_index, "check_consecutive");
 
         if first_log_index > self.max_possible_matched_index {
@@ -408,6 +630,133 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     fn set_target_repl_state(&mut self, state: TargetReplState) {
         tracing::debug!(?state, "set_target_repl_state");
         self.target_repl_state = state;
+        self.report_progress();
+
+        // Release the permit only after reporting the new state. Thus the stream that takes the permit next is never
+        // reported sending a snapshot before this one is reported done.
+        if self.target_repl_state != TargetReplState::Snapshotting {
+            self.snapshot_permit = None;
+        }
+    }
+
+    /// Compress the entries of `payload` with `Config::replication_compression`, if the target supports it and it
+    /// makes them smaller.
+    async fn compress_entries(&mut self, payload: &mut AppendEntriesRequest<D, NID>) {
+        let codec = self.config.replication_compression;
+        if codec == Compression::None || payload.entries.is_empty() {
+            return;
+        }
+
+        if !self.target_supports_compression().await {
+            return;
+        }
+
+        match compression::compress(codec, &payload.entries, self.config.max_payload_bytes) {
+            Ok(Some(compressed)) => {
+                payload.entries = vec![];
+                payload.compressed_entries = Some(compressed);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(error=%err, "failed to compress entries, send them uncompressed");
+            }
+        }
+    }
+
+    /// Whether the target decompresses entries, from the cache or by asking `RaftNetwork::capabilities()`.
+    ///
+    /// Unlike the other optional RPCs, if the target can not be asked, it is assumed not to support it: entries it
+    /// can not decompress would never be replicated. It is asked again after `CAPABILITIES_RETRY_HEARTBEATS`
+    /// heartbeats.
+    async fn target_supports_compression(&mut self) -> bool {
+        if let Some(supported) = self.supports_compression {
+            return supported;
+        }
+
+        if let Some(retry_at) = self.capabilities_retry_at {
+            if self.clock.now() < retry_at {
+                return false;
+            }
+        }
+
+        let ttl = Duration::from_millis(self.config.heartbeat_interval);
+        let res = timeout(ttl, self.network.capabilities(self.target)).await;
+
+        let err = match res {
+            Ok(Ok(caps)) => {
+                tracing::debug!(target=%self.target, ?caps, "got capabilities");
+                self.supports_compression = Some(caps.compression);
+                self.capabilities_retry_at = None;
+                return caps.compression;
+            }
+            Ok(Err(err)) => err.to_string(),
+            Err(_timeout) => "timeout".to_string(),
+        };
+
+        tracing::warn!(target=%self.target, error=%err, "failed to get capabilities, send uncompressed");
+
+        let retry_after = Duration::from_millis(self.config.heartbeat_interval * CAPABILITIES_RETRY_HEARTBEATS);
+        self.capabilities_retry_at = Some(self.clock.now() + retry_after);
+        false
+    }
+
+    fn update_rpc_latency(&mut self, latency: Duration) {
+        self.last_rpc_latency = Some(latency);
+        self.report_progress();
+    }
+
+    /// Report to RaftCore if this stream is sending or waiting to send a snapshot, the latency of the last RPC, and
+    /// the number of consecutive failed RPCs, if any of them changes.
+    fn report_progress(&mut self) {
+        let (snapshotting, snapshot_queued) = match self.target_repl_state {
+            TargetReplState::LineRate => (false, false),
+            TargetReplState::Snapshotting => (self.snapshot_permit.is_some(), self.snapshot_permit.is_none()),
+            TargetReplState::Shutdown => return,
+        };
+
+        let latency_ms = self.last_rpc_latency.map(|x| x.as_millis() as u64);
+        let progress = Some((snapshotting, snapshot_queued, latency_ms, self.retry_attempt));
+
+        if self.reported_progress == progress {
+            return;
+        }
+        self.reported_progress = progress;
+
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::UpdateProgress {
+                target: self.target,
+                snapshotting,
+                snapshot_queued,
+                last_rpc_latency: self.last_rpc_latency,
+                consecutive_failures: self.retry_attempt as u64,
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
+    /// Report to RaftCore when the last AppendEntries or snapshot chunk accepted by the target was sent, which extends
+    /// the leader lease.
+    fn report_acked(&self, sent_at: Instant) {
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::Acked {
+                target: self.target,
+                sent_at,
+                received_at: self.clock.now(),
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
+    /// Report to RaftCore the number of AppendEntries the target rejected so far.
+    fn report_rejections(&self) {
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::UpdateRejections {
+                target: self.target,
+                log_conflicts: self.log_conflicts,
+                term_rejections: self.term_rejections,
+            },
+            tracing::debug_span!("CH"),
+        ));
     }
 
     /// Update the `matched` and `max_possible_matched_index`, which both are for tracking
@@ -442,23 +791,22 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     /// snapshot is warranted.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(self) fn needs_snapshot(&self) -> bool {
-        match &self.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => {
-                let needs_snap = self
-                    .committed
-                    .index
-                    .checked_sub(self.matched.index)
-                    .map(|diff| diff >= *threshold)
-                    .unwrap_or(false);
-
-                tracing::trace!("snapshot needed: {}", needs_snap);
-                needs_snap
-            }
-        }
+        // A policy without a log count threshold has no lag threshold either.
+        // The replication then only falls back to snapshot when the logs it needs are purged.
+        let threshold = match self.config.snapshot_policy.logs_threshold() {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+
+        let needs_snap =
+            self.committed.index.checked_sub(self.matched.index).map(|diff| diff >= threshold).unwrap_or(false);
+
+        tracing::trace!("snapshot needed: {}", needs_snap);
+        needs_snap
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    pub async fn try_drain_raft_rx(&mut self) -> Result<(), ReplicationError> {
+    pub async fn try_drain_raft_rx(&mut self) -> Result<(), ReplicationError<NID>> {
         tracing::debug!("try_drain_raft_rx");
 
         for _i in 0..self.config.max_payload_entries {
@@ -487,7 +835,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 
     #[tracing::instrument(level = "trace", skip(self), fields(event=%event.summary()))]
-    pub fn process_raft_event(&mut self, event: RaftEvent<D>) -> Result<(), ReplicationError> {
+    pub fn process_raft_event(&mut self, event: RaftEvent<D, NID>) -> Result<(), ReplicationError<NID>> {
         tracing::debug!(event=%event.summary(), "process_raft_event");
 
         match event {
@@ -503,6 +851,21 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 self.last_log_index = entry.log_id.index;
             }
 
+            RaftEvent::UpdateConfig { config } => {
+                // Heartbeats are sent at the new interval from now on.
+                self.heartbeat.reset(Duration::from_millis(config.heartbeat_interval));
+                self.install_snapshot_timeout = Duration::from_millis(config.install_snapshot_timeout);
+                self.config = config;
+            }
+
+            RaftEvent::SetPaused { paused } => {
+                self.paused = paused;
+            }
+
+            RaftEvent::TargetIsWitness => {
+                self.witness = true;
+            }
+
             RaftEvent::Terminate => {
                 tracing::debug!("received: RaftEvent::Terminate");
                 // TODO(xp): just close the channel to shut replication down.
@@ -515,6 +878,32 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 }
 
+/// Returns the number of leading entries whose total size is within `max_bytes`, see `AppData::size_hint()`.
+///
+/// The first entry is always included, no matter how large it is, so that replication always makes progress.
+pub(crate) fn entries_within_size<D: AppData, NID: RaftNodeId>(entries: &[Entry<D, NID>], max_bytes: u64) -> usize {
+    let mut total = 0;
+
+    for (i, entry) in entries.iter().enumerate() {
+        total += entry.size_hint();
+        if i > 0 && total > max_bytes {
+            return i;
+        }
+    }
+
+    entries.len()
+}
+
+/// Replace the application data of the entries with blank payloads, for a witness keeps only the log ids and the
+/// membership configs.
+fn strip_payloads<D: AppData, NID: RaftNodeId>(entries: &mut [Entry<D, NID>]) {
+    for entry in entries.iter_mut() {
+        if let EntryPayload::Normal(_) = entry.payload {
+            entry.payload = EntryPayload::Blank;
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// The state of the replication stream.
@@ -530,13 +919,13 @@ enum TargetReplState {
 
 // TODO(xp): remove Replicate
 /// An event from the Raft node.
-pub(crate) enum RaftEvent<D: AppData> {
+pub(crate) enum RaftEvent<D: AppData, NID: RaftNodeId> {
     Replicate {
         /// The new entry which needs to be replicated.
         ///
         /// This entry will always be the most recent entry to have been appended to the log, so its
         /// index is the new last_log_index value.
-        entry: Arc<Entry<D>>,
+        entry: Arc<Entry<D, NID>>,
 
         /// The index of the highest log entry which is known to be committed in the cluster.
         committed: LogId,
@@ -546,10 +935,20 @@ pub(crate) enum RaftEvent<D: AppData> {
         /// The index of the highest log entry which is known to be committed in the cluster.
         committed: LogId,
     },
+    /// A message from Raft indicating the config is updated.
+    UpdateConfig {
+        config: Arc<Config>,
+    },
+    /// A message from Raft to stop or start sending AppendEntries to the target.
+    SetPaused {
+        paused: bool,
+    },
+    /// A message from Raft indicating the target is a witness: no application data is sent to it from now on.
+    TargetIsWitness,
     Terminate,
 }
 
-impl<D: AppData> MessageSummary for RaftEvent<D> {
+impl<D: AppData, NID: RaftNodeId> MessageSummary for RaftEvent<D, NID> {
     fn summary(&self) -> String {
         match self {
             RaftEvent::Replicate { entry: _, committed } => {
@@ -560,41 +959,89 @@ impl<D: AppData> MessageSummary for RaftEvent<D> {
             } => {
                 format!("UpdateCommitIndex: commit_index: {}", commit_index)
             }
+            RaftEvent::UpdateConfig { .. } => "UpdateConfig".to_string(),
+            RaftEvent::SetPaused { paused } => format!("SetPaused: {}", paused),
+            RaftEvent::TargetIsWitness => "TargetIsWitness".to_string(),
             RaftEvent::Terminate => "Terminate".to_string(),
         }
     }
 }
 
 /// An event coming from a replication stream.
-pub(crate) enum ReplicaEvent<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
-{
+pub(crate) enum ReplicaEvent<NID: RaftNodeId> {
     /// An event from a replication stream which updates the target node's match index.
     UpdateMatched {
         /// The ID of the target node for which the match index is to be updated.
-        target: NodeId,
+        target: NID,
         /// The log of the most recent log known to have been successfully replicated on the target.
         matched: LogId,
     },
+    /// An event from a replication stream which reports if it is sending a snapshot and the latency of RPCs.
+    UpdateProgress {
+        /// The ID of the target node of the replication stream.
+        target: NID,
+        /// Whether the replication stream is sending a snapshot.
+        snapshotting: bool,
+        /// Whether the replication stream is waiting for other snapshot sendings to finish.
+        snapshot_queued: bool,
+        /// The round trip time of the last successful RPC to the target.
+        last_rpc_latency: Option<Duration>,
+        /// The number of AppendEntries RPCs to the target that failed in a row.
+        consecutive_failures: u64,
+    },
+    /// An event from a replication stream which reports the target accepted an AppendEntries of this term.
+    Acked {
+        /// The ID of the target node of the replication stream.
+        target: NID,
+        /// When the accepted AppendEntries was sent.
+        sent_at: Instant,
+        /// When the response to it was received.
+        received_at: Instant,
+    },
+    /// An event from a replication stream which reports the number of AppendEntries rejected by the target.
+    UpdateRejections {
+        /// The ID of the target node of the replication stream.
+        target: NID,
+        /// The number of rejections because of a log conflict.
+        log_conflicts: u64,
+        /// The number of rejections because of a greater term.
+        term_rejections: u64,
+    },
     /// An event indicating that the Raft node needs to revert to follower state.
     RevertToFollower {
         /// The ID of the target node from which the new term was observed.
-        target: NodeId,
+        target: NID,
         /// The new term observed.
         term: u64,
     },
     /// An event from a replication stream requesting snapshot info.
     NeedsSnapshot {
         /// The ID of the target node from which the event was sent.
-        target: NodeId,
+        target: NID,
         /// The response channel for delivering the snapshot data.
-        tx: oneshot::Sender<Snapshot<S>>,
+        tx: oneshot::Sender<Snapshot<SnapshotReader>>,
+    },
+    /// An event reporting the TimeoutNow to the target of a leadership transfer could not be sent.
+    TimeoutNowFailed {
+        /// The ID of the target node of the TimeoutNow.
+        target: NID,
+        /// The error to respond with.
+        error: TransferLeadershipError<NID>,
+        /// The channel to respond to the leadership transfer request, if there is one.
+        tx: Option<RaftRespTx<NID, TransferLeadershipError<NID>>>,
+    },
+    /// An event reporting the target rejected the format version of the snapshot sent to it.
+    SnapshotFormatRejected {
+        /// The ID of the target node of the replication stream.
+        target: NID,
+        /// The format version of the rejected snapshot.
+        format_version: u32,
     },
     /// Some critical error has taken place, and Raft needs to shutdown.
     Shutdown,
 }
 
-impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for ReplicaEvent<S> {
+impl<NID: RaftNodeId> MessageSummary for ReplicaEvent<NID> {
     fn summary(&self) -> String {
         match self {
             ReplicaEvent::UpdateMatched {
@@ -603,21 +1050,72 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
             } => {
                 format!("UpdateMatchIndex: target: {}, matched: {}", target, matched)
             }
+            ReplicaEvent::UpdateProgress {
+                ref target,
+                ref snapshotting,
+                ref snapshot_queued,
+                ref last_rpc_latency,
+                ref consecutive_failures,
+            } => {
+                format!(
+                    "UpdateProgress: target: {}, snapshotting: {}, snapshot_queued: {}, last_rpc_latency: {:?}, \
+                     consecutive_failures: {}",
+                    target, snapshotting, snapshot_queued, last_rpc_latency, consecutive_failures
+                )
+            }
+            ReplicaEvent::Acked {
+                ref target,
+                ref sent_at,
+                ref received_at,
+            } => {
+                format!(
+                    "Acked: target: {}, sent_at: {:?}, received_at: {:?}",
+                    target, sent_at, received_at
+                )
+            }
+            ReplicaEvent::UpdateRejections {
+                ref target,
+                ref log_conflicts,
+                ref term_rejections,
+            } => {
+                format!(
+                    "UpdateRejections: target: {}, log_conflicts: {}, term_rejections: {}",
+                    target, log_conflicts, term_rejections
+                )
+            }
             ReplicaEvent::RevertToFollower { ref target, ref term } => {
                 format!("RevertToFollower: target: {}, term: {}", target, term)
             }
             ReplicaEvent::NeedsSnapshot { ref target, .. } => {
                 format!("NeedsSnapshot: target: {}", target)
             }
+            ReplicaEvent::TimeoutNowFailed { ref target, .. } => {
+                format!("TimeoutNowFailed: target: {}", target)
+            }
+            ReplicaEvent::SnapshotFormatRejected {
+                ref target,
+                ref format_version,
+            } => {
+                format!(
+                    "SnapshotFormatRejected: target: {}, format_version: {}",
+                    target, format_version
+                )
+            }
             ReplicaEvent::Shutdown => "Shutdown".to_string(),
         }
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID>, S: RaftStorage<D, R, NID>, NID: RaftNodeId>
+    ReplicationCore<D, R, N, S, NID>
+{
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn line_rate_loop(&mut self) -> Result<(), ReplicationError> {
+    pub async fn line_rate_loop(&mut self) -> Result<(), ReplicationError<NID>> {
         loop {
+            if self.paused {
+                self.wait_for_resume().await?;
+            }
+
             loop {
                 tracing::debug!(
                     "current matched: {} send_prev_log_index: {}",
@@ -630,12 +1128,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 if let Err(err) = res {
                     tracing::error!(error=%err, "error replication to target={}", self.target);
 
-                    // For transport error, just keep retrying.
+                    // For transport error, just keep retrying, after a backoff.
                     match err {
                         ReplicationError::Timeout { .. } => {
+                            self.retry_attempt = self.retry_attempt.saturating_add(1);
+                            self.report_progress();
                             break;
                         }
                         ReplicationError::Network { .. } => {
+                            self.retry_attempt = self.retry_attempt.saturating_add(1);
+                            self.report_progress();
                             break;
                         }
                         _ => {
@@ -644,6 +1146,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     }
                 }
 
+                if self.retry_attempt > 0 {
+                    self.retry_attempt = 0;
+                    self.report_progress();
+                }
+
                 if self.matched.index == self.max_possible_matched_index {
                     break;
                 }
@@ -657,6 +1164,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 });
             }
 
+            if self.retry_attempt > 0 {
+                self.wait_for_retry().await?;
+                continue;
+            }
+
             let span = tracing::debug_span!("CHrx:LineRate");
             let _en = span.enter();
 
@@ -682,20 +1194,115 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         }
     }
 
+    /// Wait for a jittered exponential backoff before retrying a failed RPC to the target.
+    ///
+    /// Events from the Raft node are still processed meanwhile, but nothing is sent until the delay passes.
+    #[tracing::instrument(level = "debug", skip(self), fields(retry_attempt = self.retry_attempt))]
+    async fn wait_for_retry(&mut self) -> Result<(), ReplicationError<NID>> {
+        let delay = self.config.new_rand_replication_retry_delay(self.retry_attempt - 1);
+        tracing::debug!(?delay, "backoff before retrying replication to target={}", self.target);
+
+        let deadline = self.clock.now() + delay;
+        loop {
+            tokio::select! {
+                _ = self.clock.sleep_until(deadline) => {
+                    return Ok(());
+                }
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => self.process_raft_event(event)?,
+                        None => {
+                            tracing::debug!("received: RaftEvent::Terminate: closed");
+                            return Err(ReplicationError::Closed);
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait until replication to the target is resumed, without sending anything meanwhile.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn wait_for_resume(&mut self) -> Result<(), ReplicationError<NID>> {
+        tracing::info!("replication to target={} is paused", self.target);
+
+        while self.paused {
+            match self.repl_rx.recv().await {
+                Some((event, _span)) => self.process_raft_event(event)?,
+                None => {
+                    tracing::debug!("received: RaftEvent::Terminate: closed");
+                    return Err(ReplicationError::Closed);
+                }
+            }
+        }
+
+        tracing::info!("replication to target={} is resumed", self.target);
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self), fields(state = "snapshotting"))]
-    pub async fn replicate_snapshot(&mut self) -> Result<(), ReplicationError> {
+    pub async fn replicate_snapshot(&mut self) -> Result<(), ReplicationError<NID>> {
+        self.wait_for_snapshot_send_permit().await?;
+
         let snapshot = self.wait_for_snapshot().await?;
         self.stream_snapshot(snapshot).await?;
 
         Ok(())
     }
 
+    /// Wait until the leader sends fewer snapshots than `Config::max_concurrent_snapshot_sends`.
+    ///
+    /// While waiting, it keeps sending heartbeats to the target and handling events from the Raft node.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn wait_for_snapshot_send_permit(&mut self) -> Result<(), ReplicationError<NID>> {
+        let acquire = self.snapshot_sends.clone().acquire_owned();
+        tokio::pin!(acquire);
+
+        loop {
+            tokio::select! {
+                permit = &mut acquire => {
+                    // The semaphore is never closed.
+                    self.snapshot_permit = Some(permit.unwrap());
+                    self.report_progress();
+                    return Ok(());
+                },
+
+                _ = self.heartbeat.tick() => {
+                    self.send_heartbeat_while_waiting().await?;
+                },
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => {
+                            self.process_raft_event(event)?;
+                            self.try_drain_raft_rx().await?
+                        },
+                        None => {
+                            tracing::info!("repl_rx is closed");
+                            return Err(ReplicationError::Closed);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Send a heartbeat while waiting for a snapshot, only a storage or IO error stops the waiting.
+    async fn send_heartbeat_while_waiting(&mut self) -> Result<(), ReplicationError<NID>> {
+        match self.send_append_entries().await {
+            Err(err @ ReplicationError::StorageError(_)) => Err(err),
+            Err(err @ ReplicationError::IO { .. }) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
     /// Wait for a response from the storage layer for the current snapshot.
     ///
     /// If an error comes up during processing, this routine should simple be called again after
     /// issuing a new request to the storage layer.
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn wait_for_snapshot(&mut self) -> Result<Snapshot<S::SnapshotData>, ReplicationError> {
+    async fn wait_for_snapshot(&mut self) -> Result<Snapshot<SnapshotReader>, ReplicationError<NID>> {
         // Ask raft core for a snapshot.
         // - If raft core has a ready snapshot, it sends back through tx.
         // - Otherwise raft core starts a new task taking snapshot, and **close** `tx` when finished. Thus there has to
@@ -722,26 +1329,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             while waiting_for_snapshot {
                 tokio::select! {
                     _ = self.heartbeat.tick() => {
-                        // TODO(xp): just heartbeat:
-                        let res = self.send_append_entries().await;
-                        match res {
-                            Ok(_) => {
-                                //
-                            },
-                            Err(err) => {
-                                match err {
-                                    ReplicationError::StorageError(_) => {
-                                        return Err(err);
-                                    },
-                                    ReplicationError::IO {..} => {
-                                        return Err(err);
-                                    }
-                                    _=> {
-                                        // nothing to do
-                                    }
-                                }
-                            }
-                        }
+                        self.send_heartbeat_while_waiting().await?;
                     },
 
                     event_span = self.repl_rx.recv() =>  {
@@ -778,54 +1366,136 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
-    async fn stream_snapshot(&mut self, mut snapshot: Snapshot<S::SnapshotData>) -> Result<(), ReplicationError> {
-        let end = snapshot.snapshot.seek(SeekFrom::End(0)).await?;
+    async fn stream_snapshot(&mut self, snapshot: Snapshot<SnapshotReader>) -> Result<(), ReplicationError<NID>> {
+        let Snapshot {
+            meta,
+            snapshot: mut data,
+        } = snapshot;
+
+        // The snapshot is read in another task, at most SNAPSHOT_STREAM_BUFFER chunks ahead of the chunk being sent.
+        // Thus a slow target does not make the leader read the whole snapshot into memory.
+        let (sender, mut receiver) = snapshot_channel(SNAPSHOT_STREAM_BUFFER);
+        let chunk_size = self.config.snapshot_max_chunk_size as usize;
+        let mut reader = tokio::spawn(
+            async move { sender.send_from(data.as_mut(), chunk_size).await }
+                .instrument(tracing::debug_span!("read_snapshot")),
+        );
 
-        let mut offset = 0;
+        // The checksum is known only when every chunk is read, thus it is attached to the last chunk.
+        let mut hasher = match self.config.snapshot_checksum {
+            SnapshotChecksum::None => None,
+            SnapshotChecksum::Crc32 => Some(crc32fast::Hasher::new()),
+        };
+
+        // Up to `snapshot_ack_every_n_chunks` chunks are in flight at once. A sent chunk is kept until it is
+        // acknowledged: if a chunk fails, every chunk from it on is sent again.
+        let window = self.config.snapshot_ack_every_n_chunks as usize;
+        let network = self.network.clone();
+        let network = network.as_ref();
+        let target = self.target;
+        let rpc_timeout = self.install_snapshot_timeout;
 
-        let mut buf = Vec::with_capacity(self.config.snapshot_max_chunk_size as usize);
+        let mut in_flight = FuturesOrdered::new();
+        let mut unacked: VecDeque<InstallSnapshotRequest<NID>> = VecDeque::new();
+        let mut pending: VecDeque<InstallSnapshotRequest<NID>> = VecDeque::new();
+        let mut read_all = false;
 
         loop {
-            // Build the RPC.
-            snapshot.snapshot.seek(SeekFrom::Start(offset)).await?;
-            let n_read = snapshot.snapshot.read_buf(&mut buf).await?;
-
-            let done = (offset + n_read as u64) == end; // If bytes read == 0, then we're done.
-            let req = InstallSnapshotRequest {
-                term: self.term,
-                leader_id: self.id,
-                meta: snapshot.meta.clone(),
-                offset,
-                data: Vec::from(&buf[..n_read]),
-                done,
-            };
-            buf.clear();
+            // Fill the window. The first chunk opens the snapshot stream on the target, thus it is sent alone. The
+            // response to the last chunk tells the snapshot is installed, thus it is sent only when every chunk before
+            // it is acknowledged. The target keeps the chunks between them that arrive out of order.
+            while unacked.len() < window {
+                if matches!(unacked.front(), Some(req) if req.offset == 0) {
+                    break;
+                }
 
-            // Send the RPC over to the target.
-            tracing::debug!(
-                snapshot_size = req.data.len(),
-                req.offset,
-                end,
-                req.done,
-                "sending snapshot chunk"
-            );
+                if pending.is_empty() && !read_all {
+                    let chunk = Self::recv_snapshot_chunk(&mut receiver, &mut reader).await?;
+                    let checksum = Self::update_snapshot_checksum(&mut hasher, &chunk);
+                    read_all = chunk.done;
+
+                    let mut meta = meta.clone();
+                    if checksum.is_some() {
+                        meta.checksum = checksum;
+                    }
+
+                    pending.push_back(InstallSnapshotRequest {
+                        term: self.term,
+                        leader_id: self.id,
+                        meta,
+                        offset: chunk.offset,
+                        data: chunk.data,
+                        done: chunk.done,
+                    });
+                }
+
+                let req = match pending.pop_front() {
+                    Some(req) if req.done && !unacked.is_empty() => {
+                        pending.push_front(req);
+                        break;
+                    }
+                    Some(req) => req,
+                    None => break,
+                };
+
+                // Send the RPC over to the target.
+                tracing::debug!(
+                    snapshot_size = req.data.len(),
+                    req.offset,
+                    req.done,
+                    in_flight = unacked.len() + 1,
+                    "sending snapshot chunk"
+                );
+
+                let rpc = req.clone();
+                let sent_at = self.clock.now();
+                in_flight.push_back(async move {
+                    let start = Instant::now();
+                    let res = timeout(rpc_timeout, network.send_install_snapshot(target, rpc)).await;
+                    (start, sent_at, res)
+                });
+                unacked.push_back(req);
+            }
 
-            let res = timeout(
-                self.install_snapshot_timeout,
-                self.network.send_install_snapshot(self.target, req),
-            )
-            .await;
+            // Wait for the response to the first chunk not acknowledged.
+            let (start, sent_at, res) = match in_flight.next().await {
+                Some(x) => x,
+                None => unreachable!("the window is not empty until the last chunk is acknowledged"),
+            };
 
             let res = match res {
                 Ok(outer_res) => match outer_res {
-                    Ok(res) => res,
+                    Ok(res) => {
+                        self.update_rpc_latency(start.elapsed());
+                        res
+                    }
                     Err(err) => {
                         tracing::warn!(error=%err, "error sending InstallSnapshot RPC to target");
+
+                        match err.downcast_ref::<RaftError>() {
+                            // The target discarded the corrupted snapshot, send it again from the beginning.
+                            Some(RaftError::SnapshotChecksumMismatch { .. }) => {
+                                return Err(ReplicationError::Network { source: err });
+                            }
+                            // The target will never install it.
+                            Some(RaftError::SnapshotFormatUnsupported { format_version, .. }) => {
+                                return Err(ReplicationError::SnapshotFormatUnsupported {
+                                    format_version: *format_version,
+                                });
+                            }
+                            _ => {}
+                        }
+
+                        in_flight = FuturesOrdered::new();
+                        pending = unacked.drain(..).chain(pending.drain(..)).collect();
                         continue;
                     }
                 },
                 Err(err) => {
                     tracing::warn!(error=%err, "timeout while sending InstallSnapshot RPC to target");
+
+                    in_flight = FuturesOrdered::new();
+                    pending = unacked.drain(..).chain(pending.drain(..)).collect();
                     continue;
                 }
             };
@@ -838,24 +1508,58 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 });
             }
 
+            // The target is busy receiving the snapshot but alive, and still accepts this node as the leader.
+            if res.term == self.term {
+                self.report_acked(sent_at);
+            }
+
             // If we just sent the final chunk of the snapshot, then transition to lagging state.
-            if done {
+            if matches!(unacked.pop_front(), Some(req) if req.done) {
                 tracing::debug!(
                     "done install snapshot: snapshot last_log_id: {}, matched: {}",
-                    snapshot.meta.last_log_id,
+                    meta.last_log_id,
                     self.matched,
                 );
 
-                self.update_matched(snapshot.meta.last_log_id);
+                self.update_matched(meta.last_log_id);
 
                 return Ok(());
             }
 
-            // Everything is good, so update offset for sending the next chunk.
-            offset += n_read as u64;
-
             // Check raft channel to ensure we are staying up-to-date, then loop.
             self.try_drain_raft_rx().await?;
         }
     }
+
+    /// Feed a chunk to the snapshot checksum, and return the checksum if it is the last chunk.
+    fn update_snapshot_checksum(hasher: &mut Option<crc32fast::Hasher>, chunk: &SnapshotChunk) -> Option<u32> {
+        let h = hasher.as_mut()?;
+        h.update(&chunk.data);
+
+        if chunk.done {
+            hasher.take().map(|h| h.finalize())
+        } else {
+            None
+        }
+    }
+
+    /// Receive the next chunk read from the snapshot, or the error that stops reading it.
+    async fn recv_snapshot_chunk(
+        receiver: &mut SnapshotReceiver,
+        reader: &mut JoinHandle<io::Result<()>>,
+    ) -> Result<SnapshotChunk, ReplicationError<NID>> {
+        if let Some(chunk) = receiver.recv().await {
+            return Ok(chunk);
+        }
+
+        // The reader quits before sending the last chunk, because of an error.
+        let res = reader.await.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        res?;
+
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "snapshot reader quits before the last chunk",
+        )
+        .into())
+    }
 }
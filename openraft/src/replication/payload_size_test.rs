@@ -0,0 +1,62 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::replication::entries_within_size;
+use crate::AppData;
+use crate::LogId;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Data(String);
+
+impl AppData for Data {
+    fn size_hint(&self) -> u64 {
+        self.0.len() as u64
+    }
+}
+
+/// Build `n` entries of the same size.
+fn entries(n: u64, data_size: usize) -> Vec<Entry<Data>> {
+    (10..10 + n)
+        .map(|i| Entry {
+            log_id: LogId { term: 1, index: i },
+            payload: EntryPayload::Normal(Data("x".repeat(data_size))),
+            request_id: None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_entries_within_size() -> anyhow::Result<()> {
+    let ents = entries(10, 100);
+    let size = ents[0].size_hint();
+
+    assert_eq!(10, entries_within_size(&ents, u64::MAX), "unlimited");
+    assert_eq!(10, entries_within_size(&ents, size * 10), "exactly all");
+    assert_eq!(9, entries_within_size(&ents, size * 10 - 1), "one byte less");
+    assert_eq!(
+        3,
+        entries_within_size(&ents, size * 3 + size / 2),
+        "partial entry excluded"
+    );
+    assert_eq!(1, entries_within_size(&ents, 1), "at least one entry");
+    assert_eq!(0, entries_within_size(&entries(0, 100), 1), "empty");
+
+    Ok(())
+}
+
+#[test]
+fn test_entries_within_size_and_count() -> anyhow::Result<()> {
+    // The payload is bounded by whichever of max_payload_entries and max_payload_bytes is reached first.
+    let max_entries = 5;
+    let ents = entries(20, 100);
+    let size = ents[0].size_hint();
+
+    let bounded = |max_bytes: u64| entries_within_size(&ents[..max_entries], max_bytes);
+
+    assert_eq!(5, bounded(size * 8), "count limit is smaller");
+    assert_eq!(2, bounded(size * 2), "size limit is smaller");
+
+    Ok(())
+}
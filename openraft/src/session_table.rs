@@ -0,0 +1,115 @@
+//! The responses to the recent write requests of every client, for a state machine to apply a retried request once.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::raft::Entry;
+use crate::AppData;
+use crate::RaftNodeId;
+
+/// The requests of one client a state machine has applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Session<R> {
+    /// The index of the last entry applied for this client.
+    last_index: u64,
+
+    /// The responses to the most recent applied requests, by sequence number.
+    responses: BTreeMap<u64, R>,
+}
+
+/// Remembers the responses to the write requests with an id, see `ClientWriteRequest::with_request_id()`, so that a
+/// state machine applies a retried request only once.
+///
+/// The id of a request is written into its log entry, thus every node applying the log makes the same decision, and
+/// a request retried to a new leader is still recognized. The table is part of the state machine: a
+/// `RaftStorage` keeps it along with the state machine data and includes it in a snapshot.
+///
+/// In `RaftStorage::apply_to_state_machine()`, look up an entry with `get()` before applying it, and `insert()` the
+/// response after applying it.
+///
+/// A client keeps at most `window` responses. A client that sends no request with an id for `expire_after` log
+/// entries is forgotten. Both are counted in the log thus the table is the same on every node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientSessionTable<R> {
+    window: u64,
+    expire_after: u64,
+    sessions: BTreeMap<String, Session<R>>,
+
+    /// The client ids by the index of the last entry applied for it, to find the expired sessions.
+    by_last_index: BTreeMap<u64, String>,
+}
+
+impl<R: Clone> ClientSessionTable<R> {
+    pub fn new(window: u64, expire_after: u64) -> Self {
+        Self {
+            window,
+            expire_after,
+            sessions: BTreeMap::new(),
+            by_last_index: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the remembered response if the request of `entry` has been applied.
+    ///
+    /// It also forgets the clients that expired by the index of `entry`.
+    pub fn get<D: AppData, NID: RaftNodeId>(&mut self, entry: &Entry<D, NID>) -> Option<R> {
+        self.expire(entry.log_id.index);
+
+        let id = entry.request_id.as_ref()?;
+        let session = self.sessions.get(&id.client_id)?;
+        session.responses.get(&id.sequence).cloned()
+    }
+
+    /// Remember the response to the applied request of `entry`, if it has an id.
+    pub fn insert<D: AppData, NID: RaftNodeId>(&mut self, entry: &Entry<D, NID>, response: R) {
+        let id = match &entry.request_id {
+            None => return,
+            Some(x) => x,
+        };
+
+        if self.window == 0 {
+            return;
+        }
+
+        let index = entry.log_id.index;
+
+        let session = self.sessions.entry(id.client_id.clone()).or_insert_with(|| Session {
+            last_index: index,
+            responses: BTreeMap::new(),
+        });
+
+        self.by_last_index.remove(&session.last_index);
+        self.by_last_index.insert(index, id.client_id.clone());
+        session.last_index = index;
+
+        session.responses.insert(id.sequence, response);
+        while session.responses.len() as u64 > self.window {
+            let oldest = *session.responses.keys().next().unwrap();
+            session.responses.remove(&oldest);
+        }
+    }
+
+    /// The number of clients remembered.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Forget the clients whose last request is `expire_after` or more entries before `index`.
+    fn expire(&mut self, index: u64) {
+        while let Some((&last_index, _)) = self.by_last_index.iter().next() {
+            if last_index + self.expire_after > index {
+                break;
+            }
+
+            let client_id = self.by_last_index.remove(&last_index).unwrap();
+            tracing::debug!(%client_id, last_index, index, "client session expired");
+            self.sessions.remove(&client_id);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::raft::ClientRequestId;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
+use crate::AppData;
+use crate::ClientSessionTable;
+use crate::LogId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Data(String);
+
+impl AppData for Data {}
+
+fn ent(index: u64, client_id: &str, sequence: u64) -> Entry<Data> {
+    Entry {
+        log_id: LogId { term: 1, index },
+        payload: EntryPayload::Normal(Data(format!("{}-{}", client_id, sequence))),
+        request_id: Some(ClientRequestId {
+            client_id: client_id.to_string(),
+            sequence,
+        }),
+    }
+}
+
+#[test]
+fn test_client_session_table_window() -> anyhow::Result<()> {
+    let mut table = ClientSessionTable::<u64>::new(2, 100);
+
+    assert_eq!(None, table.get(&ent(1, "foo", 1)));
+    table.insert(&ent(1, "foo", 1), 10);
+
+    assert_eq!(
+        Some(10),
+        table.get(&ent(2, "foo", 1)),
+        "a retry is recognized by its id"
+    );
+    assert_eq!(
+        None,
+        table.get(&ent(2, "bar", 1)),
+        "clients number requests on their own"
+    );
+
+    table.insert(&ent(3, "foo", 2), 20);
+    table.insert(&ent(4, "foo", 3), 30);
+
+    assert_eq!(None, table.get(&ent(5, "foo", 1)), "older than the window");
+    assert_eq!(Some(20), table.get(&ent(5, "foo", 2)));
+    assert_eq!(Some(30), table.get(&ent(5, "foo", 3)));
+
+    tracing::info!("--- an entry without id is never remembered");
+    {
+        let mut e = ent(6, "foo", 4);
+        e.request_id = None;
+        table.insert(&e, 40);
+        assert_eq!(None, table.get(&e));
+        assert_eq!(1, table.len());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_client_session_table_expire() -> anyhow::Result<()> {
+    let mut table = ClientSessionTable::<u64>::new(2, 10);
+
+    table.insert(&ent(1, "foo", 1), 10);
+    table.insert(&ent(5, "bar", 1), 50);
+    assert_eq!(2, table.len());
+
+    assert_eq!(Some(10), table.get(&ent(10, "foo", 1)));
+    assert_eq!(2, table.len());
+
+    assert_eq!(None, table.get(&ent(11, "foo", 1)), "foo expired after 10 entries");
+    assert_eq!(1, table.len());
+
+    table.insert(&ent(14, "bar", 2), 140);
+    assert_eq!(
+        Some(50),
+        table.get(&ent(20, "bar", 1)),
+        "a new request keeps a client alive"
+    );
+
+    assert_eq!(None, table.get(&ent(24, "bar", 1)));
+    assert!(table.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Stream snapshot data in chunks through a bounded buffer.
+//!
+//! A `SnapshotSender` produces chunks of a snapshot, e.g., by reading a snapshot to send to a follower, and a
+//! `SnapshotReceiver` consumes them, e.g., by writing them to a snapshot being installed.
+//! The buffer between them holds at most `capacity` chunks: if the receiving side can not keep up, the sending side
+//! pauses, instead of buffering the whole snapshot in memory.
+
+use std::io;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// The number of chunks buffered between reading a snapshot and sending it, or between receiving and writing it.
+pub(crate) const SNAPSHOT_STREAM_BUFFER: usize = 2;
+
+/// A segment of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChunk {
+    /// The byte offset where this chunk is positioned in the snapshot.
+    pub offset: u64,
+
+    /// The raw bytes of this chunk, starting at `offset`.
+    pub data: Vec<u8>,
+
+    /// Will be `true` if this is the last chunk of the snapshot.
+    pub done: bool,
+}
+
+/// Create a snapshot stream which buffers at most `capacity` chunks.
+pub fn snapshot_channel(capacity: usize) -> (SnapshotSender, SnapshotReceiver) {
+    let (tx, rx) = mpsc::channel(capacity);
+    (SnapshotSender { tx }, SnapshotReceiver { rx })
+}
+
+/// The sending half of a snapshot stream.
+pub struct SnapshotSender {
+    tx: mpsc::Sender<SnapshotChunk>,
+}
+
+impl SnapshotSender {
+    /// Send a chunk to the receiver.
+    ///
+    /// If the buffer is full, it waits until the receiver consumes a chunk.
+    /// It returns an error if the receiver is dropped.
+    pub async fn send(&self, chunk: SnapshotChunk) -> io::Result<()> {
+        self.tx
+            .send(chunk)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "snapshot receiver is closed"))
+    }
+
+    /// Read a snapshot to its end and send it in chunks of `chunk_size` bytes, the last one may be smaller.
+    ///
+    /// The snapshot is read only as fast as the receiver consumes the chunks.
+    /// The reader does not have to be seekable: one chunk is read ahead to tell if the current one is the last.
+    pub async fn send_from<R>(&self, reader: &mut R, chunk_size: usize) -> io::Result<()>
+    where R: AsyncRead + Unpin + ?Sized {
+        let mut offset = 0;
+        let mut data = read_chunk(reader, chunk_size).await?;
+
+        loop {
+            // A chunk shorter than `chunk_size` is read only when the reader reaches its end.
+            let next = if data.len() < chunk_size {
+                vec![]
+            } else {
+                read_chunk(reader, chunk_size).await?
+            };
+
+            let n_read = data.len() as u64;
+            let done = next.is_empty();
+
+            self.send(SnapshotChunk { offset, data, done }).await?;
+
+            if done {
+                return Ok(());
+            }
+
+            offset += n_read;
+            data = next;
+        }
+    }
+}
+
+/// Read at most `chunk_size` bytes, fewer only if the reader reaches its end.
+async fn read_chunk<R>(reader: &mut R, chunk_size: usize) -> io::Result<Vec<u8>>
+where R: AsyncRead + Unpin + ?Sized {
+    let mut data = Vec::with_capacity(chunk_size);
+    (&mut *reader).take(chunk_size as u64).read_to_end(&mut data).await?;
+    Ok(data)
+}
+
+/// The receiving half of a snapshot stream.
+pub struct SnapshotReceiver {
+    rx: mpsc::Receiver<SnapshotChunk>,
+}
+
+impl SnapshotReceiver {
+    /// Receive the next chunk, or `None` if the sender is dropped.
+    pub async fn recv(&mut self) -> Option<SnapshotChunk> {
+        self.rx.recv().await
+    }
+
+    /// Write every received chunk to `writer` sequentially, until the last chunk is written, and return the CRC32 of
+    /// the written data.
+    ///
+    /// A chunk that is resent, e.g. because the response to it is lost, is written only from where the writer is.
+    /// It returns an error if a chunk starts after the end of the written data, or if the sender is dropped before
+    /// sending the last chunk.
+    pub async fn write_to<W>(&mut self, writer: &mut W) -> io::Result<u32>
+    where W: AsyncWrite + Unpin + ?Sized {
+        let mut offset = 0;
+        let mut hasher = crc32fast::Hasher::new();
+
+        while let Some(chunk) = self.recv().await {
+            if chunk.offset > offset {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "snapshot chunk at {} leaves a gap after the written data at {}",
+                        chunk.offset, offset
+                    ),
+                ));
+            }
+
+            let end = chunk.offset + chunk.data.len() as u64;
+
+            if end > offset {
+                let start = (offset - chunk.offset) as usize;
+                writer.write_all(&chunk.data[start..]).await?;
+                hasher.update(&chunk.data[start..]);
+                offset = end;
+            }
+
+            if chunk.done {
+                return Ok(hasher.finalize());
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "snapshot sender is closed before the last chunk",
+        ))
+    }
+}
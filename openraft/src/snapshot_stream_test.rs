@@ -0,0 +1,210 @@
+use std::io;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::io::AsyncRead;
+use tokio::io::ReadBuf;
+
+use crate::snapshot_stream::snapshot_channel;
+use crate::snapshot_stream::SnapshotChunk;
+
+/// A snapshot reader that counts the bytes read.
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    n_read: Arc<AtomicU64>,
+}
+
+impl AsyncRead for CountingReader {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.n_read.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        res
+    }
+}
+
+fn snapshot_data(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 251) as u8).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_send_from_write_to() -> anyhow::Result<()> {
+    let data = snapshot_data(10_500);
+
+    let (sender, mut receiver) = snapshot_channel(1);
+
+    let mut reader = Cursor::new(data.clone());
+    let reading = tokio::spawn(async move { sender.send_from(&mut reader, 1_000).await });
+
+    let mut writer = Cursor::new(Vec::new());
+    let checksum = receiver.write_to(&mut writer).await?;
+    reading.await??;
+
+    assert_eq!(data, writer.into_inner());
+    assert_eq!(crc32fast::hash(&data), checksum);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_send_from_chunks() -> anyhow::Result<()> {
+    let (sender, mut receiver) = snapshot_channel(1);
+
+    let mut reader = Cursor::new(snapshot_data(2_500));
+    tokio::spawn(async move { sender.send_from(&mut reader, 1_000).await });
+
+    let mut got = vec![];
+    while let Some(chunk) = receiver.recv().await {
+        got.push((chunk.offset, chunk.data.len(), chunk.done));
+    }
+
+    assert_eq!(vec![(0, 1_000, false), (1_000, 1_000, false), (2_000, 500, true)], got);
+
+    // An empty snapshot is sent as a single last chunk.
+    let (sender, mut receiver) = snapshot_channel(1);
+    sender.send_from(&mut Cursor::new(vec![]), 1_000).await?;
+
+    assert_eq!(
+        Some(SnapshotChunk {
+            offset: 0,
+            data: vec![],
+            done: true
+        }),
+        receiver.recv().await
+    );
+
+    Ok(())
+}
+
+/// A receiver that does not keep up pauses the sender: it does not read the whole snapshot into memory.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_slow_receiver_pauses_sender() -> anyhow::Result<()> {
+    let chunk_size = 1_000;
+    let capacity = 2;
+    let n_chunks = 100;
+
+    let data = snapshot_data(chunk_size * n_chunks);
+    let n_read = Arc::new(AtomicU64::new(0));
+
+    let (sender, mut receiver) = snapshot_channel(capacity);
+
+    let mut reader = CountingReader {
+        inner: Cursor::new(data.clone()),
+        n_read: n_read.clone(),
+    };
+    let reading = tokio::spawn(async move { sender.send_from(&mut reader, chunk_size).await });
+
+    tracing::info!("--- the receiver does not consume any chunk");
+    {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // `capacity` chunks are buffered, and the sender waits with one more chunk, and the one read ahead.
+        let want = ((capacity + 2) * chunk_size) as u64;
+        assert_eq!(want, n_read.load(Ordering::Relaxed));
+    }
+
+    tracing::info!("--- the receiver consumes chunks slowly");
+    {
+        let mut got = vec![];
+        while let Some(chunk) = receiver.recv().await {
+            // The sender never reads ahead more than the buffer.
+            let consumed = got.len() + chunk.data.len();
+            let ahead = n_read.load(Ordering::Relaxed) - consumed as u64;
+            assert!(ahead <= ((capacity + 2) * chunk_size) as u64, "read ahead: {}", ahead);
+
+            got.extend_from_slice(&chunk.data);
+            if got.len() % (chunk_size * 10) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        reading.await??;
+        assert_eq!(data, got);
+    }
+
+    Ok(())
+}
+
+/// A resent chunk is written only from the end of the written data.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_write_to_skips_written_data() -> anyhow::Result<()> {
+    let (sender, mut receiver) = snapshot_channel(4);
+
+    sender
+        .send(SnapshotChunk {
+            offset: 0,
+            data: b"foo".to_vec(),
+            done: false,
+        })
+        .await?;
+    // Resend from offset 1
+    sender
+        .send(SnapshotChunk {
+            offset: 1,
+            data: b"oobar".to_vec(),
+            done: true,
+        })
+        .await?;
+
+    let mut writer = Cursor::new(Vec::new());
+    let checksum = receiver.write_to(&mut writer).await?;
+
+    assert_eq!(b"foobar".to_vec(), writer.into_inner());
+    assert_eq!(crc32fast::hash(b"foobar"), checksum, "resent data is not hashed twice");
+
+    Ok(())
+}
+
+/// The writer returns an error if a chunk leaves a gap after the written data.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_write_to_gap() -> anyhow::Result<()> {
+    let (sender, mut receiver) = snapshot_channel(4);
+
+    sender
+        .send(SnapshotChunk {
+            offset: 0,
+            data: b"foo".to_vec(),
+            done: false,
+        })
+        .await?;
+    sender
+        .send(SnapshotChunk {
+            offset: 4,
+            data: b"bar".to_vec(),
+            done: true,
+        })
+        .await?;
+
+    let mut writer = Cursor::new(Vec::new());
+    let err = receiver.write_to(&mut writer).await.unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+    Ok(())
+}
+
+/// The writer returns an error if the sender quits before the last chunk.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_write_to_sender_closed() -> anyhow::Result<()> {
+    let (sender, mut receiver) = snapshot_channel(4);
+
+    sender
+        .send(SnapshotChunk {
+            offset: 0,
+            data: b"foo".to_vec(),
+            done: false,
+        })
+        .await?;
+    drop(sender);
+
+    let mut writer = Cursor::new(Vec::new());
+    let err = receiver.write_to(&mut writer).await.unwrap_err();
+    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+
+    Ok(())
+}
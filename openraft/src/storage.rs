@@ -1,6 +1,7 @@
 //! The Raft storage interface and data types.
 
 use std::fmt::Debug;
+use std::io::SeekFrom;
 use std::ops::RangeBounds;
 
 use async_trait::async_trait;
@@ -8,6 +9,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncSeek;
+use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWrite;
 
 use crate::core::EffectiveMembership;
@@ -17,11 +19,16 @@ use crate::raft_types::SnapshotId;
 use crate::raft_types::StateMachineChanges;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::ErrorSubject;
+use crate::ErrorVerb;
 use crate::LogId;
 use crate::NodeId;
+use crate::RaftNodeId;
 use crate::StorageError;
+use crate::StorageIOError;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct SnapshotMeta {
     // Log entries upto which this snapshot includes, inclusive.
     pub last_log_id: LogId,
@@ -29,11 +36,32 @@ pub struct SnapshotMeta {
     /// To identify a snapshot when transferring.
     /// Caveat: even when two snapshot is built with the same `last_log_id`, they still could be different in bytes.
     pub snapshot_id: SnapshotId,
+
+    /// The size in bytes of the snapshot data, 0 if unknown, e.g., in a snapshot built before it is introduced.
+    ///
+    /// A follower receiving the snapshot reports its progress against it.
+    #[serde(default)]
+    pub size: u64,
+
+    /// The CRC32 of the snapshot data, if there is one.
+    ///
+    /// A leader attaches it to the last chunk it sends, if `Config::snapshot_checksum` is enabled, and the follower
+    /// rejects the snapshot if the received data does not match it.
+    #[serde(default)]
+    pub checksum: Option<u32>,
+
+    /// The version of the encoding of the snapshot data, defined by the application.
+    ///
+    /// A state machine sets it when building a snapshot, and a follower rejects a snapshot of a version it does not
+    /// support, see `RaftStorage::is_snapshot_format_supported()`. A snapshot built before it is introduced is of
+    /// version 0.
+    #[serde(default)]
+    pub format_version: u32,
 }
 
 /// The data associated with the current snapshot.
 pub struct Snapshot<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
+where S: AsyncRead + Send + Unpin + ?Sized + 'static
 {
     /// metadata of a snapshot
     pub meta: SnapshotMeta,
@@ -42,21 +70,36 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
     pub snapshot: Box<S>,
 }
 
+/// A read handle to a snapshot, which streams the snapshot data from wherever it is stored, e.g., a local file or an
+/// object store.
+pub type SnapshotReader = dyn AsyncRead + Send + Sync + Unpin + 'static;
+
 /// A record holding the hard state of a Raft node.
 ///
 /// This model derives serde's traits for easily (de)serializing this
 /// model for storage & retrieval.
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
-pub struct HardState {
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(bound = "")]
+pub struct HardState<NID: RaftNodeId = NodeId> {
     /// The last recorded term observed by this system.
     pub current_term: u64,
     /// The ID of the node voted for in the `current_term`.
-    pub voted_for: Option<NodeId>,
+    pub voted_for: Option<NID>,
+}
+
+impl<NID: RaftNodeId> Default for HardState<NID> {
+    fn default() -> Self {
+        Self {
+            current_term: 0,
+            voted_for: None,
+        }
+    }
 }
 
 /// A struct used to represent the initial state which a Raft node needs when first starting.
 #[derive(Clone, Debug)]
-pub struct InitialState {
+pub struct InitialState<NID: RaftNodeId = NodeId> {
     /// The last entry.
     pub last_log_id: LogId,
 
@@ -64,19 +107,19 @@ pub struct InitialState {
     pub last_applied: LogId,
 
     /// The saved hard state of the node.
-    pub hard_state: HardState,
+    pub hard_state: HardState<NID>,
 
     /// The latest cluster membership configuration found, in log or in state machine, else a new initial
     /// membership config consisting only of this node's ID.
-    pub last_membership: EffectiveMembership,
+    pub last_membership: EffectiveMembership<NID>,
 }
 
-impl InitialState {
+impl<NID: RaftNodeId> InitialState<NID> {
     /// Create a new instance for a pristine Raft node.
     ///
     /// ### `id`
     /// The ID of the Raft node.
-    pub fn new_initial(id: NodeId) -> Self {
+    pub fn new_initial(id: NID) -> Self {
         Self {
             last_log_id: LogId { term: 0, index: 0 },
             last_applied: LogId { term: 0, index: 0 },
@@ -97,8 +140,9 @@ impl InitialState {
 /// See the [storage chapter of the guide](https://datafuselabs.github.io/openraft/storage.html)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftStorage<D, R>: Send + Sync + 'static
+pub trait RaftStorage<D, R, NID = NodeId>: Send + Sync + 'static
 where
+    NID: RaftNodeId,
     D: AppData,
     R: AppDataResponse,
 {
@@ -123,7 +167,7 @@ where
     /// the node's ID so that it is consistent across restarts.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_membership_config(&self) -> Result<EffectiveMembership, StorageError>;
+    async fn get_membership_config(&self) -> Result<EffectiveMembership<NID>, StorageError<NID>>;
 
     /// Get Raft's state information from storage.
     ///
@@ -136,14 +180,29 @@ where
     /// the node's hard state record; and the index of the last log applied to the state machine.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_initial_state(&self) -> Result<InitialState, StorageError>;
+    async fn get_initial_state(&self) -> Result<InitialState<NID>, StorageError<NID>>;
 
     /// Save Raft's hard-state.
     ///
+    /// The hard state does not have to be durable when it returns: Raft calls `flush_hard_state` before it relies on
+    /// it, if `Config::durability` requires so.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown.
+    async fn save_hard_state(&self, hs: &HardState<NID>) -> Result<(), StorageError<NID>>;
+
+    /// Make the hard state saved by `save_hard_state` durable.
+    ///
+    /// Raft calls it right after `save_hard_state`, before voting or acting in the new term, only with
+    /// `Durability::FullSync`. With other policies an implementation may batch hard state writes.
+    ///
+    /// By default it does nothing, for an implementation that makes the hard state durable in `save_hard_state`.
+    ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError>;
+    async fn flush_hard_state(&self) -> Result<(), StorageError<NID>> {
+        Ok(())
+    }
 
-    async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError>;
+    async fn read_hard_state(&self) -> Result<Option<HardState<NID>>, StorageError<NID>>;
 
     /// Get a series of log entries from storage.
     ///
@@ -153,7 +212,7 @@ where
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<D>>, StorageError>;
+    ) -> Result<Vec<Entry<D, NID>>, StorageError<NID>>;
 
     /// Get a series of log entries from storage.
     ///
@@ -161,27 +220,27 @@ where
     async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<D>>, StorageError>;
+    ) -> Result<Vec<Entry<D, NID>>, StorageError<NID>>;
 
     /// Try to get an log entry.
     /// It does not return an error if in defensive mode and the log entry at `log_index` is not found.
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D>>, StorageError>;
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D, NID>>, StorageError<NID>>;
 
     /// Returns the first log id in log.
     ///
     /// The impl should not consider the applied log id in state machine.
-    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError>;
+    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError<NID>>;
 
-    async fn first_known_log_id(&self) -> Result<LogId, StorageError>;
+    async fn first_known_log_id(&self) -> Result<LogId, StorageError<NID>>;
 
     /// Returns the last log id in log.
     ///
     /// The impl should not consider the applied log id in state machine.
-    async fn last_id_in_log(&self) -> Result<LogId, StorageError>;
+    async fn last_id_in_log(&self) -> Result<LogId, StorageError<NID>>;
 
     /// Returns the last applied log id which is recorded in state machine, and the last applied membership log id and
     /// membership config.
-    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError>;
+    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership<NID>>), StorageError<NID>>;
 
     /// Delete all logs in a `range`.
     ///
@@ -189,15 +248,33 @@ where
     async fn delete_logs_from<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<(), StorageError>;
+    ) -> Result<(), StorageError<NID>>;
 
     /// Append a payload of entries to the log.
     ///
     /// Though the entries will always be presented in order, each entry's index should be used to
     /// determine its location to be written in the log.
     ///
+    /// The entries do not have to be durable when it returns: Raft calls `flush` before it relies on them, e.g.,
+    /// before acknowledging them to the leader.
+    ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<(), StorageError>;
+    async fn append_to_log(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>>;
+
+    /// Make every log entry appended by `append_to_log` durable.
+    ///
+    /// Raft calls it once after appending a batch of entries: on a follower, once for all the entries in an
+    /// AppendEntries request, before responding to the leader; on the leader, before replicating the entries it
+    /// appends. Thus an implementation may buffer the entries in `append_to_log` and sync them once here.
+    ///
+    /// It is not called with `Durability::Async`.
+    ///
+    /// By default it does nothing, for an implementation that makes entries durable in `append_to_log`.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown.
+    async fn flush(&self) -> Result<(), StorageError<NID>> {
+        Ok(())
+    }
 
     /// Apply the given payload of entries to the state machine.
     ///
@@ -214,8 +291,14 @@ where
     /// - Deal with EntryPayload::Membership
     /// - A EntryPayload::SnapshotPointer log should never be seen.
     ///
+    /// It returns one response for every entry, in the same order. The response to an entry written by
+    /// `Raft::client_write()` is returned to the caller in `ClientWriteResponse::data`, e.g., the new value after a
+    /// compare-and-swap.
+    ///
+    /// `entries` holds at most `Config::max_apply_batch_size` entries. Consecutive calls deliver entries in log order.
+    ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError>;
+    async fn apply_to_state_machine(&self, entries: &[&Entry<D, NID>]) -> Result<Vec<R>, StorageError<NID>>;
 
     /// Perform log compaction, returning a handle to the generated snapshot.
     ///
@@ -227,18 +310,62 @@ where
     /// log covered by the snapshot.
     ///
     /// Errors returned from this method will be logged and retried.
-    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError>;
+    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError<NID>>;
+
+    /// Take a point-in-time view of the state machine at `last_applied`, to build a snapshot from while logs are
+    /// applied, or return `None` to build it with `do_log_compaction`.
+    ///
+    /// Raft calls it with applying paused, thus the state machine is at `last_applied` throughout the call. Applying
+    /// resumes once it returns, thus a view is meant to be cheap to take, e.g., a storage level snapshot, a
+    /// copy-on-write clone or a cursor of the state machine. The snapshot is then built from the view with
+    /// `SnapshotView::build_snapshot`, and it must have `last_applied` as its `SnapshotMeta::last_log_id`, or it is
+    /// discarded.
+    ///
+    /// By default it returns `None`: applying is not paused, and `do_log_compaction` builds a snapshot alongside.
+    async fn snapshot_view(
+        &self,
+        _last_applied: LogId,
+    ) -> Result<Option<Box<dyn SnapshotView<Self::SnapshotData, NID>>>, StorageError<NID>> {
+        Ok(None)
+    }
+
+    /// Returns the number of bytes the log takes in the storage.
+    ///
+    /// Raft calls it after applying logs to decide whether to build a snapshot, with
+    /// `SnapshotPolicy::SizeSinceLast`. It does not have to be exact, but it should grow with the log and shrink as the
+    /// log is purged.
+    ///
+    /// By default it returns 0, with which `SnapshotPolicy::SizeSinceLast` never builds a snapshot.
+    /// Errors returned from this method will be logged and the snapshot decision is skipped.
+    async fn log_storage_size(&self) -> Result<u64, StorageError<NID>> {
+        Ok(0)
+    }
+
+    /// Check if the state machine is able to install a snapshot encoded in `format_version`, see
+    /// `SnapshotMeta::format_version`.
+    ///
+    /// Raft calls it when the first chunk of a snapshot arrives, before `begin_receiving_snapshot`. A snapshot of an
+    /// unsupported version is rejected with `RaftError::SnapshotFormatUnsupported`, leaving the state machine
+    /// untouched, e.g., when a leader upgraded to a new encoding sends a snapshot to a follower not yet upgraded. The
+    /// leader then pauses replication to the follower, see `ReplicationMetrics::snapshot_format_rejected`.
+    ///
+    /// By default every version is supported.
+    fn is_snapshot_format_supported(&self, _format_version: u32) -> bool {
+        true
+    }
 
     /// Create a new blank snapshot, returning a writable handle to the snapshot object.
     ///
     /// Raft will use this handle to receive snapshot data.
+    /// Raft only writes to it sequentially from the start, thus the handle does not have to be seekable, e.g., it
+    /// could be an upload to an object store.
     ///
     /// ### implementation guide
     /// See the [storage chapter of the guide](https://datafuselabs.github.io/openraft/storage.html)
     /// for details on log compaction / snapshotting.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError>;
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>>;
 
     /// Finalize the installation of a snapshot which has finished streaming from the cluster leader.
     ///
@@ -252,7 +379,7 @@ where
         &self,
         meta: &SnapshotMeta,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError>;
+    ) -> Result<StateMachineChanges, StorageError<NID>>;
 
     /// Get a readable handle to the current snapshot, along with its metadata.
     ///
@@ -267,7 +394,80 @@ where
     /// of the snapshot, which should be decoded for creating this method's response data.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError>;
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError<NID>>;
+
+    /// Get a handle to stream the current snapshot from, along with its metadata.
+    ///
+    /// Raft uses this to send the snapshot to a follower, reading it sequentially from the start.
+    /// An implementation that stores snapshots elsewhere, e.g., in an object store, can override it to stream the
+    /// snapshot from there.
+    ///
+    /// By default it reads the snapshot returned by `get_current_snapshot`.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown.
+    async fn get_snapshot_reader(&self) -> Result<Option<Snapshot<SnapshotReader>>, StorageError<NID>> {
+        let Snapshot {
+            meta,
+            snapshot: mut data,
+        } = match self.get_current_snapshot().await? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+
+        data.seek(SeekFrom::Start(0)).await.map_err(|e| {
+            StorageIOError::new(
+                ErrorSubject::Snapshot(meta.clone()),
+                ErrorVerb::Read,
+                anyhow::Error::new(e),
+            )
+        })?;
+
+        Ok(Some(Snapshot { meta, snapshot: data }))
+    }
+
+    /// Reset the state machine to the state in the current snapshot, discarding everything applied after it, and
+    /// return the last log id applied to the reset state machine.
+    ///
+    /// Raft calls it to rebuild the state machine, see `Raft::rebuild_state_machine()`, and then re-applies the logs
+    /// after the returned log id.
+    ///
+    /// By default it installs the current snapshot again, and returns `None` if there is no snapshot, in which case
+    /// the rebuild is rejected with `RebuildStateMachineError::NoSnapshot`. An implementation that is able to reset to
+    /// the initial state, e.g., by clearing its state machine, should override it to support a rebuild without
+    /// snapshot, returning `LogId::new(0, 0)`.
+    ///
+    /// Errors returned from this method will cause Raft to go into shutdown.
+    async fn reset_state_machine(&self) -> Result<Option<LogId>, StorageError<NID>> {
+        let Snapshot {
+            meta,
+            snapshot: mut data,
+        } = match self.get_current_snapshot().await? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+
+        data.seek(SeekFrom::Start(0)).await.map_err(|e| {
+            StorageIOError::new(
+                ErrorSubject::Snapshot(meta.clone()),
+                ErrorVerb::Read,
+                anyhow::Error::new(e),
+            )
+        })?;
+
+        self.finalize_snapshot_installation(&meta, data).await?;
+        Ok(Some(meta.last_log_id))
+    }
+}
+
+/// A point-in-time view of a state machine to build a snapshot from, see `RaftStorage::snapshot_view()`.
+#[async_trait]
+pub trait SnapshotView<S, NID = NodeId>: Send + 'static
+where
+    S: AsyncRead + Send + Unpin + 'static,
+    NID: RaftNodeId,
+{
+    /// Build a snapshot of this view, and store it as the current snapshot of the storage.
+    async fn build_snapshot(self: Box<Self>) -> Result<Snapshot<S>, StorageError<NID>>;
 }
 
 /// APIs for debugging a store.
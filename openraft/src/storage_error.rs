@@ -4,23 +4,25 @@ use std::ops::Bound;
 
 use crate::storage::HardState;
 use crate::LogId;
+use crate::NodeId;
+use crate::RaftNodeId;
 use crate::SnapshotMeta;
 
 /// An error that occurs when the RaftStore impl runs defensive check of input or output.
 /// E.g. re-applying an log entry is a violation that may be a potential bug.
 #[derive(thiserror::Error, Debug)]
-pub struct DefensiveError {
+pub struct DefensiveError<NID: RaftNodeId = NodeId> {
     /// The subject that violates store defensive check, e.g. hard-state, log or state machine.
     pub subject: ErrorSubject,
 
     /// The description of the violation.
-    pub violation: Violation,
+    pub violation: Violation<NID>,
 
     pub backtrace: Backtrace,
 }
 
-impl DefensiveError {
-    pub fn new(subject: ErrorSubject, violation: Violation) -> DefensiveError {
+impl<NID: RaftNodeId> DefensiveError<NID> {
+    pub fn new(subject: ErrorSubject, violation: Violation<NID>) -> DefensiveError<NID> {
         DefensiveError {
             subject,
             violation,
@@ -29,7 +31,7 @@ impl DefensiveError {
     }
 }
 
-impl std::fmt::Display for DefensiveError {
+impl<NID: RaftNodeId> std::fmt::Display for DefensiveError<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "'{:?}' violates: '{}'", self.subject, self.violation)
     }
@@ -72,14 +74,26 @@ pub enum ErrorVerb {
     Delete,
 }
 
+/// Whether an io error is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The operation may succeed if it is retried, e.g., a timeout or a temporarily unavailable device.
+    ///
+    /// The failed operation must have changed nothing, or be safe to run again.
+    Transient,
+
+    /// The store can not be relied on any more, e.g., the data is corrupted.
+    Fatal,
+}
+
 /// Violations a store would return when running defensive check.
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
-pub enum Violation {
+pub enum Violation<NID: RaftNodeId = NodeId> {
     #[error("term can only be change to a greater value, current: {curr}, change to {to}")]
     TermNotAscending { curr: u64, to: u64 },
 
     #[error("voted_for can not change from Some() to other Some(), current: {curr:?}, change to {to:?}")]
-    VotedForChanged { curr: HardState, to: HardState },
+    VotedForChanged { curr: HardState<NID>, to: HardState<NID> },
 
     #[error("log at higher index is obsolete: {higher_index_log_id:?} should GT {lower_index_log_id:?}")]
     DirtyLog {
@@ -112,13 +126,13 @@ pub enum Violation {
 
 /// A storage error could be either a defensive check error or an error occurred when doing the actual io operation.
 #[derive(Debug, thiserror::Error)]
-pub enum StorageError {
+pub enum StorageError<NID: RaftNodeId = NodeId> {
     /// An error raised by defensive check.
     #[error(transparent)]
     Defensive {
         #[from]
         #[backtrace]
-        source: DefensiveError,
+        source: DefensiveError<NID>,
     },
 
     /// An error raised by io operation.
@@ -130,8 +144,8 @@ pub enum StorageError {
     },
 }
 
-impl StorageError {
-    pub fn into_defensive(self) -> Option<DefensiveError> {
+impl<NID: RaftNodeId> StorageError<NID> {
+    pub fn into_defensive(self) -> Option<DefensiveError<NID>> {
         match self {
             StorageError::Defensive { source } => Some(source),
             _ => None,
@@ -144,6 +158,14 @@ impl StorageError {
             _ => None,
         }
     }
+
+    /// Whether the operation may succeed if it is retried. A defensive check error is never transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            StorageError::IO { source } => source.class() == ErrorClass::Transient,
+            _ => false,
+        }
+    }
 }
 
 /// Error that occurs when operating the store.
@@ -151,6 +173,7 @@ impl StorageError {
 pub struct StorageIOError {
     subject: ErrorSubject,
     verb: ErrorVerb,
+    class: ErrorClass,
     source: anyhow::Error,
     backtrace: Backtrace,
 }
@@ -162,12 +185,27 @@ impl std::fmt::Display for StorageIOError {
 }
 
 impl StorageIOError {
+    /// Create a fatal io error: Raft shuts down when it sees it.
     pub fn new(subject: ErrorSubject, verb: ErrorVerb, source: anyhow::Error) -> StorageIOError {
         StorageIOError {
             subject,
             verb,
+            class: ErrorClass::Fatal,
             source,
             backtrace: Backtrace::capture(),
         }
     }
+
+    /// Create a transient io error: Raft retries the operation upto `Config::storage_retry_attempts` times before
+    /// shutting down.
+    pub fn transient(subject: ErrorSubject, verb: ErrorVerb, source: anyhow::Error) -> StorageIOError {
+        StorageIOError {
+            class: ErrorClass::Transient,
+            ..Self::new(subject, verb, source)
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        self.class
+    }
 }
@@ -8,12 +8,16 @@ use crate::raft::Entry;
 use crate::storage::HardState;
 use crate::storage::InitialState;
 use crate::storage::Snapshot;
+use crate::storage::SnapshotReader;
+use crate::storage::SnapshotView;
 use crate::summary::MessageSummary;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::DefensiveCheck;
 use crate::EffectiveMembership;
 use crate::LogId;
+use crate::NodeId;
+use crate::RaftNodeId;
 use crate::RaftStorage;
 use crate::RaftStorageDebug;
 use crate::SnapshotMeta;
@@ -25,13 +29,13 @@ use crate::Wrapper;
 ///
 /// It provides defensive check against input and the state of underlying store.
 /// And it provides more APIs.
-pub struct StoreExt<D, R, T> {
+pub struct StoreExt<D, R, T, NID = NodeId> {
     defensive: RwLock<bool>,
     inner: T,
-    p: PhantomData<(D, R)>,
+    p: PhantomData<(D, R, NID)>,
 }
 
-impl<D, R, T> StoreExt<D, R, T> {
+impl<D, R, T, NID> StoreExt<D, R, T, NID> {
     /// Create a StoreExt backed by another store.
     pub fn new(inner: T) -> Self {
         StoreExt {
@@ -42,17 +46,18 @@ impl<D, R, T> StoreExt<D, R, T> {
     }
 }
 
-impl<D, R, T> Wrapper<T> for StoreExt<D, R, T> {
+impl<D, R, T, NID> Wrapper<T> for StoreExt<D, R, T, NID> {
     fn inner(&self) -> &T {
         &self.inner
     }
 }
 
-impl<D, R, T> DefensiveCheck<D, R, T> for StoreExt<D, R, T>
+impl<D, R, T, NID> DefensiveCheck<D, R, T, NID> for StoreExt<D, R, T, NID>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID>,
+    NID: RaftNodeId,
 {
     fn set_defensive(&self, d: bool) {
         let mut defensive_flag = self.defensive.write().unwrap();
@@ -65,11 +70,12 @@ where
 }
 
 #[async_trait]
-impl<D, R, T, SM> RaftStorageDebug<SM> for StoreExt<D, R, T>
+impl<D, R, T, NID, SM> RaftStorageDebug<SM> for StoreExt<D, R, T, NID>
 where
-    T: RaftStorage<D, R> + RaftStorageDebug<SM>,
+    T: RaftStorage<D, R, NID> + RaftStorageDebug<SM>,
     D: AppData,
     R: AppDataResponse,
+    NID: RaftNodeId,
 {
     async fn get_state_machine(&self) -> SM {
         self.inner().get_state_machine().await
@@ -77,34 +83,40 @@ where
 }
 
 #[async_trait]
-impl<D, R, T> RaftStorage<D, R> for StoreExt<D, R, T>
+impl<D, R, T, NID> RaftStorage<D, R, NID> for StoreExt<D, R, T, NID>
 where
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID>,
     D: AppData,
     R: AppDataResponse,
+    NID: RaftNodeId,
 {
     type SnapshotData = T::SnapshotData;
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_membership_config(&self) -> Result<EffectiveMembership, StorageError> {
+    async fn get_membership_config(&self) -> Result<EffectiveMembership<NID>, StorageError<NID>> {
         self.defensive_no_dirty_log().await?;
         self.inner().get_membership_config().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
+    async fn get_initial_state(&self) -> Result<InitialState<NID>, StorageError<NID>> {
         self.defensive_no_dirty_log().await?;
         self.inner().get_initial_state().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError> {
+    async fn save_hard_state(&self, hs: &HardState<NID>) -> Result<(), StorageError<NID>> {
         self.defensive_incremental_hard_state(hs).await?;
         self.inner().save_hard_state(hs).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError> {
+    async fn flush_hard_state(&self) -> Result<(), StorageError<NID>> {
+        self.inner().flush_hard_state().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn read_hard_state(&self) -> Result<Option<HardState<NID>>, StorageError<NID>> {
         self.inner().read_hard_state().await
     }
 
@@ -112,7 +124,7 @@ where
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<D>>, StorageError> {
+    ) -> Result<Vec<Entry<D, NID>>, StorageError<NID>> {
         self.defensive_nonempty_range(range.clone()).await?;
 
         let res = self.inner().get_log_entries(range.clone()).await?;
@@ -126,34 +138,34 @@ where
     async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<D>>, StorageError> {
+    ) -> Result<Vec<Entry<D, NID>>, StorageError<NID>> {
         self.defensive_nonempty_range(range.clone()).await?;
 
         self.inner().try_get_log_entries(range).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D>>, StorageError> {
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D, NID>>, StorageError<NID>> {
         self.inner().try_get_log_entry(log_index).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
+    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError<NID>> {
         self.inner().first_id_in_log().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn first_known_log_id(&self) -> Result<LogId, StorageError> {
+    async fn first_known_log_id(&self) -> Result<LogId, StorageError<NID>> {
         self.inner().first_known_log_id().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn last_id_in_log(&self) -> Result<LogId, StorageError> {
+    async fn last_id_in_log(&self) -> Result<LogId, StorageError<NID>> {
         self.inner().last_id_in_log().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
+    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership<NID>>), StorageError<NID>> {
         self.inner().last_applied_state().await
     }
 
@@ -161,7 +173,7 @@ where
     async fn delete_logs_from<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         self.defensive_nonempty_range(range.clone()).await?;
         self.defensive_half_open_range(range.clone()).await?;
 
@@ -169,7 +181,7 @@ where
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn append_to_log(&self, entries: &[&Entry<D, NID>]) -> Result<(), StorageError<NID>> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_consecutive_input(entries).await?;
         self.defensive_append_log_index_is_last_plus_one(entries).await?;
@@ -178,8 +190,13 @@ where
         self.inner().append_to_log(entries).await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn flush(&self) -> Result<(), StorageError<NID>> {
+        self.inner().flush().await
+    }
+
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError> {
+    async fn apply_to_state_machine(&self, entries: &[&Entry<D, NID>]) -> Result<Vec<R>, StorageError<NID>> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_apply_index_is_last_applied_plus_one(entries).await?;
         self.defensive_apply_log_id_gt_last(entries).await?;
@@ -188,12 +205,24 @@ where
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError<NID>> {
         self.inner().do_log_compaction().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+    async fn snapshot_view(
+        &self,
+        last_applied: LogId,
+    ) -> Result<Option<Box<dyn SnapshotView<Self::SnapshotData, NID>>>, StorageError<NID>> {
+        self.inner().snapshot_view(last_applied).await
+    }
+
+    fn is_snapshot_format_supported(&self, format_version: u32) -> bool {
+        self.inner().is_snapshot_format_supported(format_version)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>> {
         self.inner().begin_receiving_snapshot().await
     }
 
@@ -202,12 +231,27 @@ where
         &self,
         meta: &SnapshotMeta,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError> {
+    ) -> Result<StateMachineChanges, StorageError<NID>> {
         self.inner().finalize_snapshot_installation(meta, snapshot).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError<NID>> {
         self.inner().get_current_snapshot().await
     }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_snapshot_reader(&self) -> Result<Option<Snapshot<SnapshotReader>>, StorageError<NID>> {
+        self.inner().get_snapshot_reader().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn reset_state_machine(&self) -> Result<Option<LogId>, StorageError<NID>> {
+        self.inner().reset_state_machine().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn log_storage_size(&self) -> Result<u64, StorageError<NID>> {
+        self.inner().log_storage_size().await
+    }
 }
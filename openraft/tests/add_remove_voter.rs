@@ -36,7 +36,7 @@ async fn add_remove_voter() -> Result<()> {
     let router = Arc::new(RaftRouter::new(config.clone()));
     router.new_raft_node(0).await;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     let mut want = 0;
     router
         .wait_for_metrics(
@@ -49,9 +49,9 @@ async fn add_remove_voter() -> Result<()> {
     router
         .wait_for_metrics(
             &0u64,
-            |x| x.state == State::Learner,
+            |x| x.state == State::Uninitialized,
             Some(timeout),
-            &format!("n{}.state -> {:?}", 4, State::Learner),
+            &format!("n{}.state -> {:?}", 0, State::Uninitialized),
         )
         .await?;
 
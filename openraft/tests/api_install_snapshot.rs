@@ -33,7 +33,7 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         router.new_raft_node(0).await;
 
         router.wait_for_log(&btreeset![0], want, None, "empty").await?;
-        router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
 
         router.initialize_from_single_node(0).await?;
         want += 1;
@@ -49,6 +49,9 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         meta: SnapshotMeta {
             snapshot_id: "ss1".into(),
             last_log_id: LogId { term: 1, index: 0 },
+            size: 3,
+            checksum: None,
+            format_version: 0,
         },
         offset: 0,
         data: vec![1, 2, 3],
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use memstore::ClientRequest;
+use openraft::error::RaftError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftNetwork;
+
+#[macro_use]
+mod fixtures;
+
+/// A follower never moves its committed log backward, and optionally rejects a leader whose commit index regresses.
+///
+/// What does this test do?
+///
+/// - brings up a node with `reject_commit_regression` enabled, and replicates 5 logs to it with 4 committed.
+/// - sends a heartbeat from the same leader with a smaller `leader_commit`, and asserts it is rejected with
+///   `CommitIndexRegression`.
+/// - resends logs before the committed one, and asserts the committed log does not go backward.
+/// - sends a heartbeat from the leader of the next term, who knows of fewer committed logs, and asserts it is accepted
+///   while the committed log does not go backward.
+/// - asserts with `reject_commit_regression` disabled, the regression is accepted, and the committed log does not go
+///   backward either.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_commit_regression() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    tracing::info!("--- reject a regressing commit index");
+    {
+        let router = new_router(true)?;
+        router.new_raft_node(1).await;
+        let n1 = router.get_raft_handle(&1).await?;
+
+        let resp = router.send_append_entries(1, req(1, 0, 5, 4)).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), n1.metrics().borrow().last_committed);
+
+        let res = n1.append_entries(req(1, 5, 0, 2)).await;
+        match res.unwrap_err() {
+            RaftError::CommitIndexRegression {
+                term,
+                leader_commit,
+                seen,
+            } => {
+                assert_eq!(1, term);
+                assert_eq!(LogId::new(1, 2), leader_commit);
+                assert_eq!(LogId::new(1, 4), seen);
+            }
+            err => panic!("expect CommitIndexRegression, got: {:?}", err),
+        }
+        assert_eq!(Some(LogId::new(1, 4)), n1.metrics().borrow().last_committed);
+
+        tracing::info!("--- resending logs before the committed one does not move it backward");
+
+        let resp = router.send_append_entries(1, req(1, 1, 2, 5)).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), n1.metrics().borrow().last_committed);
+
+        tracing::info!("--- a new leader may know of fewer committed logs");
+
+        let resp = router.send_append_entries(1, req(2, 5, 0, 3)).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), n1.metrics().borrow().last_committed);
+    }
+
+    tracing::info!("--- accept a regressing commit index, without moving the committed log backward");
+    {
+        let router = new_router(false)?;
+        router.new_raft_node(1).await;
+        let n1 = router.get_raft_handle(&1).await?;
+
+        let resp = router.send_append_entries(1, req(1, 0, 5, 4)).await?;
+        assert!(resp.success());
+
+        let resp = router.send_append_entries(1, req(1, 5, 0, 2)).await?;
+        assert!(resp.success());
+        assert_eq!(Some(LogId::new(1, 4)), n1.metrics().borrow().last_committed);
+    }
+
+    Ok(())
+}
+
+fn new_router(reject_commit_regression: bool) -> Result<Arc<RaftRouter>> {
+    let config = Config {
+        reject_commit_regression,
+        ..Default::default()
+    }
+    .validate()?;
+
+    Ok(Arc::new(RaftRouter::new(Arc::new(config))))
+}
+
+/// Build an AppendEntries from leader node-0 of `term`, with `n_entries` logs of term 1 following `prev_index`.
+fn req(term: u64, prev_index: u64, n_entries: u64, commit_index: u64) -> AppendEntriesRequest<ClientRequest> {
+    AppendEntriesRequest {
+        term,
+        leader_id: 0,
+        prev_log_id: LogId::new(if prev_index == 0 { 0 } else { 1 }, prev_index),
+        entries: (prev_index + 1..=prev_index + n_entries)
+            .map(|index| Entry {
+                log_id: LogId::new(1, index),
+                payload: EntryPayload::Blank,
+                request_id: None,
+            })
+            .collect(),
+        leader_commit: LogId::new(1, commit_index),
+        compressed_entries: None,
+    }
+}
@@ -37,7 +37,7 @@ async fn append_conflicts() -> Result<()> {
     tracing::info!("--- wait for init node to ready");
 
     router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
-    router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
 
     let (r0, sto0) = router.remove_node(0).await.unwrap();
     check_logs(&sto0, vec![0]).await?;
@@ -50,6 +50,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(0, 0),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req.clone()).await?;
@@ -67,6 +68,7 @@ async fn append_conflicts() -> Result<()> {
         entries: vec![ent(1, 1), ent(1, 2), ent(1, 3), ent(1, 4)],
         // this set the last_applied to 2
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req.clone()).await?;
@@ -92,6 +94,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 1),
         entries: vec![ent(1, 2)],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -109,6 +112,7 @@ async fn append_conflicts() -> Result<()> {
         entries: vec![ent(2, 3)],
         // this set the last_applied to 2
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -124,6 +128,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 2000),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -140,6 +145,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(3, 3),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -157,6 +163,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 2),
         entries: vec![ent(2, 3), ent(2, 4), ent(2, 5)],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -173,6 +180,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(2, 3),
         entries: vec![ent(3, 4)],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
@@ -190,6 +198,7 @@ async fn append_conflicts() -> Result<()> {
         prev_log_id: LogId::new(1, 200),
         entries: vec![],
         leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
     };
 
     let resp = r0.append_entries(req).await?;
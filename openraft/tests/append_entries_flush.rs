@@ -0,0 +1,90 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::StubNetwork;
+use memstore::ClientRequest;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// The logs are flushed once for every AppendEntries request that appends logs.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node, with a store counting log flushes.
+/// - sends an AppendEntries with 3 entries, asserts the logs are flushed once.
+/// - sends a heartbeat without entries, asserts the logs are not flushed.
+/// - sends another AppendEntries with 2 entries, asserts the logs are flushed once more.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn append_entries_flush() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5_000));
+    let config = Arc::new(Config::default().validate()?);
+
+    let sto = Arc::new(HookedStore::new(0).await);
+    let raft = Raft::new(0, config, Arc::new(StubNetwork::default()), sto.clone());
+
+    raft.wait(timeout).state(State::Uninitialized, "pristine node is uninitialized").await?;
+
+    let n_flush = || sto.flushes.load(Ordering::Relaxed);
+
+    tracing::info!("--- append 3 entries in one AppendEntries");
+    {
+        let resp = raft.append_entries(append_entries(LogId::new(0, 0), 1..4)).await?;
+        assert!(resp.success());
+
+        raft.wait(timeout).metrics(|x| x.last_log_index == 3, "3 entries appended").await?;
+        assert_eq!(1, n_flush(), "flush once for 3 entries");
+    }
+
+    tracing::info!("--- heartbeat does not flush");
+    {
+        let resp = raft.append_entries(append_entries(LogId::new(1, 3), 4..4)).await?;
+        assert!(resp.success());
+
+        assert_eq!(1, n_flush(), "no entry to flush");
+    }
+
+    tracing::info!("--- append 2 more entries in one AppendEntries");
+    {
+        let resp = raft.append_entries(append_entries(LogId::new(1, 3), 4..6)).await?;
+        assert!(resp.success());
+
+        raft.wait(timeout).metrics(|x| x.last_log_index == 5, "5 entries appended").await?;
+        assert_eq!(2, n_flush(), "flush once for 2 entries");
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+/// Build an AppendEntries from leader node-1 in term 1, with blank entries at `indexes`.
+fn append_entries(prev_log_id: LogId, indexes: std::ops::Range<u64>) -> AppendEntriesRequest<ClientRequest> {
+    AppendEntriesRequest {
+        term: 1,
+        leader_id: 1,
+        prev_log_id,
+        entries: indexes
+            .map(|index| Entry {
+                log_id: LogId::new(1, index),
+                payload: EntryPayload::Blank,
+                request_id: None,
+            })
+            .collect(),
+        leader_commit: LogId::new(0, 0),
+        compressed_entries: None,
+    }
+}
@@ -57,12 +57,14 @@ async fn append_inconsistent_log() -> Result<()> {
         sto0.append_to_log(&[&Entry {
             log_id: LogId { term: 2, index: i },
             payload: EntryPayload::Blank,
+            request_id: None,
         }])
         .await?;
 
         sto2.append_to_log(&[&Entry {
             log_id: LogId { term: 3, index: i },
             payload: EntryPayload::Blank,
+            request_id: None,
         }])
         .await?;
     }
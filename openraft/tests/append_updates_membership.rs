@@ -35,7 +35,7 @@ async fn append_updates_membership() -> Result<()> {
     tracing::info!("--- wait for init node to ready");
 
     router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
-    router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
 
     let (r0, _sto0) = router.remove_node(0).await.unwrap();
 
@@ -50,15 +50,18 @@ async fn append_updates_membership() -> Result<()> {
                 Entry {
                     log_id: LogId { term: 1, index: 2 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2})),
+                    request_id: None,
                 },
                 ent(1, 3),
                 Entry {
                     log_id: LogId { term: 1, index: 4 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {1,2,3,4})),
+                    request_id: None,
                 },
                 ent(1, 5),
             ],
             leader_commit: LogId::new(0, 0),
+            compressed_entries: None,
         };
 
         let resp = r0.append_entries(req.clone()).await?;
@@ -76,6 +79,7 @@ async fn append_updates_membership() -> Result<()> {
             prev_log_id: LogId::new(1, 2),
             entries: vec![ent(2, 3)],
             leader_commit: LogId::new(0, 0),
+            compressed_entries: None,
         };
 
         let resp = r0.append_entries(req.clone()).await?;
@@ -93,6 +97,7 @@ fn ent<T: AppData>(term: u64, index: u64) -> Entry<T> {
     Entry {
         log_id: LogId { term, index },
         payload: EntryPayload::Blank,
+        request_id: None,
     }
 }
 
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Committed entries are applied in batches no larger than `max_apply_batch_size`, in log order.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a slow state machine, `max_in_flight_applies = 16` and `max_apply_batch_size
+///   = 3`.
+/// - writes many logs concurrently, so that entries pile up while the state machine is busy.
+/// - asserts every call to `apply_to_state_machine()` delivers at most 3 entries, and some of them more than 1.
+/// - asserts the entries across all calls are every log index, in order, each delivered once.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn apply_batch_size() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n: u64 = 30;
+    let max_batch: u64 = 3;
+
+    let config = Arc::new(
+        Config {
+            max_in_flight_applies: 16,
+            max_apply_batch_size: max_batch,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            apply_delay: Duration::from_millis(20),
+            ..Default::default()
+        })
+        .await,
+    );
+    let raft = Raft::new(0, config, Arc::new(StubNetwork::default()), sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+
+    let mut writes = vec![];
+    for serial in 0..n {
+        let raft = raft.clone();
+        writes.push(tokio::spawn(async move {
+            raft.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+            .await
+        }));
+    }
+
+    for w in writes {
+        w.await??;
+    }
+
+    raft.wait(timeout()).metrics(|x| x.last_applied == n + 1, "all applied").await?;
+    raft.shutdown().await?;
+
+    let batches = sto.apply_batches.lock().unwrap().clone();
+
+    for b in batches.iter() {
+        assert!(
+            b.len() as u64 <= max_batch,
+            "a batch is no larger than max_apply_batch_size: {:?}",
+            b
+        );
+    }
+    assert!(
+        batches.iter().any(|b| b.len() > 1),
+        "entries are applied in batch: {:?}",
+        batches
+    );
+
+    let applied = batches.into_iter().flatten().collect::<Vec<_>>();
+    assert_eq!(
+        (1..n + 2).collect::<Vec<_>>(),
+        applied,
+        "every entry is applied once, in log order"
+    );
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -5,6 +5,7 @@ use anyhow::Result;
 use fixtures::RaftRouter;
 use maplit::btreeset;
 use openraft::Config;
+use openraft::RaftEvent;
 use openraft::RaftStorage;
 use tokio::time::sleep;
 
@@ -15,6 +16,7 @@ mod fixtures;
 ///
 /// - assert logs are deleted on leader after applying them.
 /// - assert logs are deleted on replication target after installing a snapshot.
+/// - assert the leader sends a `LogPurged` event with the id of the last deleted log.
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn clean_applied_logs() -> Result<()> {
     let (_log_guard, ut_span) = init_ut!();
@@ -32,6 +34,8 @@ async fn clean_applied_logs() -> Result<()> {
 
     let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
 
+    let mut events = router.get_raft_handle(&0).await?.events();
+
     let count = (10 - n_logs) as usize;
     for idx in 0..count {
         router.client_request(0, "0", idx as u64).await;
@@ -53,6 +57,23 @@ async fn clean_applied_logs() -> Result<()> {
         }
     }
 
+    tracing::info!("--- the last LogPurged event is the log right before the first kept log");
+    {
+        let sto = router.get_storage_handle(&0).await?;
+        let first_kept = sto.get_log_entries(..).await?[0].log_id;
+
+        let mut last_purged = None;
+        while let Ok(ev) = events.try_recv() {
+            if let RaftEvent::LogPurged(log_id) = ev {
+                last_purged = Some(log_id);
+            }
+        }
+
+        let last_purged = last_purged.expect("node 0 purged logs");
+        assert_eq!(first_kept.index - 1, last_purged.index);
+        assert_eq!(first_kept.term, last_purged.term);
+    }
+
     Ok(())
 }
 
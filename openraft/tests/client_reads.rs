@@ -30,9 +30,9 @@ async fn client_reads() -> Result<()> {
 
     let mut want = 0;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty node").await?;
-    router.wait_for_state(&btreeset![0, 1, 2], State::Learner, None, "empty node").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::Uninitialized, None, "empty node").await?;
     router.assert_pristine_cluster().await;
 
     // Initialize the cluster, then assert that a stable cluster was formed & held.
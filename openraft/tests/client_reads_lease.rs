@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::ReadStrategy;
+
+#[macro_use]
+mod fixtures;
+
+/// With `ReadIndex`, every linearizable read exchanges heartbeats with a quorum.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster reading with `ReadStrategy::ReadIndex`.
+/// - assert a linearizable read on the leader succeeds.
+/// - isolate the leader and assert a linearizable read fails at once, since the leadership can not be confirmed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_reads_read_index() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let router = Arc::new(RaftRouter::new(config(ReadStrategy::ReadIndex)?));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request(0, "foo", 1).await;
+    want += 1;
+
+    let read_log_id = router.ensure_linearizable(0).await?;
+    assert_eq!(LogId { term: 1, index: want }, read_log_id);
+
+    tracing::info!("--- read on an isolated leader fails at once");
+    {
+        router.isolate_node(0).await;
+
+        let res = tokio::time::timeout(Duration::from_millis(500), router.ensure_linearizable(0)).await?;
+        assert!(res.is_err(), "can not confirm leadership: {:?}", res);
+    }
+
+    Ok(())
+}
+
+/// With `LeaseRead`, the leader serves reads without heartbeats until the lease expires, then falls back to
+/// read-index.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster reading with `ReadStrategy::LeaseRead` and a lease of 1 second.
+/// - assert a linearizable read on the leader succeeds.
+/// - isolate the leader and assert a linearizable read still succeeds with the lease.
+/// - wait for the lease to expire, and assert a linearizable read fails, since it falls back to read-index, which can
+///   not confirm the leadership.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_reads_lease() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = config(ReadStrategy::LeaseRead)?;
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request(0, "foo", 1).await;
+    want += 1;
+
+    let read_log_id = router.ensure_linearizable(0).await?;
+    assert_eq!(LogId { term: 1, index: want }, read_log_id);
+
+    tracing::info!("--- read on an isolated leader succeeds within the lease");
+    {
+        router.isolate_node(0).await;
+
+        let read_log_id = tokio::time::timeout(Duration::from_millis(500), router.ensure_linearizable(0)).await??;
+        assert_eq!(LogId { term: 1, index: want }, read_log_id);
+    }
+
+    tracing::info!("--- read falls back to read-index once the lease expires");
+    {
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_min + 500)).await;
+
+        let res = tokio::time::timeout(Duration::from_millis(500), router.ensure_linearizable(0)).await?;
+        assert!(res.is_err(), "the lease expired, can not confirm leadership: {:?}", res);
+    }
+
+    Ok(())
+}
+
+fn config(read_strategy: ReadStrategy) -> Result<Arc<Config>> {
+    let config = Config {
+        election_timeout_min: 1_000,
+        election_timeout_max: 1_100,
+        read_strategy,
+        ..Default::default()
+    }
+    .validate()?;
+
+    Ok(Arc::new(config))
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::ClientReadError;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorageDebug;
+
+#[macro_use]
+mod fixtures;
+
+/// Linearizable read with read-index.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - write a log and assert a linearizable read on the leader observes it, without appending any log.
+/// - assert a linearizable read on a follower fails with the leader id.
+/// - isolate the leader and assert a linearizable read fails instead of blocking.
+/// - assert a linearizable read on the new leader succeeds.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_reads_linearizable() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- read after write observes the write");
+    {
+        router.client_request(0, "foo", 1).await;
+        want += 1;
+
+        let read_log_id = router.ensure_linearizable(0).await?;
+        assert_eq!(LogId { term: 1, index: want }, read_log_id);
+
+        let sto = router.get_storage_handle(&0).await?;
+        let sm = sto.get_state_machine().await;
+        assert_eq!(Some(&"request-1".to_string()), sm.client_status.get("foo"));
+
+        let m = router.wait(&0, None).await?.metrics(|_| true, "get metrics").await?;
+        assert_eq!(want, m.last_log_index, "read does not append log");
+    }
+
+    tracing::info!("--- read on a follower is forwarded to the leader");
+    {
+        let res = router.ensure_linearizable(1).await;
+        match res.unwrap_err() {
+            ClientReadError::ForwardToLeader(fwd) => {
+                assert_eq!(Some(0), fwd.leader_id);
+            }
+            err => panic!("expect ForwardToLeader, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- read on an isolated leader fails");
+    {
+        router.isolate_node(0).await;
+
+        let res = tokio::time::timeout(Duration::from_millis(1_000), router.ensure_linearizable(0)).await?;
+        assert!(res.is_err(), "can not confirm leadership: {:?}", res);
+    }
+
+    tracing::info!("--- read on the new leader succeeds");
+    {
+        let new_leader = tokio::time::timeout(Duration::from_millis(5_000), async {
+            loop {
+                if let Some(l) = router.leader().await {
+                    break l;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await?;
+        assert_ne!(0, new_leader);
+
+        router.client_request(new_leader, "foo", 2).await;
+
+        router.ensure_linearizable(new_leader).await?;
+
+        let sto = router.get_storage_handle(&new_leader).await?;
+        let sm = sto.get_state_machine().await;
+        assert_eq!(Some(&"request-2".to_string()), sm.client_status.get("foo"));
+    }
+
+    Ok(())
+}
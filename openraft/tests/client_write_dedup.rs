@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+
+#[macro_use]
+mod fixtures;
+
+/// The state machine of `MemStore` deduplicates only the last serial of a client, thus the requests use different
+/// serials to tell whether a request is applied.
+fn req(sequence: u64, serial: u64, status: &str) -> ClientWriteRequest<ClientRequest> {
+    ClientWriteRequest::new(ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: status.to_string(),
+    })
+    .with_request_id("foo", sequence)
+}
+
+/// A replayed write request with the same id is responded with the cached response, without being applied again.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, `MemStore` remembers the responses to the last 2 requests of every client.
+/// - writes a request with an id, then replays it with another status, and asserts the replay gets the response of the
+///   first write and does not change the state machine.
+/// - replays a request in a batch along with a new one, and asserts only the new one is applied.
+/// - asserts a replayed request older than the window is applied again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_write_dedup() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a replay is responded with the cached response");
+    {
+        let first = n0.client_write(req(1, 1, "a")).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, first.log_id.index);
+        assert_eq!(ClientResponse(None), first.data);
+
+        let replay = n0.client_write(req(1, 2, "b")).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, replay.log_id.index, "the replay is appended");
+        assert_eq!(first.data, replay.data, "but not applied");
+    }
+
+    tracing::info!("--- a replay in a batch is not applied");
+    {
+        let results = n0.client_write_batch(vec![req(1, 3, "c"), req(2, 4, "d")]).await;
+        let resps = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        n_logs += 2;
+
+        assert_eq!(ClientResponse(None), resps[0].data);
+        assert_eq!(ClientResponse(Some("a".to_string())), resps[1].data);
+    }
+
+    tracing::info!("--- a replay older than the window is applied again");
+    {
+        n0.client_write(req(3, 5, "e")).await?;
+        n_logs += 1;
+
+        let replay = n0.client_write(req(1, 6, "f")).await?;
+        n_logs += 1;
+
+        assert_eq!(n_logs, replay.log_id.index);
+        assert_eq!(ClientResponse(Some("e".to_string())), replay.data);
+    }
+
+    Ok(())
+}
+
+/// A write request retried to a new leader is not applied again.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters and writes a request with an id to node-0.
+/// - transfers leadership to node-1, replays the request to node-1, and asserts it is responded with the cached
+///   response, and every state machine applies it only once.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_dedup_after_failover() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let first = n0.client_write(req(1, 1, "a")).await?;
+    n_logs += 1;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write a request with id").await?;
+
+    tracing::info!("--- transfer leadership to node-1");
+    {
+        router.transfer_leadership(0, Some(1)).await?;
+        router.wait(&1, timeout()).await?.current_leader(1, "node-1 becomes leader").await?;
+        // The blank log of the new leader.
+        n_logs += 1;
+    }
+
+    tracing::info!("--- replay the request to node-1");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let replay = n1.client_write(req(1, 2, "b")).await?;
+        n_logs += 1;
+
+        assert_eq!(n_logs, replay.log_id.index);
+        assert_eq!(first.data, replay.data);
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "replay").await?;
+
+        for id in [0, 1, 2] {
+            let sto = router.get_storage_handle(&id).await?;
+            let sm = sto.get_state_machine().await;
+            assert_eq!(
+                Some(&"a".to_string()),
+                sm.client_status.get("foo"),
+                "node-{} applies it once",
+                id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
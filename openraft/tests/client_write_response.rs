@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// `client_write` returns the response of the state machine to the written entry.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster.
+/// - writes the status of a client several times, with `client_write` and `client_write_batch`.
+/// - asserts every write returns the status before it, as computed by the state machine of `MemStore`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_write_response() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    let req = |serial: u64, status: &str| {
+        ClientWriteRequest::new(ClientRequest {
+            client: "foo".to_string(),
+            serial,
+            status: status.to_string(),
+        })
+    };
+
+    tracing::info!("--- the first write returns no previous status");
+    {
+        let resp = n0.client_write(req(1, "a")).await?;
+        assert_eq!(n_logs + 1, resp.log_id.index);
+        assert_eq!(ClientResponse(None), resp.data);
+    }
+
+    tracing::info!("--- the next write returns the status written by the first");
+    {
+        let resp = n0.client_write(req(2, "b")).await?;
+        assert_eq!(n_logs + 2, resp.log_id.index);
+        assert_eq!(ClientResponse(Some("a".to_string())), resp.data);
+    }
+
+    tracing::info!("--- every write in a batch returns its own response");
+    {
+        let results = n0.client_write_batch(vec![req(3, "c"), req(4, "d")]).await;
+        assert_eq!(2, results.len());
+
+        let resps = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(ClientResponse(Some("b".to_string())), resps[0].data);
+        assert_eq!(ClientResponse(Some("c".to_string())), resps[1].data);
+    }
+
+    Ok(())
+}
@@ -42,9 +42,9 @@ async fn client_writes() -> Result<()> {
 
     let mut want = 0;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty").await?;
-    router.wait_for_state(&btreeset![0, 1, 2], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::Uninitialized, None, "empty").await?;
     router.assert_pristine_cluster().await;
 
     // Initialize the cluster, then assert that a stable cluster was formed & held.
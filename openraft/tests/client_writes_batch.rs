@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Batched client writes are appended and replicated together, and are faster than serial writes.
+///
+/// What does this test do?
+///
+/// - brings up a 3 node cluster with network delay.
+/// - writes `n` entries one by one with `client_write`, and measures the time and the AppendEntries RPCs sent.
+/// - writes another `n` entries with one `client_write_batch`, and measures the same.
+/// - asserts every batched write succeeds with consecutive log indexes, and the batch takes less time and less RPCs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_writes_batch() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n = 50;
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 500,
+            election_timeout_min: 2000,
+            election_timeout_max: 3000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(10).build());
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "leader init log").await?;
+    router.wait_for_state(&btreeset![0], State::Leader, None, "cluster leader").await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- write {} entries one by one", n);
+    let serial_elapsed = {
+        let sent_before = router.append_entries_sent(1);
+        let start = Instant::now();
+
+        router.client_request_many(0, "serial", n).await;
+        want += n as u64;
+
+        let elapsed = start.elapsed();
+        let sent = router.append_entries_sent(1) - sent_before;
+        tracing::info!(?elapsed, sent, "serial writes");
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "serial writes").await?;
+        (elapsed, sent)
+    };
+
+    tracing::info!("--- write {} entries in one batch", n);
+    let batch_elapsed = {
+        let sent_before = router.append_entries_sent(1);
+        let start = Instant::now();
+
+        let rpcs = (0..n)
+            .map(|i| {
+                ClientWriteRequest::new(ClientRequest {
+                    client: "batch".to_string(),
+                    serial: i as u64,
+                    status: format!("request-{}", i),
+                })
+            })
+            .collect::<Vec<_>>();
+        let results = leader.client_write_batch(rpcs).await;
+
+        let elapsed = start.elapsed();
+        let sent = router.append_entries_sent(1) - sent_before;
+        tracing::info!(?elapsed, sent, "batched writes");
+
+        assert_eq!(n, results.len());
+        for (i, res) in results.into_iter().enumerate() {
+            let resp = res?;
+            assert_eq!(want + 1 + i as u64, resp.log_id.index, "entries are appended in order");
+        }
+        want += n as u64;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "batched writes").await?;
+        (elapsed, sent)
+    };
+
+    assert!(
+        batch_elapsed.0 < serial_elapsed.0,
+        "batched writes {:?} should be faster than serial writes {:?}",
+        batch_elapsed.0,
+        serial_elapsed.0
+    );
+    assert!(
+        batch_elapsed.1 < serial_elapsed.1,
+        "batched writes sent {} AppendEntries, serial writes sent {}",
+        batch_elapsed.1,
+        serial_elapsed.1
+    );
+
+    tracing::info!("--- a batch to a follower is rejected as a whole");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let rpcs = (0..3)
+            .map(|i| {
+                ClientWriteRequest::new(ClientRequest {
+                    client: "follower".to_string(),
+                    serial: i,
+                    status: format!("request-{}", i),
+                })
+            })
+            .collect::<Vec<_>>();
+        let results = follower.client_write_batch(rpcs).await;
+
+        assert_eq!(3, results.len());
+        for res in results {
+            assert!(res.is_err(), "a follower rejects every request in a batch");
+        }
+    }
+
+    Ok(())
+}
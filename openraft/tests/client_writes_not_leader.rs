@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A write to a follower is rejected with the id of the leader to redirect to.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - writes to a follower, asserts it returns `ForwardToLeader` with the leader id.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_to_follower() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.wait(&1, timeout()).await?.current_leader(0, "node-1 knows the leader").await?;
+
+    let n1 = router.get_raft_handle(&1).await?;
+    let res = n1.client_write(ClientWriteRequest::new(request(0))).await;
+
+    match res.unwrap_err() {
+        ClientWriteError::ForwardToLeader(fwd) => {
+            assert_eq!(Some(0), fwd.leader_id);
+        }
+        err => panic!("expect ForwardToLeader, got: {:?}", err),
+    }
+
+    Ok(())
+}
+
+/// A write to a node that does not know the leader is rejected with a hint to retry later.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, without pre-vote.
+/// - isolates node-2 so that it keeps starting elections it can not win.
+/// - writes to node-2 during the election, asserts it returns `LeaderUnknown`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_during_election() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- isolate node-2, it starts an election");
+    {
+        router.isolate_node(2).await;
+
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(
+                |x| x.state == State::Candidate && x.current_leader.is_none(),
+                "node-2 is electing",
+            )
+            .await?;
+    }
+
+    let n2 = router.get_raft_handle(&2).await?;
+    let res = n2.client_write(ClientWriteRequest::new(request(0))).await;
+
+    match res.unwrap_err() {
+        ClientWriteError::LeaderUnknown => {}
+        err => panic!("expect LeaderUnknown, got: {:?}", err),
+    }
+
+    Ok(())
+}
+
+fn request(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
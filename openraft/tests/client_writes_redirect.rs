@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+#[macro_use]
+mod fixtures;
+
+/// With `redirect_to_leader`, the test router sends a client request rejected with `ForwardToLeader` to the leader,
+/// thus writes to any node succeed, across a leadership change.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with a router redirecting client requests to the leader.
+/// - writes logs, each to a random node, and asserts every write succeeds.
+/// - isolates the leader, writes logs, each to a random node of the others, and asserts every write succeeds once a new
+///   leader is elected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_writes_redirect() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::builder(config.clone()).redirect_to_leader(true).build());
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write to random nodes");
+    {
+        for serial in 0..10 {
+            let target = *[0, 1, 2].choose(&mut thread_rng()).unwrap();
+            router.client_request(target, "foo", serial).await;
+        }
+        want += 10;
+
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "write 10 logs").await?;
+    }
+
+    tracing::info!("--- isolate the leader, write to random nodes of the others");
+    {
+        router.isolate_node(0).await;
+
+        for serial in 10..20 {
+            let target = *[1, 2].choose(&mut thread_rng()).unwrap();
+            router.client_request(target, "foo", serial).await;
+        }
+        // The blank log of the new leader and the writes.
+        want += 1 + 10;
+
+        router.wait_for_log(&btreeset! {1,2}, want, timeout(), "write 10 logs to the new leader").await?;
+
+        let leader = router.leader().await.expect("a new leader is elected");
+        assert_ne!(0, leader);
+
+        let sm = router.get_storage_handle(&leader).await?.get_state_machine().await;
+        assert_eq!(Some(&"request-19".to_string()), sm.client_status.get("foo"));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,134 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::State;
+use tokio::sync::watch;
+
+#[macro_use]
+mod fixtures;
+
+/// A leader keeps committing while a slow state machine applies, if `max_in_flight_applies` allows it.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a blocked state machine, and `max_in_flight_applies = 1`.
+/// - writes many logs concurrently, asserts every entry is applied alone once the state machine is unblocked.
+/// - does the same with a wider window, asserts the leader commits up to the window ahead of the blocked state machine,
+///   and entries are applied in batches no larger than the window.
+/// - asserts every write is responded with its own log id in both cases.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn commit_ahead_of_apply() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n_writes = 30;
+
+    let narrow = write_concurrently(1, n_writes).await?;
+    assert_eq!(
+        1,
+        narrow.max_apply_batch.load(Ordering::Relaxed),
+        "window 1 applies entries one by one"
+    );
+
+    let wide = write_concurrently(16, n_writes).await?;
+    let max_batch = wide.max_apply_batch.load(Ordering::Relaxed);
+    assert!(
+        max_batch > 1 && max_batch <= 16,
+        "a wider window applies entries in batches within the window: {}",
+        max_batch
+    );
+    assert!(
+        wide.applies.load(Ordering::Relaxed) < narrow.applies.load(Ordering::Relaxed),
+        "a wider window applies with fewer calls: {} vs {}",
+        wide.applies.load(Ordering::Relaxed),
+        narrow.applies.load(Ordering::Relaxed),
+    );
+
+    Ok(())
+}
+
+/// Bring up a single node cluster with the given `max_in_flight_applies`, and write `n` logs concurrently while the
+/// state machine is blocked.
+///
+/// It asserts the leader commits `max_in_flight_applies` entries ahead of the blocked state machine, then unblocks it
+/// and returns the store once all the writes finish.
+async fn write_concurrently(max_in_flight_applies: u64, n: u64) -> Result<Arc<HookedStore>> {
+    let config = Arc::new(
+        Config {
+            max_in_flight_applies,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let (tx_gate, rx_gate) = watch::channel(true);
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            apply_gate: Some(rx_gate),
+            ..Default::default()
+        })
+        .await,
+    );
+    let raft = Raft::new(0, config, Arc::new(StubNetwork::default()), sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+    raft.wait(timeout()).metrics(|x| x.last_applied == 1, "initial log applied").await?;
+
+    tx_gate.send(false)?;
+
+    let mut writes = vec![];
+    for serial in 0..n {
+        let raft = raft.clone();
+        writes.push(tokio::spawn(async move {
+            raft.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+            .await
+        }));
+    }
+
+    raft.wait(timeout())
+        .metrics(
+            |x| x.last_committed.map(|c| c.index - x.last_applied).unwrap_or_default() >= max_in_flight_applies,
+            "commit ahead of the blocked state machine",
+        )
+        .await?;
+    assert_eq!(1, raft.metrics().borrow().last_applied, "the state machine is blocked");
+
+    tx_gate.send(true)?;
+
+    let mut log_indexes = vec![];
+    for w in writes {
+        let resp = w.await??;
+        log_indexes.push(resp.log_id.index);
+    }
+
+    log_indexes.sort_unstable();
+    assert_eq!(
+        (2..n + 2).collect::<Vec<_>>(),
+        log_indexes,
+        "every write has its own log"
+    );
+
+    raft.wait(timeout()).metrics(|x| x.last_applied == n + 1, "all applied").await?;
+    raft.shutdown().await?;
+
+    Ok(sto)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Right after election, a leader does not commit logs of previous terms until its initial blank log is replicated.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, writes some logs, and restarts every node, so that none of them knows the logs
+///   are committed.
+/// - delays every AppendEntries, so that the new leader replicates the logs of the previous term in one RPC and its
+///   blank log in a later one.
+/// - asserts the leader reports `commit_blocked_by_term` without committing the logs replicated to every node.
+/// - asserts the logs are committed along with the blank log once it is replicated, and the leader is no longer
+///   blocked.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn commit_current_term() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            // No election while AppendEntries are delayed.
+            election_timeout_min: 3_000,
+            election_timeout_max: 3_100,
+            append_entries_timeout: 2_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "write 10 logs").await?;
+
+    tracing::info!("--- restart every node with AppendEntries delayed");
+    {
+        for id in 0..3 {
+            let (node, sto) = router.remove_node(id).await.unwrap();
+            node.shutdown().await?;
+            router.set_append_entries_send_delay(id, 500);
+            router.new_raft_node_with_sto(id, sto).await;
+        }
+    }
+
+    let leader = {
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.current_leader.is_some(),
+                Some(Duration::from_millis(10_000)),
+                "elect",
+            )
+            .await?
+            .current_leader
+            .unwrap()
+    };
+    let n = router.get_raft_handle(&leader).await?;
+    assert_eq!(State::Leader, n.metrics().borrow().state);
+
+    tracing::info!("--- the logs of the previous term are replicated but not committed");
+    {
+        let m = router
+            .wait_for_metrics(
+                &leader,
+                |x| x.leader_metrics.as_ref().map(|l| l.commit_blocked_by_term).unwrap_or(false),
+                timeout(),
+                "commit is blocked by term",
+            )
+            .await?;
+
+        let repl = &m.leader_metrics.as_ref().unwrap().replication;
+        assert!(
+            repl.values().all(|x| x.matched.index == want),
+            "every node has the logs"
+        );
+        assert_eq!(None, m.last_committed, "nothing is committed");
+    }
+
+    tracing::info!("--- the blank log of the current term commits the logs");
+    {
+        // The blank log appended by the new leader.
+        want += 1;
+
+        let m = router
+            .wait_for_metrics(
+                &leader,
+                |x| x.last_committed.map(|l| l.index) == Some(want),
+                timeout(),
+                "commit the blank log",
+            )
+            .await?;
+
+        assert_eq!(m.current_term, m.last_committed.unwrap().term);
+        assert!(!m.leader_metrics.as_ref().unwrap().commit_blocked_by_term);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
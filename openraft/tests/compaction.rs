@@ -44,9 +44,9 @@ async fn compaction() -> Result<()> {
 
     let mut n_logs = 0;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     router.wait_for_log(&btreeset![0], n_logs, None, "empty").await?;
-    router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
 
     router.assert_pristine_cluster().await;
 
@@ -82,6 +82,7 @@ async fn compaction() -> Result<()> {
     sto1.append_to_log(&[&Entry {
         log_id: LogId { term: 1, index: 1 },
         payload: EntryPayload::Blank,
+        request_id: None,
     }])
     .await?;
 
@@ -126,6 +127,7 @@ async fn compaction() -> Result<()> {
                 prev_log_id: LogId::new(1, 2),
                 entries: vec![],
                 leader_commit: LogId::new(0, 0),
+                compressed_entries: None,
             })
             .await?;
 
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::ConfigDelta;
+
+#[macro_use]
+mod fixtures;
+
+/// `Raft::config()` returns the config in use, including the updates made at runtime.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a non-default config.
+/// - asserts the config returned is the one passed to the constructor.
+/// - updates the config at runtime, and asserts the update is reflected at once, while an invalid update is not.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn config_read_back() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 100,
+            election_timeout_min: 500,
+            election_timeout_max: 1000,
+            max_payload_entries: 20,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- the config passed to the constructor");
+    {
+        assert_eq!(*config, *n0.config());
+    }
+
+    tracing::info!("--- the config updated at runtime");
+    {
+        router
+            .update_config(0, ConfigDelta {
+                max_payload_entries: Some(30),
+                ..Default::default()
+            })
+            .await?;
+
+        let got = n0.config();
+        assert_eq!(30, got.max_payload_entries);
+        assert_eq!(
+            Config {
+                max_payload_entries: 30,
+                ..(*config).clone()
+            },
+            *got
+        );
+
+        let res = router
+            .update_config(0, ConfigDelta {
+                heartbeat_interval: Some(1000),
+                ..Default::default()
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(100, n0.config().heartbeat_interval, "an invalid update is not applied");
+    }
+
+    Ok(())
+}
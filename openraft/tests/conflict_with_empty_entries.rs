@@ -50,6 +50,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
         prev_log_id: LogId::new(1, 5),
         entries: vec![],
         leader_commit: LogId::new(1, 5),
+        compressed_entries: None,
     };
 
     let resp = router.send_append_entries(0, rpc).await?;
@@ -68,6 +69,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
             Entry {
                 log_id: (1, 1).into(),
                 payload: EntryPayload::Blank,
+                request_id: None,
             },
             Entry {
                 log_id: (1, 2).into(),
@@ -76,9 +78,11 @@ async fn conflict_with_empty_entries() -> Result<()> {
                     serial: 1,
                     status: "bar".to_string(),
                 }),
+                request_id: None,
             },
         ],
         leader_commit: LogId::new(1, 5),
+        compressed_entries: None,
     };
 
     let resp = router.send_append_entries(0, rpc).await?;
@@ -93,6 +97,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
         prev_log_id: LogId::new(1, 3),
         entries: vec![],
         leader_commit: LogId::new(1, 5),
+        compressed_entries: None,
     };
 
     let resp = router.send_append_entries(0, rpc).await?;
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::storage::HardState;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// `current_term()` and `current_vote()` reflect the hard state, and advance with an election.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, with node-0 as the leader.
+/// - asserts the term and vote on node-0 are the same as in metrics and as persisted in its storage.
+/// - transfers leadership to node-1, which elects itself in a higher term.
+/// - asserts the term on node-1 advances, it voted for itself, and it is the same as persisted.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn current_term_vote() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- the term and vote of the leader");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        assert_eq!(term, n0.current_term().await?);
+
+        let vote = n0.current_vote().await?;
+        assert_eq!(
+            HardState {
+                current_term: term,
+                voted_for: Some(0),
+            },
+            vote
+        );
+
+        let sto = router.get_storage_handle(&0).await?;
+        assert_eq!(Some(vote), sto.read_hard_state().await?, "the same as persisted");
+    }
+
+    tracing::info!("--- transfer leadership to node-1, the term advances");
+    {
+        router.transfer_leadership(0, Some(1)).await?;
+
+        let new_term = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.state == State::Leader && x.current_term > term,
+                "node-1 is leader",
+            )
+            .await?
+            .current_term;
+
+        let n1 = router.get_raft_handle(&1).await?;
+        assert_eq!(new_term, n1.current_term().await?);
+
+        let vote = n1.current_vote().await?;
+        assert_eq!(
+            HardState {
+                current_term: new_term,
+                voted_for: Some(1),
+            },
+            vote
+        );
+
+        let sto = router.get_storage_handle(&1).await?;
+        assert_eq!(Some(vote), sto.read_hard_state().await?, "the same as persisted");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use memstore::MemStore;
+use openraft::async_trait::async_trait;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::Config;
+use openraft::Raft;
+use openraft::RaftNetwork;
+use openraft::RaftStorage;
+use openraft::State;
+use tokio::sync::RwLock;
+
+#[macro_use]
+mod fixtures;
+
+/// A node id that does not fit in a `u64`.
+type BigId = u128;
+
+type BigRaft = Raft<ClientRequest, ClientResponse, BigRouter, MemStore<BigId>, BigId>;
+
+/// A network routing RPCs to nodes by their `u128` ids.
+#[derive(Default)]
+struct BigRouter {
+    nodes: RwLock<BTreeMap<BigId, BigRaft>>,
+}
+
+impl BigRouter {
+    async fn get(&self, target: BigId) -> Result<BigRaft> {
+        let nodes = self.nodes.read().await;
+        let raft = nodes.get(&target).ok_or_else(|| anyhow!("node {} not found", target))?;
+        Ok(raft.clone())
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<ClientRequest, BigId> for BigRouter {
+    async fn send_append_entries(
+        &self,
+        target: BigId,
+        rpc: AppendEntriesRequest<ClientRequest, BigId>,
+    ) -> Result<AppendEntriesResponse> {
+        Ok(self.get(target).await?.append_entries(rpc).await?)
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: BigId,
+        rpc: InstallSnapshotRequest<BigId>,
+    ) -> Result<InstallSnapshotResponse> {
+        Ok(self.get(target).await?.install_snapshot(rpc).await?)
+    }
+
+    async fn send_vote(&self, target: BigId, rpc: VoteRequest<BigId>) -> Result<VoteResponse> {
+        Ok(self.get(target).await?.vote(rpc).await?)
+    }
+
+    async fn send_timeout_now(&self, target: BigId, rpc: TimeoutNowRequest<BigId>) -> Result<TimeoutNowResponse> {
+        Ok(self.get(target).await?.timeout_now(rpc).await?)
+    }
+}
+
+/// The raft stack works with a node id type other than the default `u64`.
+///
+/// What does this test do?
+///
+/// - brings up 3 nodes with `u128` ids that do not fit in a `u64`, backed by `MemStore<u128>`.
+/// - initializes a cluster of the 3 voters, asserts the leader is elected and known by every node.
+/// - writes to the leader, asserts every node applies the write.
+/// - asserts the hard state in the store records the vote with the `u128` id.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn custom_node_id() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(BigRouter::default());
+
+    let ids: Vec<BigId> = (1..=3).map(|i| (i << 64) + i).collect();
+    let mut stores = BTreeMap::new();
+    for id in ids.iter().copied() {
+        let sto = Arc::new(MemStore::<BigId>::new(id).await);
+        let raft = Raft::new(id, config.clone(), router.clone(), sto.clone());
+        router.nodes.write().await.insert(id, raft);
+        stores.insert(id, sto);
+    }
+
+    let leader_id = ids[0];
+    let leader = router.get(leader_id).await?;
+
+    tracing::info!("--- initialize the cluster on node {}", leader_id);
+    {
+        leader.initialize(ids.iter().copied().collect()).await?;
+
+        leader.wait(timeout()).state(State::Leader, "node becomes leader").await?;
+        for id in ids.iter() {
+            router.get(*id).await?.wait(timeout()).current_leader(leader_id, "leader is known").await?;
+        }
+    }
+
+    tracing::info!("--- write to the leader");
+    {
+        let resp = leader
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 1,
+                status: "bar".to_string(),
+            }))
+            .await?;
+
+        let index = resp.log_id.index;
+        for id in ids.iter() {
+            router
+                .get(*id)
+                .await?
+                .wait(timeout())
+                .metrics(|x| x.last_applied == index, "write is applied")
+                .await?;
+        }
+    }
+
+    for id in ids.iter() {
+        let hs = stores[id].read_hard_state().await?.unwrap();
+        assert_eq!(Some(leader_id), hs.voted_for, "node {} voted for the leader", id);
+    }
+
+    for id in ids.iter() {
+        router.get(*id).await?.shutdown().await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,84 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Durability;
+use openraft::Raft;
+
+#[macro_use]
+mod fixtures;
+
+/// Raft requests syncs from the storage according to `Config::durability`.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster for every durability policy, with a store counting the syncs requested.
+/// - initializes the cluster, which saves the hard state when electing node-0, and writes a log.
+/// - asserts every hard state write is synced only with `FullSync`.
+/// - asserts appended logs are synced with `FullSync` and `LogSyncOnly`, and never with `Async`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn durability() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    for durability in [Durability::FullSync, Durability::LogSyncOnly, Durability::Async] {
+        tracing::info!("--- durability: {:?}", durability);
+
+        let config = Arc::new(
+            Config {
+                durability: durability.clone(),
+                ..Default::default()
+            }
+            .validate()?,
+        );
+
+        let sto = Arc::new(HookedStore::new(0).await);
+        let raft = Raft::new(0, config.clone(), Arc::new(StubNetwork::default()), sto.clone());
+
+        raft.initialize(btreeset! {0}).await?;
+        raft.wait(timeout()).metrics(|x| x.leader_ready, "node-0 is ready").await?;
+
+        raft.client_write(ClientWriteRequest::new(ClientRequest {
+            client: "foo".to_string(),
+            serial: 0,
+            status: "bar".to_string(),
+        }))
+        .await?;
+
+        raft.shutdown().await?;
+
+        let saves = sto.hard_state_saves.load(Ordering::Relaxed);
+        let hard_state_syncs = sto.hard_state_flushes.load(Ordering::Relaxed);
+        let log_syncs = sto.flushes.load(Ordering::Relaxed);
+
+        assert!(saves > 0, "the hard state is saved on election");
+
+        match durability {
+            Durability::FullSync => {
+                assert_eq!(saves, hard_state_syncs, "every hard state write is synced");
+                assert!(log_syncs > 0, "logs are synced");
+            }
+            Durability::LogSyncOnly => {
+                assert_eq!(0, hard_state_syncs, "hard state writes are not synced");
+                assert!(log_syncs > 0, "logs are synced");
+            }
+            Durability::Async => {
+                assert_eq!(0, hard_state_syncs, "hard state writes are not synced");
+                assert_eq!(0, log_syncs, "logs are not synced");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::config::STALE_LOG_BACKOFF_ELECTION_TIMEOUTS;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A node whose log is stale stops starting elections with `backoff_on_stale_log`, until its log advances.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and blocks every AppendEntries to node-2, so that it does not hear from the
+///   leader.
+/// - writes some logs, which node-2 does not receive.
+/// - asserts node-2, being rejected for its stale log, becomes `Paused`, and no longer asks for votes.
+/// - unblocks AppendEntries to node-2, asserts it catches up and becomes a follower again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_backoff_on_stale_log() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            backoff_on_stale_log: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- block AppendEntries to node-2 and write logs it does not receive");
+    {
+        router.set_append_entries_send_delay(2, 3_600_000);
+
+        router.client_request_many(0, "foo", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "write 10 logs").await?;
+    }
+
+    tracing::info!("--- node-2 backs off from elections");
+    {
+        router.wait_for_state(&btreeset! {2}, State::Paused, timeout(), "node-2 is paused").await?;
+
+        let asked = router.pre_votes_sent(0) + router.pre_votes_sent(1);
+        let term = router.get_raft_handle(&2).await?.metrics().borrow().current_term;
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        let m = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(State::Paused, m.state);
+        assert_eq!(term, m.current_term, "the term does not increase while paused");
+        assert_eq!(
+            asked,
+            router.pre_votes_sent(0) + router.pre_votes_sent(1),
+            "no vote is asked for while paused"
+        );
+    }
+
+    tracing::info!("--- unblock AppendEntries to node-2, it resumes once its log advances");
+    {
+        router.set_append_entries_send_delay(2, 0);
+
+        router.wait_for_log(&btreeset! {2}, want, timeout(), "node-2 catches up").await?;
+        router.wait_for_state(&btreeset! {2}, State::Follower, timeout(), "node-2 is a follower").await?;
+    }
+
+    Ok(())
+}
+
+/// A node backing off for its stale log resumes elections after a while even if its log does not advance.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and blocks every AppendEntries to node-2, then writes some logs.
+/// - asserts node-2 becomes `Paused`.
+/// - keeps AppendEntries to node-2 blocked, asserts it asks for votes again within
+///   `STALE_LOG_BACKOFF_ELECTION_TIMEOUTS` election timeouts.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_backoff_on_stale_log_is_bounded() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            backoff_on_stale_log: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- block AppendEntries to node-2 and write logs it does not receive");
+    {
+        router.set_append_entries_send_delay(2, 3_600_000);
+
+        router.client_request_many(0, "foo", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "write 10 logs").await?;
+    }
+
+    tracing::info!("--- node-2 resumes elections though its log does not advance");
+    {
+        router.wait_for_state(&btreeset! {2}, State::Paused, timeout(), "node-2 is paused").await?;
+
+        let asked = router.pre_votes_sent(0) + router.pre_votes_sent(1);
+
+        let backoff = Duration::from_millis(config.election_timeout_max * STALE_LOG_BACKOFF_ELECTION_TIMEOUTS);
+        let deadline = tokio::time::Instant::now() + backoff * 2;
+
+        while router.pre_votes_sent(0) + router.pre_votes_sent(1) == asked {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "node-2 asks for votes again within {:?}",
+                backoff
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -43,6 +43,7 @@ async fn elect_compare_last_log() -> Result<()> {
         sto0.append_to_log(&[&Entry {
             log_id: LogId { term: 2, index: 1 },
             payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+            request_id: None,
         }])
         .await?;
     }
@@ -59,10 +60,12 @@ async fn elect_compare_last_log() -> Result<()> {
             &Entry {
                 log_id: LogId { term: 1, index: 1 },
                 payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+                request_id: None,
             },
             &Entry {
                 log_id: LogId { term: 1, index: 2 },
                 payload: EntryPayload::Blank,
+                request_id: None,
             },
         ])
         .await?;
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// With `leader_stickiness`, the leader survives a heartbeat gap longer than the election timeout, while without it
+/// the followers elect another leader.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, without stickiness.
+/// - several times, isolates the leader for a gap longer than the election timeout, restores it, and counts the times
+///   the leader and the term are retained.
+/// - does the same with a cluster with stickiness, and asserts the leader is retained more often, i.e., every time.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_leader_stickiness() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let trials = 3;
+
+    tracing::info!("--- heartbeat gaps without stickiness");
+    let retained_without = {
+        let config = Arc::new(Config::default().validate()?);
+        count_retained(config, trials).await?
+    };
+
+    tracing::info!("--- heartbeat gaps with stickiness");
+    let retained_with = {
+        let config = Arc::new(
+            Config {
+                // Longer than the gap plus the replication retry backoff of the leader.
+                leader_stickiness: 3_000,
+                ..Default::default()
+            }
+            .validate()?,
+        );
+        count_retained(config, trials).await?
+    };
+
+    tracing::info!(retained_without, retained_with, "leaders retained");
+
+    assert!(retained_with > retained_without);
+    assert_eq!(trials, retained_with, "the leader is always retained with stickiness");
+
+    Ok(())
+}
+
+/// Isolate the leader of a new cluster for a gap, `trials` times, and count the times the leader and its term are
+/// retained by every node.
+async fn count_retained(config: Arc<Config>, trials: u64) -> Result<u64> {
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let gap = Duration::from_millis(config.election_timeout_max + 200);
+
+    let mut retained = 0;
+    for _ in 0..trials {
+        let leader = router.leader().await.expect("a leader is elected");
+        let term = router.get_raft_handle(&leader).await?.metrics().borrow().current_term;
+
+        router.isolate_node(leader).await;
+        tokio::time::sleep(gap).await;
+        router.restore_node(leader).await;
+
+        // Let the cluster settle, on either the retained leader or a new one.
+        tokio::time::sleep(Duration::from_millis(2_000)).await;
+
+        let mut all_retained = true;
+        for id in [0, 1, 2] {
+            let m = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            all_retained &= m.current_leader == Some(leader) && m.current_term == term;
+        }
+
+        if all_retained {
+            retained += 1;
+        }
+    }
+
+    Ok(retained)
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftEvent;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A node stops starting elections after `max_consecutive_failed_elections` failed rounds, until it hears from a
+/// leader.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters without pre-vote, pausing elections after 3 failed rounds.
+/// - isolates node-2, which keeps failing elections, and asserts it becomes `Paused` and emits
+///   `RaftEvent::ElectionsPaused`.
+/// - asserts the term of node-2 no longer increases while it is paused.
+/// - restores node-2, and asserts it becomes a follower again once a leader reaches it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pause_after_failed_elections() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: false,
+            max_consecutive_failed_elections: 3,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    let mut events = router.get_raft_handle(&2).await?.metrics_events();
+
+    tracing::info!("--- isolate node-2, it pauses elections after 3 failed rounds");
+    let paused_term = {
+        router.isolate_node(2).await;
+
+        let m = router.wait(&2, timeout()).await?.state(State::Paused, "node-2 is paused").await?;
+        assert!(m.current_term <= term + 3, "at most 3 elections are started");
+
+        let ev = tokio::time::timeout(timeout().unwrap(), events.next()).await?;
+        assert_eq!(Some(RaftEvent::ElectionsPaused(m.current_term)), ev);
+
+        m.current_term
+    };
+
+    tracing::info!("--- node-2 no longer starts elections");
+    {
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        let m = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert_eq!(State::Paused, m.state);
+        assert_eq!(paused_term, m.current_term, "the term does not increase while paused");
+    }
+
+    tracing::info!("--- restore node-2, it resumes as a follower when a leader is heard from");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(
+                |x| x.state == State::Follower && x.current_leader.is_some(),
+                "node-2 follows a leader",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
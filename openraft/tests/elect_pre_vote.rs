@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::VoteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A partitioned node does not disrupt the cluster after rejoining, with pre-vote enabled.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, with pre-vote enabled by default.
+/// - isolates node-2 for several election timeouts, asserts node-2 does not increase its term.
+/// - restores node-2, asserts node-2 follows the existing leader and the term of the cluster does not change.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_partitioned_node_rejoin() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    assert!(config.enable_pre_vote);
+
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- isolate node-2 for several election timeouts");
+    {
+        router.isolate_node(2).await;
+
+        router.wait(&2, timeout()).await?.state(State::Candidate, "node-2 starts pre-vote").await?;
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 5)).await;
+
+        let m = router.wait(&2, timeout()).await?.metrics(|_| true, "get node-2 metrics").await?;
+        assert_eq!(term, m.current_term, "a partitioned node does not increase its term");
+    }
+
+    tracing::info!("--- restore node-2");
+    {
+        router.restore_node(2).await;
+
+        router.client_request_many(0, "foo", 1).await;
+        n_logs += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "node-2 rejoins").await?;
+        router.wait(&2, timeout()).await?.current_leader(0, "node-2 follows node-0").await?;
+
+        for id in [0, 1, 2] {
+            let m = router.wait(&id, timeout()).await?.metrics(|_| true, "get metrics").await?;
+            assert_eq!(term, m.current_term, "node-{} term does not change", id);
+            assert_eq!(Some(0), m.current_leader, "node-{} leader does not change", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Without pre-vote, a partitioned node forces an election with a higher term after rejoining.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, with pre-vote disabled.
+/// - isolates node-2 for several election timeouts, asserts node-2 increases its term.
+/// - restores node-2, asserts the term of the cluster advances.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_without_pre_vote_partitioned_node_rejoin() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- isolate node-2 for several election timeouts");
+    {
+        router.isolate_node(2).await;
+
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(|m| m.current_term > term, "node-2 increases its term")
+            .await?;
+    }
+
+    tracing::info!("--- restore node-2");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|m| m.current_term > term, "the cluster term advances")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// A pre-vote is granted only for a term greater than the voter's, with an up-to-date log.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node, which hears from no leader.
+/// - asserts a pre-vote for its own term is rejected, even with an up-to-date log.
+/// - asserts a pre-vote for a greater term with an up-to-date log is granted, and does not change the term.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_greater_term() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+    let n0 = router.get_raft_handle(&0).await?;
+    let term = n0.metrics().borrow().current_term;
+
+    tracing::info!("--- a pre-vote for the same term is rejected");
+    {
+        let resp = n0
+            .vote(VoteRequest {
+                pre_vote: true,
+                ..VoteRequest::new(term, 1, LogId::new(0, 0))
+            })
+            .await?;
+        assert!(!resp.vote_granted);
+    }
+
+    tracing::info!("--- a pre-vote for a greater term is granted");
+    {
+        let resp = n0
+            .vote(VoteRequest {
+                pre_vote: true,
+                ..VoteRequest::new(term + 1, 1, LogId::new(0, 0))
+            })
+            .await?;
+        assert!(resp.vote_granted);
+        assert_eq!(term, resp.term, "a pre-vote does not change the term");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
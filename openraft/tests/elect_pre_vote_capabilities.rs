@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::config::CAPABILITIES_RETRY_HEARTBEATS;
+use openraft::Config;
+use openraft::NodeCapabilities;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A candidate does not send a pre-vote to a peer that does not support it.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, in which node-2 reports the capabilities of a legacy node.
+/// - isolates the leader node-0, so that node-1 and node-2 run pre-vote and elect a new leader.
+/// - asserts no pre-vote is ever sent to node-2, and its capabilities are queried if node-1 is elected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_capabilities() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    assert!(config.enable_pre_vote);
+
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.set_capabilities(2, NodeCapabilities::legacy());
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- isolate node-0, node-1 and node-2 elect a new leader");
+    {
+        router.isolate_node(0).await;
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.current_term > term && x.current_leader.is_some() && x.current_leader != Some(0),
+                "node-1 or node-2 becomes the leader",
+            )
+            .await?;
+
+        if m.current_leader == Some(1) {
+            // node-1 must have passed a pre-vote round, in which node-2 is checked.
+            assert!(router.capabilities_queried(2) > 0, "capabilities of node-2 are queried");
+        }
+        assert_eq!(0, router.pre_votes_sent(2), "no pre-vote is sent to node-2");
+    }
+
+    Ok(())
+}
+
+/// A candidate that fails to query the capabilities of a peer does not query them again in every pre-vote round.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - isolates node-0 and node-2, so that node-1 keeps running pre-vote rounds that fail.
+/// - asserts the capabilities of node-2 are queried by node-1 once in a retry interval, not in every round.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_capabilities_failure_cached() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 150,
+            election_timeout_max: 200,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+    router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+
+    tracing::info!("--- isolate node-0 and node-2, node-1 keeps running pre-vote");
+    {
+        let queried = router.capabilities_queried(2);
+
+        router.isolate_node(0).await;
+        router.isolate_node(2).await;
+
+        // Several pre-vote rounds fit in it, but it is shorter than the retry interval.
+        let rounds = 4;
+        assert!(rounds * config.election_timeout_max < config.heartbeat_interval * CAPABILITIES_RETRY_HEARTBEATS);
+        tokio::time::sleep(Duration::from_millis(rounds * config.election_timeout_max)).await;
+
+        // node-0 may have stepped down and queried it too.
+        let n = router.capabilities_queried(2) - queried;
+        assert!(n > 0, "capabilities of node-2 are queried");
+        assert!(n <= 2, "a failed query is not repeated in every round: {}", n);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A voter that never responds to a vote request does not stop the other voters from electing a leader.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 5 voters with `vote_request_timeout` set, in which node-4 never responds to a vote request.
+/// - isolates the leader node-0, so that a quorum of the other 3 voters have to elect a new leader.
+/// - asserts a new leader is elected, and if it is not node-4, vote requests are sent to node-4, not answered, and
+///   given up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_vote_request_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            vote_request_timeout: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2,3,4}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- node-4 stops responding to votes, isolate node-0, the others elect a new leader");
+    {
+        router.set_unresponsive_voter(4);
+        router.isolate_node(0).await;
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.current_term > term && x.current_leader.is_some() && x.current_leader != Some(0),
+                "a new leader is elected",
+            )
+            .await?;
+
+        if m.current_leader != Some(4) {
+            assert!(router.unresponsive_votes(4) > 0, "vote requests are sent to node-4");
+
+            // The unanswered requests are given up, not left waiting for node-4 forever.
+            tokio::time::timeout(timeout().unwrap(), async {
+                while router.abandoned_votes(4).is_empty() {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
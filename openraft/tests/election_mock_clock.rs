@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::StubNetwork;
+use futures::future::BoxFuture;
+use maplit::btreeset;
+use memstore::MemStore;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::raft::Membership;
+use openraft::Clock;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+use openraft::State;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// A clock that only moves when the test advances it.
+struct MockClock {
+    tx: watch::Sender<Instant>,
+}
+
+impl MockClock {
+    fn new() -> Self {
+        let (tx, _) = watch::channel(Instant::now());
+        Self { tx }
+    }
+
+    fn advance(&self, d: Duration) {
+        self.tx.send_modify(|now| *now += d);
+    }
+
+    /// The number of timers waiting for the clock.
+    fn n_sleeping(&self) -> usize {
+        self.tx.receiver_count()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.tx.borrow()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        let mut rx = self.tx.subscribe();
+        Box::pin(async move {
+            while *rx.borrow_and_update() < deadline {
+                if rx.changed().await.is_err() {
+                    futures::future::pending::<()>().await;
+                }
+            }
+        })
+    }
+}
+
+/// A follower driven by an injected clock starts an election once the clock passes the election timeout.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node with a mock clock.
+/// - sends it an AppendEntries from leader node-1 with the membership `{0,1}`, it becomes follower.
+/// - advances the mock clock past the election timeout.
+/// - asserts the follower becomes candidate in a new term, without sleeping for the election timeout.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn election_mock_clock() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5_000));
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let clock = Arc::new(MockClock::new());
+    let sto = Arc::new(MemStore::new(0).await);
+    let raft = Raft::with_clock(0, config.clone(), Arc::new(StubNetwork::default()), sto, clock.clone());
+
+    raft.wait(timeout).state(State::Uninitialized, "pristine node is uninitialized").await?;
+
+    tracing::info!("--- join the cluster of node-1 as a follower");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                term: 1,
+                leader_id: 1,
+                prev_log_id: LogId::new(0, 0),
+                entries: vec![Entry {
+                    log_id: LogId::new(1, 1),
+                    payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+                    request_id: None,
+                }],
+                leader_commit: LogId::new(1, 1),
+                compressed_entries: None,
+            })
+            .await?;
+        assert!(resp.success());
+
+        raft.wait(timeout).state(State::Follower, "node-0 is follower").await?;
+    }
+
+    tracing::info!("--- advance the clock past the election timeout");
+    {
+        // The follower is waiting for the election timeout.
+        while clock.n_sleeping() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        clock.advance(Duration::from_millis(config.election_timeout_max + 1));
+
+        let m = raft.wait(timeout).state(State::Candidate, "node-0 starts an election").await?;
+        assert_eq!(2, m.current_term);
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
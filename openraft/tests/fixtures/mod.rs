@@ -10,6 +10,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Once;
 use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::anyhow;
 use anyhow::Context;
@@ -25,6 +26,8 @@ use openraft::async_trait::async_trait;
 use openraft::error::AddLearnerError;
 use openraft::error::ClientReadError;
 use openraft::error::ClientWriteError;
+use openraft::error::TransferLeadershipError;
+use openraft::error::UpdateConfigError;
 use openraft::metrics::Wait;
 use openraft::raft::AddLearnerResponse;
 use openraft::raft::AppendEntriesRequest;
@@ -35,13 +38,17 @@ use openraft::raft::Entry;
 use openraft::raft::EntryPayload;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
 use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
 use openraft::storage::RaftStorage;
 use openraft::AppData;
 use openraft::Config;
+use openraft::ConfigDelta;
 use openraft::DefensiveCheck;
 use openraft::LogId;
+use openraft::NodeCapabilities;
 use openraft::NodeId;
 use openraft::Raft;
 use openraft::RaftMetrics;
@@ -58,6 +65,7 @@ use tracing_appender::non_blocking::WorkerGuard;
 use crate::fixtures::logging::init_file_logging;
 
 pub mod logging;
+pub mod store;
 
 macro_rules! func_name {
     () => {{
@@ -89,6 +97,9 @@ pub type StoreWithDefensive = StoreExt<ClientRequest, ClientResponse, MemStore>;
 /// A concrete Raft type used during testing.
 pub type MemRaft = Raft<MemClientRequest, MemClientResponse, RaftRouter, StoreWithDefensive>;
 
+/// How long in milli second a client request keeps being redirected to the leader before its error is returned.
+const REDIRECT_TIMEOUT: u64 = 5_000;
+
 pub fn init_default_ut_tracing() {
     static START: Once = Once::new();
 
@@ -122,11 +133,72 @@ pub struct RaftRouter {
     /// To enumlate network delay for sending, in milli second.
     /// 0 means no delay.
     send_delay: u64,
+
+    /// Whether a client request rejected with `ForwardToLeader` is sent again to the leader it is forwarded to.
+    redirect_to_leader: bool,
+
+    /// The number of AppendEntries RPCs sent to every target node.
+    append_entries_sent: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of AppendEntries RPCs attempted to every target node, including those failed by isolation.
+    append_entries_attempts: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of AppendEntries RPCs with compressed entries sent to every target node.
+    compressed_append_entries_sent: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of pre-vote RequestVote RPCs sent to every target node.
+    pre_votes_sent: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The capabilities reported for a node, instead of `NodeCapabilities::current()`.
+    capabilities: Mutex<BTreeMap<NodeId, NodeCapabilities>>,
+
+    /// The number of times the capabilities of every node are queried, including those failed by isolation.
+    capabilities_queried: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of RequestVote RPCs sent to every node that never responds to them.
+    unresponsive_votes: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The time every RequestVote RPC to an unresponsive node waited before the sender gave up on it.
+    abandoned_votes: Mutex<BTreeMap<NodeId, Vec<Duration>>>,
+
+    /// The number of non-empty InstallSnapshot chunks still to corrupt before sending to every target node.
+    corrupt_snapshot_chunks: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The format version to send every InstallSnapshot chunk to a target node with, instead of the real one.
+    snapshot_format_version: Mutex<BTreeMap<NodeId, u32>>,
+
+    /// The delay in milli second of every InstallSnapshot RPC sent to a target node.
+    snapshot_send_delay: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The upper bound in milli second of a random delay added to every InstallSnapshot RPC sent to a target node,
+    /// which reorders the chunks in flight at once.
+    snapshot_send_jitter: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The number of InstallSnapshot RPCs delivered to every target node.
+    snapshot_chunks_sent: Mutex<BTreeMap<NodeId, u64>>,
+
+    /// The delay in milli second of every AppendEntries RPC sent to a target node.
+    append_entries_send_delay: Mutex<BTreeMap<NodeId, u64>>,
+}
+
+/// Records how long a hung RPC has waited when the sender drops it.
+struct HungRpc<'a> {
+    target: NodeId,
+    start: Instant,
+    waited: &'a Mutex<BTreeMap<NodeId, Vec<Duration>>>,
+}
+
+impl<'a> Drop for HungRpc<'a> {
+    fn drop(&mut self) {
+        let mut waited = self.waited.lock().unwrap();
+        waited.entry(self.target).or_default().push(self.start.elapsed());
+    }
 }
 
 pub struct Builder {
     config: Arc<Config>,
     send_delay: u64,
+    redirect_to_leader: bool,
 }
 
 impl Builder {
@@ -135,19 +207,45 @@ impl Builder {
         self
     }
 
+    /// Send a client request again to the current leader when the target node is not the leader, so that a test does
+    /// not have to track the leader across leadership changes.
+    pub fn redirect_to_leader(mut self, redirect: bool) -> Self {
+        self.redirect_to_leader = redirect;
+        self
+    }
+
     pub fn build(self) -> RaftRouter {
         RaftRouter {
             config: self.config,
             routing_table: Default::default(),
             isolated_nodes: Default::default(),
             send_delay: self.send_delay,
+            redirect_to_leader: self.redirect_to_leader,
+            append_entries_sent: Default::default(),
+            append_entries_attempts: Default::default(),
+            compressed_append_entries_sent: Default::default(),
+            pre_votes_sent: Default::default(),
+            capabilities: Default::default(),
+            capabilities_queried: Default::default(),
+            unresponsive_votes: Default::default(),
+            abandoned_votes: Default::default(),
+            corrupt_snapshot_chunks: Default::default(),
+            snapshot_format_version: Default::default(),
+            snapshot_send_delay: Default::default(),
+            snapshot_send_jitter: Default::default(),
+            snapshot_chunks_sent: Default::default(),
+            append_entries_send_delay: Default::default(),
         }
     }
 }
 
 impl RaftRouter {
     pub fn builder(config: Arc<Config>) -> Builder {
-        Builder { config, send_delay: 0 }
+        Builder {
+            config,
+            send_delay: 0,
+            redirect_to_leader: false,
+        }
     }
 
     /// Create a new instance.
@@ -159,6 +257,99 @@ impl RaftRouter {
         self.send_delay = ms;
     }
 
+    /// Returns the number of AppendEntries RPCs sent to the target node so far.
+    pub fn append_entries_sent(&self, target: NodeId) -> u64 {
+        let sent = self.append_entries_sent.lock().unwrap();
+        sent.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of AppendEntries RPCs attempted to the target node so far, including the failed ones.
+    pub fn append_entries_attempts(&self, target: NodeId) -> u64 {
+        let attempts = self.append_entries_attempts.lock().unwrap();
+        attempts.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of AppendEntries RPCs with compressed entries sent to the target node so far.
+    pub fn compressed_append_entries_sent(&self, target: NodeId) -> u64 {
+        let sent = self.compressed_append_entries_sent.lock().unwrap();
+        sent.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of pre-vote RequestVote RPCs sent to the target node so far.
+    pub fn pre_votes_sent(&self, target: NodeId) -> u64 {
+        let sent = self.pre_votes_sent.lock().unwrap();
+        sent.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Let the target node report `caps` as its capabilities.
+    pub fn set_capabilities(&self, target: NodeId, caps: NodeCapabilities) {
+        self.capabilities.lock().unwrap().insert(target, caps);
+    }
+
+    /// Returns the number of times the capabilities of the target node are queried so far.
+    pub fn capabilities_queried(&self, target: NodeId) -> u64 {
+        let queried = self.capabilities_queried.lock().unwrap();
+        queried.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Make the target node never respond to a RequestVote RPC, as if every response were lost.
+    pub fn set_unresponsive_voter(&self, target: NodeId) {
+        self.unresponsive_votes.lock().unwrap().insert(target, 0);
+    }
+
+    /// Returns the number of RequestVote RPCs sent to an unresponsive target node so far.
+    pub fn unresponsive_votes(&self, target: NodeId) -> u64 {
+        let sent = self.unresponsive_votes.lock().unwrap();
+        sent.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Returns how long every RequestVote RPC to an unresponsive target node waited before it was abandoned, so far.
+    pub fn abandoned_votes(&self, target: NodeId) -> Vec<Duration> {
+        let waited = self.abandoned_votes.lock().unwrap();
+        waited.get(&target).cloned().unwrap_or_default()
+    }
+
+    /// Delay every InstallSnapshot RPC sent to the target node by `ms` milli seconds.
+    pub fn set_snapshot_send_delay(&self, target: NodeId, ms: u64) {
+        self.snapshot_send_delay.lock().unwrap().insert(target, ms);
+    }
+
+    /// Delay every InstallSnapshot RPC sent to the target node by a random time less than `ms` milli seconds.
+    pub fn set_snapshot_send_jitter(&self, target: NodeId, ms: u64) {
+        self.snapshot_send_jitter.lock().unwrap().insert(target, ms);
+    }
+
+    /// Returns the number of InstallSnapshot RPCs delivered to the target node so far.
+    pub fn snapshot_chunks_sent(&self, target: NodeId) -> u64 {
+        let sent = self.snapshot_chunks_sent.lock().unwrap();
+        sent.get(&target).copied().unwrap_or_default()
+    }
+
+    /// Delay every AppendEntries RPC sent to the target node by `ms` milli seconds.
+    pub fn set_append_entries_send_delay(&self, target: NodeId, ms: u64) {
+        self.append_entries_send_delay.lock().unwrap().insert(target, ms);
+    }
+
+    /// Corrupt the data of the next `n` non-empty snapshot chunks sent to the target node.
+    pub fn corrupt_snapshot_chunks(&self, target: NodeId, n: u64) {
+        self.corrupt_snapshot_chunks.lock().unwrap().insert(target, n);
+    }
+
+    /// Send every snapshot chunk to the target node as of format version `version`, or as it is if `None`.
+    pub fn set_snapshot_format_version(&self, target: NodeId, version: Option<u32>) {
+        let mut versions = self.snapshot_format_version.lock().unwrap();
+        match version {
+            Some(v) => versions.insert(target, v),
+            None => versions.remove(&target),
+        };
+    }
+
+    /// Returns the number of snapshot chunks still to corrupt before sending to the target node.
+    pub fn snapshot_chunks_to_corrupt(&self, target: NodeId) -> u64 {
+        let to_corrupt = self.corrupt_snapshot_chunks.lock().unwrap();
+        to_corrupt.get(&target).copied().unwrap_or_default()
+    }
+
     async fn rand_send_delay(&self) {
         if self.send_delay == 0 {
             return;
@@ -186,7 +377,7 @@ impl RaftRouter {
         tracing::info!("--- wait for init node to ready");
 
         self.wait_for_log(&btreeset![0], want, timeout(), "empty").await?;
-        self.wait_for_state(&btreeset![0], State::Learner, timeout(), "empty").await?;
+        self.wait_for_state(&btreeset![0], State::Uninitialized, timeout(), "empty").await?;
 
         tracing::info!("--- initializing single node cluster: {}", 0);
 
@@ -258,6 +449,14 @@ impl RaftRouter {
         sto
     }
 
+    /// Create and register a new Raft node bearing the given ID, with a config other than the one of the router.
+    pub async fn new_raft_node_with_config(self: &Arc<Self>, id: NodeId, config: Arc<Config>) {
+        let sto = self.new_store(id).await;
+        let node = Raft::new(id, config, self.clone(), sto.clone());
+        let mut rt = self.routing_table.write().await;
+        rt.insert(id, (node, sto));
+    }
+
     #[tracing::instrument(level = "debug", skip(self, sto))]
     pub async fn new_raft_node_with_sto(self: &Arc<Self>, id: NodeId, sto: Arc<StoreWithDefensive>) {
         let node = Raft::new(id, self.config.clone(), self.clone(), sto.clone());
@@ -341,6 +540,14 @@ impl RaftRouter {
         Ok(rst)
     }
 
+    /// Get a clone of the raft handle of a node.
+    pub async fn get_raft_handle(&self, node_id: &NodeId) -> Result<MemRaft> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(node_id).with_context(|| format!("node {} not found", node_id))?;
+
+        Ok(node.0.clone())
+    }
+
     pub async fn wait(&self, node_id: &NodeId, timeout: Option<Duration>) -> Result<Wait> {
         let rt = self.routing_table.read().await;
         let node = rt.get(node_id).with_context(|| format!("node {} not found", node_id))?;
@@ -416,6 +623,13 @@ impl RaftRouter {
         nodes.remove(&id);
     }
 
+    /// Update the config of a node at runtime.
+    pub async fn update_config(&self, target: NodeId, delta: ConfigDelta) -> Result<(), UpdateConfigError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.update_config(delta).await
+    }
+
     pub async fn add_learner(&self, leader: NodeId, target: NodeId) -> Result<AddLearnerResponse, AddLearnerError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
@@ -447,11 +661,31 @@ impl RaftRouter {
         &self,
         leader: NodeId,
         members: BTreeSet<NodeId>,
-        blocking: bool,
+        blocking_catch_up: bool,
+    ) -> Result<ClientWriteResponse<MemClientResponse>, ClientWriteError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.change_membership(members, blocking_catch_up).await
+    }
+
+    pub async fn promote_learner(
+        &self,
+        leader: NodeId,
+        learner: NodeId,
     ) -> Result<ClientWriteResponse<MemClientResponse>, ClientWriteError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.change_membership(members, blocking).await
+        node.0.promote_learner(learner).await
+    }
+
+    pub async fn transfer_leadership(
+        &self,
+        leader: NodeId,
+        target: Option<NodeId>,
+    ) -> Result<NodeId, TransferLeadershipError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.transfer_leadership(target).await
     }
 
     /// Send a client read request to the target node.
@@ -461,6 +695,13 @@ impl RaftRouter {
         node.0.client_read().await
     }
 
+    /// Send a linearizable read request to the target node.
+    pub async fn ensure_linearizable(&self, target: NodeId) -> Result<LogId, ClientReadError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.ensure_linearizable().await
+    }
+
     /// Send a client request to the target node, causing test failure on error.
     pub async fn client_request(&self, target: NodeId, client_id: &str, serial: u64) {
         let req = MemClientRequest {
@@ -492,6 +733,36 @@ impl RaftRouter {
         &self,
         target: NodeId,
         req: MemClientRequest,
+    ) -> std::result::Result<MemClientResponse, ClientWriteError> {
+        if !self.redirect_to_leader {
+            return self.send_client_request_to(target, req).await;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(REDIRECT_TIMEOUT);
+        let mut to = target;
+
+        loop {
+            let err = match self.send_client_request_to(to, req.clone()).await {
+                Err(ClientWriteError::ForwardToLeader(forward)) if Instant::now() < deadline => forward,
+                res => return res,
+            };
+
+            // Ask the original target again if the leader is unknown or unreachable, e.g., during an election.
+            to = match err.leader_id {
+                Some(leader) if leader != to && !self.isolated_nodes.read().await.contains(&leader) => leader,
+                _ => {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    target
+                }
+            };
+            tracing::info!(from = target, to, "redirect client request");
+        }
+    }
+
+    async fn send_client_request_to(
+        &self,
+        target: NodeId,
+        req: MemClientRequest,
     ) -> std::result::Result<MemClientResponse, ClientWriteError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node '{}' does not exist in routing table", target));
@@ -500,7 +771,7 @@ impl RaftRouter {
 
     //////////////////////////////////////////////////////////////////////////////////////////////
 
-    /// Assert that the cluster is in a pristine state, with all nodes as learners.
+    /// Assert that the cluster is in a pristine state, with all nodes uninitialized.
     pub async fn assert_pristine_cluster(&self) {
         let nodes = self.latest_metrics().await;
         for node in nodes.iter() {
@@ -511,8 +782,8 @@ impl RaftRouter {
             );
             assert_eq!(
                 node.state,
-                State::Learner,
-                "node is in state {:?}, expected Learner",
+                State::Uninitialized,
+                "node is in state {:?}, expected Uninitialized",
                 node.state
             );
             assert_eq!(
@@ -718,14 +989,24 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         rpc: AppendEntriesRequest<MemClientRequest>,
     ) -> Result<AppendEntriesResponse> {
         tracing::debug!("append_entries to id={} {:?}", target, rpc);
+        *self.append_entries_attempts.lock().unwrap().entry(target).or_default() += 1;
         self.rand_send_delay().await;
 
+        let delay = self.append_entries_send_delay.lock().unwrap().get(&target).copied();
+        if let Some(ms) = delay {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
         let addr = rt.get(&target).expect("target node not found in routing table");
         if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
             return Err(anyhow!("target node is isolated"));
         }
+        *self.append_entries_sent.lock().unwrap().entry(target).or_default() += 1;
+        if rpc.compressed_entries.is_some() {
+            *self.compressed_append_entries_sent.lock().unwrap().entry(target).or_default() += 1;
+        }
         let resp = addr.0.append_entries(rpc).await;
 
         tracing::debug!("append_entries: recv resp from id={} {:?}", target, resp);
@@ -733,15 +1014,41 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     }
 
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
-    async fn send_install_snapshot(&self, target: u64, rpc: InstallSnapshotRequest) -> Result<InstallSnapshotResponse> {
+    async fn send_install_snapshot(
+        &self,
+        target: u64,
+        mut rpc: InstallSnapshotRequest,
+    ) -> Result<InstallSnapshotResponse> {
         self.rand_send_delay().await;
 
+        let delay = self.snapshot_send_delay.lock().unwrap().get(&target).copied();
+        if let Some(ms) = delay {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+
+        let jitter = self.snapshot_send_jitter.lock().unwrap().get(&target).copied();
+        if let Some(ms) = jitter {
+            tokio::time::sleep(Duration::from_millis(rand::random::<u64>() % ms)).await;
+        }
+
+        if let Some(v) = self.snapshot_format_version.lock().unwrap().get(&target) {
+            rpc.meta.format_version = *v;
+        }
+
+        if let Some(n) = self.corrupt_snapshot_chunks.lock().unwrap().get_mut(&target) {
+            if *n > 0 && !rpc.data.is_empty() {
+                *n -= 1;
+                rpc.data[0] ^= 0xff;
+            }
+        }
+
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
         let addr = rt.get(&target).expect("target node not found in routing table");
         if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
             return Err(anyhow!("target node is isolated"));
         }
+        *self.snapshot_chunks_sent.lock().unwrap().entry(target).or_default() += 1;
         Ok(addr.0.install_snapshot(rpc).await?)
     }
 
@@ -749,14 +1056,101 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     async fn send_vote(&self, target: u64, rpc: VoteRequest) -> Result<VoteResponse> {
         self.rand_send_delay().await;
 
+        let unresponsive = match self.unresponsive_votes.lock().unwrap().get_mut(&target) {
+            Some(sent) => {
+                *sent += 1;
+                true
+            }
+            None => false,
+        };
+        if unresponsive {
+            let _hung = HungRpc {
+                target,
+                start: Instant::now(),
+                waited: &self.abandoned_votes,
+            };
+            std::future::pending::<()>().await;
+        }
+
         let rt = self.routing_table.read().await;
         let isolated = self.isolated_nodes.read().await;
         let addr = rt.get(&target).expect("target node not found in routing table");
         if isolated.contains(&target) || isolated.contains(&rpc.candidate_id) {
             return Err(anyhow!("target node is isolated"));
         }
+        if rpc.pre_vote {
+            *self.pre_votes_sent.lock().unwrap().entry(target).or_default() += 1;
+        }
         Ok(addr.0.vote(rpc).await?)
     }
+
+    /// Send a TimeoutNow RPC to the target Raft node.
+    async fn send_timeout_now(&self, target: u64, rpc: TimeoutNowRequest) -> Result<TimeoutNowResponse> {
+        self.rand_send_delay().await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.timeout_now(rpc).await?)
+    }
+
+    /// Report the capabilities set with `set_capabilities()`, or `NodeCapabilities::current()`.
+    async fn capabilities(&self, target: u64) -> Result<NodeCapabilities> {
+        *self.capabilities_queried.lock().unwrap().entry(target).or_default() += 1;
+
+        let isolated = self.isolated_nodes.read().await;
+        if isolated.contains(&target) {
+            return Err(anyhow!("target node is isolated"));
+        }
+
+        let caps = self.capabilities.lock().unwrap().get(&target).copied();
+        Ok(caps.unwrap_or_else(NodeCapabilities::current))
+    }
+}
+
+/// A network that can not reach any node: a test drives the node with RPCs directly.
+///
+/// Only a target with a delay in `vote_delays` responds, granting a vote request after that many milli seconds.
+#[derive(Default)]
+pub struct StubNetwork {
+    pub vote_delays: BTreeMap<NodeId, u64>,
+}
+
+#[async_trait]
+impl RaftNetwork<MemClientRequest> for StubNetwork {
+    async fn send_append_entries(
+        &self,
+        target: NodeId,
+        _rpc: AppendEntriesRequest<MemClientRequest>,
+    ) -> Result<AppendEntriesResponse> {
+        Err(anyhow!("node {} is unreachable", target))
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: NodeId,
+        _rpc: InstallSnapshotRequest,
+    ) -> Result<InstallSnapshotResponse> {
+        Err(anyhow!("node {} is unreachable", target))
+    }
+
+    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse> {
+        let delay = self.vote_delays.get(&target).ok_or_else(|| anyhow!("node {} is unreachable", target))?;
+        tokio::time::sleep(Duration::from_millis(*delay)).await;
+
+        Ok(VoteResponse {
+            term: rpc.term,
+            vote_granted: true,
+            last_log_id: LogId::new(0, 0),
+        })
+    }
+
+    async fn send_timeout_now(&self, target: NodeId, _rpc: TimeoutNowRequest) -> Result<TimeoutNowResponse> {
+        Err(anyhow!("node {} is unreachable", target))
+    }
 }
 
 pub enum ValueTest<T> {
@@ -785,5 +1179,6 @@ pub fn ent<T: AppData>(term: u64, index: u64) -> Entry<T> {
     Entry {
         log_id: LogId { term, index },
         payload: EntryPayload::Blank,
+        request_id: None,
     }
 }
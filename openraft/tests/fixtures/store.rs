@@ -0,0 +1,295 @@
+//! A `MemStore` with hooks for a test to count, slow down or fail the calls to the storage.
+
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::ops::RangeBounds;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use memstore::MemStore;
+use openraft::async_trait::async_trait;
+use openraft::raft::Entry;
+use openraft::storage::HardState;
+use openraft::storage::InitialState;
+use openraft::storage::Snapshot;
+use openraft::EffectiveMembership;
+use openraft::ErrorSubject;
+use openraft::ErrorVerb;
+use openraft::LogId;
+use openraft::NodeId;
+use openraft::RaftStorage;
+use openraft::SnapshotMeta;
+use openraft::SnapshotView;
+use openraft::StateMachineChanges;
+use openraft::StorageError;
+use openraft::StorageIOError;
+use tokio::sync::watch;
+
+/// What a `HookedStore` does besides calling the `MemStore`.
+#[derive(Default)]
+pub struct StoreHooks {
+    /// Sleep this long before applying entries to the state machine.
+    pub apply_delay: Duration,
+
+    /// Apply entries only when the gate is open.
+    pub apply_gate: Option<watch::Receiver<bool>>,
+
+    /// Sleep this long while taking a view of the state machine.
+    pub view_delay: Duration,
+
+    /// Sleep this long before building a snapshot, either with `do_log_compaction()` or from a view.
+    pub build_delay: Duration,
+
+    /// Do not provide a view of the state machine, thus a snapshot is built with `do_log_compaction()`.
+    pub no_view: bool,
+
+    /// Report a log storage size that grows by this many bytes with every appended entry.
+    pub entry_size: Option<u64>,
+}
+
+/// A `MemStore` that counts the calls to it, and does what its `StoreHooks` tell it.
+pub struct HookedStore {
+    pub inner: MemStore,
+    hooks: StoreHooks,
+
+    /// The number of calls to `save_hard_state()`.
+    pub hard_state_saves: AtomicU64,
+
+    /// The number of calls to `flush_hard_state()`.
+    pub hard_state_flushes: AtomicU64,
+
+    /// The number of calls to `flush()`.
+    pub flushes: AtomicU64,
+
+    /// The number of calls to `append_to_log()`, including the failed ones.
+    pub appends: AtomicU64,
+
+    /// The number of entries appended.
+    pub appended_entries: AtomicU64,
+
+    /// The number of the next calls to `append_to_log()` to fail with a transient error.
+    pub append_failures: AtomicU64,
+
+    /// The number of calls reading logs.
+    pub log_reads: AtomicU64,
+
+    /// The number of calls to `apply_to_state_machine()`.
+    pub applies: AtomicU64,
+
+    /// The max number of entries applied in one call.
+    pub max_apply_batch: AtomicU64,
+
+    /// The log indexes applied by every call to `apply_to_state_machine()`, in call order.
+    pub apply_batches: Mutex<Vec<Vec<u64>>>,
+
+    /// The `last_applied` every call to `snapshot_view()` is given, along with the last applied log id of the state
+    /// machine before and after taking the view.
+    pub views: Mutex<Vec<(LogId, LogId, LogId)>>,
+}
+
+impl HookedStore {
+    pub async fn new(id: NodeId) -> Self {
+        Self::with_hooks(id, StoreHooks::default()).await
+    }
+
+    pub async fn with_hooks(id: NodeId, hooks: StoreHooks) -> Self {
+        Self {
+            inner: MemStore::new(id).await,
+            hooks,
+            hard_state_saves: AtomicU64::new(0),
+            hard_state_flushes: AtomicU64::new(0),
+            flushes: AtomicU64::new(0),
+            appends: AtomicU64::new(0),
+            appended_entries: AtomicU64::new(0),
+            append_failures: AtomicU64::new(0),
+            log_reads: AtomicU64::new(0),
+            applies: AtomicU64::new(0),
+            max_apply_batch: AtomicU64::new(0),
+            apply_batches: Mutex::new(vec![]),
+            views: Mutex::new(vec![]),
+        }
+    }
+}
+
+/// A view of the state machine that builds a snapshot after a delay.
+struct SlowView {
+    inner: Box<dyn SnapshotView<Cursor<Vec<u8>>>>,
+    build_delay: Duration,
+}
+
+#[async_trait]
+impl SnapshotView<Cursor<Vec<u8>>> for SlowView {
+    async fn build_snapshot(self: Box<Self>) -> Result<Snapshot<Cursor<Vec<u8>>>, StorageError> {
+        tokio::time::sleep(self.build_delay).await;
+        self.inner.build_snapshot().await
+    }
+}
+
+#[async_trait]
+impl RaftStorage<ClientRequest, ClientResponse> for HookedStore {
+    type SnapshotData = Cursor<Vec<u8>>;
+
+    async fn get_membership_config(&self) -> Result<EffectiveMembership, StorageError> {
+        self.inner.get_membership_config().await
+    }
+
+    async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
+        self.inner.get_initial_state().await
+    }
+
+    async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError> {
+        self.hard_state_saves.fetch_add(1, Ordering::Relaxed);
+        self.inner.save_hard_state(hs).await
+    }
+
+    async fn flush_hard_state(&self) -> Result<(), StorageError> {
+        self.hard_state_flushes.fetch_add(1, Ordering::Relaxed);
+        self.inner.flush_hard_state().await
+    }
+
+    async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError> {
+        self.inner.read_hard_state().await
+    }
+
+    async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.log_reads.fetch_add(1, Ordering::Relaxed);
+        self.inner.get_log_entries(range).await
+    }
+
+    async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.log_reads.fetch_add(1, Ordering::Relaxed);
+        self.inner.try_get_log_entries(range).await
+    }
+
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest>>, StorageError> {
+        self.log_reads.fetch_add(1, Ordering::Relaxed);
+        self.inner.try_get_log_entry(log_index).await
+    }
+
+    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
+        self.inner.first_id_in_log().await
+    }
+
+    async fn first_known_log_id(&self) -> Result<LogId, StorageError> {
+        self.inner.first_known_log_id().await
+    }
+
+    async fn last_id_in_log(&self) -> Result<LogId, StorageError> {
+        self.inner.last_id_in_log().await
+    }
+
+    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
+        self.inner.last_applied_state().await
+    }
+
+    async fn delete_logs_from<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<(), StorageError> {
+        self.inner.delete_logs_from(range).await
+    }
+
+    async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<(), StorageError> {
+        self.appends.fetch_add(1, Ordering::Relaxed);
+
+        // Fail without writing the entries, while there are failures left.
+        let failures = self.append_failures.load(Ordering::Relaxed);
+        if failures > 0 {
+            self.append_failures.store(failures - 1, Ordering::Relaxed);
+            return Err(StorageIOError::transient(ErrorSubject::Logs, ErrorVerb::Write, anyhow!("disk busy")).into());
+        }
+
+        self.appended_entries.fetch_add(entries.len() as u64, Ordering::Relaxed);
+        self.inner.append_to_log(entries).await
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        self.inner.flush().await
+    }
+
+    async fn apply_to_state_machine(
+        &self,
+        entries: &[&Entry<ClientRequest>],
+    ) -> Result<Vec<ClientResponse>, StorageError> {
+        self.applies.fetch_add(1, Ordering::Relaxed);
+        self.max_apply_batch.fetch_max(entries.len() as u64, Ordering::Relaxed);
+        self.apply_batches.lock().unwrap().push(entries.iter().map(|x| x.log_id.index).collect());
+
+        if let Some(gate) = &self.hooks.apply_gate {
+            let mut gate = gate.clone();
+            let _ = gate.wait_for(|open| *open).await;
+        }
+
+        tokio::time::sleep(self.hooks.apply_delay).await;
+        self.inner.apply_to_state_machine(entries).await
+    }
+
+    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        tokio::time::sleep(self.hooks.build_delay).await;
+        self.inner.do_log_compaction().await
+    }
+
+    async fn snapshot_view(
+        &self,
+        last_applied: LogId,
+    ) -> Result<Option<Box<dyn SnapshotView<Self::SnapshotData>>>, StorageError> {
+        if self.hooks.no_view {
+            return Ok(None);
+        }
+
+        let (before, _) = self.inner.last_applied_state().await?;
+
+        // Give a concurrent apply, if there were any, the chance to change the state machine.
+        tokio::time::sleep(self.hooks.view_delay).await;
+        let view = self.inner.snapshot_view(last_applied).await?;
+
+        let (after, _) = self.inner.last_applied_state().await?;
+        self.views.lock().unwrap().push((last_applied, before, after));
+
+        let build_delay = self.hooks.build_delay;
+        Ok(view.map(|inner| Box::new(SlowView { inner, build_delay }) as Box<dyn SnapshotView<Self::SnapshotData>>))
+    }
+
+    async fn log_storage_size(&self) -> Result<u64, StorageError> {
+        match self.hooks.entry_size {
+            Some(size) => Ok(self.appended_entries.load(Ordering::Relaxed) * size),
+            None => self.inner.log_storage_size().await,
+        }
+    }
+
+    fn is_snapshot_format_supported(&self, format_version: u32) -> bool {
+        self.inner.is_snapshot_format_supported(format_version)
+    }
+
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+        self.inner.begin_receiving_snapshot().await
+    }
+
+    async fn finalize_snapshot_installation(
+        &self,
+        meta: &SnapshotMeta,
+        snapshot: Box<Self::SnapshotData>,
+    ) -> Result<StateMachineChanges, StorageError> {
+        self.inner.finalize_snapshot_installation(meta, snapshot).await
+    }
+
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+        self.inner.get_current_snapshot().await
+    }
+
+    async fn reset_state_machine(&self) -> Result<Option<LogId>, StorageError> {
+        self.inner.reset_state_machine().await
+    }
+}
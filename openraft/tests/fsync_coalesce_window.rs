@@ -0,0 +1,95 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// With `fsync_coalesce_window`, a leader syncs a burst of appended logs with a few flushes instead of one for
+/// every write.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a store counting log flushes, without coalescing.
+/// - writes a burst of logs concurrently, and asserts the logs are flushed at least once for every write.
+/// - does the same with a coalescing window of 20 ms, and asserts every write succeeds with far fewer flushes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn fsync_coalesce_window() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n: u64 = 50;
+
+    tracing::info!("--- without coalescing, every write is flushed");
+    {
+        let n_flush = write_burst(0, n).await?;
+        assert!(n_flush >= n, "flush for every write: {}", n_flush);
+    }
+
+    tracing::info!("--- with coalescing, a burst of writes is flushed a few times");
+    {
+        let n_flush = write_burst(20, n).await?;
+        assert!(n_flush <= n / 5, "writes are flushed together: {}", n_flush);
+    }
+
+    Ok(())
+}
+
+/// Write `n` logs concurrently to a single node cluster, and return the number of flushes for them.
+async fn write_burst(window: u64, n: u64) -> Result<u64> {
+    let config = Arc::new(
+        Config {
+            fsync_coalesce_window: window,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto = Arc::new(HookedStore::new(0).await);
+    let raft = Raft::new(0, config, router, sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+    raft.wait(timeout()).metrics(|x| x.last_applied == 1, "initial log is applied").await?;
+
+    let before = sto.flushes.load(Ordering::Relaxed);
+
+    let mut writes = vec![];
+    for serial in 0..n {
+        let raft = raft.clone();
+        writes.push(tokio::spawn(async move {
+            raft.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+            .await
+        }));
+    }
+
+    for w in writes {
+        w.await??;
+    }
+
+    raft.wait(timeout()).metrics(|x| x.last_applied == n + 1, "all applied").await?;
+    let n_flush = sto.flushes.load(Ordering::Relaxed) - before;
+
+    raft.shutdown().await?;
+
+    Ok(n_flush)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
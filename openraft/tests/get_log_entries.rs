@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::LogReadError;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// `Raft::get_log_entries()` reads a range of applied logs, and rejects a range it can not read safely.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster keeping 5 applied logs, and writes 20 logs to it.
+/// - reads a range of applied logs and asserts they are the same as in the storage.
+/// - asserts a range including purged logs fails with `Purged`.
+/// - asserts a range beyond the last applied log fails with `NotApplied`, or with `BeyondLastLog` if unapplied logs are
+///   allowed.
+/// - asserts an inverted range fails with `InvalidRange`, and an empty range reads nothing.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn get_log_entries() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_applied_log_to_keep: 5,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 20).await;
+    n_logs += 20;
+
+    router
+        .wait(&0, timeout())
+        .await?
+        .metrics(|x| x.last_applied == n_logs, "all logs are applied")
+        .await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let sto = router.get_storage_handle(&0).await?;
+
+    let first = sto.first_id_in_log().await?.expect("logs are not all purged").index;
+    assert!(first > 0, "some logs are purged");
+
+    tracing::info!("--- read applied logs");
+    {
+        let got = n0.get_log_entries(first, n_logs + 1, false).await?;
+        let want = sto.get_log_entries(first..n_logs + 1).await?;
+
+        let want_ids = want.iter().map(|x| x.log_id).collect::<Vec<_>>();
+        let got_ids = got.iter().map(|x| x.log_id).collect::<Vec<_>>();
+        assert_eq!(want_ids, got_ids);
+        assert_eq!(first, got[0].log_id.index);
+        assert_eq!(n_logs, got[got.len() - 1].log_id.index);
+
+        let got = n0.get_log_entries(n_logs - 1, n_logs, false).await?;
+        assert_eq!(1, got.len());
+        assert_eq!(n_logs - 1, got[0].log_id.index);
+    }
+
+    tracing::info!("--- a range including purged logs");
+    {
+        let res = n0.get_log_entries(first - 1, n_logs + 1, false).await;
+        match res {
+            Err(LogReadError::Purged { start, first: f }) => {
+                assert_eq!(first - 1, start);
+                assert_eq!(first, f);
+            }
+            _ => panic!("expect Purged, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- a range beyond the last applied log");
+    {
+        let res = n0.get_log_entries(first, n_logs + 2, false).await;
+        match res {
+            Err(LogReadError::NotApplied { end, last_applied, .. }) => {
+                assert_eq!(n_logs + 2, end);
+                assert_eq!(LogId::new(1, n_logs), last_applied);
+            }
+            _ => panic!("expect NotApplied, got: {:?}", res),
+        }
+
+        let res = n0.get_log_entries(first, n_logs + 2, true).await;
+        match res {
+            Err(LogReadError::BeyondLastLog { end, last_log_id, .. }) => {
+                assert_eq!(n_logs + 2, end);
+                assert_eq!(LogId::new(1, n_logs), last_log_id);
+            }
+            _ => panic!("expect BeyondLastLog, got: {:?}", res),
+        }
+
+        let got = n0.get_log_entries(first, n_logs + 1, true).await?;
+        assert_eq!(n_logs, got[got.len() - 1].log_id.index);
+    }
+
+    tracing::info!("--- an invalid or empty range");
+    {
+        let res = n0.get_log_entries(n_logs, first, false).await;
+        assert!(matches!(res, Err(LogReadError::InvalidRange { .. })), "got: {:?}", res);
+
+        let got = n0.get_log_entries(n_logs, n_logs, false).await?;
+        assert!(got.is_empty());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::StubNetwork;
+use memstore::ClientRequest;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+
+#[macro_use]
+mod fixtures;
+
+/// A heartbeat to an up to date node does not access the log in the storage.
+///
+/// What does this test do?
+///
+/// - brings up a node with a store counting the log accesses, and replicates 2 logs to it as leader node-0.
+/// - sends heartbeats with the last log of the node as `prev_log_id`.
+/// - asserts every heartbeat succeeds, without appending to or reading from the log.
+/// - asserts a heartbeat with a `prev_log_id` the node does not have still goes through the log consistency check.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn heartbeat_fast_path() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+
+    let sto = Arc::new(HookedStore::new(1).await);
+    let raft = Raft::new(1, config.clone(), Arc::new(StubNetwork::default()), sto.clone());
+
+    let heartbeat = |prev_log_id: LogId| AppendEntriesRequest::<ClientRequest> {
+        term: 1,
+        leader_id: 0,
+        prev_log_id,
+        entries: vec![],
+        leader_commit: LogId::new(1, 2),
+        compressed_entries: None,
+    };
+
+    tracing::info!("--- replicate 2 logs to node-1");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                term: 1,
+                leader_id: 0,
+                prev_log_id: LogId::new(0, 0),
+                entries: vec![
+                    Entry {
+                        log_id: LogId::new(1, 1),
+                        payload: EntryPayload::Blank,
+                        request_id: None,
+                    },
+                    Entry {
+                        log_id: LogId::new(1, 2),
+                        payload: EntryPayload::Blank,
+                        request_id: None,
+                    },
+                ],
+                leader_commit: LogId::new(1, 2),
+                compressed_entries: None,
+            })
+            .await?;
+        assert_eq!(Some(LogId::new(1, 2)), resp.matched);
+
+        raft.wait(timeout()).metrics(|x| x.last_applied == 2, "logs are applied").await?;
+        assert!(sto.appends.load(Ordering::Relaxed) > 0);
+    }
+
+    tracing::info!("--- heartbeats to an up to date node do not access the log");
+    {
+        let appends = sto.appends.load(Ordering::Relaxed);
+        let log_reads = sto.log_reads.load(Ordering::Relaxed);
+
+        for _ in 0..10 {
+            let resp = raft.append_entries(heartbeat(LogId::new(1, 2))).await?;
+            assert_eq!(Some(LogId::new(1, 2)), resp.matched);
+            assert_eq!(1, resp.term);
+        }
+
+        assert_eq!(appends, sto.appends.load(Ordering::Relaxed), "no append_to_log");
+        assert_eq!(log_reads, sto.log_reads.load(Ordering::Relaxed), "no log read");
+    }
+
+    tracing::info!("--- a heartbeat with an unknown prev_log_id is checked against the log");
+    {
+        let resp = raft.append_entries(heartbeat(LogId::new(1, 3))).await?;
+        assert_eq!(None, resp.matched);
+        assert_eq!(Some(LogId::new(1, 3)), resp.conflict);
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::InitializeError;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A cluster of several voters is brought up with one `initialize()`, and initializing it again is rejected.
+///
+/// What does this test do?
+///
+/// - brings 3 pristine nodes online, and initializes node-0 with all of them as the initial voters.
+/// - asserts a leader is elected and the initial membership log is replicated to every node.
+/// - initializes the leader and a follower again, and asserts both are rejected with `AlreadyInitialized`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn initialize_already_initialized() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    tracing::info!("--- initialize node-0 with 3 voters, on an empty log");
+    {
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty").await?;
+
+        router.initialize_with(0, btreeset! {0,1,2}).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "init").await?;
+        router.wait_for_state(&btreeset![0], State::Leader, None, "node-0 is leader").await?;
+
+        for id in [0, 1, 2] {
+            let m = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_eq!(
+                btreeset! {0,1,2},
+                m.membership_config.membership.get_ith_config(0).cloned().unwrap(),
+                "node-{} has all voters",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- initialize again on a non-empty log");
+    {
+        for id in [0, 1] {
+            let raft = router.get_raft_handle(&id).await?;
+            let res = raft.initialize(btreeset! {0,1,2}).await;
+
+            match res {
+                Err(InitializeError::AlreadyInitialized { last_log_id }) => {
+                    assert_eq!(LogId { term: 1, index: 1 }, last_log_id, "node-{}", id);
+                }
+                other => panic!("node-{}: expect AlreadyInitialized, got: {:?}", id, other),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::MemStore;
+use openraft::error::AppliedError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::raft::Membership;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+fn normal(term: u64, index: u64) -> Entry<ClientRequest> {
+    Entry {
+        log_id: LogId::new(term, index),
+        payload: EntryPayload::Normal(ClientRequest {
+            client: "foo".to_string(),
+            serial: index,
+            status: format!("{}-{}", term, index),
+        }),
+        request_id: None,
+    }
+}
+
+/// A follower tells applied, not yet applied and truncated logs apart.
+///
+/// What does this test do?
+///
+/// - sends a pristine node an AppendEntries from leader node-1 with 3 logs, of which only the first is committed.
+/// - asserts the committed log is applied, and the uncommitted or unknown logs are not yet applied.
+/// - asserts waiting for an uncommitted log times out.
+/// - waits for log 2 in the background, then leader node-1 of a new term replaces log 2 with its own and commits it.
+/// - asserts the replaced log 2 is reported `NotInLog`, by both `is_applied` and the waiting task.
+/// - asserts the new log 2 is applied.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn is_applied() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Keep the follower from starting an election during the test.
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 10_000,
+            election_timeout_max: 10_001,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let sto = Arc::new(MemStore::new(0).await);
+    let raft = Raft::new(0, config.clone(), Arc::new(StubNetwork::default()), sto);
+
+    raft.wait(timeout()).state(State::Uninitialized, "pristine node is uninitialized").await?;
+
+    tracing::info!("--- replicate 3 logs, commit only the first");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                term: 1,
+                leader_id: 1,
+                prev_log_id: LogId::new(0, 0),
+                entries: vec![
+                    Entry {
+                        log_id: LogId::new(1, 1),
+                        payload: EntryPayload::Membership(Membership::new_single(btreeset! {0,1})),
+                        request_id: None,
+                    },
+                    normal(1, 2),
+                    normal(1, 3),
+                ],
+                leader_commit: LogId::new(1, 1),
+                compressed_entries: None,
+            })
+            .await?;
+        assert!(resp.success());
+
+        raft.wait(timeout()).metrics(|x| x.last_applied == 1, "log 1 is applied").await?;
+    }
+
+    tracing::info!("--- applied and not yet applied logs");
+    {
+        assert!(raft.is_applied(LogId::new(1, 1)).await?);
+        raft.wait_applied(LogId::new(1, 1), Duration::from_millis(1_000)).await?;
+
+        assert!(!raft.is_applied(LogId::new(1, 2)).await?, "uncommitted log");
+        assert!(!raft.is_applied(LogId::new(1, 5)).await?, "log not yet replicated");
+
+        let res = raft.wait_applied(LogId::new(1, 2), Duration::from_millis(200)).await;
+        match res {
+            Err(AppliedError::Timeout { log_id, timeout }) => {
+                assert_eq!(LogId::new(1, 2), log_id);
+                assert_eq!(Duration::from_millis(200), timeout);
+            }
+            res => panic!("expect Timeout, got: {:?}", res),
+        }
+    }
+
+    let waiting = {
+        let raft = raft.clone();
+        tokio::spawn(async move { raft.wait_applied(LogId::new(1, 2), Duration::from_millis(5_000)).await })
+    };
+
+    tracing::info!("--- a new leader replaces log 2 and commits it");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                term: 2,
+                leader_id: 1,
+                prev_log_id: LogId::new(1, 1),
+                entries: vec![normal(2, 2)],
+                leader_commit: LogId::new(2, 2),
+                compressed_entries: None,
+            })
+            .await?;
+        assert!(resp.success());
+
+        raft.wait(timeout()).metrics(|x| x.last_applied == 2, "log 2 is applied").await?;
+    }
+
+    tracing::info!("--- the replaced log is not in the log");
+    {
+        match raft.is_applied(LogId::new(1, 2)).await {
+            Err(AppliedError::NotInLog { log_id, found }) => {
+                assert_eq!(LogId::new(1, 2), log_id);
+                assert_eq!(LogId::new(2, 2), found);
+            }
+            res => panic!("expect NotInLog, got: {:?}", res),
+        }
+
+        let res = waiting.await?;
+        assert!(
+            matches!(res, Err(AppliedError::NotInLog { .. })),
+            "waiting for a replaced log fails, got: {:?}",
+            res
+        );
+
+        assert!(raft.is_applied(LogId::new(2, 2)).await?);
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader tells when it last heard from every peer: the time advances with successful heartbeats, and goes stale
+/// when a peer stops responding.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, asserts the leader has heard from both followers, and there is no contact for an
+///   unknown peer, for the leader itself, or on a follower.
+/// - asserts the last contact with a follower advances as heartbeats succeed.
+/// - isolates node-2, asserts the last contact with it stays the same, while the one with node-1 keeps advancing.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn last_contact() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let n1 = router.get_raft_handle(&1).await?;
+
+    // Several heartbeat intervals.
+    let interval = Duration::from_millis(config.heartbeat_interval * 4);
+
+    tracing::info!("--- only the leader knows the last contact with its peers");
+    {
+        assert!(n0.last_contact(1).await.is_some());
+        assert!(n0.last_contact(2).await.is_some());
+
+        assert_eq!(None, n0.last_contact(9).await, "an unknown peer");
+        assert_eq!(None, n0.last_contact(0).await, "the leader itself");
+        assert_eq!(None, n1.last_contact(0).await, "a follower");
+    }
+
+    tracing::info!("--- the last contact advances with heartbeats");
+    {
+        let before = n0.last_contact(1).await.unwrap();
+        tokio::time::sleep(interval).await;
+        let after = n0.last_contact(1).await.unwrap();
+
+        assert!(after > before, "before: {:?}, after: {:?}", before, after);
+    }
+
+    tracing::info!("--- isolate node-2, the last contact with it goes stale");
+    {
+        router.isolate_node(2).await;
+
+        // Wait for the heartbeats sent before the isolation to be responded.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let n1_before = n0.last_contact(1).await.unwrap();
+        let n2_before = n0.last_contact(2).await.unwrap();
+
+        tokio::time::sleep(interval).await;
+        let n1_after = n0.last_contact(1).await.unwrap();
+        let n2_after = n0.last_contact(2).await.unwrap();
+
+        assert_eq!(n2_before, n2_after, "no response from the isolated node-2");
+        assert!(n2_after.elapsed() >= interval);
+        assert!(n1_after > n1_before, "node-1 still responds");
+    }
+
+    Ok(())
+}
+
+/// A peer busy installing a long snapshot is still in contact with the leader: every snapshot chunk it accepts counts.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, writes logs until a snapshot of many small chunks is built and the logs are
+///   purged.
+/// - adds a learner behind a slow link for snapshot chunks, thus it receives no AppendEntries for a while.
+/// - asserts the last contact with the learner advances while the snapshot chunks are sent.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn last_contact_while_installing_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+    n_logs = snapshot_threshold;
+
+    router.wait(&0, timeout()).await?.snapshot(LogId::new(1, n_logs), "snapshot is built").await?;
+
+    router.new_raft_node(1).await;
+    router.set_snapshot_send_delay(1, 20);
+    router.add_learner_with_blocking(0, 1, false).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- the last contact advances while the snapshot is being sent");
+    {
+        wait_for_chunks(&router, 1, 2).await;
+        let before = n0.last_contact(1).await;
+
+        wait_for_chunks(&router, 1, 6).await;
+        let after = n0.last_contact(1).await;
+
+        assert!(before.is_some());
+        assert!(after > before, "before: {:?}, after: {:?}", before, after);
+    }
+
+    router
+        .wait(&1, timeout())
+        .await?
+        .snapshot(LogId::new(1, n_logs), "learner installs snapshot")
+        .await?;
+
+    Ok(())
+}
+
+/// Wait until at least `n` snapshot chunks are sent to `target`.
+async fn wait_for_chunks(router: &RaftRouter, target: u64, n: u64) {
+    while router.snapshot_chunks_sent(target) < n {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
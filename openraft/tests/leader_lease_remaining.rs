@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader lease decreases as time passes without a successful heartbeat round, and is refreshed by one.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, asserts the leader holds a lease no longer than `election_timeout_min` less
+///   `max_clock_drift`, and a follower holds none.
+/// - isolates both followers, so that no heartbeat succeeds, and asserts the lease decreases over time.
+/// - restores the followers, and asserts the lease is refreshed by the heartbeats.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn leader_lease_remaining() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            // No election while the followers are isolated.
+            election_timeout_min: 2_000,
+            election_timeout_max: 2_100,
+            max_clock_drift: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let max_lease = Duration::from_millis(config.election_timeout_min - config.max_clock_drift);
+
+    let lease = n0.leader_lease_remaining().await.expect("the leader has a lease");
+    assert!(lease > Duration::ZERO && lease <= max_lease, "lease: {:?}", lease);
+
+    let n1 = router.get_raft_handle(&1).await?;
+    assert_eq!(None, n1.leader_lease_remaining().await, "a follower has no lease");
+
+    tracing::info!("--- isolate the followers, the lease decreases");
+    let decreased = {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        // Wait for the heartbeats sent before the isolation to be acknowledged.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let before = n0.leader_lease_remaining().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let after = n0.leader_lease_remaining().await.unwrap();
+
+        assert!(
+            after + Duration::from_millis(300) <= before,
+            "the lease decreases as time passes: before: {:?}, after: {:?}",
+            before,
+            after
+        );
+        after
+    };
+
+    tracing::info!("--- restore the followers, the lease is refreshed");
+    {
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        // Long enough for the replication to retry after the failures.
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+        let refreshed = n0.leader_lease_remaining().await.unwrap();
+
+        assert!(
+            refreshed > decreased,
+            "the lease is refreshed by heartbeats: {:?} > {:?}",
+            refreshed,
+            decreased
+        );
+    }
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -9,6 +10,7 @@ use maplit::hashmap;
 use openraft::raft::VoteRequest;
 use openraft::Config;
 use openraft::LogId;
+use openraft::NodeId;
 use openraft::RaftNetwork;
 use openraft::ReplicationMetrics;
 use openraft::State;
@@ -47,10 +49,10 @@ async fn leader_metrics() -> Result<()> {
     let router = Arc::new(RaftRouter::new(config.clone()));
     router.new_raft_node(0).await;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     let mut want = 0;
     router.wait_for_log(&btreeset![0], want, timeout, "init").await?;
-    router.wait_for_state(&btreeset![0], State::Learner, timeout, "init").await?;
+    router.wait_for_state(&btreeset![0], State::Uninitialized, timeout, "init").await?;
 
     router.assert_pristine_cluster().await;
 
@@ -105,16 +107,14 @@ async fn leader_metrics() -> Result<()> {
 
     router.assert_stable_cluster(Some(1), Some(want)).await; // Still in term 1, so leader is still node 0.
 
-    let ww = ReplicationMetrics {
-        matched: LogId { term: 1, index: want },
-    };
-    let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone(), 4=>ww.clone(), };
+    let ww = LogId { term: 1, index: want };
+    let want_repl = hashmap! { 1=>ww, 2=>ww, 3=>ww, 4=>ww, };
     router
         .wait_for_metrics(
             &0,
             |x| {
                 if let Some(ref q) = x.leader_metrics {
-                    q.replication == want_repl
+                    matched_of(&q.replication) == want_repl
                 } else {
                     false
                 }
@@ -155,16 +155,14 @@ async fn leader_metrics() -> Result<()> {
 
     tracing::info!("--- replication metrics should reflect the replication state");
     {
-        let ww = ReplicationMetrics {
-            matched: LogId { term: 1, index: want },
-        };
-        let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone()};
+        let ww = LogId { term: 1, index: want };
+        let want_repl = hashmap! { 1=>ww, 2=>ww, 3=>ww};
         router
             .wait_for_metrics(
                 &0,
                 |x| {
                     if let Some(ref q) = x.leader_metrics {
-                        q.replication == want_repl
+                        matched_of(&q.replication) == want_repl
                     } else {
                         false
                     }
@@ -179,13 +177,7 @@ async fn leader_metrics() -> Result<()> {
 
     tracing::info!("--- take leadership of node {}", leader);
     {
-        router
-            .send_vote(leader, VoteRequest {
-                term: 100,
-                candidate_id: 100,
-                last_log_id: LogId { term: 10, index: 100 },
-            })
-            .await?;
+        router.send_vote(leader, VoteRequest::new(100, 100, LogId { term: 10, index: 100 })).await?;
 
         // The next election may have finished before waiting.
         router
@@ -212,3 +204,7 @@ async fn leader_metrics() -> Result<()> {
 
     Ok(())
 }
+
+fn matched_of(replication: &HashMap<NodeId, ReplicationMetrics>) -> HashMap<NodeId, LogId> {
+    replication.iter().map(|(id, m)| (*id, m.matched)).collect()
+}
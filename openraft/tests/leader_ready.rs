@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A new leader reports itself ready only after the blank entry it appends on election is committed.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with a slow network, asserts the leader node-0 is ready.
+/// - transfers leadership to node-1 while watching the metrics of node-1.
+/// - asserts node-1 appends a blank entry of its term and reports itself ready once the entry is committed.
+/// - asserts node-1 reports being the leader before it is ready, and never ready before the blank entry is appended.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn leader_ready() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(20).build());
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.wait(&0, timeout()).await?.metrics(|x| x.leader_ready, "node-0 is ready").await?;
+    for id in [1, 2] {
+        let m = router.wait(&id, timeout()).await?.current_leader(0, "follower knows the leader").await?;
+        assert!(!m.leader_ready, "a follower is never ready");
+    }
+
+    let n1 = router.get_raft_handle(&1).await?;
+    let watcher = {
+        let mut rx = n1.metrics();
+        tokio::spawn(async move {
+            let mut seen = vec![];
+            loop {
+                let m = rx.borrow().clone();
+                let ready = m.leader_ready;
+                seen.push(m);
+                if ready || rx.changed().await.is_err() {
+                    return seen;
+                }
+            }
+        })
+    };
+
+    tracing::info!("--- transfer leadership to node-1");
+    {
+        router.transfer_leadership(0, Some(1)).await?;
+
+        let m = router.wait(&1, timeout()).await?.metrics(|x| x.leader_ready, "node-1 is ready").await?;
+        assert_eq!(State::Leader, m.state);
+        assert_eq!(Some(1), m.current_leader);
+
+        let sto = router.get_storage_handle(&1).await?;
+        let ent = sto.try_get_log_entry(n_logs + 1).await?.unwrap();
+        assert_eq!(LogId::new(m.current_term, n_logs + 1), ent.log_id);
+        assert!(
+            matches!(ent.payload, EntryPayload::Blank),
+            "node-1 appends a blank entry"
+        );
+    }
+
+    let seen = watcher.await?;
+    assert!(
+        seen.iter().any(|m| m.state == State::Leader && m.current_leader == Some(1) && !m.leader_ready),
+        "node-1 is leader before it is ready"
+    );
+    for m in seen.iter().filter(|m| m.leader_ready) {
+        assert!(
+            m.last_log_index > n_logs,
+            "ready only after the blank entry is appended"
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
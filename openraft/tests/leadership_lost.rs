@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftNetwork;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader notifies the leadership loss when it sees a higher term AppendEntries.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - asserts `leadership_lost()` returns `None` at once on a follower, and does not return on the leader.
+/// - sends an AppendEntries with a higher term to the leader.
+/// - asserts `leadership_lost()` returns the term in which node-0 was the leader, and node-0 is no longer a leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn leadership_lost_on_higher_term_append_entries() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    tracing::info!("--- a follower has no leadership to lose");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        assert_eq!(None, n1.leadership_lost().await);
+    }
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let mut lost = tokio::spawn(async move { n0.leadership_lost().await });
+
+    tracing::info!("--- the leader does not lose leadership");
+    {
+        let res = tokio::time::timeout(Duration::from_millis(500), &mut lost).await;
+        assert!(
+            res.is_err(),
+            "leadership_lost() does not return while node-0 is the leader"
+        );
+    }
+
+    tracing::info!("--- send a higher term AppendEntries to the leader");
+    {
+        router
+            .send_append_entries(0, AppendEntriesRequest {
+                term: term + 100,
+                leader_id: 1,
+                prev_log_id: LogId { term: 1, index: n_logs },
+                entries: vec![],
+                leader_commit: LogId { term: 1, index: n_logs },
+                compressed_entries: None,
+            })
+            .await?;
+
+        let got = tokio::time::timeout(Duration::from_millis(5_000), lost).await??;
+        assert_eq!(Some(term), got);
+
+        let m = router.wait(&0, timeout()).await?.metrics(|_| true, "get node-0 metrics").await?;
+        assert!(m.current_term >= term + 100, "node-0 sees the higher term");
+        assert!(
+            m.state != State::Leader || m.current_term > term + 100,
+            "node-0 steps down from the leader of term {}",
+            term
+        );
+    }
+
+    Ok(())
+}
+
+/// The leader notifies the leadership loss when it transfers leadership to another node.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - transfers leadership to node-1.
+/// - asserts `leadership_lost()` on node-0 returns the term in which node-0 was the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn leadership_lost_on_transfer() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?.current_term;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let mut lost = Box::pin(n0.leadership_lost());
+
+    // Poll it once so that it sees node-0 as the leader before the leadership is transferred.
+    assert!(futures::poll!(&mut lost).is_pending(), "node-0 is still leader");
+
+    router.transfer_leadership(0, Some(1)).await?;
+
+    let got = tokio::time::timeout(Duration::from_millis(5_000), lost).await?;
+    assert_eq!(Some(term), got);
+
+    router.wait(&1, timeout()).await?.state(State::Leader, "node-1 becomes leader").await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
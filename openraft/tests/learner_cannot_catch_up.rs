@@ -0,0 +1,141 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::AddLearnerError;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Adding a learner with `blocking` is aborted with `LearnerCannotCatchUp`, instead of hanging, when the learner
+/// falls further behind.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, and slows down every AppendEntries to node-1, so that it receives logs slower
+///   than they are written.
+/// - keeps writing to the leader, and adds node-1 as a learner with `blocking`.
+/// - asserts the add is aborted with `LearnerCannotCatchUp`, though there is no catch up deadline, and the leader stops
+///   replicating to node-1.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn learner_cannot_catch_up() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_payload_entries: 1,
+            replication_lag_threshold: 5,
+            learner_catch_up_window: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 20).await;
+
+    tracing::info!("--- keep writing faster than node-1 receives logs");
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let router = router.clone();
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            let mut serial = 20;
+            while !stop.load(Ordering::Relaxed) {
+                router.client_request(0, "foo", serial).await;
+                serial += 1;
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        })
+    };
+
+    router.set_append_entries_send_delay(1, 50);
+    router.new_raft_node(1).await;
+
+    tracing::info!("--- add node-1 as learner, it is aborted");
+    {
+        let res = tokio::time::timeout(timeout().unwrap(), router.add_learner(0, 1)).await?;
+
+        match res.unwrap_err() {
+            AddLearnerError::LearnerCannotCatchUp { node_id, distance, .. } => {
+                assert_eq!(1, node_id);
+                assert!(
+                    distance > config.replication_lag_threshold,
+                    "still lagging: {}",
+                    distance
+                );
+            }
+            err => panic!("expect LearnerCannotCatchUp, got: {:?}", err),
+        }
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let m = n0.metrics().borrow().clone();
+        let replicated = m.leader_metrics.as_ref().map(|x| x.replication.contains_key(&1));
+        assert_eq!(Some(false), replicated, "no replication to node-1");
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    writer.await?;
+
+    Ok(())
+}
+
+/// Adding a learner with `blocking` is aborted with `LearnerCannotCatchUp` if it does not catch up in
+/// `learner_catch_up_timeout`.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with sampling the lag disabled, and blocks every AppendEntries to node-1.
+/// - adds node-1 as a learner with `blocking`.
+/// - asserts the add is aborted with `LearnerCannotCatchUp` after `learner_catch_up_timeout`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn learner_catch_up_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: 5,
+            learner_catch_up_window: 0,
+            learner_catch_up_timeout: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 20).await;
+
+    router.set_append_entries_send_delay(1, 3_600_000);
+    router.new_raft_node(1).await;
+
+    tracing::info!("--- add node-1 as learner, it is aborted at the deadline");
+    {
+        let start = tokio::time::Instant::now();
+        let res = tokio::time::timeout(timeout().unwrap(), router.add_learner(0, 1)).await?;
+
+        match res.unwrap_err() {
+            AddLearnerError::LearnerCannotCatchUp { node_id, .. } => {
+                assert_eq!(1, node_id);
+            }
+            err => panic!("expect LearnerCannotCatchUp, got: {:?}", err),
+        }
+        assert!(start.elapsed() >= Duration::from_millis(config.learner_catch_up_timeout));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
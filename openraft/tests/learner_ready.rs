@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftEvent;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader reports a learner ready to promote only once it is within `replication_lag_threshold` of the leader.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with 200 logs, and a slow network.
+/// - adds a pristine node-1 as learner without blocking, while watching the metrics and the events of the leader.
+/// - asserts `LearnerReady(1)` is emitted, and node-1 is within the threshold by then.
+/// - asserts node-1 is seen lagging beyond the threshold, and never reported ready while lagging.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn learner_ready() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            max_payload_entries: 10,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(10).build());
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write up to 200 logs");
+    {
+        router.client_request_many(0, "foo", 200 - n_logs as usize).await;
+        n_logs = 200;
+
+        router.wait(&0, timeout()).await?.log(n_logs, "received 200 logs").await?;
+    }
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let mut events = n0.metrics_events();
+    let watcher = {
+        let mut rx = n0.metrics();
+        tokio::spawn(async move {
+            let mut seen = vec![];
+            loop {
+                let m = rx.borrow().clone();
+                let ready = m.leader_metrics.as_ref().unwrap().ready_learners.contains(&1);
+                seen.push(m);
+                if ready || rx.changed().await.is_err() {
+                    return seen;
+                }
+            }
+        })
+    };
+
+    tracing::info!("--- add far behind node-1 as learner");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner_with_blocking(0, 1, false).await?;
+
+        let ev = tokio::time::timeout(timeout().unwrap(), events.next()).await?;
+        assert_eq!(Some(RaftEvent::LearnerReady(1)), ev);
+
+        let m = n0.metrics().borrow().clone();
+        let matched = m.leader_metrics.as_ref().unwrap().replication[&1].matched;
+        assert!(
+            matched.index + lag_threshold >= n_logs,
+            "node-1 is within the threshold when ready, matched: {}",
+            matched
+        );
+    }
+
+    let seen = watcher.await?;
+    let mut lagging = 0;
+    for m in seen.iter() {
+        let lm = m.leader_metrics.as_ref().unwrap();
+        let matched = match lm.replication.get(&1) {
+            Some(x) => x.matched,
+            None => continue,
+        };
+
+        if matched.index + lag_threshold < n_logs {
+            lagging += 1;
+            assert!(
+                !lm.ready_learners.contains(&1),
+                "a lagging learner is not ready, matched: {}",
+                matched
+            );
+        }
+    }
+    assert!(lagging > 0, "node-1 is seen lagging before it is ready");
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,93 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// With a log cache, the replication streams read the logs from memory instead of from the storage.
+///
+/// What does this test do?
+///
+/// - brings up a leader with a store counting the log reads, and 3 learners.
+/// - writes logs and waits for them to be replicated to every learner, once with `log_cache_size = 0` and once with a
+///   cache large enough to hold all of them.
+/// - asserts the leader reads the log from the storage less often with the cache enabled.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn log_cache() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n = 50;
+
+    let without_cache = replicate(0, n).await?;
+    let with_cache = replicate(1_000, n).await?;
+
+    tracing::info!("log reads without cache: {}, with cache: {}", without_cache, with_cache);
+
+    assert!(
+        with_cache < without_cache,
+        "the cache saves log reads: {} vs {}",
+        with_cache,
+        without_cache
+    );
+
+    Ok(())
+}
+
+/// Write `n` logs to a leader with 3 learners, and return the number of log reads on the leader since the learners
+/// are added.
+async fn replicate(log_cache_size: u64, n: u64) -> Result<u64> {
+    let config = Arc::new(
+        Config {
+            log_cache_size,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto = Arc::new(HookedStore::new(0).await);
+    let leader = Raft::new(0, config.clone(), router.clone(), sto.clone());
+
+    leader.initialize(btreeset! {0}).await?;
+    leader.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+
+    for id in 1..=3 {
+        router.new_raft_node(id).await;
+        leader.add_learner(id, true).await?;
+    }
+
+    sto.log_reads.store(0, Ordering::Relaxed);
+
+    for serial in 0..n {
+        leader
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+            .await?;
+    }
+
+    router.wait_for_log(&btreeset! {1,2,3}, n + 1, timeout(), "replicated to learners").await?;
+
+    let log_reads = sto.log_reads.load(Ordering::Relaxed);
+    leader.shutdown().await?;
+
+    Ok(log_reads)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
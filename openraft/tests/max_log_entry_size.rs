@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::AppData;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+const MAX_LOG_ENTRY_SIZE: u64 = 1024;
+
+/// A leader rejects a write whose payload is larger than `max_log_entry_size` with `PayloadTooLarge`.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with `max_log_entry_size` of 1KiB.
+/// - asserts a write just under the limit is accepted.
+/// - asserts a write exceeding the limit is rejected with `PayloadTooLarge` and is not appended, both with
+///   `client_write` and `client_write_batch`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn max_log_entry_size() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_log_entry_size: MAX_LOG_ENTRY_SIZE,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a write just under the limit is accepted");
+    {
+        let req = request_of_size(1, MAX_LOG_ENTRY_SIZE - 1);
+
+        let resp = n0.client_write(ClientWriteRequest::new(req)).await?;
+        want += 1;
+        assert_eq!(want, resp.log_id.index);
+    }
+
+    tracing::info!("--- a write exceeding the limit is rejected");
+    {
+        let req = request_of_size(2, MAX_LOG_ENTRY_SIZE + 1);
+
+        let res = n0.client_write(ClientWriteRequest::new(req)).await;
+        match res.unwrap_err() {
+            ClientWriteError::PayloadTooLarge { size, max } => {
+                assert_eq!(MAX_LOG_ENTRY_SIZE + 1, size);
+                assert_eq!(MAX_LOG_ENTRY_SIZE, max);
+            }
+            err => panic!("expect PayloadTooLarge, got: {:?}", err),
+        }
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(want, m.last_log_index, "the rejected write is not appended");
+    }
+
+    tracing::info!("--- only the write exceeding the limit in a batch is rejected");
+    {
+        let results = n0
+            .client_write_batch(vec![
+                ClientWriteRequest::new(request_of_size(3, MAX_LOG_ENTRY_SIZE)),
+                ClientWriteRequest::new(request_of_size(4, MAX_LOG_ENTRY_SIZE + 1)),
+            ])
+            .await;
+        assert_eq!(2, results.len());
+
+        let resp = results[0].as_ref().expect("the write within the limit is accepted");
+        want += 1;
+        assert_eq!(want, resp.log_id.index);
+
+        assert!(
+            matches!(results[1], Err(ClientWriteError::PayloadTooLarge { .. })),
+            "got: {:?}",
+            results[1]
+        );
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(want, m.last_log_index, "the rejected write is not appended");
+    }
+
+    Ok(())
+}
+
+/// Build a request whose payload is exactly `size` bytes.
+fn request_of_size(serial: u64, size: u64) -> ClientRequest {
+    let mut req = ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: String::new(),
+    };
+
+    let base = req.size_hint();
+    req.status = "x".repeat((size - base) as usize);
+    assert_eq!(size, req.size_hint());
+
+    req
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// A leader rejects writes with `Overloaded` while it has `max_uncommitted_entries` uncommitted entries.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with `max_uncommitted_entries` of 3.
+/// - isolates both followers, so that nothing is committed, and writes 3 entries to the leader without waiting.
+/// - asserts the next write is rejected with `Overloaded` and is not appended.
+/// - restores the followers, waits until the 3 entries are committed, and asserts a write succeeds again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn max_uncommitted_entries() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            // No election while the followers are isolated.
+            election_timeout_min: 3_000,
+            election_timeout_max: 3_100,
+            max_uncommitted_entries: 3,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- isolate the followers and fill up the uncommitted entries");
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        for serial in 0..3 {
+            let n0 = router.get_raft_handle(&0).await?;
+            tokio::spawn(async move { n0.client_write(ClientWriteRequest::new(request(serial))).await });
+        }
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == want + 3, "3 uncommitted entries are appended")
+            .await?;
+    }
+
+    tracing::info!("--- a write beyond the limit is rejected");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        let res = n0.client_write(ClientWriteRequest::new(request(3))).await;
+
+        match res.unwrap_err() {
+            ClientWriteError::Overloaded { uncommitted, max } => {
+                assert_eq!(3, uncommitted);
+                assert_eq!(3, max);
+            }
+            err => panic!("expect Overloaded, got: {:?}", err),
+        }
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(want + 3, m.last_log_index, "the rejected write is not appended");
+    }
+
+    tracing::info!("--- restore the followers, writes are accepted once the entries are committed");
+    {
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        want += 3;
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2},
+                want,
+                timeout(),
+                "the uncommitted entries are committed",
+            )
+            .await?;
+
+        router.client_request(0, "foo", 4).await;
+        want += 1;
+
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "a write succeeds again").await?;
+    }
+
+    Ok(())
+}
+
+fn request(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
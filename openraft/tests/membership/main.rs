@@ -4,8 +4,17 @@ mod fixtures;
 
 mod t00_learner_restart;
 mod t10_add_learner;
+mod t11_learner_no_election;
+mod t15_voters_and_learners;
+mod t16_learner_follows_membership;
 mod t20_change_membership;
+mod t22_membership_log_id;
 mod t25_elect_with_new_config;
 mod t30_commit_joint_config;
+mod t35_change_membership_catch_up;
+mod t36_can_change_membership;
+mod t37_abort_membership_change;
+mod t38_force_remove_node;
 mod t40_removed_follower;
+mod t50_add_and_remove_at_once;
 mod t99_new_leader_auto_commit_uniform_config;
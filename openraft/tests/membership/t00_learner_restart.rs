@@ -36,9 +36,9 @@ async fn learner_restart() -> Result<()> {
 
     let mut want = 0;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     router.wait_for_log(&btreeset![0, 1], want, None, "empty").await?;
-    router.wait_for_state(&btreeset![0, 1], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0, 1], State::Uninitialized, None, "empty").await?;
     router.assert_pristine_cluster().await;
 
     tracing::info!("--- initializing single node cluster");
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// A learner never starts an election, even if it misses heartbeats.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters and adds a learner.
+/// - asserts the learner reports `State::Learner` and follows the leader.
+/// - isolates the learner for several election timeouts.
+/// - asserts the learner stays in `State::Learner` and does not increase its term.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_no_election() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    tracing::info!("--- add a learner");
+    {
+        router.new_raft_node(2).await;
+        router.add_learner(0, 2).await?;
+
+        router.client_request_many(0, "foo", 5).await;
+        n_logs += 5;
+
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "learner receives logs").await?;
+    }
+
+    let m = router.wait(&2, timeout()).await?.state(State::Learner, "node-2 is learner").await?;
+    assert_eq!(Some(0), m.current_leader, "the learner follows the leader");
+    let term = m.current_term;
+
+    tracing::info!("--- isolate the learner for several election timeouts");
+    {
+        router.isolate_node(2).await;
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 5)).await;
+
+        let m = router.wait(&2, timeout()).await?.metrics(|_| true, "get node-2 metrics").await?;
+        assert_eq!(State::Learner, m.state, "the learner never becomes candidate");
+        assert_eq!(term, m.current_term, "the learner never starts an election");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// The membership reported by the leader tells the voters from the learners.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters.
+/// - adds node-2 as a learner.
+/// - asserts the leader reports node-2 in `learners()` but not in `voters()`, and a follower reports no learners.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn voters_and_learners() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- no learner yet");
+    {
+        let membership = n0.membership();
+        assert_eq!(&btreeset! {0,1}, membership.voters());
+        assert!(membership.learners().is_empty());
+    }
+
+    tracing::info!("--- add node-2 as learner");
+    {
+        router.new_raft_node(2).await;
+        router.add_learner(0, 2).await?;
+
+        n0.wait(timeout())
+            .metrics(
+                |x| x.membership().learners().contains(&2),
+                "leader reports learner node-2",
+            )
+            .await?;
+
+        let membership = n0.membership();
+        assert_eq!(&btreeset! {0,1}, membership.voters());
+        assert_eq!(&btreeset! {2}, membership.learners());
+
+        let n1 = router.get_raft_handle(&1).await?;
+        let membership = n1.membership();
+        assert_eq!(&btreeset! {0,1}, membership.voters());
+        assert!(
+            membership.learners().is_empty(),
+            "a follower does not know the learners"
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
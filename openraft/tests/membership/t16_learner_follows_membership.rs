@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// A learner learns the voter set changes, stays a learner, and is still replicated to after them.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters and a learner node-3.
+/// - changes membership to {0,1}, removing node-2.
+/// - asserts `membership()` of node-3 reflects the new voters, and node-3 is still a learner.
+/// - writes logs, and asserts they are replicated to node-3, while the removed node-2 is no longer replicated to.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn learner_follows_membership() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let n3 = router.get_raft_handle(&3).await?;
+
+    tracing::info!("--- change membership to {{0,1}}");
+    {
+        n0.change_membership(btreeset! {0,1}, false).await?;
+        // The joint config and the uniform config.
+        n_logs += 2;
+
+        router.wait_for_log(&btreeset! {0,1,3}, n_logs, timeout(), "change membership").await?;
+
+        let membership = n3.membership();
+        assert_eq!(
+            &btreeset! {0,1},
+            membership.voters(),
+            "the learner knows the new voters"
+        );
+        assert!(!membership.voters().contains(&3), "the learner gains no vote");
+        assert_eq!(State::Learner, n3.metrics().borrow().state);
+    }
+
+    tracing::info!("--- the learner is still replicated to, the removed voter is not");
+    {
+        n0.wait(timeout())
+            .metrics(
+                |x| x.leader_metrics.as_ref().map(|l| !l.replication.contains_key(&2)).unwrap_or(false),
+                "replication to node-2 is removed",
+            )
+            .await?;
+
+        router.client_request_many(0, "foo", 5).await;
+        n_logs += 5;
+
+        router.wait_for_log(&btreeset! {0,1,3}, n_logs, timeout(), "write 5 logs").await?;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(&btreeset! {3}, m.membership().learners());
+
+        assert!(m.leader_metrics.as_ref().unwrap().replication.contains_key(&3));
+
+        let m2 = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert!(m2.last_log_index < n_logs, "node-2 is removed");
+        assert_eq!(State::Learner, n3.metrics().borrow().state);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LogId;
+
+use crate::fixtures::RaftRouter;
+
+/// `Raft::membership_log_id()` returns the id of the log that established the current membership config.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node, asserts there is no membership log id.
+/// - initializes a single voter cluster with a learner node-1, and writes some logs.
+/// - changes membership to {0,1}, asserts the returned log id is the membership log id on both nodes, in the API and in
+///   the metrics, and the log at it is a membership config.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn membership_log_id() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+
+    tracing::info!("--- no membership log on a pristine node");
+    {
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        router.new_raft_node(0).await;
+        let n0 = router.get_raft_handle(&0).await?;
+        assert_eq!(None, n0.membership_log_id());
+        assert_eq!(LogId::new(0, 0), n0.metrics().borrow().membership_config.log_id);
+    }
+
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    router.client_request_many(0, "foo", 5).await;
+    n_logs += 5;
+    router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "write 5 logs").await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let before = n0.membership_log_id().expect("a membership config is in the log");
+    assert!(before.index < n_logs);
+
+    tracing::info!("--- change membership");
+    {
+        let resp = n0.change_membership(btreeset! {0,1}, true).await?;
+        // The joint config and the uniform config.
+        n_logs += 2;
+        assert_eq!(n_logs, resp.log_id.index);
+
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "change membership").await?;
+
+        for id in [0, 1] {
+            let n = router.get_raft_handle(&id).await?;
+            assert_eq!(Some(resp.log_id), n.membership_log_id(), "node-{}", id);
+
+            let m = n.metrics().borrow().clone();
+            assert_eq!(resp.log_id, m.membership_config.log_id, "node-{}", id);
+        }
+
+        let entries = n0.get_log_entries(resp.log_id.index, resp.log_id.index + 1, false).await?;
+        assert_eq!(resp.log_id, entries[0].log_id);
+        match &entries[0].payload {
+            EntryPayload::Membership(m) => assert_eq!(&btreeset! {0,1}, m.voters()),
+            payload => panic!("expect a membership config, got: {:?}", payload),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
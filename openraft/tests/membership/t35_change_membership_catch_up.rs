@@ -0,0 +1,213 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::config::MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS;
+use openraft::error::ClientWriteError;
+use openraft::ChangeMembershipError;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// A far behind new voter is kept as a learner until it catches up, then the voting config change is committed.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with 200 logs, and a slow network.
+/// - adds a pristine node-1 as voter with `blocking_catch_up`, while watching the metrics of the leader.
+/// - asserts the change commits and node-1 becomes a voter.
+/// - asserts the joint config is never seen on the leader before node-1 catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn change_membership_catch_up() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            max_payload_entries: 10,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(10).build());
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write up to 200 logs");
+    {
+        router.client_request_many(0, "foo", 200 - n_logs as usize).await;
+        n_logs = 200;
+
+        router.wait(&0, timeout()).await?.log(n_logs, "received 200 logs").await?;
+    }
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let watcher = {
+        let mut rx = n0.metrics();
+        tokio::spawn(async move {
+            let mut seen = vec![];
+            loop {
+                let m = rx.borrow().clone();
+                let done = m.membership_config.membership.get_ith_config(0) == Some(&btreeset! {0,1})
+                    && !m.membership_config.membership.is_in_joint_consensus();
+                seen.push(m);
+                if done || rx.changed().await.is_err() {
+                    return seen;
+                }
+            }
+        })
+    };
+
+    tracing::info!("--- add far behind node-1 as voter");
+    {
+        router.new_raft_node(1).await;
+
+        router.change_membership_with_blocking(0, btreeset! {0,1}, true).await?;
+
+        router.wait(&1, timeout()).await?.members(btreeset! {0,1}, "node-1 is a voter").await?;
+    }
+
+    let seen = watcher.await?;
+    for m in seen.iter().filter(|m| m.membership_config.membership.is_in_joint_consensus()) {
+        let matched = m.leader_metrics.as_ref().unwrap().replication[&1].matched;
+        assert!(
+            matched.index + lag_threshold >= n_logs,
+            "joint config is proposed only after node-1 catches up, matched: {}",
+            matched
+        );
+    }
+
+    Ok(())
+}
+
+/// A new voter that can not catch up in time is rejected, and the membership is not changed.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with 100 logs.
+/// - adds an isolated node-1 as voter with `blocking_catch_up`.
+/// - asserts it returns `CatchUpTimeout` after `membership_catch_up_timeout`.
+/// - asserts the voters are not changed, and the leader still accepts writes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn change_membership_catch_up_timeout() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            membership_catch_up_timeout: 2_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write up to 100 logs");
+    {
+        router.client_request_many(0, "foo", 100 - n_logs as usize).await;
+        n_logs = 100;
+
+        router.wait(&0, timeout()).await?.log(n_logs, "received 100 logs").await?;
+    }
+
+    tracing::info!("--- add isolated node-1 as voter");
+    {
+        router.new_raft_node(1).await;
+        router.isolate_node(1).await;
+
+        let res = router.change_membership_with_blocking(0, btreeset! {0,1}, true).await;
+
+        match res.unwrap_err() {
+            ClientWriteError::ChangeMembershipError(ChangeMembershipError::CatchUpTimeout {
+                node_id, timeout, ..
+            }) => {
+                assert_eq!(1, node_id);
+                assert_eq!(Duration::from_millis(2_000), timeout);
+            }
+            err => panic!("expect CatchUpTimeout, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- voters are not changed");
+    {
+        let m = router.wait(&0, timeout()).await?.log(n_logs, "no membership log is appended").await?;
+        assert_eq!(
+            btreeset! {0},
+            m.membership_config.membership.get_ith_config(0).cloned().unwrap()
+        );
+        assert!(!m.membership_config.membership.is_in_joint_consensus());
+
+        router.client_request(0, "foo", 100).await;
+        n_logs += 1;
+        router.wait(&0, timeout()).await?.log(n_logs, "leader accepts writes").await?;
+    }
+
+    Ok(())
+}
+
+/// A new voter that never catches up is rejected, even if `membership_catch_up_timeout` is not set.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with 100 logs, a short `install_snapshot_timeout`, and no
+///   `membership_catch_up_timeout`.
+/// - adds an isolated node-1 as voter with `blocking_catch_up`.
+/// - asserts it returns `CatchUpTimeout` after the deadline derived from `install_snapshot_timeout`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn change_membership_catch_up_default_timeout() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: 5,
+            install_snapshot_timeout: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write up to 100 logs");
+    {
+        router.client_request_many(0, "foo", 100 - n_logs as usize).await;
+        router.wait(&0, timeout()).await?.log(100, "received 100 logs").await?;
+    }
+
+    tracing::info!("--- add isolated node-1 as voter");
+    {
+        router.new_raft_node(1).await;
+        router.isolate_node(1).await;
+
+        let res = router.change_membership_with_blocking(0, btreeset! {0,1}, true).await;
+
+        match res.unwrap_err() {
+            ClientWriteError::ChangeMembershipError(ChangeMembershipError::CatchUpTimeout {
+                node_id, timeout, ..
+            }) => {
+                assert_eq!(1, node_id);
+                assert_eq!(
+                    Duration::from_millis(config.install_snapshot_timeout * MEMBERSHIP_CATCH_UP_SNAPSHOT_TIMEOUTS),
+                    timeout
+                );
+            }
+            err => panic!("expect CatchUpTimeout, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
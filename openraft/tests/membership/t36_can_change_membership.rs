@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::ChangeMembershipError;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// `Raft::can_change_membership()` validates a membership change against the replication state, without proposing
+/// anything.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with 2 learners, isolates learner node-2, and writes logs so that node-2 falls
+///   far behind, while node-1 is caught up.
+/// - asserts the dry run of adding the caught-up node-1 as a voter passes.
+/// - asserts the dry run of adding the far-behind node-2 as a voter fails with `LearnerIsLagging`.
+/// - asserts neither dry run appended a log or changed the membership.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn can_change_membership() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- isolate node-2 and write logs, node-2 falls behind");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "foo", 20).await;
+        n_logs += 20;
+
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "write 20 logs").await?;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.leader_metrics
+                        .as_ref()
+                        .and_then(|l| l.replication.get(&1))
+                        .map_or(false, |r| r.matched.index == n_logs)
+                },
+                "the leader sees node-1 caught up",
+            )
+            .await?;
+    }
+
+    let before = n0.metrics().borrow().clone();
+
+    tracing::info!("--- adding the caught-up node-1 passes the dry run");
+    {
+        n0.can_change_membership(btreeset! {0,1}).await?;
+    }
+
+    tracing::info!("--- adding the far-behind node-2 fails the dry run");
+    {
+        let res = n0.can_change_membership(btreeset! {0,2}).await;
+        match res {
+            Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::LearnerIsLagging {
+                node_id,
+                distance,
+                ..
+            })) => {
+                assert_eq!(2, node_id);
+                assert!(distance > lag_threshold);
+            }
+            res => panic!("expect LearnerIsLagging, got: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- nothing is proposed by a dry run");
+    {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(n_logs, m.last_log_index);
+        assert_eq!(before.membership_config.log_id, m.membership_config.log_id);
+        assert_eq!(&btreeset! {0}, m.membership_config.membership.voters());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::ChangeMembershipError;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// A membership change stuck on an unreachable new voter is aborted, and the previous membership is restored.
+///
+/// What does this test do?
+///
+/// - brings up a single voter cluster with a learner node-1, then isolates node-1.
+/// - changes membership to {0,1}: the joint config can not be committed without node-1.
+/// - aborts the change, asserts the pending change returns `Aborted` and the leader goes back to the voters {0}.
+/// - asserts aborting again returns `NotInProgress`, and the leader still accepts writes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn abort_membership_change() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+    router.wait(&1, timeout()).await?.log(n_logs, "node-1 replicated").await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- isolate node-1 and change membership to {{0,1}}");
+    let change = {
+        router.isolate_node(1).await;
+
+        let n0 = n0.clone();
+        let change = tokio::spawn(async move { n0.change_membership(btreeset! {0,1}, false).await });
+
+        n_logs += 1;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.last_log_index == n_logs && x.membership_config.membership.is_in_joint_consensus(),
+                "joint config is proposed",
+            )
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let m = router.wait(&0, timeout()).await?.metrics(|_| true, "get node-0 metrics").await?;
+        assert!(
+            m.last_applied < n_logs,
+            "joint config can not be committed without node-1"
+        );
+
+        change
+    };
+
+    tracing::info!("--- abort the membership change");
+    {
+        let log_id = n0.abort_membership_change().await?;
+        n_logs += 1;
+        assert_eq!(n_logs, log_id.index, "a config of the previous voters is committed");
+
+        let res = change.await?;
+        assert!(
+            matches!(
+                res,
+                Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::Aborted))
+            ),
+            "the pending change is aborted, got: {:?}",
+            res
+        );
+
+        let m = router.wait(&0, timeout()).await?.members(btreeset! {0}, "voters are {0}").await?;
+        assert!(!m.membership_config.membership.is_in_joint_consensus());
+        assert_eq!(n_logs, m.membership_config.log_id.index);
+    }
+
+    tracing::info!("--- nothing to abort");
+    {
+        let res = n0.abort_membership_change().await;
+        assert!(
+            matches!(
+                res,
+                Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::NotInProgress
+                ))
+            ),
+            "got: {:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- the leader still accepts writes");
+    {
+        router.client_request_many(0, "foo", 1).await;
+        n_logs += 1;
+
+        router.wait(&0, timeout()).await?.metrics(|x| x.last_applied == n_logs, "write is applied").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
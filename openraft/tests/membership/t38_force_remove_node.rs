@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::ChangeMembershipError;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// UNSAFE PATH: force removing a voter that is gone for good restores the ability of the cluster to commit.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters, then shuts down node-1 for good: nothing can be committed without it.
+/// - asserts a client write is not committed.
+/// - asserts force removing is rejected without confirming the node is gone, or for the leader itself.
+/// - force removes node-1, asserts the config {0} is committed along with the pending write, and the leader commits new
+///   writes on its own.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn force_remove_node() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- shut down node-1, a write is not committed");
+    let write = {
+        let (n1, _sto) = router.remove_node(1).await.unwrap();
+        n1.shutdown().await?;
+
+        let r0 = n0.clone();
+        let write = tokio::spawn(async move {
+            r0.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 0,
+                status: "request-0".to_string(),
+            }))
+            .await
+        });
+
+        n_logs += 1;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs, "write is appended")
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let m = n0.metrics().borrow().clone();
+        assert!(m.last_applied < n_logs, "the write can not be committed without node-1");
+
+        write
+    };
+
+    tracing::info!("--- force removing requires confirming the node is gone, and a voter other than the leader");
+    {
+        let res = n0.force_remove_node(1, false).await;
+        assert!(
+            matches!(
+                res,
+                Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::ForceRemoveNotConfirmed { node_id: 1 }
+                ))
+            ),
+            "got: {:?}",
+            res
+        );
+
+        let res = n0.force_remove_node(0, true).await;
+        assert!(
+            matches!(
+                res,
+                Err(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::InvalidForceRemove { node_id: 0 }
+                ))
+            ),
+            "got: {:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- force remove node-1, the pending write is committed");
+    {
+        let resp = n0.force_remove_node(1, true).await?;
+        n_logs += 1;
+        assert_eq!(n_logs, resp.log_id.index);
+
+        let resp = write.await??;
+        assert_eq!(n_logs - 1, resp.log_id.index);
+
+        let m = router.wait(&0, timeout()).await?.members(btreeset! {0}, "voters are {0}").await?;
+        assert!(!m.membership_config.membership.is_in_joint_consensus());
+        assert_eq!(n_logs, m.membership_config.log_id.index);
+        assert!(
+            m.leader_metrics.unwrap().replication.is_empty(),
+            "node-1 is not replicated to"
+        );
+    }
+
+    tracing::info!("--- the leader commits new writes on its own");
+    {
+        router.client_request_many(0, "foo", 2).await;
+        n_logs += 2;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "writes are applied")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// UNSAFE PATH: force removing a leader that is gone for good, on the follower left, elects the follower.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters, then shuts down the leader node-0 for good: node-1 can not be elected.
+/// - force removes node-0 on node-1, asserts node-1 becomes the leader and commits the config {1}.
+/// - asserts node-1 commits new writes on its own.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn force_remove_node_on_follower() -> anyhow::Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    let n1 = router.get_raft_handle(&1).await?;
+
+    tracing::info!("--- shut down the leader node-0, node-1 can not be elected");
+    {
+        let (n0, _sto) = router.remove_node(0).await.unwrap();
+        n0.shutdown().await?;
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 2)).await;
+        let m = n1.metrics().borrow().clone();
+        assert_ne!(Some(1), m.current_leader, "node-1 is not elected without node-0");
+    }
+
+    tracing::info!("--- force remove node-0 on node-1, node-1 is elected");
+    {
+        let resp = n1.force_remove_node(0, true).await?;
+        // The blank log of node-1 as the leader, then the config.
+        n_logs += 2;
+        assert_eq!(n_logs, resp.log_id.index);
+
+        let m = router.wait(&1, timeout()).await?.members(btreeset! {1}, "voters are {1}").await?;
+        assert_eq!(Some(1), m.current_leader);
+        assert_eq!(n_logs, m.membership_config.log_id.index);
+    }
+
+    tracing::info!("--- node-1 commits new writes on its own");
+    {
+        router.client_request_many(1, "foo", 2).await;
+        n_logs += 2;
+
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "writes are applied")
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,127 @@
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::ChangeMembershipError;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// Add two members and remove one in a single `change_membership()` call, while clients keep writing.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters and 2 learners.
+/// - keeps sending client writes to the leader in a background task.
+/// - changes membership from {0,1,2} to {0,1,3,4}, which goes through the joint config {0,1,2} & {0,1,3,4}.
+/// - asserts every client write succeeds and all members end up in the uniform config.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn add_two_remove_one_with_writes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n_writes = 100;
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3,4}).await?;
+
+    tracing::info!("--- change membership while writing");
+    {
+        let writer = tokio::spawn({
+            let router = router.clone();
+            async move {
+                // `client_request()` panics on any error.
+                router.client_request_many(0, "client", n_writes).await;
+            }
+        });
+
+        router.change_membership(0, btreeset! {0,1,3,4}).await?;
+        // joint config and uniform config
+        n_logs += 2;
+
+        writer.await?;
+        n_logs += n_writes as u64;
+    }
+
+    tracing::info!("--- all members apply the new config");
+    {
+        router
+            .wait_for_log(
+                &btreeset![0, 1, 3, 4],
+                n_logs,
+                timeout(),
+                "change membership with writes",
+            )
+            .await?;
+
+        for id in [0, 1, 3, 4] {
+            router.wait(&id, timeout()).await?.members(btreeset! {0,1,3,4}, "uniform config").await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A membership change is rejected while the previous one is not committed yet.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - isolates node 1 and 2 so that the joint config {0,1,2} & {0,1} can not be committed.
+/// - starts a change to {0,1} in a background task.
+/// - asserts another change to {0} is rejected with `InProgress`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn reject_concurrent_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.isolate_node(1).await;
+    router.isolate_node(2).await;
+
+    tracing::info!("--- start a change that can not be committed");
+    {
+        tokio::spawn({
+            let router = router.clone();
+            async move {
+                let _ = router.change_membership(0, btreeset! {0,1}).await;
+            }
+        });
+
+        n_logs += 1;
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|m| m.last_log_index == n_logs, "joint config is appended")
+            .await?;
+    }
+
+    tracing::info!("--- another change is rejected");
+    {
+        let res = router.change_membership(0, btreeset! {0}).await;
+        let err: ChangeMembershipError = res.unwrap_err().try_into().unwrap();
+
+        match err {
+            ChangeMembershipError::InProgress { membership_log_id } => {
+                assert_eq!(n_logs, membership_log_id.index);
+            }
+            _ => {
+                panic!("expect ChangeMembershipError::InProgress, got: {:?}", err);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
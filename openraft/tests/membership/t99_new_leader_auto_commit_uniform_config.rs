@@ -41,6 +41,7 @@ async fn new_leader_auto_commit_uniform_config() -> Result<()> {
                 index: want + 1,
             },
             payload: EntryPayload::Membership(Membership::new_multi(vec![btreeset! {0}, btreeset! {0,1,2}])),
+            request_id: None,
         }])
         .await?;
     }
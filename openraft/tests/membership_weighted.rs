@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::error::ChangeMembershipError;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// With vote weights, a quorum is a set of voters having more than half of the total weight.
+///
+/// What does this test do?
+///
+/// - build a stable 3-node cluster, and change the membership to weights `{0: 2, 1: 1, 2: 1}`.
+/// - isolate node 2, and assert a write is committed by node 0 and 1.
+/// - restore node 2 and isolate node 1, and assert a write is committed by node 0 and 2.
+/// - restore node 1 and isolate node 0, and assert node 1 and 2, having half of the total weight, can not elect a
+///   leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn membership_weighted_quorum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- change membership to weights 2/1/1");
+    {
+        let leader = router.get_raft_handle(&0).await?;
+        leader.change_membership_weighted(btreemap! {0=>2,1=>1,2=>1}, false).await?;
+        want += 2;
+
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "weighted membership").await?;
+
+        let m = leader.metrics().borrow().clone();
+        assert_eq!(
+            Some(btreemap! {0=>2,1=>1,2=>1}),
+            m.membership_config.membership.get_ith_weights(0)
+        );
+        assert!(!m.membership_config.membership.is_in_joint_consensus());
+    }
+
+    tracing::info!("--- node 0 and 1 form a quorum");
+    {
+        router.isolate_node(2).await;
+
+        tokio::time::timeout(timeout().unwrap(), router.client_request(0, "foo", 1)).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "committed by node 0 and 1").await?;
+        router.restore_node(2).await;
+    }
+
+    tracing::info!("--- node 0 and 2 form a quorum");
+    {
+        router.isolate_node(1).await;
+
+        tokio::time::timeout(timeout().unwrap(), router.client_request(0, "foo", 2)).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset! {0,2}, want, timeout(), "committed by node 0 and 2").await?;
+        router.restore_node(1).await;
+    }
+
+    tracing::info!("--- node 1 and 2 do not form a quorum");
+    {
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "node 1 caught up").await?;
+        router.isolate_node(0).await;
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 5)).await;
+
+        for id in [1, 2] {
+            let m = router.get_raft_handle(&id).await?.metrics().borrow().clone();
+            assert_ne!(
+                State::Leader,
+                m.state,
+                "node {} can not be elected with half of the weight",
+                id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// A membership of a total weight of 0 is rejected, since no set of voters has more than half of it, and so is a
+/// membership of a total weight beyond `u64`.
+///
+/// What does this test do?
+///
+/// - build a stable 3-node cluster.
+/// - change the membership to weights of 0, and assert it is rejected with `NoWeightedMajority`.
+/// - change the membership to weights summing up beyond `u64::MAX`, and assert it is rejected with `WeightOverflow`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn membership_weighted_no_majority() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+    let res = leader.change_membership_weighted(btreemap! {0=>0,1=>0,2=>0}, false).await;
+
+    match res {
+        Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::NoWeightedMajority { weights })) => {
+            assert_eq!(btreemap! {0=>0,1=>0,2=>0}, weights);
+        }
+        other => panic!("expect NoWeightedMajority, got: {:?}", other),
+    }
+
+    let res = leader.change_membership_weighted(btreemap! {0=>u64::MAX,1=>1,2=>1}, false).await;
+
+    match res {
+        Err(ClientWriteError::ChangeMembershipError(ChangeMembershipError::WeightOverflow { weights })) => {
+            assert_eq!(btreemap! {0=>u64::MAX,1=>1,2=>1}, weights);
+        }
+        other => panic!("expect WeightOverflow, got: {:?}", other),
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::storage::HardState;
+use openraft::Config;
+use openraft::LogId;
+use openraft::MessageSummary;
+use openraft::RaftStorage;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// The AppendEntries rejected because of a log conflict are counted on both the follower and the leader.
+///
+/// What does this test do?
+///
+/// - fakes a cluster of node 0,1,2. R0 has ~100 uncommitted logs at term 2, R2 has ~100 uncommitted logs at term 3.
+/// - starts the cluster with node-1 isolated, so that node-2 becomes the leader and replicates to node-0.
+/// - asserts node-0 reports a conflict while its log still diverges from the leader's.
+/// - asserts once node-0 converges, the leader counts the conflicts of node-0, and no term rejection.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn metrics_append_conflicts() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- remove all nodes and fake the logs");
+
+    let (r0, sto0) = router.remove_node(0).await.unwrap();
+    let (r1, sto1) = router.remove_node(1).await.unwrap();
+    let (r2, sto2) = router.remove_node(2).await.unwrap();
+
+    r0.shutdown().await?;
+    r1.shutdown().await?;
+    r2.shutdown().await?;
+
+    for i in n_logs + 1..=100 {
+        sto0.append_to_log(&[&Entry {
+            log_id: LogId { term: 2, index: i },
+            payload: EntryPayload::Blank,
+            request_id: None,
+        }])
+        .await?;
+
+        sto2.append_to_log(&[&Entry {
+            log_id: LogId { term: 3, index: i },
+            payload: EntryPayload::Blank,
+            request_id: None,
+        }])
+        .await?;
+    }
+
+    sto0.save_hard_state(&HardState {
+        current_term: 2,
+        voted_for: Some(0),
+    })
+    .await?;
+
+    sto2.save_hard_state(&HardState {
+        current_term: 3,
+        voted_for: Some(2),
+    })
+    .await?;
+
+    n_logs = 100;
+
+    tracing::info!("--- restart node 1 and isolate. To let node-2 to become leader, node-1 should not vote for node-0");
+    {
+        router.new_raft_node_with_sto(1, sto1.clone()).await;
+        router.isolate_node(1).await;
+    }
+
+    tracing::info!("--- restart node 0 and 2, watch the conflicts reported by node-0");
+    let watcher = {
+        router.new_raft_node_with_sto(0, sto0.clone()).await;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let mut rx = n0.metrics();
+        let watcher = tokio::spawn(async move {
+            loop {
+                let m = rx.borrow().clone();
+                if m.conflicts_reported > 0 {
+                    return Some(m);
+                }
+                if rx.changed().await.is_err() {
+                    return None;
+                }
+            }
+        });
+
+        router.new_raft_node_with_sto(2, sto2.clone()).await;
+        watcher
+    };
+
+    // leader appends a blank log.
+    n_logs += 1;
+
+    router.wait_for_state(&btreeset! {2}, State::Leader, timeout(), "node 2 become leader").await?;
+
+    tracing::info!("--- node-0 reports a conflict before it converges");
+    {
+        let m = watcher.await?.expect("node-0 reports a conflict");
+        assert_eq!(Some(2), m.current_leader);
+        assert!(
+            m.last_log_index < n_logs,
+            "the conflict is reported before node-0 catches up: {}",
+            m.summary()
+        );
+    }
+
+    router.wait(&0, timeout()).await?.log(n_logs, "sync log to node 0").await?;
+
+    tracing::info!("--- the leader counts the conflicts of node-0");
+    {
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.leader_metrics
+                        .as_ref()
+                        .and_then(|lm| lm.replication.get(&0).map(|r| r.log_conflicts > 0))
+                        .unwrap_or(false)
+                },
+                "leader counts the conflicts of node-0",
+            )
+            .await?;
+
+        let n2 = router.get_raft_handle(&2).await?;
+        let m = n2.metrics().borrow().clone();
+        let repl = &m.leader_metrics.as_ref().unwrap().replication[&0];
+        assert_eq!(0, repl.term_rejections);
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let conflicts_reported = n0.metrics().borrow().conflicts_reported;
+        assert!(
+            repl.log_conflicts <= conflicts_reported,
+            "the leader counts no more conflicts than node-0 reports: {} vs {}",
+            repl.log_conflicts,
+            conflicts_reported
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
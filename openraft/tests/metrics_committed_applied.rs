@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+use tokio::sync::watch;
+
+#[macro_use]
+mod fixtures;
+
+/// The metrics tell the last appended, committed and applied log apart.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a state machine that can be paused.
+/// - pauses the state machine and writes a log.
+/// - asserts `last_committed` runs ahead of `last_applied` while the state machine is paused.
+/// - resumes the state machine, asserts `last_applied` catches up with `last_committed`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn metrics_committed_applied() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+
+    let (tx_gate, gate) = watch::channel(true);
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            apply_gate: Some(gate),
+            ..Default::default()
+        })
+        .await,
+    );
+    let raft = Raft::new(0, config.clone(), Arc::new(StubNetwork::default()), sto);
+
+    raft.initialize(btreeset! {0}).await?;
+    let m = raft.wait(timeout()).metrics(|x| x.leader_ready, "node-0 is ready").await?;
+
+    let base = m.last_committed.unwrap();
+    assert_eq!(base.index, m.last_log_index);
+    raft.wait(timeout()).metrics(|x| x.last_applied == base.index, "initial logs are applied").await?;
+
+    tracing::info!("--- pause the state machine and write a log");
+    let want = LogId::new(base.term, base.index + 1);
+    let write = {
+        tx_gate.send(false)?;
+
+        let raft = raft.clone();
+        tokio::spawn(async move {
+            raft.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 0,
+                status: "bar".to_string(),
+            }))
+            .await
+        })
+    };
+
+    {
+        let m = raft.wait(timeout()).metrics(|x| x.last_committed == Some(want), "the log is committed").await?;
+        assert_eq!(want.index, m.last_log_index);
+        assert_eq!(base.index, m.last_applied, "committed runs ahead of applied");
+    }
+
+    tracing::info!("--- resume the state machine");
+    {
+        tx_gate.send(true)?;
+
+        let resp = write.await??;
+        assert_eq!(want, resp.log_id);
+
+        let m = raft.wait(timeout()).metrics(|x| x.last_applied == want.index, "the log is applied").await?;
+        assert_eq!(Some(want), m.last_committed);
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// `Raft::current_metrics()` returns the latest value sent to the metrics channel.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters.
+/// - writes some logs and waits for them to be applied on the leader.
+/// - asserts the metrics returned by `current_metrics()` are the same as the latest value in the metrics channel.
+/// - asserts the follower reports no leader metrics, and the leader reports the follower in its leader metrics.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn metrics_current() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs, the current metrics reflect them");
+    {
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "logs are applied").await?;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let rx = n0.metrics();
+
+        // Metrics keep changing, e.g., with the replication progress. Retry until no update is sent in between.
+        let m = loop {
+            let m = n0.current_metrics();
+            if m == *rx.borrow() {
+                break m;
+            }
+        };
+
+        assert_eq!(State::Leader, m.state);
+        assert_eq!(n_logs, m.last_applied);
+
+        let leader_metrics = m.leader_metrics.expect("the leader reports leader metrics");
+        assert!(leader_metrics.replication.contains_key(&1));
+    }
+
+    tracing::info!("--- the current metrics of the follower");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "node-1 applied all logs")
+            .await?;
+
+        let m = n1.current_metrics();
+        assert_eq!(State::Follower, m.state);
+        assert_eq!(n_logs, m.last_applied);
+        assert_eq!(Some(0), m.current_leader);
+        assert!(m.leader_metrics.is_none());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
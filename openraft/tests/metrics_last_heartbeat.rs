@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// `millis_since_last_heartbeat` on a follower grows when heartbeats stop and resets when they resume.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with a long election timeout, so that no election happens during the test.
+/// - asserts the followers have heard from the leader recently, and the leader reports no value.
+/// - asserts the metrics of node-1 are rarely published while heartbeats arrive in time.
+/// - isolates the leader, and asserts the value on node-1 grows while it still knows node-0 as the leader.
+/// - restores the leader, and asserts the value on node-1 resets.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn metrics_last_heartbeat() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 5_000,
+            election_timeout_max: 6_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- followers hear from the leader");
+    {
+        let m = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+        assert_eq!(None, m.millis_since_last_heartbeat, "a leader reports no heartbeat");
+
+        for id in [1, 2] {
+            router
+                .wait(&id, timeout())
+                .await?
+                .metrics(
+                    |x| x.millis_since_last_heartbeat.map(|t| t < 500).unwrap_or(false),
+                    "heard from the leader recently",
+                )
+                .await?;
+        }
+    }
+
+    tracing::info!("--- metrics are not published for every heartbeat");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let mut rx = n1.metrics();
+
+        let mut published = 0;
+        let deadline = Instant::now() + Duration::from_millis(1_000);
+        while let Ok(res) = tokio::time::timeout_at(deadline, rx.changed()).await {
+            res?;
+            published += 1;
+        }
+
+        // 20 heartbeats are received, and as many metrics ticks pass. Allow a few late heartbeats.
+        assert!(published < 5, "metrics are published only on change: {}", published);
+    }
+
+    tracing::info!("--- isolate the leader, the value grows");
+    {
+        router.isolate_node(0).await;
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.millis_since_last_heartbeat.map(|t| t >= 1_000).unwrap_or(false),
+                "no heartbeat for a while",
+            )
+            .await?;
+        assert_eq!(Some(0), m.current_leader, "the leader is still known");
+        assert_eq!(State::Follower, m.state);
+    }
+
+    tracing::info!("--- restore the leader, the value resets");
+    {
+        router.restore_node(0).await;
+
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.millis_since_last_heartbeat.map(|t| t < 500).unwrap_or(false),
+                "heard from the leader again",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3_000))
+}
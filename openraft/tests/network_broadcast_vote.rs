@@ -0,0 +1,43 @@
+use anyhow::Result;
+use fixtures::StubNetwork;
+use futures::StreamExt;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::raft::VoteRequest;
+use openraft::LogId;
+use openraft::RaftNetwork;
+
+#[macro_use]
+mod fixtures;
+
+/// The default `RaftNetwork::broadcast_vote()` yields the responses in the order they arrive.
+///
+/// What does this test do?
+///
+/// - builds a network in which node-1 responds the slowest and node-3 the fastest, and node-4 is unreachable.
+/// - broadcasts a vote request to node-1 to node-4, in this order.
+/// - asserts the failure of node-4 comes first, then the responses from node-3, node-2 and node-1.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn network_broadcast_vote() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let network = StubNetwork {
+        vote_delays: btreemap! {1 => 300, 2 => 200, 3 => 100},
+    };
+
+    let rpc = VoteRequest::new(5, 0, LogId::new(1, 2));
+    let responses = network.broadcast_vote(btreeset! {1,2,3,4}, rpc).collect::<Vec<_>>().await;
+
+    let order = responses.iter().map(|(target, _)| *target).collect::<Vec<_>>();
+    assert_eq!(vec![4, 3, 2, 1], order, "in the order of arrival");
+
+    assert!(responses[0].1.is_err(), "node-4 is unreachable");
+    for (target, res) in &responses[1..] {
+        let resp = res.as_ref().unwrap_or_else(|err| panic!("node-{} responds: {}", target, err));
+        assert_eq!(5, resp.term);
+        assert!(resp.vote_granted);
+    }
+
+    Ok(())
+}
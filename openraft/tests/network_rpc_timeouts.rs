@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A RequestVote RPC that never responds is abandoned once `vote_timeout` expires.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with `vote_timeout` much shorter than `install_snapshot_timeout`, in which node-1
+///   and node-2 never respond to a vote request.
+/// - isolates the leader node-0, so that no leader can be elected and node-1 keeps requesting votes from node-2.
+/// - asserts the vote requests to node-2 are abandoned after about `vote_timeout`, not the longer budget of an
+///   InstallSnapshot RPC.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn vote_rpc_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = config()?;
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+
+    tracing::info!("--- node-1 and node-2 stop responding to votes, isolate node-0, node-1 requests votes");
+    {
+        // Otherwise node-2 may be elected before node-1 requests a vote from it.
+        router.set_unresponsive_voter(1);
+        router.set_unresponsive_voter(2);
+        router.isolate_node(0).await;
+    }
+
+    let waited = tokio::time::timeout(Duration::from_millis(5_000), async {
+        loop {
+            let waited = router.abandoned_votes(2);
+            if !waited.is_empty() {
+                return waited;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await?;
+
+    let vote_timeout = Duration::from_millis(config.vote_timeout);
+    let snapshot_timeout = Duration::from_millis(config.install_snapshot_timeout);
+
+    for w in waited {
+        assert!(
+            w >= vote_timeout,
+            "a vote request is not abandoned before the vote timeout: {:?}",
+            w
+        );
+        assert!(
+            w < snapshot_timeout,
+            "a vote request is abandoned long before the install-snapshot timeout: {:?}",
+            w
+        );
+    }
+
+    Ok(())
+}
+
+/// An InstallSnapshot RPC is given `install_snapshot_timeout`, which is longer than the timeouts of the other RPCs.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster and send enough requests to it to build a snapshot.
+/// - delay every InstallSnapshot RPC to node-1 longer than `vote_timeout` and `heartbeat_interval`, but within
+///   `install_snapshot_timeout`.
+/// - add node-1 as a learner and assert it receives the snapshot.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn install_snapshot_rpc_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = config()?;
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send enough requests to build a snapshot");
+    {
+        router.client_request_many(0, "0", (SNAPSHOT_THRESHOLD - n_logs) as usize).await;
+        n_logs = SNAPSHOT_THRESHOLD;
+
+        router
+            .wait_for_snapshot(&btreeset! {0}, LogId { term: 1, index: n_logs }, timeout(), "snapshot")
+            .await?;
+    }
+
+    tracing::info!("--- add a learner that receives snapshots slowly");
+    {
+        let delay = config.vote_timeout.max(config.heartbeat_interval) * 3;
+        assert!(delay < config.install_snapshot_timeout);
+
+        router.set_snapshot_send_delay(1, delay);
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+
+        router
+            .wait_for_snapshot(
+                &btreeset! {1},
+                LogId { term: 1, index: n_logs },
+                timeout(),
+                "node-1 snapshot",
+            )
+            .await?;
+        router.wait_for_log(&btreeset! {1}, n_logs, timeout(), "node-1 catches up").await?;
+    }
+
+    Ok(())
+}
+
+const SNAPSHOT_THRESHOLD: u64 = 20;
+
+fn config() -> Result<Arc<Config>> {
+    let config = Config {
+        vote_timeout: 100,
+        install_snapshot_timeout: 1_000,
+        snapshot_policy: SnapshotPolicy::LogsSinceLast(SNAPSHOT_THRESHOLD),
+        max_applied_log_to_keep: 2,
+        ..Default::default()
+    }
+    .validate()?;
+
+    Ok(Arc::new(config))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::NodeId;
+use openraft::PauseReplicationError;
+use openraft::RaftMetrics;
+use openraft::ReplicationStatus;
+
+#[macro_use]
+mod fixtures;
+
+/// Pausing replication to a follower stops its matched log from advancing, and resuming lets it catch up.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and pauses replication to node-2.
+/// - writes some logs, asserts they are replicated to node-1 but not to node-2, and node-2 is reported as paused and
+///   lagging, without being removed.
+/// - resumes replication to node-2, and asserts it catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn pause_replication() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            // No election while node-2 does not hear from the leader.
+            election_timeout_min: 3_000,
+            election_timeout_max: 3_100,
+            replication_lag_threshold: 5,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a node not replicated to can not be paused");
+    {
+        let res = n0.pause_replication(9).await;
+        match res.unwrap_err() {
+            PauseReplicationError::NotReplicated { node_id } => assert_eq!(9, node_id),
+            err => panic!("expect NotReplicated, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- pause node-2, its matched log does not advance");
+    let paused_at = {
+        // Pause it once the leader sees it in sync, so that no response in flight advances its matched log.
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| matched_index(x, 2) == Some(want), "node-2 is in sync")
+            .await?;
+        let matched = m.leader_metrics.as_ref().unwrap().replication[&2].matched;
+
+        n0.pause_replication(2).await?;
+
+        router.client_request_many(0, "foo", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "write 10 logs").await?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let m = n0.metrics().borrow().clone();
+        let repl = &m.leader_metrics.as_ref().unwrap().replication[&2];
+        assert_eq!(matched, repl.matched, "node-2 matched does not advance");
+        assert!(repl.paused);
+        assert_eq!(ReplicationStatus::Lagging, repl.state);
+        assert!(m.membership_config.membership.contains(&2), "node-2 is not removed");
+
+        let m2 = router.get_raft_handle(&2).await?.metrics().borrow().clone();
+        assert!(m2.last_log_index < want, "node-2 receives no log");
+
+        matched
+    };
+
+    tracing::info!("--- resume node-2, it catches up");
+    {
+        n0.resume_replication(2).await?;
+
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "node-2 catches up").await?;
+
+        // The leader sees it catch up only after node-2 responds.
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| matched_index(x, 2) == Some(want), "the leader sees node-2 catch up")
+            .await?;
+        let repl = &m.leader_metrics.as_ref().unwrap().replication[&2];
+        assert!(repl.matched > paused_at);
+        assert!(!repl.paused);
+    }
+
+    Ok(())
+}
+
+/// The index of the matched log of `target` seen by the leader.
+fn matched_index(m: &RaftMetrics, target: NodeId) -> Option<u64> {
+    m.leader_metrics.as_ref().and_then(|x| x.replication.get(&target)).map(|x| x.matched.index)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::PeerHealth;
+use openraft::RaftMetrics;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader classifies a target it fails to reach as `Unreachable`, and as `Healthy` again once it is reached.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, asserts the leader reports both followers `Healthy`.
+/// - isolates node-2 so that every RPC to it fails, asserts the leader reports it `Unreachable` while node-1 is still
+///   `Healthy`.
+/// - restores node-2, asserts the leader reports it `Healthy` again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn peer_health() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            unreachable_rpc_failures: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router
+        .wait(&0, timeout())
+        .await?
+        .metrics(
+            |x| health(x, 1) == Some(PeerHealth::Healthy) && health(x, 2) == Some(PeerHealth::Healthy),
+            "both followers are healthy",
+        )
+        .await?;
+
+    tracing::info!("--- isolate node-2, it becomes unreachable");
+    {
+        router.isolate_node(2).await;
+
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| health(x, 2) == Some(PeerHealth::Unreachable),
+                "node-2 is unreachable",
+            )
+            .await?;
+
+        assert_eq!(Some(PeerHealth::Healthy), health(&m, 1));
+
+        let failures = m.leader_metrics.as_ref().map(|x| x.replication[&2].consecutive_failures).unwrap_or_default();
+        assert!(failures > 2, "more failures than the threshold: {}", failures);
+    }
+
+    tracing::info!("--- restore node-2, it becomes healthy again");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| health(x, 2) == Some(PeerHealth::Healthy), "node-2 is healthy again")
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn health(m: &RaftMetrics, target: u64) -> Option<PeerHealth> {
+    m.leader_metrics.as_ref().and_then(|x| x.peer_health.get(&target).copied())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
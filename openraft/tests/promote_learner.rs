@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::ChangeMembershipError;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// Add learners then promote them to voters.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster.
+/// - add node-1 as learner, assert it replicates logs but is not a voter.
+/// - promote node-1, assert it becomes a voter.
+/// - assert promoting a node that is not a learner is rejected.
+/// - add node-2 as learner and make it lag behind, assert promoting it is rejected and the membership is unchanged.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn promote_learner() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add node-1 as learner");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+
+        router.client_request_many(0, "foo", 2).await;
+        want += 2;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "learner replicates logs").await?;
+        router.wait(&0, None).await?.members(btreeset! {0}, "node-1 is not a voter").await?;
+    }
+
+    tracing::info!("--- promote node-1");
+    {
+        router.promote_learner(0, 1).await?;
+        // joint config and uniform config
+        want += 2;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "promote node-1").await?;
+        router.wait(&0, None).await?.members(btreeset! {0,1}, "node-1 is a voter").await?;
+        router.wait(&1, None).await?.members(btreeset! {0,1}, "node-1 is a voter").await?;
+    }
+
+    tracing::info!("--- promote a node that is not a learner");
+    {
+        let res = router.promote_learner(0, 3).await;
+        match res.unwrap_err() {
+            ClientWriteError::ChangeMembershipError(ChangeMembershipError::LearnerNotFound { node_id }) => {
+                assert_eq!(3, node_id);
+            }
+            err => panic!("expect LearnerNotFound, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- promote a lagging learner");
+    {
+        router.new_raft_node(2).await;
+        router.isolate_node(2).await;
+        router.add_learner_with_blocking(0, 2, false).await?;
+
+        router.client_request_many(0, "foo", (lag_threshold * 2) as usize).await;
+        want += lag_threshold * 2;
+        router.wait_for_log(&btreeset![0, 1], want, None, "write while node-2 is isolated").await?;
+
+        let res = router.promote_learner(0, 2).await;
+        match res.unwrap_err() {
+            ClientWriteError::ChangeMembershipError(ChangeMembershipError::LearnerIsLagging { node_id, .. }) => {
+                assert_eq!(2, node_id);
+            }
+            err => panic!("expect LearnerIsLagging, got: {:?}", err),
+        }
+
+        let m = router.wait(&0, Some(Duration::from_millis(500))).await?.metrics(|_| true, "get metrics").await?;
+        assert_eq!(want, m.last_log_index, "no membership log is written");
+        assert_eq!(Some(&btreeset! {0,1}), m.membership_config.membership.get_ith_config(0));
+        assert_eq!(None, m.membership_config.membership.get_ith_config(1));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::LogPurgePolicy;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// With `LogPurgePolicy::AfterSnapshot`, logs are purged only after a snapshot including them is built.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, purging logs after snapshot and keeping 2 logs.
+/// - writes logs below the snapshot threshold, and asserts no log is purged although all of them are applied.
+/// - writes logs upto the snapshot threshold, and asserts the logs included in the snapshot are purged, except the last
+///   2 of them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn purge_after_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold = 20;
+
+    let config = Arc::new(
+        Config {
+            max_applied_log_to_keep: 2,
+            log_purge_policy: LogPurgePolicy::AfterSnapshot,
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs below the snapshot threshold, no log is purged");
+    {
+        let count = (snapshot_threshold - 1 - n_logs) as usize;
+        router.client_request_many(0, "0", count).await;
+        n_logs = snapshot_threshold - 1;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "all logs are applied")
+            .await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let logs = sto.get_log_entries(..).await?;
+        assert_eq!(0, logs[0].log_id.index, "no log is purged before a snapshot is built");
+        assert_eq!(n_logs, logs[logs.len() - 1].log_id.index);
+    }
+
+    tracing::info!("--- write upto the snapshot threshold, logs in the snapshot are purged");
+    {
+        router.client_request(0, "0", 1000).await;
+        n_logs += 1;
+
+        router
+            .wait_for_snapshot(&btreeset! {0}, LogId::new(1, n_logs), timeout(), "snapshot is built")
+            .await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let logs = sto.get_log_entries(..).await?;
+        assert_eq!(2, logs.len(), "only {} logs are kept before the snapshot boundary", 2);
+        assert_eq!(n_logs - 1, logs[0].log_id.index);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftEvent;
+use tokio::sync::broadcast;
+
+#[macro_use]
+mod fixtures;
+
+/// Lifecycle events are broadcast to every subscriber, in the order they happen.
+///
+/// What does this test do?
+///
+/// - subscribe twice to the events of node 0, and once to node 1, before they join a cluster.
+/// - initialize a single node cluster of node 0, add node 1 and 2 and change the membership to {0,1,2}.
+/// - assert both subscribers of node 0 receive: the initial membership, becoming the leader of term 1, the learners
+///   being ready, the joint and then the uniform membership.
+/// - transfer the leadership to node 1.
+/// - assert node 1 receives becoming a follower of node 0, then the uniform membership, then becoming the leader of a
+///   greater term; and node 0 receives becoming a follower of that term.
+/// - shut down node 0, assert its subscribers see the channel closed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn raft_events() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut ev0 = router.get_raft_handle(&0).await?.events();
+    let mut ev0_other = router.get_raft_handle(&0).await?.events();
+    let mut ev1 = router.get_raft_handle(&1).await?.events();
+
+    let mut want = 0;
+
+    tracing::info!("--- initialize node 0 and change membership to {{0,1,2}}");
+    {
+        router.initialize_with(0, btreeset! {0}).await?;
+        want += 1;
+        router.wait_for_log(&btreeset! {0}, want, timeout(), "init").await?;
+
+        router.add_learner(0, 1).await?;
+        router.add_learner(0, 2).await?;
+        router.change_membership(0, btreeset! {0,1,2}).await?;
+        want += 2;
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "cluster of {0,1,2}").await?;
+    }
+
+    tracing::info!("--- every subscriber of node 0 receives the same events in order");
+    for rx in [&mut ev0, &mut ev0_other] {
+        match recv(rx).await? {
+            RaftEvent::MembershipChanged(m) => {
+                assert_eq!(Some(&btreeset! {0}), m.membership.get_ith_config(0));
+            }
+            ev => panic!("expect the initial membership, got: {:?}", ev),
+        }
+
+        assert_eq!(RaftEvent::BecameLeader(1), recv(rx).await?);
+
+        let mut ready = btreeset! {};
+        loop {
+            match recv(rx).await? {
+                RaftEvent::LearnerReady(id) => {
+                    ready.insert(id);
+                }
+                RaftEvent::MembershipChanged(m) => {
+                    assert!(m.membership.is_in_joint_consensus());
+                    break;
+                }
+                ev => panic!("expect the joint membership, got: {:?}", ev),
+            }
+        }
+        assert_eq!(
+            btreeset! {1,2},
+            ready,
+            "learners are ready before they are added as voters"
+        );
+
+        match recv(rx).await? {
+            RaftEvent::MembershipChanged(m) => {
+                assert!(!m.membership.is_in_joint_consensus());
+                assert_eq!(Some(&btreeset! {0,1,2}), m.membership.get_ith_config(0));
+            }
+            ev => panic!("expect the uniform membership, got: {:?}", ev),
+        }
+    }
+
+    tracing::info!("--- transfer leadership to node 1");
+    {
+        router.transfer_leadership(0, Some(1)).await?;
+
+        let mut events = vec![];
+        loop {
+            let ev = recv(&mut ev1).await?;
+            events.push(ev.clone());
+            if let RaftEvent::BecameLeader(_) = ev {
+                break;
+            }
+        }
+        tracing::info!("events of node 1: {:?}", events);
+
+        let follower_at = events
+            .iter()
+            .position(|ev| ev == &RaftEvent::BecameFollower(1, Some(0)))
+            .expect("node 1 becomes a follower of node 0");
+
+        let uniform_at = events
+            .iter()
+            .rposition(|ev| match ev {
+                RaftEvent::MembershipChanged(m) => m.membership.get_configs() == &vec![btreeset! {0,1,2}],
+                _ => false,
+            })
+            .expect("node 1 receives the uniform membership");
+
+        assert!(follower_at < uniform_at);
+
+        let term = match events.last() {
+            Some(RaftEvent::BecameLeader(term)) => *term,
+            ev => panic!("expect node 1 becomes the leader, got: {:?}", ev),
+        };
+        assert!(term > 1);
+
+        match recv(&mut ev0).await? {
+            RaftEvent::BecameFollower(t, _) => assert_eq!(term, t),
+            ev => panic!("expect node 0 becomes a follower, got: {:?}", ev),
+        }
+    }
+
+    tracing::info!("--- the channel is closed when node 0 shuts down");
+    {
+        let (n0, _sto) = router.remove_node(0).await.unwrap();
+        n0.shutdown().await?;
+
+        while !matches!(
+            tokio::time::timeout(timeout().unwrap(), ev0_other.recv()).await?,
+            Err(broadcast::error::RecvError::Closed)
+        ) {}
+
+        assert!(matches!(
+            n0.events().recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        ));
+    }
+
+    Ok(())
+}
+
+async fn recv(rx: &mut broadcast::Receiver<RaftEvent>) -> Result<RaftEvent> {
+    Ok(tokio::time::timeout(timeout().unwrap(), rx.recv()).await??)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
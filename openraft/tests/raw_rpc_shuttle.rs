@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use memstore::MemStore;
+use openraft::async_trait::async_trait;
+use openraft::error::RaftError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::Config;
+use openraft::LogId;
+use openraft::NodeId;
+use openraft::Raft;
+use openraft::RaftNetwork;
+use openraft::SnapshotPolicy;
+use openraft::State;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+#[macro_use]
+mod fixtures;
+
+type ShuttleRaft = Raft<ClientRequest, ClientResponse, ShuttleNetwork, MemStore>;
+
+/// An RPC handed out by a node, along with the channel to send back the response.
+enum Rpc {
+    AppendEntries(
+        AppendEntriesRequest<ClientRequest>,
+        oneshot::Sender<Result<AppendEntriesResponse, RaftError>>,
+    ),
+    Vote(VoteRequest, oneshot::Sender<Result<VoteResponse, RaftError>>),
+    InstallSnapshot(
+        InstallSnapshotRequest,
+        oneshot::Sender<Result<InstallSnapshotResponse, RaftError>>,
+    ),
+    TimeoutNow(
+        TimeoutNowRequest,
+        oneshot::Sender<Result<TimeoutNowResponse, RaftError>>,
+    ),
+}
+
+/// A network that does not deliver anything: it hands every RPC to the test, which shuttles it to the target.
+struct ShuttleNetwork {
+    tx: mpsc::UnboundedSender<(NodeId, Rpc)>,
+}
+
+impl ShuttleNetwork {
+    async fn call<Resp>(
+        &self,
+        target: NodeId,
+        rpc: impl FnOnce(oneshot::Sender<Result<Resp, RaftError>>) -> Rpc,
+    ) -> Result<Resp> {
+        let (tx, rx) = oneshot::channel();
+        self.tx.send((target, rpc(tx))).map_err(|_| anyhow!("shuttle is closed"))?;
+
+        let resp = rx.await.map_err(|_| anyhow!("target {} dropped the rpc", target))??;
+        Ok(resp)
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<ClientRequest> for ShuttleNetwork {
+    async fn send_append_entries(
+        &self,
+        target: NodeId,
+        rpc: AppendEntriesRequest<ClientRequest>,
+    ) -> Result<AppendEntriesResponse> {
+        self.call(target, |tx| Rpc::AppendEntries(rpc, tx)).await
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: NodeId,
+        rpc: InstallSnapshotRequest,
+    ) -> Result<InstallSnapshotResponse> {
+        self.call(target, |tx| Rpc::InstallSnapshot(rpc, tx)).await
+    }
+
+    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse> {
+        self.call(target, |tx| Rpc::Vote(rpc, tx)).await
+    }
+
+    async fn send_timeout_now(&self, target: NodeId, rpc: TimeoutNowRequest) -> Result<TimeoutNowResponse> {
+        self.call(target, |tx| Rpc::TimeoutNow(rpc, tx)).await
+    }
+}
+
+/// The number of RPCs of every kind the test has shuttled.
+#[derive(Default)]
+struct Shuttled {
+    append_entries: AtomicU64,
+    vote: AtomicU64,
+    install_snapshot: AtomicU64,
+    timeout_now: AtomicU64,
+}
+
+/// Deliver every RPC to its target by calling the raw handler of the target `Raft`.
+async fn shuttle(
+    nodes: BTreeMap<NodeId, ShuttleRaft>,
+    mut rx: mpsc::UnboundedReceiver<(NodeId, Rpc)>,
+    shuttled: Arc<Shuttled>,
+) {
+    while let Some((target, rpc)) = rx.recv().await {
+        let raft = nodes[&target].clone();
+
+        // Do not block other RPCs while the target is handling this one.
+        match rpc {
+            Rpc::AppendEntries(rpc, tx) => {
+                shuttled.append_entries.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move { tx.send(raft.append_entries(rpc).await) });
+            }
+            Rpc::Vote(rpc, tx) => {
+                shuttled.vote.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move { tx.send(raft.vote(rpc).await) });
+            }
+            Rpc::InstallSnapshot(rpc, tx) => {
+                shuttled.install_snapshot.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move { tx.send(raft.install_snapshot(rpc).await) });
+            }
+            Rpc::TimeoutNow(rpc, tx) => {
+                shuttled.timeout_now.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move { tx.send(raft.timeout_now(rpc).await) });
+            }
+        }
+    }
+}
+
+/// A cluster runs without a `RaftNetwork` delivering RPCs, if the application feeds them into the raw handlers.
+///
+/// What does this test do?
+///
+/// - brings up 2 nodes whose network only hands RPCs to the test, which calls the raw handler of the target.
+/// - initializes node-0 alone and writes logs until a snapshot is built and the logs are purged.
+/// - adds node-1 as a learner, asserts it receives the snapshot with `InstallSnapshot`.
+/// - changes membership to `{0,1}`, writes logs, asserts both nodes apply them.
+/// - transfers leadership to node-1, asserts it is elected with `TimeoutNow` and `Vote`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn raw_rpc_shuttle() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut nodes = BTreeMap::new();
+    for id in [0, 1] {
+        let network = Arc::new(ShuttleNetwork { tx: tx.clone() });
+        let sto = Arc::new(MemStore::new(id).await);
+        nodes.insert(id, Raft::new(id, config.clone(), network, sto));
+    }
+    let n0 = nodes[&0].clone();
+    let n1 = nodes[&1].clone();
+
+    let shuttled = Arc::new(Shuttled::default());
+    tokio::spawn(shuttle(nodes, rx, shuttled.clone()));
+
+    tracing::info!("--- initialize node-0 and build a snapshot");
+    let mut n_logs = 1;
+    {
+        n0.initialize(btreeset! {0}).await?;
+        n0.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+
+        n_logs += write(&n0, snapshot_threshold - n_logs).await?;
+        n0.wait(timeout()).snapshot(LogId { term: 1, index: n_logs }, "snapshot is built").await?;
+    }
+
+    tracing::info!("--- add node-1 as learner, it receives the snapshot");
+    {
+        n0.add_learner(1, true).await?;
+        n1.wait(timeout()).metrics(|x| x.last_applied == n_logs, "node-1 applies the snapshot").await?;
+
+        assert!(
+            shuttled.install_snapshot.load(Ordering::Relaxed) > 0,
+            "snapshot is shuttled"
+        );
+    }
+
+    tracing::info!("--- change membership to {{0,1}} and write logs");
+    {
+        n0.change_membership(btreeset! {0,1}, true).await?;
+        n_logs += 2;
+
+        n_logs += write(&n0, 5).await?;
+        for n in [&n0, &n1] {
+            n.wait(timeout()).metrics(|x| x.last_applied == n_logs, "every node applies the logs").await?;
+        }
+
+        assert!(shuttled.append_entries.load(Ordering::Relaxed) > 0, "logs are shuttled");
+    }
+
+    tracing::info!("--- transfer leadership to node-1");
+    {
+        n0.transfer_leadership(Some(1)).await?;
+        n1.wait(timeout()).state(State::Leader, "node-1 is leader").await?;
+
+        assert!(
+            shuttled.timeout_now.load(Ordering::Relaxed) > 0,
+            "timeout-now is shuttled"
+        );
+        assert!(shuttled.vote.load(Ordering::Relaxed) > 0, "vote is shuttled");
+    }
+
+    n0.shutdown().await?;
+    n1.shutdown().await?;
+
+    Ok(())
+}
+
+/// Write `n` logs to the leader, return the number of logs written.
+async fn write(leader: &ShuttleRaft, n: u64) -> Result<u64> {
+    for serial in 0..n {
+        leader
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial,
+                status: format!("request-{}", serial),
+            }))
+            .await?;
+    }
+    Ok(n)
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::ClientReadError;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// A local read on a follower succeeds only if the follower has been in sync with the leader within the bound.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters and writes logs.
+/// - reads node-1 locally, asserts it returns the last applied log id.
+/// - reads the leader locally, asserts it succeeds since a quorum has confirmed its leadership within the bound.
+/// - isolates node-2 for longer than the bound, asserts a local read on it returns `Stale` with the leader id.
+/// - isolates the leader for longer than the bound, asserts a local read on it returns `Stale`: it may have been
+///   deposed without knowing it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn read_local() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 5).await;
+    n_logs += 5;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "cluster applies logs").await?;
+
+    // Several heartbeats fit in the bound.
+    let max_staleness = Duration::from_millis(config.heartbeat_interval * 10);
+
+    tracing::info!("--- read a follower in sync with the leader");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let applied = n1.read_local(max_staleness).await?;
+        assert_eq!(n_logs, applied.index);
+    }
+
+    tracing::info!("--- read the leader");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        let applied = n0.read_local(max_staleness).await?;
+        assert_eq!(n_logs, applied.index);
+    }
+
+    tracing::info!("--- read a follower isolated for longer than the bound");
+    {
+        router.isolate_node(2).await;
+        tokio::time::sleep(max_staleness * 2).await;
+
+        let n2 = router.get_raft_handle(&2).await?;
+        let res = n2.read_local(max_staleness).await;
+
+        match res.unwrap_err() {
+            ClientReadError::Stale { leader_id, .. } => {
+                assert_eq!(Some(0), leader_id);
+            }
+            err => panic!("expect Stale, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- read the leader isolated for longer than the bound");
+    {
+        router.isolate_node(0).await;
+        tokio::time::sleep(max_staleness * 2).await;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let res = n0.read_local(max_staleness).await;
+
+        match res.unwrap_err() {
+            ClientReadError::Stale { .. } => {}
+            err => panic!("expect Stale, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
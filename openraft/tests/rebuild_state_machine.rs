@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorageDebug;
+use openraft::RebuildStateMachineError;
+use openraft::State;
+use openraft::Wrapper;
+
+#[macro_use]
+mod fixtures;
+
+/// Rebuilding a state machine resets it to the snapshot and re-applies the logs after it.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, writes some logs, builds a snapshot on node-1 and writes some more logs.
+/// - corrupts the state machine of node-1.
+/// - rebuilds the state machine of node-1, asserts it is the same as before it is corrupted.
+/// - asserts the leader rejects a rebuild.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn rebuild_state_machine() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs, build a snapshot on node-1, then write more logs");
+    {
+        router.client_request_many(0, "foo", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "write 10 logs").await?;
+
+        let n1 = router.get_raft_handle(&1).await?;
+        let meta = n1.trigger_snapshot().await?;
+        assert_eq!(LogId { term: 1, index: want }, meta.last_log_id);
+
+        router.client_request_many(0, "bar", 5).await;
+        want += 5;
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "write 5 more logs").await?;
+    }
+
+    let sto1 = router.get_storage_handle(&1).await?;
+    let expected = sto1.get_state_machine().await;
+
+    tracing::info!("--- corrupt the state machine of node-1");
+    {
+        let mut corrupted = expected.clone();
+        corrupted.client_status.clear();
+        corrupted.client_status.insert("baz".to_string(), "garbage".to_string());
+        sto1.inner().set_state_machine(corrupted).await;
+    }
+
+    tracing::info!("--- rebuild the state machine of node-1");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let last_applied = n1.rebuild_state_machine().await?;
+        assert_eq!(LogId { term: 1, index: want }, last_applied);
+
+        let sm = sto1.get_state_machine().await;
+        assert_eq!(expected.last_applied_log, sm.last_applied_log);
+        assert_eq!(expected.client_status, sm.client_status);
+        assert_eq!(expected.client_serial_responses, sm.client_serial_responses);
+        assert_eq!(Some(&"request-4".to_string()), sm.client_status.get("bar"));
+    }
+
+    tracing::info!("--- the leader rejects a rebuild");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        let res = n0.rebuild_state_machine().await;
+
+        match res.unwrap_err() {
+            RebuildStateMachineError::NotAllowed(state) => {
+                assert_eq!(State::Leader, state);
+            }
+            err => panic!("expect NotAllowed, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
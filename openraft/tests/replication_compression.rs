@@ -0,0 +1,217 @@
+#![cfg(any(feature = "lz4_flex", feature = "zstd"))]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::config::CAPABILITIES_RETRY_HEARTBEATS;
+use openraft::Compression;
+use openraft::Config;
+use openraft::NodeCapabilities;
+use openraft::RaftStorageDebug;
+
+#[macro_use]
+mod fixtures;
+
+/// A leader replicates compressed entries with every codec, and the follower decompresses them.
+///
+/// What does this test do?
+///
+/// - for every codec enabled by cargo features, brings up a single node cluster compressing with it, and writes some
+///   logs.
+/// - adds a learner, so that the logs are replicated to it in batches.
+/// - asserts compressed AppendEntries are sent to the learner, and its state machine is the same as the leader's.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_compression() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    for codec in enabled_codecs() {
+        tracing::info!("--- replicate with {:?}", codec);
+
+        let router = Arc::new(RaftRouter::new(config(codec)?));
+
+        let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+        router.client_request_many(0, "foo", 50).await;
+        want += 50;
+
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "replicate to learner").await?;
+
+        assert!(
+            router.compressed_append_entries_sent(1) > 0,
+            "{:?}: compressed entries are sent",
+            codec
+        );
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status, "{:?}: same state machine", codec);
+        assert_eq!(Some(&"request-49".to_string()), sm1.client_status.get("foo"));
+    }
+
+    Ok(())
+}
+
+/// A leader sends entries uncompressed to a follower that fails to decompress them.
+///
+/// What does this test do?
+///
+/// - for every codec enabled by cargo features, brings up a single node cluster compressing with it, and writes some
+///   logs.
+/// - adds a learner with a `max_payload_bytes` so small that it refuses to decompress the entries.
+/// - asserts the logs are still replicated to it, and no more compressed AppendEntries is sent to it after that.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_compression_decompression_failure() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    for codec in enabled_codecs() {
+        tracing::info!("--- replicate with {:?}", codec);
+
+        let router = Arc::new(RaftRouter::new(config(codec)?));
+
+        let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+        router.client_request_many(0, "foo", 50).await;
+        want += 50;
+
+        let learner_config = Config {
+            replication_compression: codec,
+            max_payload_bytes: 64,
+            ..Default::default()
+        }
+        .validate()?;
+
+        router.new_raft_node_with_config(1, Arc::new(learner_config)).await;
+        router.add_learner(0, 1).await?;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "replicate to learner").await?;
+
+        let compressed = router.compressed_append_entries_sent(1);
+        assert!(compressed > 0, "{:?}: compressed entries are tried", codec);
+
+        router.client_request_many(0, "foo", 50).await;
+        want += 50;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "replicate more to learner").await?;
+
+        assert_eq!(
+            compressed,
+            router.compressed_append_entries_sent(1),
+            "{:?}: no more compressed entries are sent",
+            codec
+        );
+    }
+
+    Ok(())
+}
+
+/// A leader sends entries uncompressed to a follower that does not support compression.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster compressing with an enabled codec, and writes some logs.
+/// - adds a learner that reports it does not support compression.
+/// - asserts no compressed AppendEntries is sent to the learner, and the logs are still replicated to it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_compression_not_supported() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let router = Arc::new(RaftRouter::new(config(enabled_codecs()[0])?));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 50).await;
+    want += 50;
+
+    router.set_capabilities(1, NodeCapabilities {
+        compression: false,
+        ..NodeCapabilities::current()
+    });
+
+    router.new_raft_node(1).await;
+    router.add_learner(0, 1).await?;
+    router.wait_for_log(&btreeset! {0,1}, want, timeout(), "replicate to learner").await?;
+
+    assert_eq!(
+        0,
+        router.compressed_append_entries_sent(1),
+        "no compressed entries are sent"
+    );
+    assert!(router.capabilities_queried(1) > 0, "capabilities are negotiated");
+
+    let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+    assert_eq!(Some(&"request-49".to_string()), sm1.client_status.get("foo"));
+
+    Ok(())
+}
+
+/// A leader that fails to query the capabilities of a follower does not query them again for every AppendEntries.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster compressing with an enabled codec, and writes some logs.
+/// - adds an isolated learner, so that the leader keeps failing to replicate to it and to query its capabilities.
+/// - asserts the capabilities are queried only once in a retry interval.
+/// - restores the learner, asserts the logs are replicated to it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_compression_capabilities_unavailable() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = config(enabled_codecs()[0])?;
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 50).await;
+    want += 50;
+
+    tracing::info!("--- add an isolated learner");
+    {
+        router.new_raft_node(1).await;
+        router.isolate_node(1).await;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        n0.add_learner(1, false).await?;
+
+        let retry_interval = config.heartbeat_interval * CAPABILITIES_RETRY_HEARTBEATS;
+        tokio::time::sleep(Duration::from_millis(retry_interval / 2)).await;
+
+        assert_eq!(
+            1,
+            router.capabilities_queried(1),
+            "a failed query is not retried at once"
+        );
+    }
+
+    tracing::info!("--- restore the learner, it is replicated to");
+    {
+        router.restore_node(1).await;
+        router.wait_for_log(&btreeset! {0,1}, want, timeout(), "replicate to learner").await?;
+    }
+
+    Ok(())
+}
+
+fn enabled_codecs() -> Vec<Compression> {
+    [Compression::Lz4, Compression::Zstd].into_iter().filter(|c| c.is_enabled()).collect()
+}
+
+fn config(codec: Compression) -> Result<Arc<Config>> {
+    let config = Config {
+        replication_compression: codec,
+        ..Default::default()
+    }
+    .validate()?;
+
+    Ok(Arc::new(config))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+
+#[macro_use]
+mod fixtures;
+
+/// `Raft::replication_lag()` reports how many logs a replication target is behind the leader.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and isolates node-2 so that it can not receive any log.
+/// - writes 10 logs, and asserts the leader reports node-2 is 10 logs behind, while node-1 is up to date.
+/// - asserts the lag of an unknown node, or the lag queried on a follower, is `None`.
+/// - restores node-2, and asserts its lag drops to 0 once it catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_lag() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let n1 = router.get_raft_handle(&1).await?;
+
+    tracing::info!("--- isolate node-2 and write 10 logs");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "foo", 10).await;
+        n_logs += 10;
+        router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "write 10 logs").await?;
+
+        n0.wait(timeout())
+            .metrics(
+                |x| {
+                    x.leader_metrics.as_ref().and_then(|lm| lm.replication.get(&1).map(|r| r.lag == 0)).unwrap_or(false)
+                },
+                "node-1 is up to date",
+            )
+            .await?;
+
+        assert_eq!(Some(10), n0.replication_lag(2));
+        assert_eq!(Some(0), n0.replication_lag(1));
+    }
+
+    tracing::info!("--- no lag of an unknown node, or on a follower");
+    {
+        assert_eq!(None, n0.replication_lag(9));
+        assert_eq!(None, n1.replication_lag(2));
+    }
+
+    tracing::info!("--- restore node-2, it catches up");
+    {
+        router.restore_node(2).await;
+        router.wait_for_log(&btreeset! {2}, n_logs, timeout(), "node-2 catches up").await?;
+
+        n0.wait(timeout())
+            .metrics(
+                |x| {
+                    x.leader_metrics.as_ref().and_then(|lm| lm.replication.get(&2).map(|r| r.lag == 0)).unwrap_or(false)
+                },
+                "node-2 is up to date",
+            )
+            .await?;
+
+        assert_eq!(Some(0), n0.replication_lag(2));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
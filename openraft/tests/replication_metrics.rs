@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::ReplicationStatus;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader reports the replication metrics of every follower.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and writes some logs.
+/// - asserts the leader metrics contain one entry for every follower, with the matched log and latency.
+/// - asserts followers do not report replication metrics.
+/// - isolates node-2 and writes more logs than `replication_lag_threshold`, asserts node-2 is reported lagging.
+/// - restores node-2, asserts it is reported at line rate again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_metrics() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let lag_threshold = 5;
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: lag_threshold,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write 10 logs").await?;
+
+    tracing::info!("--- the leader reports every follower");
+    {
+        let want = LogId { term: 1, index: n_logs };
+
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.leader_metrics.as_ref().map_or(false, |l| {
+                        l.replication.len() == 2 && l.replication.values().all(|r| r.matched == want)
+                    })
+                },
+                "leader reports node-1 and node-2",
+            )
+            .await?;
+
+        let replication = m.leader_metrics.unwrap().replication.clone();
+        for id in [1, 2] {
+            let r = &replication[&id];
+            assert_eq!(want, r.matched, "node-{} matched", id);
+            assert_eq!(0, r.lag, "node-{} lag", id);
+            assert_eq!(ReplicationStatus::LineRate, r.state, "node-{} state", id);
+            assert!(r.last_rpc_latency_ms.is_some(), "node-{} latency", id);
+        }
+    }
+
+    tracing::info!("--- followers do not report replication metrics");
+    {
+        for id in [1, 2] {
+            let m = router.wait(&id, timeout()).await?.metrics(|_| true, "get follower metrics").await?;
+            assert!(m.leader_metrics.is_none(), "node-{} has no replication metrics", id);
+        }
+    }
+
+    tracing::info!("--- isolate node-2, it lags behind");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "foo", (lag_threshold * 2) as usize).await;
+        n_logs += lag_threshold * 2;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.leader_metrics.as_ref().map_or(false, |l| {
+                        l.replication[&1].matched.index == n_logs
+                            && l.replication[&1].state == ReplicationStatus::LineRate
+                            && l.replication[&2].lag > lag_threshold
+                            && l.replication[&2].state == ReplicationStatus::Lagging
+                    })
+                },
+                "node-2 is lagging",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restore node-2, it catches up");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.leader_metrics.as_ref().map_or(false, |l| {
+                        l.replication[&2].matched.index == n_logs
+                            && l.replication[&2].lag == 0
+                            && l.replication[&2].state == ReplicationStatus::LineRate
+                    })
+                },
+                "node-2 catches up",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+use tokio::time::sleep;
+
+#[macro_use]
+mod fixtures;
+
+/// The leader backs off retrying replication to an unreachable target, and resets the backoff once it recovers.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, with a retry backoff from 20 ms upto 400 ms and a heartbeat of 50 ms.
+/// - isolates node-1, waits for the backoff to reach the cap, and asserts the leader retries far less often than every
+///   heartbeat, but at least once every 400 ms.
+/// - writes logs and restores node-1, and asserts the logs are replicated to it within the cap.
+/// - asserts the leader sends to node-1 every heartbeat again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_retry_backoff() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 1000,
+            election_timeout_max: 1500,
+            replication_retry_base: 20,
+            replication_retry_max: 400,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+    router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+
+    tracing::info!("--- isolate node-1, the retries back off");
+    {
+        router.isolate_node(1).await;
+
+        // Let the backoff grow to the cap.
+        sleep(Duration::from_millis(1_000)).await;
+
+        let before = router.append_entries_attempts(1);
+        sleep(Duration::from_millis(2_000)).await;
+        let attempts = router.append_entries_attempts(1) - before;
+
+        tracing::info!(attempts, "attempts to isolated node-1 in 2 seconds");
+
+        // Retrying every heartbeat would make 40 attempts.
+        assert!(attempts < 20, "retries back off, got {} attempts", attempts);
+        assert!(
+            attempts >= 4,
+            "the delay is capped at 400 ms, got {} attempts",
+            attempts
+        );
+    }
+
+    tracing::info!("--- restore node-1, the backoff resets");
+    {
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.restore_node(1).await;
+        router
+            .wait(&1, Some(Duration::from_millis(1_000)))
+            .await?
+            .log(n_logs, "node-1 catches up within the cap")
+            .await?;
+
+        let before = router.append_entries_sent(1);
+        sleep(Duration::from_millis(1_000)).await;
+        let sent = router.append_entries_sent(1) - before;
+
+        tracing::info!(sent, "sent to node-1 in 1 second after recovery");
+        assert!(sent >= 10, "heartbeats are sent at the normal rate again, got {}", sent);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
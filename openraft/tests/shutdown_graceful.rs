@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::RaftStorage;
+
+#[macro_use]
+mod fixtures;
+
+/// A graceful shutdown applies every committed entry before it returns.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, whose leader commits ahead of apply.
+/// - writes many logs concurrently to the leader, and shuts the leader down gracefully in the middle.
+/// - asserts the last applied log of the leader is the last write that is committed.
+/// - asserts the hard state is saved and a write after shutdown is rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn shutdown_graceful() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_in_flight_applies: 16,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- write concurrently and shutdown gracefully");
+    let mut writes = vec![];
+    {
+        for serial in 0..100 {
+            let n0 = n0.clone();
+            writes.push(tokio::spawn(async move {
+                n0.client_write(ClientWriteRequest::new(request(serial))).await
+            }));
+        }
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.last_applied >= n_logs + 10, "some writes are applied")
+            .await?;
+
+        n0.shutdown_graceful(Duration::from_millis(5_000)).await?;
+    }
+
+    let mut max_committed = 0;
+    for w in writes {
+        if let Ok(resp) = w.await? {
+            max_committed = std::cmp::max(max_committed, resp.log_id.index);
+        }
+    }
+    assert!(max_committed >= n_logs + 10);
+
+    let sto = router.get_storage_handle(&0).await?;
+    let (last_applied, _) = sto.last_applied_state().await?;
+    assert_eq!(max_committed, last_applied.index, "every committed write is applied");
+
+    let hs = sto.read_hard_state().await?.unwrap();
+    let m = n0.metrics().borrow().clone();
+    assert_eq!(m.current_term, hs.current_term, "hard state is saved");
+
+    let res = n0.client_write(ClientWriteRequest::new(request(100))).await;
+    assert!(res.is_err(), "a shutdown node rejects writes");
+
+    Ok(())
+}
+
+fn request(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use fixtures::RaftRouter;
@@ -31,9 +32,9 @@ async fn singlenode() -> Result<()> {
 
     let mut want = 0;
 
-    // Assert all nodes are in learner state & have no entries.
+    // Assert all nodes are uninitialized & have no entries.
     router.wait_for_log(&btreeset![0], want, None, "empty").await?;
-    router.wait_for_state(&btreeset![0], State::Learner, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
     router.assert_pristine_cluster().await;
 
     // Initialize the cluster, then assert that a stable cluster was formed & held.
@@ -54,3 +55,72 @@ async fn singlenode() -> Result<()> {
 
     Ok(())
 }
+
+/// A single-node cluster commits without any RPC round, and never holds an election while it is the leader.
+///
+/// What does this test do?
+///
+/// - initializes a single-node cluster, and writes some logs, asserting every one is committed once it is written.
+/// - asserts no RPC is sent, and the node stays the leader of term 1 for several election timeouts.
+/// - steps down the leader, and asserts it is elected again in the next term by its own vote, then stays the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn singlenode_stable_leadership() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- every write is committed at once");
+    {
+        for serial in 0..10 {
+            router.client_request(0, "foo", serial).await;
+            n_logs += 1;
+
+            let m = n0.metrics().borrow().clone();
+            assert_eq!(Some(LogId { term: 1, index: n_logs }), m.last_committed);
+        }
+
+        assert_eq!(0, router.append_entries_attempts(0));
+        assert_eq!(0, router.pre_votes_sent(0));
+    }
+
+    tracing::info!("--- no election while the node is the leader");
+    {
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(State::Leader, m.state);
+        assert_eq!(Some(0), m.current_leader);
+        assert_eq!(1, m.current_term);
+    }
+
+    tracing::info!("--- step down, the node elects itself in the next term");
+    {
+        let target = n0.step_down().await?;
+        assert_eq!(None, target, "there is no other voter to transfer to");
+
+        n0.wait(timeout())
+            .metrics(
+                |x| x.state == State::Leader && x.current_term == 2,
+                "node-0 is elected in term 2",
+            )
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(State::Leader, m.state);
+        assert_eq!(2, m.current_term, "no more election");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
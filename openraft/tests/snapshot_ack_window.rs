@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// With `snapshot_ack_every_n_chunks`, a leader sends several snapshot chunks before waiting for an acknowledgement,
+/// which installs a snapshot faster over a high-latency link than acknowledging every chunk.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, writes logs until a snapshot of many small chunks is built and the logs are
+///   purged.
+/// - adds a learner behind a high-latency link for snapshot chunks, and measures the time it takes to install the
+///   snapshot, with every chunk acknowledged.
+/// - does the same with a window of 8 chunks, and asserts the snapshot is installed faster.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_ack_window() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    tracing::info!("--- install a snapshot acknowledging every chunk");
+    let (per_chunk, _) = install_snapshot(1, 0).await?;
+
+    tracing::info!("--- install a snapshot acknowledging every 8 chunks");
+    let (windowed, _) = install_snapshot(8, 0).await?;
+
+    tracing::info!(?per_chunk, ?windowed, "snapshot install time");
+
+    assert!(
+        windowed < per_chunk,
+        "windowed acks: {:?}, per-chunk acks: {:?}",
+        windowed,
+        per_chunk
+    );
+
+    Ok(())
+}
+
+/// The target keeps the snapshot chunks that arrive out of order, thus the leader sends no chunk twice.
+///
+/// What does this test do?
+///
+/// - installs a snapshot on a learner, with every chunk acknowledged, and counts the chunks sent.
+/// - installs it again with a window of 8 chunks, each delayed by a random time, which reorders the chunks.
+/// - asserts the same number of chunks is sent.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_ack_window_reordered() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    tracing::info!("--- install a snapshot acknowledging every chunk");
+    let (_, in_order) = install_snapshot(1, 0).await?;
+
+    tracing::info!("--- install a snapshot with 8 chunks in flight that are reordered");
+    let (_, reordered) = install_snapshot(8, 20).await?;
+
+    assert_eq!(in_order, reordered, "no chunk is sent again");
+
+    Ok(())
+}
+
+/// Build a snapshot on a new single node cluster, and return the time it takes a new learner to install it and the
+/// number of chunks sent to it.
+///
+/// Every chunk is delayed by 20 ms, plus a random time less than `jitter_ms`.
+async fn install_snapshot(snapshot_ack_every_n_chunks: u64, jitter_ms: u64) -> Result<(Duration, u64)> {
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            max_applied_log_to_keep: 0,
+            snapshot_ack_every_n_chunks,
+            // A chunk that times out is sent again, which is not what is measured here.
+            install_snapshot_timeout: 2_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+    n_logs = snapshot_threshold;
+
+    router.wait(&0, timeout()).await?.snapshot(LogId::new(1, n_logs), "snapshot is built").await?;
+
+    router.new_raft_node(1).await;
+    router.set_snapshot_send_delay(1, 20);
+    if jitter_ms > 0 {
+        router.set_snapshot_send_jitter(1, jitter_ms);
+    }
+
+    let start = Instant::now();
+
+    router.add_learner_with_blocking(0, 1, false).await?;
+    router
+        .wait(&1, timeout())
+        .await?
+        .snapshot(LogId::new(1, n_logs), "learner installs snapshot")
+        .await?;
+
+    let elapsed = start.elapsed();
+
+    router.wait_for_log(&btreeset! {0,1}, n_logs, timeout(), "learner catches up").await?;
+
+    Ok((elapsed, router.snapshot_chunks_sent(1)))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
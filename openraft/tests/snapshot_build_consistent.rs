@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::SnapshotPolicy;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// `RaftStorage::snapshot_view()` is called with the log id the state machine is at, and the state machine does not
+/// change until the view is taken, even while the leader keeps applying logs.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a slow state machine and a slow `snapshot_view()`, building a snapshot every
+///   5 logs.
+/// - writes many logs concurrently, so that snapshots are built while entries are being applied.
+/// - asserts every call to `snapshot_view()` sees the state machine at the given `last_applied`, before and after
+///   taking the view.
+/// - triggers a snapshot once every log is applied, and asserts its `last_log_id` is the last committed log.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_build_consistent() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let n: u64 = 30;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(5),
+            max_in_flight_applies: 16,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            apply_delay: Duration::from_millis(10),
+            view_delay: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .await,
+    );
+    let raft = Raft::new(0, config.clone(), router.clone(), sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+
+    tracing::info!("--- write logs concurrently, snapshots are built meanwhile");
+    {
+        let mut writes = vec![];
+        for serial in 0..n {
+            let raft = raft.clone();
+            writes.push(tokio::spawn(async move {
+                raft.client_write(ClientWriteRequest::new(ClientRequest {
+                    client: "foo".to_string(),
+                    serial,
+                    status: format!("request-{}", serial),
+                }))
+                .await
+            }));
+        }
+
+        for w in writes {
+            w.await??;
+        }
+
+        raft.wait(timeout()).metrics(|x| x.last_applied == n + 1, "all applied").await?;
+        raft.wait(timeout()).metrics(|x| !x.snapshot_building, "no snapshot is being built").await?;
+
+        let builds = sto.views.lock().unwrap().clone();
+        assert!(!builds.is_empty(), "snapshots are built");
+
+        for (last_applied, before, after) in builds {
+            assert_eq!(
+                last_applied, before,
+                "the state machine is at last_applied when taking a view starts"
+            );
+            assert_eq!(
+                last_applied, after,
+                "the state machine does not change while taking a view"
+            );
+        }
+    }
+
+    tracing::info!("--- a triggered snapshot includes every committed log");
+    {
+        let meta = raft.trigger_snapshot().await?;
+
+        let m = raft.metrics().borrow().clone();
+        assert_eq!(m.last_committed, Some(meta.last_log_id));
+        assert_eq!(m.last_applied, meta.last_log_id.index);
+
+        let (last_applied, _, _) = *sto.views.lock().unwrap().last().unwrap();
+        assert_eq!(meta.last_log_id, last_applied);
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
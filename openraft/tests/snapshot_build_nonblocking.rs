@@ -0,0 +1,138 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::SnapshotPolicy;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+type SlowRaft = Raft<ClientRequest, ClientResponse, RaftRouter, HookedStore>;
+
+/// Logs are applied while a snapshot is being built from a view of the state machine taken with
+/// `RaftStorage::snapshot_view()`.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a snapshot builder taking 2 seconds, and writes some logs.
+/// - triggers a snapshot, and asserts `snapshot_building` is reported.
+/// - writes more logs, and asserts they are applied while the snapshot is still being built.
+/// - asserts the snapshot includes exactly the logs applied when it is triggered.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_build_nonblocking() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    build_while_writing(true).await
+}
+
+/// Logs are applied while a snapshot is being built with `RaftStorage::do_log_compaction()`, by a storage taking no
+/// view of the state machine.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a `do_log_compaction()` taking 2 seconds, and writes some logs.
+/// - triggers a snapshot, writes more logs, and asserts they are applied while the snapshot is still being built.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_build_nonblocking_without_view() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    build_while_writing(false).await
+}
+
+async fn build_while_writing(take_view: bool) -> Result<()> {
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(10_000),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            no_view: !take_view,
+            build_delay: Duration::from_millis(2_000),
+            ..Default::default()
+        })
+        .await,
+    );
+    let raft = Raft::new(0, config.clone(), router.clone(), sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).state(State::Leader, "node-0 is leader").await?;
+
+    let mut want = 1;
+    for serial in 0..10 {
+        write(&raft, serial).await?;
+    }
+    want += 10;
+    raft.wait(timeout()).metrics(|x| x.last_applied == want, "write 10 logs").await?;
+
+    tracing::info!("--- trigger a snapshot");
+    let snapshot_at = want;
+    let trigger = {
+        let r = raft.clone();
+        let trigger = tokio::spawn(async move { r.trigger_snapshot().await });
+
+        raft.wait(timeout()).metrics(|x| x.snapshot_building, "snapshot is being built").await?;
+        trigger
+    };
+
+    tracing::info!("--- logs are applied while the snapshot is being built");
+    {
+        for serial in 10..20 {
+            write(&raft, serial).await?;
+        }
+        want += 10;
+
+        let m = raft.metrics().borrow().clone();
+        assert_eq!(want, m.last_applied, "every write is applied once it returns");
+        assert!(m.snapshot_building, "the snapshot is still being built");
+    }
+
+    tracing::info!("--- the snapshot includes the logs applied when it is triggered");
+    {
+        let meta = trigger.await??;
+        let m = raft.wait(timeout()).metrics(|x| !x.snapshot_building, "snapshot is built").await?;
+        assert_eq!(want, m.last_applied);
+
+        if take_view {
+            assert_eq!(snapshot_at, meta.last_log_id.index);
+            assert_eq!(snapshot_at, m.snapshot.index);
+        } else {
+            // `do_log_compaction()` includes the logs applied by the time it reads the state machine.
+            assert!(meta.last_log_id.index >= snapshot_at);
+        }
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+async fn write(raft: &SlowRaft, serial: u64) -> Result<()> {
+    raft.client_write(ClientWriteRequest::new(ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }))
+    .await?;
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotChecksum;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// A corrupted snapshot is rejected by the checksum, and the leader sends it again.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster attaching a CRC32 to the snapshots it sends, and builds a snapshot.
+/// - corrupts the first snapshot chunk sent to node-1, then adds node-1 as a learner.
+/// - asserts node-1 rejects the corrupted snapshot, and installs the snapshot sent again, along with its checksum.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_checksum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 0,
+            snapshot_max_chunk_size: 10,
+            snapshot_checksum: SnapshotChecksum::Crc32,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], n_logs, timeout(), "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId::new(1, n_logs), timeout(), "snapshot").await?;
+    }
+
+    tracing::info!("--- add learner with the first snapshot chunk corrupted");
+    {
+        router.corrupt_snapshot_chunks(1, 1);
+
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "learner catches up").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![1],
+                LogId::new(1, n_logs),
+                timeout(),
+                "learner installs snapshot",
+            )
+            .await?;
+
+        assert_eq!(0, router.snapshot_chunks_to_corrupt(1), "a corrupted chunk is sent");
+
+        let sto = router.get_storage_handle(&1).await?;
+        let snapshot = sto.get_current_snapshot().await?.expect("snapshot is installed");
+        assert!(
+            snapshot.meta.checksum.is_some(),
+            "the installed snapshot carries a checksum"
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
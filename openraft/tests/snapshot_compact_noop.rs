@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// With `Config::compact_noop_on_snapshot`, the blank logs at the head of the log are purged after building a
+/// snapshot, as long as `max_applied_log_to_keep` logs are left.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters keeping 12 applied logs, with and without `compact_noop_on_snapshot`.
+/// - transfers leadership 10 times, which appends 10 blank logs, then writes 5 logs.
+/// - isolates the followers and writes 20 more logs, which the leader appends but can not commit.
+/// - triggers a snapshot on the leader.
+/// - asserts without the option, the leader keeps 12 applied logs starting with 7 blank ones, and the 20 logs.
+/// - asserts with the option, the 7 blank logs are purged, since the 20 logs after the snapshot are more than 12.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_compact_noop() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    for compact_noop in [false, true] {
+        tracing::info!("--- compact_noop_on_snapshot: {}", compact_noop);
+
+        let config = Arc::new(
+            Config {
+                max_applied_log_to_keep: 12,
+                compact_noop_on_snapshot: compact_noop,
+                snapshot_policy: SnapshotPolicy::LogsSinceLast(10_000),
+                ..Default::default()
+            }
+            .validate()?,
+        );
+        let router = Arc::new(RaftRouter::new(config.clone()));
+
+        let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+        tracing::info!("--- transfer leadership to append blank logs");
+        let mut leader = 0;
+        for _ in 0..10 {
+            let target = (leader + 1) % 3;
+            router.transfer_leadership(leader, Some(target)).await?;
+            n_logs += 1;
+
+            router
+                .wait(&target, timeout())
+                .await?
+                .metrics(
+                    |x| x.leader_ready && x.last_applied == n_logs,
+                    "new leader commits its blank log",
+                )
+                .await?;
+            leader = target;
+        }
+
+        tracing::info!("--- write logs and build a snapshot");
+        router.client_request_many(leader, "0", 5).await;
+        n_logs += 5;
+        router
+            .wait(&leader, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "logs are applied")
+            .await?;
+
+        tracing::info!("--- isolate the followers and write logs that can not be committed");
+        for id in 0..3 {
+            if id != leader {
+                router.isolate_node(id).await;
+            }
+        }
+        for serial in 0..20 {
+            let router = router.clone();
+            tokio::spawn(async move { router.client_request(leader, "1", serial).await });
+        }
+        router
+            .wait(&leader, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == n_logs + 20, "logs are appended")
+            .await?;
+
+        let n = router.get_raft_handle(&leader).await?;
+        let meta = n.trigger_snapshot().await?;
+        assert_eq!(n_logs, meta.last_log_id.index);
+
+        let sto = router.get_storage_handle(&leader).await?;
+        let logs = sto.get_log_entries(..).await?;
+        assert_eq!(n_logs + 20, logs[logs.len() - 1].log_id.index);
+
+        let is_blank = |i: usize| matches!(logs[i].payload, EntryPayload::Blank);
+
+        if compact_noop {
+            assert_eq!(5 + 20, logs.len(), "blank logs are purged");
+            assert!(!is_blank(0));
+        } else {
+            assert_eq!(12 + 20, logs.len(), "12 applied logs are kept");
+            assert!((0..7).all(is_blank), "the kept logs start with 7 blank logs");
+            assert!(!is_blank(7));
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::ReplicationStatus;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// A leader sends no more than `max_concurrent_snapshot_sends` snapshots at the same time.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, writes logs until a snapshot is built and the logs are purged.
+/// - adds 3 learners behind a slow network, every one of them needs the snapshot.
+/// - watches the replication metrics of the leader, asserts at most 1 snapshot is being sent at any time, and the other
+///   learners are queued.
+/// - asserts every learner eventually installs the snapshot.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_concurrent_sends() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            max_applied_log_to_keep: 0,
+            max_concurrent_snapshot_sends: 1,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(5).build());
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs to build a snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        router.wait(&0, timeout()).await?.snapshot(LogId::new(1, n_logs), "snapshot is built").await?;
+    }
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let mut rx = n0.metrics();
+
+    // Record the max number of snapshots being sent at once, and the max number of queued ones.
+    let watcher = tokio::spawn(async move {
+        let mut max_sending = 0;
+        let mut max_queued = 0;
+
+        while rx.changed().await.is_ok() {
+            let m = rx.borrow().clone();
+            let repl = match m.leader_metrics {
+                Some(x) => x.replication.clone(),
+                None => continue,
+            };
+
+            let count = |status| repl.values().filter(|x| x.state == status).count();
+            max_sending = std::cmp::max(max_sending, count(ReplicationStatus::Snapshotting));
+            max_queued = std::cmp::max(max_queued, count(ReplicationStatus::SnapshotQueued));
+
+            if repl.len() == 3 && repl.values().all(|x| x.matched.index >= n_logs) {
+                break;
+            }
+        }
+
+        (max_sending, max_queued)
+    });
+
+    tracing::info!("--- add 3 learners, they all need the snapshot");
+    {
+        for id in [1, 2, 3] {
+            router.new_raft_node(id).await;
+            router.add_learner_with_blocking(0, id, false).await?;
+        }
+
+        for id in [1, 2, 3] {
+            router
+                .wait(&id, timeout())
+                .await?
+                .snapshot(LogId::new(1, n_logs), "learner installs snapshot")
+                .await?;
+        }
+    }
+
+    let (max_sending, max_queued) = tokio::time::timeout(Duration::from_millis(5_000), watcher).await??;
+    assert_eq!(1, max_sending, "no more than 1 snapshot is sent at once");
+    assert!(max_queued > 0, "the other snapshot sendings are queued");
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A time based snapshot policy builds a snapshot once the interval elapsed, without waiting for the log to grow, even
+/// if the node applies no more logs.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - send a few logs within the interval and assert no snapshot is built.
+/// - send no more logs, and assert a snapshot is built after the interval.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_every_duration() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let interval = Duration::from_millis(1_000);
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::EveryDuration(interval),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.wait_for_log(&btreeset![0], want, None, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Uninitialized, None, "empty").await?;
+        router.initialize_from_single_node(0).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0], want, None, "init leader").await?;
+    }
+
+    tracing::info!("--- send logs within the interval, no snapshot");
+    {
+        router.client_request_many(0, "0", 5).await;
+        want += 5;
+
+        router.wait_for_log(&btreeset![0], want, None, "send log within interval").await?;
+
+        let metrics = router.wait(&0, None).await?.metrics(|_| true, "get metrics").await?;
+        assert_eq!(LogId { term: 0, index: 0 }, metrics.snapshot);
+    }
+
+    tracing::info!("--- send no more logs, snapshot is built after the interval");
+    {
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId { term: 1, index: want },
+                Some(interval * 3),
+                "snapshot on idle node",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::testing::Router;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use memstore::MemStore;
+use openraft::async_trait::async_trait;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::Entry;
+use openraft::storage::HardState;
+use openraft::storage::InitialState;
+use openraft::storage::Snapshot;
+use openraft::storage::SnapshotReader;
+use openraft::Config;
+use openraft::EffectiveMembership;
+use openraft::ErrorSubject;
+use openraft::ErrorVerb;
+use openraft::LogId;
+use openraft::NodeId;
+use openraft::Raft;
+use openraft::RaftStorage;
+use openraft::SnapshotMeta;
+use openraft::SnapshotPolicy;
+use openraft::State;
+use openraft::StateMachineChanges;
+use openraft::StorageError;
+use openraft::StorageIOError;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+#[macro_use]
+mod fixtures;
+
+/// A store that keeps its snapshot in a file, instead of in memory.
+///
+/// The state machine and logs are kept in a `MemStore`.
+/// A snapshot is read from and written to the file as a stream.
+struct FileSnapshotStore {
+    inner: MemStore,
+    dir: PathBuf,
+
+    /// The number of times the snapshot file is opened for sending.
+    n_reader: AtomicU64,
+}
+
+impl FileSnapshotStore {
+    async fn new(id: NodeId, dir: PathBuf) -> Self {
+        std::fs::create_dir_all(&dir).unwrap();
+
+        Self {
+            inner: MemStore::new(id).await,
+            dir,
+            n_reader: AtomicU64::new(0),
+        }
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.dir.join("current.snap")
+    }
+
+    fn receiving_path(&self) -> PathBuf {
+        self.dir.join("receiving.snap")
+    }
+
+    async fn open_current(&self, meta: &SnapshotMeta) -> Result<File, StorageError> {
+        let f = File::open(self.current_path()).await.map_err(|e| io_error(Some(meta), ErrorVerb::Read, e))?;
+        Ok(f)
+    }
+}
+
+fn io_error(meta: Option<&SnapshotMeta>, verb: ErrorVerb, e: std::io::Error) -> StorageError {
+    let subject = match meta {
+        Some(meta) => ErrorSubject::Snapshot(meta.clone()),
+        None => ErrorSubject::Store,
+    };
+    StorageIOError::new(subject, verb, anyhow::Error::new(e)).into()
+}
+
+#[async_trait]
+impl RaftStorage<ClientRequest, ClientResponse> for FileSnapshotStore {
+    type SnapshotData = File;
+
+    async fn get_membership_config(&self) -> Result<EffectiveMembership, StorageError> {
+        self.inner.get_membership_config().await
+    }
+
+    async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
+        self.inner.get_initial_state().await
+    }
+
+    async fn save_hard_state(&self, hs: &HardState) -> Result<(), StorageError> {
+        self.inner.save_hard_state(hs).await
+    }
+
+    async fn read_hard_state(&self) -> Result<Option<HardState>, StorageError> {
+        self.inner.read_hard_state().await
+    }
+
+    async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.inner.get_log_entries(range).await
+    }
+
+    async fn try_get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+        self.inner.try_get_log_entries(range).await
+    }
+
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest>>, StorageError> {
+        self.inner.try_get_log_entry(log_index).await
+    }
+
+    async fn first_id_in_log(&self) -> Result<Option<LogId>, StorageError> {
+        self.inner.first_id_in_log().await
+    }
+
+    async fn first_known_log_id(&self) -> Result<LogId, StorageError> {
+        self.inner.first_known_log_id().await
+    }
+
+    async fn last_id_in_log(&self) -> Result<LogId, StorageError> {
+        self.inner.last_id_in_log().await
+    }
+
+    async fn last_applied_state(&self) -> Result<(LogId, Option<EffectiveMembership>), StorageError> {
+        self.inner.last_applied_state().await
+    }
+
+    async fn delete_logs_from<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &self,
+        range: RNG,
+    ) -> Result<(), StorageError> {
+        self.inner.delete_logs_from(range).await
+    }
+
+    async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<(), StorageError> {
+        self.inner.append_to_log(entries).await
+    }
+
+    async fn apply_to_state_machine(
+        &self,
+        entries: &[&Entry<ClientRequest>],
+    ) -> Result<Vec<ClientResponse>, StorageError> {
+        self.inner.apply_to_state_machine(entries).await
+    }
+
+    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+        let Snapshot { meta, snapshot } = self.inner.do_log_compaction().await?;
+
+        tokio::fs::write(self.current_path(), snapshot.into_inner())
+            .await
+            .map_err(|e| io_error(Some(&meta), ErrorVerb::Write, e))?;
+
+        let f = self.open_current(&meta).await?;
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(f),
+        })
+    }
+
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+        let f = File::create(self.receiving_path()).await.map_err(|e| io_error(None, ErrorVerb::Write, e))?;
+        Ok(Box::new(f))
+    }
+
+    async fn finalize_snapshot_installation(
+        &self,
+        meta: &SnapshotMeta,
+        snapshot: Box<Self::SnapshotData>,
+    ) -> Result<StateMachineChanges, StorageError> {
+        snapshot.sync_all().await.map_err(|e| io_error(Some(meta), ErrorVerb::Write, e))?;
+        drop(snapshot);
+
+        tokio::fs::rename(self.receiving_path(), self.current_path())
+            .await
+            .map_err(|e| io_error(Some(meta), ErrorVerb::Write, e))?;
+
+        let data = tokio::fs::read(self.current_path()).await.map_err(|e| io_error(Some(meta), ErrorVerb::Read, e))?;
+
+        self.inner.finalize_snapshot_installation(meta, Box::new(Cursor::new(data))).await
+    }
+
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+        let meta = match self.inner.get_current_snapshot().await? {
+            Some(snapshot) => snapshot.meta,
+            None => return Ok(None),
+        };
+
+        let f = self.open_current(&meta).await?;
+        Ok(Some(Snapshot {
+            meta,
+            snapshot: Box::new(f),
+        }))
+    }
+
+    async fn get_snapshot_reader(&self) -> Result<Option<Snapshot<SnapshotReader>>, StorageError> {
+        let meta = match self.inner.get_current_snapshot().await? {
+            Some(snapshot) => snapshot.meta,
+            None => return Ok(None),
+        };
+
+        self.n_reader.fetch_add(1, Ordering::Relaxed);
+
+        let f = self.open_current(&meta).await?;
+        Ok(Some(Snapshot {
+            meta,
+            snapshot: Box::new(f),
+        }))
+    }
+}
+
+/// Replicate a snapshot between stores that keep snapshots in files.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with a store that keeps its snapshot in a temp file.
+/// - send enough requests to the node that log compaction will be triggered, and the logs are purged.
+/// - add a learner and assert that it receives the snapshot, streamed from the leader's snapshot file to its own.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_external_store() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let timeout = Some(Duration::from_millis(5_000));
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let tmp_dir = std::env::temp_dir().join(format!("openraft-snapshot-external-store-{}", std::process::id()));
+
+    let router = Arc::new(Router::<FileSnapshotStore>::default());
+    let mut stores = BTreeMap::new();
+
+    for id in [0, 1] {
+        let sto = Arc::new(FileSnapshotStore::new(id, tmp_dir.join(id.to_string())).await);
+        let raft = Raft::new(id, config.clone(), router.clone(), sto.clone());
+
+        router.add_node(id, raft).await;
+        stores.insert(id, sto);
+    }
+
+    let n0 = router.get_node(0).await?;
+    let n1 = router.get_node(1).await?;
+
+    tracing::info!("--- initializing cluster");
+    {
+        n0.initialize(btreeset![0]).await?;
+        n0.wait(timeout).state(State::Leader, "init leader").await?;
+    }
+
+    let mut want = 1;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        for i in want..snapshot_threshold {
+            n0.client_write(ClientWriteRequest::new(ClientRequest {
+                client: "0".to_string(),
+                serial: i,
+                status: format!("request-{}", i),
+            }))
+            .await?;
+        }
+        want = snapshot_threshold;
+
+        n0.wait(timeout).log(want, "send log to trigger snapshot").await?;
+        n0.wait(timeout).snapshot(LogId { term: 1, index: want }, "snapshot").await?;
+    }
+
+    tracing::info!("--- add learner to receive snapshot");
+    {
+        n0.add_learner(1, true).await?;
+
+        n1.wait(timeout).log(want, "add learner").await?;
+        n1.wait(timeout).snapshot(LogId { term: 1, index: want }, "learner snapshot").await?;
+
+        assert!(
+            stores[&0].n_reader.load(Ordering::Relaxed) >= 1,
+            "the snapshot is sent from the snapshot reader"
+        );
+
+        let mut sent = vec![];
+        File::open(stores[&0].current_path()).await?.read_to_end(&mut sent).await?;
+        let received = tokio::fs::read(stores[&1].current_path()).await?;
+
+        assert!(!sent.is_empty());
+        assert_eq!(sent, received, "the learner snapshot file is the same as the leader's");
+    }
+
+    n0.shutdown().await?;
+    n1.shutdown().await?;
+    std::fs::remove_dir_all(&tmp_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::SNAPSHOT_FORMAT_VERSION;
+use openraft::error::RaftError;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftMetrics;
+use openraft::RaftStorage;
+use openraft::SnapshotMeta;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// A snapshot of a format version the state machine does not support is rejected before anything is written.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster.
+/// - send the first chunk of a snapshot of an unknown format version, and assert it is rejected with
+///   `SnapshotFormatUnsupported`, while no snapshot is received or installed.
+/// - send the same snapshot of the supported format version, and assert it is received.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_format_version() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let (raft, sto) = router.remove_node(0).await.ok_or_else(|| anyhow::anyhow!("node not found"))?;
+
+    let req0 = InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: SnapshotMeta {
+            snapshot_id: "ss1".into(),
+            last_log_id: LogId { term: 1, index: 0 },
+            size: 3,
+            checksum: None,
+            format_version: SNAPSHOT_FORMAT_VERSION,
+        },
+        offset: 0,
+        data: vec![1, 2, 3],
+        done: false,
+    };
+
+    tracing::info!("--- a snapshot of an unknown format version is rejected");
+    {
+        let mut req = req0.clone();
+        req.meta.format_version = SNAPSHOT_FORMAT_VERSION + 1;
+
+        let res = raft.install_snapshot(req).await;
+        match res.unwrap_err() {
+            RaftError::SnapshotFormatUnsupported {
+                snapshot_id,
+                format_version,
+            } => {
+                assert_eq!("ss1", snapshot_id);
+                assert_eq!(SNAPSHOT_FORMAT_VERSION + 1, format_version);
+            }
+            err => panic!("expect SnapshotFormatUnsupported, got: {:?}", err),
+        }
+
+        let m = raft.metrics().borrow().clone();
+        assert_eq!(None, m.installing_snapshot_progress, "no snapshot is being received");
+
+        let snapshot = sto.get_current_snapshot().await?;
+        assert!(snapshot.is_none(), "no snapshot is installed");
+    }
+
+    tracing::info!("--- a snapshot of the supported format version is received");
+    {
+        raft.install_snapshot(req0.clone()).await?;
+
+        let m = raft.metrics().borrow().clone();
+        assert_eq!(Some((3, 3)), m.installing_snapshot_progress);
+    }
+
+    Ok(())
+}
+
+/// A leader stops sending a snapshot whose format the target rejected, and reports it until replication is resumed.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, writes logs until a snapshot is built and the logs are purged.
+/// - adds a learner, to which the snapshot is sent as of a format version it does not support.
+/// - asserts the leader reports the rejected format and pauses replication to the learner, and sends no more chunks.
+/// - sends the snapshot as it is, resumes replication, and asserts the learner installs the snapshot.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_format_rejected() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+    n_logs = snapshot_threshold;
+
+    router.wait(&0, timeout()).await?.snapshot(LogId::new(1, n_logs), "snapshot is built").await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+    let unsupported = SNAPSHOT_FORMAT_VERSION + 1;
+
+    tracing::info!("--- the learner rejects the snapshot format, the leader stops sending it");
+    {
+        router.new_raft_node(1).await;
+        router.set_snapshot_format_version(1, Some(unsupported));
+        router.add_learner_with_blocking(0, 1, false).await?;
+
+        let m = n0
+            .wait(timeout())
+            .metrics(
+                |x| format_rejected(x, 1) == Some(unsupported),
+                "the rejected format is reported",
+            )
+            .await?;
+        assert!(m.leader_metrics.as_ref().unwrap().replication[&1].paused);
+
+        let sent = router.snapshot_chunks_sent(1);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(sent, router.snapshot_chunks_sent(1), "no snapshot chunk is sent again");
+    }
+
+    tracing::info!("--- resume replication once the learner supports the format");
+    {
+        router.set_snapshot_format_version(1, None);
+        n0.resume_replication(1).await?;
+
+        router
+            .wait(&1, timeout())
+            .await?
+            .snapshot(LogId::new(1, n_logs), "learner installs snapshot")
+            .await?;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(None, format_rejected(&m, 1));
+    }
+
+    Ok(())
+}
+
+/// The snapshot format version the leader reports `target` rejected.
+fn format_rejected(m: &RaftMetrics, target: u64) -> Option<u32> {
+    let repl = m.leader_metrics.as_ref()?.replication.get(&target)?;
+    repl.snapshot_format_rejected
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// The metrics report the snapshot being built, and the progress of a snapshot being installed.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with `LogsSinceLast` snapshot policy.
+/// - writes logs until a snapshot is built, asserts `snapshot` advances and `snapshot_building` resets.
+/// - adds a learner behind a slow network, asserts it reports the progress of installing the snapshot.
+/// - asserts the progress resets when the snapshot is installed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_metrics() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: 10,
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(10).build());
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    let m = router.wait(&0, timeout()).await?.metrics(|_| true, "get node-0 metrics").await?;
+    assert_eq!(LogId::new(0, 0), m.snapshot, "no snapshot yet");
+
+    tracing::info!("--- write logs to build a snapshot");
+    let snapshot_log_id = LogId {
+        term: 1,
+        index: snapshot_threshold,
+    };
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - n_logs) as usize).await;
+        n_logs = snapshot_threshold;
+
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.snapshot == snapshot_log_id && !x.snapshot_building,
+                "snapshot advances",
+            )
+            .await?;
+        assert_eq!(None, m.installing_snapshot_progress, "leader installs no snapshot");
+    }
+
+    tracing::info!("--- add a learner, it installs the snapshot in chunks");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner_with_blocking(0, 1, false).await?;
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.installing_snapshot_progress.is_some(),
+                "learner is installing snapshot",
+            )
+            .await?;
+        let (received, total) = m.installing_snapshot_progress.unwrap();
+        assert!(received < total, "received {} of {} bytes", received, total);
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(|x| x.last_applied == n_logs, "learner installs snapshot")
+            .await?;
+        assert_eq!(snapshot_log_id, m.snapshot);
+        assert_eq!(None, m.installing_snapshot_progress, "progress resets when installed");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+/// A graceful shutdown with `snapshot_on_shutdown` builds a snapshot of the last applied log.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters with a snapshot policy that never builds a snapshot, and writes some logs.
+/// - shuts the leader down gracefully.
+/// - asserts the leader has a snapshot whose last log id is the last applied log id.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_on_shutdown() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(10_000),
+            snapshot_on_shutdown: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "write 10 logs").await?;
+
+    let sto = router.get_storage_handle(&0).await?;
+    assert!(
+        sto.get_current_snapshot().await?.is_none(),
+        "no snapshot before shutdown"
+    );
+
+    tracing::info!("--- shutdown node-0 gracefully");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        n0.shutdown_graceful(Duration::from_millis(5_000)).await?;
+    }
+
+    let (last_applied, _) = sto.last_applied_state().await?;
+    assert_eq!(LogId { term: 1, index: want }, last_applied);
+
+    let snapshot = sto.get_current_snapshot().await?.expect("a snapshot is built on shutdown");
+    assert_eq!(
+        last_applied, snapshot.meta.last_log_id,
+        "the snapshot includes the last applied log"
+    );
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
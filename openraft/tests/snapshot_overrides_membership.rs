@@ -49,7 +49,7 @@ async fn snapshot_overrides_membership() -> Result<()> {
         router.new_raft_node(0).await;
 
         router.wait_for_log(&btreeset![0], want, timeout(), "empty").await?;
-        router.wait_for_state(&btreeset![0], State::Learner, timeout(), "empty").await?;
+        router.wait_for_state(&btreeset![0], State::Uninitialized, timeout(), "empty").await?;
         router.initialize_from_single_node(0).await?;
         want += 1;
 
@@ -88,8 +88,10 @@ async fn snapshot_overrides_membership() -> Result<()> {
                 entries: vec![Entry {
                     log_id: LogId { term: 1, index: 1 },
                     payload: EntryPayload::Membership(Membership::new_single(btreeset! {2,3})),
+                    request_id: None,
                 }],
                 leader_commit: LogId::new(0, 0),
+                compressed_entries: None,
             };
             router.send_append_entries(1, req).await?;
 
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::store::StoreHooks;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::Raft;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+#[macro_use]
+mod fixtures;
+
+type SizedRaft = Raft<ClientRequest, ClientResponse, RaftRouter, HookedStore>;
+
+/// The size every appended entry is reported to take in the log storage.
+const ENTRY_SIZE: u64 = 100;
+
+/// With `SnapshotPolicy::SizeSinceLast`, a snapshot is built every time the log grows by the byte threshold.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster with a store reporting 100 bytes for every appended entry, and a snapshot policy
+///   of 1000 bytes.
+/// - writes logs until 900 bytes are appended, asserts no snapshot is built.
+/// - writes one more log, asserts a snapshot of the 10th log is built.
+/// - writes 9 more logs, asserts no new snapshot; writes one more, asserts a snapshot of the 20th log is built.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_size_since_last() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::SizeSinceLast(10 * ENTRY_SIZE),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let sto = Arc::new(
+        HookedStore::with_hooks(0, StoreHooks {
+            entry_size: Some(ENTRY_SIZE),
+            ..Default::default()
+        })
+        .await,
+    );
+    let leader = Raft::new(0, config.clone(), router.clone(), sto.clone());
+
+    leader.initialize(btreeset! {0}).await?;
+    let m = leader.wait(timeout()).metrics(|x| x.leader_ready, "node-0 is ready").await?;
+
+    let mut serial = 0;
+    let mut last_log = m.last_log_index;
+
+    tracing::info!("--- write up to 900 bytes, no snapshot");
+    {
+        while last_log < 9 {
+            write(&leader, &mut serial).await?;
+            last_log += 1;
+        }
+
+        assert_eq!(9 * ENTRY_SIZE, sto.log_storage_size().await?);
+        assert_eq!(LogId::new(0, 0), leader.metrics().borrow().snapshot);
+        assert!(sto.get_current_snapshot().await?.is_none());
+    }
+
+    tracing::info!("--- reach 1000 bytes, a snapshot is built");
+    {
+        write(&leader, &mut serial).await?;
+        leader.wait(timeout()).snapshot(LogId::new(1, 10), "snapshot at 1000 bytes").await?;
+    }
+
+    tracing::info!("--- 1000 more bytes since the last snapshot, another snapshot is built");
+    {
+        for _ in 0..9 {
+            write(&leader, &mut serial).await?;
+        }
+        assert_eq!(
+            LogId::new(1, 10),
+            leader.metrics().borrow().snapshot,
+            "900 bytes since last snapshot"
+        );
+
+        write(&leader, &mut serial).await?;
+        leader.wait(timeout()).snapshot(LogId::new(1, 20), "snapshot at 2000 bytes").await?;
+    }
+
+    leader.shutdown().await?;
+
+    Ok(())
+}
+
+async fn write(leader: &SizedRaft, serial: &mut u64) -> Result<()> {
+    leader
+        .client_write(ClientWriteRequest::new(ClientRequest {
+            client: "foo".to_string(),
+            serial: *serial,
+            status: format!("request-{}", serial),
+        }))
+        .await?;
+    *serial += 1;
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+use openraft::TriggerSnapshotError;
+
+#[macro_use]
+mod fixtures;
+
+/// `Raft::trigger_snapshot()` builds a snapshot at once, regardless of the snapshot policy.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node, and asserts triggering a snapshot is rejected because there is no applied log.
+/// - initializes it as a single node cluster with a snapshot threshold that is never reached, and writes logs.
+/// - triggers a snapshot, and asserts it includes every applied log, and it is reported in metrics and storage.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_trigger() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(10_000),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    tracing::info!("--- nothing to snapshot on a pristine node");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        let res = n0.trigger_snapshot().await;
+        assert!(
+            matches!(res, Err(TriggerSnapshotError::NothingToSnapshot)),
+            "got: {:?}",
+            res
+        );
+    }
+
+    let mut n_logs = 0;
+
+    tracing::info!("--- initialize and write logs");
+    {
+        router.initialize_from_single_node(0).await?;
+        n_logs += 1;
+
+        router.client_request_many(0, "0", 10).await;
+        n_logs += 10;
+
+        router.wait(&0, timeout()).await?.metrics(|x| x.last_applied == n_logs, "logs are applied").await?;
+        assert_eq!(
+            LogId::new(0, 0),
+            router.get_raft_handle(&0).await?.metrics().borrow().snapshot
+        );
+    }
+
+    tracing::info!("--- trigger a snapshot");
+    {
+        let n0 = router.get_raft_handle(&0).await?;
+        let meta = n0.trigger_snapshot().await?;
+        assert_eq!(LogId::new(1, n_logs), meta.last_log_id);
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(|x| x.snapshot == LogId::new(1, n_logs), "snapshot advances")
+            .await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let snap = sto.get_current_snapshot().await?.unwrap();
+        assert_eq!(meta, snap.meta);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
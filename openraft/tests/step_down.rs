@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::error::TransferLeadershipError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A leader steps down for maintenance, a new leader emerges and the old leader stays as a follower.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, and writes some logs so that every voter is up to date.
+/// - asserts `step_down()` on a follower is rejected.
+/// - steps down the leader node-0, and asserts it returns an up to date voter, which becomes the leader.
+/// - asserts node-0 is a follower, rejects writes, and still replicates logs from the new leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn step_down() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write 10 logs").await?;
+
+    tracing::info!("--- a follower can not step down");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let res = n1.step_down().await;
+        match res.unwrap_err() {
+            TransferLeadershipError::ForwardToLeader(e) => {
+                assert_eq!(Some(0), e.leader_id);
+            }
+            err => panic!("expect ForwardToLeader, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- the leader steps down, an up to date voter takes over");
+    let new_leader = {
+        let n0 = router.get_raft_handle(&0).await?;
+        let target = n0.step_down().await?;
+
+        let target = target.expect("every voter is up to date");
+        assert!(target == 1 || target == 2, "target: {}", target);
+
+        for id in [0, 1, 2] {
+            router.wait(&id, timeout()).await?.current_leader(target, "a new leader emerges").await?;
+        }
+        target
+    };
+
+    tracing::info!("--- node-0 stays in the cluster as a follower");
+    {
+        router.wait(&0, timeout()).await?.state(State::Follower, "node-0 is a follower").await?;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let res = n0
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 100,
+                status: "x".to_string(),
+            }))
+            .await;
+        match res.unwrap_err() {
+            ClientWriteError::ForwardToLeader(e) => {
+                assert_eq!(Some(new_leader), e.leader_id);
+            }
+            err => panic!("expect ForwardToLeader, got: {:?}", err),
+        }
+
+        router.client_request_many(new_leader, "foo", 10).await;
+        // 1 blank log from the new leader
+        n_logs += 11;
+        router
+            .wait_for_log(
+                &btreeset![0, 1, 2],
+                n_logs,
+                timeout(),
+                "node-0 replicates from the new leader",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
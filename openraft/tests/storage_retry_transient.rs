@@ -0,0 +1,83 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::store::HookedStore;
+use fixtures::StubNetwork;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::Raft;
+use openraft::RaftStorage;
+use openraft::State;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// A transient storage error is retried, and the write succeeds once the storage recovers.
+///
+/// What does this test do?
+///
+/// - brings up a single node cluster, with a store failing `append_to_log` with a transient error.
+/// - makes the store fail the next 2 appends, then writes a log.
+/// - asserts the write succeeds after 2 retries with a growing backoff, and the node is still the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn storage_retry_transient() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            storage_retry_attempts: 3,
+            storage_retry_backoff: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let sto = Arc::new(HookedStore::new(0).await);
+    let raft = Raft::new(0, config.clone(), Arc::new(StubNetwork::default()), sto.clone());
+
+    raft.initialize(btreeset! {0}).await?;
+    raft.wait(timeout()).metrics(|x| x.leader_ready, "node-0 is ready").await?;
+
+    tracing::info!("--- fail the next 2 appends, then write a log");
+    {
+        sto.append_failures.store(2, Ordering::Relaxed);
+        let attempts_before = sto.appends.load(Ordering::Relaxed);
+        let now = Instant::now();
+
+        let resp = raft
+            .client_write(ClientWriteRequest::new(ClientRequest {
+                client: "foo".to_string(),
+                serial: 0,
+                status: "bar".to_string(),
+            }))
+            .await?;
+
+        let attempts = sto.appends.load(Ordering::Relaxed) - attempts_before;
+        assert_eq!(3, attempts, "2 failed attempts and 1 successful retry");
+        assert!(
+            now.elapsed() >= Duration::from_millis(100 + 200),
+            "the retries back off: {:?}",
+            now.elapsed()
+        );
+
+        let logs = sto.get_log_entries(resp.log_id.index..=resp.log_id.index).await?;
+        assert_eq!(1, logs.len(), "the log is written");
+
+        raft.wait(timeout()).metrics(|x| x.last_applied == resp.log_id.index, "the log is applied").await?;
+        raft.wait(timeout()).state(State::Leader, "node-0 is still the leader").await?;
+    }
+
+    raft.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
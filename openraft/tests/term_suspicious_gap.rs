@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::VoteRequest;
+use openraft::Config;
+use openraft::LogId;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A term far greater than the local term is recorded in `max_term_seen`, and is still adopted as Raft requires.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, warning about a term more than 100 greater than the local term.
+/// - sends a pre-vote with a much greater term to follower node-1, and asserts the term is recorded but not adopted.
+/// - sends an AppendEntries with a much greater term to the leader node-0, and asserts it steps down to a follower in
+///   that term, and the term is recorded.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn term_suspicious_gap() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            suspicious_term_gap: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let m = router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+    let term = m.current_term;
+    assert_eq!(term, m.max_term_seen);
+
+    tracing::info!("--- a pre-vote with a suspicious term is recorded, not adopted");
+    {
+        let suspicious = term + 1_000;
+
+        let n1 = router.get_raft_handle(&1).await?;
+        let resp = n1
+            .vote(VoteRequest {
+                pre_vote: true,
+                ..VoteRequest::new(suspicious, 2, LogId::new(0, 0))
+            })
+            .await?;
+        assert!(!resp.vote_granted);
+
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(|x| x.max_term_seen == suspicious, "node-1 records the term")
+            .await?;
+        assert!(m.current_term < suspicious, "a pre-vote does not change the term");
+    }
+
+    tracing::info!("--- an AppendEntries with a suspicious term makes the leader step down");
+    {
+        let suspicious = term + 1_000_000;
+
+        let n0 = router.get_raft_handle(&0).await?;
+        let resp = n0
+            .append_entries(AppendEntriesRequest {
+                term: suspicious,
+                leader_id: 1,
+                prev_log_id: LogId::new(0, 0),
+                entries: vec![],
+                leader_commit: LogId::new(0, 0),
+                compressed_entries: None,
+            })
+            .await?;
+        assert_eq!(suspicious, resp.term);
+
+        let m = router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.current_term >= suspicious && x.state == State::Follower,
+                "node-0 steps down and adopts the term",
+            )
+            .await?;
+        assert!(m.max_term_seen >= suspicious);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
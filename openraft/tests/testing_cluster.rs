@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::testing::Cluster;
+use memstore::ClientRequest;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+
+#[macro_use]
+mod fixtures;
+
+/// `memstore::testing::Cluster` brings up a working cluster in one call.
+///
+/// What does this test do?
+///
+/// - builds an in-process cluster of 3 voters, and asserts a leader is elected.
+/// - writes through the leader, and asserts the write is applied on every node.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn testing_cluster() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let cluster = Cluster::new(config, btreeset! {0,1,2}).await?;
+
+    let leader = cluster.leader().await.expect("a leader is elected");
+    assert!(leader <= 2);
+
+    let resp = cluster
+        .client_write(ClientRequest {
+            client: "foo".to_string(),
+            serial: 1,
+            status: "bar".to_string(),
+        })
+        .await?;
+    let index = resp.log_id.index;
+
+    for id in 0..=2 {
+        let raft = cluster.raft(id).await?;
+        raft.wait(timeout()).log(index, "the write is applied").await?;
+
+        let sto = cluster.store(id).expect("every node has a store");
+        let sm = sto.get_state_machine().await;
+        assert_eq!(Some(&"bar".to_string()), sm.client_status.get("foo"));
+    }
+
+    cluster.shutdown().await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
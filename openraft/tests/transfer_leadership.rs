@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::TransferLeadershipError;
+use openraft::Config;
+use openraft::RaftError;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Transfer leadership to a specified node or to the most up to date voter.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters and 1 learner.
+/// - asserts invalid transfers are rejected: on a follower, to a learner and to the leader itself.
+/// - transfers leadership to node-2, asserts node-2 becomes the leader of every voter and accepts writes.
+/// - transfers leadership without a target, asserts one of the other voters becomes the leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn transfer_leadership() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    router.client_request_many(0, "foo", 10).await;
+    n_logs += 10;
+    router.wait_for_log(&btreeset![0, 1, 2, 3], n_logs, timeout(), "write 10 logs").await?;
+
+    tracing::info!("--- invalid transfers are rejected");
+    {
+        let res = router.transfer_leadership(1, Some(2)).await;
+        match res.unwrap_err() {
+            TransferLeadershipError::ForwardToLeader(e) => {
+                assert_eq!(Some(0), e.leader_id);
+            }
+            err => panic!("expect ForwardToLeader, got: {:?}", err),
+        }
+
+        for target in [0, 3, 4] {
+            let res = router.transfer_leadership(0, Some(target)).await;
+            match res.unwrap_err() {
+                TransferLeadershipError::InvalidTarget { node_id } => {
+                    assert_eq!(target, node_id);
+                }
+                err => panic!("expect InvalidTarget, got: {:?}", err),
+            }
+        }
+    }
+
+    tracing::info!("--- transfer leadership to node-2");
+    {
+        let target = router.transfer_leadership(0, Some(2)).await?;
+        assert_eq!(2, target);
+
+        for id in [0, 1, 2] {
+            router.wait(&id, timeout()).await?.current_leader(2, "node-2 becomes leader").await?;
+        }
+
+        router.client_request_many(2, "foo", 10).await;
+        // 1 blank log from the new leader
+        n_logs += 11;
+        router.wait_for_log(&btreeset![0, 1, 2], n_logs, timeout(), "write to node-2").await?;
+    }
+
+    tracing::info!("--- transfer leadership to the most up to date voter");
+    {
+        let target = router.transfer_leadership(2, None).await?;
+        assert!(target == 0 || target == 1, "target: {}", target);
+
+        for id in [0, 1, 2] {
+            router.wait(&id, timeout()).await?.current_leader(target, "transfer without target").await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Transfer leadership to a node that can not be caught up.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - isolates node-2 and writes a log so that node-2 is lagging.
+/// - asserts transferring leadership to node-2 times out, and the leader resumes accepting writes afterwards.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn transfer_leadership_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.isolate_node(2).await;
+
+    router.client_request_many(0, "foo", 1).await;
+    n_logs += 1;
+    router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write while node-2 is isolated").await?;
+
+    tracing::info!("--- transfer leadership to lagging node-2");
+    {
+        let res = router.transfer_leadership(0, Some(2)).await;
+        match res.unwrap_err() {
+            TransferLeadershipError::Timeout { target, timeout } => {
+                assert_eq!(2, target);
+                assert_eq!(Duration::from_millis(config.election_timeout_max), timeout);
+            }
+            err => panic!("expect Timeout, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- node-0 is still the leader and accepts writes");
+    {
+        router.client_request_many(0, "foo", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write after transfer timeout").await?;
+        router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is still the leader").await?;
+    }
+
+    Ok(())
+}
+
+/// A leadership transfer whose TimeoutNow can not be sent ends at once.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters.
+/// - isolates node-2, which still has every log of the leader, so that the TimeoutNow to it fails.
+/// - asserts transferring leadership to node-2 fails with the network error.
+/// - asserts the leader accepts writes at once, without waiting for the transfer deadline.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn transfer_leadership_send_failure() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    router.isolate_node(2).await;
+
+    tracing::info!("--- transfer leadership to unreachable node-2");
+    {
+        let res = router.transfer_leadership(0, Some(2)).await;
+        match res.unwrap_err() {
+            TransferLeadershipError::RaftError(RaftError::RaftNetwork(_)) => {}
+            err => panic!("expect RaftNetwork error, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- node-0 accepts writes at once");
+    {
+        router.client_request(0, "foo", 1).await;
+        n_logs += 1;
+        router.wait_for_log(&btreeset![0, 1], n_logs, timeout(), "write after transfer failure").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
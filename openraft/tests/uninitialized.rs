@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteRequest;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// A node before `initialize()` reports `State::Uninitialized`, and rejects client writes with
+/// `ClientWriteError::Uninitialized`.
+///
+/// What does this test do?
+///
+/// - brings up a pristine node-0, asserts it reports `Uninitialized` with term 0 and an empty log.
+/// - asserts a client write to it fails with `ClientWriteError::Uninitialized`, not a not-leader error.
+/// - initializes it, asserts it becomes the leader and accepts writes.
+/// - adds a pristine node-1 as a learner, asserts it is no longer uninitialized once it receives logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn uninitialized() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a pristine node is uninitialized");
+    {
+        let m = router.wait(&0, timeout()).await?.state(State::Uninitialized, "pristine node").await?;
+        assert_eq!(0, m.current_term);
+        assert_eq!(0, m.last_log_index);
+        assert_eq!(None, m.current_leader);
+    }
+
+    tracing::info!("--- a client write is rejected as uninitialized");
+    {
+        let res = n0.client_write(ClientWriteRequest::new(request(0))).await;
+        assert!(matches!(res, Err(ClientWriteError::Uninitialized)), "got: {:?}", res);
+    }
+
+    tracing::info!("--- an initialized node accepts writes");
+    {
+        router.initialize_from_single_node(0).await?;
+        router.wait(&0, timeout()).await?.state(State::Leader, "node-0 is leader").await?;
+
+        let resp = n0.client_write(ClientWriteRequest::new(request(1))).await?;
+        assert!(resp.log_id.index > 1);
+    }
+
+    tracing::info!("--- a learner is not uninitialized once it receives logs");
+    {
+        router.new_raft_node(1).await;
+        router.wait(&1, timeout()).await?.state(State::Uninitialized, "pristine node-1").await?;
+
+        router.add_learner(0, 1).await?;
+        router.wait(&1, timeout()).await?.state(State::Learner, "node-1 is learner").await?;
+
+        let res = router.get_raft_handle(&1).await?.client_write(ClientWriteRequest::new(request(2))).await;
+        assert!(
+            matches!(res, Err(ClientWriteError::ForwardToLeader(_))),
+            "a learner redirects to the leader, got: {:?}",
+            res
+        );
+    }
+
+    Ok(())
+}
+
+fn request(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::UpdateConfigError;
+use openraft::Config;
+use openraft::ConfigDelta;
+use openraft::ConfigError;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// Update heartbeat interval of a running cluster.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 3 voters, with the default heartbeat interval.
+/// - rejects an update that makes the heartbeat interval too large for the election timeout.
+/// - widens election timeouts on every node, then widens the heartbeat interval on the leader.
+/// - asserts the leader sends heartbeats at the new cadence, and the leadership is retained.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn update_config_heartbeat() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let m = router.wait(&0, None).await?.state(State::Leader, "leader").await?;
+    let term = m.current_term;
+
+    let window = Duration::from_millis(1_000);
+
+    tracing::info!("--- count heartbeats with the default interval");
+    let sent_before = count_append_entries(&router, 1, window).await;
+    tracing::info!("sent in {:?} with heartbeat interval 50 ms: {}", window, sent_before);
+
+    tracing::info!("--- reject an invalid delta");
+    {
+        let res = router
+            .update_config(0, ConfigDelta {
+                heartbeat_interval: Some(100),
+                ..Default::default()
+            })
+            .await;
+
+        match res.unwrap_err() {
+            UpdateConfigError::ConfigError(err) => {
+                assert_eq!(
+                    ConfigError::ElectionTimeoutTooCloseToHeartbeat { min_heartbeats: 3 },
+                    err
+                );
+            }
+            err => panic!("expect ConfigError, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- widen election timeouts, then the heartbeat interval");
+    {
+        let election_timeouts = ConfigDelta {
+            election_timeout_min: Some(600),
+            election_timeout_max: Some(1000),
+            ..Default::default()
+        };
+        router.update_config(1, election_timeouts.clone()).await?;
+        router.update_config(2, election_timeouts.clone()).await?;
+        router.update_config(0, election_timeouts).await?;
+
+        router
+            .update_config(0, ConfigDelta {
+                heartbeat_interval: Some(200),
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    tracing::info!("--- count heartbeats with the new interval");
+    // Let the heartbeats scheduled before the update go.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let sent_after = count_append_entries(&router, 1, window).await;
+    tracing::info!("sent in {:?} with heartbeat interval 200 ms: {}", window, sent_after);
+
+    // About 20 heartbeats in a second with 50 ms interval, about 5 with 200 ms interval.
+    assert!(sent_before >= 10, "sent with 50 ms interval: {}", sent_before);
+    assert!(sent_after <= 8, "sent with 200 ms interval: {}", sent_after);
+
+    tracing::info!("--- leadership is retained");
+    for id in [0, 1, 2] {
+        let m = router.wait(&id, None).await?.metrics(|_| true, "get metrics").await?;
+        assert_eq!(Some(0), m.current_leader, "node {} leader", id);
+        assert_eq!(term, m.current_term, "node {} term", id);
+    }
+
+    Ok(())
+}
+
+/// Count the AppendEntries RPCs sent to the target within a window.
+async fn count_append_entries(router: &RaftRouter, target: u64, window: Duration) -> u64 {
+    let start = router.append_entries_sent(target);
+    tokio::time::sleep(window).await;
+    router.append_entries_sent(target) - start
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::error::ChangeMembershipError;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+use openraft::State;
+use openraft::TransferLeadershipError;
+
+#[macro_use]
+mod fixtures;
+
+/// A witness casts votes and counts toward the commit quorum, without applying any application data, and is never
+/// elected.
+///
+/// What does this test do?
+///
+/// - brings up a cluster of 2 voters, and adds node-2 as a witness.
+/// - isolates node-1, writes some logs, and asserts they are committed with node-2, whose state machine stays empty.
+/// - restores node-1, isolates the leader, and asserts node-1 is elected with the vote of node-2, while node-2 stays a
+///   follower.
+/// - asserts leadership can not be transferred to node-2.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn witness() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- add node-2 as a witness");
+    {
+        router.new_raft_node(2).await;
+        n0.add_witness(2, true).await?;
+        want += 2;
+
+        router.wait_for_log(&btreeset! {0,1,2}, want, timeout(), "add witness").await?;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(&btreeset! {2}, m.membership_config.membership.witnesses());
+        assert_eq!(&btreeset! {0,1,2}, m.membership_config.membership.voters());
+    }
+
+    tracing::info!("--- a full voter can not become a witness");
+    {
+        let res = n0.add_witness(1, false).await;
+        match res.unwrap_err() {
+            ClientWriteError::ChangeMembershipError(ChangeMembershipError::InvalidWitness { node_id }) => {
+                assert_eq!(1, node_id)
+            }
+            err => panic!("expect InvalidWitness, got: {:?}", err),
+        }
+    }
+
+    tracing::info!("--- isolate node-1, logs are committed with the witness");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "foo", 5).await;
+        want += 5;
+
+        router.wait_for_log(&btreeset! {0,2}, want, timeout(), "commit with the witness").await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm2 = router.get_storage_handle(&2).await?.get_state_machine().await;
+        assert_eq!(Some(&"request-4".to_string()), sm0.client_status.get("foo"));
+        assert!(sm2.client_status.is_empty(), "the witness applies no application data");
+    }
+
+    tracing::info!("--- isolate the leader, node-1 is elected with the vote of the witness");
+    {
+        router.restore_node(1).await;
+        router.wait_for_log(&btreeset! {1}, want, timeout(), "node-1 catches up").await?;
+
+        router.isolate_node(0).await;
+
+        router.wait_for_state(&btreeset! {1}, State::Leader, timeout(), "node-1 is elected").await?;
+
+        let m2 = router
+            .wait_for_metrics(&2, |x| x.current_leader == Some(1), timeout(), "node-2 follows node-1")
+            .await?;
+        assert_eq!(State::Follower, m2.state, "the witness is never elected");
+    }
+
+    tracing::info!("--- leadership can not be transferred to the witness");
+    {
+        let n1 = router.get_raft_handle(&1).await?;
+        let res = n1.transfer_leadership(Some(2)).await;
+        match res.unwrap_err() {
+            TransferLeadershipError::InvalidTarget { node_id } => assert_eq!(2, node_id),
+            err => panic!("expect InvalidTarget, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}